@@ -29,12 +29,10 @@ use smithay::{
     utils::{Logical, Point, Serial, Transform, SERIAL_COUNTER as SCOUNTER},
     wayland::{
         compositor::with_states,
+        data_device::{cancel_dnd, update_dnd_modifiers},
         input_method::InputMethodSeat,
         keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitorSeat,
-        shell::{
-            wlr_layer::{KeyboardInteractivity, Layer as WlrLayer, LayerSurfaceCachedState},
-            xdg::XdgToplevelSurfaceData,
-        },
+        shell::{wlr_layer::Layer as WlrLayer, xdg::XdgToplevelSurfaceData},
     },
 };
 
@@ -149,26 +147,16 @@ impl<BackendData: Backend> AnvilState<BackendData> {
         let mut suppressed_keys = self.suppressed_keys.clone();
         let keyboard = self.seat.get_keyboard().unwrap();
 
-        for layer in self.layer_shell_state.layer_surfaces().rev() {
-            let data = with_states(layer.wl_surface(), |states| {
-                *states.cached_state.current::<LayerSurfaceCachedState>()
+        if let Some(surface) = self
+            .space
+            .outputs()
+            .find_map(|o| layer_map_for_output(o).exclusive_keyboard_focus().cloned())
+        {
+            keyboard.set_focus(self, Some(surface.into()), serial);
+            keyboard.input::<(), _>(self, keycode, state, serial, time, |_, _, _| {
+                FilterResult::Forward
             });
-            if data.keyboard_interactivity == KeyboardInteractivity::Exclusive
-                && (data.layer == WlrLayer::Top || data.layer == WlrLayer::Overlay)
-            {
-                let surface = self.space.outputs().find_map(|o| {
-                    let map = layer_map_for_output(o);
-                    let cloned = map.layers().find(|l| l.layer_surface() == &layer).cloned();
-                    cloned
-                });
-                if let Some(surface) = surface {
-                    keyboard.set_focus(self, Some(surface.into()), serial);
-                    keyboard.input::<(), _>(self, keycode, state, serial, time, |_, _, _| {
-                        FilterResult::Forward
-                    });
-                    return KeyAction::None;
-                };
-            }
+            return KeyAction::None;
         }
 
         let inhibited = self
@@ -182,7 +170,7 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             .unwrap_or(false);
 
         let action = keyboard
-            .input(self, keycode, state, serial, time, |_, modifiers, handle| {
+            .input(self, keycode, state, serial, time, |data, modifiers, handle| {
                 let keysym = handle.modified_sym();
 
                 debug!(
@@ -198,6 +186,13 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                 // so that we can decide on a release if the key
                 // should be forwarded to the client or not.
                 if let KeyState::Pressed = state {
+                    // escape aborts an ongoing drag'n'drop
+                    let seat = data.seat.clone();
+                    if keysym == xkb::KEY_Escape && cancel_dnd(data, &seat, serial, time) {
+                        suppressed_keys.push(keysym);
+                        return FilterResult::Intercept(KeyAction::None);
+                    }
+
                     if !inhibited {
                         let action = process_keyboard_shortcut(*modifiers, keysym);
 
@@ -223,6 +218,10 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             })
             .unwrap_or(KeyAction::None);
 
+        // holding modifiers changes the action of an ongoing drag'n'drop
+        let seat = self.seat.clone();
+        update_dnd_modifiers(self, &seat, keyboard.modifier_state());
+
         self.suppressed_keys = suppressed_keys;
         action
     }
@@ -392,6 +391,11 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                 if let Some(discrete) = horizontal_amount_discrete {
                     frame = frame.discrete(Axis::Horizontal, discrete as i32);
                 }
+                if let Some(v120) = evt.amount_v120(input::Axis::Horizontal) {
+                    frame = frame.v120(Axis::Horizontal, v120 as i32);
+                }
+                frame = frame
+                    .relative_direction(Axis::Horizontal, evt.relative_direction(input::Axis::Horizontal));
             } else if evt.source() == AxisSource::Finger {
                 frame = frame.stop(Axis::Horizontal);
             }
@@ -400,6 +404,11 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                 if let Some(discrete) = vertical_amount_discrete {
                     frame = frame.discrete(Axis::Vertical, discrete as i32);
                 }
+                if let Some(v120) = evt.amount_v120(input::Axis::Vertical) {
+                    frame = frame.v120(Axis::Vertical, v120 as i32);
+                }
+                frame =
+                    frame.relative_direction(Axis::Vertical, evt.relative_direction(input::Axis::Vertical));
             } else if evt.source() == AxisSource::Finger {
                 frame = frame.stop(Axis::Vertical);
             }
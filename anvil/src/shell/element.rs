@@ -452,6 +452,13 @@ impl SpaceElement for WindowElement {
             WindowElement::X11(w) => SpaceElement::output_leave(w, output),
         }
     }
+    fn primary_output_changed(&self, output: Option<&Output>) {
+        match self {
+            WindowElement::Wayland(w) => SpaceElement::primary_output_changed(w, output),
+            #[cfg(feature = "xwayland")]
+            WindowElement::X11(w) => SpaceElement::primary_output_changed(w, output),
+        }
+    }
     fn refresh(&self) {
         match self {
             WindowElement::Wayland(w) => SpaceElement::refresh(w),
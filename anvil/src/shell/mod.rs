@@ -11,6 +11,7 @@ use smithay::{
     output::Output,
     reexports::{
         calloop::Interest,
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
         wayland_server::{
             protocol::{wl_buffer::WlBuffer, wl_output, wl_surface::WlSurface},
             Client, Resource,
@@ -158,7 +159,8 @@ impl<BackendData: Backend> CompositorHandler for AnvilState<BackendData> {
         }
         self.popups.commit(surface);
 
-        ensure_initial_configure(surface, &self.space, &mut self.popups)
+        ensure_initial_configure(surface, &self.space, &mut self.popups);
+        self.fixup_maximized_windows();
     }
 }
 
@@ -203,6 +205,43 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             .find(|window| window.wl_surface().map(|s| s == *surface).unwrap_or(false))
             .cloned()
     }
+
+    /// Fit maximized windows to the work area of their output, if it changed
+    /// since the last call, e.g. because a panel with an exclusive zone got mapped.
+    fn fixup_maximized_windows(&mut self) {
+        for output in self.space.outputs().cloned().collect::<Vec<_>>() {
+            if layer_map_for_output(&output).take_zone_change().is_none() {
+                continue;
+            }
+            let Some(work_area) = self.space.output_work_area(&output) else {
+                continue;
+            };
+
+            let maximized = self
+                .space
+                .elements()
+                .filter(|window| self.space.outputs_for_element(window).contains(&output))
+                .filter_map(|window| match window {
+                    WindowElement::Wayland(w)
+                        if w.toplevel()
+                            .current_state()
+                            .states
+                            .contains(xdg_toplevel::State::Maximized) =>
+                    {
+                        Some((window.clone(), w.clone()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            for (element, window) in maximized {
+                window.toplevel().with_pending_state(|state| {
+                    state.size = Some(work_area.size);
+                });
+                window.toplevel().send_pending_configure();
+                self.space.map_element(element, work_area.loc, false);
+            }
+        }
+    }
 }
 
 #[derive(Default)]
@@ -320,12 +359,7 @@ fn place_new_window(space: &mut Space<WindowElement>, window: &WindowElement, ac
 
     let output = space.outputs().next().cloned();
     let output_geometry = output
-        .and_then(|o| {
-            let geo = space.output_geometry(&o)?;
-            let map = layer_map_for_output(&o);
-            let zone = map.non_exclusive_zone();
-            Some(Rectangle::from_loc_and_size(geo.loc + zone.loc, zone.size))
-        })
+        .and_then(|o| space.output_work_area(&o))
         .unwrap_or_else(|| Rectangle::from_loc_and_size((0, 0), (800, 800)));
 
     // set the initial toplevel bounds
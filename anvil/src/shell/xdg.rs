@@ -3,7 +3,7 @@ use std::cell::RefCell;
 use smithay::{
     desktop::{
         find_popup_root_surface, layer_map_for_output, space::SpaceElement, PopupKeyboardGrab, PopupKind,
-        PopupPointerGrab, PopupUngrabStrategy, Window, WindowSurfaceType,
+        PopupManager, PopupPointerGrab, PopupUngrabStrategy, Window, WindowSurfaceType,
     },
     input::{pointer::Focus, Seat},
     output::Output,
@@ -15,7 +15,7 @@ use smithay::{
             Resource,
         },
     },
-    utils::Serial,
+    utils::{Logical, Rectangle, Serial},
     wayland::{
         compositor::with_states,
         seat::WaylandFocus,
@@ -55,30 +55,29 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
         // of a xdg_surface has to be sent during the commit if
         // the surface is not already configured
 
-        // TODO: properly recompute the geometry with the whole of positioner state
         surface.with_pending_state(|state| {
-            // NOTE: This is not really necessary as the default geometry
-            // is already set the same way, but for demonstrating how
-            // to set the initial popup geometry this code is left as
-            // an example
             state.geometry = positioner.get_geometry();
+            state.positioner = positioner;
         });
+        self.unconstrain_popup(&surface);
         if let Err(err) = self.popups.track_popup(PopupKind::from(surface)) {
             warn!("Failed to track popup: {}", err);
         }
     }
 
     fn reposition_request(&mut self, surface: PopupSurface, positioner: PositionerState, token: u32) {
-        surface.with_pending_state(|state| {
-            // NOTE: This is again a simplification, a proper compositor would
-            // calculate the geometry of the popup here. For simplicity we just
-            // use the default implementation here that does not take the
-            // window position and output constraints into account.
-            let geometry = positioner.get_geometry();
-            state.geometry = geometry;
-            state.positioner = positioner;
-        });
-        surface.send_repositioned(token);
+        let Some(target) = self.popup_target(&surface) else {
+            // not placed inside the space, keep the position requested by the client
+            surface.with_pending_state(|state| {
+                state.geometry = positioner.get_geometry();
+                state.positioner = positioner;
+            });
+            surface.send_repositioned(token);
+            return;
+        };
+        if let Err(err) = PopupManager::reposition_popup(&surface, positioner, token, target) {
+            warn!("Failed to reposition popup: {}", err);
+        }
     }
 
     fn move_request(&mut self, surface: ToplevelSurface, seat: wl_seat::WlSeat, serial: Serial) {
@@ -287,8 +286,6 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
     }
 
     fn maximize_request(&mut self, surface: ToplevelSurface) {
-        // NOTE: This should use layer-shell when it is implemented to
-        // get the correct maximum size
         if surface
             .current_state()
             .capabilities
@@ -302,7 +299,7 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
                 .or_else(|| self.space.outputs().next())
                 // Assumes that at least one output exists
                 .expect("No outputs found");
-            let geometry = self.space.output_geometry(output).unwrap();
+            let geometry = self.space.output_work_area(output).unwrap();
 
             surface.with_pending_state(|state| {
                 state.states.set(xdg_toplevel::State::Maximized);
@@ -332,6 +329,21 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
         surface.send_pending_configure();
     }
 
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        if !surface
+            .current_state()
+            .capabilities
+            .contains(xdg_toplevel::WmCapabilities::Minimize)
+        {
+            return;
+        }
+
+        // the window gets restored once it requests activation
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.space.hide_element(&window);
+        }
+    }
+
     fn grab(&mut self, surface: PopupSurface, seat: wl_seat::WlSeat, serial: Serial) {
         let seat: Seat<AnvilState<BackendData>> = Seat::from_resource(&seat).unwrap();
         let kind = PopupKind::Xdg(surface);
@@ -381,6 +393,37 @@ impl<BackendData: Backend> XdgShellHandler for AnvilState<BackendData> {
 }
 
 impl<BackendData: Backend> AnvilState<BackendData> {
+    /// The area a popup should be constrained to, relative to the window geometry of its root
+    /// surface: the union of the work areas of all outputs the window is displayed on, or the
+    /// output of a layer surface.
+    fn popup_target(&self, popup: &PopupSurface) -> Option<Rectangle<i32, Logical>> {
+        let root = find_popup_root_surface(&PopupKind::Xdg(popup.clone())).ok()?;
+        let Some(window) = self.window_for_surface(&root) else {
+            return self.space.outputs().find_map(|output| {
+                let map = layer_map_for_output(output);
+                let layer = map.layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)?;
+                map.popup_constraint_area(layer)
+            });
+        };
+        let outputs_geo = self
+            .space
+            .outputs_for_element(&window)
+            .iter()
+            .filter_map(|output| self.space.output_work_area(output))
+            .reduce(|a, b| a.merge(b))?;
+        let window_geo = self.space.element_geometry(&window)?;
+
+        let mut target = outputs_geo;
+        target.loc -= window_geo.loc;
+        Some(target)
+    }
+
+    fn unconstrain_popup(&self, popup: &PopupSurface) {
+        if let Some(target) = self.popup_target(popup) {
+            let _ = PopupManager::unconstrain_popup(popup, target);
+        }
+    }
+
     pub fn move_request_xdg(&mut self, surface: &ToplevelSurface, seat: &Seat<Self>, serial: Serial) {
         // TODO: touch move.
         let pointer = seat.get_pointer().unwrap();
@@ -20,7 +20,7 @@ use smithay::{
             surface_presentation_feedback_flags_from_states, surface_primary_scanout_output,
             update_surface_primary_scanout_output, OutputPresentationFeedback,
         },
-        PopupManager, Space,
+        DndIcon, PopupManager, Space,
     },
     input::{keyboard::XkbConfig, pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
     output::Output,
@@ -130,7 +130,7 @@ pub struct AnvilState<BackendData: Backend + 'static> {
     pub presentation_state: PresentationState,
     pub fractional_scale_manager_state: FractionalScaleManagerState,
 
-    pub dnd_icon: Option<WlSurface>,
+    pub dnd_icon: Option<DndIcon>,
 
     // input-related fields
     pub suppressed_keys: Vec<u32>,
@@ -189,7 +189,7 @@ impl<BackendData: Backend> DataDeviceHandler for AnvilState<BackendData> {
 }
 impl<BackendData: Backend> ClientDndGrabHandler for AnvilState<BackendData> {
     fn started(&mut self, _source: Option<WlDataSource>, icon: Option<WlSurface>, _seat: Seat<Self>) {
-        self.dnd_icon = icon;
+        self.dnd_icon = icon.and_then(|surface| DndIcon::new(surface).ok());
     }
     fn dropped(&mut self, _seat: Seat<Self>) {
         self.dnd_icon = None;
@@ -307,10 +307,15 @@ impl<BackendData: Backend> XdgActivationHandler for AnvilState<BackendData> {
             let w = self
                 .space
                 .elements()
+                .chain(self.space.hidden_elements())
                 .find(|window| window.wl_surface().map(|s| s == surface).unwrap_or(false))
                 .cloned();
             if let Some(window) = w {
-                self.space.raise_element(&window, true);
+                if self.space.is_element_hidden(&window) {
+                    self.space.show_element(&window, true);
+                } else {
+                    self.space.raise_element(&window, true);
+                }
             }
         } else {
             // Discard the request
@@ -395,8 +400,8 @@ impl<BackendData: Backend> FractionalScaleHandler for AnvilState<BackendData> {
         // First we look if the surface already has a primary scan-out output, if not
         // we test if the surface is a subsurface and try to use the primary scan-out output
         // of the root surface. If the root also has no primary scan-out output we just try
-        // to use the first output of the toplevel.
-        // If the surface is the root we also try to use the first output of the toplevel.
+        // to use the output showing most of the toplevel.
+        // If the surface is the root we also try to use the output showing most of the toplevel.
         //
         // If all the above tests do not lead to a output we just use the first output
         // of the space (which in case of anvil will also be the output a toplevel will
@@ -412,14 +417,13 @@ impl<BackendData: Backend> FractionalScaleHandler for AnvilState<BackendData> {
                     if root != surface {
                         with_states(&root, |states| {
                             surface_primary_scanout_output(&root, states).or_else(|| {
-                                self.window_for_surface(&root).and_then(|window| {
-                                    self.space.outputs_for_element(&window).first().cloned()
-                                })
+                                self.window_for_surface(&root)
+                                    .and_then(|window| self.space.element_primary_output(&window))
                             })
                         })
                     } else {
                         self.window_for_surface(&root)
-                            .and_then(|window| self.space.outputs_for_element(&window).first().cloned())
+                            .and_then(|window| self.space.element_primary_output(&window))
                     }
                 })
                 .or_else(|| self.space.outputs().next().cloned());
@@ -603,6 +607,9 @@ pub fn post_repaint(
     let throttle = Some(Duration::from_secs(1));
 
     space.elements().for_each(|window| {
+        // the preferred scale follows the output showing most of the window,
+        // so it changes as soon as the window is dragged mostly onto another output
+        let primary_output = space.element_primary_output(window);
         window.with_surfaces(|surface, states| {
             let primary_scanout_output = update_surface_primary_scanout_output(
                 surface,
@@ -612,7 +619,7 @@ pub fn post_repaint(
                 default_primary_scanout_output_compare,
             );
 
-            if let Some(output) = primary_scanout_output {
+            if let Some(output) = primary_output.as_ref().or(primary_scanout_output.as_ref()) {
                 with_fractional_scale(states, |fraction_scale| {
                     fraction_scale.set_preferred_scale(output.current_scale().fractional_scale());
                 });
@@ -633,6 +640,10 @@ pub fn post_repaint(
             }
         }
     });
+    // minimized windows are not on any output, keep them alive with throttled frame callbacks
+    space.hidden_elements().for_each(|window| {
+        window.send_frame(output, time, throttle, |_, _| None);
+    });
     let map = smithay::desktop::layer_map_for_output(output);
     for layer_surface in map.layers() {
         layer_surface.with_surfaces(|surface, states| {
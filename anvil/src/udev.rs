@@ -53,6 +53,7 @@ use smithay::{
     desktop::{
         space::{Space, SurfaceTree},
         utils::OutputPresentationFeedback,
+        DndIcon,
     },
     input::pointer::{CursorImageAttributes, CursorImageStatus},
     output::{Mode as WlMode, Output, PhysicalProperties, Subpixel},
@@ -69,10 +70,7 @@ use smithay::{
         },
         input::Libinput,
         nix::fcntl::OFlag,
-        wayland_protocols::wp::{
-            linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
-            presentation_time::server::wp_presentation_feedback,
-        },
+        wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
         wayland_server::{backend::GlobalId, protocol::wl_surface, Display, DisplayHandle},
     },
     utils::{Clock, DeviceFd, IsAlive, Logical, Monotonic, Point, Scale, Transform},
@@ -443,24 +441,13 @@ pub fn run_udev() {
     event_loop
         .handle()
         .insert_source(udev_backend, move |event, _, data| match event {
-            UdevEvent::Added { device_id, path } => {
-                if let Err(err) = DrmNode::from_dev_id(device_id)
-                    .map_err(DeviceAddError::DrmNode)
-                    .and_then(|node| data.state.device_added(node, &path))
-                {
-                    error!("Skipping device {device_id}: {err}");
-                }
-            }
-            UdevEvent::Changed { device_id } => {
-                if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    data.state.device_changed(node)
-                }
-            }
-            UdevEvent::Removed { device_id } => {
-                if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    data.state.device_removed(node)
+            UdevEvent::Added { device } => {
+                if let Err(err) = data.state.device_added(device.node, &device.path) {
+                    error!("Skipping device {}: {err}", device.node);
                 }
             }
+            UdevEvent::Changed { device } => data.state.device_changed(device.node),
+            UdevEvent::Removed { device } => data.state.device_removed(device.node),
         })
         .unwrap();
 
@@ -1043,6 +1030,9 @@ impl AnvilState<UdevData> {
                 .gpus
                 .as_mut()
                 .remove_node(&backend_data.render_node);
+            if let Err(err) = self.backend_data.gpus.update_devices() {
+                warn!("Failed to update gpus: {}", err);
+            }
 
             self.handle.remove(backend_data.registration_token);
 
@@ -1089,32 +1079,7 @@ impl AnvilState<UdevData> {
         {
             Ok(user_data) => {
                 if let Some(mut feedback) = user_data.flatten() {
-                    let tp = metadata.as_ref().and_then(|metadata| match metadata.time {
-                        smithay::backend::drm::DrmEventTime::Monotonic(tp) => Some(tp),
-                        smithay::backend::drm::DrmEventTime::Realtime(_) => None,
-                    });
-                    let seq = metadata.as_ref().map(|metadata| metadata.sequence).unwrap_or(0);
-
-                    let (clock, flags) = if let Some(tp) = tp {
-                        (
-                            tp.into(),
-                            wp_presentation_feedback::Kind::Vsync
-                                | wp_presentation_feedback::Kind::HwClock
-                                | wp_presentation_feedback::Kind::HwCompletion,
-                        )
-                    } else {
-                        (self.clock.now(), wp_presentation_feedback::Kind::Vsync)
-                    };
-
-                    feedback.presented(
-                        clock,
-                        output
-                            .current_mode()
-                            .map(|mode| mode.refresh as u32)
-                            .unwrap_or_default(),
-                        seq as u64,
-                        flags,
-                    );
+                    feedback.presented_drm(&self.clock, metadata.as_ref());
                 }
 
                 true
@@ -1408,7 +1373,7 @@ fn render_surface<'a, 'b>(
     pointer_location: Point<f64, Logical>,
     pointer_image: &TextureBuffer<MultiTexture>,
     pointer_element: &mut PointerElement<MultiTexture>,
-    dnd_icon: &Option<wl_surface::WlSurface>,
+    dnd_icon: &Option<DndIcon>,
     cursor_status: &mut CursorImageStatus,
     clock: &Clock<Monotonic>,
     show_window_preview: bool,
@@ -1471,10 +1436,10 @@ fn render_surface<'a, 'b>(
 
         // draw the dnd icon if applicable
         {
-            if let Some(wl_surface) = dnd_icon.as_ref() {
-                if wl_surface.alive() {
+            if let Some(icon) = dnd_icon.as_ref() {
+                if icon.alive() {
                     custom_elements.extend(AsRenderElements::<UdevRenderer<'a, 'b>>::render_elements(
-                        &SurfaceTree::from_surface(wl_surface),
+                        icon,
                         renderer,
                         cursor_pos_scaled,
                         scale,
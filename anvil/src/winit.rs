@@ -328,10 +328,10 @@ pub fn run_winit() {
                 });
 
                 // draw the dnd icon if any
-                if let Some(surface) = dnd_icon {
-                    if surface.alive() {
+                if let Some(icon) = dnd_icon {
+                    if icon.alive() {
                         elements.extend(AsRenderElements::<GlesRenderer>::render_elements(
-                            &smithay::desktop::space::SurfaceTree::from_surface(surface),
+                            icon,
                             renderer,
                             cursor_pos_scaled,
                             scale,
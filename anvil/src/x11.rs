@@ -31,6 +31,7 @@ use smithay::{
         x11::{WindowBuilder, X11Backend, X11Event, X11Surface},
     },
     delegate_dmabuf,
+    desktop::utils::OutputPresentationFeedback,
     input::pointer::{CursorImageAttributes, CursorImageStatus},
     output::{Mode, Output, PhysicalProperties, Subpixel},
     reexports::{
@@ -40,7 +41,7 @@ use smithay::{
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
         wayland_server::{protocol::wl_surface, Display},
     },
-    utils::{DeviceFd, IsAlive, Point, Scale},
+    utils::{DeviceFd, IsAlive, Monotonic, Point, Scale},
     wayland::{
         compositor,
         dmabuf::{
@@ -65,6 +66,7 @@ pub struct X11Data {
     dmabuf_state: DmabufState,
     _dmabuf_global: DmabufGlobal,
     _dmabuf_default_feedback: DmabufFeedback,
+    pending_presentation: Option<OutputPresentationFeedback>,
     #[cfg(feature = "debug")]
     fps: fps_ticker::Fps,
 }
@@ -241,6 +243,7 @@ pub fn run_x11() {
         dmabuf_state,
         _dmabuf_global: dmabuf_global,
         _dmabuf_default_feedback: dmabuf_default_feedback,
+        pending_presentation: None,
         #[cfg(feature = "debug")]
         fps: fps_ticker::Fps::default(),
     };
@@ -273,7 +276,21 @@ pub fn run_x11() {
 
                 data.state.backend_data.render = true;
             }
-            X11Event::PresentCompleted { .. } | X11Event::Refresh { .. } => {
+            X11Event::PresentCompleted { feedback, .. } => {
+                if let Some(mut presentation) = data.state.backend_data.pending_presentation.take() {
+                    presentation.presented::<_, Monotonic>(
+                        feedback.time,
+                        feedback
+                            .refresh
+                            .map(|refresh| refresh.as_nanos() as u32)
+                            .unwrap_or_default(),
+                        feedback.msc,
+                        wp_presentation_feedback::Kind::Vsync | wp_presentation_feedback::Kind::HwCompletion,
+                    );
+                }
+                data.state.backend_data.render = true;
+            }
+            X11Event::Refresh { .. } => {
                 data.state.backend_data.render = true;
             }
             X11Event::Input(event) => {
@@ -378,10 +395,10 @@ pub fn run_x11() {
             });
 
             // draw the dnd icon if any
-            if let Some(surface) = state.dnd_icon.as_ref() {
-                if surface.alive() {
+            if let Some(icon) = state.dnd_icon.as_ref() {
+                if icon.alive() {
                     elements.extend(AsRenderElements::<GlesRenderer>::render_elements(
-                        &smithay::desktop::space::SurfaceTree::from_surface(surface),
+                        icon,
                         &mut backend_data.renderer,
                         cursor_pos_scaled,
                         scale,
@@ -433,17 +450,16 @@ pub fn run_x11() {
                     post_repaint(&output, &states, &state.space, None, time);
 
                     if damage.is_some() {
-                        let mut output_presentation_feedback =
+                        // the feedback is sent once the X server reports the buffer as presented
+                        let output_presentation_feedback =
                             take_presentation_feedback(&output, &state.space, &states);
-                        output_presentation_feedback.presented(
-                            time,
-                            output
-                                .current_mode()
-                                .map(|mode| mode.refresh as u32)
-                                .unwrap_or_default(),
-                            0,
-                            wp_presentation_feedback::Kind::Vsync,
-                        )
+                        if let Some(mut previous) = state
+                            .backend_data
+                            .pending_presentation
+                            .replace(output_presentation_feedback)
+                        {
+                            previous.discarded();
+                        }
                     }
                 }
                 Err(err) => {
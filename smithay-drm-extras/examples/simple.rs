@@ -65,10 +65,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 fn init_udev(state: &mut State) {
     let backend = UdevBackend::new(state.session.seat()).unwrap();
-    for (device_id, path) in backend.device_list() {
+    for device in backend.devices() {
         state.on_udev_event(UdevEvent::Added {
-            device_id,
-            path: path.to_owned(),
+            device: device.clone(),
         });
     }
 
@@ -81,21 +80,9 @@ fn init_udev(state: &mut State) {
 impl State {
     fn on_udev_event(&mut self, event: UdevEvent) {
         match event {
-            UdevEvent::Added { device_id, path } => {
-                if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    self.device_added(node, path);
-                }
-            }
-            UdevEvent::Changed { device_id } => {
-                if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    self.device_changed(node);
-                }
-            }
-            UdevEvent::Removed { device_id } => {
-                if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    self.device_removed(node);
-                }
-            }
+            UdevEvent::Added { device } => self.device_added(device.node, device.path),
+            UdevEvent::Changed { device } => self.device_changed(device.node),
+            UdevEvent::Removed { device } => self.device_removed(device.node),
         }
     }
 
@@ -14,7 +14,7 @@ pub(super) mod atomic;
 mod fd;
 pub use self::fd::DrmDeviceFd;
 pub(super) mod legacy;
-use crate::utils::{Buffer, DevPath, Size};
+use crate::utils::{Buffer, DevPath, Monotonic, PresentationClock, Realtime, Size, Time as ClockTime};
 
 use super::surface::{atomic::AtomicDrmSurface, legacy::LegacyDrmSurface, DrmSurface, DrmSurfaceInternal};
 use super::{error::Error, planes, Planes};
@@ -112,6 +112,7 @@ pub struct DrmDevice {
     pub(super) dev_id: dev_t,
     pub(crate) internal: Arc<DrmDeviceInternal>,
     has_universal_planes: bool,
+    has_monotonic_timestamps: bool,
     cursor_size: Size<u32, Buffer>,
     resources: ResourceHandles,
     plane_claim_storage: PlaneClaimStorage,
@@ -150,6 +151,39 @@ impl DrmDeviceInternal {
             DrmDeviceInternal::Legacy(internal) => &internal.span,
         }
     }
+
+    fn pause(&self) {
+        self.set_active(false);
+        if self.device_fd().is_privileged() {
+            if let Err(err) = self.release_master_lock() {
+                error!("Failed to drop drm master state Error: {}", err);
+            }
+        }
+    }
+
+    fn activate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let res = if self.device_fd().is_privileged() {
+            self.acquire_master_lock()
+        } else {
+            Ok(())
+        };
+        self.set_active(true);
+        Ok(res?)
+    }
+
+    fn is_active(&self) -> bool {
+        match self {
+            DrmDeviceInternal::Atomic(internal) => internal.active.load(Ordering::SeqCst),
+            DrmDeviceInternal::Legacy(internal) => internal.active.load(Ordering::SeqCst),
+        }
+    }
+
+    fn set_active(&self, active: bool) {
+        match self {
+            DrmDeviceInternal::Atomic(internal) => internal.active.store(active, Ordering::SeqCst),
+            DrmDeviceInternal::Legacy(internal) => internal.active.store(active, Ordering::SeqCst),
+        }
+    }
 }
 
 impl AsFd for DrmDeviceInternal {
@@ -220,6 +254,7 @@ impl DrmDevice {
                 dev_id,
                 internal: internal.clone(),
                 has_universal_planes,
+                has_monotonic_timestamps,
                 cursor_size,
                 resources,
                 plane_claim_storage: Default::default(),
@@ -291,6 +326,19 @@ impl DrmDevice {
         self.cursor_size
     }
 
+    /// Returns the clock domain of the timestamps of the page flip events of this device
+    ///
+    /// This should be advertised through the
+    /// [`PresentationState`](crate::wayland::presentation::PresentationState) global,
+    /// unless the timestamps are converted, see [`Time::to_monotonic`].
+    pub fn presentation_clock(&self) -> PresentationClock {
+        if self.has_monotonic_timestamps {
+            PresentationClock::Monotonic
+        } else {
+            PresentationClock::Realtime
+        }
+    }
+
     /// Creates a new rendering surface.
     ///
     /// # Arguments
@@ -387,38 +435,58 @@ impl DrmDevice {
     /// Note that calls directly utilizing the underlying file descriptor, like the traits of the `drm-rs` crate,
     /// will ignore this state. Use [`DrmDevice::is_active`] to guard these calls.
     pub fn pause(&self) {
-        self.set_active(false);
-        if self.device_fd().is_privileged() {
-            if let Err(err) = self.release_master_lock() {
-                error!("Failed to drop drm master state Error: {}", err);
-            }
-        }
+        self.internal.pause();
     }
 
     /// Actives a previously paused device.
     pub fn activate(&self) {
-        if self.device_fd().is_privileged() {
-            if let Err(err) = self.acquire_master_lock() {
-                error!("Failed to acquire drm master again. Error: {}", err);
-            }
+        if let Err(err) = self.internal.activate() {
+            error!("Failed to acquire drm master again. Error: {}", err);
         }
-        self.set_active(true);
     }
 
     /// Returns if the device is currently paused or not.
     pub fn is_active(&self) -> bool {
-        match &*self.internal {
-            DrmDeviceInternal::Atomic(internal) => internal.active.load(Ordering::SeqCst),
-            DrmDeviceInternal::Legacy(internal) => internal.active.load(Ordering::SeqCst),
+        self.internal.is_active()
+    }
+
+    /// Returns a handle to pause and activate this device through a
+    /// [`SessionMultiplexer`](crate::backend::session::SessionMultiplexer)
+    #[cfg(feature = "backend_session")]
+    pub fn session_device(&self) -> DrmSessionDevice {
+        DrmSessionDevice(Arc::downgrade(&self.internal))
+    }
+}
+
+/// Handle to pause and activate a [`DrmDevice`] on session changes
+///
+/// Does not keep the device alive.
+#[cfg(feature = "backend_session")]
+#[derive(Debug)]
+pub struct DrmSessionDevice(Weak<DrmDeviceInternal>);
+
+#[cfg(feature = "backend_session")]
+impl crate::backend::session::SessionDevice for DrmSessionDevice {
+    fn kind(&self) -> crate::backend::session::SessionDeviceKind {
+        crate::backend::session::SessionDeviceKind::Graphics
+    }
+
+    fn pause(&mut self) {
+        if let Some(internal) = self.0.upgrade() {
+            internal.pause();
         }
     }
 
-    fn set_active(&self, active: bool) {
-        match &*self.internal {
-            DrmDeviceInternal::Atomic(internal) => internal.active.store(active, Ordering::SeqCst),
-            DrmDeviceInternal::Legacy(internal) => internal.active.store(active, Ordering::SeqCst),
+    fn activate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.0.upgrade() {
+            Some(internal) => internal.activate(),
+            None => Ok(()),
         }
     }
+
+    fn alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
 }
 
 /// Events that can be generated by a DrmDevice
@@ -448,6 +516,24 @@ pub enum Time {
     Realtime(SystemTime),
 }
 
+impl Time {
+    /// Returns the clock domain of the timestamp
+    pub fn clock(&self) -> PresentationClock {
+        match self {
+            Time::Monotonic(_) => PresentationClock::Monotonic,
+            Time::Realtime(_) => PresentationClock::Realtime,
+        }
+    }
+
+    /// Converts the timestamp into the monotonic clock domain
+    pub fn to_monotonic(&self) -> ClockTime<Monotonic> {
+        match self {
+            Time::Monotonic(tp) => ClockTime::from(*tp),
+            Time::Realtime(tp) => ClockTime::<Realtime>::from(*tp).to_clock(),
+        }
+    }
+}
+
 /// Even source of [`DrmDevice`]
 #[derive(Debug)]
 pub struct DrmDeviceNotifier {
@@ -81,6 +81,8 @@ pub mod node;
 pub(self) mod surface;
 
 use crate::utils::DevPath;
+#[cfg(feature = "backend_session")]
+pub use device::DrmSessionDevice;
 pub use device::{
     DrmDevice, DrmDeviceFd, DrmDeviceNotifier, DrmEvent, EventMetadata as DrmEventMetadata, PlaneClaim,
     Time as DrmEventTime,
@@ -0,0 +1,251 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use calloop::{
+    ping::{make_ping, Ping, PingSource},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug_span, info, instrument};
+
+use super::{FrameMode, FrameQueue, HeadlessFrame, HeadlessFrameClock, HeadlessOutput};
+use crate::{
+    backend::allocator::{dmabuf::AsDmabuf, Allocator, Fourcc, Modifier},
+    output::{Mode, Output, Scale},
+};
+
+#[derive(Debug, Default)]
+struct PendingClocks {
+    added: Vec<HeadlessFrameClock>,
+    removed: Vec<Arc<Mutex<FrameQueue>>>,
+}
+
+/// Headless backend managing virtual outputs added and removed at runtime
+///
+/// All outputs allocate their buffers with the same allocator, format and modifiers and
+/// are paced by the same [`FrameMode`]. Their frames are presented by a single
+/// [`HeadlessFrameSource`], which needs to be inserted into the event loop once.
+///
+/// ```ignore
+/// let (mut backend, frame_source) = HeadlessBackend::new(allocator, Fourcc::Argb8888, vec![Modifier::Linear], FrameMode::Timer)?;
+/// event_loop.handle().insert_source(frame_source, |frame, _, state| {
+///     state.render(&frame.output);
+/// })?;
+///
+/// let output = backend.add_output("HEADLESS-1", mode, Scale::Integer(1))?.output().clone();
+/// // ...
+/// backend.remove_output(&output);
+/// ```
+#[derive(Debug)]
+pub struct HeadlessBackend<A: Allocator> {
+    allocator: A,
+    format: Fourcc,
+    modifiers: Vec<Modifier>,
+    frame_mode: FrameMode,
+    outputs: Vec<HeadlessOutput<A>>,
+    pending: Arc<Mutex<PendingClocks>>,
+    ping: Ping,
+    span: tracing::Span,
+}
+
+/// Event source presenting the frames of all outputs of a [`HeadlessBackend`]
+///
+/// Emits a [`HeadlessFrame`] for every presented frame.
+#[derive(Debug)]
+pub struct HeadlessFrameSource {
+    clocks: Vec<HeadlessFrameClock>,
+    pending: Arc<Mutex<PendingClocks>>,
+    ping: PingSource,
+}
+
+impl<A> HeadlessBackend<A>
+where
+    A: Allocator + Clone,
+    A::Buffer: AsDmabuf,
+    <A::Buffer as AsDmabuf>::Error: std::error::Error + 'static,
+    A::Error: 'static,
+{
+    /// Create a new headless backend without any outputs
+    ///
+    /// The returned [`HeadlessFrameSource`] presents the frames of all outputs
+    /// and needs to be inserted into the event loop.
+    pub fn new(
+        allocator: A,
+        format: Fourcc,
+        modifiers: Vec<Modifier>,
+        frame_mode: FrameMode,
+    ) -> io::Result<(HeadlessBackend<A>, HeadlessFrameSource)> {
+        let pending = Arc::new(Mutex::new(PendingClocks::default()));
+        let (ping, ping_source) = make_ping()?;
+
+        Ok((
+            HeadlessBackend {
+                allocator,
+                format,
+                modifiers,
+                frame_mode,
+                outputs: Vec::new(),
+                pending: pending.clone(),
+                ping,
+                span: debug_span!("backend_headless"),
+            },
+            HeadlessFrameSource {
+                clocks: Vec::new(),
+                pending,
+                ping: ping_source,
+            },
+        ))
+    }
+
+    /// Add a new virtual output
+    ///
+    /// The frame clock of the output is picked up by the [`HeadlessFrameSource`]
+    /// on the next dispatch of the event loop.
+    #[instrument(level = "debug", parent = &self.span, skip(self, name))]
+    pub fn add_output(
+        &mut self,
+        name: impl Into<String>,
+        mode: Mode,
+        scale: Scale,
+    ) -> io::Result<&mut HeadlessOutput<A>> {
+        let name = name.into();
+        if self.outputs.iter().any(|output| output.output().name() == name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("An output named {} already exists", name),
+            ));
+        }
+
+        let (mut output, clock) = HeadlessOutput::new(
+            self.allocator.clone(),
+            name,
+            mode,
+            self.format,
+            self.modifiers.clone(),
+            self.frame_mode,
+        )?;
+        output.set_scale(scale);
+        info!(name = output.output().name(), "Added headless output");
+
+        self.pending.lock().unwrap().added.push(clock);
+        self.ping.ping();
+        self.outputs.push(output);
+        Ok(self.outputs.last_mut().unwrap())
+    }
+
+    /// Remove a virtual output
+    ///
+    /// Frames of the output are not presented anymore. Returns the removed output,
+    /// or `None` if the output does not belong to this backend.
+    #[instrument(level = "debug", parent = &self.span, skip(self), fields(name = output.name()))]
+    pub fn remove_output(&mut self, output: &Output) -> Option<HeadlessOutput<A>> {
+        let idx = self.outputs.iter().position(|o| o.output() == output)?;
+        let removed = self.outputs.remove(idx);
+        info!("Removed headless output");
+
+        self.pending.lock().unwrap().removed.push(removed.queue.clone());
+        self.ping.ping();
+        Some(removed)
+    }
+
+    /// Returns the virtual outputs of this backend
+    pub fn outputs(&self) -> impl Iterator<Item = &HeadlessOutput<A>> {
+        self.outputs.iter()
+    }
+
+    /// Returns the virtual outputs of this backend for rendering
+    pub fn outputs_mut(&mut self) -> impl Iterator<Item = &mut HeadlessOutput<A>> {
+        self.outputs.iter_mut()
+    }
+
+    /// Returns the virtual output of the given [`Output`]
+    pub fn output_mut(&mut self, output: &Output) -> Option<&mut HeadlessOutput<A>> {
+        self.outputs.iter_mut().find(|o| o.output() == output)
+    }
+}
+
+impl HeadlessFrameSource {
+    // Applies added and removed outputs, unregistering the frame clocks of removed outputs
+    fn update_clocks(&mut self, poll: &mut Poll) -> calloop::Result<Vec<HeadlessFrameClock>> {
+        let PendingClocks { mut added, removed } = std::mem::take(&mut *self.pending.lock().unwrap());
+
+        for queue in removed {
+            if let Some(idx) = self
+                .clocks
+                .iter()
+                .position(|clock| Arc::ptr_eq(&clock.queue, &queue))
+            {
+                self.clocks.remove(idx).unregister(poll)?;
+            } else {
+                // the output was removed before its clock got registered
+                added.retain(|clock| !Arc::ptr_eq(&clock.queue, &queue));
+            }
+        }
+
+        Ok(added)
+    }
+}
+
+impl EventSource for HeadlessFrameSource {
+    type Event = HeadlessFrame;
+    type Metadata = ();
+    type Ret = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut ()),
+    {
+        let mut outputs_changed = false;
+        self.ping
+            .process_events(readiness, token, |_, _| outputs_changed = true)?;
+
+        for clock in self.clocks.iter_mut() {
+            clock.process_events(readiness, token, &mut callback)?;
+        }
+
+        // (un-)register the frame clocks of added or removed outputs
+        if outputs_changed {
+            Ok(PostAction::Reregister)
+        } else {
+            Ok(PostAction::Continue)
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.ping.register(poll, token_factory)?;
+        let added = self.update_clocks(poll)?;
+        self.clocks.extend(added);
+        for clock in self.clocks.iter_mut() {
+            clock.register(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.ping.reregister(poll, token_factory)?;
+        let added = self.update_clocks(poll)?;
+        for clock in self.clocks.iter_mut() {
+            clock.reregister(poll, token_factory)?;
+        }
+        for mut clock in added {
+            clock.register(poll, token_factory)?;
+            self.clocks.push(clock);
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.ping.unregister(poll)?;
+        for clock in self.clocks.iter_mut() {
+            clock.unregister(poll)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+//! Headless backend rendering virtual outputs into offscreen buffers
+//!
+//! This backend does not require any display hardware. A [`HeadlessOutput`] is a virtual
+//! [`Output`] rendering into a [`Swapchain`] of buffers allocated by any [`Allocator`],
+//! e.g. a [`GbmAllocator`](crate::backend::allocator::gbm::GbmAllocator) on a render node.
+//! The buffers are handed out as [`Dmabuf`]s, which can be bound as a render target by the renderers.
+//!
+//! Submitted frames are presented by the [`HeadlessFrameClock`] of the output, which needs
+//! to be inserted into the event loop. It emits a [`HeadlessFrame`] for every presented frame,
+//! which may be consumed e.g. by a remote-desktop implementation streaming the output or
+//! in a CI setup taking screenshots. Frames are either paced by a timer running at the
+//! refresh rate of the output ([`FrameMode::Timer`]), or presented as soon as they are
+//! submitted ([`FrameMode::OnDemand`]).
+//!
+//! ```ignore
+//! let (mut output, frame_clock) = HeadlessOutput::new(
+//!     allocator,
+//!     "HEADLESS-1",
+//!     Mode { size: (1920, 1080).into(), refresh: 60_000 },
+//!     Fourcc::Argb8888,
+//!     vec![Modifier::Linear],
+//!     FrameMode::Timer,
+//! )?;
+//! event_loop.handle().insert_source(frame_clock, |frame, _, state| {
+//!     state.stream_frame(&frame.dmabuf);
+//!     // the previous frame was presented, render the next one
+//!     state.render(&frame.output);
+//! })?;
+//!
+//! let (dmabuf, age) = output.next_buffer()?;
+//! renderer.bind(dmabuf)?;
+//! // render the output..
+//! output.submit()?;
+//! ```
+//!
+//! Outputs can also be added and removed at runtime through a [`HeadlessBackend`], which presents
+//! the frames of all its outputs through a single [`HeadlessFrameSource`].
+//!
+//! ## Testing
+//!
+//! For integration tests of compositor logic, e.g. in CI, this module also provides a
+//! [`TestOutput`], a virtual output rendered by the cpu-based
+//! [`SoftwareRenderer`](crate::backend::renderer::software::SoftwareRenderer), and a
+//! deterministic [`TestClock`] driving the presentation of its frames. The mode, scale and
+//! transform of the outputs can be changed at any time. Together with an
+//! [`InputScript`](crate::backend::replay::InputScript) injecting scripted input sequences,
+//! a compositor can be tested without any hardware or timing dependencies.
+//!
+//! ```ignore
+//! let clock = TestClock::new();
+//! let mut output = TestOutput::new("TEST-1", mode, &clock)?;
+//! state.space.map_output(output.output(), (0, 0));
+//!
+//! for event in script {
+//!     state.process_input_event(event);
+//! }
+//!
+//! renderer.bind(output.buffer().clone())?;
+//! damage_tracker.render_output(&mut renderer, 0, &elements, [0.0, 0.0, 0.0, 1.0])?;
+//! clock.advance(output.refresh_interval());
+//! let frame = output.present();
+//! state.space.elements().for_each(|window| window.send_frame(&frame.output, frame.time, None, |_, _| None));
+//! assert_eq!(output.buffer().pixel((10, 10).into()), Some(0xff000000));
+//! ```
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    ping::{make_ping, Ping, PingSource},
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug_span, info, instrument, trace};
+
+use crate::{
+    backend::allocator::{
+        dmabuf::{AsDmabuf, Dmabuf},
+        Allocator, Fourcc, Modifier, Slot, Swapchain,
+    },
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel, WeakOutput},
+    utils::{Clock, Monotonic, Time, Transform},
+};
+
+mod backend;
+pub use backend::{HeadlessBackend, HeadlessFrameSource};
+
+#[cfg(feature = "renderer_software")]
+mod testing;
+#[cfg(feature = "renderer_software")]
+pub use testing::{TestClock, TestFrame, TestOutput};
+
+/// Pacing of the frames of a [`HeadlessOutput`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameMode {
+    /// Submitted frames are presented at the next tick of a timer running at the refresh rate
+    /// of the output
+    Timer,
+    /// Submitted frames are presented immediately
+    OnDemand,
+}
+
+/// A frame presented by a [`HeadlessOutput`]
+#[derive(Debug)]
+pub struct HeadlessFrame {
+    /// The output the frame was presented on
+    pub output: Output,
+    /// The buffer containing the frame
+    ///
+    /// The buffer is not re-used for rendering until the next frame was submitted,
+    /// so its contents have to be consumed before that.
+    pub dmabuf: Dmabuf,
+    /// Sequence number of the frame, starting at one for the first presented frame
+    pub sequence: u64,
+    /// Time the frame was presented at
+    pub time: Time<Monotonic>,
+}
+
+/// Errors of a [`HeadlessOutput`]
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError<A, E>
+where
+    A: std::error::Error + 'static,
+    E: std::error::Error + 'static,
+{
+    /// All buffers of the swapchain are in use
+    #[error("No free buffer available")]
+    NoFreeSlots,
+    /// No buffer was acquired through [`HeadlessOutput::next_buffer`] before submitting
+    #[error("No buffer has been acquired to be submitted")]
+    NoBuffer,
+    /// Allocating a buffer failed
+    #[error("Failed to allocate a buffer: {0}")]
+    Allocation(#[source] A),
+    /// Exporting a buffer as dmabuf failed
+    #[error("Failed to export the buffer as dmabuf: {0}")]
+    Export(#[source] E),
+}
+
+#[derive(Debug)]
+struct FrameQueue {
+    refresh: Duration,
+    queued: Option<Dmabuf>,
+    sequence: u64,
+}
+
+/// A virtual output rendering into offscreen buffers
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct HeadlessOutput<A: Allocator> {
+    output: Output,
+    swapchain: Swapchain<A>,
+    pending: Option<Slot<A::Buffer>>,
+    front: Option<Slot<A::Buffer>>,
+    queue: Arc<Mutex<FrameQueue>>,
+    ping: Option<Ping>,
+    span: tracing::Span,
+}
+
+/// Event source presenting the frames of a [`HeadlessOutput`]
+///
+/// Emits a [`HeadlessFrame`] for every presented frame.
+#[derive(Debug)]
+pub struct HeadlessFrameClock {
+    output: WeakOutput,
+    queue: Arc<Mutex<FrameQueue>>,
+    timer: Option<Timer>,
+    ping: Option<PingSource>,
+    clock: Clock<Monotonic>,
+}
+
+// the refresh interval of a mode, 60Hz if the refresh rate is unknown
+fn refresh_interval(mode: Mode) -> Duration {
+    if mode.refresh > 0 {
+        Duration::from_nanos(1_000_000_000_000 / mode.refresh as u64)
+    } else {
+        Duration::from_nanos(1_000_000_000 / 60)
+    }
+}
+
+impl<A> HeadlessOutput<A>
+where
+    A: Allocator,
+    A::Buffer: AsDmabuf,
+    <A::Buffer as AsDmabuf>::Error: std::error::Error + 'static,
+    A::Error: 'static,
+{
+    /// Create a new virtual output
+    ///
+    /// The returned [`HeadlessFrameClock`] presents the submitted frames
+    /// and needs to be inserted into the event loop.
+    pub fn new(
+        allocator: A,
+        name: impl Into<String>,
+        mode: Mode,
+        format: Fourcc,
+        modifiers: Vec<Modifier>,
+        frame_mode: FrameMode,
+    ) -> io::Result<(HeadlessOutput<A>, HeadlessFrameClock)> {
+        let name = name.into();
+        let span = debug_span!("backend_headless", output = name);
+        let _guard = span.enter();
+        info!(?mode, ?frame_mode, "Creating headless output");
+
+        let output = Output::new(
+            name,
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Headless".into(),
+            },
+        );
+        output.set_preferred(mode);
+        output.change_current_state(Some(mode), None, None, None);
+
+        let queue = Arc::new(Mutex::new(FrameQueue {
+            refresh: refresh_interval(mode),
+            queued: None,
+            sequence: 0,
+        }));
+        let (ping, ping_source, timer) = match frame_mode {
+            FrameMode::Timer => (None, None, Some(Timer::from_duration(refresh_interval(mode)))),
+            FrameMode::OnDemand => {
+                let (ping, source) = make_ping()?;
+                (Some(ping), Some(source), None)
+            }
+        };
+        let frame_clock = HeadlessFrameClock {
+            output: output.downgrade(),
+            queue: queue.clone(),
+            timer,
+            ping: ping_source,
+            clock: Clock::new()?,
+        };
+
+        let swapchain = Swapchain::new(
+            allocator,
+            mode.size.w as u32,
+            mode.size.h as u32,
+            format,
+            modifiers,
+        );
+        drop(_guard);
+
+        Ok((
+            HeadlessOutput {
+                output,
+                swapchain,
+                pending: None,
+                front: None,
+                queue,
+                ping,
+                span,
+            },
+            frame_clock,
+        ))
+    }
+
+    /// Returns the [`Output`] of this virtual output
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Returns the format of the buffers
+    pub fn format(&self) -> Fourcc {
+        self.swapchain.format()
+    }
+
+    /// Change the mode of the output
+    ///
+    /// Buffers are re-allocated with the new size and the frame timer
+    /// picks up the new refresh rate.
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.output.change_current_state(Some(mode), None, None, None);
+        self.swapchain.resize(mode.size.w as u32, mode.size.h as u32);
+        self.pending = None;
+        self.queue.lock().unwrap().refresh = refresh_interval(mode);
+    }
+
+    /// Change the scale of the output
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.output.change_current_state(None, None, Some(scale), None);
+    }
+
+    /// Change the transform of the output
+    ///
+    /// The size of the buffers is not affected by the transform.
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.output
+            .change_current_state(None, Some(transform), None, None);
+    }
+
+    /// Returns the buffer to render the next frame into and its age
+    ///
+    /// This returns the same buffer until [`submit`](Self::submit) is called.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    #[allow(clippy::type_complexity)]
+    pub fn next_buffer(
+        &mut self,
+    ) -> Result<(Dmabuf, u8), HeadlessError<A::Error, <A::Buffer as AsDmabuf>::Error>> {
+        if self.pending.is_none() {
+            let slot = self
+                .swapchain
+                .acquire()
+                .map_err(HeadlessError::Allocation)?
+                .ok_or(HeadlessError::NoFreeSlots)?;
+            self.pending = Some(slot);
+        }
+
+        let slot = self.pending.as_ref().unwrap();
+        let dmabuf = slot.export().map_err(HeadlessError::Export)?;
+        Ok((dmabuf, slot.age()))
+    }
+
+    /// Submit the buffer returned by [`next_buffer`](Self::next_buffer)
+    ///
+    /// The frame is presented by the [`HeadlessFrameClock`] of the output. If a previous
+    /// frame was not presented yet, it is replaced by this frame.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn submit(&mut self) -> Result<(), HeadlessError<A::Error, <A::Buffer as AsDmabuf>::Error>> {
+        let slot = self.pending.take().ok_or(HeadlessError::NoBuffer)?;
+        let dmabuf = slot.export().map_err(HeadlessError::Export)?;
+        self.swapchain.submitted(&slot);
+        // keep the buffer from being re-used, until the next frame is submitted
+        self.front = Some(slot);
+
+        self.queue.lock().unwrap().queued = Some(dmabuf);
+        if let Some(ping) = self.ping.as_ref() {
+            ping.ping();
+        }
+        Ok(())
+    }
+
+    /// Returns the buffer of the last submitted frame
+    pub fn last_frame(&self) -> Option<&A::Buffer> {
+        self.front.as_deref()
+    }
+
+    /// Reset the buffers, e.g. to reset their age
+    pub fn reset_buffers(&mut self) {
+        self.swapchain.reset_buffers();
+        self.pending = None;
+    }
+}
+
+impl HeadlessFrameClock {
+    fn present<F>(&self, callback: &mut F)
+    where
+        F: FnMut(HeadlessFrame, &mut ()),
+    {
+        let (dmabuf, sequence) = {
+            let mut queue = self.queue.lock().unwrap();
+            let Some(dmabuf) = queue.queued.take() else {
+                return;
+            };
+            queue.sequence += 1;
+            (dmabuf, queue.sequence)
+        };
+
+        if let Some(output) = self.output.upgrade() {
+            trace!(output = output.name(), sequence, "Presenting frame");
+            callback(
+                HeadlessFrame {
+                    output,
+                    dmabuf,
+                    sequence,
+                    time: self.clock.now(),
+                },
+                &mut (),
+            );
+        }
+    }
+}
+
+impl EventSource for HeadlessFrameClock {
+    type Event = HeadlessFrame;
+    type Metadata = ();
+    type Ret = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut ()),
+    {
+        if let Some(mut timer) = self.timer.take() {
+            let res = timer.process_events(readiness, token, |deadline, _| {
+                self.present(&mut callback);
+
+                let refresh = self.queue.lock().unwrap().refresh;
+                let now = Instant::now();
+                let mut next = deadline + refresh;
+                // skip missed ticks
+                while next <= now {
+                    next += refresh;
+                }
+                TimeoutAction::ToInstant(next)
+            });
+            self.timer = Some(timer);
+            return res.map_err(Into::into);
+        }
+
+        if let Some(mut ping) = self.ping.take() {
+            let res = ping.process_events(readiness, token, |_, _| self.present(&mut callback));
+            self.ping = Some(ping);
+            return res.map_err(Into::into);
+        }
+
+        Ok(PostAction::Continue)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        if let Some(timer) = self.timer.as_mut() {
+            timer.register(poll, token_factory)?;
+        }
+        if let Some(ping) = self.ping.as_mut() {
+            ping.register(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        if let Some(timer) = self.timer.as_mut() {
+            timer.reregister(poll, token_factory)?;
+        }
+        if let Some(ping) = self.ping.as_mut() {
+            ping.reregister(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        if let Some(timer) = self.timer.as_mut() {
+            timer.unregister(poll)?;
+        }
+        if let Some(ping) = self.ping.as_mut() {
+            ping.unregister(poll)?;
+        }
+        Ok(())
+    }
+}
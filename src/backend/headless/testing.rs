@@ -0,0 +1,255 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::{debug_span, info, instrument, trace};
+
+use super::refresh_interval;
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::software::{SoftwareBuffer, SoftwareError},
+    },
+    output::{self, Mode, Output, PhysicalProperties, Subpixel},
+    utils::{Monotonic, Time, Transform},
+};
+
+/// Deterministic clock for tests
+///
+/// The time of the clock only moves forward, when it is [advanced](TestClock::advance)
+/// explicitly. Clones of a clock share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct TestClock(Arc<Mutex<Duration>>);
+
+impl TestClock {
+    /// Create a new clock starting at zero
+    pub fn new() -> TestClock {
+        TestClock::default()
+    }
+
+    /// Create a new clock starting at the given time
+    pub fn starting_at(time: Duration) -> TestClock {
+        TestClock(Arc::new(Mutex::new(time)))
+    }
+
+    /// Returns the current time
+    pub fn now(&self) -> Time<Monotonic> {
+        (*self.0.lock().unwrap()).into()
+    }
+
+    /// Returns the current time as a [`Duration`] since the start of the clock domain
+    pub fn elapsed(&self) -> Duration {
+        *self.0.lock().unwrap()
+    }
+
+    /// Move the time forward
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+/// A frame presented by a [`TestOutput`]
+#[derive(Debug)]
+pub struct TestFrame {
+    /// The output the frame was presented on
+    pub output: Output,
+    /// Sequence number of the frame, starting at one for the first presented frame
+    pub sequence: u64,
+    /// Time the frame was presented at, according to the [`TestClock`] of the output
+    pub time: Time<Monotonic>,
+    /// Refresh interval of the output at the time of presentation
+    pub refresh: Duration,
+}
+
+/// A virtual output for tests, rendered by the [`SoftwareRenderer`](crate::backend::renderer::software::SoftwareRenderer)
+///
+/// The output renders into a [`SoftwareBuffer`] of the size of its current mode, whose contents
+/// can be inspected after rendering. Frames are presented explicitly by calling
+/// [`present`](TestOutput::present) and are timestamped with a [`TestClock`], so the timing
+/// of the output is fully under control of the test.
+///
+/// ```
+/// use std::time::Duration;
+/// use smithay::backend::{
+///     headless::{TestClock, TestOutput},
+///     renderer::{software::SoftwareRenderer, Bind},
+/// };
+/// use smithay::output::Mode;
+///
+/// let clock = TestClock::new();
+/// let mut output = TestOutput::new(
+///     "TEST-1",
+///     Mode { size: (320, 240).into(), refresh: 60_000 },
+///     &clock,
+/// )
+/// .unwrap();
+///
+/// let mut renderer = SoftwareRenderer::new();
+/// renderer.bind(output.buffer().clone()).unwrap();
+/// // render the output..
+///
+/// clock.advance(output.refresh_interval());
+/// let frame = output.present();
+/// assert_eq!(frame.sequence, 1);
+/// assert_eq!(Duration::from(frame.time), Duration::from_nanos(16_666_666));
+/// ```
+#[derive(Debug)]
+pub struct TestOutput {
+    output: Output,
+    buffer: SoftwareBuffer,
+    clock: TestClock,
+    sequence: u64,
+    span: tracing::Span,
+}
+
+impl TestOutput {
+    /// Create a new virtual output with the given mode
+    ///
+    /// The output uses an `Argb8888` buffer and presents its frames at the time of the given clock.
+    pub fn new(name: impl Into<String>, mode: Mode, clock: &TestClock) -> Result<TestOutput, SoftwareError> {
+        let name = name.into();
+        let span = debug_span!("backend_headless_test", output = name);
+        let _guard = span.enter();
+        info!(?mode, "Creating test output");
+
+        let output = Output::new(
+            name,
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Test".into(),
+            },
+        );
+        output.set_preferred(mode);
+        output.change_current_state(Some(mode), None, None, None);
+        let buffer = SoftwareBuffer::new(Fourcc::Argb8888, (mode.size.w, mode.size.h).into())?;
+        drop(_guard);
+
+        Ok(TestOutput {
+            output,
+            buffer,
+            clock: clock.clone(),
+            sequence: 0,
+            span,
+        })
+    }
+
+    /// Returns the [`Output`] of this virtual output
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Returns the buffer the output renders into
+    ///
+    /// The buffer needs to be bound to the renderer before rendering the output.
+    /// It is replaced, when the mode of the output changes.
+    pub fn buffer(&self) -> &SoftwareBuffer {
+        &self.buffer
+    }
+
+    /// Change the mode of the output
+    ///
+    /// A new buffer of the size of the mode is allocated.
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), SoftwareError> {
+        self.buffer = SoftwareBuffer::new(Fourcc::Argb8888, (mode.size.w, mode.size.h).into())?;
+        self.output.change_current_state(Some(mode), None, None, None);
+        Ok(())
+    }
+
+    /// Change the scale of the output
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_scale(&mut self, scale: output::Scale) {
+        self.output.change_current_state(None, None, Some(scale), None);
+    }
+
+    /// Change the transform of the output
+    ///
+    /// The size of the buffer is not affected by the transform.
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.output
+            .change_current_state(None, Some(transform), None, None);
+    }
+
+    /// Returns the refresh interval of the current mode
+    ///
+    /// Outputs with an unknown refresh rate refresh at 60Hz.
+    pub fn refresh_interval(&self) -> Duration {
+        self.output
+            .current_mode()
+            .map(refresh_interval)
+            .unwrap_or_else(|| Duration::from_nanos(1_000_000_000 / 60))
+    }
+
+    /// Number of frames presented so far
+    pub fn presented_frames(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Present the contents of the buffer at the current time of the clock
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn present(&mut self) -> TestFrame {
+        self.sequence += 1;
+        let time = self.clock.now();
+        trace!(sequence = self.sequence, ?time, "Presenting frame");
+        TestFrame {
+            output: self.output.clone(),
+            sequence: self.sequence,
+            time,
+            refresh: self.refresh_interval(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Size;
+
+    #[test]
+    fn present_with_test_clock() {
+        let clock = TestClock::starting_at(Duration::from_secs(1));
+        let mode = Mode {
+            size: (64, 32).into(),
+            refresh: 50_000,
+        };
+        let mut output = TestOutput::new("TEST-1", mode, &clock).unwrap();
+        assert_eq!(output.refresh_interval(), Duration::from_millis(20));
+
+        clock.advance(output.refresh_interval());
+        let frame = output.present();
+        assert_eq!(frame.sequence, 1);
+        assert_eq!(Duration::from(frame.time), Duration::from_millis(1020));
+
+        let frame = output.present();
+        assert_eq!(frame.sequence, 2);
+        assert_eq!(Duration::from(frame.time), Duration::from_millis(1020));
+        assert_eq!(output.presented_frames(), 2);
+    }
+
+    #[test]
+    fn change_output_state() {
+        let clock = TestClock::new();
+        let mode = Mode {
+            size: (64, 32).into(),
+            refresh: 60_000,
+        };
+        let mut output = TestOutput::new("TEST-1", mode, &clock).unwrap();
+
+        let mode = Mode {
+            size: (128, 64).into(),
+            refresh: 120_000,
+        };
+        output.set_mode(mode).unwrap();
+        output.set_scale(output::Scale::Fractional(1.5));
+        output.set_transform(Transform::_90);
+
+        assert_eq!(output.output().current_mode(), Some(mode));
+        assert_eq!(output.output().current_scale().fractional_scale(), 1.5);
+        assert_eq!(output.output().current_transform(), Transform::_90);
+        assert_eq!(output.buffer().size(), Size::from((128, 64)));
+    }
+}
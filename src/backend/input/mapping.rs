@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::{
+    output::{Output, WeakOutput},
+    utils::{Logical, Point, Raw, Rectangle, Transform},
+};
+
+use super::{AbsolutePositionEvent, Device, Event, InputBackend, TabletToolEvent};
+
+// Extent used to query normalized positions, which scale linearly with the extent
+const NORMALIZE_EXTENT: i32 = 1 << 24;
+
+/// Calibration matrix applied to normalized device coordinates
+///
+/// The matrix uses the same layout as libinput's `LIBINPUT_CALIBRATION_MATRIX`: the first
+/// two rows of a 3x3 affine matrix in row-major order `[a, b, c, d, e, f]`, mapping a position
+/// `(x, y)` in the range `[0, 1]` to `(a * x + b * y + c, d * x + e * y + f)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationMatrix(pub [f64; 6]);
+
+impl CalibrationMatrix {
+    /// The identity matrix, leaving positions unaltered
+    pub const IDENTITY: CalibrationMatrix = CalibrationMatrix([1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+
+    /// Apply this matrix to a normalized position
+    pub fn apply(&self, point: Point<f64, Raw>) -> Point<f64, Raw> {
+        let [a, b, c, d, e, f] = self.0;
+        (a * point.x + b * point.y + c, d * point.x + e * point.y + f).into()
+    }
+}
+
+impl Default for CalibrationMatrix {
+    fn default() -> Self {
+        CalibrationMatrix::IDENTITY
+    }
+}
+
+impl From<[f64; 6]> for CalibrationMatrix {
+    fn from(matrix: [f64; 6]) -> Self {
+        CalibrationMatrix(matrix)
+    }
+}
+
+/// Area an absolute input device is mapped to
+#[derive(Debug, Clone, PartialEq)]
+pub enum MappingTarget {
+    /// The geometry of an output, following its mode, scale, transform and location
+    Output(WeakOutput),
+    /// A fixed region in the global compositor space
+    Region(Rectangle<f64, Logical>),
+}
+
+/// Mapping of an absolute input device to the compositor space
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceMapping {
+    target: MappingTarget,
+    calibration: CalibrationMatrix,
+    transform: Option<Transform>,
+}
+
+impl DeviceMapping {
+    /// Map a device to the geometry of an output
+    ///
+    /// By default the device is rotated along with the output's transform, as is the case for
+    /// touchscreens built into a display.
+    pub fn output(output: &Output) -> Self {
+        DeviceMapping::new(MappingTarget::Output(output.downgrade()))
+    }
+
+    /// Map a device to a fixed region of the compositor space
+    pub fn region(region: Rectangle<f64, Logical>) -> Self {
+        DeviceMapping::new(MappingTarget::Region(region))
+    }
+
+    fn new(target: MappingTarget) -> Self {
+        DeviceMapping {
+            target,
+            calibration: CalibrationMatrix::IDENTITY,
+            transform: None,
+        }
+    }
+
+    /// Set the calibration matrix, applied before any rotation
+    pub fn calibration(mut self, calibration: impl Into<CalibrationMatrix>) -> Self {
+        self.calibration = calibration.into();
+        self
+    }
+
+    /// Set the rotation of the device relative to the compositor space
+    ///
+    /// This overrides the transform of the output the device is mapped to.
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    /// The area the device is mapped to
+    pub fn target(&self) -> &MappingTarget {
+        &self.target
+    }
+
+    /// The calibration matrix of the device
+    pub fn calibration_matrix(&self) -> CalibrationMatrix {
+        self.calibration
+    }
+
+    /// The rotation of the device, if it does not follow the output's transform
+    pub fn device_transform(&self) -> Option<Transform> {
+        self.transform
+    }
+
+    /// Map a normalized device position into the compositor space
+    ///
+    /// Returns `None` if the target output was destroyed or has no mode set.
+    /// Calibrated positions are not clamped to the target area.
+    pub fn map(&self, normalized: Point<f64, Raw>) -> Option<Point<f64, Logical>> {
+        let (area, transform) = match &self.target {
+            MappingTarget::Output(output) => {
+                let output = output.upgrade()?;
+                let mode = output.current_mode()?;
+                let transform = output.current_transform();
+                let size = transform
+                    .transform_size(mode.size)
+                    .to_f64()
+                    .to_logical(output.current_scale().fractional_scale());
+                let area = Rectangle::from_loc_and_size(output.current_location().to_f64(), size);
+                (area, self.transform.unwrap_or(transform))
+            }
+            MappingTarget::Region(region) => (*region, self.transform.unwrap_or(Transform::Normal)),
+        };
+        Some(map_normalized(normalized, self.calibration, transform, area))
+    }
+}
+
+fn map_normalized(
+    normalized: Point<f64, Raw>,
+    calibration: CalibrationMatrix,
+    transform: Transform,
+    area: Rectangle<f64, Logical>,
+) -> Point<f64, Logical> {
+    let point = calibration.apply(normalized);
+    // device positions are given in the device's native orientation, like a buffer of the output
+    let point = transform.invert().transform_point_in(point, &(1.0, 1.0).into());
+    (
+        area.loc.x + point.x * area.size.w,
+        area.loc.y + point.y * area.size.h,
+    )
+        .into()
+}
+
+/// Policy mapping absolute input devices like touchscreens and tablets to the compositor space
+///
+/// Devices are matched by their [name](Device::name). Devices without a mapping of their own use
+/// the default mapping, if any.
+///
+/// ```no_run
+/// # use smithay::backend::input::{AbsoluteInputMapper, DeviceMapping};
+/// # use smithay::output::Output;
+/// # let output: Output = unimplemented!();
+/// let mut mapper = AbsoluteInputMapper::new();
+/// mapper.set_device_mapping(
+///     "ELAN Touchscreen",
+///     DeviceMapping::output(&output).calibration([-1.0, 0.0, 1.0, 0.0, 1.0, 0.0]),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AbsoluteInputMapper {
+    default: Option<DeviceMapping>,
+    devices: HashMap<String, DeviceMapping>,
+}
+
+impl AbsoluteInputMapper {
+    /// Create a mapper without any mappings
+    pub fn new() -> Self {
+        AbsoluteInputMapper::default()
+    }
+
+    /// Set the mapping used for devices without a mapping of their own
+    pub fn set_default_mapping(&mut self, mapping: Option<DeviceMapping>) {
+        self.default = mapping;
+    }
+
+    /// The mapping used for devices without a mapping of their own
+    pub fn default_mapping(&self) -> Option<&DeviceMapping> {
+        self.default.as_ref()
+    }
+
+    /// Set the mapping of the devices with the given name
+    pub fn set_device_mapping(&mut self, name: impl Into<String>, mapping: DeviceMapping) {
+        self.devices.insert(name.into(), mapping);
+    }
+
+    /// Remove the mapping of the devices with the given name
+    pub fn remove_device_mapping(&mut self, name: &str) -> Option<DeviceMapping> {
+        self.devices.remove(name)
+    }
+
+    /// The mapping applying to the given device
+    pub fn mapping_for<D: Device>(&self, device: &D) -> Option<&DeviceMapping> {
+        self.devices.get(&device.name()).or(self.default.as_ref())
+    }
+
+    /// Map the position of an absolute motion or touch event
+    ///
+    /// Returns `None` if no mapping applies to the device of the event, or its target is not
+    /// available. The compositor is expected to fall back to its own policy in that case.
+    pub fn map_absolute<B, E>(&self, event: &E) -> Option<Point<f64, Logical>>
+    where
+        B: InputBackend,
+        E: AbsolutePositionEvent<B>,
+    {
+        let normalized = (
+            event.x_transformed(NORMALIZE_EXTENT),
+            event.y_transformed(NORMALIZE_EXTENT),
+        );
+        self.map_normalized(&event.device(), normalized)
+    }
+
+    /// Map the position of a tablet tool event
+    ///
+    /// See [`AbsoluteInputMapper::map_absolute`].
+    pub fn map_tablet_tool<B, E>(&self, event: &E) -> Option<Point<f64, Logical>>
+    where
+        B: InputBackend,
+        E: TabletToolEvent<B> + Event<B>,
+    {
+        let normalized = (
+            event.x_transformed(NORMALIZE_EXTENT),
+            event.y_transformed(NORMALIZE_EXTENT),
+        );
+        self.map_normalized(&event.device(), normalized)
+    }
+
+    fn map_normalized<D: Device>(&self, device: &D, (x, y): (f64, f64)) -> Option<Point<f64, Logical>> {
+        let extent = NORMALIZE_EXTENT as f64;
+        self.mapping_for(device)?.map((x / extent, y / extent).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area() -> Rectangle<f64, Logical> {
+        Rectangle::from_loc_and_size((100.0, 50.0), (200.0, 100.0))
+    }
+
+    #[test]
+    fn maps_into_area() {
+        let point = map_normalized(
+            (0.5, 0.25).into(),
+            CalibrationMatrix::IDENTITY,
+            Transform::Normal,
+            area(),
+        );
+        assert_eq!(point, (200.0, 75.0).into());
+    }
+
+    #[test]
+    fn applies_calibration() {
+        // mirror the x axis
+        let calibration = CalibrationMatrix([-1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+        let point = map_normalized((0.25, 0.5).into(), calibration, Transform::Normal, area());
+        assert_eq!(point, (250.0, 100.0).into());
+    }
+
+    #[test]
+    fn follows_rotation() {
+        let calibration = CalibrationMatrix::IDENTITY;
+        let corner = (0.0, 0.0).into();
+        assert_eq!(
+            map_normalized(corner, calibration, Transform::_90, area()),
+            (100.0, 150.0).into()
+        );
+        assert_eq!(
+            map_normalized(corner, calibration, Transform::_180, area()),
+            (300.0, 150.0).into()
+        );
+        assert_eq!(
+            map_normalized(corner, calibration, Transform::_270, area()),
+            (300.0, 50.0).into()
+        );
+    }
+}
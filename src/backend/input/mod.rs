@@ -2,8 +2,12 @@
 
 use std::path::PathBuf;
 
+mod mapping;
+mod switch;
 mod tablet;
 
+pub use mapping::{AbsoluteInputMapper, CalibrationMatrix, DeviceMapping, MappingTarget};
+pub use switch::{Switch, SwitchState, SwitchToggleEvent, SwitchTracker};
 pub use tablet::{
     ProximityState, TabletToolAxisEvent, TabletToolButtonEvent, TabletToolCapabilities, TabletToolDescriptor,
     TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
@@ -32,7 +36,7 @@ pub trait Device: PartialEq + Eq + std::hash::Hash {
 }
 
 /// Set of input types a device may provide
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(missing_docs)] // self explainatory
 pub enum DeviceCapability {
     Keyboard,
@@ -333,6 +337,16 @@ pub enum AxisSource {
     WheelTilt,
 }
 
+/// Direction of physical motion that caused a scroll event, relative to the scroll direction
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum AxisRelativeDirection {
+    /// Physical motion matches the scroll direction
+    #[default]
+    Identical,
+    /// Physical motion is the inverse of the scroll direction, e.g. with natural scrolling
+    Inverted,
+}
+
 /// Trait for pointer events generated by scrolling on an axis.
 pub trait PointerAxisEvent<B: InputBackend>: Event<B> {
     /// Amount of scrolling in pixels on the given [`Axis`].
@@ -345,6 +359,20 @@ pub trait PointerAxisEvent<B: InputBackend>: Event<B> {
     /// Guaranteed to be `Some` when source returns either [`AxisSource::Wheel`] or [`AxisSource::WheelTilt`].
     fn amount_discrete(&self, axis: Axis) -> Option<f64>;
 
+    /// Amount of scrolling in high-resolution wheel steps on the given [`Axis`].
+    ///
+    /// A value of 120 represents one logical scroll step (a wheel detent).
+    /// Backends without high-resolution scroll information fall back to
+    /// [`PointerAxisEvent::amount_discrete`] multiplied by 120.
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        self.amount_discrete(axis).map(|discrete| discrete * 120.0)
+    }
+
+    /// Relation of the physical motion to the scroll direction on the given [`Axis`].
+    fn relative_direction(&self, _axis: Axis) -> AxisRelativeDirection {
+        AxisRelativeDirection::Identical
+    }
+
     /// Source of the scroll event.
     fn source(&self) -> AxisSource;
 }
@@ -571,6 +599,8 @@ pub trait InputBackend: Sized {
     type TabletToolTipEvent: TabletToolTipEvent<Self>;
     /// Type representing button events on tablet tool devices
     type TabletToolButtonEvent: TabletToolButtonEvent<Self>;
+    /// Type representing toggle events of switches
+    type SwitchToggleEvent: SwitchToggleEvent<Self>;
 
     /// Special events that are custom to this backend
     type SpecialEvent;
@@ -707,6 +737,12 @@ pub enum InputEvent<B: InputBackend> {
         event: B::TabletToolButtonEvent,
     },
 
+    /// A switch was toggled
+    SwitchToggle {
+        /// The switch toggle event
+        event: B::SwitchToggleEvent,
+    },
+
     /// Special event specific of this backend
     Special(B::SpecialEvent),
 }
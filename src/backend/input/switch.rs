@@ -0,0 +1,203 @@
+use std::{collections::HashMap, fmt};
+
+use super::{Device, Event, InputBackend, InputEvent, UnusedEvent};
+
+/// Switches a device may provide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Switch {
+    /// The lid of a laptop
+    ///
+    /// The switch is [`On`](SwitchState::On) while the lid is closed.
+    Lid,
+    /// The tablet mode of a convertible device
+    ///
+    /// The switch is [`On`](SwitchState::On) while the device is in tablet mode, in which the
+    /// keyboard and touchpad are usually inaccessible.
+    TabletMode,
+}
+
+/// State of a switch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwitchState {
+    /// The switch is off
+    Off,
+    /// The switch is on
+    On,
+}
+
+/// Trait for switch toggle events
+pub trait SwitchToggleEvent<B: InputBackend>: Event<B> {
+    /// The switch that was toggled
+    ///
+    /// Returns `None` if the switch is unknown to smithay.
+    fn switch(&self) -> Option<Switch>;
+
+    /// The new state of the switch
+    fn state(&self) -> SwitchState;
+}
+
+impl<B: InputBackend> SwitchToggleEvent<B> for UnusedEvent {
+    fn switch(&self) -> Option<Switch> {
+        match *self {}
+    }
+
+    fn state(&self) -> SwitchState {
+        match *self {}
+    }
+}
+
+/// Tracker of the current state of all switches
+///
+/// A switch is considered [`On`](SwitchState::On) if it is on for any device providing it.
+/// Switch states of removed devices are forgotten.
+///
+/// ```no_run
+/// # use smithay::backend::input::{InputBackend, InputEvent, Switch, SwitchState, SwitchTracker};
+/// # fn process<B: InputBackend>(event: InputEvent<B>) {
+/// let mut switches = SwitchTracker::new();
+/// switches.on_change(|switch, state| {
+///     if switch == Switch::Lid && state == SwitchState::On {
+///         // disable the internal panel
+///     }
+/// });
+/// switches.process_event(&event);
+/// # }
+/// ```
+#[derive(Default)]
+pub struct SwitchTracker {
+    states: HashMap<(String, Switch), SwitchState>,
+    callbacks: Vec<Box<dyn FnMut(Switch, SwitchState)>>,
+}
+
+impl fmt::Debug for SwitchTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwitchTracker")
+            .field("states", &self.states)
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+impl SwitchTracker {
+    /// Create a tracker with all switches off
+    pub fn new() -> Self {
+        SwitchTracker::default()
+    }
+
+    /// Register a callback invoked whenever the state of a switch changes
+    pub fn on_change<F>(&mut self, callback: F)
+    where
+        F: FnMut(Switch, SwitchState) + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// The current state of a switch
+    pub fn state(&self, switch: Switch) -> SwitchState {
+        let on = self
+            .states
+            .iter()
+            .any(|((_, s), state)| *s == switch && *state == SwitchState::On);
+        if on {
+            SwitchState::On
+        } else {
+            SwitchState::Off
+        }
+    }
+
+    /// Whether the lid is closed
+    pub fn is_lid_closed(&self) -> bool {
+        self.state(Switch::Lid) == SwitchState::On
+    }
+
+    /// Whether the device is in tablet mode
+    pub fn is_tablet_mode(&self) -> bool {
+        self.state(Switch::TabletMode) == SwitchState::On
+    }
+
+    /// Update the switch states from an input event
+    ///
+    /// Handles switch toggles and device removals, other events are ignored.
+    /// Returns the switches whose state changed.
+    pub fn process_event<B: InputBackend>(&mut self, event: &InputEvent<B>) -> Vec<(Switch, SwitchState)> {
+        match event {
+            InputEvent::SwitchToggle { event } => match event.switch() {
+                Some(switch) => self.update(event.device().id(), switch, Some(event.state())),
+                None => Vec::new(),
+            },
+            InputEvent::DeviceRemoved { device } => {
+                let id = device.id();
+                [Switch::Lid, Switch::TabletMode]
+                    .into_iter()
+                    .flat_map(|switch| self.update(id.clone(), switch, None))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(
+        &mut self,
+        device: String,
+        switch: Switch,
+        state: Option<SwitchState>,
+    ) -> Vec<(Switch, SwitchState)> {
+        let previous = self.state(switch);
+        match state {
+            Some(state) => self.states.insert((device, switch), state),
+            None => self.states.remove(&(device, switch)),
+        };
+
+        let current = self.state(switch);
+        if current == previous {
+            return Vec::new();
+        }
+        for callback in &mut self.callbacks {
+            callback(switch, current);
+        }
+        vec![(switch, current)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn combines_devices() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut tracker = SwitchTracker::new();
+        tracker.on_change({
+            let changes = changes.clone();
+            move |switch, state| changes.borrow_mut().push((switch, state))
+        });
+
+        assert_eq!(
+            tracker.update("a".into(), Switch::Lid, Some(SwitchState::On)),
+            vec![(Switch::Lid, SwitchState::On)]
+        );
+        assert!(tracker.is_lid_closed());
+        assert!(!tracker.is_tablet_mode());
+
+        assert!(tracker
+            .update("b".into(), Switch::Lid, Some(SwitchState::On))
+            .is_empty());
+        assert!(tracker
+            .update("a".into(), Switch::Lid, Some(SwitchState::Off))
+            .is_empty());
+        assert!(tracker.is_lid_closed());
+
+        assert_eq!(
+            tracker.update("b".into(), Switch::Lid, None),
+            vec![(Switch::Lid, SwitchState::Off)]
+        );
+        assert!(!tracker.is_lid_closed());
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![(Switch::Lid, SwitchState::On), (Switch::Lid, SwitchState::Off)]
+        );
+    }
+}
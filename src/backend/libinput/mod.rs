@@ -159,6 +159,15 @@ impl backend::PointerAxisEvent<LibinputInputBackend> for event::pointer::Pointer
         }
     }
 
+    fn relative_direction(&self, _axis: Axis) -> backend::AxisRelativeDirection {
+        // libinput inverts the scroll values of devices with natural scrolling enabled
+        if event::EventTrait::device(self).config_scroll_natural_scroll_enabled() {
+            backend::AxisRelativeDirection::Inverted
+        } else {
+            backend::AxisRelativeDirection::Identical
+        }
+    }
+
     fn source(&self) -> backend::AxisSource {
         self.axis_source().into()
     }
@@ -507,6 +516,33 @@ impl backend::Event<LibinputInputBackend> for event::touch::TouchFrameEvent {
 
 impl backend::TouchFrameEvent<LibinputInputBackend> for event::touch::TouchFrameEvent {}
 
+impl backend::Event<LibinputInputBackend> for event::switch::SwitchToggleEvent {
+    fn time(&self) -> u64 {
+        event::switch::SwitchEventTrait::time_usec(self)
+    }
+
+    fn device(&self) -> libinput::Device {
+        event::EventTrait::device(self)
+    }
+}
+
+impl backend::SwitchToggleEvent<LibinputInputBackend> for event::switch::SwitchToggleEvent {
+    fn switch(&self) -> Option<backend::Switch> {
+        match event::switch::SwitchToggleEvent::switch(self) {
+            Some(event::switch::Switch::Lid) => Some(backend::Switch::Lid),
+            Some(event::switch::Switch::TabletMode) => Some(backend::Switch::TabletMode),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> backend::SwitchState {
+        match event::switch::SwitchToggleEvent::switch_state(self) {
+            event::switch::SwitchState::Off => backend::SwitchState::Off,
+            event::switch::SwitchState::On => backend::SwitchState::On,
+        }
+    }
+}
+
 impl InputBackend for LibinputInputBackend {
     type Device = libinput::Device;
     type KeyboardKeyEvent = event::keyboard::KeyboardKeyEvent;
@@ -539,6 +575,7 @@ impl InputBackend for LibinputInputBackend {
     type TabletToolProximityEvent = event::tablet_tool::TabletToolProximityEvent;
     type TabletToolTipEvent = event::tablet_tool::TabletToolTipEvent;
     type TabletToolButtonEvent = event::tablet_tool::TabletToolButtonEvent;
+    type SwitchToggleEvent = event::switch::SwitchToggleEvent;
 
     type SpecialEvent = backend::UnusedEvent;
 }
@@ -619,6 +656,22 @@ impl<S: Session> libinput::LibinputInterface for LibinputSessionInterface<S> {
     }
 }
 
+#[cfg(feature = "backend_session")]
+impl crate::backend::session::SessionDevice for libinput::Libinput {
+    fn kind(&self) -> crate::backend::session::SessionDeviceKind {
+        crate::backend::session::SessionDeviceKind::Input
+    }
+
+    fn pause(&mut self) {
+        self.suspend();
+    }
+
+    fn activate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.resume()
+            .map_err(|()| "Failed to resume libinput context".into())
+    }
+}
+
 impl AsFd for LibinputInputBackend {
     fn as_fd(&self) -> BorrowedFd<'_> {
         self.context.as_fd()
@@ -758,6 +811,14 @@ impl EventSource for LibinputInputBackend {
                             trace!("Unknown libinput tablet event");
                         }
                     },
+                    libinput::Event::Switch(switch_event) => match switch_event {
+                        event::SwitchEvent::Toggle(event) => {
+                            callback(InputEvent::SwitchToggle { event }, &mut ());
+                        }
+                        _ => {
+                            trace!("Unknown libinput switch event");
+                        }
+                    },
                     _ => {} //FIXME: What to do with the rest.
                 }
             }
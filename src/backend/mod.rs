@@ -26,7 +26,8 @@
 //! that input data, with a generic API provided by the traits and types defined in the
 //! [`input`] module. An input provider following this API based on `libinput` is given in the
 //! [`libinput`] module, gated by the `backend_libinput` cargo feature. The winit backend
-//! (see below) also provides an input provider.
+//! (see below) also provides an input provider. The [`replay`] module allows to record the events
+//! of any input provider and to play them back later.
 //!
 //! ### Graphics
 //!
@@ -78,10 +79,31 @@
 //! development and debugging. That backend is both a renderer and an input provider, and is
 //! accessible in the [`winit`] module, gated by the `backend_winit` cargo feature.
 //!
+//! ## Wayland backend
+//!
+//! The [`wayland`] backend runs your compositor as a client of another Wayland compositor, without
+//! going through winit. Every output is a toplevel window of the parent compositor, frames are
+//! presented as dmabufs and the fractional scale of the parent compositor is passed through. It is
+//! also an input provider, gated by the `backend_wayland` cargo feature.
+//!
+//! ## Headless backend
+//!
+//! The [`headless`] backend provides virtual outputs without any display hardware, rendering
+//! into offscreen buffers. The presented frames can be consumed e.g. to stream the outputs or
+//! to take screenshots in automated tests.
+//!
+//! ## VNC backend
+//!
+//! The [`vnc`] backend serves a framebuffer to remote VNC clients and converts their keyboard and
+//! pointer input into [`InputEvent`](input::InputEvent)s, letting a compositor rendering offscreen serve
+//! remote desktop sessions without a local GPU or seat. It is gated by the `backend_vnc` cargo feature.
+//!
 
 pub mod allocator;
+pub mod headless;
 pub mod input;
 pub mod renderer;
+pub mod replay;
 
 #[cfg(feature = "backend_drm")]
 pub mod drm;
@@ -97,6 +119,12 @@ pub mod udev;
 #[cfg(feature = "backend_vulkan")]
 pub mod vulkan;
 
+#[cfg(feature = "backend_wayland")]
+pub mod wayland;
+
+#[cfg(feature = "backend_vnc")]
+pub mod vnc;
+
 #[cfg(feature = "backend_winit")]
 pub mod winit;
 
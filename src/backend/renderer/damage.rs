@@ -197,7 +197,7 @@ use tracing::{info_span, instrument, trace};
 use crate::{
     backend::renderer::{element::RenderElementPresentationState, Frame},
     output::Output,
-    utils::{Physical, Rectangle, Scale, Size, Transform},
+    utils::{Physical, Rectangle, Region, Scale, Size, Transform},
 };
 
 use super::{
@@ -400,18 +400,11 @@ impl OutputDamageTracker {
         let render_res = (|| {
             let mut frame = renderer.render(output_size, output_transform)?;
 
-            let clear_damage = opaque_regions.iter().flat_map(|(_, regions)| regions).fold(
-                damage.clone(),
-                |damage, region| {
-                    damage
-                        .into_iter()
-                        .flat_map(|geo| geo.subtract_rect(*region))
-                        .collect::<Vec<_>>()
-                },
-            );
+            let mut clear_damage = Region::from_rects(damage.iter().copied());
+            clear_damage.subtract_rects(opaque_regions.iter().flat_map(|(_, regions)| regions).copied());
 
             trace!("clearing damage {:?}", clear_damage);
-            frame.clear(clear_color, &clear_damage)?;
+            frame.clear(clear_color, clear_damage.rects())?;
 
             for (mut z_index, element) in render_elements.iter().rev().enumerate() {
                 // This is necessary because we reversed the render elements to draw
@@ -422,23 +415,17 @@ impl OutputDamageTracker {
                 let element_id = element.id();
                 let element_geometry = element.geometry(output_scale);
 
-                let element_damage = opaque_regions
-                    .iter()
-                    .filter(|(index, _)| *index < z_index)
-                    .flat_map(|(_, regions)| regions)
-                    .fold(
-                        damage
-                            .clone()
-                            .into_iter()
-                            .filter_map(|d| d.intersection(element_geometry))
-                            .collect::<Vec<_>>(),
-                        |damage, region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|geo| geo.subtract_rect(*region))
-                                .collect::<Vec<_>>()
-                        },
-                    )
+                let mut element_damage =
+                    Region::from_rects(damage.iter().filter_map(|d| d.intersection(element_geometry)));
+                element_damage.subtract_rects(
+                    opaque_regions
+                        .iter()
+                        .filter(|(index, _)| *index < z_index)
+                        .flat_map(|(_, regions)| regions)
+                        .copied(),
+                );
+                let element_damage = element_damage
+                    .into_rects()
                     .into_iter()
                     .map(|mut d| {
                         d.loc -= element_geometry.loc;
@@ -551,17 +538,14 @@ impl OutputDamageTracker {
             };
 
             // Then test if the element is completely hidden behind opaque regions
-            let element_visible_area = opaque_regions
-                .iter()
-                .flat_map(|(_, opaque_regions)| opaque_regions)
-                .fold([element_output_geometry].to_vec(), |geometry, opaque_region| {
-                    geometry
-                        .into_iter()
-                        .flat_map(|g| g.subtract_rect(*opaque_region))
-                        .collect::<Vec<_>>()
-                })
-                .into_iter()
-                .fold(0usize, |acc, item| acc + (item.size.w * item.size.h) as usize);
+            let mut element_visible_region = Region::from(element_output_geometry);
+            element_visible_region.subtract_rects(
+                opaque_regions
+                    .iter()
+                    .flat_map(|(_, opaque_regions)| opaque_regions)
+                    .copied(),
+            );
+            let element_visible_area = element_visible_region.area() as usize;
 
             // No need to draw a completely hidden element
             if element_visible_area == 0 {
@@ -623,23 +607,20 @@ impl OutputDamageTracker {
             .iter()
             .filter(|(id, _)| !render_elements.iter().any(|e| e.id() == *id))
             .flat_map(|(_, state)| {
-                opaque_regions
-                    .iter()
-                    .filter(|(z_index, _)| state.last_instances.iter().any(|i| *z_index < i.last_z_index))
-                    .flat_map(|(_, opaque_regions)| opaque_regions)
-                    .fold(
-                        state
-                            .last_instances
-                            .iter()
-                            .filter_map(|i| i.last_geometry.intersection(output_geo))
-                            .collect::<Vec<_>>(),
-                        |damage, opaque_region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                                .collect::<Vec<_>>()
-                        },
-                    )
+                let mut damage = Region::from_rects(
+                    state
+                        .last_instances
+                        .iter()
+                        .filter_map(|i| i.last_geometry.intersection(output_geo)),
+                );
+                damage.subtract_rects(
+                    opaque_regions
+                        .iter()
+                        .filter(|(z_index, _)| state.last_instances.iter().any(|i| *z_index < i.last_z_index))
+                        .flat_map(|(_, opaque_regions)| opaque_regions)
+                        .copied(),
+                );
+                damage.into_rects()
             })
             .collect::<Vec<_>>();
         damage.extend(elements_gone);
@@ -667,18 +648,15 @@ impl OutputDamageTracker {
                             .filter_map(|i| i.last_geometry.intersection(output_geo)),
                     );
                 }
-                damage.extend(
+                let mut element_damage = Region::from_rects(element_damage);
+                element_damage.subtract_rects(
                     opaque_regions
                         .iter()
                         .filter(|(index, _)| *index < z_index)
                         .flat_map(|(_, opaque_regions)| opaque_regions)
-                        .fold(element_damage, |damage, opaque_region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                                .collect::<Vec<_>>()
-                        }),
+                        .copied(),
                 );
+                damage.extend(element_damage.into_rects());
             }
         }
 
@@ -12,6 +12,7 @@
 //! - [`texture`](crate::backend::renderer::element::texture) - Texture based render element
 //! - [`surface`](crate::backend::renderer::element::surface) - Wayland surface render element
 //! - [`solid`](crate::backend::renderer::element::solid) - Solid color render element
+//! - [`perf`](crate::backend::renderer::element::perf) - Performance overlay render element
 //!
 //! The [`render_elements!`] macro provides an easy way to aggregate multiple different [RenderElement]s
 //! into a single enum.
@@ -34,6 +35,7 @@ use super::utils::Buffer;
 use super::{utils::CommitCounter, Renderer};
 
 pub mod memory;
+pub mod perf;
 pub mod solid;
 #[cfg(feature = "wayland_frontend")]
 pub mod surface;
@@ -0,0 +1,474 @@
+//! Element to render a performance overlay
+//!
+//! A [`PerfHud`] draws the frame rate, frame and render times, the damage of the last frame and a
+//! graph of recent frame times of an output into a [`MemoryRenderBuffer`], which can be rendered
+//! on top of the output like any other element. The statistics are collected per output in
+//! [`PerfStats`], which are fed by the compositor after every presented frame, e.g. with the
+//! damage returned by [`OutputDamageTracker::render_output`](crate::backend::renderer::damage::OutputDamageTracker::render_output)
+//! and the render times also recorded for a [`FrameScheduler`](crate::output::FrameScheduler).
+//!
+//! Note that the overlay itself is damaged whenever it is updated, so the damage it reports
+//! includes its own area.
+//!
+//! # How to use it
+//!
+//! ```no_run
+//! # use smithay::backend::renderer::{ImportMem, Renderer};
+//! # use smithay::utils::{Monotonic, Physical, Rectangle, Time};
+//! # fn present_frame() -> (Time<Monotonic>, std::time::Duration, Vec<Rectangle<i32, Physical>>) { unimplemented!() }
+//! # fn render<R: Renderer + ImportMem>(renderer: &mut R) where R::TextureId: 'static {
+//! use smithay::{
+//!     backend::renderer::element::perf::{PerfHud, PerfStats},
+//!     output::Mode,
+//! };
+//!
+//! let mode = Mode { size: (1920, 1080).into(), refresh: 60_000 };
+//! let mut stats = PerfStats::new(mode);
+//! let mut hud = PerfHud::new("DP-1", 1);
+//!
+//! loop {
+//!     // after a frame was presented
+//!     let (time, render_time, damage) = present_frame();
+//!     stats.record_frame(time, render_time, &damage);
+//!     hud.update(&stats);
+//!
+//!     // render the overlay on top of the output
+//!     let element = hud.render_element(renderer, (16.0, 16.0), 0.9).unwrap();
+//!     # let _ = element;
+//! }
+//! # }
+//! ```
+
+use std::{collections::VecDeque, convert::Infallible, time::Duration};
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{ImportMem, Renderer},
+    },
+    output::Mode,
+    utils::{Buffer, Monotonic, Physical, Point, Rectangle, Time, Transform},
+};
+
+use super::memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement};
+
+const MAX_SAMPLES: usize = 120;
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+const GLYPH_SCALE: i32 = 2;
+const GLYPH_ADVANCE: i32 = 4 * GLYPH_SCALE;
+const LINE_HEIGHT: i32 = 7 * GLYPH_SCALE;
+const PADDING: i32 = 6;
+const LINES: i32 = 4;
+const BAR_WIDTH: i32 = 2;
+const GRAPH_HEIGHT: i32 = 48;
+const HUD_WIDTH: i32 = 240;
+const HUD_HEIGHT: i32 = PADDING + LINES * LINE_HEIGHT + PADDING + GRAPH_HEIGHT + PADDING;
+
+const BACKGROUND: u32 = 0xc000_0000;
+const TEXT: u32 = 0xffff_ffff;
+const FRAME_OK: u32 = 0xff4c_af50;
+const FRAME_LATE: u32 = 0xfff4_4336;
+const RENDER: u32 = 0xff21_96f3;
+const BUDGET: u32 = 0xffff_eb3b;
+
+#[derive(Debug, Clone, Copy)]
+struct FrameSample {
+    time: Duration,
+    render_time: Duration,
+}
+
+/// Performance statistics of an output
+#[derive(Debug, Clone)]
+pub struct PerfStats {
+    mode: Mode,
+    samples: VecDeque<FrameSample>,
+    frames: u64,
+    damage_rects: usize,
+    damage_area: i64,
+}
+
+impl PerfStats {
+    /// Create new statistics for an output with the given mode
+    pub fn new(mode: Mode) -> PerfStats {
+        PerfStats {
+            mode,
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+            frames: 0,
+            damage_rects: 0,
+            damage_area: 0,
+        }
+    }
+
+    /// Update the mode of the output
+    ///
+    /// The recorded frames are discarded.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.samples.clear();
+    }
+
+    /// Returns the refresh interval of the output
+    ///
+    /// Outputs with an unknown refresh rate are assumed to refresh at 60Hz.
+    pub fn refresh_interval(&self) -> Duration {
+        if self.mode.refresh > 0 {
+            Duration::from_nanos(1_000_000_000_000 / self.mode.refresh as u64)
+        } else {
+            Duration::from_nanos(1_000_000_000 / 60)
+        }
+    }
+
+    /// Record a presented frame
+    ///
+    /// `time` is the presentation time of the frame, `render_time` the time it took to render it
+    /// and `damage` the damage of the frame in output coordinates.
+    pub fn record_frame(
+        &mut self,
+        time: Time<Monotonic>,
+        render_time: Duration,
+        damage: &[Rectangle<i32, Physical>],
+    ) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(FrameSample {
+            time: time.into(),
+            render_time,
+        });
+        self.frames += 1;
+        self.damage_rects = damage.len();
+        self.damage_area = damage
+            .iter()
+            .map(|rect| rect.size.w as i64 * rect.size.h as i64)
+            .sum();
+    }
+
+    /// Number of frames recorded so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Frames per second presented during the last second
+    pub fn fps(&self) -> f64 {
+        let Some(last) = self.samples.back() else {
+            return 0.0;
+        };
+        let count = self
+            .samples
+            .iter()
+            .rev()
+            .take_while(|sample| last.time - sample.time <= FPS_WINDOW)
+            .count();
+        let first = self.samples[self.samples.len() - count];
+        let span = last.time - first.time;
+        if span.is_zero() {
+            return 0.0;
+        }
+        (count - 1) as f64 / span.as_secs_f64()
+    }
+
+    /// Average time between the recorded frames
+    pub fn frame_time(&self) -> Option<Duration> {
+        let first = self.samples.front()?;
+        let last = self.samples.back()?;
+        let intervals = self.samples.len().checked_sub(1).filter(|n| *n > 0)?;
+        Some((last.time - first.time) / intervals as u32)
+    }
+
+    /// Average render time of the recorded frames
+    pub fn render_time(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let total = self
+            .samples
+            .iter()
+            .map(|sample| sample.render_time)
+            .sum::<Duration>();
+        Some(total / self.samples.len() as u32)
+    }
+
+    /// Longest render time of the recorded frames
+    pub fn max_render_time(&self) -> Option<Duration> {
+        self.samples.iter().map(|sample| sample.render_time).max()
+    }
+
+    /// Number of damage rectangles of the last frame
+    pub fn damage_rects(&self) -> usize {
+        self.damage_rects
+    }
+
+    /// Fraction of the output damaged by the last frame
+    ///
+    /// Overlapping damage rectangles are counted multiple times.
+    pub fn damage_ratio(&self) -> f64 {
+        let area = self.mode.size.w as i64 * self.mode.size.h as i64;
+        if area == 0 {
+            return 0.0;
+        }
+        (self.damage_area as f64 / area as f64).min(1.0)
+    }
+}
+
+/// Overlay showing the [`PerfStats`] of an output
+#[derive(Debug)]
+pub struct PerfHud {
+    label: String,
+    scale: i32,
+    buffer: MemoryRenderBuffer,
+}
+
+impl PerfHud {
+    /// Create a new overlay with the given label, usually the name of the output
+    ///
+    /// `scale` is the buffer scale of the overlay, e.g. the integer scale of the output.
+    pub fn new(label: impl Into<String>, scale: i32) -> PerfHud {
+        let scale = scale.max(1);
+        let size = (HUD_WIDTH * scale, HUD_HEIGHT * scale);
+        PerfHud {
+            label: label.into(),
+            scale,
+            buffer: MemoryRenderBuffer::new(Fourcc::Argb8888, size, scale, Transform::Normal, None),
+        }
+    }
+
+    /// Redraw the overlay with the given statistics
+    pub fn update(&mut self, stats: &PerfStats) {
+        let (label, scale) = (&self.label, self.scale);
+        let mut context = self.buffer.render();
+        context
+            .draw(|mem| {
+                let mut canvas = Canvas {
+                    mem,
+                    width: HUD_WIDTH * scale,
+                    scale,
+                };
+                canvas.draw_stats(label, stats);
+                Ok::<_, Infallible>(vec![Rectangle::from_loc_and_size(
+                    (0, 0),
+                    (HUD_WIDTH * scale, HUD_HEIGHT * scale),
+                )])
+            })
+            .unwrap();
+    }
+
+    /// Returns the buffer the overlay is drawn into
+    pub fn buffer(&self) -> &MemoryRenderBuffer {
+        &self.buffer
+    }
+
+    /// Create a render element for the overlay at the given location
+    pub fn render_element<R>(
+        &self,
+        renderer: &mut R,
+        location: impl Into<Point<f64, Physical>>,
+        alpha: f32,
+    ) -> Result<MemoryRenderBufferRenderElement<R>, <R as Renderer>::Error>
+    where
+        R: Renderer + ImportMem,
+        <R as Renderer>::TextureId: 'static,
+    {
+        MemoryRenderBufferRenderElement::from_buffer(
+            renderer,
+            location,
+            &self.buffer,
+            Some(alpha),
+            None,
+            None,
+        )
+    }
+}
+
+struct Canvas<'a> {
+    mem: &'a mut [u8],
+    width: i32,
+    scale: i32,
+}
+
+impl<'a> Canvas<'a> {
+    fn fill(&mut self, rect: Rectangle<i32, Buffer>, color: u32) {
+        let height = self.mem.len() as i32 / 4 / self.width;
+        let x0 = (rect.loc.x * self.scale).clamp(0, self.width);
+        let y0 = (rect.loc.y * self.scale).clamp(0, height);
+        let x1 = ((rect.loc.x + rect.size.w) * self.scale).clamp(0, self.width);
+        let y1 = ((rect.loc.y + rect.size.h) * self.scale).clamp(0, height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let offset = ((y * self.width + x) * 4) as usize;
+                self.mem[offset..offset + 4].copy_from_slice(&color.to_le_bytes());
+            }
+        }
+    }
+
+    fn text(&mut self, loc: (i32, i32), text: &str, color: u32) {
+        for (idx, c) in text.chars().enumerate() {
+            let x = loc.0 + idx as i32 * GLYPH_ADVANCE;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        self.fill(
+                            Rectangle::from_loc_and_size(
+                                (x + col * GLYPH_SCALE, loc.1 + row as i32 * GLYPH_SCALE),
+                                (GLYPH_SCALE, GLYPH_SCALE),
+                            ),
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_stats(&mut self, label: &str, stats: &PerfStats) {
+        self.fill(
+            Rectangle::from_loc_and_size((0, 0), (HUD_WIDTH, HUD_HEIGHT)),
+            BACKGROUND,
+        );
+
+        let millis = |duration: Option<Duration>| duration.unwrap_or_default().as_secs_f64() * 1000.0;
+        let lines = [
+            label.to_string(),
+            format!(
+                "FPS {:.1}  FRAME {:.1}MS",
+                stats.fps(),
+                millis(stats.frame_time())
+            ),
+            format!(
+                "RENDER {:.1}MS MAX {:.1}MS",
+                millis(stats.render_time()),
+                millis(stats.max_render_time())
+            ),
+            format!(
+                "DAMAGE {} RECTS {:.1}%",
+                stats.damage_rects(),
+                stats.damage_ratio() * 100.0
+            ),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            self.text((PADDING, PADDING + idx as i32 * LINE_HEIGHT), line, TEXT);
+        }
+
+        // frame times scaled to twice the refresh interval, with the budget at half height
+        let graph_width = HUD_WIDTH - 2 * PADDING;
+        let bottom = HUD_HEIGHT - PADDING;
+        let budget = stats.refresh_interval().as_secs_f64();
+        let bar_height = |duration: Duration| {
+            ((duration.as_secs_f64() / (2.0 * budget)).min(1.0) * GRAPH_HEIGHT as f64).round() as i32
+        };
+
+        let bars = (graph_width / BAR_WIDTH) as usize;
+        let samples = stats.samples.iter().collect::<Vec<_>>();
+        let intervals = samples
+            .windows(2)
+            .map(|pair| (pair[1].time - pair[0].time, pair[1].render_time));
+        let skip = samples.len().saturating_sub(1).saturating_sub(bars);
+        for (idx, (interval, render_time)) in intervals.skip(skip).enumerate() {
+            let x = PADDING + idx as i32 * BAR_WIDTH;
+            let color = if interval.as_secs_f64() <= budget * 1.5 {
+                FRAME_OK
+            } else {
+                FRAME_LATE
+            };
+            let height = bar_height(interval);
+            self.fill(
+                Rectangle::from_loc_and_size((x, bottom - height), (BAR_WIDTH, height)),
+                color,
+            );
+            let height = bar_height(render_time);
+            self.fill(
+                Rectangle::from_loc_and_size((x, bottom - height), (BAR_WIDTH, height)),
+                RENDER,
+            );
+        }
+
+        self.fill(
+            Rectangle::from_loc_and_size((PADDING, bottom - GRAPH_HEIGHT / 2), (graph_width, 1)),
+            BUDGET,
+        );
+    }
+}
+
+// 3x5 pixel glyphs, one row per entry with the most significant bit on the left
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        _ => [0; 5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(millis: u64) -> Time<Monotonic> {
+        Duration::from_millis(millis).into()
+    }
+
+    #[test]
+    fn collect_stats() {
+        let mode = Mode {
+            size: (100, 100).into(),
+            refresh: 50_000,
+        };
+        let mut stats = PerfStats::new(mode);
+        assert_eq!(stats.fps(), 0.0);
+        assert_eq!(stats.frame_time(), None);
+
+        for frame in 0..100 {
+            stats.record_frame(
+                time(frame * 20),
+                Duration::from_millis(2 + frame % 3),
+                &[Rectangle::from_loc_and_size((0, 0), (50, 50))],
+            );
+        }
+
+        assert_eq!(stats.frames(), 100);
+        assert_eq!(stats.fps(), 50.0);
+        assert_eq!(stats.frame_time(), Some(Duration::from_millis(20)));
+        assert_eq!(stats.max_render_time(), Some(Duration::from_millis(4)));
+        assert_eq!(stats.damage_rects(), 1);
+        assert_eq!(stats.damage_ratio(), 0.25);
+    }
+}
@@ -370,17 +370,7 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
         compositor::with_states(&self.surface, |states| {
             let data = states.data_map.get::<RendererSurfaceStateUserData>();
             if let Some(data) = data {
-                let data = data.borrow();
-
-                if let Some(view) = data.view() {
-                    Some(view.src.to_buffer(
-                        data.buffer_scale as f64,
-                        data.buffer_transform,
-                        &data.buffer_size().unwrap().to_f64(),
-                    ))
-                } else {
-                    None
-                }
+                data.borrow().geometry().map(|geometry| geometry.src_buffer())
             } else {
                 None
             }
@@ -425,7 +415,7 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
                                 // then crop by the surface view (viewporter for example could define a src rect)
                                 .intersection(surface_view.src)
                                 // move and scale the cropped rect (viewporter could define a dst size)
-                                .map(|rect| surface_view.rect_to_global(rect).to_i32_up::<i32>())
+                                .map(|rect| surface_view.rect_to_global(rect))
                                 // now bring the damage to physical space
                                 .map(|rect| {
                                     // We calculate the scale between to rounded
@@ -433,9 +423,13 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
                                     // and use it to scale the damage to the rounded
                                     // surface size by multiplying the output scale
                                     // with the result.
+                                    //
+                                    // Rounding only happens at the very end, otherwise
+                                    // a viewport scaling the buffer combined with a fractional
+                                    // output scale could result in too small damage.
                                     let surface_scale =
                                         dst_size.to_f64() / surface_view.dst.to_f64().to_physical(scale);
-                                    rect.to_physical_precise_up(surface_scale * scale)
+                                    rect.to_physical(surface_scale * scale).to_i32_up::<i32>()
                                 })
                         })
                         .collect::<Vec<_>>();
@@ -10,6 +10,7 @@ pub const fn fourcc_to_gl_formats(value: Fourcc) -> Option<(GLenum, GLenum, GLen
         Fourcc::Argb8888 => Some((ffi::BGRA_EXT, ffi::BGRA_EXT, ffi::UNSIGNED_BYTE)),
         Fourcc::Abgr2101010 => Some((ffi::RGB10_A2, ffi::RGBA, ffi::UNSIGNED_INT_2_10_10_10_REV)),
         Fourcc::Abgr16161616f => Some((ffi::RGBA16F, ffi::RGBA, ffi::HALF_FLOAT)),
+        Fourcc::Rgb565 => Some((ffi::RGB565, ffi::RGB, ffi::UNSIGNED_SHORT_5_6_5)),
         _ => None,
     }
 }
@@ -22,6 +23,7 @@ pub const fn gl_internal_format_to_fourcc(format: GLenum) -> Option<Fourcc> {
         ffi::RGB8 => Some(Fourcc::Bgr888),
         ffi::RGB10_A2 => Some(Fourcc::Abgr2101010),
         ffi::RGBA16F => Some(Fourcc::Abgr16161616f),
+        ffi::RGB565 => Some(Fourcc::Rgb565),
         _ => None,
     }
 }
@@ -34,6 +36,7 @@ pub const fn gl_read_format_to_fourcc(format: GLenum, type_: GLenum) -> Option<F
         (ffi::RGB, ffi::UNSIGNED_BYTE) => Some(Fourcc::Bgr888),
         (ffi::RGBA, ffi::UNSIGNED_INT_2_10_10_10_REV) => Some(Fourcc::Abgr2101010),
         (ffi::RGBA, ffi::HALF_FLOAT) => Some(Fourcc::Abgr16161616f),
+        (ffi::RGB, ffi::UNSIGNED_SHORT_5_6_5) => Some(Fourcc::Rgb565),
         _ => None,
     }
 }
@@ -46,6 +49,7 @@ pub const fn gl_read_for_internal(format: GLenum) -> Option<(GLenum, GLenum)> {
         ffi::RGB8 => Some((ffi::RGB, ffi::UNSIGNED_BYTE)),
         ffi::RGB10_A2 => Some((ffi::RGBA, ffi::UNSIGNED_INT_2_10_10_10_REV)),
         ffi::RGBA16F => Some((ffi::RGBA, ffi::HALF_FLOAT)),
+        ffi::RGB565 => Some((ffi::RGB, ffi::UNSIGNED_SHORT_5_6_5)),
         _ => None,
     }
 }
@@ -53,6 +57,7 @@ pub const fn gl_read_for_internal(format: GLenum) -> Option<(GLenum, GLenum)> {
 /// Returns the bits per pixel for a given read format and type
 pub const fn gl_bpp(format: GLenum, type_: GLenum) -> Option<usize> {
     match (format, type_) {
+        (ffi::RGB, ffi::UNSIGNED_SHORT_5_6_5) => Some(16),
         (ffi::RGB, ffi::UNSIGNED_BYTE) => Some(24),
         (ffi::RGBA, ffi::UNSIGNED_BYTE)
         | (ffi::BGRA_EXT, ffi::UNSIGNED_BYTE)
@@ -61,3 +66,29 @@ pub const fn gl_bpp(format: GLenum, type_: GLenum) -> Option<usize> {
         _ => None,
     }
 }
+
+/// Returns the `UNPACK_ALIGNMENT` for uploading tightly packed rows of a given read format and type
+///
+/// The default alignment of 4 would make GL read past the end of rows with an odd width in 16-bit formats.
+pub const fn gl_unpack_alignment(format: GLenum, type_: GLenum) -> Option<i32> {
+    match gl_bpp(format, type_) {
+        Some(16) => Some(2),
+        Some(32) | Some(64) => Some(4),
+        Some(_) => Some(1),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odd_width_rgb565_rows_are_tightly_packed() {
+        let (_, format, type_) = fourcc_to_gl_formats(Fourcc::Rgb565).unwrap();
+        let alignment = gl_unpack_alignment(format, type_).unwrap() as usize;
+        let row = 3 * gl_bpp(format, type_).unwrap() / 8;
+        // rows are padded to a multiple of the alignment, which would read past the 3 pixel wide buffer
+        assert_eq!(row % alignment, 0);
+    }
+}
@@ -771,10 +771,11 @@ impl ImportMemWl for GlesRenderer {
             }
 
             let has_alpha = has_alpha(fourcc);
+            // opaque formats without a transparent alternative (e.g. RGB565) are uploaded as-is
             let (mut internal_format, read_format, type_) = fourcc_to_gl_formats(if has_alpha {
                 fourcc
             } else {
-                get_transparent(fourcc).ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?
+                get_transparent(fourcc).unwrap_or(fourcc)
             })
             .ok_or(GlesError::UnsupportedWlPixelFormat(data.format))?;
             if self.gl_version.major == 2 {
@@ -782,13 +783,14 @@ impl ImportMemWl for GlesRenderer {
                 internal_format = match internal_format {
                     ffi::BGRA_EXT => ffi::BGRA_EXT,
                     ffi::RGBA8 => ffi::RGBA,
-                    ffi::RGB8 => ffi::RGB,
+                    ffi::RGB8 | ffi::RGB565 => ffi::RGB,
                     _ => unreachable!(),
                 };
             }
 
             // number of bytes per pixel
             let pixelsize = gl_bpp(read_format, type_).expect("We check the format before") / 8;
+            let alignment = gl_unpack_alignment(read_format, type_).expect("We check the format before");
             // ensure consistency, the SHM handler of smithay should ensure this
             assert!((offset + (height - 1) * stride + width * pixelsize as i32) as usize <= len);
 
@@ -846,6 +848,7 @@ impl ImportMemWl for GlesRenderer {
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
                 self.gl
                     .PixelStorei(ffi::UNPACK_ROW_LENGTH, stride / pixelsize as i32);
+                self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, alignment);
 
                 if upload_full || damage.is_empty() {
                     trace!("Uploading shm texture");
@@ -882,6 +885,7 @@ impl ImportMemWl for GlesRenderer {
                 }
 
                 self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
+                self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, 4);
                 self.gl.BindTexture(ffi::TEXTURE_2D, 0);
             }
 
@@ -896,6 +900,7 @@ const SUPPORTED_MEM_FORMATS_2: &[Fourcc] = &[
     Fourcc::Xbgr8888,
     Fourcc::Argb8888,
     Fourcc::Xrgb8888,
+    Fourcc::Rgb565,
 ];
 const SUPPORTED_MEM_FORMATS_3: &[Fourcc] = &[
     Fourcc::Abgr8888,
@@ -906,6 +911,7 @@ const SUPPORTED_MEM_FORMATS_3: &[Fourcc] = &[
     Fourcc::Xbgr2101010,
     Fourcc::Abgr16161616f,
     Fourcc::Xbgr16161616f,
+    Fourcc::Rgb565,
 ];
 
 impl ImportMem for GlesRenderer {
@@ -938,14 +944,16 @@ impl ImportMem for GlesRenderer {
         let (mut internal, format, layout) = fourcc_to_gl_formats(if has_alpha {
             format
         } else {
-            get_transparent(format).expect("We check the format before")
+            get_transparent(format).unwrap_or(format)
         })
         .expect("We check the format before");
+        let alignment = gl_unpack_alignment(format, layout).expect("We check the format before");
         if self.gl_version.major == 2 {
             // es 2.0 doesn't define sized variants
             internal = match internal {
+                ffi::BGRA_EXT => ffi::BGRA_EXT,
                 ffi::RGBA8 => ffi::RGBA,
-                ffi::RGB8 => ffi::RGB,
+                ffi::RGB8 | ffi::RGB565 => ffi::RGB,
                 _ => unreachable!(),
             };
         }
@@ -959,6 +967,7 @@ impl ImportMem for GlesRenderer {
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_S, ffi::CLAMP_TO_EDGE as i32);
                 self.gl
                     .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
+                self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, alignment);
                 self.gl.TexImage2D(
                     ffi::TEXTURE_2D,
                     0,
@@ -970,6 +979,7 @@ impl ImportMem for GlesRenderer {
                     layout as u32,
                     data.as_ptr() as *const _,
                 );
+                self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, 4);
                 self.gl.BindTexture(ffi::TEXTURE_2D, 0);
             }
             // new texture, upload in full
@@ -1012,6 +1022,7 @@ impl ImportMem for GlesRenderer {
         {
             return Err(GlesError::UnexpectedSize);
         }
+        let alignment = gl_unpack_alignment(read_format, type_).ok_or(GlesError::UnknownPixelFormat)?;
 
         unsafe {
             self.gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
@@ -1020,6 +1031,7 @@ impl ImportMem for GlesRenderer {
             self.gl
                 .TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_WRAP_T, ffi::CLAMP_TO_EDGE as i32);
             self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, texture.0.size.w);
+            self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, alignment);
             self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, region.loc.x);
             self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, region.loc.y);
             self.gl.TexSubImage2D(
@@ -1036,6 +1048,7 @@ impl ImportMem for GlesRenderer {
             self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
             self.gl.PixelStorei(ffi::UNPACK_SKIP_PIXELS, 0);
             self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
+            self.gl.PixelStorei(ffi::UNPACK_ALIGNMENT, 4);
             self.gl.BindTexture(ffi::TEXTURE_2D, 0);
         }
 
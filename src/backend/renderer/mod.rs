@@ -6,6 +6,7 @@
 //! Supported rendering apis:
 //!
 //! - Raw OpenGL ES 2
+//! - Software rendering on the cpu
 
 use std::collections::HashSet;
 use std::error::Error;
@@ -24,6 +25,9 @@ pub mod gles;
 #[cfg(feature = "renderer_glow")]
 pub mod glow;
 
+#[cfg(feature = "renderer_software")]
+pub mod software;
+
 use crate::backend::allocator::{dmabuf::Dmabuf, Format, Fourcc};
 #[cfg(all(
     feature = "wayland_frontend",
@@ -73,6 +73,9 @@ impl<R> Default for GbmGlesBackend<R> {
 
 impl<R> GbmGlesBackend<R> {
     /// Add a new GBM device for a given node to the api
+    ///
+    /// Call [`GpuManager::update_devices`](super::GpuManager::update_devices) afterwards
+    /// to create a renderer for the new device.
     pub fn add_node<T: AsFd + Send + 'static>(
         &mut self,
         node: DrmNode,
@@ -83,6 +86,9 @@ impl<R> GbmGlesBackend<R> {
     }
 
     /// Remove a given node from the api
+    ///
+    /// Call [`GpuManager::update_devices`](super::GpuManager::update_devices) afterwards
+    /// to drop the renderer of the removed device.
     pub fn remove_node(&mut self, node: &DrmNode) {
         self.devices.remove(node);
     }
@@ -34,6 +34,10 @@
 //! Any `ExportMem`-implementations will originate from the render-gpu, which again
 //! needs to support the requested format directly. No paths across other gpus are tested.
 //!
+//! Gpus may be added and removed at runtime. After adding or removing a device from
+//! the [`GraphicsApi`], call [`GpuManager::update_devices`] to create or drop the corresponding
+//! renderers and get notified about the changes.
+//!
 //! *Note*: This module will not keep you from selecting sub-optimal configurations.
 //! Any heuristics for which render-gpu to use for a given set of client buffers
 //! and desired target-gpu are up to be implemented by the compositor. The module only
@@ -188,6 +192,51 @@ impl<A: GraphicsApi> GpuManager<A> {
         })
     }
 
+    /// Update the list of devices after gpus were added or removed from the [`GraphicsApi`]
+    ///
+    /// Renderers for new gpus are created and renderers of removed gpus are dropped
+    /// together with any buffers cached for copies from or to them.
+    /// The returned [`GpuEvent`]s describe which gpus changed, so the compositor can
+    /// e.g. move outputs of a removed gpu to another render node.
+    ///
+    /// Client buffers are imported again on the new render node on their next use.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn update_devices(&mut self) -> Result<Vec<GpuEvent>, Error<A, A>> {
+        let previous = self.devices.iter().map(|dev| *dev.node()).collect::<Vec<_>>();
+        self.api
+            .enumerate(&mut self.devices)
+            .map_err(Error::RenderApiError)?;
+
+        let mut events = previous
+            .iter()
+            .filter(|node| !self.devices.iter().any(|dev| dev.node() == *node))
+            .map(|node| GpuEvent::Removed(*node))
+            .collect::<Vec<_>>();
+        events.extend(
+            self.devices
+                .iter()
+                .filter(|dev| !previous.contains(dev.node()))
+                .map(|dev| GpuEvent::Added(*dev.node())),
+        );
+
+        for event in &events {
+            if let GpuEvent::Removed(node) = event {
+                info!(?node, "Gpu removed");
+                self.dmabuf_cache
+                    .retain(|(render, target), _| render != node && target != node);
+            } else if let GpuEvent::Added(node) = event {
+                info!(?node, "Gpu added");
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the nodes of all gpus currently known to this [`GpuManager`]
+    pub fn devices(&self) -> impl Iterator<Item = &DrmNode> {
+        self.devices.iter().map(|dev| dev.node())
+    }
+
     /// Create a [`MultiRenderer`] from a single device.
     ///
     /// This a convenience function to deal with the same types even, if you only need one device.
@@ -618,6 +667,17 @@ impl<A: GraphicsApi> GpuManager<A> {
     }
 }
 
+/// Change of the gpus available to a [`GpuManager`]
+///
+/// Returned by [`GpuManager::update_devices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuEvent {
+    /// A new gpu is available for rendering
+    Added(DrmNode),
+    /// A gpu was removed, its renderer has been dropped
+    Removed(DrmNode),
+}
+
 /// A graphics api, that supports enumerating graphics devices
 pub trait GraphicsApi {
     /// Devices this api produces
@@ -0,0 +1,863 @@
+//! Software renderer rendering on the cpu
+//!
+//! The [`SoftwareRenderer`] renders into [`SoftwareBuffer`]s in main memory. It does not need
+//! any graphics hardware and its output is fully deterministic, which makes it suitable for
+//! running compositor logic headlessly in tests, e.g. in CI, and taking screenshots of the
+//! rendered frames. It is not meant to be fast.
+//!
+//! Buffers and textures are stored as premultiplied `Argb8888`. Memory in the `Argb8888`,
+//! `Xrgb8888`, `Abgr8888` and `Xbgr8888` formats can be imported, which includes shm-based
+//! wl_buffers of these formats. Importing dmabufs is not supported.
+//!
+//! ```
+//! use smithay::backend::{
+//!     allocator::Fourcc,
+//!     renderer::{software::SoftwareRenderer, Bind, Frame, Offscreen, Renderer},
+//! };
+//! use smithay::utils::{Rectangle, Transform};
+//!
+//! let mut renderer = SoftwareRenderer::new();
+//! let buffer = renderer.create_buffer(Fourcc::Argb8888, (4, 4).into()).unwrap();
+//! renderer.bind(buffer.clone()).unwrap();
+//!
+//! let mut frame = renderer.render((4, 4).into(), Transform::Normal).unwrap();
+//! frame
+//!     .clear([1.0, 0.0, 0.0, 1.0], &[Rectangle::from_loc_and_size((0, 0), (4, 4))])
+//!     .unwrap();
+//! frame.finish().unwrap();
+//!
+//! assert_eq!(buffer.pixel((2, 2).into()), Some(0xffff0000));
+//! ```
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock, RwLockReadGuard,
+};
+
+use tracing::{instrument, trace, warn};
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::protocol::{wl_buffer, wl_shm};
+
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+use super::ImportEgl;
+use super::{
+    Bind, DebugFlags, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture, TextureFilter,
+    TextureMapping, Unbind,
+};
+#[cfg(feature = "wayland_frontend")]
+use super::{ImportDmaWl, ImportMemWl};
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+use crate::backend::egl::display::EGLBufferReader;
+use crate::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, Fourcc},
+        SwapBuffersError,
+    },
+    utils::{Buffer as BufferCoord, Logical, Physical, Point, Rectangle, Size, Transform},
+};
+
+static RENDERER_ID: AtomicUsize = AtomicUsize::new(0);
+
+const SUPPORTED_FORMATS: &[Fourcc] = &[
+    Fourcc::Argb8888,
+    Fourcc::Xrgb8888,
+    Fourcc::Abgr8888,
+    Fourcc::Xbgr8888,
+];
+
+/// Error returned by the [`SoftwareRenderer`]
+#[derive(Debug, thiserror::Error)]
+pub enum SoftwareError {
+    /// No target is bound to render into
+    #[error("No target is bound to render into")]
+    NoTarget,
+    /// The given pixel format is not supported
+    #[error("Unsupported pixel format: {0:?}")]
+    UnsupportedPixelFormat(Fourcc),
+    /// The given wl buffer has an unsupported pixel format
+    #[error("Unsupported wl_shm format: {0:?}")]
+    #[cfg(feature = "wayland_frontend")]
+    UnsupportedWlPixelFormat(wl_shm::Format),
+    /// The given buffer was not accessible
+    #[error("Error accessing the buffer ({0:?})")]
+    #[cfg(feature = "wayland_frontend")]
+    BufferAccessError(crate::wayland::shm::BufferAccessError),
+    /// The provided memory does not match the expected size
+    #[error("The provided memory does not match the expected size")]
+    UnexpectedSize,
+    /// The given region is not inside the bounds of the buffer
+    #[error("Region {0:?} is out of bounds")]
+    OutOfBounds(Rectangle<i32, BufferCoord>),
+    /// Importing dmabufs is not supported by the software renderer
+    #[error("Importing dmabufs is not supported")]
+    DmabufUnsupported,
+}
+
+impl From<SoftwareError> for SwapBuffersError {
+    fn from(err: SoftwareError) -> SwapBuffersError {
+        SwapBuffersError::TemporaryFailure(Box::new(err))
+    }
+}
+
+#[derive(Debug)]
+struct Image {
+    size: Size<i32, BufferCoord>,
+    format: Fourcc,
+    flipped: bool,
+    // premultiplied `0xAARRGGBB` pixels, row by row
+    pixels: Vec<u32>,
+}
+
+impl Image {
+    fn new(format: Fourcc, size: Size<i32, BufferCoord>, flipped: bool) -> Result<Image, SoftwareError> {
+        if !SUPPORTED_FORMATS.contains(&format) {
+            return Err(SoftwareError::UnsupportedPixelFormat(format));
+        }
+        if size.w < 0 || size.h < 0 {
+            return Err(SoftwareError::UnexpectedSize);
+        }
+        Ok(Image {
+            size,
+            format,
+            flipped,
+            pixels: vec![0; size.w as usize * size.h as usize],
+        })
+    }
+
+    fn bounds(&self) -> Rectangle<i32, BufferCoord> {
+        Rectangle::from_loc_and_size((0, 0), self.size)
+    }
+
+    fn index(&self, point: Point<i32, BufferCoord>) -> usize {
+        point.y as usize * self.size.w as usize + point.x as usize
+    }
+
+    // copies `region` of the image contained in `data` into this image
+    fn write(
+        &mut self,
+        data: &[u8],
+        stride: usize,
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), SoftwareError> {
+        if !self.bounds().contains_rect(region) {
+            return Err(SoftwareError::OutOfBounds(region));
+        }
+        if region.size.w <= 0 || region.size.h <= 0 {
+            return Ok(());
+        }
+        let end = (region.loc.y + region.size.h - 1) as usize * stride
+            + (region.loc.x + region.size.w) as usize * 4;
+        if data.len() < end {
+            return Err(SoftwareError::UnexpectedSize);
+        }
+
+        for y in region.loc.y..region.loc.y + region.size.h {
+            for x in region.loc.x..region.loc.x + region.size.w {
+                let offset = y as usize * stride + x as usize * 4;
+                let bytes = [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]];
+                let idx = self.index((x, y).into());
+                self.pixels[idx] = decode_pixel(self.format, bytes);
+            }
+        }
+        Ok(())
+    }
+
+    // copies `region` of this image into memory of the given format
+    fn read(&self, region: Rectangle<i32, BufferCoord>, format: Fourcc) -> Result<Vec<u8>, SoftwareError> {
+        if !SUPPORTED_FORMATS.contains(&format) {
+            return Err(SoftwareError::UnsupportedPixelFormat(format));
+        }
+        if !self.bounds().contains_rect(region) {
+            return Err(SoftwareError::OutOfBounds(region));
+        }
+
+        let mut data = Vec::with_capacity(region.size.w.max(0) as usize * region.size.h.max(0) as usize * 4);
+        for y in region.loc.y..region.loc.y + region.size.h {
+            for x in region.loc.x..region.loc.x + region.size.w {
+                data.extend_from_slice(&encode_pixel(format, self.pixels[self.index((x, y).into())]));
+            }
+        }
+        Ok(data)
+    }
+
+    fn texel(&self, x: i32, y: i32) -> [f32; 4] {
+        let x = x.clamp(0, self.size.w - 1);
+        let mut y = y.clamp(0, self.size.h - 1);
+        if self.flipped {
+            y = self.size.h - 1 - y;
+        }
+        unpack(self.pixels[self.index((x, y).into())])
+    }
+
+    // samples the image at a location in buffer coordinates
+    fn sample(&self, point: Point<f64, BufferCoord>, filter: TextureFilter) -> [f32; 4] {
+        match filter {
+            TextureFilter::Nearest => self.texel(point.x.floor() as i32, point.y.floor() as i32),
+            TextureFilter::Linear => {
+                let x = point.x - 0.5;
+                let y = point.y - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+
+                let top = mix(self.texel(x0, y0), self.texel(x0 + 1, y0), fx);
+                let bottom = mix(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), fx);
+                mix(top, bottom, fy)
+            }
+        }
+    }
+}
+
+fn swap_red_blue(pixel: u32) -> u32 {
+    (pixel & 0xff00_ff00) | ((pixel >> 16) & 0xff) | ((pixel & 0xff) << 16)
+}
+
+fn decode_pixel(format: Fourcc, bytes: [u8; 4]) -> u32 {
+    let pixel = u32::from_le_bytes(bytes);
+    match format {
+        Fourcc::Xrgb8888 => pixel | 0xff00_0000,
+        Fourcc::Abgr8888 => swap_red_blue(pixel),
+        Fourcc::Xbgr8888 => swap_red_blue(pixel) | 0xff00_0000,
+        _ => pixel,
+    }
+}
+
+fn encode_pixel(format: Fourcc, pixel: u32) -> [u8; 4] {
+    match format {
+        Fourcc::Abgr8888 | Fourcc::Xbgr8888 => swap_red_blue(pixel).to_le_bytes(),
+        _ => pixel.to_le_bytes(),
+    }
+}
+
+// [r, g, b, a] in the range 0.0..=1.0
+fn unpack(pixel: u32) -> [f32; 4] {
+    let channel = |shift: u32| ((pixel >> shift) & 0xff) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), channel(24)]
+}
+
+fn pack(color: [f32; 4]) -> u32 {
+    let channel = |value: f32, shift: u32| ((value.clamp(0.0, 1.0) * 255.0).round() as u32) << shift;
+    channel(color[3], 24) | channel(color[0], 16) | channel(color[1], 8) | channel(color[2], 0)
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        a[3] + (b[3] - a[3]) * t,
+    ]
+}
+
+// blends a premultiplied color over the pixel
+fn blend(pixel: &mut u32, color: [f32; 4]) {
+    let dst = unpack(*pixel);
+    let inv = 1.0 - color[3];
+    *pixel = pack([
+        color[0] + dst[0] * inv,
+        color[1] + dst[1] * inv,
+        color[2] + dst[2] * inv,
+        color[3] + dst[3] * inv,
+    ]);
+}
+
+/// Render target of the [`SoftwareRenderer`]
+///
+/// Clones of a buffer refer to the same memory, so a clone can be bound to the renderer
+/// while the contents are read through another one.
+#[derive(Debug, Clone)]
+pub struct SoftwareBuffer(Arc<RwLock<Image>>);
+
+impl SoftwareBuffer {
+    /// Create a new buffer cleared to transparent black
+    pub fn new(format: Fourcc, size: Size<i32, BufferCoord>) -> Result<SoftwareBuffer, SoftwareError> {
+        Ok(SoftwareBuffer(Arc::new(RwLock::new(Image::new(
+            format, size, false,
+        )?))))
+    }
+
+    /// Size of the buffer
+    pub fn size(&self) -> Size<i32, BufferCoord> {
+        self.0.read().unwrap().size
+    }
+
+    /// Format of the buffer
+    pub fn format(&self) -> Fourcc {
+        self.0.read().unwrap().format
+    }
+
+    /// Returns the premultiplied `0xAARRGGBB` value of a pixel
+    ///
+    /// Returns `None` if the location is outside of the buffer.
+    pub fn pixel(&self, location: Point<i32, BufferCoord>) -> Option<u32> {
+        let image = self.0.read().unwrap();
+        if !image.bounds().contains(location) {
+            return None;
+        }
+        Some(image.pixels[image.index(location)])
+    }
+
+    /// Returns the premultiplied `0xAARRGGBB` pixels of the buffer, row by row
+    pub fn pixels(&self) -> Vec<u32> {
+        self.0.read().unwrap().pixels.clone()
+    }
+
+    /// Copies the contents of the buffer into memory of the given format
+    ///
+    /// Rows are tightly packed, so the stride is `4 * width`.
+    pub fn to_memory(&self, format: Fourcc) -> Result<Vec<u8>, SoftwareError> {
+        let image = self.0.read().unwrap();
+        image.read(image.bounds(), format)
+    }
+}
+
+/// Texture of the [`SoftwareRenderer`]
+#[derive(Debug, Clone)]
+pub struct SoftwareTexture(Arc<RwLock<Image>>);
+
+impl SoftwareTexture {
+    fn image(&self) -> RwLockReadGuard<'_, Image> {
+        self.0.read().unwrap()
+    }
+}
+
+impl Texture for SoftwareTexture {
+    fn width(&self) -> u32 {
+        self.image().size.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.image().size.h as u32
+    }
+
+    fn format(&self) -> Option<Fourcc> {
+        Some(self.image().format)
+    }
+}
+
+/// Contents of a [`SoftwareBuffer`] or [`SoftwareTexture`] copied into memory
+#[derive(Debug)]
+pub struct SoftwareMapping {
+    size: Size<i32, BufferCoord>,
+    format: Fourcc,
+    data: Vec<u8>,
+}
+
+impl Texture for SoftwareMapping {
+    fn width(&self) -> u32 {
+        self.size.w as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.size.h as u32
+    }
+
+    fn format(&self) -> Option<Fourcc> {
+        Some(self.format)
+    }
+}
+
+impl TextureMapping for SoftwareMapping {
+    fn flipped(&self) -> bool {
+        false
+    }
+}
+
+/// A renderer rendering on the cpu
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug)]
+pub struct SoftwareRenderer {
+    id: usize,
+    target: Option<SoftwareBuffer>,
+    downscale_filter: TextureFilter,
+    upscale_filter: TextureFilter,
+    debug_flags: DebugFlags,
+}
+
+impl SoftwareRenderer {
+    /// Create a new software renderer
+    pub fn new() -> SoftwareRenderer {
+        SoftwareRenderer {
+            id: RENDERER_ID.fetch_add(1, Ordering::Relaxed),
+            target: None,
+            downscale_filter: TextureFilter::Linear,
+            upscale_filter: TextureFilter::Linear,
+            debug_flags: DebugFlags::empty(),
+        }
+    }
+
+    /// Returns the currently bound buffer
+    pub fn target(&self) -> Option<&SoftwareBuffer> {
+        self.target.as_ref()
+    }
+}
+
+impl Default for SoftwareRenderer {
+    fn default() -> Self {
+        SoftwareRenderer::new()
+    }
+}
+
+/// Frame of the [`SoftwareRenderer`]
+#[derive(Debug)]
+pub struct SoftwareFrame<'frame> {
+    renderer: &'frame mut SoftwareRenderer,
+    target: SoftwareBuffer,
+    // size of the output after applying the transformation
+    size: Size<i32, Physical>,
+    transform: Transform,
+}
+
+impl<'frame> SoftwareFrame<'frame> {
+    // invokes `f` for every pixel of `dst` covered by `damage`, passing the location
+    // of the pixel relative to `dst` and the pixel of the target
+    fn for_each_pixel(
+        &self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        mut f: impl FnMut(Point<i32, Physical>, &mut u32),
+    ) {
+        let mut image = self.target.0.write().unwrap();
+        let bounds = image.bounds();
+        let output = Rectangle::from_loc_and_size((0, 0), self.size);
+
+        for rect in damage {
+            let rect = Rectangle::from_loc_and_size(rect.loc + dst.loc, rect.size);
+            let Some(rect) = rect.intersection(dst).and_then(|rect| rect.intersection(output)) else {
+                continue;
+            };
+
+            for y in rect.loc.y..rect.loc.y + rect.size.h {
+                for x in rect.loc.x..rect.loc.x + rect.size.w {
+                    let pixel = Rectangle::<i32, Physical>::from_loc_and_size((x, y), (1, 1));
+                    let location = self.transform.transform_rect_in(pixel, &self.size).loc;
+                    let location = Point::<i32, BufferCoord>::from((location.x, location.y));
+                    if !bounds.contains(location) {
+                        continue;
+                    }
+                    let idx = image.index(location);
+                    f(Point::from((x, y)) - dst.loc, &mut image.pixels[idx]);
+                }
+            }
+        }
+    }
+}
+
+impl<'frame> Frame for SoftwareFrame<'frame> {
+    type Error = SoftwareError;
+    type TextureId = SoftwareTexture;
+
+    fn id(&self) -> usize {
+        self.renderer.id
+    }
+
+    fn clear(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>]) -> Result<(), SoftwareError> {
+        let color = pack(color);
+        self.for_each_pixel(Rectangle::from_loc_and_size((0, 0), self.size), at, |_, pixel| {
+            *pixel = color
+        });
+        Ok(())
+    }
+
+    fn draw_solid(
+        &mut self,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        color: [f32; 4],
+    ) -> Result<(), SoftwareError> {
+        self.for_each_pixel(dst, damage, |_, pixel| blend(pixel, color));
+        Ok(())
+    }
+
+    fn render_texture_from_to(
+        &mut self,
+        texture: &SoftwareTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+    ) -> Result<(), SoftwareError> {
+        if dst.size.w <= 0 || dst.size.h <= 0 || src.size.w <= 0.0 || src.size.h <= 0.0 {
+            warn!("Texture/Src is zero sized");
+            return Ok(());
+        }
+
+        let image = texture.image();
+        if image.size.w <= 0 || image.size.h <= 0 {
+            warn!("Texture/Src is zero sized");
+            return Ok(());
+        }
+
+        let src_size = src_transform.invert().transform_size(src.size);
+        let filter = if (dst.size.w as f64) < src_size.w || (dst.size.h as f64) < src_size.h {
+            self.renderer.downscale_filter
+        } else {
+            self.renderer.upscale_filter
+        };
+        let tint = self.renderer.debug_flags.contains(DebugFlags::TINT);
+        let unit = Size::<f64, Logical>::from((1.0, 1.0));
+
+        self.for_each_pixel(dst, damage, |location, pixel| {
+            // sample at the center of the pixel, relative to the (untransformed) destination
+            let point = Point::<f64, Logical>::from((
+                (location.x as f64 + 0.5) / dst.size.w as f64,
+                (location.y as f64 + 0.5) / dst.size.h as f64,
+            ));
+            let point = src_transform.transform_point_in(point, &unit);
+            let point = Point::<f64, BufferCoord>::from((
+                src.loc.x + point.x * src.size.w,
+                src.loc.y + point.y * src.size.h,
+            ));
+
+            let mut color = image.sample(point, filter).map(|channel| channel * alpha);
+            if tint {
+                color = [
+                    color[0] * 0.8,
+                    0.3 + color[1] * 0.8,
+                    color[2] * 0.8,
+                    0.2 + color[3] * 0.8,
+                ];
+            }
+            blend(pixel, color);
+        });
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn finish(self) -> Result<(), SoftwareError> {
+        Ok(())
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    type Error = SoftwareError;
+    type TextureId = SoftwareTexture;
+    type Frame<'frame> = SoftwareFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), SoftwareError> {
+        self.downscale_filter = filter;
+        Ok(())
+    }
+
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), SoftwareError> {
+        self.upscale_filter = filter;
+        Ok(())
+    }
+
+    fn set_debug_flags(&mut self, flags: DebugFlags) {
+        self.debug_flags = flags;
+    }
+
+    fn debug_flags(&self) -> DebugFlags {
+        self.debug_flags
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    fn render(
+        &mut self,
+        output_size: Size<i32, Physical>,
+        dst_transform: Transform,
+    ) -> Result<SoftwareFrame<'_>, SoftwareError> {
+        let target = self.target.clone().ok_or(SoftwareError::NoTarget)?;
+        trace!(target_size = ?target.size(), "Starting frame");
+        Ok(SoftwareFrame {
+            renderer: self,
+            target,
+            size: dst_transform.transform_size(output_size),
+            transform: dst_transform,
+        })
+    }
+}
+
+impl Bind<SoftwareBuffer> for SoftwareRenderer {
+    fn bind(&mut self, target: SoftwareBuffer) -> Result<(), SoftwareError> {
+        self.target = Some(target);
+        Ok(())
+    }
+}
+
+impl Unbind for SoftwareRenderer {
+    fn unbind(&mut self) -> Result<(), SoftwareError> {
+        self.target = None;
+        Ok(())
+    }
+}
+
+impl Offscreen<SoftwareBuffer> for SoftwareRenderer {
+    fn create_buffer(
+        &mut self,
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+    ) -> Result<SoftwareBuffer, SoftwareError> {
+        SoftwareBuffer::new(format, size)
+    }
+}
+
+impl ImportMem for SoftwareRenderer {
+    fn import_memory(
+        &mut self,
+        data: &[u8],
+        format: Fourcc,
+        size: Size<i32, BufferCoord>,
+        flipped: bool,
+    ) -> Result<SoftwareTexture, SoftwareError> {
+        let mut image = Image::new(format, size, flipped)?;
+        image.write(data, size.w as usize * 4, image.bounds())?;
+        Ok(SoftwareTexture(Arc::new(RwLock::new(image))))
+    }
+
+    fn update_memory(
+        &mut self,
+        texture: &SoftwareTexture,
+        data: &[u8],
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), SoftwareError> {
+        let mut image = texture.0.write().unwrap();
+        let stride = image.size.w as usize * 4;
+        image.write(data, stride, region)
+    }
+
+    fn mem_formats(&self) -> Box<dyn Iterator<Item = Fourcc>> {
+        Box::new(SUPPORTED_FORMATS.iter().copied())
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportMemWl for SoftwareRenderer {
+    fn import_shm_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        _surface: Option<&crate::wayland::compositor::SurfaceData>,
+        _damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<SoftwareTexture, SoftwareError> {
+        use crate::wayland::shm::{shm_format_to_fourcc, with_buffer_contents};
+
+        with_buffer_contents(buffer, |ptr, len, data| {
+            let format = shm_format_to_fourcc(data.format)
+                .ok_or(SoftwareError::UnsupportedWlPixelFormat(data.format))?;
+            if !SUPPORTED_FORMATS.contains(&format) {
+                return Err(SoftwareError::UnsupportedWlPixelFormat(data.format));
+            }
+
+            let offset = data.offset as usize;
+            if offset > len {
+                return Err(SoftwareError::UnexpectedSize);
+            }
+            // SAFETY: the pool is mapped for `len` bytes during this callback
+            let memory = unsafe { std::slice::from_raw_parts(ptr.add(offset), len - offset) };
+
+            let mut image = Image::new(format, (data.width, data.height).into(), false)?;
+            image.write(memory, data.stride as usize, image.bounds())?;
+            Ok(SoftwareTexture(Arc::new(RwLock::new(image))))
+        })
+        .map_err(SoftwareError::BufferAccessError)?
+    }
+}
+
+impl ImportDma for SoftwareRenderer {
+    fn import_dmabuf(
+        &mut self,
+        _dmabuf: &Dmabuf,
+        _damage: Option<&[Rectangle<i32, BufferCoord>]>,
+    ) -> Result<SoftwareTexture, SoftwareError> {
+        Err(SoftwareError::DmabufUnsupported)
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl ImportDmaWl for SoftwareRenderer {}
+
+#[cfg(all(
+    feature = "wayland_frontend",
+    feature = "backend_egl",
+    feature = "use_system_lib"
+))]
+impl ImportEgl for SoftwareRenderer {
+    fn bind_wl_display(
+        &mut self,
+        _display: &wayland_server::DisplayHandle,
+    ) -> Result<(), crate::backend::egl::Error> {
+        Err(crate::backend::egl::Error::EglExtensionNotSupported(&[
+            "EGL_WL_bind_wayland_display",
+        ]))
+    }
+
+    fn unbind_wl_display(&mut self) {}
+
+    fn egl_reader(&self) -> Option<&EGLBufferReader> {
+        None
+    }
+
+    fn import_egl_buffer(
+        &mut self,
+        _buffer: &wl_buffer::WlBuffer,
+        _surface: Option<&crate::wayland::compositor::SurfaceData>,
+        _damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<SoftwareTexture, SoftwareError> {
+        Err(SoftwareError::UnsupportedPixelFormat(Fourcc::Argb8888))
+    }
+}
+
+impl ExportMem for SoftwareRenderer {
+    type TextureMapping = SoftwareMapping;
+
+    fn copy_framebuffer(
+        &mut self,
+        region: Rectangle<i32, BufferCoord>,
+        format: Fourcc,
+    ) -> Result<SoftwareMapping, SoftwareError> {
+        let target = self.target.as_ref().ok_or(SoftwareError::NoTarget)?;
+        let data = target.0.read().unwrap().read(region, format)?;
+        Ok(SoftwareMapping {
+            size: region.size,
+            format,
+            data,
+        })
+    }
+
+    fn copy_texture(
+        &mut self,
+        texture: &SoftwareTexture,
+        region: Rectangle<i32, BufferCoord>,
+        format: Fourcc,
+    ) -> Result<SoftwareMapping, SoftwareError> {
+        let image = texture.image();
+        let mut data = image.read(region, format)?;
+        if image.flipped {
+            // mappings are never flipped
+            let stride = region.size.w.max(0) as usize * 4;
+            let rows = data.chunks(stride.max(1)).rev().flatten().copied().collect();
+            data = rows;
+        }
+        Ok(SoftwareMapping {
+            size: region.size,
+            format,
+            data,
+        })
+    }
+
+    fn map_texture<'a>(&mut self, texture_mapping: &'a SoftwareMapping) -> Result<&'a [u8], SoftwareError> {
+        Ok(&texture_mapping.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(
+        renderer: &mut SoftwareRenderer,
+        size: Size<i32, Physical>,
+        transform: Transform,
+        f: impl FnOnce(&mut SoftwareFrame<'_>),
+    ) -> SoftwareBuffer {
+        let buffer_size = transform.transform_size(size);
+        let buffer = renderer
+            .create_buffer(Fourcc::Argb8888, (buffer_size.w, buffer_size.h).into())
+            .unwrap();
+        renderer.bind(buffer.clone()).unwrap();
+        let mut frame = renderer.render(buffer_size, transform).unwrap();
+        f(&mut frame);
+        frame.finish().unwrap();
+        buffer
+    }
+
+    #[test]
+    fn clear_and_draw_solid() {
+        let mut renderer = SoftwareRenderer::new();
+        let buffer = render(&mut renderer, (4, 4).into(), Transform::Normal, |frame| {
+            frame
+                .clear(
+                    [0.0, 0.0, 0.0, 1.0],
+                    &[Rectangle::from_loc_and_size((0, 0), (4, 4))],
+                )
+                .unwrap();
+            frame
+                .draw_solid(
+                    Rectangle::from_loc_and_size((1, 1), (2, 2)),
+                    &[Rectangle::from_loc_and_size((0, 0), (1, 2))],
+                    [0.5, 0.0, 0.0, 0.5],
+                )
+                .unwrap();
+        });
+
+        assert_eq!(buffer.pixel((0, 0).into()), Some(0xff000000));
+        assert_eq!(buffer.pixel((1, 1).into()), Some(0xff800000));
+        assert_eq!(buffer.pixel((1, 2).into()), Some(0xff800000));
+        // outside of the damage
+        assert_eq!(buffer.pixel((2, 1).into()), Some(0xff000000));
+    }
+
+    #[test]
+    fn render_transformed_texture() {
+        let mut renderer = SoftwareRenderer::new();
+        renderer.upscale_filter(TextureFilter::Nearest).unwrap();
+        // 2x1 texture, red on the left and blue on the right
+        let data = [0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0xff];
+        let texture = renderer
+            .import_memory(&data, Fourcc::Argb8888, (2, 1).into(), false)
+            .unwrap();
+
+        // the texture is rotated into the portrait destination according to the transform
+        let buffer = render(&mut renderer, (1, 2).into(), Transform::Normal, |frame| {
+            frame
+                .render_texture_from_to(
+                    &texture,
+                    Rectangle::from_loc_and_size((0.0, 0.0), (2.0, 1.0)),
+                    Rectangle::from_loc_and_size((0, 0), (1, 2)),
+                    &[Rectangle::from_loc_and_size((0, 0), (1, 2))],
+                    Transform::_90,
+                    1.0,
+                )
+                .unwrap();
+        });
+        assert_eq!(buffer.pixels(), vec![0xff0000ff, 0xffff0000]);
+    }
+
+    #[test]
+    fn export_formats() {
+        let mut renderer = SoftwareRenderer::new();
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let texture = renderer
+            .import_memory(&data, Fourcc::Abgr8888, (1, 1).into(), false)
+            .unwrap();
+
+        let mapping = renderer
+            .copy_texture(
+                &texture,
+                Rectangle::from_loc_and_size((0, 0), (1, 1)),
+                Fourcc::Argb8888,
+            )
+            .unwrap();
+        assert_eq!(renderer.map_texture(&mapping).unwrap(), &[0x03, 0x02, 0x01, 0x04]);
+
+        let texture = renderer
+            .import_memory(&data, Fourcc::Xrgb8888, (1, 1).into(), false)
+            .unwrap();
+        let mapping = renderer
+            .copy_texture(
+                &texture,
+                Rectangle::from_loc_and_size((0, 0), (1, 1)),
+                Fourcc::Argb8888,
+            )
+            .unwrap();
+        assert_eq!(renderer.map_texture(&mapping).unwrap(), &[0x01, 0x02, 0x03, 0xff]);
+    }
+}
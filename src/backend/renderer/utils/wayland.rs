@@ -1,16 +1,20 @@
 use crate::{
     backend::renderer::{buffer_dimensions, buffer_has_alpha, element::RenderElement, ImportAll, Renderer},
-    utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+    utils::{
+        Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Region, Scale, Size,
+        Transform,
+    },
     wayland::{
         compositor::{
             self, add_destruction_hook, is_sync_subsurface, with_surface_tree_downward,
-            with_surface_tree_upward, BufferAssignment, Damage, RectangleKind, SubsurfaceCachedState,
+            with_surface_tree_upward, BufferAssignment, Damage, RegionAttributes, SubsurfaceCachedState,
             SurfaceAttributes, SurfaceData, TraversalAction,
         },
+        explicit_synchronization::{BufferRelease, ExplicitSyncCachedState},
         viewporter,
     },
 };
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{
     any::TypeId,
     cell::RefCell,
@@ -44,17 +48,24 @@ pub struct RendererSurfaceState {
     pub(crate) renderer_seen: HashMap<(TypeId, usize), CommitCounter>,
     pub(crate) textures: HashMap<(TypeId, usize), Box<dyn std::any::Any>>,
     pub(crate) surface_view: Option<SurfaceView>,
-    pub(crate) opaque_regions: Vec<Rectangle<i32, Logical>>,
+    pub(crate) opaque_regions: Region<i32, Logical>,
+    pub(crate) input_region: Option<Region<i32, Logical>>,
 
     accumulated_buffer_delta: Point<i32, Logical>,
 }
 
 #[derive(Debug)]
-struct InnerBuffer(WlBuffer);
+struct InnerBuffer {
+    buffer: WlBuffer,
+    release: Mutex<Option<BufferRelease>>,
+}
 
 impl Drop for InnerBuffer {
     fn drop(&mut self) {
-        self.0.release();
+        self.buffer.release();
+        if let Some(release) = self.release.get_mut().unwrap().take() {
+            release.immediate_release();
+        }
     }
 }
 
@@ -67,7 +78,22 @@ pub struct Buffer {
 impl From<WlBuffer> for Buffer {
     fn from(buffer: WlBuffer) -> Self {
         Buffer {
-            inner: Arc::new(InnerBuffer(buffer)),
+            inner: Arc::new(InnerBuffer {
+                buffer,
+                release: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl Buffer {
+    /// Attach an explicit release object to this buffer
+    ///
+    /// The release object will be signalled once the buffer is released.
+    /// A previously attached release object belongs to an older commit and is released immediately.
+    fn set_release(&self, release: BufferRelease) {
+        if let Some(old) = self.inner.release.lock().unwrap().replace(release) {
+            old.immediate_release();
         }
     }
 }
@@ -76,19 +102,19 @@ impl std::ops::Deref for Buffer {
     type Target = WlBuffer;
 
     fn deref(&self) -> &Self::Target {
-        &self.inner.0
+        &self.inner.buffer
     }
 }
 
 impl PartialEq<WlBuffer> for Buffer {
     fn eq(&self, other: &WlBuffer) -> bool {
-        self.inner.0 == *other
+        self.inner.buffer == *other
     }
 }
 
 impl PartialEq<WlBuffer> for &Buffer {
     fn eq(&self, other: &WlBuffer) -> bool {
-        self.inner.0 == *other
+        self.inner.buffer == *other
     }
 }
 
@@ -100,6 +126,7 @@ impl RendererSurfaceState {
         if let Some(delta) = self.buffer_delta {
             self.accumulated_buffer_delta += delta;
         }
+        self.input_region = attrs.input_region.as_ref().map(RegionAttributes::to_region);
 
         match attrs.buffer.take() {
             Some(BufferAssignment::NewBuffer(buffer)) => {
@@ -118,6 +145,14 @@ impl RendererSurfaceState {
                 if !self.buffer.as_ref().map_or(false, |b| b == buffer) {
                     self.buffer = Some(Buffer::from(buffer));
                 }
+                if let Some(release) = states
+                    .cached_state
+                    .current::<ExplicitSyncCachedState>()
+                    .release
+                    .take()
+                {
+                    self.buffer.as_ref().unwrap().set_release(release);
+                }
 
                 self.textures.clear();
 
@@ -149,66 +184,58 @@ impl RendererSurfaceState {
                 buffer_damage.dedup();
                 self.damage.add(buffer_damage);
 
-                self.opaque_regions.clear();
-                if !self.buffer_has_alpha.unwrap_or(true) {
-                    self.opaque_regions.push(Rectangle::from_loc_and_size(
-                        (0, 0),
-                        self.surface_view.unwrap().dst,
-                    ))
-                } else if let Some(region_attributes) = &attrs.opaque_region {
-                    let opaque_regions = region_attributes
-                        .rects
-                        .iter()
-                        .map(|(kind, rect)| {
-                            let dest_size = self.surface_view.unwrap().dst;
-
-                            let rect_constrained_loc = rect
-                                .loc
-                                .constrain(Rectangle::from_extemities((0, 0), dest_size.to_point()));
-                            let rect_clamped_size = rect
-                                .size
-                                .clamp((0, 0), (dest_size.to_point() - rect_constrained_loc).to_size());
-
-                            let rect = Rectangle::from_loc_and_size(rect_constrained_loc, rect_clamped_size);
-
-                            (kind, rect)
-                        })
-                        .fold(
-                            std::mem::take(&mut self.opaque_regions),
-                            |mut new_regions, (kind, rect)| {
-                                match kind {
-                                    RectangleKind::Add => {
-                                        let added_regions = new_regions
-                                            .iter()
-                                            .filter(|region| region.overlaps_or_touches(rect))
-                                            .fold(vec![rect], |new_regions, existing_region| {
-                                                new_regions
-                                                    .into_iter()
-                                                    .flat_map(|region| region.subtract_rect(*existing_region))
-                                                    .collect::<Vec<_>>()
-                                            });
-                                        new_regions.extend(added_regions);
-                                    }
-                                    RectangleKind::Subtract => {
-                                        new_regions = new_regions
-                                            .into_iter()
-                                            .flat_map(|r| r.subtract_rect(rect))
-                                            .collect::<Vec<_>>();
-                                    }
-                                }
-
-                                new_regions
-                            },
-                        );
-
-                    self.opaque_regions = opaque_regions;
-                }
+                self.update_opaque_regions(attrs.opaque_region.as_ref());
             }
             Some(BufferAssignment::Removed) => {
                 // remove the contents
                 self.reset();
             }
-            None => {}
+            None => {
+                // No new contents, but the client might have changed the buffer scale or transform,
+                // the viewport or the subsurface position, which all influence the surface view.
+                let Some(buffer_dimensions) = self.buffer_dimensions else {
+                    return;
+                };
+
+                let buffer_transform: Transform = attrs.buffer_transform.into();
+                let transform_changed =
+                    self.buffer_scale != attrs.buffer_scale || self.buffer_transform != buffer_transform;
+                self.buffer_scale = attrs.buffer_scale;
+                self.buffer_transform = buffer_transform;
+
+                let surface_size = buffer_dimensions.to_logical(self.buffer_scale, self.buffer_transform);
+                let surface_view = SurfaceView::from_states(states, surface_size);
+                let view_changed = self
+                    .surface_view
+                    .map(|view| view.src != surface_view.src || view.dst != surface_view.dst)
+                    .unwrap_or(true);
+                self.surface_view = Some(surface_view);
+
+                if transform_changed || view_changed {
+                    // the whole content is displayed differently now
+                    self.damage
+                        .add([Rectangle::from_loc_and_size((0, 0), buffer_dimensions)]);
+                    self.update_opaque_regions(attrs.opaque_region.as_ref());
+                }
+            }
+        }
+    }
+
+    fn update_opaque_regions(&mut self, opaque_region: Option<&RegionAttributes>) {
+        let Some(surface_view) = self.surface_view else {
+            self.opaque_regions = Region::new();
+            return;
+        };
+        let surface_rect = Rectangle::from_loc_and_size((0, 0), surface_view.dst);
+
+        if !self.buffer_has_alpha.unwrap_or(true) {
+            self.opaque_regions = Region::from(surface_rect);
+        } else if let Some(region_attributes) = opaque_region {
+            let mut opaque_regions = region_attributes.to_region();
+            opaque_regions.intersect_rect(surface_rect);
+            self.opaque_regions = opaque_regions;
+        } else {
+            self.opaque_regions = Region::new();
         }
     }
 
@@ -291,7 +318,7 @@ impl RendererSurfaceState {
             return None;
         }
 
-        Some(&self.opaque_regions[..])
+        Some(self.opaque_regions.rects())
     }
 
     /// Gets the [`SurfaceView`] of this surface
@@ -299,6 +326,21 @@ impl RendererSurfaceState {
         self.surface_view
     }
 
+    /// Gets the [`SurfaceGeometry`] of this surface
+    ///
+    /// Returns `None` if no buffer is attached to the surface.
+    pub fn geometry(&self) -> Option<SurfaceGeometry> {
+        let buffer_size = self.buffer_dimensions?;
+        let view = self.surface_view?;
+        Some(SurfaceGeometry {
+            buffer_size,
+            buffer_scale: self.buffer_scale,
+            buffer_transform: self.buffer_transform,
+            src: view.src,
+            size: view.dst,
+        })
+    }
+
     fn reset(&mut self) {
         self.buffer_dimensions = None;
         self.buffer = None;
@@ -306,7 +348,7 @@ impl RendererSurfaceState {
         self.damage.reset();
         self.surface_view = None;
         self.buffer_has_alpha = None;
-        self.opaque_regions.clear();
+        self.opaque_regions = Region::new();
     }
 }
 
@@ -404,6 +446,111 @@ impl SurfaceView {
     }
 }
 
+/// Mapping between the surface-local and the buffer coordinate space of a surface
+///
+/// This combines the buffer transform and buffer scale of the attached buffer with the
+/// source rectangle and destination size of an optional viewport. It can be used by renderers
+/// to find the part of the buffer to sample and to transform input coordinates into buffer
+/// coordinates (and back).
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SurfaceGeometry {
+    /// The size of the attached buffer in buffer coordinates
+    pub buffer_size: Size<i32, BufferCoord>,
+    /// The buffer scale of the surface
+    pub buffer_scale: i32,
+    /// The buffer transform of the surface
+    pub buffer_transform: Transform,
+    /// The part of the buffer shown by the surface, in logical coordinates of the buffer
+    ///
+    /// This is the source rectangle of the viewport, or the whole buffer.
+    pub src: Rectangle<f64, Logical>,
+    /// The logical size of the surface
+    ///
+    /// This is the destination size of the viewport, or the logical size of the buffer.
+    pub size: Size<i32, Logical>,
+}
+
+impl SurfaceGeometry {
+    /// Logical size of the attached buffer, with buffer scale and transform applied
+    pub fn buffer_logical_size(&self) -> Size<i32, Logical> {
+        self.buffer_size
+            .to_logical(self.buffer_scale, self.buffer_transform)
+    }
+
+    /// The part of the buffer shown by the surface in buffer coordinates
+    ///
+    /// This is the source rectangle to sample from when rendering the surface.
+    pub fn src_buffer(&self) -> Rectangle<f64, BufferCoord> {
+        self.src.to_buffer(
+            self.buffer_scale as f64,
+            self.buffer_transform,
+            &self.buffer_logical_size().to_f64(),
+        )
+    }
+
+    /// Convert a point from surface-local coordinates to buffer coordinates
+    pub fn point_to_buffer(&self, point: Point<f64, Logical>) -> Point<f64, BufferCoord> {
+        let point = point.downscale(self.viewport_scale()) + self.src.loc;
+        point.to_buffer(
+            self.buffer_scale as f64,
+            self.buffer_transform,
+            &self.buffer_logical_size().to_f64(),
+        )
+    }
+
+    /// Convert a point from buffer coordinates to surface-local coordinates
+    pub fn point_to_surface(&self, point: Point<f64, BufferCoord>) -> Point<f64, Logical> {
+        let point = point.to_logical(
+            self.buffer_scale as f64,
+            self.buffer_transform,
+            &self.buffer_size.to_f64(),
+        ) - self.src.loc;
+        point.upscale(self.viewport_scale())
+    }
+
+    /// Convert a rectangle from surface-local coordinates to buffer coordinates
+    pub fn rect_to_buffer(&self, rect: Rectangle<f64, Logical>) -> Rectangle<f64, BufferCoord> {
+        let mut rect = rect.downscale(self.viewport_scale());
+        rect.loc += self.src.loc;
+        rect.to_buffer(
+            self.buffer_scale as f64,
+            self.buffer_transform,
+            &self.buffer_logical_size().to_f64(),
+        )
+    }
+
+    /// Convert a rectangle from buffer coordinates to surface-local coordinates
+    pub fn rect_to_surface(&self, rect: Rectangle<f64, BufferCoord>) -> Rectangle<f64, Logical> {
+        let mut rect = rect.to_logical(
+            self.buffer_scale as f64,
+            self.buffer_transform,
+            &self.buffer_size.to_f64(),
+        );
+        rect.loc -= self.src.loc;
+        rect.upscale(self.viewport_scale())
+    }
+
+    fn viewport_scale(&self) -> Scale<f64> {
+        Scale::from((
+            self.size.w as f64 / self.src.size.w,
+            self.size.h as f64 / self.src.size.h,
+        ))
+    }
+}
+
+/// Retrieve the [`SurfaceGeometry`] of a surface
+///
+/// Returns `None` if the surface has no buffer attached or if buffer management
+/// is not handled by [`on_commit_buffer_handler`].
+pub fn surface_geometry(surface: &WlSurface) -> Option<SurfaceGeometry> {
+    compositor::with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RendererSurfaceStateUserData>()
+            .and_then(|data| data.borrow().geometry())
+    })
+}
+
 /// Access the buffer related states associated to this surface
 ///
 /// Calls [`compositor::with_states`] internally
@@ -540,40 +687,23 @@ where
     let scale = scale.into();
 
     let mut render_elements: Vec<&E> = Vec::with_capacity(elements.len());
-    let mut opaque_regions: Vec<Rectangle<i32, Physical>> = Vec::new();
+    let mut opaque_regions: Region<i32, Physical> = Region::new();
     let mut render_damage: Vec<Rectangle<i32, Physical>> = Vec::with_capacity(damage.len());
 
     for element in elements {
         let element_geometry = element.geometry(scale);
 
         // Then test if the element is completely hidden behind opaque regions
-        let is_hidden = opaque_regions
-            .iter()
-            .fold([element_geometry].to_vec(), |geometry, opaque_region| {
-                geometry
-                    .into_iter()
-                    .flat_map(|g| g.subtract_rect(*opaque_region))
-                    .collect::<Vec<_>>()
-            })
-            .is_empty();
-
-        if is_hidden {
+        if opaque_regions.contains_rect(element_geometry) {
             // No need to draw a completely hidden element
             continue;
         }
 
-        let damage = opaque_regions
-            .iter()
-            .fold(damage.to_vec(), |damage, opaque_region| {
-                damage
-                    .into_iter()
-                    .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                    .collect::<Vec<_>>()
-            });
-
-        render_damage.extend(damage);
+        let mut element_damage = Region::from_rects(damage.iter().copied());
+        element_damage.subtract(&opaque_regions);
+        render_damage.extend(element_damage.into_rects());
 
-        opaque_regions.extend(element.opaque_regions(scale).into_iter().map(|mut region| {
+        opaque_regions.union_rects(element.opaque_regions(scale).into_iter().map(|mut region| {
             region.loc += element_geometry.loc;
             region
         }));
@@ -0,0 +1,671 @@
+//! Text format of input recordings
+//!
+//! A recording starts with a header line, followed by one line per device declaration or event.
+//! Tokens are separated by spaces, strings are percent-encoded and missing values are written
+//! as `-`:
+//!
+//! ```text
+//! smithay-input-recording 1
+//! device <id> <name> <capabilities> <vendor:product> <syspath>
+//! <time> <device id> <kind> <fields...>
+//! ```
+//!
+//! Devices are declared before the first event referring to them. Device added and removed
+//! events carry no timestamp.
+
+use std::{fmt::Write as _, io, path::PathBuf, str::FromStr};
+
+use crate::backend::input::{
+    AxisRelativeDirection, AxisSource, ButtonState, DeviceCapability, KeyState, Switch, SwitchState,
+};
+
+use super::ReplayDevice;
+
+pub(super) const HEADER: &str = "smithay-input-recording 1";
+
+/// Location of an absolute position event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Position {
+    pub(super) x: f64,
+    pub(super) y: f64,
+    // position relative to the extent of the device, transformed positions scale linearly
+    pub(super) x_fraction: f64,
+    pub(super) y_fraction: f64,
+}
+
+/// Data of an axis event, indexed by horizontal (0) and vertical (1) axis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct AxisData {
+    pub(super) source: AxisSource,
+    pub(super) amount: [Option<f64>; 2],
+    pub(super) discrete: [Option<f64>; 2],
+    pub(super) v120: [Option<f64>; 2],
+    pub(super) relative_direction: [AxisRelativeDirection; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum EventKind {
+    DeviceAdded,
+    DeviceRemoved,
+    Keyboard {
+        key: u32,
+        state: KeyState,
+        count: u32,
+    },
+    PointerMotion {
+        delta: (f64, f64),
+        delta_unaccel: (f64, f64),
+    },
+    PointerMotionAbsolute(Position),
+    PointerButton {
+        button: u32,
+        state: ButtonState,
+    },
+    PointerAxis(AxisData),
+    GestureSwipeBegin {
+        fingers: u32,
+    },
+    GestureSwipeUpdate {
+        delta: (f64, f64),
+    },
+    GestureSwipeEnd {
+        cancelled: bool,
+    },
+    GesturePinchBegin {
+        fingers: u32,
+    },
+    GesturePinchUpdate {
+        delta: (f64, f64),
+        scale: f64,
+        rotation: f64,
+    },
+    GesturePinchEnd {
+        cancelled: bool,
+    },
+    GestureHoldBegin {
+        fingers: u32,
+    },
+    GestureHoldEnd {
+        cancelled: bool,
+    },
+    TouchDown {
+        slot: Option<u32>,
+        position: Position,
+    },
+    TouchMotion {
+        slot: Option<u32>,
+        position: Position,
+    },
+    TouchUp {
+        slot: Option<u32>,
+    },
+    TouchCancel {
+        slot: Option<u32>,
+    },
+    TouchFrame,
+    SwitchToggle {
+        switch: Switch,
+        state: SwitchState,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Line {
+    Device(ReplayDevice),
+    Event {
+        time: Option<u64>,
+        device: String,
+        kind: EventKind,
+    },
+}
+
+const CAPABILITIES: &[(DeviceCapability, &str)] = &[
+    (DeviceCapability::Keyboard, "keyboard"),
+    (DeviceCapability::Pointer, "pointer"),
+    (DeviceCapability::Touch, "touch"),
+    (DeviceCapability::TabletTool, "tablet-tool"),
+    (DeviceCapability::TabletPad, "tablet-pad"),
+    (DeviceCapability::Gesture, "gesture"),
+    (DeviceCapability::Switch, "switch"),
+];
+
+pub(super) fn all_capabilities() -> impl Iterator<Item = DeviceCapability> {
+    CAPABILITIES.iter().map(|(capability, _)| *capability)
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn encode_str(value: &str, out: &mut String) {
+    if value.is_empty() {
+        out.push('%');
+        return;
+    }
+    for c in value.chars() {
+        if c == '%' || (c == '-' && value.len() == 1) || c.is_ascii_whitespace() || c.is_ascii_control() {
+            let _ = write!(out, "%{:02x}", c as u8);
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+fn decode_str(token: &str) -> io::Result<String> {
+    if token == "%" {
+        return Ok(String::new());
+    }
+    let mut bytes = Vec::with_capacity(token.len());
+    let mut iter = token.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [
+                iter.next().ok_or_else(|| invalid("truncated escape"))?,
+                iter.next().ok_or_else(|| invalid("truncated escape"))?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid("invalid escape"))?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid("invalid escape"))?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid("invalid utf-8 in string"))
+}
+
+struct Writer(String);
+
+impl Writer {
+    fn token(&mut self, token: impl std::fmt::Display) -> &mut Self {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = write!(self.0, "{}", token);
+        self
+    }
+
+    fn string(&mut self, value: &str) -> &mut Self {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        encode_str(value, &mut self.0);
+        self
+    }
+
+    fn opt(&mut self, value: Option<impl std::fmt::Display>) -> &mut Self {
+        match value {
+            Some(value) => self.token(value),
+            None => self.token("-"),
+        }
+    }
+
+    fn point(&mut self, (x, y): (f64, f64)) -> &mut Self {
+        self.token(x).token(y)
+    }
+
+    fn position(&mut self, position: &Position) -> &mut Self {
+        self.token(position.x)
+            .token(position.y)
+            .token(position.x_fraction)
+            .token(position.y_fraction)
+    }
+}
+
+struct Reader<'a>(std::str::SplitWhitespace<'a>);
+
+impl<'a> Reader<'a> {
+    fn token(&mut self) -> io::Result<&'a str> {
+        self.0.next().ok_or_else(|| invalid("missing field"))
+    }
+
+    fn parse<T: FromStr>(&mut self) -> io::Result<T> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| invalid(format!("invalid value `{}`", token)))
+    }
+
+    fn opt<T: FromStr>(&mut self) -> io::Result<Option<T>> {
+        match self.token()? {
+            "-" => Ok(None),
+            token => token
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid(format!("invalid value `{}`", token))),
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        decode_str(self.token()?)
+    }
+
+    fn point(&mut self) -> io::Result<(f64, f64)> {
+        Ok((self.parse()?, self.parse()?))
+    }
+
+    fn position(&mut self) -> io::Result<Position> {
+        Ok(Position {
+            x: self.parse()?,
+            y: self.parse()?,
+            x_fraction: self.parse()?,
+            y_fraction: self.parse()?,
+        })
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.0.next() {
+            Some(token) => Err(invalid(format!("unexpected trailing `{}`", token))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn key_state(state: KeyState) -> &'static str {
+    match state {
+        KeyState::Pressed => "pressed",
+        KeyState::Released => "released",
+    }
+}
+
+fn parse_key_state(token: &str) -> io::Result<KeyState> {
+    match token {
+        "pressed" => Ok(KeyState::Pressed),
+        "released" => Ok(KeyState::Released),
+        _ => Err(invalid(format!("invalid key state `{}`", token))),
+    }
+}
+
+fn button_state(state: ButtonState) -> &'static str {
+    match state {
+        ButtonState::Pressed => "pressed",
+        ButtonState::Released => "released",
+    }
+}
+
+fn parse_button_state(token: &str) -> io::Result<ButtonState> {
+    match token {
+        "pressed" => Ok(ButtonState::Pressed),
+        "released" => Ok(ButtonState::Released),
+        _ => Err(invalid(format!("invalid button state `{}`", token))),
+    }
+}
+
+fn switch(switch: Switch) -> &'static str {
+    match switch {
+        Switch::Lid => "lid",
+        Switch::TabletMode => "tablet-mode",
+    }
+}
+
+fn parse_switch(token: &str) -> io::Result<Switch> {
+    match token {
+        "lid" => Ok(Switch::Lid),
+        "tablet-mode" => Ok(Switch::TabletMode),
+        _ => Err(invalid(format!("invalid switch `{}`", token))),
+    }
+}
+
+fn switch_state(state: SwitchState) -> &'static str {
+    match state {
+        SwitchState::Off => "off",
+        SwitchState::On => "on",
+    }
+}
+
+fn parse_switch_state(token: &str) -> io::Result<SwitchState> {
+    match token {
+        "off" => Ok(SwitchState::Off),
+        "on" => Ok(SwitchState::On),
+        _ => Err(invalid(format!("invalid switch state `{}`", token))),
+    }
+}
+
+fn axis_source(source: AxisSource) -> &'static str {
+    match source {
+        AxisSource::Finger => "finger",
+        AxisSource::Continuous => "continuous",
+        AxisSource::Wheel => "wheel",
+        AxisSource::WheelTilt => "wheel-tilt",
+    }
+}
+
+fn parse_axis_source(token: &str) -> io::Result<AxisSource> {
+    match token {
+        "finger" => Ok(AxisSource::Finger),
+        "continuous" => Ok(AxisSource::Continuous),
+        "wheel" => Ok(AxisSource::Wheel),
+        "wheel-tilt" => Ok(AxisSource::WheelTilt),
+        _ => Err(invalid(format!("invalid axis source `{}`", token))),
+    }
+}
+
+fn relative_direction(direction: AxisRelativeDirection) -> &'static str {
+    match direction {
+        AxisRelativeDirection::Identical => "identical",
+        AxisRelativeDirection::Inverted => "inverted",
+    }
+}
+
+fn parse_relative_direction(token: &str) -> io::Result<AxisRelativeDirection> {
+    match token {
+        "identical" => Ok(AxisRelativeDirection::Identical),
+        "inverted" => Ok(AxisRelativeDirection::Inverted),
+        _ => Err(invalid(format!("invalid relative direction `{}`", token))),
+    }
+}
+
+impl Line {
+    pub(super) fn encode(&self) -> String {
+        let mut w = Writer(String::new());
+        match self {
+            Line::Device(device) => {
+                let capabilities = CAPABILITIES
+                    .iter()
+                    .filter(|(capability, _)| device.capabilities.contains(capability))
+                    .map(|(_, name)| *name)
+                    .collect::<Vec<_>>();
+                w.token("device").string(&device.id).string(&device.name);
+                if capabilities.is_empty() {
+                    w.token("-");
+                } else {
+                    w.token(capabilities.join(","));
+                }
+                w.opt(
+                    device
+                        .usb_id
+                        .map(|(vendor, product)| format!("{}:{}", vendor, product)),
+                );
+                match &device.syspath {
+                    Some(path) => w.string(&path.to_string_lossy()),
+                    None => w.token("-"),
+                };
+            }
+            Line::Event { time, device, kind } => {
+                w.opt(*time).string(device);
+                match kind {
+                    EventKind::DeviceAdded => w.token("device-added"),
+                    EventKind::DeviceRemoved => w.token("device-removed"),
+                    EventKind::Keyboard { key, state, count } => {
+                        w.token("key").token(key).token(key_state(*state)).token(count)
+                    }
+                    EventKind::PointerMotion { delta, delta_unaccel } => {
+                        w.token("pointer-motion").point(*delta).point(*delta_unaccel)
+                    }
+                    EventKind::PointerMotionAbsolute(position) => {
+                        w.token("pointer-motion-absolute").position(position)
+                    }
+                    EventKind::PointerButton { button, state } => w
+                        .token("pointer-button")
+                        .token(button)
+                        .token(button_state(*state)),
+                    EventKind::PointerAxis(axis) => {
+                        w.token("pointer-axis").token(axis_source(axis.source));
+                        for i in 0..2 {
+                            w.opt(axis.amount[i])
+                                .opt(axis.discrete[i])
+                                .opt(axis.v120[i])
+                                .token(relative_direction(axis.relative_direction[i]));
+                        }
+                        &mut w
+                    }
+                    EventKind::GestureSwipeBegin { fingers } => w.token("swipe-begin").token(fingers),
+                    EventKind::GestureSwipeUpdate { delta } => w.token("swipe-update").point(*delta),
+                    EventKind::GestureSwipeEnd { cancelled } => w.token("swipe-end").token(cancelled),
+                    EventKind::GesturePinchBegin { fingers } => w.token("pinch-begin").token(fingers),
+                    EventKind::GesturePinchUpdate {
+                        delta,
+                        scale,
+                        rotation,
+                    } => w.token("pinch-update").point(*delta).token(scale).token(rotation),
+                    EventKind::GesturePinchEnd { cancelled } => w.token("pinch-end").token(cancelled),
+                    EventKind::GestureHoldBegin { fingers } => w.token("hold-begin").token(fingers),
+                    EventKind::GestureHoldEnd { cancelled } => w.token("hold-end").token(cancelled),
+                    EventKind::TouchDown { slot, position } => {
+                        w.token("touch-down").opt(*slot).position(position)
+                    }
+                    EventKind::TouchMotion { slot, position } => {
+                        w.token("touch-motion").opt(*slot).position(position)
+                    }
+                    EventKind::TouchUp { slot } => w.token("touch-up").opt(*slot),
+                    EventKind::TouchCancel { slot } => w.token("touch-cancel").opt(*slot),
+                    EventKind::TouchFrame => w.token("touch-frame"),
+                    EventKind::SwitchToggle { switch: s, state } => w
+                        .token("switch-toggle")
+                        .token(switch(*s))
+                        .token(switch_state(*state)),
+                };
+            }
+        }
+        w.0
+    }
+
+    pub(super) fn decode(line: &str) -> io::Result<Line> {
+        let mut r = Reader(line.split_whitespace());
+        let first = r.token()?;
+        if first == "device" {
+            let id = r.string()?;
+            let name = r.string()?;
+            let capabilities = match r.token()? {
+                "-" => Vec::new(),
+                list => list
+                    .split(',')
+                    .map(|name| {
+                        CAPABILITIES
+                            .iter()
+                            .find(|(_, n)| *n == name)
+                            .map(|(capability, _)| *capability)
+                            .ok_or_else(|| invalid(format!("invalid capability `{}`", name)))
+                    })
+                    .collect::<io::Result<_>>()?,
+            };
+            let usb_id = match r.token()? {
+                "-" => None,
+                token => {
+                    let (vendor, product) = token
+                        .split_once(':')
+                        .ok_or_else(|| invalid(format!("invalid usb id `{}`", token)))?;
+                    Some((
+                        vendor.parse().map_err(|_| invalid("invalid usb vendor id"))?,
+                        product.parse().map_err(|_| invalid("invalid usb product id"))?,
+                    ))
+                }
+            };
+            let syspath = match r.token()? {
+                "-" => None,
+                token => Some(PathBuf::from(decode_str(token)?)),
+            };
+            r.finish()?;
+            return Ok(Line::Device(ReplayDevice {
+                id,
+                name,
+                capabilities,
+                usb_id,
+                syspath,
+            }));
+        }
+
+        let time = match first {
+            "-" => None,
+            token => Some(
+                token
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid timestamp `{}`", token)))?,
+            ),
+        };
+        let device = r.string()?;
+        let kind = match r.token()? {
+            "device-added" => EventKind::DeviceAdded,
+            "device-removed" => EventKind::DeviceRemoved,
+            "key" => EventKind::Keyboard {
+                key: r.parse()?,
+                state: parse_key_state(r.token()?)?,
+                count: r.parse()?,
+            },
+            "pointer-motion" => EventKind::PointerMotion {
+                delta: r.point()?,
+                delta_unaccel: r.point()?,
+            },
+            "pointer-motion-absolute" => EventKind::PointerMotionAbsolute(r.position()?),
+            "pointer-button" => EventKind::PointerButton {
+                button: r.parse()?,
+                state: parse_button_state(r.token()?)?,
+            },
+            "pointer-axis" => {
+                let mut axis = AxisData {
+                    source: parse_axis_source(r.token()?)?,
+                    amount: [None; 2],
+                    discrete: [None; 2],
+                    v120: [None; 2],
+                    relative_direction: [AxisRelativeDirection::Identical; 2],
+                };
+                for i in 0..2 {
+                    axis.amount[i] = r.opt()?;
+                    axis.discrete[i] = r.opt()?;
+                    axis.v120[i] = r.opt()?;
+                    axis.relative_direction[i] = parse_relative_direction(r.token()?)?;
+                }
+                EventKind::PointerAxis(axis)
+            }
+            "swipe-begin" => EventKind::GestureSwipeBegin { fingers: r.parse()? },
+            "swipe-update" => EventKind::GestureSwipeUpdate { delta: r.point()? },
+            "swipe-end" => EventKind::GestureSwipeEnd {
+                cancelled: r.parse()?,
+            },
+            "pinch-begin" => EventKind::GesturePinchBegin { fingers: r.parse()? },
+            "pinch-update" => EventKind::GesturePinchUpdate {
+                delta: r.point()?,
+                scale: r.parse()?,
+                rotation: r.parse()?,
+            },
+            "pinch-end" => EventKind::GesturePinchEnd {
+                cancelled: r.parse()?,
+            },
+            "hold-begin" => EventKind::GestureHoldBegin { fingers: r.parse()? },
+            "hold-end" => EventKind::GestureHoldEnd {
+                cancelled: r.parse()?,
+            },
+            "touch-down" => EventKind::TouchDown {
+                slot: r.opt()?,
+                position: r.position()?,
+            },
+            "touch-motion" => EventKind::TouchMotion {
+                slot: r.opt()?,
+                position: r.position()?,
+            },
+            "touch-up" => EventKind::TouchUp { slot: r.opt()? },
+            "touch-cancel" => EventKind::TouchCancel { slot: r.opt()? },
+            "touch-frame" => EventKind::TouchFrame,
+            "switch-toggle" => EventKind::SwitchToggle {
+                switch: parse_switch(r.token()?)?,
+                state: parse_switch_state(r.token()?)?,
+            },
+            kind => return Err(invalid(format!("unknown event `{}`", kind))),
+        };
+        r.finish()?;
+        Ok(Line::Event { time, device, kind })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(line: Line) {
+        let encoded = line.encode();
+        assert!(!encoded.contains('\n'));
+        assert_eq!(Line::decode(&encoded).unwrap(), line);
+    }
+
+    #[test]
+    fn strings() {
+        for value in ["", "-", "%", "Logitech USB Receiver", "tab\tnew\nline", "ünïcode"] {
+            let mut encoded = String::new();
+            encode_str(value, &mut encoded);
+            assert!(!encoded.contains(char::is_whitespace));
+            assert_eq!(decode_str(&encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn devices() {
+        roundtrip(Line::Device(ReplayDevice {
+            id: "event3".into(),
+            name: "AT Translated Set 2 keyboard".into(),
+            capabilities: vec![DeviceCapability::Keyboard, DeviceCapability::Pointer],
+            usb_id: Some((1133, 49271)),
+            syspath: Some("/sys/devices/platform/i8042/serio0/input/input3/event3".into()),
+        }));
+        roundtrip(Line::Device(ReplayDevice {
+            id: "-".into(),
+            name: String::new(),
+            capabilities: Vec::new(),
+            usb_id: None,
+            syspath: None,
+        }));
+    }
+
+    #[test]
+    fn events() {
+        let position = Position {
+            x: 12.5,
+            y: -3.0,
+            x_fraction: 0.1,
+            y_fraction: 1.0 / 3.0,
+        };
+        let kinds = [
+            EventKind::DeviceAdded,
+            EventKind::Keyboard {
+                key: 30,
+                state: KeyState::Pressed,
+                count: 1,
+            },
+            EventKind::PointerMotion {
+                delta: (0.1, -2.25),
+                delta_unaccel: (0.05, -1.0),
+            },
+            EventKind::PointerMotionAbsolute(position),
+            EventKind::PointerButton {
+                button: 0x110,
+                state: ButtonState::Released,
+            },
+            EventKind::PointerAxis(AxisData {
+                source: AxisSource::Wheel,
+                amount: [None, Some(15.0)],
+                discrete: [None, Some(1.0)],
+                v120: [None, Some(120.0)],
+                relative_direction: [AxisRelativeDirection::Identical, AxisRelativeDirection::Inverted],
+            }),
+            EventKind::GesturePinchUpdate {
+                delta: (1.0, 2.0),
+                scale: 1.5,
+                rotation: -10.0,
+            },
+            EventKind::GestureHoldEnd { cancelled: true },
+            EventKind::TouchDown {
+                slot: Some(2),
+                position,
+            },
+            EventKind::TouchCancel { slot: None },
+            EventKind::TouchFrame,
+            EventKind::SwitchToggle {
+                switch: Switch::TabletMode,
+                state: SwitchState::On,
+            },
+        ];
+        for (i, kind) in kinds.into_iter().enumerate() {
+            roundtrip(Line::Event {
+                time: if i == 0 { None } else { Some(1_000_000 + i as u64) },
+                device: "event 5".into(),
+                kind,
+            });
+        }
+    }
+
+    #[test]
+    fn invalid_lines() {
+        assert!(Line::decode("").is_err());
+        assert!(Line::decode("12 dev key 30 pressed").is_err());
+        assert!(Line::decode("12 dev key 30 pressed 1 2").is_err());
+        assert!(Line::decode("12 dev teleport").is_err());
+        assert!(Line::decode("device dev name joystick - -").is_err());
+    }
+}
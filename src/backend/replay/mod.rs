@@ -0,0 +1,701 @@
+//! Recording and replay of input events
+//!
+//! This module provides an [`InputRecorder`], which writes the events of any [`InputBackend`]
+//! along with their timestamps to a file, and the [`InputReplay`] input backend, which plays
+//! such a recording back as [`InputEvent`]s. This allows to reproduce input-related bugs and to
+//! write deterministic integration tests.
+//!
+//! The easiest way to record the events of a backend is to wrap its event source into a
+//! [`RecordingBackend`] before inserting it into the event loop:
+//!
+//! ```ignore
+//! let recorder = InputRecorder::create("input.rec")?;
+//! let backend = RecordingBackend::new(libinput_backend, recorder);
+//! event_loop.handle().insert_source(backend, |event, _, state| state.process_input_event(event))?;
+//! ```
+//!
+//! The recording can later be played back with the original timing by inserting an
+//! [`InputReplay`] into the event loop, or as fast as possible by iterating over it:
+//!
+//! ```ignore
+//! for event in InputReplay::open("input.rec")? {
+//!     state.process_input_event(event);
+//! }
+//! ```
+//!
+//! Devices are replayed as [`ReplayDevice`]s, retaining the id, name, capabilities, usb id and
+//! syspath of the recorded devices. Tablet tool events and backend specific
+//! [`InputEvent::Special`] events are not recorded.
+//!
+//! Input sequences can also be scripted without recording them first, using an [`InputScript`].
+//! This allows to inject deterministic input into a compositor in tests, see the
+//! [`headless`](crate::backend::headless) backend for virtual outputs to go along with it.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+
+use crate::backend::input::{
+    self, AbsolutePositionEvent, Axis, AxisRelativeDirection, AxisSource, ButtonState, Device,
+    DeviceCapability, GestureBeginEvent, GestureEndEvent, GestureHoldBeginEvent, GestureHoldEndEvent,
+    GesturePinchBeginEvent, GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
+    GestureSwipeEndEvent, GestureSwipeUpdateEvent, InputBackend, InputEvent, KeyState, KeyboardKeyEvent,
+    PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, Switch,
+    SwitchState, SwitchToggleEvent, TouchCancelEvent, TouchDownEvent, TouchEvent, TouchFrameEvent,
+    TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+};
+
+mod format;
+use format::{EventKind, Line, Position};
+
+mod recorder;
+pub use recorder::{InputRecorder, RecordingBackend};
+
+mod script;
+pub use script::InputScript;
+
+/// Marker used to define the `InputBackend` types for replayed input.
+#[derive(Debug)]
+pub struct ReplayInput;
+
+/// Input device of a recording
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayDevice {
+    id: String,
+    name: String,
+    capabilities: Vec<DeviceCapability>,
+    usb_id: Option<(u32, u32)>,
+    syspath: Option<PathBuf>,
+}
+
+impl ReplayDevice {
+    fn from_device<D: Device>(device: &D) -> Self {
+        ReplayDevice {
+            id: device.id(),
+            name: device.name(),
+            capabilities: format::all_capabilities()
+                .filter(|capability| device.has_capability(*capability))
+                .collect(),
+            usb_id: device.usb_id(),
+            syspath: device.syspath(),
+        }
+    }
+}
+
+impl Device for ReplayDevice {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        self.usb_id
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        self.syspath.clone()
+    }
+}
+
+/// Replayed input event
+///
+/// A single type is used for all kinds of events, accessors of event traits not matching
+/// the [`InputEvent`] variant the event was delivered with return neutral values.
+#[derive(Debug, Clone)]
+pub struct ReplayEvent {
+    time: u64,
+    device: ReplayDevice,
+    kind: EventKind,
+}
+
+impl input::Event<ReplayInput> for ReplayEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> ReplayDevice {
+        self.device.clone()
+    }
+}
+
+impl KeyboardKeyEvent<ReplayInput> for ReplayEvent {
+    fn key_code(&self) -> u32 {
+        match self.kind {
+            EventKind::Keyboard { key, .. } => key,
+            _ => 0,
+        }
+    }
+
+    fn state(&self) -> KeyState {
+        match self.kind {
+            EventKind::Keyboard { state, .. } => state,
+            _ => KeyState::Released,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        match self.kind {
+            EventKind::Keyboard { count, .. } => count,
+            _ => 0,
+        }
+    }
+}
+
+impl PointerMotionEvent<ReplayInput> for ReplayEvent {
+    fn delta_x(&self) -> f64 {
+        match self.kind {
+            EventKind::PointerMotion { delta, .. } => delta.0,
+            _ => 0.0,
+        }
+    }
+
+    fn delta_y(&self) -> f64 {
+        match self.kind {
+            EventKind::PointerMotion { delta, .. } => delta.1,
+            _ => 0.0,
+        }
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        match self.kind {
+            EventKind::PointerMotion { delta_unaccel, .. } => delta_unaccel.0,
+            _ => 0.0,
+        }
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        match self.kind {
+            EventKind::PointerMotion { delta_unaccel, .. } => delta_unaccel.1,
+            _ => 0.0,
+        }
+    }
+}
+
+impl ReplayEvent {
+    fn recorded_position(&self) -> Option<&Position> {
+        match &self.kind {
+            EventKind::PointerMotionAbsolute(position)
+            | EventKind::TouchDown { position, .. }
+            | EventKind::TouchMotion { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    fn touch_slot(&self) -> Option<u32> {
+        match self.kind {
+            EventKind::TouchDown { slot, .. }
+            | EventKind::TouchMotion { slot, .. }
+            | EventKind::TouchUp { slot }
+            | EventKind::TouchCancel { slot } => slot,
+            _ => None,
+        }
+    }
+}
+
+impl AbsolutePositionEvent<ReplayInput> for ReplayEvent {
+    fn x(&self) -> f64 {
+        self.recorded_position().map(|position| position.x).unwrap_or(0.0)
+    }
+
+    fn y(&self) -> f64 {
+        self.recorded_position().map(|position| position.y).unwrap_or(0.0)
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.recorded_position()
+            .map(|position| position.x_fraction)
+            .unwrap_or(0.0)
+            * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.recorded_position()
+            .map(|position| position.y_fraction)
+            .unwrap_or(0.0)
+            * height as f64
+    }
+}
+
+impl PointerMotionAbsoluteEvent<ReplayInput> for ReplayEvent {}
+
+impl PointerButtonEvent<ReplayInput> for ReplayEvent {
+    fn button_code(&self) -> u32 {
+        match self.kind {
+            EventKind::PointerButton { button, .. } => button,
+            _ => 0,
+        }
+    }
+
+    fn state(&self) -> ButtonState {
+        match self.kind {
+            EventKind::PointerButton { state, .. } => state,
+            _ => ButtonState::Released,
+        }
+    }
+}
+
+impl PointerAxisEvent<ReplayInput> for ReplayEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        match &self.kind {
+            EventKind::PointerAxis(data) => data.amount[axis_index(axis)],
+            _ => None,
+        }
+    }
+
+    fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+        match &self.kind {
+            EventKind::PointerAxis(data) => data.discrete[axis_index(axis)],
+            _ => None,
+        }
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        match &self.kind {
+            EventKind::PointerAxis(data) => data.v120[axis_index(axis)],
+            _ => None,
+        }
+    }
+
+    fn relative_direction(&self, axis: Axis) -> AxisRelativeDirection {
+        match &self.kind {
+            EventKind::PointerAxis(data) => data.relative_direction[axis_index(axis)],
+            _ => AxisRelativeDirection::Identical,
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        match &self.kind {
+            EventKind::PointerAxis(data) => data.source,
+            _ => AxisSource::Wheel,
+        }
+    }
+}
+
+fn axis_index(axis: Axis) -> usize {
+    match axis {
+        Axis::Horizontal => 0,
+        Axis::Vertical => 1,
+    }
+}
+
+impl GestureBeginEvent<ReplayInput> for ReplayEvent {
+    fn fingers(&self) -> u32 {
+        match self.kind {
+            EventKind::GestureSwipeBegin { fingers }
+            | EventKind::GesturePinchBegin { fingers }
+            | EventKind::GestureHoldBegin { fingers } => fingers,
+            _ => 0,
+        }
+    }
+}
+
+impl GestureEndEvent<ReplayInput> for ReplayEvent {
+    fn cancelled(&self) -> bool {
+        match self.kind {
+            EventKind::GestureSwipeEnd { cancelled }
+            | EventKind::GesturePinchEnd { cancelled }
+            | EventKind::GestureHoldEnd { cancelled } => cancelled,
+            _ => false,
+        }
+    }
+}
+
+impl GestureSwipeBeginEvent<ReplayInput> for ReplayEvent {}
+
+impl GestureSwipeUpdateEvent<ReplayInput> for ReplayEvent {
+    fn delta_x(&self) -> f64 {
+        match self.kind {
+            EventKind::GestureSwipeUpdate { delta } => delta.0,
+            _ => 0.0,
+        }
+    }
+
+    fn delta_y(&self) -> f64 {
+        match self.kind {
+            EventKind::GestureSwipeUpdate { delta } => delta.1,
+            _ => 0.0,
+        }
+    }
+}
+
+impl GestureSwipeEndEvent<ReplayInput> for ReplayEvent {}
+
+impl GesturePinchBeginEvent<ReplayInput> for ReplayEvent {}
+
+impl GesturePinchUpdateEvent<ReplayInput> for ReplayEvent {
+    fn delta_x(&self) -> f64 {
+        match self.kind {
+            EventKind::GesturePinchUpdate { delta, .. } => delta.0,
+            _ => 0.0,
+        }
+    }
+
+    fn delta_y(&self) -> f64 {
+        match self.kind {
+            EventKind::GesturePinchUpdate { delta, .. } => delta.1,
+            _ => 0.0,
+        }
+    }
+
+    fn scale(&self) -> f64 {
+        match self.kind {
+            EventKind::GesturePinchUpdate { scale, .. } => scale,
+            _ => 1.0,
+        }
+    }
+
+    fn rotation(&self) -> f64 {
+        match self.kind {
+            EventKind::GesturePinchUpdate { rotation, .. } => rotation,
+            _ => 0.0,
+        }
+    }
+}
+
+impl GesturePinchEndEvent<ReplayInput> for ReplayEvent {}
+
+impl GestureHoldBeginEvent<ReplayInput> for ReplayEvent {}
+
+impl GestureHoldEndEvent<ReplayInput> for ReplayEvent {}
+
+impl TouchEvent<ReplayInput> for ReplayEvent {
+    fn slot(&self) -> TouchSlot {
+        self.touch_slot().into()
+    }
+}
+
+impl TouchDownEvent<ReplayInput> for ReplayEvent {}
+
+impl TouchMotionEvent<ReplayInput> for ReplayEvent {}
+
+impl TouchUpEvent<ReplayInput> for ReplayEvent {}
+
+impl TouchCancelEvent<ReplayInput> for ReplayEvent {}
+
+impl TouchFrameEvent<ReplayInput> for ReplayEvent {}
+
+impl SwitchToggleEvent<ReplayInput> for ReplayEvent {
+    fn switch(&self) -> Option<Switch> {
+        match self.kind {
+            EventKind::SwitchToggle { switch, .. } => Some(switch),
+            _ => None,
+        }
+    }
+
+    fn state(&self) -> SwitchState {
+        match self.kind {
+            EventKind::SwitchToggle { state, .. } => state,
+            _ => SwitchState::Off,
+        }
+    }
+}
+
+impl InputBackend for ReplayInput {
+    type Device = ReplayDevice;
+    type KeyboardKeyEvent = ReplayEvent;
+    type PointerAxisEvent = ReplayEvent;
+    type PointerButtonEvent = ReplayEvent;
+    type PointerMotionEvent = ReplayEvent;
+    type PointerMotionAbsoluteEvent = ReplayEvent;
+    type GestureSwipeBeginEvent = ReplayEvent;
+    type GestureSwipeUpdateEvent = ReplayEvent;
+    type GestureSwipeEndEvent = ReplayEvent;
+    type GesturePinchBeginEvent = ReplayEvent;
+    type GesturePinchUpdateEvent = ReplayEvent;
+    type GesturePinchEndEvent = ReplayEvent;
+    type GestureHoldBeginEvent = ReplayEvent;
+    type GestureHoldEndEvent = ReplayEvent;
+    type TouchDownEvent = ReplayEvent;
+    type TouchUpEvent = ReplayEvent;
+    type TouchMotionEvent = ReplayEvent;
+    type TouchCancelEvent = ReplayEvent;
+    type TouchFrameEvent = ReplayEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = ReplayEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Input backend replaying a recording of input events
+///
+/// Inserted into an event loop, the events are emitted with the timing of the recording,
+/// starting once the event loop is dispatched. Iterating over the replay instead yields all
+/// events immediately.
+pub struct InputReplay {
+    events: VecDeque<(Option<u64>, ReplayDevice, EventKind)>,
+    // timestamp of the last replayed event, used for untimed events
+    last_time: u64,
+    timer: Timer,
+    // instant the replay started at and recorded timestamp of the first event
+    start: Option<(Instant, u64)>,
+}
+
+impl fmt::Debug for InputReplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputReplay")
+            .field("remaining", &self.events.len())
+            .field("last_time", &self.last_time)
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl InputReplay {
+    /// Load a recording from a file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<InputReplay> {
+        InputReplay::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Load a recording from a reader
+    ///
+    /// Fails if the recording is malformed.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<InputReplay> {
+        let mut lines = reader.lines();
+        match lines.next().transpose()? {
+            Some(header) if header == format::HEADER => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not an input recording",
+                ))
+            }
+        }
+
+        let mut devices = HashMap::new();
+        let mut events = VecDeque::new();
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |msg: String| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", idx + 2, msg))
+            };
+            match Line::decode(&line).map_err(|err| invalid(err.to_string()))? {
+                Line::Device(device) => {
+                    devices.insert(device.id.clone(), device);
+                }
+                Line::Event { time, device, kind } => {
+                    let device = devices
+                        .get(&device)
+                        .cloned()
+                        .ok_or_else(|| invalid(format!("undeclared device `{}`", device)))?;
+                    events.push_back((time, device, kind));
+                }
+            }
+        }
+
+        Ok(InputReplay {
+            events,
+            last_time: 0,
+            timer: Timer::immediate(),
+            start: None,
+        })
+    }
+
+    /// Number of events left to be replayed
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+fn next_event(
+    events: &mut VecDeque<(Option<u64>, ReplayDevice, EventKind)>,
+    last_time: &mut u64,
+) -> Option<InputEvent<ReplayInput>> {
+    let (time, device, kind) = events.pop_front()?;
+    // untimed events happen at the time of the preceding event
+    let time = time.unwrap_or(*last_time);
+    *last_time = time;
+
+    let event = ReplayEvent { time, device, kind };
+    Some(match event.kind {
+        EventKind::DeviceAdded => InputEvent::DeviceAdded { device: event.device },
+        EventKind::DeviceRemoved => InputEvent::DeviceRemoved { device: event.device },
+        EventKind::Keyboard { .. } => InputEvent::Keyboard { event },
+        EventKind::PointerMotion { .. } => InputEvent::PointerMotion { event },
+        EventKind::PointerMotionAbsolute(_) => InputEvent::PointerMotionAbsolute { event },
+        EventKind::PointerButton { .. } => InputEvent::PointerButton { event },
+        EventKind::PointerAxis(_) => InputEvent::PointerAxis { event },
+        EventKind::GestureSwipeBegin { .. } => InputEvent::GestureSwipeBegin { event },
+        EventKind::GestureSwipeUpdate { .. } => InputEvent::GestureSwipeUpdate { event },
+        EventKind::GestureSwipeEnd { .. } => InputEvent::GestureSwipeEnd { event },
+        EventKind::GesturePinchBegin { .. } => InputEvent::GesturePinchBegin { event },
+        EventKind::GesturePinchUpdate { .. } => InputEvent::GesturePinchUpdate { event },
+        EventKind::GesturePinchEnd { .. } => InputEvent::GesturePinchEnd { event },
+        EventKind::GestureHoldBegin { .. } => InputEvent::GestureHoldBegin { event },
+        EventKind::GestureHoldEnd { .. } => InputEvent::GestureHoldEnd { event },
+        EventKind::TouchDown { .. } => InputEvent::TouchDown { event },
+        EventKind::TouchMotion { .. } => InputEvent::TouchMotion { event },
+        EventKind::TouchUp { .. } => InputEvent::TouchUp { event },
+        EventKind::TouchCancel { .. } => InputEvent::TouchCancel { event },
+        EventKind::TouchFrame => InputEvent::TouchFrame { event },
+        EventKind::SwitchToggle { .. } => InputEvent::SwitchToggle { event },
+    })
+}
+
+impl Iterator for InputReplay {
+    type Item = InputEvent<ReplayInput>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_event(&mut self.events, &mut self.last_time)
+    }
+}
+
+impl EventSource for InputReplay {
+    type Event = InputEvent<ReplayInput>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut ()) -> Self::Ret,
+    {
+        let InputReplay {
+            events,
+            last_time,
+            timer,
+            start,
+        } = self;
+
+        timer.process_events(readiness, token, |now, _| {
+            // the recorded timestamps are replayed relative to the first one
+            let (started, first) = *start.get_or_insert_with(|| {
+                let first = events.iter().find_map(|(time, _, _)| *time).unwrap_or(0);
+                (now, first)
+            });
+            loop {
+                let time = match events.front() {
+                    Some((time, _, _)) => time.unwrap_or(*last_time),
+                    None => return TimeoutAction::Drop,
+                };
+                let deadline = started + Duration::from_micros(time.saturating_sub(first));
+                if deadline > Instant::now() {
+                    return TimeoutAction::ToInstant(deadline);
+                }
+                if let Some(event) = next_event(events, last_time) {
+                    callback(event, &mut ());
+                }
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.timer.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.timer.unregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::input::Event;
+
+    const RECORDING: &str = "smithay-input-recording 1
+device event3 Keyboard keyboard - -
+device event7 Touchscreen touch,pointer 1267:12345 /sys/devices/virtual/input/event7
+- event3 device-added
+1000 event3 key 30 pressed 1
+- event7 device-added
+1500 event7 touch-down 0 10 20 0.25 0.5
+1600 event7 touch-frame
+2000 event3 key 30 released 0
+";
+
+    #[test]
+    fn replay_events() {
+        let events = InputReplay::from_reader(RECORDING.as_bytes())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 6);
+
+        match &events[1] {
+            InputEvent::Keyboard { event } => {
+                assert_eq!(event.time(), 1000);
+                assert_eq!(event.key_code(), 30);
+                assert_eq!(KeyboardKeyEvent::state(event), KeyState::Pressed);
+                assert_eq!(event.device().name(), "Keyboard");
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &events[2] {
+            InputEvent::DeviceAdded { device } => {
+                assert!(device.has_capability(DeviceCapability::Touch));
+                assert!(!device.has_capability(DeviceCapability::Keyboard));
+                assert_eq!(device.usb_id(), Some((1267, 12345)));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &events[3] {
+            InputEvent::TouchDown { event } => {
+                assert_eq!(TouchEvent::slot(event), TouchSlot::from(Some(0)));
+                assert_eq!(event.x(), 10.0);
+                assert_eq!(event.x_transformed(1920), 480.0);
+                assert_eq!(event.y_transformed(1080), 540.0);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn record_replayed_events() {
+        let mut recorder = InputRecorder::new(Vec::new()).unwrap();
+        for event in InputReplay::from_reader(RECORDING.as_bytes()).unwrap() {
+            recorder.record(&event).unwrap();
+        }
+        let recorded = String::from_utf8(recorder.into_inner()).unwrap();
+
+        // devices are declared when first used
+        let expected = RECORDING.replace(
+            "device event7 Touchscreen touch,pointer 1267:12345 /sys/devices/virtual/input/event7\n",
+            "",
+        );
+        let expected = expected.replace(
+            "- event7 device-added\n",
+            "device event7 Touchscreen pointer,touch 1267:12345 /sys/devices/virtual/input/event7\n- event7 device-added\n",
+        );
+        assert_eq!(recorded, expected);
+    }
+
+    #[test]
+    fn invalid_recordings() {
+        assert!(InputReplay::from_reader("".as_bytes()).is_err());
+        assert!(InputReplay::from_reader(
+            "smithay-input-recording 1\n1000 event3 key 30 pressed 1\n".as_bytes()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::warn;
+
+use crate::backend::input::{
+    AbsolutePositionEvent, Axis, Device, Event, GestureBeginEvent, GestureEndEvent, GesturePinchUpdateEvent,
+    GestureSwipeUpdateEvent, InputBackend, InputEvent, KeyboardKeyEvent, PointerAxisEvent,
+    PointerButtonEvent, PointerMotionEvent, SwitchToggleEvent, TouchEvent,
+};
+
+use super::{
+    format::{self, AxisData, EventKind, Line, Position},
+    ReplayDevice,
+};
+
+// Extent used to record transformed absolute positions, which scale linearly with the extent
+const TRANSFORM_EXTENT: i32 = 1 << 24;
+
+/// Writer recording input events
+///
+/// See the [module-level documentation](super) for details.
+#[derive(Debug)]
+pub struct InputRecorder<W: Write> {
+    writer: W,
+    devices: HashMap<String, ReplayDevice>,
+}
+
+impl InputRecorder<BufWriter<File>> {
+    /// Create a recorder writing to a new file
+    ///
+    /// An existing file is truncated.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        InputRecorder::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> InputRecorder<W> {
+    /// Create a recorder writing to the given writer
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", format::HEADER)?;
+        Ok(InputRecorder {
+            writer,
+            devices: HashMap::new(),
+        })
+    }
+
+    /// Record an input event
+    ///
+    /// Tablet tool events, toggles of unknown switches and backend specific events are ignored.
+    pub fn record<B: InputBackend>(&mut self, event: &InputEvent<B>) -> io::Result<()> {
+        let (time, device, kind) = match event {
+            InputEvent::DeviceAdded { device } => {
+                return self.write_event(None, device, EventKind::DeviceAdded, true);
+            }
+            InputEvent::DeviceRemoved { device } => {
+                return self.write_event(None, device, EventKind::DeviceRemoved, false);
+            }
+            InputEvent::Keyboard { event } => timed::<B, _>(
+                event,
+                EventKind::Keyboard {
+                    key: event.key_code(),
+                    state: event.state(),
+                    count: event.count(),
+                },
+            ),
+            InputEvent::PointerMotion { event } => timed::<B, _>(
+                event,
+                EventKind::PointerMotion {
+                    delta: (event.delta_x(), event.delta_y()),
+                    delta_unaccel: (event.delta_x_unaccel(), event.delta_y_unaccel()),
+                },
+            ),
+            InputEvent::PointerMotionAbsolute { event } => {
+                timed::<B, _>(event, EventKind::PointerMotionAbsolute(position::<B, _>(event)))
+            }
+            InputEvent::PointerButton { event } => timed::<B, _>(
+                event,
+                EventKind::PointerButton {
+                    button: event.button_code(),
+                    state: event.state(),
+                },
+            ),
+            InputEvent::PointerAxis { event } => {
+                let axes = [Axis::Horizontal, Axis::Vertical];
+                let data = AxisData {
+                    source: event.source(),
+                    amount: axes.map(|axis| event.amount(axis)),
+                    discrete: axes.map(|axis| event.amount_discrete(axis)),
+                    v120: axes.map(|axis| event.amount_v120(axis)),
+                    relative_direction: axes.map(|axis| event.relative_direction(axis)),
+                };
+                timed::<B, _>(event, EventKind::PointerAxis(data))
+            }
+            InputEvent::GestureSwipeBegin { event } => timed::<B, _>(
+                event,
+                EventKind::GestureSwipeBegin {
+                    fingers: event.fingers(),
+                },
+            ),
+            InputEvent::GestureSwipeUpdate { event } => timed::<B, _>(
+                event,
+                EventKind::GestureSwipeUpdate {
+                    delta: (event.delta_x(), event.delta_y()),
+                },
+            ),
+            InputEvent::GestureSwipeEnd { event } => timed::<B, _>(
+                event,
+                EventKind::GestureSwipeEnd {
+                    cancelled: event.cancelled(),
+                },
+            ),
+            InputEvent::GesturePinchBegin { event } => timed::<B, _>(
+                event,
+                EventKind::GesturePinchBegin {
+                    fingers: event.fingers(),
+                },
+            ),
+            InputEvent::GesturePinchUpdate { event } => timed::<B, _>(
+                event,
+                EventKind::GesturePinchUpdate {
+                    delta: (event.delta_x(), event.delta_y()),
+                    scale: event.scale(),
+                    rotation: event.rotation(),
+                },
+            ),
+            InputEvent::GesturePinchEnd { event } => timed::<B, _>(
+                event,
+                EventKind::GesturePinchEnd {
+                    cancelled: event.cancelled(),
+                },
+            ),
+            InputEvent::GestureHoldBegin { event } => timed::<B, _>(
+                event,
+                EventKind::GestureHoldBegin {
+                    fingers: event.fingers(),
+                },
+            ),
+            InputEvent::GestureHoldEnd { event } => timed::<B, _>(
+                event,
+                EventKind::GestureHoldEnd {
+                    cancelled: event.cancelled(),
+                },
+            ),
+            InputEvent::TouchDown { event } => timed::<B, _>(
+                event,
+                EventKind::TouchDown {
+                    slot: slot::<B, _>(event),
+                    position: position::<B, _>(event),
+                },
+            ),
+            InputEvent::TouchMotion { event } => timed::<B, _>(
+                event,
+                EventKind::TouchMotion {
+                    slot: slot::<B, _>(event),
+                    position: position::<B, _>(event),
+                },
+            ),
+            InputEvent::TouchUp { event } => timed::<B, _>(
+                event,
+                EventKind::TouchUp {
+                    slot: slot::<B, _>(event),
+                },
+            ),
+            InputEvent::TouchCancel { event } => timed::<B, _>(
+                event,
+                EventKind::TouchCancel {
+                    slot: slot::<B, _>(event),
+                },
+            ),
+            InputEvent::TouchFrame { event } => timed::<B, _>(event, EventKind::TouchFrame),
+            InputEvent::SwitchToggle { event } => match event.switch() {
+                Some(switch) => timed::<B, _>(
+                    event,
+                    EventKind::SwitchToggle {
+                        switch,
+                        state: event.state(),
+                    },
+                ),
+                None => return Ok(()),
+            },
+            InputEvent::TabletToolAxis { .. }
+            | InputEvent::TabletToolProximity { .. }
+            | InputEvent::TabletToolTip { .. }
+            | InputEvent::TabletToolButton { .. }
+            | InputEvent::Special(_) => return Ok(()),
+        };
+        self.write_event(Some(time), &device, kind, false)
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_event<D: Device>(
+        &mut self,
+        time: Option<u64>,
+        device: &D,
+        kind: EventKind,
+        update_device: bool,
+    ) -> io::Result<()> {
+        let id = device.id();
+        // device ids may be reused, so declare the device again if it changed
+        if update_device || !self.devices.contains_key(&id) {
+            let device = ReplayDevice::from_device(device);
+            if self.devices.get(&id) != Some(&device) {
+                writeln!(self.writer, "{}", Line::Device(device.clone()).encode())?;
+                self.devices.insert(id.clone(), device);
+            }
+        }
+
+        let line = Line::Event {
+            time,
+            device: id,
+            kind,
+        };
+        writeln!(self.writer, "{}", line.encode())
+    }
+}
+
+fn timed<B: InputBackend, E: Event<B>>(event: &E, kind: EventKind) -> (u64, B::Device, EventKind) {
+    (event.time(), event.device(), kind)
+}
+
+fn position<B: InputBackend, E: AbsolutePositionEvent<B>>(event: &E) -> Position {
+    Position {
+        x: event.x(),
+        y: event.y(),
+        x_fraction: event.x_transformed(TRANSFORM_EXTENT) / TRANSFORM_EXTENT as f64,
+        y_fraction: event.y_transformed(TRANSFORM_EXTENT) / TRANSFORM_EXTENT as f64,
+    }
+}
+
+fn slot<B: InputBackend, E: TouchEvent<B>>(event: &E) -> Option<u32> {
+    let slot = i32::from(event.slot());
+    (slot >= 0).then_some(slot as u32)
+}
+
+/// Event source wrapping an input backend, recording all its events
+///
+/// The events are passed on unchanged. If writing the recording fails, an error is logged and
+/// recording stops.
+#[derive(Debug)]
+pub struct RecordingBackend<S, W: Write> {
+    source: S,
+    recorder: Option<InputRecorder<W>>,
+}
+
+impl<S, W: Write> RecordingBackend<S, W> {
+    /// Wrap an input backend
+    pub fn new(source: S, recorder: InputRecorder<W>) -> Self {
+        RecordingBackend {
+            source,
+            recorder: Some(recorder),
+        }
+    }
+
+    /// Access the wrapped input backend
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Mutably access the wrapped input backend
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Access the recorder, if recording did not fail
+    pub fn recorder_mut(&mut self) -> Option<&mut InputRecorder<W>> {
+        self.recorder.as_mut()
+    }
+
+    /// Returns the wrapped input backend and the recorder, if recording did not fail
+    pub fn into_inner(self) -> (S, Option<InputRecorder<W>>) {
+        (self.source, self.recorder)
+    }
+}
+
+impl<S, B, W> EventSource for RecordingBackend<S, W>
+where
+    S: EventSource<Event = InputEvent<B>>,
+    B: InputBackend,
+    W: Write,
+{
+    type Event = InputEvent<B>;
+    type Metadata = S::Metadata;
+    type Ret = S::Ret;
+    type Error = S::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        let recorder = &mut self.recorder;
+        let result = self.source.process_events(readiness, token, |event, metadata| {
+            if let Some(Err(err)) = recorder.as_mut().map(|recorder| recorder.record(&event)) {
+                warn!(?err, "Failed to record input event, stopping recording");
+                *recorder = None;
+            }
+            callback(event, metadata)
+        });
+
+        if let Some(Err(err)) = self.recorder.as_mut().map(|recorder| recorder.flush()) {
+            warn!(?err, "Failed to record input events, stopping recording");
+            self.recorder = None;
+        }
+        result
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
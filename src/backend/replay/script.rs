@@ -0,0 +1,372 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    time::Duration,
+};
+
+use calloop::timer::Timer;
+
+use crate::backend::input::{
+    AxisRelativeDirection, AxisSource, ButtonState, DeviceCapability, InputEvent, KeyState, Switch,
+    SwitchState,
+};
+
+use super::{
+    format::{AxisData, EventKind, Position},
+    next_event, InputReplay, ReplayDevice, ReplayInput,
+};
+
+impl ReplayDevice {
+    /// Create a new device with the given capabilities, e.g. to be used in an [`InputScript`]
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        capabilities: impl IntoIterator<Item = DeviceCapability>,
+    ) -> ReplayDevice {
+        ReplayDevice {
+            id: id.into(),
+            name: name.into(),
+            capabilities: capabilities.into_iter().collect(),
+            usb_id: None,
+            syspath: None,
+        }
+    }
+
+    /// Set the usb vendor and product id of the device
+    pub fn with_usb_id(mut self, vendor: u32, product: u32) -> ReplayDevice {
+        self.usb_id = Some((vendor, product));
+        self
+    }
+
+    /// Set the syspath of the device
+    pub fn with_syspath(mut self, syspath: impl Into<PathBuf>) -> ReplayDevice {
+        self.syspath = Some(syspath.into());
+        self
+    }
+}
+
+/// Scripted sequence of input events
+///
+/// An input script allows to inject input events into a compositor without any input devices,
+/// e.g. in integration tests. Events are appended with the timestamp of the script, which starts
+/// at zero and is moved forward with [`advance`](InputScript::advance), so the resulting events
+/// are fully deterministic.
+///
+/// Iterating over the script yields the events immediately, while [`into_replay`](InputScript::into_replay)
+/// converts the script into an [`InputReplay`] emitting the events with their timing, when
+/// inserted into an event loop.
+///
+/// ```
+/// use std::time::Duration;
+/// use smithay::backend::{
+///     input::{ButtonState, DeviceCapability, KeyState},
+///     replay::{InputScript, ReplayDevice},
+/// };
+///
+/// let keyboard = ReplayDevice::new("kbd", "Test keyboard", [DeviceCapability::Keyboard]);
+/// let mouse = ReplayDevice::new("mouse", "Test mouse", [DeviceCapability::Pointer]);
+///
+/// let mut script = InputScript::new();
+/// script
+///     .add_device(&keyboard)
+///     .add_device(&mouse)
+///     .pointer_motion_absolute(&mouse, (0.5, 0.5))
+///     .pointer_button(&mouse, 0x110, ButtonState::Pressed)
+///     .advance(Duration::from_millis(50))
+///     .pointer_button(&mouse, 0x110, ButtonState::Released)
+///     .key(&keyboard, 30, KeyState::Pressed)
+///     .advance(Duration::from_millis(50))
+///     .key(&keyboard, 30, KeyState::Released);
+///
+/// for event in script {
+///     // state.process_input_event(event);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct InputScript {
+    events: VecDeque<(Option<u64>, ReplayDevice, EventKind)>,
+    // current time in microseconds
+    time: u64,
+    // number of devices pressing a key, as reported by `KeyboardKeyEvent::count`
+    pressed_keys: HashMap<u32, u32>,
+    last_time: u64,
+}
+
+impl InputScript {
+    /// Create a new empty script
+    pub fn new() -> InputScript {
+        InputScript::default()
+    }
+
+    /// Current time of the script
+    ///
+    /// Events are appended with this timestamp.
+    pub fn time(&self) -> Duration {
+        Duration::from_micros(self.time)
+    }
+
+    /// Move the time of the script forward
+    pub fn advance(&mut self, duration: Duration) -> &mut Self {
+        self.time += duration.as_micros() as u64;
+        self
+    }
+
+    /// Number of events in the script
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if the script contains no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn push(&mut self, device: &ReplayDevice, kind: EventKind) -> &mut Self {
+        self.events.push_back((Some(self.time), device.clone(), kind));
+        self
+    }
+
+    /// Announce a new input device
+    pub fn add_device(&mut self, device: &ReplayDevice) -> &mut Self {
+        self.events
+            .push_back((None, device.clone(), EventKind::DeviceAdded));
+        self
+    }
+
+    /// Remove an input device
+    pub fn remove_device(&mut self, device: &ReplayDevice) -> &mut Self {
+        self.events
+            .push_back((None, device.clone(), EventKind::DeviceRemoved));
+        self
+    }
+
+    /// Press or release a key
+    ///
+    /// The key code is an evdev key code, e.g. `30` for the `A` key.
+    pub fn key(&mut self, device: &ReplayDevice, key: u32, state: KeyState) -> &mut Self {
+        let count = self.pressed_keys.entry(key).or_default();
+        match state {
+            KeyState::Pressed => *count += 1,
+            KeyState::Released => *count = count.saturating_sub(1),
+        }
+        let count = *count;
+        self.push(device, EventKind::Keyboard { key, state, count })
+    }
+
+    /// Press and immediately release a key
+    pub fn tap_key(&mut self, device: &ReplayDevice, key: u32) -> &mut Self {
+        self.key(device, key, KeyState::Pressed)
+            .key(device, key, KeyState::Released)
+    }
+
+    /// Move the pointer relatively
+    ///
+    /// The motion is not accelerated, so the accelerated and unaccelerated deltas are the same.
+    pub fn pointer_motion(&mut self, device: &ReplayDevice, delta: (f64, f64)) -> &mut Self {
+        self.push(
+            device,
+            EventKind::PointerMotion {
+                delta,
+                delta_unaccel: delta,
+            },
+        )
+    }
+
+    /// Move the pointer to an absolute position
+    ///
+    /// The position is given relative to the extent of the device, `(0.0, 0.0)` being the top-left
+    /// and `(1.0, 1.0)` the bottom-right corner, and is scaled to the size passed to
+    /// [`AbsolutePositionEvent::x_transformed`](crate::backend::input::AbsolutePositionEvent::x_transformed).
+    pub fn pointer_motion_absolute(&mut self, device: &ReplayDevice, position: (f64, f64)) -> &mut Self {
+        self.push(device, EventKind::PointerMotionAbsolute(absolute(position)))
+    }
+
+    /// Press or release a pointer button
+    ///
+    /// The button code is an evdev button code, e.g. `0x110` for the left button.
+    pub fn pointer_button(&mut self, device: &ReplayDevice, button: u32, state: ButtonState) -> &mut Self {
+        self.push(device, EventKind::PointerButton { button, state })
+    }
+
+    /// Press and immediately release a pointer button
+    pub fn click(&mut self, device: &ReplayDevice, button: u32) -> &mut Self {
+        self.pointer_button(device, button, ButtonState::Pressed)
+            .pointer_button(device, button, ButtonState::Released)
+    }
+
+    /// Scroll by the given horizontal and vertical amount
+    ///
+    /// For wheel sources, discrete steps are derived from the amount assuming 15 units per step.
+    pub fn pointer_axis(
+        &mut self,
+        device: &ReplayDevice,
+        source: AxisSource,
+        amount: (f64, f64),
+    ) -> &mut Self {
+        let axis = |value: f64| (value != 0.0).then_some(value);
+        let amount = [axis(amount.0), axis(amount.1)];
+        let (discrete, v120) = if source == AxisSource::Wheel {
+            let steps = amount.map(|amount| amount.map(|amount| amount / 15.0));
+            (steps, steps.map(|steps| steps.map(|steps| steps * 120.0)))
+        } else {
+            ([None; 2], [None; 2])
+        };
+        self.push(
+            device,
+            EventKind::PointerAxis(AxisData {
+                source,
+                amount,
+                discrete,
+                v120,
+                relative_direction: [AxisRelativeDirection::Identical; 2],
+            }),
+        )
+    }
+
+    /// Put down a touch point at an absolute position
+    ///
+    /// The position is relative to the extent of the device,
+    /// see [`pointer_motion_absolute`](InputScript::pointer_motion_absolute).
+    /// Touch events need to be followed by a [`touch_frame`](InputScript::touch_frame).
+    pub fn touch_down(
+        &mut self,
+        device: &ReplayDevice,
+        slot: Option<u32>,
+        position: (f64, f64),
+    ) -> &mut Self {
+        self.push(
+            device,
+            EventKind::TouchDown {
+                slot,
+                position: absolute(position),
+            },
+        )
+    }
+
+    /// Move a touch point to an absolute position
+    pub fn touch_motion(
+        &mut self,
+        device: &ReplayDevice,
+        slot: Option<u32>,
+        position: (f64, f64),
+    ) -> &mut Self {
+        self.push(
+            device,
+            EventKind::TouchMotion {
+                slot,
+                position: absolute(position),
+            },
+        )
+    }
+
+    /// Lift a touch point
+    pub fn touch_up(&mut self, device: &ReplayDevice, slot: Option<u32>) -> &mut Self {
+        self.push(device, EventKind::TouchUp { slot })
+    }
+
+    /// Cancel a touch point
+    pub fn touch_cancel(&mut self, device: &ReplayDevice, slot: Option<u32>) -> &mut Self {
+        self.push(device, EventKind::TouchCancel { slot })
+    }
+
+    /// Group the preceding touch events into one logical hardware event
+    pub fn touch_frame(&mut self, device: &ReplayDevice) -> &mut Self {
+        self.push(device, EventKind::TouchFrame)
+    }
+
+    /// Toggle a switch, e.g. a laptop lid
+    pub fn switch_toggle(&mut self, device: &ReplayDevice, switch: Switch, state: SwitchState) -> &mut Self {
+        self.push(device, EventKind::SwitchToggle { switch, state })
+    }
+
+    /// Convert the script into an [`InputReplay`]
+    ///
+    /// The replay emits the events with the timing of the script, relative to the first
+    /// dispatch of the event loop it is inserted into.
+    pub fn into_replay(self) -> InputReplay {
+        InputReplay {
+            events: self.events,
+            last_time: 0,
+            timer: Timer::immediate(),
+            start: None,
+        }
+    }
+}
+
+// absolute positions of scripted events are only known relative to the device extent
+fn absolute(position: (f64, f64)) -> Position {
+    Position {
+        x: position.0,
+        y: position.1,
+        x_fraction: position.0,
+        y_fraction: position.1,
+    }
+}
+
+impl Iterator for InputScript {
+    type Item = InputEvent<ReplayInput>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_event(&mut self.events, &mut self.last_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::input::{
+        AbsolutePositionEvent, Axis, Event, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+    };
+
+    #[test]
+    fn scripted_events() {
+        let keyboard = ReplayDevice::new("kbd", "Keyboard", [DeviceCapability::Keyboard]);
+        let mouse = ReplayDevice::new("mouse", "Mouse", [DeviceCapability::Pointer]);
+
+        let mut script = InputScript::new();
+        script
+            .add_device(&keyboard)
+            .add_device(&mouse)
+            .advance(Duration::from_millis(1))
+            .pointer_motion_absolute(&mouse, (0.25, 0.5))
+            .click(&mouse, 0x110)
+            .advance(Duration::from_millis(1))
+            .key(&keyboard, 30, KeyState::Pressed)
+            .key(&keyboard, 30, KeyState::Pressed)
+            .pointer_axis(&mouse, AxisSource::Wheel, (0.0, 15.0));
+        assert_eq!(script.len(), 8);
+        assert_eq!(script.time(), Duration::from_millis(2));
+
+        let events = script.collect::<Vec<_>>();
+        match &events[2] {
+            InputEvent::PointerMotionAbsolute { event } => {
+                assert_eq!(event.time(), 1000);
+                assert_eq!(event.x_transformed(1920), 480.0);
+                assert_eq!(event.y_transformed(1080), 540.0);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &events[4] {
+            InputEvent::PointerButton { event } => {
+                assert_eq!(event.button_code(), 0x110);
+                assert_eq!(PointerButtonEvent::state(event), ButtonState::Released);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &events[6] {
+            InputEvent::Keyboard { event } => {
+                assert_eq!(event.time(), 2000);
+                assert_eq!(event.count(), 2);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &events[7] {
+            InputEvent::PointerAxis { event } => {
+                assert_eq!(event.amount(Axis::Horizontal), None);
+                assert_eq!(event.amount_discrete(Axis::Vertical), Some(1.0));
+                assert_eq!(event.amount_v120(Axis::Vertical), Some(120.0));
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
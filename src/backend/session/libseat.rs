@@ -2,6 +2,11 @@
 //! Implementation of the [`Session`](::backend::session::Session) trait through the libseat.
 //!
 //! This requires libseat to be available on the system.
+//!
+//! libseat does not require systemd-logind. Without logind it talks to seatd, or,
+//! when running as root, uses its builtin backend to open devices directly.
+//! In every case devices of multiple gpus and input devices can be opened through
+//! the same session. Devices still open when the session is dropped are closed.
 
 use libseat::{Seat, SeatEvent};
 use std::{
@@ -36,6 +41,15 @@ struct LibSeatSessionImpl {
 
 impl Drop for LibSeatSessionImpl {
     fn drop(&mut self) {
+        // release devices still opened through the seat, so seatd or logind
+        // do not keep them assigned to us
+        let seat = self.seat.get_mut();
+        for (fd, id) in self.devices.get_mut().drain() {
+            debug!("Closing leftover device: {:?}", fd);
+            if let Err(err) = seat.close_device(id) {
+                error!("Failed to close device {:?}: {}", fd, Errno::from_i32(err.into()));
+            }
+        }
         debug!("Closing seat")
     }
 }
@@ -82,36 +96,35 @@ impl LibSeatSession {
         };
 
         drop(_guard);
-        seat.map(|mut seat| {
-            let seat_name = seat.name().to_owned();
-
-            // In some cases enable_seat event is avalible right after startup
-            // so, we can dispatch it
-            seat.dispatch(0).unwrap();
-            let active = matches!(rx.try_recv(), Ok(SeatEvent::Enable));
-
-            let internal = Rc::new(LibSeatSessionImpl {
-                seat: RefCell::new(seat),
-                active: Arc::new(AtomicBool::new(active)),
-                devices: RefCell::new(HashMap::new()),
-            });
-
-            let session = LibSeatSession {
-                internal: Rc::downgrade(&internal),
-                seat_name,
-                span: span.clone(),
-            };
+        let mut seat = seat.map_err(|err| Error::FailedToOpenSession(Errno::from_i32(err.into())))?;
+        let seat_name = seat.name().to_owned();
+
+        // In some cases enable_seat event is avalible right after startup
+        // so, we can dispatch it
+        seat.dispatch(0)
+            .map_err(|err| Error::FailedToOpenSession(Errno::from_i32(err.into())))?;
+        let active = matches!(rx.try_recv(), Ok(SeatEvent::Enable));
+
+        let internal = Rc::new(LibSeatSessionImpl {
+            seat: RefCell::new(seat),
+            active: Arc::new(AtomicBool::new(active)),
+            devices: RefCell::new(HashMap::new()),
+        });
+
+        let session = LibSeatSession {
+            internal: Rc::downgrade(&internal),
+            seat_name,
+            span: span.clone(),
+        };
 
-            let notifier = LibSeatSessionNotifier {
-                internal,
-                rx,
-                token: None,
-                span,
-            };
+        let notifier = LibSeatSessionNotifier {
+            internal,
+            rx,
+            token: None,
+            span,
+        };
 
-            (session, notifier)
-        })
-        .map_err(|err| Error::FailedToOpenSession(Errno::from_i32(err.into())))
+        Ok((session, notifier))
     }
 }
 
@@ -142,7 +155,7 @@ impl Session for LibSeatSession {
         if let Some(session) = self.internal.upgrade() {
             debug!("Closing device: {:?}", fd);
 
-            let dev = session.devices.borrow().get(&fd).copied();
+            let dev = session.devices.borrow_mut().remove(&fd);
 
             let out = if let Some(dev) = dev {
                 session
@@ -154,9 +167,10 @@ impl Session for LibSeatSession {
                 Ok(())
             };
 
-            close(fd).unwrap();
+            // close the fd regardless, to not leak it
+            let closed = close(fd).map_err(Error::FailedToCloseDevice);
 
-            out
+            out.and(closed)
         } else {
             Err(Error::SessionLost)
         }
@@ -221,7 +235,11 @@ impl EventSource for LibSeatSessionNotifier {
         F: FnMut(SessionEvent, &mut ()),
     {
         if Some(token) == self.token {
-            self.internal.seat.borrow_mut().dispatch(0).unwrap();
+            self.internal
+                .seat
+                .borrow_mut()
+                .dispatch(0)
+                .map_err(|err| Error::FailedToDispatch(Errno::from_i32(err.into())))?;
         }
 
         let internal = &self.internal;
@@ -234,7 +252,9 @@ impl EventSource for LibSeatSessionNotifier {
                     }
                     SeatEvent::Disable => {
                         internal.active.store(false, Ordering::SeqCst);
-                        internal.seat.borrow_mut().disable().unwrap();
+                        if let Err(err) = internal.seat.borrow_mut().disable() {
+                            error!(?err, "Failed to acknowledge disabling the seat");
+                        }
                         callback(SessionEvent::PauseSession, &mut ());
                     }
                 },
@@ -292,10 +312,14 @@ pub enum Error {
     #[error("Failed to close device: {0}")]
     FailedToCloseDevice(Errno),
 
-    /// Failed to close device
+    /// Failed to change vt
     #[error("Failed to change vt: {0}")]
     FailedToChangeVt(Errno),
 
+    /// Failed to dispatch seat events
+    #[error("Failed to dispatch seat events: {0}")]
+    FailedToDispatch(Errno),
+
     /// Session is already closed,
     #[error("Session is already closed")]
     SessionLost,
@@ -307,7 +331,8 @@ impl AsErrno for Error {
             &Self::FailedToOpenSession(errno)
             | &Self::FailedToOpenDevice(errno)
             | &Self::FailedToCloseDevice(errno)
-            | &Self::FailedToChangeVt(errno) => Some(errno as i32),
+            | &Self::FailedToChangeVt(errno)
+            | &Self::FailedToDispatch(errno) => Some(errno as i32),
             _ => None,
         }
     }
@@ -19,6 +19,13 @@
 //! The second is a notifier which informs you when the session is enabled or disabled by the system.
 //! This notifier takes the form of a [`calloop`] event source to deliver pause and activation events.
 //!
+//! ## Pausing devices
+//!
+//! Devices opened through a session have to be paused while the session is inactive.
+//! Instead of doing so manually for every backend, the notifier can be wrapped in a
+//! [`SessionMultiplexer`], which pauses and activates all added [`SessionDevice`]s,
+//! like [`DrmDevice`](crate::backend::drm::DrmDevice)s and libinput contexts, in the right order.
+//!
 //! ## Available providers
 //!
 //! This module provides just one session implementation, through [libseat](https://sr.ht/~kennylevinsen/seatd/),
@@ -151,5 +158,8 @@ impl AsErrno for () {
     }
 }
 
+mod multiplexer;
+pub use self::multiplexer::{SessionDevice, SessionDeviceKind, SessionMultiplexer, SessionMultiplexerHandle};
+
 #[cfg(feature = "backend_session_libseat")]
 pub mod libseat;
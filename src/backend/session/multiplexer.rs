@@ -0,0 +1,155 @@
+//! Coordination of devices on session changes
+
+use std::{cell::RefCell, error::Error, fmt, rc::Rc};
+
+use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
+use tracing::{debug, error};
+
+use super::Event;
+
+/// Kind of a [`SessionDevice`]
+///
+/// Defines the order devices are paused and activated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SessionDeviceKind {
+    /// Graphics device, e.g. a drm device
+    ///
+    /// Graphics devices are activated first and paused last.
+    Graphics,
+    /// Input device, e.g. a libinput context
+    ///
+    /// Input devices are activated last and paused first,
+    /// so no input is processed while the outputs are unavailable.
+    Input,
+}
+
+/// Device, that has to release its access while the session is paused
+pub trait SessionDevice: fmt::Debug {
+    /// Returns the kind of this device
+    fn kind(&self) -> SessionDeviceKind;
+    /// Release the device, as the session was paused
+    fn pause(&mut self);
+    /// Acquire the device again, as the session was activated
+    fn activate(&mut self) -> Result<(), Box<dyn Error>>;
+    /// Returns if the device still exists
+    ///
+    /// Devices, that are not alive anymore, are removed from the [`SessionMultiplexer`].
+    fn alive(&self) -> bool {
+        true
+    }
+}
+
+type Devices = Rc<RefCell<Vec<Box<dyn SessionDevice>>>>;
+
+/// Event source pausing and activating [`SessionDevice`]s on session changes
+///
+/// Wraps the notifier of a session, e.g. a
+/// [`LibSeatSessionNotifier`](super::libseat::LibSeatSessionNotifier).
+/// When the session is paused, input devices are suspended before graphics devices
+/// drop their drm master. When it is activated again, graphics devices acquire
+/// drm master before input devices are resumed.
+///
+/// The session [`Event`]s are forwarded after all devices were handled, so the
+/// compositor only needs to e.g. reset its rendering state on activation.
+#[derive(Debug)]
+pub struct SessionMultiplexer<N> {
+    notifier: N,
+    devices: Devices,
+}
+
+/// Handle to add devices to a [`SessionMultiplexer`]
+#[derive(Debug, Clone)]
+pub struct SessionMultiplexerHandle {
+    devices: Devices,
+}
+
+impl<N> SessionMultiplexer<N>
+where
+    N: EventSource<Event = Event, Metadata = (), Ret = ()>,
+{
+    /// Create a new multiplexer wrapping a session notifier
+    pub fn new(notifier: N) -> Self {
+        SessionMultiplexer {
+            notifier,
+            devices: Default::default(),
+        }
+    }
+
+    /// Returns a handle to add devices to this multiplexer
+    ///
+    /// The handle stays valid after the multiplexer was inserted into the event loop.
+    pub fn handle(&self) -> SessionMultiplexerHandle {
+        SessionMultiplexerHandle {
+            devices: self.devices.clone(),
+        }
+    }
+}
+
+impl SessionMultiplexerHandle {
+    /// Add a device to be paused and activated with the session
+    ///
+    /// The device is removed once it is not [alive](SessionDevice::alive) anymore.
+    pub fn add_device(&self, device: impl SessionDevice + 'static) {
+        self.devices.borrow_mut().push(Box::new(device));
+    }
+}
+
+impl<N> EventSource for SessionMultiplexer<N>
+where
+    N: EventSource<Event = Event, Metadata = (), Ret = ()>,
+{
+    type Event = Event;
+    type Metadata = ();
+    type Ret = ();
+    type Error = N::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Event, &mut ()),
+    {
+        let devices = &self.devices;
+        self.notifier.process_events(readiness, token, |event, meta| {
+            {
+                let mut devices = devices.borrow_mut();
+                devices.retain(|device| device.alive());
+                devices.sort_by_key(|device| device.kind());
+
+                match event {
+                    Event::PauseSession => {
+                        for device in devices.iter_mut().rev() {
+                            debug!(?device, "Pausing device");
+                            device.pause();
+                        }
+                    }
+                    Event::ActivateSession => {
+                        for device in devices.iter_mut() {
+                            debug!(?device, "Activating device");
+                            if let Err(err) = device.activate() {
+                                error!(?device, "Failed to activate device: {}", err);
+                            }
+                        }
+                    }
+                }
+            }
+
+            callback(event, meta)
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.notifier.register(poll, factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.notifier.reregister(poll, factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.notifier.unregister(poll)
+    }
+}
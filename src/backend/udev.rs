@@ -12,7 +12,7 @@
 //!
 //! let udev = UdevBackend::new("seat0").expect("Failed to monitor udev.");
 //!
-//! for (dev_id, node_path) in udev.device_list() {
+//! for device in udev.devices() {
 //!     // process the initial list of devices
 //! }
 //!
@@ -20,24 +20,29 @@
 //! # let loop_handle = event_loop.handle();
 //! // setup the event source for long-term monitoring
 //! loop_handle.insert_source(udev, |event, _, _dispatch_data| match event {
-//!     UdevEvent::Added { device_id, path } => {
+//!     UdevEvent::Added { device } => {
 //!         // a new device has been added
 //!     },
-//!     UdevEvent::Changed { device_id } => {
+//!     UdevEvent::Changed { device } => {
 //!         // a device has been changed
 //!     },
-//!     UdevEvent::Removed { device_id } => {
+//!     UdevEvent::Removed { device } => {
 //!         // a device has been removed
 //!     }
 //! }).expect("Failed to insert the udev source into the event loop");
 //! ```
 //!
+//! Every event carries a [`UdevDevice`] describing the device by its path and the relevant udev
+//! properties, as well as its `DrmNode` if the `backend_drm` feature is enabled. By default only
+//! primary nodes are reported, with the `backend_drm` feature other node types can be requested
+//! through `UdevBackend::with_node_types`.
+//!
 //! Additionally this contains some utility functions related to scanning.
 //!
 //! See also `anvil/src/udev.rs` for pure hardware backed example of a compositor utilizing this
 //! backend.
 
-use nix::sys::stat::{dev_t, stat};
+use nix::sys::stat::dev_t;
 use std::{
     collections::HashMap,
     ffi::OsString,
@@ -49,7 +54,10 @@ use udev::{Enumerator, EventType, MonitorBuilder, MonitorSocket};
 
 use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory};
 
-use tracing::{debug, debug_span, info, warn};
+#[cfg(feature = "backend_drm")]
+use crate::backend::drm::{DrmNode, NodeType};
+
+use tracing::{debug, debug_span, info};
 
 /// Backend to monitor available drm devices.
 ///
@@ -57,7 +65,10 @@ use tracing::{debug, debug_span, info, warn};
 /// given handler of any changes. Can be used to provide hot-plug functionality for gpus and
 /// attached monitors.
 pub struct UdevBackend {
-    devices: HashMap<dev_t, PathBuf>,
+    devices: HashMap<dev_t, UdevDevice>,
+    seat: String,
+    #[cfg(feature = "backend_drm")]
+    node_types: Vec<NodeType>,
     monitor: MonitorSocket,
     token: Option<Token>,
     span: tracing::Span,
@@ -67,8 +78,11 @@ pub struct UdevBackend {
 impl fmt::Debug for UdevBackend {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use udev::AsRaw;
-        f.debug_struct("UdevBackend")
-            .field("devices", &self.devices)
+        let mut debug = f.debug_struct("UdevBackend");
+        debug.field("devices", &self.devices).field("seat", &self.seat);
+        #[cfg(feature = "backend_drm")]
+        debug.field("node_types", &self.node_types);
+        debug
             .field("monitor", &format!("MonitorSocket ({:?})", self.monitor.as_raw()))
             .finish()
     }
@@ -81,36 +95,56 @@ impl AsFd for UdevBackend {
 }
 
 impl UdevBackend {
-    /// Creates a new [`UdevBackend`]
+    /// Creates a new [`UdevBackend`] monitoring primary drm nodes
     ///
     /// ## Arguments
     /// `seat`    - system seat which should be bound
     pub fn new<S: AsRef<str>>(seat: S) -> io::Result<UdevBackend> {
-        let seat = seat.as_ref();
+        #[cfg(feature = "backend_drm")]
+        {
+            UdevBackend::with_node_types(seat, &[NodeType::Primary])
+        }
+        #[cfg(not(feature = "backend_drm"))]
+        {
+            UdevBackend::init(seat.as_ref())
+        }
+    }
+
+    /// Creates a new [`UdevBackend`] monitoring the given types of drm nodes
+    ///
+    /// ## Arguments
+    /// `seat`       - system seat which should be bound
+    /// `node_types` - types of drm nodes to report, e.g. [`NodeType::Render`] to monitor render nodes
+    #[cfg(feature = "backend_drm")]
+    pub fn with_node_types<S: AsRef<str>>(seat: S, node_types: &[NodeType]) -> io::Result<UdevBackend> {
+        UdevBackend::init(seat.as_ref(), node_types)
+    }
+
+    fn init(seat: &str, #[cfg(feature = "backend_drm")] node_types: &[NodeType]) -> io::Result<UdevBackend> {
         let span = debug_span!("backend_udev", seat = seat.to_string());
         let _guard = span.enter();
 
-        let devices = all_gpus(seat)?
-            .into_iter()
-            // Create devices
-            .flat_map(|path| match stat(&path) {
-                Ok(stat) => Some((stat.st_rdev, path)),
-                Err(err) => {
-                    warn!("Unable to get id of {:?}, Error: {:?}. Skipping", path, err);
-                    None
-                }
-            })
-            .collect();
-
+        let mut enumerator = Enumerator::new()?;
+        enumerator.match_subsystem("drm")?;
         let monitor = MonitorBuilder::new()?.match_subsystem("drm")?.listen()?;
 
         drop(_guard);
-        Ok(UdevBackend {
-            devices,
+        let mut backend = UdevBackend {
+            devices: HashMap::new(),
+            seat: seat.to_string(),
+            #[cfg(feature = "backend_drm")]
+            node_types: node_types.to_vec(),
             monitor,
             token: None,
             span,
-        })
+        };
+        backend.devices = enumerator
+            .scan_devices()?
+            .flat_map(|device| UdevDevice::from_udev(&device))
+            .filter(|device| backend.matches(device))
+            .map(|device| (device.dev_id, device))
+            .collect();
+        Ok(backend)
     }
 
     /// Get a list of DRM devices currently known to the backend
@@ -118,7 +152,25 @@ impl UdevBackend {
     /// You should call this once before inserting the event source into your
     /// event loop, to get an initial snapshot of the device state.
     pub fn device_list(&self) -> impl Iterator<Item = (dev_t, &Path)> {
-        self.devices.iter().map(|(&id, path)| (id, path.as_ref()))
+        self.devices
+            .iter()
+            .map(|(&id, device)| (id, device.path.as_ref()))
+    }
+
+    /// Get the DRM devices currently known to the backend including their udev properties
+    ///
+    /// Like [`UdevBackend::device_list`] this should be used to get an initial
+    /// snapshot of the device state.
+    pub fn devices(&self) -> impl Iterator<Item = &UdevDevice> {
+        self.devices.values()
+    }
+
+    fn matches(&self, device: &UdevDevice) -> bool {
+        #[cfg(feature = "backend_drm")]
+        if !self.node_types.contains(&device.node.ty()) {
+            return false;
+        }
+        device.seat == self.seat
     }
 }
 
@@ -152,34 +204,39 @@ impl EventSource for UdevBackend {
             match event.event_type() {
                 // New device
                 EventType::Add => {
-                    if let (Some(path), Some(devnum)) = (event.devnode(), event.devnum()) {
-                        info!("New device: #{} at {}", devnum, path.display());
-                        if self.devices.insert(devnum, path.to_path_buf()).is_none() {
-                            callback(
-                                UdevEvent::Added {
-                                    device_id: devnum,
-                                    path: path.to_path_buf(),
-                                },
-                                &mut (),
-                            );
+                    if let Some(device) = UdevDevice::from_udev(&event) {
+                        if self.matches(&device) {
+                            info!("New device: #{} at {}", device.dev_id, device.path.display());
+                            if !self.devices.contains_key(&device.dev_id) {
+                                self.devices.insert(device.dev_id, device.clone());
+                                callback(UdevEvent::Added { device }, &mut ());
+                            }
                         }
                     }
                 }
                 // Device removed
                 EventType::Remove => {
                     if let Some(devnum) = event.devnum() {
-                        info!("Device removed: #{}", devnum);
-                        if self.devices.remove(&devnum).is_some() {
-                            callback(UdevEvent::Removed { device_id: devnum }, &mut ());
+                        if let Some(device) = self.devices.remove(&devnum) {
+                            info!("Device removed: #{}", devnum);
+                            callback(UdevEvent::Removed { device }, &mut ());
                         }
                     }
                 }
                 // New connector
                 EventType::Change => {
                     if let Some(devnum) = event.devnum() {
-                        info!("Device changed: #{}", devnum);
-                        if self.devices.contains_key(&devnum) {
-                            callback(UdevEvent::Changed { device_id: devnum }, &mut ());
+                        if let Some(known) = self.devices.get_mut(&devnum) {
+                            info!("Device changed: #{}", devnum);
+                            if let Some(device) = UdevDevice::from_udev(&event) {
+                                *known = device;
+                            }
+                            callback(
+                                UdevEvent::Changed {
+                                    device: known.clone(),
+                                },
+                                &mut (),
+                            );
                         }
                     }
                 }
@@ -205,25 +262,74 @@ impl EventSource for UdevBackend {
     }
 }
 
+/// Drm device reported by the [`UdevBackend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdevDevice {
+    /// Drm node of the device
+    #[cfg(feature = "backend_drm")]
+    pub node: DrmNode,
+    dev_id: dev_t,
+    /// Path of the device node, e.g. `/dev/dri/card0`
+    pub path: PathBuf,
+    /// Persistent path of the underlying hardware as given by the `ID_PATH` udev property
+    ///
+    /// Unlike the node, this stays the same across reboots and hotplugs.
+    pub id_path: Option<String>,
+    /// Name of the seat the device is assigned to
+    ///
+    /// Devices without an explicit `ID_SEAT` udev property belong to `"seat0"`.
+    pub seat: String,
+}
+
+impl UdevDevice {
+    fn from_udev(device: &udev::Device) -> Option<UdevDevice> {
+        let path = device.devnode()?.to_path_buf();
+        let dev_id = device.devnum()?;
+        #[cfg(feature = "backend_drm")]
+        let node = DrmNode::from_dev_id(dev_id).ok()?;
+        // without the node type only primary nodes are reported
+        #[cfg(not(feature = "backend_drm"))]
+        if !device.sysname().to_string_lossy().starts_with("card") {
+            return None;
+        }
+        Some(UdevDevice {
+            #[cfg(feature = "backend_drm")]
+            node,
+            dev_id,
+            path,
+            id_path: device
+                .property_value("ID_PATH")
+                .map(|x| x.to_string_lossy().into_owned()),
+            seat: device
+                .property_value("ID_SEAT")
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("seat0")),
+        })
+    }
+
+    /// Returns the id of the device
+    pub fn device_id(&self) -> dev_t {
+        self.dev_id
+    }
+}
+
 /// Events generated by the [`UdevBackend`], notifying you of changes in system devices
 #[derive(Debug)]
 pub enum UdevEvent {
     /// A new device has been detected
     Added {
-        /// ID of the new device
-        device_id: dev_t,
-        /// Path of the new device
-        path: PathBuf,
+        /// The new device
+        device: UdevDevice,
     },
     /// A device has changed
     Changed {
-        /// ID of the changed device
-        device_id: dev_t,
+        /// The changed device
+        device: UdevDevice,
     },
     /// A device has been removed
     Removed {
-        /// ID of the removed device
-        device_id: dev_t,
+        /// The removed device
+        device: UdevDevice,
     },
 }
 
@@ -282,6 +388,19 @@ pub fn all_gpus<S: AsRef<str>>(seat: S) -> io::Result<Vec<PathBuf>> {
         .collect())
 }
 
+/// Returns the name of the seat a device is assigned to
+///
+/// The device is identified by its syspath, e.g. as returned by
+/// [`Device::syspath`](crate::backend::input::Device::syspath). Devices without an explicit
+/// `ID_SEAT` udev property belong to `"seat0"`.
+pub fn device_seat<P: AsRef<Path>>(syspath: P) -> io::Result<String> {
+    let device = udev::Device::from_syspath(syspath.as_ref())?;
+    Ok(device
+        .property_value("ID_SEAT")
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("seat0")))
+}
+
 /// Returns the loaded driver for a device named by it's [`dev_t`](::nix::sys::stat::dev_t).
 pub fn driver(dev: dev_t) -> io::Result<Option<OsString>> {
     let mut enumerator = Enumerator::new()?;
@@ -0,0 +1,381 @@
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+use calloop::{generic::Generic, Interest, Mode, PostAction, Readiness, Token};
+use tracing::trace;
+
+use super::{
+    protocol::{
+        self, ClientMessage, PixelFormat, ProtocolError, Version, ENCODING_DESKTOP_SIZE, ENCODING_RAW,
+        SECURITY_NONE, VERSION,
+    },
+    Framebuffer,
+};
+use crate::utils::{Physical, Rectangle, Size};
+
+/// Upper bound of rectangles sent in one update, more damage is merged into its bounding box
+const MAX_RECTS: usize = 32;
+
+/// Upper bound of unsent bytes, updates are held back and their damage coalesced while a client is behind
+const MAX_BACKLOG: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Version,
+    Security(Version),
+    ClientInit,
+    Running,
+}
+
+/// Events of a client relevant to the compositor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ClientEvent {
+    Initialized,
+    Key {
+        down: bool,
+        keysym: u32,
+    },
+    Pointer {
+        previous: u8,
+        buttons: u8,
+        x: u16,
+        y: u16,
+    },
+}
+
+/// Connection to one VNC client
+#[derive(Debug)]
+pub(super) struct Client {
+    pub(super) id: u32,
+    pub(super) address: SocketAddr,
+    pub(super) source: Generic<TcpStream>,
+    pub(super) closed: bool,
+    stage: Stage,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    pixel_format: PixelFormat,
+    desktop_size: bool,
+    // size of the framebuffer known to the client
+    size: Size<i32, Physical>,
+    update_requested: bool,
+    damage: Vec<Rectangle<i32, Physical>>,
+    buttons: u8,
+}
+
+fn read_available(stream: &mut TcpStream, incoming: &mut Vec<u8>) -> io::Result<bool> {
+    let mut buf = [0u8; 4096];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => return Ok(false),
+            Ok(len) => incoming.extend_from_slice(&buf[..len]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn write_pending(stream: &mut TcpStream, outgoing: &mut Vec<u8>) -> io::Result<()> {
+    let mut written = 0;
+    let result = loop {
+        if written == outgoing.len() {
+            break Ok(());
+        }
+        match stream.write(&outgoing[written..]) {
+            Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+            Ok(len) => written += len,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => break Err(err),
+        }
+    };
+    outgoing.drain(..written);
+    result
+}
+
+impl Client {
+    pub(super) fn new(id: u32, stream: TcpStream, address: SocketAddr) -> io::Result<Client> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Client {
+            id,
+            address,
+            // the version is sent right away, so the socket starts out waiting to be writable
+            source: Generic::new(stream, Interest::BOTH, Mode::Level),
+            closed: false,
+            stage: Stage::Version,
+            incoming: Vec::new(),
+            outgoing: VERSION.to_vec(),
+            pixel_format: PixelFormat::XRGB8888,
+            desktop_size: false,
+            size: (0, 0).into(),
+            update_requested: false,
+            damage: Vec::new(),
+            buttons: 0,
+        })
+    }
+
+    /// Reads available data and writes pending data, depending on the readiness of the socket
+    ///
+    /// Returns `false` once the client has disconnected.
+    pub(super) fn dispatch(&mut self, readiness: Readiness, token: Token) -> io::Result<bool> {
+        let incoming = &mut self.incoming;
+        let outgoing = &mut self.outgoing;
+        let mut connected = true;
+        self.source
+            .process_events(readiness, token, |readiness, stream| {
+                if readiness.readable {
+                    connected = read_available(stream, incoming)?;
+                }
+                if readiness.writable {
+                    write_pending(stream, outgoing)?;
+                }
+                Ok(PostAction::Continue)
+            })?;
+        Ok(connected)
+    }
+
+    /// Returns whether the client has completed the initialization
+    pub(super) fn is_initialized(&self) -> bool {
+        self.stage == Stage::Running
+    }
+
+    /// Releases the pointer buttons held by the client, used once it disconnected
+    pub(super) fn release_buttons(&mut self, position: Option<(u16, u16)>) -> Option<ClientEvent> {
+        let previous = std::mem::take(&mut self.buttons);
+        let (x, y) = position?;
+        (previous != 0).then_some(ClientEvent::Pointer {
+            previous,
+            buttons: 0,
+            x,
+            y,
+        })
+    }
+
+    /// Writes as much pending data as possible without blocking
+    pub(super) fn flush(&mut self) -> io::Result<()> {
+        write_pending(&mut self.source.file, &mut self.outgoing)
+    }
+
+    /// Updates the interest of the socket, returns whether it needs to be reregistered
+    pub(super) fn update_interest(&mut self) -> bool {
+        let interest = if self.outgoing.is_empty() {
+            Interest::READ
+        } else {
+            Interest::BOTH
+        };
+        let changed = self.source.interest != interest;
+        self.source.interest = interest;
+        changed
+    }
+
+    /// Processes the data received from the client
+    pub(super) fn process(
+        &mut self,
+        framebuffer: &Framebuffer,
+        name: &str,
+    ) -> Result<Vec<ClientEvent>, ProtocolError> {
+        let mut events = Vec::new();
+        let mut consumed = 0;
+        let result = loop {
+            let buf = &self.incoming[consumed..];
+            match self.stage {
+                Stage::Version => {
+                    if buf.len() < VERSION.len() {
+                        break Ok(());
+                    }
+                    let version = match Version::parse(&buf[..VERSION.len()]) {
+                        Ok(version) => version,
+                        Err(err) => break Err(err),
+                    };
+                    consumed += VERSION.len();
+                    trace!(client = self.id, ?version, "Negotiated protocol version");
+                    if version == Version::V3_3 {
+                        // the server decides on the security type
+                        self.outgoing
+                            .extend_from_slice(&u32::from(SECURITY_NONE).to_be_bytes());
+                        self.stage = Stage::ClientInit;
+                    } else {
+                        self.outgoing.extend_from_slice(&[1, SECURITY_NONE]);
+                        self.stage = Stage::Security(version);
+                    }
+                }
+                Stage::Security(version) => {
+                    let Some(&security) = buf.first() else {
+                        break Ok(());
+                    };
+                    consumed += 1;
+                    if security != SECURITY_NONE {
+                        if version == Version::V3_8 {
+                            let reason = b"Unsupported security type";
+                            self.outgoing.extend_from_slice(&1u32.to_be_bytes());
+                            self.outgoing
+                                .extend_from_slice(&(reason.len() as u32).to_be_bytes());
+                            self.outgoing.extend_from_slice(reason);
+                        }
+                        break Err(ProtocolError::UnsupportedSecurity(security));
+                    }
+                    if version == Version::V3_8 {
+                        self.outgoing.extend_from_slice(&0u32.to_be_bytes());
+                    }
+                    self.stage = Stage::ClientInit;
+                }
+                Stage::ClientInit => {
+                    // the shared flag is ignored, all clients share the framebuffer
+                    if buf.is_empty() {
+                        break Ok(());
+                    }
+                    consumed += 1;
+                    self.size = framebuffer.size;
+                    protocol::write_server_init(&mut self.outgoing, framebuffer.size, name);
+                    self.stage = Stage::Running;
+                    events.push(ClientEvent::Initialized);
+                }
+                Stage::Running => {
+                    let (message, len) = match ClientMessage::parse(buf) {
+                        Ok(Some(message)) => message,
+                        Ok(None) => break Ok(()),
+                        Err(err) => break Err(err),
+                    };
+                    consumed += len;
+                    if let Some(event) = self.handle_message(message, framebuffer) {
+                        events.push(event);
+                    }
+                }
+            }
+        };
+        self.incoming.drain(..consumed);
+
+        result.map(|_| events)
+    }
+
+    fn handle_message(&mut self, message: ClientMessage, framebuffer: &Framebuffer) -> Option<ClientEvent> {
+        match message {
+            ClientMessage::SetPixelFormat(format) => {
+                trace!(client = self.id, ?format, "Client changed pixel format");
+                self.pixel_format = format;
+                None
+            }
+            ClientMessage::SetEncodings(encodings) => {
+                self.desktop_size = encodings.contains(&ENCODING_DESKTOP_SIZE);
+                None
+            }
+            ClientMessage::FramebufferUpdateRequest { incremental, rect } => {
+                self.update_requested = true;
+                if !incremental {
+                    self.add_damage(rect, framebuffer);
+                }
+                None
+            }
+            ClientMessage::KeyEvent { down, keysym } => Some(ClientEvent::Key { down, keysym }),
+            ClientMessage::PointerEvent { buttons, x, y } => {
+                let previous = std::mem::replace(&mut self.buttons, buttons);
+                Some(ClientEvent::Pointer {
+                    previous,
+                    buttons,
+                    x,
+                    y,
+                })
+            }
+            ClientMessage::ClientCutText(text) => {
+                trace!(client = self.id, len = text.len(), "Ignoring cut text of client");
+                None
+            }
+        }
+    }
+
+    /// Adds damage to be sent with the next update
+    pub(super) fn add_damage(&mut self, rect: Rectangle<i32, Physical>, framebuffer: &Framebuffer) {
+        if self.stage != Stage::Running {
+            return;
+        }
+        let bounds = Rectangle::from_loc_and_size((0, 0), framebuffer.size);
+        if let Some(rect) = rect.intersection(bounds) {
+            self.damage.push(rect);
+        }
+        // keep the damage of a slow client from piling up
+        if self.damage.len() > MAX_RECTS {
+            let bbox = self
+                .damage
+                .iter()
+                .skip(1)
+                .fold(self.damage[0], |bbox, rect| bbox.merge(*rect));
+            self.damage = vec![bbox];
+        }
+    }
+
+    /// Sends an update of the damaged parts of the framebuffer, if the client requested one
+    pub(super) fn send_update(&mut self, framebuffer: &Framebuffer) {
+        if self.stage != Stage::Running || !self.update_requested || self.outgoing.len() >= MAX_BACKLOG {
+            return;
+        }
+
+        let resized = self.desktop_size && self.size != framebuffer.size;
+        if resized {
+            self.size = framebuffer.size;
+            self.damage = vec![Rectangle::from_loc_and_size((0, 0), self.size)];
+        } else if self.damage.is_empty() {
+            return;
+        }
+
+        // clients unaware of size changes only receive the part they know about
+        let bounds = Rectangle::from_loc_and_size(
+            (0, 0),
+            (
+                self.size.w.min(framebuffer.size.w),
+                self.size.h.min(framebuffer.size.h),
+            ),
+        );
+        let mut damage = std::mem::take(&mut self.damage)
+            .into_iter()
+            .filter_map(|rect| rect.intersection(bounds))
+            .collect::<Vec<_>>();
+        if damage.len() > MAX_RECTS {
+            let bbox = damage
+                .iter()
+                .skip(1)
+                .fold(damage[0], |bbox, rect| bbox.merge(*rect));
+            damage = vec![bbox];
+        }
+        if damage.is_empty() && !resized {
+            return;
+        }
+
+        self.update_requested = false;
+        let rects = damage.len() + resized as usize;
+        protocol::write_update_header(&mut self.outgoing, rects as u16);
+        if resized {
+            protocol::write_rect_header(
+                &mut self.outgoing,
+                Rectangle::from_loc_and_size((0, 0), self.size),
+                ENCODING_DESKTOP_SIZE,
+            );
+        }
+        for rect in damage {
+            protocol::write_rect_header(&mut self.outgoing, rect, ENCODING_RAW);
+            self.write_pixels(rect, framebuffer);
+        }
+    }
+
+    fn write_pixels(&mut self, rect: Rectangle<i32, Physical>, framebuffer: &Framebuffer) {
+        let stride = framebuffer.size.w as usize * 4;
+        self.outgoing
+            .reserve(rect.size.w as usize * rect.size.h as usize * self.pixel_format.bytes_per_pixel());
+
+        for y in rect.loc.y..rect.loc.y + rect.size.h {
+            let start = y as usize * stride + rect.loc.x as usize * 4;
+            let row = &framebuffer.data[start..start + rect.size.w as usize * 4];
+            if self.pixel_format == PixelFormat::XRGB8888 {
+                self.outgoing.extend_from_slice(row);
+            } else {
+                for pixel in row.chunks_exact(4) {
+                    let xrgb = u32::from_le_bytes(pixel.try_into().unwrap());
+                    self.pixel_format.write_pixel(xrgb, &mut self.outgoing);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Input backend implementation for the VNC backend.
+
+use crate::{
+    backend::input::{
+        self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, InputBackend,
+        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent,
+        UnusedEvent,
+    },
+    utils::{Physical, Size},
+};
+
+/// Scroll distance of one wheel step, matching the default of libinput
+const AXIS_STEP: f64 = 15.0;
+
+/// Marker used to define the `InputBackend` types for the VNC backend.
+#[derive(Debug)]
+pub struct VncInput;
+
+/// Virtual input device used by the backend to associate input events.
+///
+/// Input of all connected clients is reported through this device.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct VncVirtualDevice;
+
+impl Device for VncVirtualDevice {
+    fn id(&self) -> String {
+        "vnc".to_owned()
+    }
+
+    fn name(&self) -> String {
+        "vnc virtual input".to_owned()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(capability, DeviceCapability::Keyboard | DeviceCapability::Pointer)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// VNC-Backend internal event wrapping key events of clients into a [`KeyboardKeyEvent`].
+///
+/// The keysyms sent by clients are translated into the keycodes of a US keyboard layout.
+#[derive(Debug, Clone)]
+pub struct VncKeyboardInputEvent {
+    pub(crate) time: u64,
+    pub(crate) key: u32,
+    pub(crate) count: u32,
+    pub(crate) state: KeyState,
+}
+
+impl input::Event<VncInput> for VncKeyboardInputEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        VncVirtualDevice
+    }
+}
+
+impl KeyboardKeyEvent<VncInput> for VncKeyboardInputEvent {
+    fn key_code(&self) -> u32 {
+        self.key
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// VNC-Backend internal event wrapping scroll buttons of clients into a [`PointerAxisEvent`]
+#[derive(Debug, Clone)]
+pub struct VncPointerAxisEvent {
+    pub(crate) time: u64,
+    pub(crate) axis: Axis,
+    pub(crate) steps: f64,
+}
+
+impl input::Event<VncInput> for VncPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        VncVirtualDevice
+    }
+}
+
+impl PointerAxisEvent<VncInput> for VncPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            Some(self.steps * AXIS_STEP)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            Some(self.steps)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        AxisSource::Wheel
+    }
+}
+
+/// VNC-Backend internal event wrapping button presses of clients into a [`PointerButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct VncPointerButtonEvent {
+    pub(crate) time: u64,
+    pub(crate) button: u32,
+    pub(crate) state: ButtonState,
+}
+
+impl input::Event<VncInput> for VncPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        VncVirtualDevice
+    }
+}
+
+impl PointerButtonEvent<VncInput> for VncPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// VNC-Backend internal event wrapping pointer motion of clients into a [`PointerMotionAbsoluteEvent`]
+#[derive(Debug, Clone)]
+pub struct VncPointerMotionEvent {
+    pub(crate) time: u64,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<i32, Physical>,
+}
+
+impl input::Event<VncInput> for VncPointerMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> VncVirtualDevice {
+        VncVirtualDevice
+    }
+}
+
+impl PointerMotionAbsoluteEvent<VncInput> for VncPointerMotionEvent {}
+impl AbsolutePositionEvent<VncInput> for VncPointerMotionEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl InputBackend for VncInput {
+    type Device = VncVirtualDevice;
+    type KeyboardKeyEvent = VncKeyboardInputEvent;
+    type PointerAxisEvent = VncPointerAxisEvent;
+    type PointerButtonEvent = VncPointerButtonEvent;
+
+    type PointerMotionEvent = UnusedEvent;
+
+    type PointerMotionAbsoluteEvent = VncPointerMotionEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Translates a keysym sent by a client into the keycode of a US keyboard layout
+///
+/// Shifted symbols map to the key producing them, clients send the modifiers as separate key events.
+pub(super) fn keysym_to_keycode(keysym: u32) -> Option<u32> {
+    let code = match keysym {
+        // latin letters
+        0x61..=0x7a => return keysym_to_keycode(keysym - 0x20),
+        0x41..=0x5a => {
+            const LETTERS: [u32; 26] = [
+                30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47, 17,
+                45, 21, 44,
+            ];
+            LETTERS[(keysym - 0x41) as usize]
+        }
+        // digits
+        0x30 => 11,
+        0x31..=0x39 => keysym - 0x31 + 2,
+        // punctuation of a US layout
+        0x20 => 57,        // space
+        0x21 => 2,         // exclam
+        0x40 => 3,         // at
+        0x23 => 4,         // numbersign
+        0x24 => 5,         // dollar
+        0x25 => 6,         // percent
+        0x5e => 7,         // asciicircum
+        0x26 => 8,         // ampersand
+        0x2a => 9,         // asterisk
+        0x28 => 10,        // parenleft
+        0x29 => 11,        // parenright
+        0x2d | 0x5f => 12, // minus, underscore
+        0x3d | 0x2b => 13, // equal, plus
+        0x5b | 0x7b => 26, // bracketleft, braceleft
+        0x5d | 0x7d => 27, // bracketright, braceright
+        0x3b | 0x3a => 39, // semicolon, colon
+        0x27 | 0x22 => 40, // apostrophe, quotedbl
+        0x60 | 0x7e => 41, // grave, asciitilde
+        0x5c | 0x7c => 43, // backslash, bar
+        0x2c | 0x3c => 51, // comma, less
+        0x2e | 0x3e => 52, // period, greater
+        0x2f | 0x3f => 53, // slash, question
+        // function and modifier keys
+        0xff08 => 14,                            // BackSpace
+        0xff09 | 0xfe20 => 15,                   // Tab, ISO_Left_Tab
+        0xff0d => 28,                            // Return
+        0xff13 => 119,                           // Pause
+        0xff14 => 70,                            // Scroll_Lock
+        0xff1b => 1,                             // Escape
+        0xff50 => 102,                           // Home
+        0xff51 => 105,                           // Left
+        0xff52 => 103,                           // Up
+        0xff53 => 106,                           // Right
+        0xff54 => 108,                           // Down
+        0xff55 => 104,                           // Page_Up
+        0xff56 => 109,                           // Page_Down
+        0xff57 => 107,                           // End
+        0xff61 => 99,                            // Print
+        0xff63 => 110,                           // Insert
+        0xff67 => 127,                           // Menu
+        0xff7f => 69,                            // Num_Lock
+        0xffbe..=0xffc7 => keysym - 0xffbe + 59, // F1 - F10
+        0xffc8 => 87,                            // F11
+        0xffc9 => 88,                            // F12
+        0xffe1 => 42,                            // Shift_L
+        0xffe2 => 54,                            // Shift_R
+        0xffe3 => 29,                            // Control_L
+        0xffe4 => 97,                            // Control_R
+        0xffe5 => 58,                            // Caps_Lock
+        0xffe7 | 0xffeb => 125,                  // Meta_L, Super_L
+        0xffe8 | 0xffec => 126,                  // Meta_R, Super_R
+        0xffe9 => 56,                            // Alt_L
+        0xffea | 0xfe03 => 100,                  // Alt_R, ISO_Level3_Shift
+        0xffff => 111,                           // Delete
+        // keypad
+        0xff8d => 96, // KP_Enter
+        0xffaa => 55, // KP_Multiply
+        0xffab => 78, // KP_Add
+        0xffad => 74, // KP_Subtract
+        0xffae => 83, // KP_Decimal
+        0xffaf => 98, // KP_Divide
+        0xffb0..=0xffb9 => {
+            const KEYPAD: [u32; 10] = [82, 79, 80, 81, 75, 76, 77, 71, 72, 73];
+            KEYPAD[(keysym - 0xffb0) as usize]
+        }
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keysym_to_keycode;
+
+    #[test]
+    fn keysyms() {
+        // KEY_A, KEY_Z, KEY_Q
+        assert_eq!(keysym_to_keycode(u32::from('a')), Some(30));
+        assert_eq!(keysym_to_keycode(u32::from('Z')), Some(44));
+        assert_eq!(keysym_to_keycode(u32::from('q')), Some(16));
+        // KEY_1, KEY_0, shifted symbols share the key
+        assert_eq!(keysym_to_keycode(u32::from('1')), Some(2));
+        assert_eq!(keysym_to_keycode(u32::from('0')), Some(11));
+        assert_eq!(
+            keysym_to_keycode(u32::from('?')),
+            keysym_to_keycode(u32::from('/'))
+        );
+        // KEY_F1, KEY_F10, KEY_KP7
+        assert_eq!(keysym_to_keycode(0xffbe), Some(59));
+        assert_eq!(keysym_to_keycode(0xffc7), Some(68));
+        assert_eq!(keysym_to_keycode(0xffb7), Some(71));
+        assert_eq!(keysym_to_keycode(0x1000000), None);
+    }
+}
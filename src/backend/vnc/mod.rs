@@ -0,0 +1,630 @@
+//! Implementation of a remote backend serving the compositor over VNC.
+//!
+//! This backend runs a VNC server, which lets remote clients view the rendered frames and control
+//! the compositor with their keyboard and pointer, without requiring a local GPU or seat. It speaks
+//! the remote framebuffer protocol (RFB, RFC 6143) in versions 3.3 to 3.8 without authentication,
+//! so it should only be exposed on trusted networks or tunneled through e.g. SSH.
+//!
+//! The backend is initialized using [`VncBackend::bind`], which returns two objects:
+//!
+//! - a [`VncBackend`], which receives the frames to be shown to the clients,
+//! - a [`VncSource`], which you will insert into an [`EventLoop`](calloop::EventLoop) to serve the
+//!   clients and receive their input as [`InputEvent`]s.
+//!
+//! All clients share one framebuffer. It is usually rendered offscreen, e.g. into the buffers of a
+//! [`HeadlessOutput`](crate::backend::headless::HeadlessOutput), and copied into the backend with
+//! [`VncBackend::update_from_framebuffer`] or [`VncBackend::update`]. Only damaged regions are sent to
+//! the clients, using the raw encoding.
+//!
+//! Keysyms sent by clients are translated into the keycodes of a US keyboard layout, pointer buttons
+//! four to seven are reported as scroll events.
+//!
+//! ## Example usage
+//!
+//! ```rust,no_run
+//! # use std::error::Error;
+//! use smithay::backend::vnc::{VncBackend, VncEvent};
+//!
+//! # struct CompositorState;
+//! fn init_vnc_backend(
+//!    handle: calloop::LoopHandle<CompositorState>,
+//! ) -> Result<(), Box<dyn Error>> {
+//!     let (vnc, source) = VncBackend::bind("127.0.0.1:5900", "smithay", (1920, 1080).into())?;
+//!     let output = vnc.create_output("VNC-1");
+//!
+//!     handle.insert_source(source, |event, _, state| match event {
+//!         VncEvent::Input(event) => {
+//!             // Process input of the remote clients
+//!         }
+//!         _ => {}
+//!     })?;
+//!
+//!     // After rendering a frame into the bound framebuffer of `renderer`:
+//!     // vnc.update_from_framebuffer(&mut renderer, &damage)?;
+//!     Ok(())
+//! }
+//! ```
+
+mod client;
+mod input;
+mod protocol;
+
+use std::{
+    collections::HashSet,
+    io,
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use calloop::{
+    generic::Generic,
+    ping::{make_ping, Ping, PingSource},
+    EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug, debug_span, info, instrument, warn};
+
+use self::client::{Client, ClientEvent};
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        input::{xorg_mouse_to_libinput, Axis, ButtonState, InputEvent, KeyState},
+        renderer::{ExportMem, TextureMapping},
+    },
+    output::{Output, PhysicalProperties, Subpixel},
+    utils::{Buffer as BufferCoord, Clock, Monotonic, Physical, Rectangle, Size},
+};
+
+pub use self::input::*;
+
+/// Refresh rate reported for the output of the backend, in millihertz
+const DEFAULT_REFRESH: i32 = 60_000;
+
+/// Framebuffer shared by all clients, stored as `Fourcc::Xrgb8888`
+#[derive(Debug)]
+struct Framebuffer {
+    size: Size<i32, Physical>,
+    data: Vec<u8>,
+}
+
+impl Framebuffer {
+    fn new(size: Size<i32, Physical>) -> Framebuffer {
+        Framebuffer {
+            size,
+            data: vec![0; size.w as usize * size.h as usize * 4],
+        }
+    }
+
+    fn bounds(&self) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size((0, 0), self.size)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    framebuffer: Framebuffer,
+    damage: Vec<Rectangle<i32, Physical>>,
+    clients: usize,
+}
+
+/// An event emitted by the VNC backend.
+#[derive(Debug)]
+pub enum VncEvent {
+    /// An input event of a client occurred.
+    Input(InputEvent<VncInput>),
+
+    /// A client has connected and completed the initialization.
+    ClientConnected {
+        /// Id of the client
+        client_id: u32,
+        /// Address of the client
+        address: SocketAddr,
+    },
+
+    /// A client has disconnected.
+    ClientDisconnected {
+        /// Id of the client
+        client_id: u32,
+    },
+}
+
+/// Remote backend serving a framebuffer to VNC clients
+///
+/// See the [module documentation](self) for an overview of the backend.
+#[derive(Debug)]
+pub struct VncBackend {
+    shared: Arc<Mutex<Shared>>,
+    ping: Ping,
+    local_address: SocketAddr,
+    span: tracing::Span,
+}
+
+/// Event source serving the clients of a [`VncBackend`]
+///
+/// Emits [`VncEvent`]s for connecting and disconnecting clients and their input.
+#[derive(Debug)]
+pub struct VncSource {
+    listener: Generic<TcpListener>,
+    clients: Vec<Client>,
+    new_clients: Vec<Client>,
+    next_id: u32,
+    name: String,
+    shared: Arc<Mutex<Shared>>,
+    ping: PingSource,
+    input: InputState,
+    clock: Clock<Monotonic>,
+    span: tracing::Span,
+}
+
+/// Input state shared by all clients
+#[derive(Debug, Default)]
+struct InputState {
+    device_added: bool,
+    pressed_keys: HashSet<u32>,
+    pointer: Option<(u16, u16)>,
+}
+
+impl VncBackend {
+    /// Starts a VNC server listening on the given address
+    ///
+    /// `name` is shown by the clients, `size` is the initial size of the framebuffer.
+    /// The returned [`VncSource`] serves the clients and needs to be inserted into the event loop.
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        name: impl Into<String>,
+        size: Size<i32, Physical>,
+    ) -> io::Result<(VncBackend, VncSource)> {
+        let span = debug_span!("backend_vnc");
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let local_address = listener.local_addr()?;
+        let (ping, ping_source) = make_ping()?;
+        let shared = Arc::new(Mutex::new(Shared {
+            framebuffer: Framebuffer::new(size),
+            damage: Vec::new(),
+            clients: 0,
+        }));
+        info!(parent: &span, address = %local_address, "Listening for VNC clients");
+
+        Ok((
+            VncBackend {
+                shared: shared.clone(),
+                ping,
+                local_address,
+                span: span.clone(),
+            },
+            VncSource {
+                listener: Generic::new(listener, Interest::READ, Mode::Level),
+                clients: Vec::new(),
+                new_clients: Vec::new(),
+                next_id: 1,
+                name: name.into(),
+                shared,
+                ping: ping_source,
+                input: InputState::default(),
+                clock: Clock::new()?,
+                span,
+            },
+        ))
+    }
+
+    /// Returns the address the server is listening on
+    pub fn local_address(&self) -> SocketAddr {
+        self.local_address
+    }
+
+    /// Returns the number of connected clients
+    pub fn client_count(&self) -> usize {
+        self.shared.lock().unwrap().clients
+    }
+
+    /// Returns the size of the framebuffer
+    pub fn size(&self) -> Size<i32, Physical> {
+        self.shared.lock().unwrap().framebuffer.size
+    }
+
+    /// Changes the size of the framebuffer
+    ///
+    /// The contents of the framebuffer are cleared. Clients supporting the `DesktopSize`
+    /// pseudo-encoding are notified, other clients keep seeing the area of their initial size.
+    #[instrument(level = "debug", parent = &self.span, skip(self))]
+    pub fn resize(&self, size: Size<i32, Physical>) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.framebuffer.size == size {
+            return;
+        }
+        shared.framebuffer = Framebuffer::new(size);
+        let bounds = shared.framebuffer.bounds();
+        shared.damage = vec![bounds];
+        self.ping.ping();
+    }
+
+    /// Updates the damaged regions of the framebuffer
+    ///
+    /// `data` contains the whole frame in the memory layout of `Fourcc::Xrgb8888` or `Fourcc::Argb8888`
+    /// with `stride` bytes per row, `damage` the regions changed since the previous frame.
+    /// Rows missing from `data` are skipped.
+    pub fn update(&self, data: &[u8], stride: usize, damage: &[Rectangle<i32, Physical>]) {
+        let mut shared = self.shared.lock().unwrap();
+        let shared = &mut *shared;
+        let framebuffer = &mut shared.framebuffer;
+        let dst_stride = framebuffer.size.w as usize * 4;
+
+        for rect in damage {
+            let Some(rect) = rect.intersection(framebuffer.bounds()) else {
+                continue;
+            };
+            let len = rect.size.w as usize * 4;
+            for y in rect.loc.y as usize..(rect.loc.y + rect.size.h) as usize {
+                let offset = rect.loc.x as usize * 4;
+                let Some(src) = data.get(y * stride + offset..y * stride + offset + len) else {
+                    break;
+                };
+                framebuffer.data[y * dst_stride + offset..y * dst_stride + offset + len].copy_from_slice(src);
+            }
+            shared.damage.push(rect);
+        }
+
+        if !shared.damage.is_empty() {
+            self.ping.ping();
+        }
+    }
+
+    /// Copies the currently bound framebuffer of a renderer into the backend
+    ///
+    /// The framebuffer needs to have the size of the backend, `damage` gives the regions changed since
+    /// the previous frame. See [`VncBackend::update`].
+    pub fn update_from_framebuffer<R: ExportMem>(
+        &self,
+        renderer: &mut R,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        let size = self.size();
+        let region = Rectangle::<i32, BufferCoord>::from_loc_and_size((0, 0), (size.w, size.h));
+        let mapping = renderer.copy_framebuffer(region, Fourcc::Xrgb8888)?;
+        let flipped = mapping.flipped();
+        let data = renderer.map_texture(&mapping)?;
+        let stride = size.w as usize * 4;
+
+        if flipped {
+            let data = data
+                .chunks_exact(stride)
+                .rev()
+                .flatten()
+                .copied()
+                .collect::<Vec<u8>>();
+            self.update(&data, stride, damage);
+        } else {
+            self.update(data, stride, damage);
+        }
+        Ok(())
+    }
+
+    /// Creates an [`Output`] matching the framebuffer
+    ///
+    /// The output has to be updated by the compositor on [`VncBackend::resize`], and a global
+    /// for it still has to be created.
+    pub fn create_output(&self, name: impl Into<String>) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "VNC".into(),
+            },
+        );
+        let mode = crate::output::Mode {
+            size: self.size(),
+            refresh: DEFAULT_REFRESH,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+        output
+    }
+}
+
+impl InputState {
+    fn handle_event<F>(&mut self, event: ClientEvent, time: u64, size: Size<i32, Physical>, callback: &mut F)
+    where
+        F: FnMut(VncEvent, &mut ()),
+    {
+        let mut emit = |event: InputEvent<VncInput>| callback(VncEvent::Input(event), &mut ());
+
+        match event {
+            ClientEvent::Initialized => {}
+            ClientEvent::Key { down, keysym } => {
+                let Some(key) = keysym_to_keycode(keysym) else {
+                    debug!(keysym, "Ignoring key without keycode");
+                    return;
+                };
+                // clients repeat key presses themselves, the compositor does its own key repeat
+                let changed = if down {
+                    self.pressed_keys.insert(key)
+                } else {
+                    self.pressed_keys.remove(&key)
+                };
+                if changed {
+                    emit(InputEvent::Keyboard {
+                        event: VncKeyboardInputEvent {
+                            time,
+                            key,
+                            count: self.pressed_keys.len() as u32,
+                            state: if down {
+                                KeyState::Pressed
+                            } else {
+                                KeyState::Released
+                            },
+                        },
+                    });
+                }
+            }
+            ClientEvent::Pointer {
+                previous,
+                buttons,
+                x,
+                y,
+            } => {
+                if self.pointer != Some((x, y)) {
+                    self.pointer = Some((x, y));
+                    emit(InputEvent::PointerMotionAbsolute {
+                        event: VncPointerMotionEvent {
+                            time,
+                            x: x as f64,
+                            y: y as f64,
+                            size,
+                        },
+                    });
+                }
+
+                for bit in 0..8 {
+                    let mask = 1 << bit;
+                    if (previous ^ buttons) & mask == 0 {
+                        continue;
+                    }
+                    let pressed = buttons & mask != 0;
+                    let axis = match bit {
+                        3 => Some((Axis::Vertical, -1.0)),
+                        4 => Some((Axis::Vertical, 1.0)),
+                        5 => Some((Axis::Horizontal, -1.0)),
+                        6 => Some((Axis::Horizontal, 1.0)),
+                        _ => None,
+                    };
+                    match axis {
+                        // scroll buttons are pressed and released for every step
+                        Some((axis, steps)) if pressed => emit(InputEvent::PointerAxis {
+                            event: VncPointerAxisEvent { time, axis, steps },
+                        }),
+                        Some(_) => {}
+                        None => emit(InputEvent::PointerButton {
+                            event: VncPointerButtonEvent {
+                                time,
+                                button: xorg_mouse_to_libinput(bit + 1),
+                                state: if pressed {
+                                    ButtonState::Pressed
+                                } else {
+                                    ButtonState::Released
+                                },
+                            },
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Releases all keys, used once the last client disconnected
+    fn release_keys<F>(&mut self, time: u64, callback: &mut F)
+    where
+        F: FnMut(VncEvent, &mut ()),
+    {
+        let mut count = self.pressed_keys.len() as u32;
+        for key in self.pressed_keys.drain() {
+            count -= 1;
+            callback(
+                VncEvent::Input(InputEvent::Keyboard {
+                    event: VncKeyboardInputEvent {
+                        time,
+                        key,
+                        count,
+                        state: KeyState::Released,
+                    },
+                }),
+                &mut (),
+            );
+        }
+    }
+}
+
+impl VncSource {
+    // Registers new clients and unregisters disconnected ones
+    fn update_clients(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        let (closed, open): (Vec<Client>, Vec<Client>) = std::mem::take(&mut self.clients)
+            .into_iter()
+            .partition(|client| client.closed);
+        self.clients = open;
+        for mut client in closed {
+            client.source.unregister(poll)?;
+        }
+        for mut client in self.new_clients.drain(..) {
+            client.source.register(poll, token_factory)?;
+            self.clients.push(client);
+        }
+        Ok(())
+    }
+}
+
+impl EventSource for VncSource {
+    type Event = VncEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    #[instrument(level = "trace", parent = &self.span, skip_all)]
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut ()),
+    {
+        let mut reregister = false;
+
+        let new_clients = &mut self.new_clients;
+        let next_id = &mut self.next_id;
+        self.listener.process_events(readiness, token, |_, listener| {
+            loop {
+                let (stream, address) = match listener.accept() {
+                    Ok(client) => client,
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => return Err(err),
+                };
+                match Client::new(*next_id, stream, address) {
+                    Ok(client) => {
+                        debug!(client = client.id, %address, "New VNC client");
+                        *next_id += 1;
+                        new_clients.push(client);
+                    }
+                    Err(err) => warn!(?err, %address, "Failed to set up VNC client"),
+                }
+            }
+            Ok(PostAction::Continue)
+        })?;
+        reregister |= !self.new_clients.is_empty();
+
+        // the ping only wakes up the event loop to send the new damage
+        self.ping.process_events(readiness, token, |_, _| {})?;
+
+        let time = Duration::from(self.clock.now()).as_micros() as u64;
+        let mut shared = self.shared.lock().unwrap();
+        let shared = &mut *shared;
+        let damage = std::mem::take(&mut shared.damage);
+        let framebuffer = &shared.framebuffer;
+
+        for client in self.clients.iter_mut().filter(|client| !client.closed) {
+            let events = match client.dispatch(readiness, token) {
+                Ok(true) => match client.process(framebuffer, &self.name) {
+                    Ok(events) => Some(events),
+                    Err(err) => {
+                        warn!(client = client.id, %err, "VNC client violated the protocol");
+                        None
+                    }
+                },
+                Ok(false) => None,
+                Err(err) => {
+                    debug!(client = client.id, ?err, "VNC client connection failed");
+                    None
+                }
+            };
+
+            match events {
+                Some(events) => {
+                    for rect in damage.iter() {
+                        client.add_damage(*rect, framebuffer);
+                    }
+                    for event in events {
+                        if event == ClientEvent::Initialized {
+                            info!(client = client.id, address = %client.address, "VNC client connected");
+                            shared.clients += 1;
+                            if !self.input.device_added {
+                                self.input.device_added = true;
+                                callback(
+                                    VncEvent::Input(InputEvent::DeviceAdded {
+                                        device: VncVirtualDevice,
+                                    }),
+                                    &mut (),
+                                );
+                            }
+                            callback(
+                                VncEvent::ClientConnected {
+                                    client_id: client.id,
+                                    address: client.address,
+                                },
+                                &mut (),
+                            );
+                        }
+                        self.input
+                            .handle_event(event, time, framebuffer.size, &mut callback);
+                    }
+                    client.send_update(framebuffer);
+                    if let Err(err) = client.flush() {
+                        debug!(client = client.id, ?err, "VNC client connection failed");
+                        client.closed = true;
+                    }
+                }
+                None => {
+                    // try to deliver the reason of a failed handshake
+                    let _ = client.flush();
+                    client.closed = true;
+                }
+            }
+
+            if client.closed {
+                info!(client = client.id, "VNC client disconnected");
+                reregister = true;
+                if client.is_initialized() {
+                    shared.clients -= 1;
+                    callback(VncEvent::ClientDisconnected { client_id: client.id }, &mut ());
+                    // release the buttons held by the client
+                    if let Some(event) = client.release_buttons(self.input.pointer) {
+                        self.input
+                            .handle_event(event, time, framebuffer.size, &mut callback);
+                    }
+                }
+            } else {
+                reregister |= client.update_interest();
+            }
+        }
+
+        if shared.clients == 0 && self.input.device_added {
+            self.input.device_added = false;
+            self.input.release_keys(time, &mut callback);
+            callback(
+                VncEvent::Input(InputEvent::DeviceRemoved {
+                    device: VncVirtualDevice,
+                }),
+                &mut (),
+            );
+        }
+
+        if reregister {
+            Ok(PostAction::Reregister)
+        } else {
+            Ok(PostAction::Continue)
+        }
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.register(poll, token_factory)?;
+        self.ping.register(poll, token_factory)?;
+        for client in self.clients.iter_mut() {
+            client.source.register(poll, token_factory)?;
+        }
+        self.update_clients(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.listener.reregister(poll, token_factory)?;
+        self.ping.reregister(poll, token_factory)?;
+        self.update_clients(poll, token_factory)?;
+        for client in self.clients.iter_mut() {
+            client.source.reregister(poll, token_factory)?;
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.listener.unregister(poll)?;
+        self.ping.unregister(poll)?;
+        for client in self.clients.iter_mut() {
+            client.source.unregister(poll)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+//! Encoding and decoding of the messages of the remote framebuffer protocol, see RFC 6143
+
+use crate::utils::{Physical, Rectangle, Size};
+
+/// Protocol version announced by the server
+pub(super) const VERSION: &[u8; 12] = b"RFB 003.008\n";
+
+/// The "None" security type, the only one supported
+pub(super) const SECURITY_NONE: u8 = 1;
+
+/// Raw encoding of rectangles
+pub(super) const ENCODING_RAW: i32 = 0;
+/// Pseudo-encoding announcing changes of the framebuffer size
+pub(super) const ENCODING_DESKTOP_SIZE: i32 = -223;
+
+/// Upper bound of the length of cut text accepted from clients
+const MAX_CUT_TEXT: usize = 1 << 20;
+
+/// Errors of clients violating the protocol
+#[derive(Debug, thiserror::Error)]
+pub(super) enum ProtocolError {
+    #[error("The client requested an unsupported protocol version")]
+    UnsupportedVersion,
+    #[error("The client requested the unsupported security type {0}")]
+    UnsupportedSecurity(u8),
+    #[error("The client requested an unsupported pixel format: {0:?}")]
+    UnsupportedPixelFormat(PixelFormat),
+    #[error("The client sent the unknown message type {0}")]
+    UnknownMessage(u8),
+    #[error("The client sent {0} bytes of cut text")]
+    CutTextTooLong(usize),
+}
+
+/// Protocol versions understood by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Version {
+    V3_3,
+    V3_7,
+    V3_8,
+}
+
+impl Version {
+    /// Parses the protocol version sent by a client
+    pub(super) fn parse(buf: &[u8]) -> Result<Version, ProtocolError> {
+        let version = std::str::from_utf8(buf).map_err(|_| ProtocolError::UnsupportedVersion)?;
+        let (major, minor) = version
+            .strip_prefix("RFB ")
+            .and_then(|version| version.strip_suffix('\n'))
+            .and_then(|version| version.split_once('.'))
+            .ok_or(ProtocolError::UnsupportedVersion)?;
+        let major: u32 = major.parse().map_err(|_| ProtocolError::UnsupportedVersion)?;
+        let minor: u32 = minor.parse().map_err(|_| ProtocolError::UnsupportedVersion)?;
+
+        // unknown minor versions are treated as 3.3, newer ones as 3.8 (RFC 6143, section 7.1.1)
+        match (major, minor) {
+            (3, 7) => Ok(Version::V3_7),
+            (3, minor) if minor >= 8 => Ok(Version::V3_8),
+            (3, _) => Ok(Version::V3_3),
+            _ => Err(ProtocolError::UnsupportedVersion),
+        }
+    }
+}
+
+/// Format of the pixels sent to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct PixelFormat {
+    pub(super) bits_per_pixel: u8,
+    pub(super) depth: u8,
+    pub(super) big_endian: bool,
+    pub(super) true_color: bool,
+    pub(super) red_max: u16,
+    pub(super) green_max: u16,
+    pub(super) blue_max: u16,
+    pub(super) red_shift: u8,
+    pub(super) green_shift: u8,
+    pub(super) blue_shift: u8,
+}
+
+impl PixelFormat {
+    /// Native format of the server, matching the memory layout of `Fourcc::Xrgb8888`
+    pub(super) const XRGB8888: PixelFormat = PixelFormat {
+        bits_per_pixel: 32,
+        depth: 24,
+        big_endian: false,
+        true_color: true,
+        red_max: 255,
+        green_max: 255,
+        blue_max: 255,
+        red_shift: 16,
+        green_shift: 8,
+        blue_shift: 0,
+    };
+
+    fn parse(buf: &[u8; 16]) -> PixelFormat {
+        PixelFormat {
+            bits_per_pixel: buf[0],
+            depth: buf[1],
+            big_endian: buf[2] != 0,
+            true_color: buf[3] != 0,
+            red_max: u16::from_be_bytes([buf[4], buf[5]]),
+            green_max: u16::from_be_bytes([buf[6], buf[7]]),
+            blue_max: u16::from_be_bytes([buf[8], buf[9]]),
+            red_shift: buf[10],
+            green_shift: buf[11],
+            blue_shift: buf[12],
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[
+            self.bits_per_pixel,
+            self.depth,
+            self.big_endian as u8,
+            self.true_color as u8,
+        ]);
+        out.extend_from_slice(&self.red_max.to_be_bytes());
+        out.extend_from_slice(&self.green_max.to_be_bytes());
+        out.extend_from_slice(&self.blue_max.to_be_bytes());
+        out.extend_from_slice(&[self.red_shift, self.green_shift, self.blue_shift, 0, 0, 0]);
+    }
+
+    /// Color maps are not supported, so only true color formats can be sent.
+    ///
+    /// Every channel also has to fit into a pixel, which keeps the shifts of `write_pixel` in range.
+    fn is_supported(&self) -> bool {
+        let bits = self.bits_per_pixel as u32;
+        let fits = |max: u16, shift: u8| (shift as u32) < bits && (max as u64) << shift < 1u64 << bits;
+        self.true_color
+            && matches!(self.bits_per_pixel, 8 | 16 | 32)
+            && fits(self.red_max, self.red_shift)
+            && fits(self.green_max, self.green_shift)
+            && fits(self.blue_max, self.blue_shift)
+    }
+
+    /// Returns the number of bytes of one pixel
+    pub(super) fn bytes_per_pixel(&self) -> usize {
+        self.bits_per_pixel as usize / 8
+    }
+
+    /// Converts a pixel given as `0x00RRGGBB` into this format
+    pub(super) fn write_pixel(&self, xrgb: u32, out: &mut Vec<u8>) {
+        let scale = |value: u32, max: u16| (value * max as u32 + 127) / 255;
+        let pixel = scale((xrgb >> 16) & 0xff, self.red_max) << self.red_shift
+            | scale((xrgb >> 8) & 0xff, self.green_max) << self.green_shift
+            | scale(xrgb & 0xff, self.blue_max) << self.blue_shift;
+
+        match (self.bits_per_pixel, self.big_endian) {
+            (32, false) => out.extend_from_slice(&pixel.to_le_bytes()),
+            (32, true) => out.extend_from_slice(&pixel.to_be_bytes()),
+            (16, false) => out.extend_from_slice(&(pixel as u16).to_le_bytes()),
+            (16, true) => out.extend_from_slice(&(pixel as u16).to_be_bytes()),
+            _ => out.push(pixel as u8),
+        }
+    }
+}
+
+/// Messages sent by clients after the initialization
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ClientMessage {
+    SetPixelFormat(PixelFormat),
+    SetEncodings(Vec<i32>),
+    FramebufferUpdateRequest {
+        incremental: bool,
+        rect: Rectangle<i32, Physical>,
+    },
+    KeyEvent {
+        down: bool,
+        keysym: u32,
+    },
+    PointerEvent {
+        buttons: u8,
+        x: u16,
+        y: u16,
+    },
+    ClientCutText(Vec<u8>),
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]])
+}
+
+impl ClientMessage {
+    /// Parses the next message of a client.
+    ///
+    /// Returns the message along with the number of bytes it occupied, or `None` if `buf` does not
+    /// contain the whole message yet.
+    pub(super) fn parse(buf: &[u8]) -> Result<Option<(ClientMessage, usize)>, ProtocolError> {
+        let Some(&message_type) = buf.first() else {
+            return Ok(None);
+        };
+
+        let len = match message_type {
+            0 => 20,
+            2 if buf.len() >= 4 => 4 + 4 * u16_at(buf, 2) as usize,
+            2 => 4,
+            3 => 10,
+            4 => 8,
+            5 => 6,
+            6 if buf.len() >= 8 => {
+                let text_len = u32_at(buf, 4) as usize;
+                if text_len > MAX_CUT_TEXT {
+                    return Err(ProtocolError::CutTextTooLong(text_len));
+                }
+                8 + text_len
+            }
+            6 => 8,
+            other => return Err(ProtocolError::UnknownMessage(other)),
+        };
+        if buf.len() < len {
+            return Ok(None);
+        }
+
+        let message = match message_type {
+            0 => {
+                let format = PixelFormat::parse(buf[4..20].try_into().unwrap());
+                if !format.is_supported() {
+                    return Err(ProtocolError::UnsupportedPixelFormat(format));
+                }
+                ClientMessage::SetPixelFormat(format)
+            }
+            2 => ClientMessage::SetEncodings(
+                buf[4..len]
+                    .chunks_exact(4)
+                    .map(|encoding| i32::from_be_bytes(encoding.try_into().unwrap()))
+                    .collect(),
+            ),
+            3 => ClientMessage::FramebufferUpdateRequest {
+                incremental: buf[1] != 0,
+                rect: Rectangle::from_loc_and_size(
+                    (u16_at(buf, 2) as i32, u16_at(buf, 4) as i32),
+                    (u16_at(buf, 6) as i32, u16_at(buf, 8) as i32),
+                ),
+            },
+            4 => ClientMessage::KeyEvent {
+                down: buf[1] != 0,
+                keysym: u32_at(buf, 4),
+            },
+            5 => ClientMessage::PointerEvent {
+                buttons: buf[1],
+                x: u16_at(buf, 2),
+                y: u16_at(buf, 4),
+            },
+            _ => ClientMessage::ClientCutText(buf[8..len].to_vec()),
+        };
+
+        Ok(Some((message, len)))
+    }
+}
+
+/// Writes the `ServerInit` message
+pub(super) fn write_server_init(out: &mut Vec<u8>, size: Size<i32, Physical>, name: &str) {
+    out.extend_from_slice(&(size.w as u16).to_be_bytes());
+    out.extend_from_slice(&(size.h as u16).to_be_bytes());
+    PixelFormat::XRGB8888.write(out);
+    out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Writes the header of a `FramebufferUpdate` message
+pub(super) fn write_update_header(out: &mut Vec<u8>, rects: u16) {
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&rects.to_be_bytes());
+}
+
+/// Writes the header of a rectangle of a `FramebufferUpdate` message
+pub(super) fn write_rect_header(out: &mut Vec<u8>, rect: Rectangle<i32, Physical>, encoding: i32) {
+    out.extend_from_slice(&(rect.loc.x as u16).to_be_bytes());
+    out.extend_from_slice(&(rect.loc.y as u16).to_be_bytes());
+    out.extend_from_slice(&(rect.size.w as u16).to_be_bytes());
+    out.extend_from_slice(&(rect.size.h as u16).to_be_bytes());
+    out.extend_from_slice(&encoding.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(Version::parse(b"RFB 003.008\n").unwrap(), Version::V3_8);
+        assert_eq!(Version::parse(b"RFB 003.007\n").unwrap(), Version::V3_7);
+        assert_eq!(Version::parse(b"RFB 003.003\n").unwrap(), Version::V3_3);
+        // apple remote desktop
+        assert_eq!(Version::parse(b"RFB 003.889\n").unwrap(), Version::V3_8);
+        assert!(Version::parse(b"RFB 004.001\n").is_err());
+        assert!(Version::parse(b"HTTP/1.1 200").is_err());
+    }
+
+    #[test]
+    fn parse_partial_messages() {
+        let key_event = [4, 1, 0, 0, 0, 0, 0xff, 0x0d];
+        for len in 0..key_event.len() {
+            assert_eq!(ClientMessage::parse(&key_event[..len]).unwrap(), None);
+        }
+        assert_eq!(
+            ClientMessage::parse(&key_event).unwrap(),
+            Some((
+                ClientMessage::KeyEvent {
+                    down: true,
+                    keysym: 0xff0d
+                },
+                8
+            ))
+        );
+
+        let set_encodings = [2, 0, 0, 2, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0x21, 5];
+        assert_eq!(ClientMessage::parse(&set_encodings[..11]).unwrap(), None);
+        assert_eq!(
+            ClientMessage::parse(&set_encodings).unwrap(),
+            Some((
+                ClientMessage::SetEncodings(vec![ENCODING_RAW, ENCODING_DESKTOP_SIZE]),
+                12
+            ))
+        );
+
+        assert!(matches!(
+            ClientMessage::parse(&[42]),
+            Err(ProtocolError::UnknownMessage(42))
+        ));
+    }
+
+    #[test]
+    fn convert_pixels() {
+        let mut out = Vec::new();
+        PixelFormat::XRGB8888.write_pixel(0x00123456, &mut out);
+        assert_eq!(out, [0x56, 0x34, 0x12, 0]);
+
+        let rgb565 = PixelFormat {
+            bits_per_pixel: 16,
+            depth: 16,
+            big_endian: true,
+            true_color: true,
+            red_max: 31,
+            green_max: 63,
+            blue_max: 31,
+            red_shift: 11,
+            green_shift: 5,
+            blue_shift: 0,
+        };
+        out.clear();
+        rgb565.write_pixel(0x00ff00ff, &mut out);
+        assert_eq!(out, [0xf8, 0x1f]);
+    }
+
+    #[test]
+    fn reject_invalid_pixel_formats() {
+        assert!(PixelFormat::XRGB8888.is_supported());
+
+        let overflowing_shift = PixelFormat {
+            red_shift: 32,
+            ..PixelFormat::XRGB8888
+        };
+        assert!(!overflowing_shift.is_supported());
+        let mut message = vec![0, 0, 0, 0];
+        overflowing_shift.write(&mut message);
+        assert!(matches!(
+            ClientMessage::parse(&message),
+            Err(ProtocolError::UnsupportedPixelFormat(_))
+        ));
+
+        // 255 << 16 does not fit into 16 bits
+        let overflowing_max = PixelFormat {
+            bits_per_pixel: 16,
+            depth: 16,
+            ..PixelFormat::XRGB8888
+        };
+        assert!(!overflowing_max.is_supported());
+    }
+}
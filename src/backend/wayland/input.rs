@@ -0,0 +1,387 @@
+//! Input backend implementation for the wayland backend.
+
+use super::{window::WindowInner, WaylandWindow};
+use crate::{
+    backend::input::{
+        self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, InputBackend,
+        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent,
+        TouchCancelEvent, TouchDownEvent, TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot,
+        TouchUpEvent, UnusedEvent,
+    },
+    utils::{Logical, Size},
+};
+use std::sync::Weak;
+
+/// Marker used to define the `InputBackend` types for the wayland backend.
+#[derive(Debug)]
+pub struct WaylandInput;
+
+/// Virtual input device used by the backend to associate input events.
+///
+/// Represents the `wl_seat` of the parent compositor.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct WaylandVirtualDevice;
+
+impl Device for WaylandVirtualDevice {
+    fn id(&self) -> String {
+        "wayland".to_owned()
+    }
+
+    fn name(&self) -> String {
+        "wayland virtual input".to_owned()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(
+            capability,
+            DeviceCapability::Keyboard | DeviceCapability::Pointer | DeviceCapability::Touch
+        )
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    fn syspath(&self) -> Option<std::path::PathBuf> {
+        None
+    }
+}
+
+/// Wayland-Backend internal event wrapping `wl_keyboard`'s key events into a [`KeyboardKeyEvent`].
+#[derive(Debug, Clone)]
+pub struct WaylandKeyboardInputEvent {
+    pub(crate) time: u32,
+    pub(crate) key: u32,
+    pub(crate) count: u32,
+    pub(crate) state: KeyState,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl WaylandKeyboardInputEvent {
+    /// Returns the window having keyboard focus when this event occurred.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<WaylandWindow> {
+        self.window.upgrade().map(WaylandWindow)
+    }
+}
+
+impl input::Event<WaylandInput> for WaylandKeyboardInputEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl KeyboardKeyEvent<WaylandInput> for WaylandKeyboardInputEvent {
+    fn key_code(&self) -> u32 {
+        self.key
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Wayland-Backend internal event wrapping `wl_pointer`'s axis events into a [`PointerAxisEvent`]
+#[derive(Debug, Clone)]
+pub struct WaylandPointerAxisEvent {
+    pub(crate) time: u32,
+    pub(crate) axis: Axis,
+    pub(crate) amount: f64,
+    pub(crate) discrete: Option<i32>,
+    pub(crate) source: AxisSource,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl WaylandPointerAxisEvent {
+    /// Returns the window this event occurred in.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<WaylandWindow> {
+        self.window.upgrade().map(WaylandWindow)
+    }
+}
+
+impl input::Event<WaylandInput> for WaylandPointerAxisEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl PointerAxisEvent<WaylandInput> for WaylandPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            Some(self.amount)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+        if self.axis == axis {
+            self.discrete.map(f64::from)
+        } else {
+            Some(0.0)
+        }
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source
+    }
+}
+
+/// Wayland-Backend internal event wrapping `wl_pointer`'s button events into a [`PointerButtonEvent`]
+#[derive(Debug, Clone)]
+pub struct WaylandPointerButtonEvent {
+    pub(crate) time: u32,
+    pub(crate) button: u32,
+    pub(crate) state: ButtonState,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl WaylandPointerButtonEvent {
+    /// Returns the window this event occurred in.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<WaylandWindow> {
+        self.window.upgrade().map(WaylandWindow)
+    }
+}
+
+impl input::Event<WaylandInput> for WaylandPointerButtonEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl PointerButtonEvent<WaylandInput> for WaylandPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        // wl_pointer already reports linux button codes
+        self.button
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+/// Wayland-Backend internal event wrapping `wl_pointer`'s motion events into a [`PointerMotionAbsoluteEvent`]
+#[derive(Debug, Clone)]
+pub struct WaylandPointerMotionEvent {
+    pub(crate) time: u32,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<i32, Logical>,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl WaylandPointerMotionEvent {
+    /// Returns the window this event occurred in.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<WaylandWindow> {
+        self.window.upgrade().map(WaylandWindow)
+    }
+}
+
+impl input::Event<WaylandInput> for WaylandPointerMotionEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl PointerMotionAbsoluteEvent<WaylandInput> for WaylandPointerMotionEvent {}
+impl AbsolutePositionEvent<WaylandInput> for WaylandPointerMotionEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+/// Wayland-Backend internal event wrapping `wl_touch`'s down and motion events into
+/// [`TouchDownEvent`] and [`TouchMotionEvent`]
+#[derive(Debug, Clone)]
+pub struct WaylandTouchEvent {
+    pub(crate) time: u32,
+    pub(crate) id: i32,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<i32, Logical>,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl WaylandTouchEvent {
+    /// Returns the window this event occurred in.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<WaylandWindow> {
+        self.window.upgrade().map(WaylandWindow)
+    }
+}
+
+impl input::Event<WaylandInput> for WaylandTouchEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id as u32).into()
+    }
+}
+
+impl AbsolutePositionEvent<WaylandInput> for WaylandTouchEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl TouchDownEvent<WaylandInput> for WaylandTouchEvent {}
+impl TouchMotionEvent<WaylandInput> for WaylandTouchEvent {}
+
+/// Wayland-Backend internal event wrapping `wl_touch`'s up events into a [`TouchUpEvent`]
+#[derive(Debug, Clone)]
+pub struct WaylandTouchUpEvent {
+    pub(crate) time: u32,
+    pub(crate) id: i32,
+}
+
+impl input::Event<WaylandInput> for WaylandTouchUpEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchUpEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id as u32).into()
+    }
+}
+
+impl TouchUpEvent<WaylandInput> for WaylandTouchUpEvent {}
+
+/// Wayland-Backend internal event wrapping `wl_touch`'s cancel events into a [`TouchCancelEvent`]
+///
+/// The parent compositor cancels all touch points at once, so this event is not associated
+/// with a slot.
+#[derive(Debug, Clone)]
+pub struct WaylandTouchCancelEvent;
+
+impl input::Event<WaylandInput> for WaylandTouchCancelEvent {
+    fn time(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl TouchEvent<WaylandInput> for WaylandTouchCancelEvent {
+    fn slot(&self) -> TouchSlot {
+        None.into()
+    }
+}
+
+impl TouchCancelEvent<WaylandInput> for WaylandTouchCancelEvent {}
+
+/// Wayland-Backend internal event marking the end of a set of touch events
+#[derive(Debug, Clone)]
+pub struct WaylandTouchFrameEvent;
+
+impl input::Event<WaylandInput> for WaylandTouchFrameEvent {
+    fn time(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> WaylandVirtualDevice {
+        WaylandVirtualDevice
+    }
+}
+
+impl TouchFrameEvent<WaylandInput> for WaylandTouchFrameEvent {}
+
+impl InputBackend for WaylandInput {
+    type Device = WaylandVirtualDevice;
+    type KeyboardKeyEvent = WaylandKeyboardInputEvent;
+    type PointerAxisEvent = WaylandPointerAxisEvent;
+    type PointerButtonEvent = WaylandPointerButtonEvent;
+
+    type PointerMotionEvent = UnusedEvent;
+
+    type PointerMotionAbsoluteEvent = WaylandPointerMotionEvent;
+
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+
+    type TouchDownEvent = WaylandTouchEvent;
+    type TouchUpEvent = WaylandTouchUpEvent;
+    type TouchMotionEvent = WaylandTouchEvent;
+    type TouchCancelEvent = WaylandTouchCancelEvent;
+    type TouchFrameEvent = WaylandTouchFrameEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+
+    type SpecialEvent = UnusedEvent;
+}
@@ -0,0 +1,424 @@
+//! Implementation of the backend types using a parent wayland compositor.
+//!
+//! This backend provides the appropriate backend implementations to run a Wayland compositor as a
+//! client of another Wayland compositor, talking to it directly through `wayland-client`.
+//!
+//! The backend is initialized using [`WaylandBackend::new`], which connects to the compositor given by
+//! the `WAYLAND_DISPLAY` environment variable. The [`WaylandBackend`] is inserted into an
+//! [`EventLoop`](calloop::EventLoop) to process the events of the parent compositor, while windows
+//! are created through a [`WaylandHandle`]:
+//!
+//! - Every [`WaylandWindow`] is a `wl_surface` with the `xdg_toplevel` role and is usually used as one
+//!   output of the nested compositor, see [`WaylandWindow::create_output`].
+//! - Frames are presented by submitting dmabufs with [`WaylandWindow::submit`], which imports them into
+//!   the parent compositor through `zwp_linux_dmabuf_v1`. Buffers may be allocated with any allocator the
+//!   parent compositor can import from, e.g. a [`GbmAllocator`](crate::backend::allocator::gbm::GbmAllocator)
+//!   rendered to with EGL, using one of the [`WaylandHandle::dmabuf_formats`].
+//! - Keyboard, pointer and touch input of the seat of the parent compositor is translated into
+//!   [`InputEvent`]s, which carry the window they occurred in.
+//! - If the parent compositor supports `wp_fractional_scale_v1` and `wp_viewporter`, the preferred
+//!   fractional scale of every window is passed through, otherwise the integer scale of the outputs
+//!   the window is shown on is used.
+//!
+//! ## Example usage
+//!
+//! ```rust,no_run
+//! # use std::error::Error;
+//! use smithay::backend::wayland::{WaylandBackend, WaylandEvent};
+//!
+//! # struct CompositorState;
+//! fn init_wayland_backend(
+//!    handle: calloop::LoopHandle<CompositorState>,
+//! ) -> Result<(), Box<dyn Error>> {
+//!     let backend = WaylandBackend::new()?;
+//!
+//!     // Create a window, which will be used as an output of the compositor
+//!     let window = backend.handle().create_window("Wayland inside Wayland")?;
+//!     let output = window.create_output("wayland-1");
+//!
+//!     // Insert the backend into the event loop to receive events.
+//!     handle.insert_source(backend, move |event, _, state| match event {
+//!         WaylandEvent::Resized { .. } | WaylandEvent::Frame { .. } => {
+//!             // Render a frame and present it with `window.submit`
+//!         }
+//!         WaylandEvent::Input(event) => {
+//!             // Process input of the parent compositor's seat
+//!         }
+//!         _ => {}
+//!     })?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+mod input;
+mod state;
+mod window;
+
+use std::{
+    collections::HashSet,
+    io,
+    os::unix::io::OwnedFd,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use calloop::{
+    generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use tracing::{debug_span, info, instrument, warn};
+use wayland_client::{
+    backend::WaylandError,
+    globals::{registry_queue_init, BindError, GlobalError, GlobalList},
+    protocol::{wl_compositor::WlCompositor, wl_output::WlOutput, wl_seat::WlSeat},
+    ConnectError, Connection, DispatchError, EventQueue, QueueHandle,
+};
+use wayland_protocols::{
+    wp::{
+        fractional_scale::v1::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        linux_dmabuf::zv1::client::zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1,
+        viewporter::client::wp_viewporter::WpViewporter,
+    },
+    xdg::shell::client::xdg_wm_base::XdgWmBase,
+};
+
+use self::{state::BackendState, window::WindowInner};
+use crate::{
+    backend::{allocator::Format, input::InputEvent},
+    utils::{Logical, Size},
+};
+
+pub use self::input::*;
+pub use self::window::WaylandWindow;
+
+/// An error emitted by the wayland backend.
+#[derive(Debug, thiserror::Error)]
+pub enum WaylandBackendError {
+    /// Connecting to the parent compositor failed.
+    #[error("Connecting to the parent compositor failed")]
+    Connect(#[from] ConnectError),
+
+    /// The globals of the parent compositor could not be retrieved.
+    #[error("The globals of the parent compositor could not be retrieved")]
+    Globals(#[from] GlobalError),
+
+    /// The parent compositor does not support a required protocol.
+    #[error("The parent compositor does not support {0}")]
+    MissingGlobal(&'static str),
+
+    /// Dispatching the events of the parent compositor failed.
+    #[error("Dispatching the events of the parent compositor failed")]
+    Dispatch(#[from] DispatchError),
+
+    /// The connection to the parent compositor failed.
+    #[error("The connection to the parent compositor failed")]
+    Connection(#[from] WaylandError),
+
+    /// A buffer was submitted to a window before it was configured.
+    #[error("The window was not configured by the parent compositor yet")]
+    NotConfigured,
+
+    /// An I/O error occurred while polling the connection.
+    #[error("Polling the connection to the parent compositor failed")]
+    Io(#[from] io::Error),
+}
+
+/// An event emitted by the wayland backend.
+#[derive(Debug)]
+pub enum WaylandEvent {
+    /// An input event occurred.
+    Input(InputEvent<WaylandInput>),
+
+    /// The window was configured with a new size.
+    ///
+    /// This is also emitted once the window was configured initially, buffers may only be submitted
+    /// to the window afterwards.
+    Resized {
+        /// The new size of the window
+        new_size: Size<i32, Logical>,
+        /// Id of the window
+        window_id: u32,
+    },
+
+    /// The preferred scale of the window changed.
+    ScaleChanged {
+        /// The new scale of the window
+        scale: f64,
+        /// Id of the window
+        window_id: u32,
+    },
+
+    /// The parent compositor asked for the next frame of the window.
+    ///
+    /// Emitted once for every submitted buffer, when it is a good time to draw the next frame.
+    Frame {
+        /// Id of the window
+        window_id: u32,
+    },
+
+    /// The window has received a request to be closed.
+    CloseRequested {
+        /// Id of the window
+        window_id: u32,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct Globals {
+    compositor: WlCompositor,
+    wm_base: XdgWmBase,
+    pub(crate) dmabuf: ZwpLinuxDmabufV1,
+    fractional_scale: Option<(WpFractionalScaleManagerV1, WpViewporter)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct HandleInner {
+    pub(crate) connection: Connection,
+    pub(crate) queue_handle: QueueHandle<BackendState>,
+    pub(crate) globals: Globals,
+    formats: HashSet<Format>,
+}
+
+/// User data of the outputs of the parent compositor
+#[derive(Debug)]
+pub(crate) struct OutputData {
+    scale: AtomicI32,
+}
+
+impl OutputData {
+    pub(crate) fn scale(&self) -> i32 {
+        self.scale.load(Ordering::Acquire)
+    }
+}
+
+impl Default for OutputData {
+    fn default() -> Self {
+        OutputData {
+            scale: AtomicI32::new(1),
+        }
+    }
+}
+
+/// An event source processing the events of a parent wayland compositor.
+///
+/// See the [module documentation](self) for an overview of the backend.
+#[derive(Debug)]
+pub struct WaylandBackend {
+    handle: WaylandHandle,
+    queue: EventQueue<BackendState>,
+    state: BackendState,
+    source: Generic<OwnedFd>,
+    span: tracing::Span,
+}
+
+/// A handle to the wayland backend, used to create windows.
+#[derive(Debug, Clone)]
+pub struct WaylandHandle {
+    inner: Arc<HandleInner>,
+}
+
+fn bind_error(interface: &'static str) -> impl Fn(BindError) -> WaylandBackendError {
+    move |_| WaylandBackendError::MissingGlobal(interface)
+}
+
+impl WaylandBackend {
+    /// Connects to the parent compositor given by the environment.
+    ///
+    /// The parent compositor needs to support `xdg_wm_base` and `zwp_linux_dmabuf_v1` version 3 or newer.
+    pub fn new() -> Result<WaylandBackend, WaylandBackendError> {
+        let span = debug_span!("backend_wayland");
+        let guard = span.enter();
+
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<BackendState>(&connection)?;
+        let queue_handle = queue.handle();
+
+        let compositor = globals
+            .bind::<WlCompositor, _, _>(&queue_handle, 4..=5, ())
+            .map_err(bind_error("wl_compositor"))?;
+        let wm_base = globals
+            .bind::<XdgWmBase, _, _>(&queue_handle, 1..=5, ())
+            .map_err(bind_error("xdg_wm_base"))?;
+        let dmabuf = globals
+            .bind::<ZwpLinuxDmabufV1, _, _>(&queue_handle, 3..=3, ())
+            .map_err(bind_error("zwp_linux_dmabuf_v1"))?;
+        let fractional_scale = match (
+            globals.bind::<WpFractionalScaleManagerV1, _, _>(&queue_handle, 1..=1, ()),
+            globals.bind::<WpViewporter, _, _>(&queue_handle, 1..=1, ()),
+        ) {
+            (Ok(manager), Ok(viewporter)) => Some((manager, viewporter)),
+            (manager, viewporter) => {
+                info!("Parent compositor does not support fractional scaling");
+                if let Ok(manager) = manager {
+                    manager.destroy();
+                }
+                if let Ok(viewporter) = viewporter {
+                    viewporter.destroy();
+                }
+                None
+            }
+        };
+        if globals.bind::<WlSeat, _, _>(&queue_handle, 5..=7, ()).is_err() {
+            warn!("Parent compositor has no seat, input will not be available");
+        }
+        bind_outputs(&globals, &queue_handle);
+
+        // receive the supported formats, output scales and seat capabilities
+        let mut state = BackendState::default();
+        queue.roundtrip(&mut state)?;
+
+        let fd = queue.prepare_read()?.connection_fd().try_clone_to_owned()?;
+        let source = Generic::new(fd, Interest::READ, Mode::Level);
+        let handle = WaylandHandle {
+            inner: Arc::new(HandleInner {
+                connection,
+                queue_handle,
+                globals: Globals {
+                    compositor,
+                    wm_base,
+                    dmabuf,
+                    fractional_scale,
+                },
+                formats: std::mem::take(&mut state.formats),
+            }),
+        };
+        info!(
+            formats = handle.inner.formats.len(),
+            "Connected to parent compositor"
+        );
+
+        drop(guard);
+        Ok(WaylandBackend {
+            handle,
+            queue,
+            state,
+            source,
+            span,
+        })
+    }
+
+    /// Returns a handle to the backend.
+    pub fn handle(&self) -> WaylandHandle {
+        self.handle.clone()
+    }
+}
+
+fn bind_outputs(globals: &GlobalList, queue_handle: &QueueHandle<BackendState>) {
+    for global in globals.contents().clone_list() {
+        if global.interface == "wl_output" {
+            globals.registry().bind::<WlOutput, _, _>(
+                global.name,
+                global.version.min(2),
+                queue_handle,
+                OutputData::default(),
+            );
+        }
+    }
+}
+
+impl WaylandHandle {
+    /// Returns the connection to the parent compositor.
+    pub fn connection(&self) -> &Connection {
+        &self.inner.connection
+    }
+
+    /// Returns the dmabuf formats the parent compositor can import.
+    pub fn dmabuf_formats(&self) -> impl Iterator<Item = &Format> {
+        self.inner.formats.iter()
+    }
+
+    /// Creates a new toplevel window in the parent compositor.
+    ///
+    /// The window may only be presented to once it was configured, see [`WaylandEvent::Resized`].
+    pub fn create_window(&self, title: &str) -> Result<WaylandWindow, WaylandBackendError> {
+        let handle = &self.inner;
+        let queue_handle = &handle.queue_handle;
+        let globals = &handle.globals;
+
+        let inner = Arc::new_cyclic(|weak| {
+            let surface = globals.compositor.create_surface(queue_handle, weak.clone());
+            let xdg_surface = globals
+                .wm_base
+                .get_xdg_surface(&surface, queue_handle, weak.clone());
+            let toplevel = xdg_surface.get_toplevel(queue_handle, weak.clone());
+            toplevel.set_title(title.into());
+            toplevel.set_app_id("smithay".into());
+
+            let (fractional_scale, viewport) = match globals.fractional_scale.as_ref() {
+                Some((manager, viewporter)) => (
+                    Some(manager.get_fractional_scale(&surface, queue_handle, weak.clone())),
+                    Some(viewporter.get_viewport(&surface, queue_handle, ())),
+                ),
+                None => (None, None),
+            };
+
+            // the initial commit without a buffer asks for the initial configure
+            surface.commit();
+            WindowInner::new(
+                surface,
+                xdg_surface,
+                toplevel,
+                fractional_scale,
+                viewport,
+                handle.clone(),
+            )
+        });
+        handle.connection.flush()?;
+
+        Ok(WaylandWindow(inner))
+    }
+}
+
+impl EventSource for WaylandBackend {
+    type Event = WaylandEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = WaylandBackendError;
+
+    #[instrument(level = "trace", parent = &self.span, skip_all)]
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut ()),
+    {
+        let queue = &self.queue;
+        let post_action = self.source.process_events(readiness, token, |_, _| {
+            // events already queued are dispatched below
+            if let Ok(guard) = queue.prepare_read() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(WaylandError::Io(err)) => return Err(err),
+                    Err(err) => return Err(io::Error::new(io::ErrorKind::Other, err)),
+                }
+            }
+            Ok(PostAction::Continue)
+        })?;
+
+        self.queue.dispatch_pending(&mut self.state)?;
+        for event in self.state.events.drain(..) {
+            callback(event, &mut ());
+        }
+        self.handle.inner.connection.flush()?;
+
+        Ok(post_action)
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
@@ -0,0 +1,623 @@
+//! Dispatching of the events of the parent compositor
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::Ordering, Weak},
+};
+
+use tracing::{debug, trace, warn};
+use wayland_client::{
+    globals::GlobalListContents,
+    protocol::{
+        wl_buffer::WlBuffer,
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_output::{self, WlOutput},
+        wl_pointer::{self, WlPointer},
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_surface::{self, WlSurface},
+        wl_touch::{self, WlTouch},
+    },
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols::{
+    wp::{
+        fractional_scale::v1::client::{
+            wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+            wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+        },
+        linux_dmabuf::zv1::client::{
+            zwp_linux_buffer_params_v1::{self, ZwpLinuxBufferParamsV1},
+            zwp_linux_dmabuf_v1::{self, ZwpLinuxDmabufV1},
+        },
+        viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+    },
+    xdg::shell::client::{
+        xdg_surface::{self, XdgSurface},
+        xdg_toplevel::{self, XdgToplevel},
+        xdg_wm_base::{self, XdgWmBase},
+    },
+};
+
+use super::{
+    window::WindowInner, OutputData, WaylandEvent, WaylandKeyboardInputEvent, WaylandPointerAxisEvent,
+    WaylandPointerButtonEvent, WaylandPointerMotionEvent, WaylandTouchCancelEvent, WaylandTouchEvent,
+    WaylandTouchFrameEvent, WaylandTouchUpEvent, WaylandVirtualDevice,
+};
+use crate::backend::{
+    allocator::{Format, Fourcc, Modifier},
+    input::{Axis, AxisSource, ButtonState, InputEvent, KeyState},
+};
+
+/// State of the seat of the parent compositor
+#[derive(Debug, Default)]
+struct SeatState {
+    pointer: Option<WlPointer>,
+    keyboard: Option<WlKeyboard>,
+    touch: Option<WlTouch>,
+    device_added: bool,
+    pointer_focus: Weak<WindowInner>,
+    keyboard_focus: Weak<WindowInner>,
+    touch_focus: HashMap<i32, Weak<WindowInner>>,
+    pressed_keys: HashSet<u32>,
+    // axis information of the current pointer frame
+    axis_source: Option<AxisSource>,
+    axis_discrete: [Option<i32>; 2],
+}
+
+/// Dispatch state of the event queue of the backend
+#[derive(Debug, Default)]
+pub(crate) struct BackendState {
+    pub(crate) events: Vec<WaylandEvent>,
+    pub(crate) formats: HashSet<Format>,
+    seat: SeatState,
+}
+
+fn window_of(surface: &WlSurface) -> Weak<WindowInner> {
+    surface.data::<Weak<WindowInner>>().cloned().unwrap_or_default()
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for BackendState {
+    fn event(
+        _state: &mut Self,
+        registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        // outputs are only used to determine the scale of windows, other globals are bound once
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            if interface == WlOutput::interface().name {
+                registry.bind::<WlOutput, _, _>(name, version.min(2), qh, OutputData::default());
+            }
+        }
+    }
+}
+
+impl Dispatch<WlOutput, OutputData> for BackendState {
+    fn event(
+        _state: &mut Self,
+        _output: &WlOutput,
+        event: wl_output::Event,
+        data: &OutputData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Scale { factor } = event {
+            data.scale.store(factor, Ordering::Release);
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for BackendState {
+    fn event(
+        _state: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<WlSurface, Weak<WindowInner>> for BackendState {
+    fn event(
+        state: &mut Self,
+        _surface: &WlSurface,
+        event: wl_surface::Event,
+        data: &Weak<WindowInner>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(window) = data.upgrade() else {
+            return;
+        };
+        let mut window_state = window.state.lock().unwrap();
+        match event {
+            wl_surface::Event::Enter { output } => window_state.outputs.push(output),
+            wl_surface::Event::Leave { output } => window_state.outputs.retain(|o| o != &output),
+            _ => return,
+        }
+
+        // the fractional scale takes precedence if supported
+        if window.fractional_scale.is_some() {
+            return;
+        }
+        let scale = WindowInner::output_scale(&window_state.outputs);
+        if scale != window_state.scale {
+            window_state.scale = scale;
+            state.events.push(WaylandEvent::ScaleChanged {
+                scale,
+                window_id: window.id(),
+            });
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleV1, Weak<WindowInner>> for BackendState {
+    fn event(
+        state: &mut Self,
+        _fractional_scale: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        data: &Weak<WindowInner>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(window) = data.upgrade() else {
+            return;
+        };
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            let scale = scale as f64 / 120.0;
+            let mut window_state = window.state.lock().unwrap();
+            if scale != window_state.scale {
+                window_state.scale = scale;
+                state.events.push(WaylandEvent::ScaleChanged {
+                    scale,
+                    window_id: window.id(),
+                });
+            }
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, Weak<WindowInner>> for BackendState {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &XdgSurface,
+        event: xdg_surface::Event,
+        data: &Weak<WindowInner>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let xdg_surface::Event::Configure { serial } = event else {
+            return;
+        };
+        xdg_surface.ack_configure(serial);
+
+        let Some(window) = data.upgrade() else {
+            return;
+        };
+        let mut window_state = window.state.lock().unwrap();
+        let new_size = window_state.pending_size.take().unwrap_or(window_state.size);
+        if !window_state.configured || new_size != window_state.size {
+            window_state.configured = true;
+            window_state.size = new_size;
+            debug!(window = window.id(), ?new_size, "Window configured");
+            state.events.push(WaylandEvent::Resized {
+                new_size,
+                window_id: window.id(),
+            });
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, Weak<WindowInner>> for BackendState {
+    fn event(
+        state: &mut Self,
+        _toplevel: &XdgToplevel,
+        event: xdg_toplevel::Event,
+        data: &Weak<WindowInner>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(window) = data.upgrade() else {
+            return;
+        };
+        match event {
+            xdg_toplevel::Event::Configure { width, height, .. } => {
+                // a size of zero leaves the size up to us
+                if width > 0 && height > 0 {
+                    window.state.lock().unwrap().pending_size = Some((width, height).into());
+                }
+            }
+            xdg_toplevel::Event::Close => state.events.push(WaylandEvent::CloseRequested {
+                window_id: window.id(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlCallback, Weak<WindowInner>> for BackendState {
+    fn event(
+        state: &mut Self,
+        _callback: &WlCallback,
+        event: wl_callback::Event,
+        data: &Weak<WindowInner>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let (wl_callback::Event::Done { .. }, Some(window)) = (event, data.upgrade()) {
+            state.events.push(WaylandEvent::Frame {
+                window_id: window.id(),
+            });
+        }
+    }
+}
+
+impl Dispatch<ZwpLinuxDmabufV1, ()> for BackendState {
+    fn event(
+        state: &mut Self,
+        _dmabuf: &ZwpLinuxDmabufV1,
+        event: zwp_linux_dmabuf_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwp_linux_dmabuf_v1::Event::Modifier {
+            format,
+            modifier_hi,
+            modifier_lo,
+        } = event
+        {
+            let Ok(code) = Fourcc::try_from(format) else {
+                return;
+            };
+            let modifier = Modifier::from(((modifier_hi as u64) << 32) | modifier_lo as u64);
+            state.formats.insert(Format { code, modifier });
+        }
+    }
+}
+
+impl Dispatch<ZwpLinuxBufferParamsV1, ()> for BackendState {
+    fn event(
+        _state: &mut Self,
+        _params: &ZwpLinuxBufferParamsV1,
+        event: zwp_linux_buffer_params_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // buffers are created with `create_immed`, so failures are the only events
+        if let zwp_linux_buffer_params_v1::Event::Failed = event {
+            warn!("Parent compositor failed to import dmabuf");
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for BackendState {
+    fn event(
+        state: &mut Self,
+        seat: &WlSeat,
+        event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        else {
+            return;
+        };
+
+        let seat_state = &mut state.seat;
+        let pointer = capabilities.contains(wl_seat::Capability::Pointer);
+        match (pointer, seat_state.pointer.take()) {
+            (true, None) => seat_state.pointer = Some(seat.get_pointer(qh, ())),
+            (true, Some(old)) => seat_state.pointer = Some(old),
+            (false, Some(old)) => old.release(),
+            (false, None) => {}
+        }
+        let keyboard = capabilities.contains(wl_seat::Capability::Keyboard);
+        match (keyboard, seat_state.keyboard.take()) {
+            (true, None) => seat_state.keyboard = Some(seat.get_keyboard(qh, ())),
+            (true, Some(old)) => seat_state.keyboard = Some(old),
+            (false, Some(old)) => old.release(),
+            (false, None) => {}
+        }
+        let touch = capabilities.contains(wl_seat::Capability::Touch);
+        match (touch, seat_state.touch.take()) {
+            (true, None) => seat_state.touch = Some(seat.get_touch(qh, ())),
+            (true, Some(old)) => seat_state.touch = Some(old),
+            (false, Some(old)) => old.release(),
+            (false, None) => {}
+        }
+
+        if !seat_state.device_added {
+            seat_state.device_added = true;
+            state.events.push(WaylandEvent::Input(InputEvent::DeviceAdded {
+                device: WaylandVirtualDevice,
+            }));
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for BackendState {
+    fn event(
+        state: &mut Self,
+        pointer: &WlPointer,
+        event: wl_pointer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let seat_state = &mut state.seat;
+        let event = match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface,
+                surface_x,
+                surface_y,
+            } => {
+                // the nested compositor draws its own cursor
+                pointer.set_cursor(serial, None, 0, 0);
+                seat_state.pointer_focus = window_of(&surface);
+                motion_event(&seat_state.pointer_focus, 0, surface_x, surface_y)
+            }
+            wl_pointer::Event::Leave { .. } => {
+                seat_state.pointer_focus = Weak::new();
+                None
+            }
+            wl_pointer::Event::Motion {
+                time,
+                surface_x,
+                surface_y,
+            } => motion_event(&seat_state.pointer_focus, time, surface_x, surface_y),
+            wl_pointer::Event::Button {
+                time,
+                button,
+                state: WEnum::Value(button_state),
+                ..
+            } => Some(InputEvent::PointerButton {
+                event: WaylandPointerButtonEvent {
+                    time,
+                    button,
+                    state: match button_state {
+                        wl_pointer::ButtonState::Pressed => ButtonState::Pressed,
+                        _ => ButtonState::Released,
+                    },
+                    window: seat_state.pointer_focus.clone(),
+                },
+            }),
+            wl_pointer::Event::Axis {
+                time,
+                axis: WEnum::Value(axis),
+                value,
+            } => {
+                let (axis, idx) = match axis {
+                    wl_pointer::Axis::HorizontalScroll => (Axis::Horizontal, 0),
+                    _ => (Axis::Vertical, 1),
+                };
+                Some(InputEvent::PointerAxis {
+                    event: WaylandPointerAxisEvent {
+                        time,
+                        axis,
+                        amount: value,
+                        discrete: seat_state.axis_discrete[idx].take(),
+                        source: seat_state.axis_source.unwrap_or(AxisSource::Continuous),
+                        window: seat_state.pointer_focus.clone(),
+                    },
+                })
+            }
+            wl_pointer::Event::AxisSource {
+                axis_source: WEnum::Value(axis_source),
+            } => {
+                seat_state.axis_source = Some(match axis_source {
+                    wl_pointer::AxisSource::Wheel => AxisSource::Wheel,
+                    wl_pointer::AxisSource::Finger => AxisSource::Finger,
+                    wl_pointer::AxisSource::WheelTilt => AxisSource::WheelTilt,
+                    _ => AxisSource::Continuous,
+                });
+                None
+            }
+            wl_pointer::Event::AxisDiscrete {
+                axis: WEnum::Value(axis),
+                discrete,
+            } => {
+                let idx = match axis {
+                    wl_pointer::Axis::HorizontalScroll => 0,
+                    _ => 1,
+                };
+                seat_state.axis_discrete[idx] = Some(discrete);
+                None
+            }
+            wl_pointer::Event::Frame => {
+                seat_state.axis_source = None;
+                seat_state.axis_discrete = [None; 2];
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(event) = event {
+            state.events.push(WaylandEvent::Input(event));
+        }
+    }
+}
+
+fn motion_event(
+    window: &Weak<WindowInner>,
+    time: u32,
+    x: f64,
+    y: f64,
+) -> Option<InputEvent<super::WaylandInput>> {
+    let size = window.upgrade()?.state.lock().unwrap().size;
+    Some(InputEvent::PointerMotionAbsolute {
+        event: WaylandPointerMotionEvent {
+            time,
+            x,
+            y,
+            size,
+            window: window.clone(),
+        },
+    })
+}
+
+impl Dispatch<WlKeyboard, ()> for BackendState {
+    fn event(
+        state: &mut Self,
+        _keyboard: &WlKeyboard,
+        event: wl_keyboard::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let seat_state = &mut state.seat;
+        match event {
+            // the keymap of the parent compositor is not used, keys are forwarded as evdev codes
+            wl_keyboard::Event::Enter { surface, .. } => {
+                seat_state.keyboard_focus = window_of(&surface);
+            }
+            wl_keyboard::Event::Leave { .. } => {
+                // keys released while unfocused are never reported, so release them now
+                let window = std::mem::take(&mut seat_state.keyboard_focus);
+                let pressed = std::mem::take(&mut seat_state.pressed_keys);
+                let mut count = pressed.len() as u32;
+                for key in pressed {
+                    count -= 1;
+                    state.events.push(WaylandEvent::Input(InputEvent::Keyboard {
+                        event: WaylandKeyboardInputEvent {
+                            time: 0,
+                            key,
+                            count,
+                            state: KeyState::Released,
+                            window: window.clone(),
+                        },
+                    }));
+                }
+            }
+            wl_keyboard::Event::Key {
+                time,
+                key,
+                state: WEnum::Value(key_state),
+                ..
+            } => {
+                let key_state = match key_state {
+                    wl_keyboard::KeyState::Pressed => {
+                        seat_state.pressed_keys.insert(key);
+                        KeyState::Pressed
+                    }
+                    _ => {
+                        seat_state.pressed_keys.remove(&key);
+                        KeyState::Released
+                    }
+                };
+                trace!(key, ?key_state, "Key event");
+                state.events.push(WaylandEvent::Input(InputEvent::Keyboard {
+                    event: WaylandKeyboardInputEvent {
+                        time,
+                        key,
+                        count: seat_state.pressed_keys.len() as u32,
+                        state: key_state,
+                        window: seat_state.keyboard_focus.clone(),
+                    },
+                }));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlTouch, ()> for BackendState {
+    fn event(
+        state: &mut Self,
+        _touch: &WlTouch,
+        event: wl_touch::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let seat_state = &mut state.seat;
+        let event = match event {
+            wl_touch::Event::Down {
+                time,
+                surface,
+                id,
+                x,
+                y,
+                ..
+            } => {
+                let window = window_of(&surface);
+                seat_state.touch_focus.insert(id, window.clone());
+                touch_event(window, time, id, x, y).map(|event| InputEvent::TouchDown { event })
+            }
+            wl_touch::Event::Motion { time, id, x, y } => {
+                let window = seat_state.touch_focus.get(&id).cloned().unwrap_or_default();
+                touch_event(window, time, id, x, y).map(|event| InputEvent::TouchMotion { event })
+            }
+            wl_touch::Event::Up { time, id, .. } => {
+                seat_state.touch_focus.remove(&id);
+                Some(InputEvent::TouchUp {
+                    event: WaylandTouchUpEvent { time, id },
+                })
+            }
+            wl_touch::Event::Frame => Some(InputEvent::TouchFrame {
+                event: WaylandTouchFrameEvent,
+            }),
+            wl_touch::Event::Cancel => {
+                seat_state.touch_focus.clear();
+                Some(InputEvent::TouchCancel {
+                    event: WaylandTouchCancelEvent,
+                })
+            }
+            _ => None,
+        };
+
+        if let Some(event) = event {
+            state.events.push(WaylandEvent::Input(event));
+        }
+    }
+}
+
+fn touch_event(window: Weak<WindowInner>, time: u32, id: i32, x: f64, y: f64) -> Option<WaylandTouchEvent> {
+    let size = window.upgrade()?.state.lock().unwrap().size;
+    Some(WaylandTouchEvent {
+        time,
+        id,
+        x,
+        y,
+        size,
+        window,
+    })
+}
+
+impl Dispatch<WlBuffer, ()> for BackendState {
+    fn event(
+        _state: &mut Self,
+        _buffer: &WlBuffer,
+        _event: <WlBuffer as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // buffers are owned by the compositor rendering into them, releases are not tracked
+    }
+}
+
+wayland_client::delegate_noop!(BackendState: WlCompositor);
+wayland_client::delegate_noop!(BackendState: WpFractionalScaleManagerV1);
+wayland_client::delegate_noop!(BackendState: WpViewporter);
+wayland_client::delegate_noop!(BackendState: WpViewport);
@@ -0,0 +1,307 @@
+use std::{
+    os::unix::io::AsRawFd,
+    sync::{Arc, Mutex},
+};
+
+use tracing::trace;
+use wayland_client::{
+    protocol::{wl_buffer::WlBuffer, wl_output::WlOutput, wl_surface::WlSurface},
+    Proxy,
+};
+use wayland_protocols::{
+    wp::{
+        fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1,
+        linux_dmabuf::zv1::client::zwp_linux_buffer_params_v1, viewporter::client::wp_viewport::WpViewport,
+    },
+    xdg::shell::client::{xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel},
+};
+
+use super::{HandleInner, WaylandBackendError};
+use crate::{
+    backend::allocator::{
+        dmabuf::{Dmabuf, WeakDmabuf},
+        Buffer,
+    },
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    utils::{Logical, Physical, Rectangle, Size},
+};
+
+/// Size of a window until the parent compositor suggests one
+const DEFAULT_SIZE: (i32, i32) = (1280, 800);
+
+/// Refresh rate reported for windows, in millihertz
+///
+/// The parent compositor paces the windows with frame callbacks instead.
+const DEFAULT_REFRESH: i32 = 60_000;
+
+#[derive(Debug)]
+pub(crate) struct WindowState {
+    pub(crate) size: Size<i32, Logical>,
+    pub(crate) pending_size: Option<Size<i32, Logical>>,
+    pub(crate) configured: bool,
+    pub(crate) scale: f64,
+    pub(crate) outputs: Vec<WlOutput>,
+    buffers: Vec<(WeakDmabuf, WlBuffer)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct WindowInner {
+    pub(crate) surface: WlSurface,
+    pub(crate) xdg_surface: XdgSurface,
+    pub(crate) toplevel: XdgToplevel,
+    pub(crate) fractional_scale: Option<WpFractionalScaleV1>,
+    pub(crate) viewport: Option<WpViewport>,
+    pub(crate) state: Mutex<WindowState>,
+    pub(crate) handle: Arc<HandleInner>,
+}
+
+impl WindowInner {
+    pub(crate) fn new(
+        surface: WlSurface,
+        xdg_surface: XdgSurface,
+        toplevel: XdgToplevel,
+        fractional_scale: Option<WpFractionalScaleV1>,
+        viewport: Option<WpViewport>,
+        handle: Arc<HandleInner>,
+    ) -> WindowInner {
+        WindowInner {
+            surface,
+            xdg_surface,
+            toplevel,
+            fractional_scale,
+            viewport,
+            state: Mutex::new(WindowState {
+                size: DEFAULT_SIZE.into(),
+                pending_size: None,
+                configured: false,
+                scale: 1.0,
+                outputs: Vec::new(),
+                buffers: Vec::new(),
+            }),
+            handle,
+        }
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.surface.id().protocol_id()
+    }
+
+    /// Scale of the outputs of the parent compositor the window is shown on
+    ///
+    /// Used if the parent compositor does not support fractional scaling.
+    pub(crate) fn output_scale(outputs: &[WlOutput]) -> f64 {
+        outputs
+            .iter()
+            .filter_map(|output| output.data::<super::OutputData>())
+            .map(|data| data.scale())
+            .max()
+            .unwrap_or(1) as f64
+    }
+
+    fn import_dmabuf(&self, dmabuf: &Dmabuf) -> WlBuffer {
+        let handle = &self.handle;
+        let params = handle.globals.dmabuf.create_params(&handle.queue_handle, ());
+        let format = dmabuf.format();
+        let modifier: u64 = format.modifier.into();
+
+        for (idx, ((fd, offset), stride)) in dmabuf
+            .handles()
+            .zip(dmabuf.offsets())
+            .zip(dmabuf.strides())
+            .enumerate()
+        {
+            params.add(
+                fd.as_raw_fd(),
+                idx as u32,
+                offset,
+                stride,
+                (modifier >> 32) as u32,
+                (modifier & 0xFFFF_FFFF) as u32,
+            );
+        }
+
+        let flags = if dmabuf.y_inverted() {
+            zwp_linux_buffer_params_v1::Flags::YInvert
+        } else {
+            zwp_linux_buffer_params_v1::Flags::empty()
+        };
+        let size = dmabuf.size();
+        let buffer = params.create_immed(
+            size.w,
+            size.h,
+            format.code as u32,
+            flags,
+            &handle.queue_handle,
+            (),
+        );
+        params.destroy();
+        trace!(
+            window = self.id(),
+            ?format,
+            "Imported dmabuf into the parent compositor"
+        );
+        buffer
+    }
+}
+
+impl Drop for WindowInner {
+    fn drop(&mut self) {
+        for (_, buffer) in self.state.get_mut().unwrap().buffers.drain(..) {
+            buffer.destroy();
+        }
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+        if let Some(fractional_scale) = self.fractional_scale.take() {
+            fractional_scale.destroy();
+        }
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+        let _ = self.handle.connection.flush();
+    }
+}
+
+/// A toplevel window in the parent compositor.
+///
+/// Created through [`WaylandHandle::create_window`](super::WaylandHandle::create_window), the window is
+/// closed once all references to it are dropped.
+#[derive(Debug, Clone)]
+pub struct WaylandWindow(pub(crate) Arc<WindowInner>);
+
+impl WaylandWindow {
+    /// Returns the id of the window.
+    ///
+    /// Events concerning this window carry this id.
+    pub fn id(&self) -> u32 {
+        self.0.id()
+    }
+
+    /// Returns whether the parent compositor has configured the window.
+    ///
+    /// Buffers may only be submitted after the window was configured, which is
+    /// signaled by the first [`WaylandEvent::Resized`](super::WaylandEvent::Resized).
+    pub fn is_configured(&self) -> bool {
+        self.0.state.lock().unwrap().configured
+    }
+
+    /// Returns the size of the window in the logical coordinate space of the parent compositor.
+    pub fn size(&self) -> Size<i32, Logical> {
+        self.0.state.lock().unwrap().size
+    }
+
+    /// Returns the scale the parent compositor prefers for the window.
+    ///
+    /// This is the fractional scale if the parent compositor supports `wp_fractional_scale_v1`,
+    /// otherwise the highest integer scale of the outputs the window is shown on.
+    pub fn scale(&self) -> f64 {
+        self.0.state.lock().unwrap().scale
+    }
+
+    /// Returns the size buffers submitted to this window should have.
+    pub fn buffer_size(&self) -> Size<i32, Physical> {
+        let state = self.0.state.lock().unwrap();
+        state.size.to_physical_precise_round(state.scale)
+    }
+
+    /// Sets the title of the window.
+    pub fn set_title(&self, title: &str) {
+        self.0.toplevel.set_title(title.into());
+    }
+
+    /// Returns the mode of an output matching this window.
+    ///
+    /// The refresh rate is not known and assumed to be 60Hz.
+    pub fn output_mode(&self) -> Mode {
+        Mode {
+            size: self.buffer_size(),
+            refresh: DEFAULT_REFRESH,
+        }
+    }
+
+    /// Creates an [`Output`] for this window.
+    ///
+    /// The output uses the [mode](WaylandWindow::output_mode) and the [scale](WaylandWindow::scale)
+    /// of the window. It has to be positioned and updated on [`WaylandEvent::Resized`](super::WaylandEvent::Resized)
+    /// and [`WaylandEvent::ScaleChanged`](super::WaylandEvent::ScaleChanged) by the compositor, and a global for
+    /// it still has to be created.
+    pub fn create_output(&self, name: impl Into<String>) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Wayland Window".into(),
+            },
+        );
+        let mode = self.output_mode();
+        output.change_current_state(Some(mode), None, Some(Scale::Fractional(self.scale())), None);
+        output.set_preferred(mode);
+        output
+    }
+
+    /// Presents a dmabuf in the window.
+    ///
+    /// The dmabuf is imported into the parent compositor through `zwp_linux_dmabuf_v1`, see
+    /// [`WaylandHandle::dmabuf_formats`](super::WaylandHandle::dmabuf_formats) for the supported formats.
+    /// Imports are cached for as long as the dmabuf is alive, so buffers of a swapchain are only imported
+    /// once. The buffer should have the [buffer size](WaylandWindow::buffer_size) of the window, `damage`
+    /// is given in buffer coordinates.
+    ///
+    /// A [`WaylandEvent::Frame`](super::WaylandEvent::Frame) is emitted once the parent compositor wants
+    /// the next frame to be drawn.
+    pub fn submit(
+        &self,
+        dmabuf: &Dmabuf,
+        damage: &[Rectangle<i32, Physical>],
+    ) -> Result<(), WaylandBackendError> {
+        let inner = &self.0;
+        let mut state = inner.state.lock().unwrap();
+        if !state.configured {
+            return Err(WaylandBackendError::NotConfigured);
+        }
+
+        state.buffers.retain(|(weak, buffer)| {
+            if weak.is_gone() {
+                buffer.destroy();
+                false
+            } else {
+                true
+            }
+        });
+        let weak = dmabuf.weak();
+        let buffer = match state.buffers.iter().find(|(other, _)| *other == weak) {
+            Some((_, buffer)) => buffer.clone(),
+            None => {
+                let buffer = inner.import_dmabuf(dmabuf);
+                state.buffers.push((weak, buffer.clone()));
+                buffer
+            }
+        };
+
+        let surface = &inner.surface;
+        surface.attach(Some(&buffer), 0, 0);
+        if damage.is_empty() {
+            surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        }
+        for rect in damage {
+            surface.damage_buffer(rect.loc.x, rect.loc.y, rect.size.w, rect.size.h);
+        }
+        match inner.viewport.as_ref() {
+            Some(viewport) => viewport.set_destination(state.size.w, state.size.h),
+            None => surface.set_buffer_scale(state.scale as i32),
+        }
+        surface.frame(&inner.handle.queue_handle, Arc::downgrade(inner));
+        surface.commit();
+        inner.handle.connection.flush()?;
+
+        Ok(())
+    }
+}
+
+impl PartialEq for WaylandWindow {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
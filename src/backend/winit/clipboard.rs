@@ -0,0 +1,199 @@
+//! Bridging of the host clipboard into the data device selection of the nested compositor
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::unix::io::{FromRawFd, OwnedFd},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+};
+
+use nix::fcntl::OFlag;
+use tracing::{debug, warn};
+use wayland_server::DisplayHandle;
+
+use crate::{
+    input::{Seat, SeatHandler},
+    wayland::data_device::{
+        current_data_device_selection_userdata, request_data_device_client_selection,
+        set_data_device_selection, DataDeviceHandler,
+    },
+};
+
+/// Mime types used for plain text, in order of preference
+const TEXT_MIME_TYPES: [&str; 5] = [
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "TEXT",
+    "STRING",
+];
+
+/// Text of the host clipboard offered as data device selection
+///
+/// This is the user data of the selections set by [`WinitClipboard::sync_from_host`],
+/// your [`DataDeviceHandler::SelectionUserData`] needs to be convertible from it.
+/// Use [`HostSelection::send`] in [`DataDeviceHandler::send_selection`] to answer requests
+/// of clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostSelection {
+    text: Arc<str>,
+}
+
+impl HostSelection {
+    /// The text copied on the host
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Write the text into the fd of a client reading the selection
+    ///
+    /// The text is written from a separate thread, so a slow client cannot block the compositor.
+    pub fn send(&self, mime_type: &str, fd: OwnedFd) {
+        if !TEXT_MIME_TYPES.contains(&mime_type) {
+            warn!(mime_type, "Mime type not offered by the host selection");
+            return;
+        }
+
+        let text = self.text.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = File::from(fd).write_all(text.as_bytes()) {
+                debug!(?err, "Failed to send host selection");
+            }
+        });
+    }
+}
+
+/// Bridge between the clipboard of the host and the data device selection of a seat
+///
+/// Obtained through [`WinitEventLoop::clipboard`](super::WinitEventLoop::clipboard), which is only
+/// supported if winit runs on Wayland. The host does not notify about clipboard changes,
+/// so the compositor has to synchronize the selection at appropriate times, usually whenever the
+/// focus of the window changes:
+///
+/// - [`sync_from_host`](WinitClipboard::sync_from_host) on [`WinitEvent::Focus(true)`](super::WinitEvent::Focus)
+///   offers the text copied on the host to the clients of the compositor,
+/// - [`sync_to_host`](WinitClipboard::sync_to_host) on [`WinitEvent::Focus(false)`](super::WinitEvent::Focus)
+///   copies the text selection of the clients to the host.
+///
+/// Only plain text is bridged.
+pub struct WinitClipboard {
+    clipboard: smithay_clipboard::Clipboard,
+    last_text: Option<String>,
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+}
+
+impl std::fmt::Debug for WinitClipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WinitClipboard")
+            .field("last_text", &self.last_text)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WinitClipboard {
+    /// Safety: `display` has to be a valid `wl_display` outliving the returned clipboard.
+    pub(super) unsafe fn new(display: *mut std::ffi::c_void) -> WinitClipboard {
+        let (sender, receiver) = mpsc::channel();
+        WinitClipboard {
+            clipboard: smithay_clipboard::Clipboard::new(display),
+            last_text: None,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Offer the text copied on the host as selection of the given seat
+    ///
+    /// Does nothing if the host clipboard did not change since the last synchronization in either
+    /// direction, so the selection of a client is not replaced by its own text.
+    pub fn sync_from_host<D>(&mut self, dh: &DisplayHandle, seat: &Seat<D>)
+    where
+        D: SeatHandler + DataDeviceHandler + 'static,
+        D::SelectionUserData: From<HostSelection>,
+    {
+        self.store_transferred();
+
+        let text = match self.clipboard.load() {
+            Ok(text) => text,
+            Err(err) => {
+                debug!(?err, "Failed to load host clipboard");
+                return;
+            }
+        };
+        if text.is_empty() || self.last_text.as_ref() == Some(&text) {
+            return;
+        }
+
+        debug!("Host clipboard changed");
+        let selection = HostSelection {
+            text: Arc::from(text.as_str()),
+        };
+        self.last_text = Some(text);
+        set_data_device_selection(
+            dh,
+            seat,
+            TEXT_MIME_TYPES.iter().map(|mime| mime.to_string()).collect(),
+            selection.into(),
+        );
+    }
+
+    /// Copy the text selection of a client of the given seat to the host
+    ///
+    /// The transfer happens asynchronously, the text is stored in the host clipboard on one of the
+    /// next calls to [`WinitEventLoop::dispatch_new_events`](super::WinitEventLoop::dispatch_new_events).
+    /// Selections set by the compositor, including the ones set by [`sync_from_host`](WinitClipboard::sync_from_host),
+    /// are not copied.
+    pub fn sync_to_host<D>(&mut self, seat: &Seat<D>)
+    where
+        D: SeatHandler + DataDeviceHandler + 'static,
+    {
+        if current_data_device_selection_userdata(seat).is_some() {
+            return;
+        }
+
+        let (recv_fd, send_fd) = match nix::unistd::pipe2(OFlag::O_CLOEXEC) {
+            Ok(fds) => unsafe { (OwnedFd::from_raw_fd(fds.0), OwnedFd::from_raw_fd(fds.1)) },
+            Err(err) => {
+                warn!(?err, "Failed to create pipe for the selection transfer");
+                return;
+            }
+        };
+
+        // the source only needs to offer one of the text mime types
+        for mime_type in TEXT_MIME_TYPES {
+            let Ok(send_fd) = send_fd.try_clone() else {
+                return;
+            };
+            if request_data_device_client_selection(seat, mime_type.to_string(), send_fd).is_ok() {
+                let sender = self.sender.clone();
+                std::thread::spawn(move || {
+                    let mut text = String::new();
+                    match File::from(recv_fd).read_to_string(&mut text) {
+                        Ok(_) => {
+                            let _ = sender.send(text);
+                        }
+                        Err(err) => debug!(?err, "Failed to read client selection"),
+                    }
+                });
+                // our write end is closed on return, so the reader sees the end of the transfer
+                return;
+            }
+        }
+    }
+
+    /// Store finished transfers of [`sync_to_host`](WinitClipboard::sync_to_host) in the host clipboard
+    pub(super) fn store_transferred(&mut self) {
+        while let Ok(text) = self.receiver.try_recv() {
+            if text.is_empty() || self.last_text.as_ref() == Some(&text) {
+                continue;
+            }
+            debug!("Storing client selection in the host clipboard");
+            self.clipboard.store(text.clone());
+            self.last_text = Some(text);
+        }
+    }
+}
@@ -8,8 +8,8 @@ use winit::{
 use crate::backend::input::{
     self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event,
     InputBackend, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
-    PointerMotionAbsoluteEvent, TouchCancelEvent, TouchDownEvent, TouchEvent, TouchMotionEvent, TouchSlot,
-    TouchUpEvent, UnusedEvent,
+    PointerMotionAbsoluteEvent, PointerMotionEvent, TouchCancelEvent, TouchDownEvent, TouchEvent,
+    TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
 };
 
 use super::WindowSize;
@@ -124,6 +124,44 @@ impl AbsolutePositionEvent<WinitInput> for WinitMouseMovedEvent {
     }
 }
 
+/// Winit-Backend internal event wrapping `winit`'s types into a [`PointerMotionEvent`]
+///
+/// Only generated while the pointer is locked, see [`WinitGraphicsBackend::set_cursor_grab`](super::WinitGraphicsBackend::set_cursor_grab).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WinitMouseRelativeMotionEvent {
+    pub(crate) time: u64,
+    pub(crate) delta: (f64, f64),
+}
+
+impl Event<WinitInput> for WinitMouseRelativeMotionEvent {
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn device(&self) -> WinitVirtualDevice {
+        WinitVirtualDevice
+    }
+}
+
+impl PointerMotionEvent<WinitInput> for WinitMouseRelativeMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    // winit only reports raw motion of the device
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta.1
+    }
+}
+
 /// Winit-Backend internal event wrapping `winit`'s types into a [`PointerAxisEvent`]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct WinitMouseWheelEvent {
@@ -381,7 +419,7 @@ impl InputBackend for WinitInput {
     type KeyboardKeyEvent = WinitKeyboardInputEvent;
     type PointerAxisEvent = WinitMouseWheelEvent;
     type PointerButtonEvent = WinitMouseInputEvent;
-    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionEvent = WinitMouseRelativeMotionEvent;
     type PointerMotionAbsoluteEvent = WinitMouseMovedEvent;
 
     type GestureSwipeBeginEvent = UnusedEvent;
@@ -402,6 +440,7 @@ impl InputBackend for WinitInput {
     type TabletToolProximityEvent = UnusedEvent;
     type TabletToolTipEvent = UnusedEvent;
     type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
 
     type SpecialEvent = UnusedEvent;
 }
@@ -19,7 +19,10 @@
 //! The other types in this module are the instances of the associated types of these
 //! two traits for the winit backend.
 
+#[cfg(feature = "backend_winit_clipboard")]
+mod clipboard;
 mod input;
+mod monitor;
 
 use crate::{
     backend::{
@@ -34,23 +37,35 @@ use crate::{
             Bind,
         },
     },
-    utils::{Logical, Physical, Rectangle, Size},
+    utils::{Clock, Logical, Monotonic, Physical, Point, Rectangle, Size},
+};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{cell::RefCell, rc::Rc, sync::Arc, time::Instant};
 use wayland_egl as wegl;
 use winit::{
-    dpi::LogicalSize,
-    event::{ElementState, Event, KeyboardInput, Touch, TouchPhase, WindowEvent},
+    dpi::{LogicalPosition, LogicalSize, PhysicalSize},
+    error::ExternalError,
+    event::{DeviceEvent, ElementState, Event, Ime, KeyboardInput, Touch, TouchPhase, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     platform::run_return::EventLoopExtRunReturn,
     platform::{wayland::WindowExtWayland, x11::WindowExtX11},
-    window::{Window as WinitWindow, WindowBuilder},
+    window::{CursorGrabMode, Fullscreen, Window as WinitWindow, WindowBuilder},
 };
 
 use std::cell::Cell;
 use tracing::{debug, error, info, info_span, instrument, trace, warn};
 
+#[cfg(feature = "backend_winit_clipboard")]
+pub use self::clipboard::{HostSelection, WinitClipboard};
 pub use self::input::*;
+pub use self::monitor::WinitMonitor;
 
 use super::renderer::Renderer;
 
@@ -72,6 +87,9 @@ pub enum Error {
     /// Renderer initialization failed
     #[error("Renderer creation failed: {0}")]
     RendererCreationError(#[from] GlesError),
+    /// The monotonic clock used for presentation feedback is not available
+    #[error("Failed to initialize the monotonic clock: {0}")]
+    Clock(#[source] std::io::Error),
 }
 
 /// Size properties of a winit window
@@ -100,6 +118,11 @@ pub struct WinitGraphicsBackend<R> {
     size: Rc<RefCell<WindowSize>>,
     damage_tracking: bool,
     resize_notification: Rc<Cell<Option<Size<i32, Physical>>>>,
+    pointer_locked: Rc<Cell<bool>>,
+    clock: Clock<Monotonic>,
+    vsync: bool,
+    sequence: u64,
+    pending_events: Rc<RefCell<VecDeque<WinitEvent>>>,
     span: tracing::Span,
 }
 
@@ -109,6 +132,9 @@ pub struct WinitGraphicsBackend<R> {
 /// periodically to receive any events.
 #[derive(Debug)]
 pub struct WinitEventLoop {
+    // Declared before the event loop, as it has to be dropped before the host connection
+    #[cfg(feature = "backend_winit_clipboard")]
+    clipboard: Option<WinitClipboard>,
     window: Arc<WinitWindow>,
     events_loop: EventLoop<()>,
     time: Instant,
@@ -116,6 +142,12 @@ pub struct WinitEventLoop {
     initialized: bool,
     size: Rc<RefCell<WindowSize>>,
     resize_notification: Rc<Cell<Option<Size<i32, Physical>>>>,
+    cursor_position: Point<f64, Logical>,
+    pointer_locked: Rc<Cell<bool>>,
+    pending_events: Rc<RefCell<VecDeque<WinitEvent>>>,
+    fullscreen: bool,
+    monitors: Vec<WinitMonitor>,
+    last_monitor_check: Instant,
     /// Whether winit is using Wayland or X11 as it's backend.
     is_x11: bool,
     span: tracing::Span,
@@ -236,6 +268,14 @@ where
     let renderer = unsafe { GlesRenderer::new(context)?.into() };
     let resize_notification = Rc::new(Cell::new(None));
     let damage_tracking = display.supports_damage();
+    let pointer_locked = Rc::new(Cell::new(false));
+    let pending_events = Rc::new(RefCell::new(VecDeque::new()));
+    let clock = Clock::new().map_err(Error::Clock)?;
+    let fullscreen = winit_window.fullscreen().is_some();
+    let monitors = events_loop
+        .available_monitors()
+        .map(|monitor| WinitMonitor::from_handle(&monitor))
+        .collect();
 
     drop(_guard);
     Ok((
@@ -247,15 +287,28 @@ where
             damage_tracking,
             size: size.clone(),
             resize_notification: resize_notification.clone(),
+            pointer_locked: pointer_locked.clone(),
+            clock,
+            vsync: attributes.vsync,
+            sequence: 0,
+            pending_events: pending_events.clone(),
             span: span.clone(),
         },
         WinitEventLoop {
+            #[cfg(feature = "backend_winit_clipboard")]
+            clipboard: None,
             resize_notification,
             events_loop,
             window: winit_window,
             time: Instant::now(),
             key_counter: 0,
             initialized: false,
+            cursor_position: Point::default(),
+            pointer_locked,
+            pending_events,
+            fullscreen,
+            monitors,
+            last_monitor_check: Instant::now(),
             size,
             is_x11,
             span,
@@ -282,6 +335,135 @@ pub enum WinitEvent {
 
     /// A redraw was requested
     Refresh,
+
+    /// The input method of the host sent an event
+    ///
+    /// Input method events are only sent after enabling them with
+    /// [`WinitGraphicsBackend::set_ime_allowed`]. They can be forwarded to the
+    /// focused text input, e.g. using `TextInputHandle::with_focused_text_input`.
+    Ime(WinitImeEvent),
+
+    /// Files of the host are dragged over or dropped onto the window
+    ///
+    /// This can be used to start a compositor-initiated drag'n'drop offering
+    /// [`WinitFileDrop::uri_list`] as `text/uri-list` to the clients.
+    FileDrop(WinitFileDrop),
+
+    /// A frame submitted with [`WinitGraphicsBackend::submit`] was handed to the host
+    PresentationFeedback(WinitPresentationFeedback),
+
+    /// The window entered or left fullscreen
+    ///
+    /// This is reported for changes requested by [`WinitGraphicsBackend::set_fullscreen`] as well as
+    /// by the host.
+    Fullscreen(bool),
+
+    /// The decorations of the window were enabled or disabled with [`WinitGraphicsBackend::set_decorations`]
+    Decorations(bool),
+
+    /// The monitors of the host changed
+    ///
+    /// Contains the new list of monitors, see [`WinitEventLoop::monitors`].
+    MonitorsChanged(Vec<WinitMonitor>),
+}
+
+/// Presentation feedback of a frame submitted to the host
+///
+/// winit does not expose the presentation timestamps of the host, so the time is taken when
+/// swapping the buffers completed. With vsync enabled, swapping blocks until the host is ready to
+/// accept a new frame, which makes this a close estimate of when the frame is latched for the next
+/// vblank of the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WinitPresentationFeedback {
+    /// Time the frame was handed to the host, in the `CLOCK_MONOTONIC` clock domain
+    pub time: Duration,
+    /// Number of the frame, incremented with every submitted frame
+    pub sequence: u64,
+    /// Duration of one frame of the monitor currently showing the window, if known
+    pub refresh: Option<Duration>,
+    /// Whether presentation is synchronized to the vblank of the host
+    pub vsync: bool,
+}
+
+/// Drag'n'drop of files from the host onto the window
+#[derive(Debug, Clone, PartialEq)]
+pub enum WinitFileDrop {
+    /// Files are dragged over the window
+    Hovered {
+        /// Paths of the dragged files
+        paths: Vec<PathBuf>,
+        /// Location of the pointer in the window
+        location: Point<f64, Logical>,
+    },
+    /// Files were dropped onto the window
+    Dropped {
+        /// Paths of the dropped files
+        paths: Vec<PathBuf>,
+        /// Location of the pointer in the window
+        location: Point<f64, Logical>,
+    },
+    /// The files were dragged out of the window or the drag was cancelled
+    Cancelled,
+}
+
+impl WinitFileDrop {
+    /// Returns the paths of the files, empty if the drag was cancelled
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            WinitFileDrop::Hovered { paths, .. } | WinitFileDrop::Dropped { paths, .. } => paths,
+            WinitFileDrop::Cancelled => &[],
+        }
+    }
+
+    /// Returns the files as `text/uri-list`, as expected by clients receiving dropped files
+    pub fn uri_list(&self) -> String {
+        let mut list = String::new();
+        for path in self.paths() {
+            list.push_str("file://");
+            for &byte in path.as_os_str().as_bytes() {
+                if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+                    list.push(byte as char);
+                } else {
+                    list.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            list.push_str("\r\n");
+        }
+        list
+    }
+}
+
+/// Input method events of the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WinitImeEvent {
+    /// The input method was enabled for the window
+    Enabled,
+    /// The input method updated the pre-edit text
+    ///
+    /// An empty text clears the pre-edit text.
+    Preedit {
+        /// The pre-edit text
+        text: String,
+        /// Byte offsets of the begin and end of the cursor in the text, `None` hides the cursor
+        cursor: Option<(usize, usize)>,
+    },
+    /// The input method committed text, replacing the pre-edit text
+    Commit(String),
+    /// The input method was disabled for the window
+    ///
+    /// Any pre-edit text should be cleared.
+    Disabled,
+}
+
+impl From<Ime> for WinitImeEvent {
+    fn from(ime: Ime) -> Self {
+        match ime {
+            Ime::Enabled => WinitImeEvent::Enabled,
+            Ime::Preedit(text, cursor) => WinitImeEvent::Preedit { text, cursor },
+            Ime::Commit(text) => WinitImeEvent::Commit(text),
+            Ime::Disabled => WinitImeEvent::Disabled,
+        }
+    }
 }
 
 impl<R> WinitGraphicsBackend<R>
@@ -299,6 +481,100 @@ where
         &self.window
     }
 
+    /// Set whether the input method of the host is used for the window
+    ///
+    /// While allowed, text composed with the input method is reported as [`WinitEvent::Ime`]
+    /// instead of keyboard events. Compositors usually allow it while a client has an enabled
+    /// text input.
+    pub fn set_ime_allowed(&self, allowed: bool) {
+        self.window.set_ime_allowed(allowed);
+    }
+
+    /// Set the area of the text cursor, so the host can place the input method popup next to it
+    pub fn set_ime_cursor_area(&self, area: Rectangle<i32, Logical>) {
+        self.window
+            .set_ime_position(LogicalPosition::new(area.loc.x, area.loc.y + area.size.h));
+    }
+
+    /// Confine or lock the pointer of the host to the window
+    ///
+    /// While the pointer is [locked](CursorGrabMode::Locked), the backend reports the raw motion of the
+    /// pointer as [`InputEvent::PointerMotion`] instead of [`InputEvent::PointerMotionAbsolute`],
+    /// e.g. to support relative pointer and pointer constraints for clients.
+    /// Locking is not supported on X11 hosts, use [`CursorGrabMode::Confined`] as fallback.
+    ///
+    /// You may want to hide the cursor of the host while the pointer is locked using
+    /// [`WinitWindow::set_cursor_visible`].
+    pub fn set_cursor_grab(&self, mode: CursorGrabMode) -> Result<(), ExternalError> {
+        self.window.set_cursor_grab(mode)?;
+        self.pointer_locked.set(mode == CursorGrabMode::Locked);
+        Ok(())
+    }
+
+    /// Returns whether the pointer is locked to the window
+    pub fn is_pointer_locked(&self) -> bool {
+        self.pointer_locked.get()
+    }
+
+    /// Make the window fullscreen on its current monitor or restore it
+    ///
+    /// The window is resized by the host, which is reported as [`WinitEvent::Resized`] followed
+    /// by [`WinitEvent::Fullscreen`].
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.window
+            .set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+    }
+
+    /// Returns whether the window is fullscreen
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.fullscreen().is_some()
+    }
+
+    /// Request the window to be resized to the given size
+    ///
+    /// The host may ignore or adjust the request, the resulting size is reported as [`WinitEvent::Resized`].
+    pub fn request_size(&self, size: Size<i32, Physical>) {
+        self.window
+            .set_inner_size(PhysicalSize::new(size.w.max(1) as u32, size.h.max(1) as u32));
+    }
+
+    /// Set the minimum size of the window, `None` removes the limit
+    pub fn set_min_size(&self, size: Option<Size<i32, Physical>>) {
+        self.window.set_min_inner_size(
+            size.map(|size| PhysicalSize::new(size.w.max(1) as u32, size.h.max(1) as u32)),
+        );
+    }
+
+    /// Set the maximum size of the window, `None` removes the limit
+    pub fn set_max_size(&self, size: Option<Size<i32, Physical>>) {
+        self.window.set_max_inner_size(
+            size.map(|size| PhysicalSize::new(size.w.max(1) as u32, size.h.max(1) as u32)),
+        );
+    }
+
+    /// Set whether the window can be resized by the user
+    pub fn set_resizable(&self, resizable: bool) {
+        self.window.set_resizable(resizable);
+    }
+
+    /// Enable or disable the decorations of the host for the window
+    ///
+    /// Changes are reported as [`WinitEvent::Decorations`].
+    pub fn set_decorations(&self, decorations: bool) {
+        if self.window.is_decorated() == decorations {
+            return;
+        }
+        self.window.set_decorations(decorations);
+        self.pending_events
+            .borrow_mut()
+            .push_back(WinitEvent::Decorations(decorations));
+    }
+
+    /// Returns whether the window is decorated by the host
+    pub fn is_decorated(&self) -> bool {
+        self.window.is_decorated()
+    }
+
     /// Access the underlying renderer
     pub fn renderer(&mut self) -> &mut R {
         &mut self.renderer
@@ -367,6 +643,22 @@ where
             _ => None,
         };
         self.egl.swap_buffers(damage.as_deref_mut())?;
+
+        self.sequence += 1;
+        let refresh = self
+            .window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|refresh| *refresh > 0)
+            .map(|refresh| Duration::from_nanos(1_000_000_000_000 / refresh as u64));
+        self.pending_events
+            .borrow_mut()
+            .push_back(WinitEvent::PresentationFeedback(WinitPresentationFeedback {
+                time: self.clock.now().into(),
+                sequence: self.sequence,
+                refresh,
+                vsync: self.vsync,
+            }));
         Ok(())
     }
 }
@@ -381,7 +673,39 @@ pub enum WinitError {
     WindowClosed,
 }
 
+/// Interval in which the monitors of the host are checked for changes
+const MONITOR_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 impl WinitEventLoop {
+    /// Returns the monitors of the host
+    ///
+    /// Changes are reported as [`WinitEvent::MonitorsChanged`]. Use [`WinitMonitor::create_output`] to
+    /// create outputs matching the monitors, e.g. to simulate the multi-monitor layout of the host.
+    pub fn monitors(&self) -> &[WinitMonitor] {
+        &self.monitors
+    }
+
+    /// Returns the primary monitor of the host, if the host has one
+    pub fn primary_monitor(&self) -> Option<WinitMonitor> {
+        self.events_loop
+            .primary_monitor()
+            .map(|monitor| WinitMonitor::from_handle(&monitor))
+    }
+
+    /// Returns the bridge to the clipboard of the host
+    ///
+    /// The bridge is created on first use. Returns `None` if winit does not run on Wayland,
+    /// bridging the clipboard of X11 hosts is not supported.
+    #[cfg(feature = "backend_winit_clipboard")]
+    pub fn clipboard(&mut self) -> Option<&mut WinitClipboard> {
+        if self.clipboard.is_none() {
+            let display = self.window.wayland_display()?;
+            // SAFETY: the clipboard is dropped before the event loop owning the connection
+            self.clipboard = Some(unsafe { WinitClipboard::new(display) });
+        }
+        self.clipboard.as_mut()
+    }
+
     /// Processes new events of the underlying event loop and calls the provided callback.
     ///
     /// You need to periodically call this function to keep the underlying event loop and
@@ -402,6 +726,31 @@ impl WinitEventLoop {
 
         let mut closed = false;
 
+        let pending_events = self.pending_events.take();
+        for event in pending_events {
+            callback(event);
+        }
+
+        // winit does not notify about monitor changes
+        if self.last_monitor_check.elapsed() >= MONITOR_CHECK_INTERVAL {
+            self.last_monitor_check = Instant::now();
+            let monitors = self
+                .events_loop
+                .available_monitors()
+                .map(|monitor| WinitMonitor::from_handle(&monitor))
+                .collect::<Vec<_>>();
+            if monitors != self.monitors {
+                debug!(?monitors, "Monitors of the host changed");
+                self.monitors = monitors.clone();
+                callback(MonitorsChanged(monitors));
+            }
+        }
+
+        #[cfg(feature = "backend_winit_clipboard")]
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            clipboard.store_transferred();
+        }
+
         {
             // NOTE: This ugly pile of references is here, because rustc could not
             // figure out how to reference all these objects correctly into the
@@ -414,7 +763,13 @@ impl WinitEventLoop {
             let window = &self.window;
             let resize_notification = &self.resize_notification;
             let window_size = &self.size;
+            let cursor_position = &mut self.cursor_position;
+            let pointer_locked = &self.pointer_locked;
+            let fullscreen = &mut self.fullscreen;
             let is_x11 = self.is_x11;
+            // winit reports dragged files one by one, they are batched into one event
+            let mut hovered_files = Vec::new();
+            let mut dropped_files = Vec::new();
 
             if !self.initialized {
                 callback(Input(InputEvent::DeviceAdded {
@@ -425,9 +780,32 @@ impl WinitEventLoop {
 
             self.events_loop
                 .run_return(move |event, _target, control_flow| match event {
+                    Event::MainEventsCleared => {
+                        if !hovered_files.is_empty() {
+                            callback(FileDrop(WinitFileDrop::Hovered {
+                                paths: std::mem::take(&mut hovered_files),
+                                location: *cursor_position,
+                            }));
+                        }
+                        if !dropped_files.is_empty() {
+                            callback(FileDrop(WinitFileDrop::Dropped {
+                                paths: std::mem::take(&mut dropped_files),
+                                location: *cursor_position,
+                            }));
+                        }
+                    }
                     Event::RedrawEventsCleared => {
                         *control_flow = ControlFlow::Exit;
                     }
+                    Event::DeviceEvent {
+                        event: DeviceEvent::MouseMotion { delta },
+                        ..
+                    } if pointer_locked.get() => {
+                        let time = Instant::now().duration_since(*time).as_micros() as u64;
+                        callback(Input(InputEvent::PointerMotion {
+                            event: WinitMouseRelativeMotionEvent { time, delta },
+                        }));
+                    }
                     Event::RedrawRequested(_id) => {
                         callback(WinitEvent::Refresh);
                     }
@@ -449,6 +827,12 @@ impl WinitEventLoop {
                                     size: wsize.physical_size,
                                     scale_factor,
                                 });
+
+                                let is_fullscreen = window.fullscreen().is_some();
+                                if *fullscreen != is_fullscreen {
+                                    *fullscreen = is_fullscreen;
+                                    callback(WinitEvent::Fullscreen(is_fullscreen));
+                                }
                             }
                             WindowEvent::Focused(focus) => {
                                 callback(WinitEvent::Focus(focus));
@@ -490,6 +874,10 @@ impl WinitEventLoop {
                             }
                             WindowEvent::CursorMoved { position, .. } => {
                                 let lpos = position.to_logical(window_size.borrow().scale_factor);
+                                *cursor_position = (lpos.x, lpos.y).into();
+                                if pointer_locked.get() {
+                                    return;
+                                }
                                 callback(Input(InputEvent::PointerMotionAbsolute {
                                     event: WinitMouseMovedEvent {
                                         size: window_size.clone(),
@@ -575,6 +963,20 @@ impl WinitEventLoop {
                                     event: WinitTouchCancelledEvent { time, id },
                                 }));
                             }
+                            WindowEvent::HoveredFile(path) => {
+                                hovered_files.push(path);
+                            }
+                            WindowEvent::HoveredFileCancelled => {
+                                hovered_files.clear();
+                                callback(FileDrop(WinitFileDrop::Cancelled));
+                            }
+                            WindowEvent::DroppedFile(path) => {
+                                dropped_files.push(path);
+                            }
+                            WindowEvent::Ime(ime) => {
+                                trace!(?ime, "Input method event");
+                                callback(WinitEvent::Ime(ime.into()));
+                            }
                             WindowEvent::CloseRequested | WindowEvent::Destroyed => {
                                 callback(Input(InputEvent::DeviceRemoved {
                                     device: WinitVirtualDevice,
@@ -596,3 +998,21 @@ impl WinitEventLoop {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_drop_uri_list() {
+        let drop = WinitFileDrop::Dropped {
+            paths: vec![PathBuf::from("/tmp/a file.txt"), PathBuf::from("/home/ü/b")],
+            location: (0.0, 0.0).into(),
+        };
+        assert_eq!(
+            drop.uri_list(),
+            "file:///tmp/a%20file.txt\r\nfile:///home/%C3%BC/b\r\n"
+        );
+        assert_eq!(WinitFileDrop::Cancelled.uri_list(), "");
+    }
+}
@@ -0,0 +1,77 @@
+use winit::monitor::MonitorHandle;
+
+use crate::{
+    output::{Mode, Output, PhysicalProperties, Scale, Subpixel},
+    utils::{Physical, Point, Size},
+};
+
+/// Refresh rate assumed for monitors not reporting one, in millihertz
+const DEFAULT_REFRESH: i32 = 60_000;
+
+/// Monitor of the host the winit window can be shown on
+///
+/// See [`WinitEventLoop::monitors`](super::WinitEventLoop::monitors).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WinitMonitor {
+    /// Name of the monitor, if known
+    pub name: Option<String>,
+    /// Size of the monitor, in pixels
+    pub size: Size<i32, Physical>,
+    /// Position of the monitor in the layout of the host, in pixels
+    pub location: Point<i32, Physical>,
+    /// Scale factor of the monitor
+    pub scale_factor: f64,
+    /// Refresh rate of the monitor in millihertz, if known
+    pub refresh: Option<i32>,
+}
+
+impl WinitMonitor {
+    pub(super) fn from_handle(handle: &MonitorHandle) -> WinitMonitor {
+        let size = handle.size();
+        let location = handle.position();
+        WinitMonitor {
+            name: handle.name(),
+            size: (size.width as i32, size.height as i32).into(),
+            location: (location.x, location.y).into(),
+            scale_factor: handle.scale_factor(),
+            refresh: handle
+                .refresh_rate_millihertz()
+                .filter(|refresh| *refresh > 0)
+                .map(|refresh| refresh as i32),
+        }
+    }
+
+    /// Returns the mode of the monitor, assuming 60Hz if the refresh rate is unknown
+    pub fn mode(&self) -> Mode {
+        Mode {
+            size: self.size,
+            refresh: self.refresh.unwrap_or(DEFAULT_REFRESH),
+        }
+    }
+
+    /// Create an [`Output`] matching the monitor
+    ///
+    /// The output uses the mode, scale and position of the monitor, the position is converted
+    /// into the logical space using the scale of the monitor. A global for the output still has to be
+    /// created by the compositor.
+    pub fn create_output(&self, name: impl Into<String>) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: self.name.clone().unwrap_or_else(|| "Winit".into()),
+            },
+        );
+        let mode = self.mode();
+        output.change_current_state(
+            Some(mode),
+            None,
+            Some(Scale::Fractional(self.scale_factor)),
+            Some(self.location.to_logical_precise_round(self.scale_factor)),
+        );
+        output.set_preferred(mode);
+        output
+    }
+}
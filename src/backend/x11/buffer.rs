@@ -2,7 +2,7 @@
 //!
 //! Buffers imported into X11 are represented as X pixmaps which are then presented to the window.
 //!
-//! At the moment only [`Dmabuf`] backed pixmaps are supported.
+//! Pixmaps may be backed by a [`Dmabuf`] or by shared memory.
 //!
 //! ## Dmabuf pixmaps
 //!
@@ -14,6 +14,11 @@
 //! If you do need to modify any of the logic pertaining to the Dmabuf presentation, do ensure you
 //! read the `dri3proto.txt` file (link in the non-public comments of the x11 mod.rs).
 //!
+//! ## Shared memory pixmaps
+//!
+//! A shared memory backed pixmap is created from a segment of the [`MIT-SHM`](x11rb::protocol::shm)
+//! extension. This does not require any gpu, but the X server has to copy the content on presentation.
+//!
 //! ## Presentation to the window
 //!
 //! Presentation to the window is handled through the [`Present`](x11rb::protocol::present)
@@ -37,14 +42,16 @@ use x11rb::{
     protocol::{
         dri3::ConnectionExt as _,
         present::{self, ConnectionExt},
+        shm::ConnectionExt as _,
         xproto::PixmapWrapper,
     },
     utils::RawFdContainer,
 };
 
-use crate::backend::allocator::{dmabuf::Dmabuf, Buffer};
-
-// Shm can be easily supported in the future using, xcb_shm_create_pixmap.
+use crate::{
+    backend::allocator::{dmabuf::Dmabuf, Buffer},
+    utils::{Logical, Size},
+};
 
 pub trait PixmapWrapperExt<'c, C>
 where
@@ -59,6 +66,16 @@ where
         dmabuf: &Dmabuf,
     ) -> Result<PixmapWrapper<'c, C>, X11Error>;
 
+    /// Creates a new Pixmap using the supplied shared memory segment.
+    ///
+    /// The returned Pixmap is freed when dropped.
+    fn with_shm(
+        connection: &'c C,
+        window: &Window,
+        seg: u32,
+        size: Size<u16, Logical>,
+    ) -> Result<PixmapWrapper<'c, C>, X11Error>;
+
     /// Presents the pixmap to the window.
     ///
     /// The wrapper is consumed when this function is called. The return value will contain the
@@ -164,6 +181,18 @@ where
         Ok(PixmapWrapper::for_pixmap(connection, xid))
     }
 
+    fn with_shm(
+        connection: &'c C,
+        window: &Window,
+        seg: u32,
+        size: Size<u16, Logical>,
+    ) -> Result<PixmapWrapper<'c, C>, X11Error> {
+        let xid = connection.generate_id()?;
+        connection.shm_create_pixmap(xid, window.id(), size.w, size.h, window.depth(), seg, 0)?;
+
+        Ok(PixmapWrapper::for_pixmap(connection, xid))
+    }
+
     fn present(self, connection: &C, window: &Window) -> Result<u32, X11Error> {
         let next_serial = window.0.next_serial.fetch_add(1, Ordering::SeqCst);
         // We want to present as soon as possible, so wait 1ms so the X server will present when next convenient.
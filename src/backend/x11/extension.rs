@@ -61,7 +61,7 @@ macro_rules! extensions {
                                     version.minor_version,
                                 );
 
-                                Some((version.major_version, version.minor_version))
+                                Some((version.major_version.into(), version.minor_version.into()))
                             } else {
                                 if $required {
                                     error!(
@@ -77,8 +77,8 @@ macro_rules! extensions {
                                         name: X11_EXTENSION_NAME,
                                         required_major: $req_major,
                                         required_minor: $req_minor,
-                                        available_major: version.major_version,
-                                        available_minor: version.minor_version,
+                                        available_major: version.major_version.into(),
+                                        available_minor: version.minor_version.into(),
                                     }.into());
                                 } else {
                                     None
@@ -128,8 +128,15 @@ extensions! {
 
     dri3 {
         dri3_query_version,
-        required: true,
+        required: false,
         minimum: (1, 0),
         request: (1, 2),
     },
+
+    xinput {
+        xinput_xi_query_version,
+        required: false,
+        minimum: (2, 2),
+        request: (2, 2),
+    },
 }
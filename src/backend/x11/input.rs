@@ -5,7 +5,9 @@ use crate::{
     backend::input::{
         self, AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, InputBackend,
         KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionAbsoluteEvent,
-        UnusedEvent,
+        TabletToolAxisEvent, TabletToolCapabilities, TabletToolDescriptor, TabletToolEvent,
+        TabletToolTipEvent, TabletToolTipState, TabletToolType, TouchDownEvent, TouchEvent, TouchFrameEvent,
+        TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
     },
     utils::{Logical, Size},
 };
@@ -31,7 +33,10 @@ impl Device for X11VirtualDevice {
     fn has_capability(&self, capability: DeviceCapability) -> bool {
         matches!(
             capability,
-            DeviceCapability::Keyboard | DeviceCapability::Pointer | DeviceCapability::Touch
+            DeviceCapability::Keyboard
+                | DeviceCapability::Pointer
+                | DeviceCapability::Touch
+                | DeviceCapability::TabletTool
         )
     }
 
@@ -220,6 +225,352 @@ impl AbsolutePositionEvent<X11Input> for X11MouseMovedEvent {
     }
 }
 
+/// X11-Backend internal event wrapping `XInput2`'s touch events into [`TouchDownEvent`],
+/// [`TouchMotionEvent`] and [`TouchUpEvent`]
+#[derive(Debug, Clone)]
+pub struct X11TouchEvent {
+    pub(crate) time: u32,
+    pub(crate) id: u32,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) size: Size<u16, Logical>,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TouchEvent {
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TouchEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+}
+
+impl TouchEvent<X11Input> for X11TouchEvent {
+    fn slot(&self) -> TouchSlot {
+        Some(self.id).into()
+    }
+}
+
+impl AbsolutePositionEvent<X11Input> for X11TouchEvent {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+}
+
+impl TouchDownEvent<X11Input> for X11TouchEvent {}
+impl TouchMotionEvent<X11Input> for X11TouchEvent {}
+impl TouchUpEvent<X11Input> for X11TouchEvent {}
+
+/// X11-Backend internal event marking the end of a set of [`X11TouchEvent`]s
+#[derive(Debug, Clone)]
+pub struct X11TouchFrameEvent {
+    pub(crate) time: u32,
+}
+
+impl input::Event<X11Input> for X11TouchFrameEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+}
+
+impl TouchFrameEvent<X11Input> for X11TouchFrameEvent {}
+
+/// Range of a valuator of an `XInput2` device
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Valuator {
+    pub(crate) number: u16,
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+}
+
+impl Valuator {
+    /// Returns the value of this valuator, if it is part of the given mask
+    pub(crate) fn value(&self, mask: &[u32], values: &[f64]) -> Option<f64> {
+        let number = self.number as usize;
+        if mask.get(number / 32).copied().unwrap_or(0) & (1 << (number % 32)) == 0 {
+            return None;
+        }
+        // values only contain the valuators set in the mask
+        let index = (0..number)
+            .filter(|i| mask.get(i / 32).copied().unwrap_or(0) & (1 << (i % 32)) != 0)
+            .count();
+        values.get(index).copied()
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        if self.max > self.min {
+            (value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Axes of a tablet tool changed by an `XInput2` event
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TabletChanges {
+    pressure: bool,
+    tilt_x: bool,
+    tilt_y: bool,
+    delta: (f64, f64),
+}
+
+/// State of a tablet tool reported through `XInput2` valuators
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TabletState {
+    pub(crate) pressure: Option<Valuator>,
+    pub(crate) tilt_x: Option<Valuator>,
+    pub(crate) tilt_y: Option<Valuator>,
+    pub(crate) position: Option<(f64, f64)>,
+    pub(crate) current_pressure: f64,
+    pub(crate) current_tilt: (f64, f64),
+    pub(crate) tip: bool,
+}
+
+impl TabletState {
+    /// Updates the state from the valuators of an event and returns the changed axes
+    pub(crate) fn update(&mut self, x: f64, y: f64, mask: &[u32], values: &[f64]) -> TabletChanges {
+        let pressure = self
+            .pressure
+            .and_then(|valuator| valuator.value(mask, values).map(|v| valuator.normalize(v)));
+        let tilt_x = self.tilt_x.and_then(|valuator| valuator.value(mask, values));
+        let tilt_y = self.tilt_y.and_then(|valuator| valuator.value(mask, values));
+
+        let delta = self
+            .position
+            .map(|(last_x, last_y)| (x - last_x, y - last_y))
+            .unwrap_or_default();
+        self.position = Some((x, y));
+        if let Some(pressure) = pressure {
+            self.current_pressure = pressure;
+        }
+        if let Some(tilt_x) = tilt_x {
+            self.current_tilt.0 = tilt_x;
+        }
+        if let Some(tilt_y) = tilt_y {
+            self.current_tilt.1 = tilt_y;
+        }
+
+        TabletChanges {
+            pressure: pressure.is_some(),
+            tilt_x: tilt_x.is_some(),
+            tilt_y: tilt_y.is_some(),
+            delta,
+        }
+    }
+
+    fn capabilities(&self) -> TabletToolCapabilities {
+        let mut capabilities = TabletToolCapabilities::empty();
+        if self.pressure.is_some() {
+            capabilities |= TabletToolCapabilities::PRESSURE;
+        }
+        if self.tilt_x.is_some() || self.tilt_y.is_some() {
+            capabilities |= TabletToolCapabilities::TILT;
+        }
+        capabilities
+    }
+}
+
+/// X11-Backend internal event wrapping `XInput2` events of tablet devices into
+/// [`TabletToolAxisEvent`] and [`TabletToolTipEvent`]
+///
+/// Tilt is reported in the unit of the X server's valuators, which is usually degrees.
+#[derive(Debug, Clone)]
+pub struct X11TabletToolEvent {
+    pub(crate) time: u32,
+    pub(crate) device_id: u16,
+    pub(crate) capabilities: TabletToolCapabilities,
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    pub(crate) delta: (f64, f64),
+    pub(crate) size: Size<u16, Logical>,
+    pub(crate) pressure: f64,
+    pub(crate) pressure_changed: bool,
+    pub(crate) tilt: (f64, f64),
+    pub(crate) tilt_changed: (bool, bool),
+    pub(crate) tip_state: TabletToolTipState,
+    pub(crate) window: Weak<WindowInner>,
+}
+
+impl X11TabletToolEvent {
+    pub(crate) fn new(
+        time: u32,
+        device_id: u16,
+        state: &TabletState,
+        changes: TabletChanges,
+        size: Size<u16, Logical>,
+        window: Weak<WindowInner>,
+    ) -> X11TabletToolEvent {
+        let (x, y) = state.position.unwrap_or_default();
+        X11TabletToolEvent {
+            time,
+            device_id,
+            capabilities: state.capabilities(),
+            x,
+            y,
+            delta: changes.delta,
+            size,
+            pressure: state.current_pressure,
+            pressure_changed: changes.pressure,
+            tilt: state.current_tilt,
+            tilt_changed: (changes.tilt_x, changes.tilt_y),
+            tip_state: if state.tip {
+                TabletToolTipState::Down
+            } else {
+                TabletToolTipState::Up
+            },
+            window,
+        }
+    }
+
+    /// Returns a temporary reference to the window belonging to this event.
+    ///
+    /// Returns None if the window is not alive anymore.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+}
+
+impl input::Event<X11Input> for X11TabletToolEvent {
+    fn time(&self) -> u64 {
+        self.time as u64 * 1000
+    }
+
+    fn device(&self) -> X11VirtualDevice {
+        X11VirtualDevice
+    }
+}
+
+impl TabletToolEvent<X11Input> for X11TabletToolEvent {
+    fn tool(&self) -> TabletToolDescriptor {
+        TabletToolDescriptor {
+            tool_type: TabletToolType::Pen,
+            hardware_serial: self.device_id as u64,
+            hardware_id_wacom: 0,
+            capabilities: self.capabilities,
+        }
+    }
+
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        f64::max(self.x * width as f64 / self.size.w as f64, 0.0)
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        f64::max(self.y * height as f64 / self.size.h as f64, 0.0)
+    }
+
+    fn distance(&self) -> f64 {
+        0.0
+    }
+
+    fn distance_has_changed(&self) -> bool {
+        false
+    }
+
+    fn pressure(&self) -> f64 {
+        self.pressure
+    }
+
+    fn pressure_has_changed(&self) -> bool {
+        self.pressure_changed
+    }
+
+    fn slider_position(&self) -> f64 {
+        0.0
+    }
+
+    fn slider_has_changed(&self) -> bool {
+        false
+    }
+
+    fn tilt_x(&self) -> f64 {
+        self.tilt.0
+    }
+
+    fn tilt_x_has_changed(&self) -> bool {
+        self.tilt_changed.0
+    }
+
+    fn tilt_y(&self) -> f64 {
+        self.tilt.1
+    }
+
+    fn tilt_y_has_changed(&self) -> bool {
+        self.tilt_changed.1
+    }
+
+    fn rotation(&self) -> f64 {
+        0.0
+    }
+
+    fn rotation_has_changed(&self) -> bool {
+        false
+    }
+
+    fn wheel_delta(&self) -> f64 {
+        0.0
+    }
+
+    fn wheel_delta_discrete(&self) -> i32 {
+        0
+    }
+
+    fn wheel_has_changed(&self) -> bool {
+        false
+    }
+}
+
+impl TabletToolAxisEvent<X11Input> for X11TabletToolEvent {}
+
+impl TabletToolTipEvent<X11Input> for X11TabletToolEvent {
+    fn tip_state(&self) -> TabletToolTipState {
+        self.tip_state
+    }
+}
+
 impl InputBackend for X11Input {
     type Device = X11VirtualDevice;
     type KeyboardKeyEvent = X11KeyboardInputEvent;
@@ -239,15 +590,16 @@ impl InputBackend for X11Input {
     type GestureHoldBeginEvent = UnusedEvent;
     type GestureHoldEndEvent = UnusedEvent;
 
-    type TouchDownEvent = UnusedEvent;
-    type TouchUpEvent = UnusedEvent;
-    type TouchMotionEvent = UnusedEvent;
+    type TouchDownEvent = X11TouchEvent;
+    type TouchUpEvent = X11TouchEvent;
+    type TouchMotionEvent = X11TouchEvent;
     type TouchCancelEvent = UnusedEvent;
-    type TouchFrameEvent = UnusedEvent;
-    type TabletToolAxisEvent = UnusedEvent;
+    type TouchFrameEvent = X11TouchFrameEvent;
+    type TabletToolAxisEvent = X11TabletToolEvent;
     type TabletToolProximityEvent = UnusedEvent;
-    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolTipEvent = X11TabletToolEvent;
     type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
 
     type SpecialEvent = UnusedEvent;
 }
@@ -9,6 +9,9 @@
 //! - an [`X11Backend`], which you will insert into an [`EventLoop`](calloop::EventLoop) to process events from the backend.
 //! - an [`X11Surface`], which represents a surface that buffers are presented to for display.
 //!
+//! If the X server supports `XInput2` 2.2, touch events and the pressure and tilt of tablet tools
+//! are reported as well, in addition to keyboard and pointer input.
+//!
 //! ## Example usage
 //!
 //! ```rust,no_run
@@ -59,6 +62,13 @@
 //! }
 //! ```
 //!
+//! ## Multiple windows
+//!
+//! Any number of windows can be created from one [`X11Handle`], e.g. to emulate a setup with multiple
+//! monitors. Each window gets its own surface and is presented with its own timing, events concerning
+//! a window carry its XID (see [`Window::id`]) and input events provide the window they occurred in.
+//! [`Window::create_output`] creates an [`Output`] matching a window.
+//!
 //! ## EGL
 //!
 //! When using [`EGL`](crate::backend::egl), an [`X11Surface`] may be used to create an [`EGLDisplay`](crate::backend::egl::EGLDisplay).
@@ -78,6 +88,7 @@ mod error;
 #[macro_use]
 mod extension;
 mod input;
+mod shm;
 mod surface;
 mod window_inner;
 
@@ -88,6 +99,7 @@ use crate::{
         egl::{native::X11DefaultDisplay, EGLDevice, EGLDisplay, Error as EGLError},
         input::{Axis, ButtonState, InputEvent, KeyState},
     },
+    output::{Mode, Output, PhysicalProperties, Subpixel},
     utils::{x11rb::X11Source, Logical, Size},
 };
 use calloop::{EventSource, Poll, PostAction, Readiness, Token, TokenFactory};
@@ -104,14 +116,17 @@ use std::{
         atomic::{AtomicU32, Ordering},
         mpsc, Arc, Mutex, Weak,
     },
+    time::Duration,
 };
-use tracing::{debug_span, error, info, instrument, warn};
+use tracing::{debug, debug_span, error, info, instrument, warn};
 use x11rb::{
     atom_manager,
     connection::Connection,
     protocol::{
         self as x11,
         dri3::ConnectionExt as _,
+        shm::ConnectionExt as _,
+        xinput::{self, ConnectionExt as _},
         xproto::{ColormapAlloc, ConnectionExt, CreateWindowAux, VisualClass, WindowClass, WindowWrapper},
         ErrorKind,
     },
@@ -122,6 +137,7 @@ use self::{extension::Extensions, window_inner::WindowInner};
 
 pub use self::error::*;
 pub use self::input::*;
+pub use self::shm::X11ShmSurface;
 pub use self::surface::*;
 
 use super::allocator::dmabuf::{AnyError, Dmabuf};
@@ -152,6 +168,8 @@ pub enum X11Event {
     PresentCompleted {
         /// XID of the window
         window_id: u32,
+        /// When and in which frame the buffer was displayed
+        feedback: X11PresentationFeedback,
     },
 
     /// The window has received a request to be closed.
@@ -161,6 +179,33 @@ pub enum X11Event {
     },
 }
 
+/// Presentation feedback of a window reported by the X server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X11PresentationFeedback {
+    /// Time the buffer was displayed at, in the `CLOCK_MONOTONIC` clock domain
+    pub time: Duration,
+    /// Media stream counter of the frame the buffer was displayed in
+    pub msc: u64,
+    /// Estimated duration of one frame, if known
+    ///
+    /// The estimate is derived from the timestamps of consecutive presentations.
+    pub refresh: Option<Duration>,
+}
+
+impl X11PresentationFeedback {
+    fn next(previous: Option<X11PresentationFeedback>, ust: u64, msc: u64) -> X11PresentationFeedback {
+        let time = Duration::from_micros(ust);
+        let refresh = previous.and_then(|previous| {
+            if msc > previous.msc && time > previous.time {
+                Some((time - previous.time) / (msc - previous.msc) as u32)
+            } else {
+                previous.refresh
+            }
+        });
+        X11PresentationFeedback { time, msc, refresh }
+    }
+}
+
 /// Represents an active connection to the X to manage events on the Window provided by the backend.
 #[derive(Debug)]
 pub struct X11Backend {
@@ -218,6 +263,25 @@ impl X11Backend {
 
         let atoms = Atoms::new(&*connection)?.reply()?;
 
+        let shm = if connection
+            .extension_information(x11rb::protocol::shm::X11_EXTENSION_NAME)?
+            .is_some()
+        {
+            let version = connection.shm_query_version()?.reply()?;
+            Some((version.major_version.into(), version.minor_version.into()))
+        } else {
+            None
+        };
+
+        let tablets = if extensions.xinput.is_some() {
+            query_tablets(&connection, &atoms).unwrap_or_else(|err| {
+                warn!("Failed to query XInput2 devices: {}", err);
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+
         // We need to give the X11Source a window we have created, we cannot send the close event to the root
         // window (0). To handle this, we will create a window we never map or provide to users to the backend
         // can be sent a message for shutdown.
@@ -251,6 +315,8 @@ impl X11Backend {
             depth,
             visual_id,
             devices: false,
+            tablets,
+            shm,
         };
 
         drop(_guard);
@@ -364,11 +430,32 @@ impl X11Handle {
             return Err(X11Error::InvalidWindow);
         }
 
+        if window.0.extensions.dri3.is_none() {
+            return Err(MissingExtensionError::NotFound {
+                name: x11rb::protocol::dri3::X11_EXTENSION_NAME,
+                major: 1,
+                minor: 0,
+            }
+            .into());
+        }
+
         let mut modifiers = modifiers.collect::<Vec<_>>();
         // older dri3 versions do only support buffers with one plane.
         // we need to make sure, we don't accidently allocate buffers with more.
         if window.0.extensions.dri3 < Some((1, 2)) {
             modifiers.retain(|modi| modi == &DrmModifier::Invalid || modi == &DrmModifier::Linear);
+        } else if let Some(supported) = supported_modifiers(&inner_guard.connection, window) {
+            // prefer modifiers the X server can present directly, to avoid copies on its side.
+            let presentable = modifiers
+                .iter()
+                .copied()
+                .filter(|modi| modi == &DrmModifier::Invalid || supported.contains(modi))
+                .collect::<Vec<_>>();
+            if presentable.is_empty() {
+                debug!("X server supports none of the requested modifiers, keeping all of them");
+            } else {
+                modifiers = presentable;
+            }
         }
 
         let format = window.0.format;
@@ -401,6 +488,69 @@ impl X11Handle {
         })
     }
 
+    /// Creates a surface that presents buffers in shared memory to the window.
+    ///
+    /// This does not require a gpu and may be used to display the content of a software renderer.
+    /// Requires version 1.2 of the `MIT-SHM` extension.
+    ///
+    /// This will fail if the window has already been used to create a surface.
+    #[instrument(parent = &self.span, skip(self))]
+    pub fn create_shm_surface(&self, window: &Window) -> Result<X11ShmSurface, X11Error> {
+        let has_resize = { window.0.resize.lock().unwrap().is_some() };
+
+        if has_resize {
+            return Err(X11Error::SurfaceExists);
+        }
+
+        let inner = self.inner.clone();
+        let inner_guard = inner.lock().unwrap();
+
+        // Fail if the window is not managed by this backend or is destroyed
+        if !inner_guard.windows.contains_key(&window.id()) {
+            return Err(X11Error::InvalidWindow);
+        }
+
+        match inner_guard.shm {
+            Some(version) if version >= (1, 2) => {}
+            Some((major, minor)) => {
+                return Err(MissingExtensionError::WrongVersion {
+                    name: x11rb::protocol::shm::X11_EXTENSION_NAME,
+                    required_major: 1,
+                    required_minor: 2,
+                    available_major: major,
+                    available_minor: minor,
+                }
+                .into())
+            }
+            None => {
+                return Err(MissingExtensionError::NotFound {
+                    name: x11rb::protocol::shm::X11_EXTENSION_NAME,
+                    major: 1,
+                    minor: 2,
+                }
+                .into())
+            }
+        }
+
+        let (sender, recv) = mpsc::channel();
+
+        {
+            let mut resize = window.0.resize.lock().unwrap();
+            *resize = Some(sender);
+        }
+
+        Ok(X11ShmSurface {
+            connection: Arc::downgrade(&inner_guard.connection),
+            window: Arc::downgrade(&window.0),
+            resize: recv,
+            format: window.0.format,
+            size: window.size(),
+            buffers: Vec::new(),
+            current: 0,
+            span: self.span.clone(),
+        })
+    }
+
     /// Get a temporary reference to a window by its XID
     pub fn window_ref_from_id(&self, id: u32) -> Option<impl AsRef<Window> + '_> {
         X11Inner::window_ref_from_id(&self.inner, &id)
@@ -466,6 +616,8 @@ impl<'a> WindowBuilder<'a> {
             inner.extensions,
         )?);
 
+        inner.select_xinput_events(window.id)?;
+
         let downgrade = Arc::downgrade(&window);
         inner.windows.insert(window.id, downgrade);
 
@@ -523,6 +675,62 @@ impl Window {
     pub fn format(&self) -> DrmFourcc {
         self.0.format
     }
+
+    /// Returns the feedback of the last buffer displayed in this window, if any.
+    pub fn presentation_feedback(&self) -> Option<X11PresentationFeedback> {
+        *self.0.last_presentation.lock().unwrap()
+    }
+
+    /// Estimates the time until the next frame of this window starts.
+    ///
+    /// `now` has to be in the `CLOCK_MONOTONIC` clock domain.
+    /// Returns `None` until the duration of a frame could be estimated from the presentation feedback.
+    pub fn time_to_next_frame(&self, now: Duration) -> Option<Duration> {
+        let feedback = self.presentation_feedback()?;
+        let refresh = feedback.refresh.filter(|refresh| !refresh.is_zero())?;
+
+        let elapsed = now.saturating_sub(feedback.time);
+        let frames = elapsed.as_nanos() / refresh.as_nanos() + 1;
+        let next_frame = feedback.time + Duration::from_nanos((refresh.as_nanos() * frames) as u64);
+        Some(next_frame - now)
+    }
+
+    /// Returns the mode of an output showing this window.
+    ///
+    /// The refresh rate is estimated from the presentation feedback, assuming 60Hz until a frame was presented.
+    pub fn output_mode(&self) -> Mode {
+        let size = self.size();
+        let refresh = self
+            .presentation_feedback()
+            .and_then(|feedback| feedback.refresh)
+            .filter(|refresh| !refresh.is_zero())
+            .map(|refresh| (1_000_000_000_000 / refresh.as_nanos()) as i32)
+            .unwrap_or(60_000);
+        Mode {
+            size: (size.w as i32, size.h as i32).into(),
+            refresh,
+        }
+    }
+
+    /// Creates an [`Output`] for this window.
+    ///
+    /// The output uses the [mode](Window::output_mode) of the window. It has to be positioned and updated
+    /// on [`X11Event::Resized`] by the compositor, and a global for it still has to be created.
+    pub fn create_output(&self, name: impl Into<String>) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "X11 Window".into(),
+            },
+        );
+        let mode = self.output_mode();
+        output.change_current_state(Some(mode), None, None, None);
+        output.set_preferred(mode);
+        output
+    }
 }
 
 impl PartialEq for Window {
@@ -591,6 +799,9 @@ atom_manager! {
         _NET_WM_NAME,
         UTF8_STRING,
         _SMITHAY_X11_BACKEND_CLOSE,
+        ABS_PRESSURE: b"Abs Pressure",
+        ABS_TILT_X: b"Abs Tilt X",
+        ABS_TILT_Y: b"Abs Tilt Y",
     }
 }
 
@@ -607,6 +818,8 @@ pub(crate) struct X11Inner {
     depth: x11::xproto::Depth,
     visual_id: u32,
     devices: bool,
+    tablets: HashMap<u16, TabletState>,
+    shm: Option<(u32, u32)>,
 }
 
 impl X11Inner {
@@ -616,6 +829,78 @@ impl X11Inner {
         inner.windows.get(id).cloned()
     }
 
+    fn select_xinput_events(&self, window: u32) -> Result<(), X11Error> {
+        if self.extensions.xinput.is_none() {
+            return Ok(());
+        }
+
+        // Touch events are only delivered through XInput2, selecting them also disables
+        // the pointer emulation of touches for our windows.
+        let mut masks = vec![xinput::EventMask {
+            deviceid: xinput::Device::ALL_MASTER.into(),
+            mask: vec![(xinput::XIEventMask::TOUCH_BEGIN
+                | xinput::XIEventMask::TOUCH_UPDATE
+                | xinput::XIEventMask::TOUCH_END)
+                .into()],
+        }];
+        // Valuators of tablets are only available from the events of the slave devices.
+        masks.extend(self.tablets.keys().map(|&deviceid| xinput::EventMask {
+            deviceid,
+            mask: vec![(xinput::XIEventMask::MOTION
+                | xinput::XIEventMask::BUTTON_PRESS
+                | xinput::XIEventMask::BUTTON_RELEASE)
+                .into()],
+        }));
+        self.connection.xinput_xi_select_events(window, &masks)?;
+
+        Ok(())
+    }
+
+    fn touch_event(inner: &Arc<Mutex<X11Inner>>, event: &xinput::TouchBeginEvent) -> Option<X11TouchEvent> {
+        let window = X11Inner::window_ref_from_id(inner, &event.event).and_then(|w| w.upgrade())?;
+        let size = { *window.size.lock().unwrap() };
+
+        Some(X11TouchEvent {
+            time: event.time,
+            id: event.detail,
+            x: fp1616_to_f64(event.event_x),
+            y: fp1616_to_f64(event.event_y),
+            size,
+            window: Arc::downgrade(&window),
+        })
+    }
+
+    fn tablet_event(
+        inner: &Arc<Mutex<X11Inner>>,
+        event: &xinput::ButtonPressEvent,
+        tip: Option<bool>,
+    ) -> Option<X11TabletToolEvent> {
+        let window = X11Inner::window_ref_from_id(inner, &event.event).and_then(|w| w.upgrade())?;
+        let size = { *window.size.lock().unwrap() };
+
+        let mut inner = inner.lock().unwrap();
+        let state = inner.tablets.get_mut(&event.deviceid)?;
+        if let Some(tip) = tip {
+            state.tip = tip;
+        }
+        let values = event.axisvalues.iter().map(fp3232_to_f64).collect::<Vec<_>>();
+        let changes = state.update(
+            fp1616_to_f64(event.event_x),
+            fp1616_to_f64(event.event_y),
+            &event.valuator_mask,
+            &values,
+        );
+
+        Some(X11TabletToolEvent::new(
+            event.time,
+            event.deviceid,
+            state,
+            changes,
+            size,
+            Arc::downgrade(&window),
+        ))
+    }
+
     fn process_event<F>(inner: &Arc<Mutex<X11Inner>>, event: x11::Event, callback: &mut F)
     where
         F: FnMut(X11Event, &mut ()),
@@ -817,6 +1102,64 @@ impl X11Inner {
                 }
             }
 
+            x11::Event::XinputTouchBegin(touch) => {
+                if let Some(event) = X11Inner::touch_event(inner, &touch) {
+                    let time = event.time;
+                    callback(Input(InputEvent::TouchDown { event }), &mut ());
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent { time },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
+            x11::Event::XinputTouchUpdate(touch) => {
+                if let Some(event) = X11Inner::touch_event(inner, &touch) {
+                    let time = event.time;
+                    callback(Input(InputEvent::TouchMotion { event }), &mut ());
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent { time },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
+            x11::Event::XinputTouchEnd(touch) => {
+                if let Some(event) = X11Inner::touch_event(inner, &touch) {
+                    let time = event.time;
+                    callback(Input(InputEvent::TouchUp { event }), &mut ());
+                    callback(
+                        Input(InputEvent::TouchFrame {
+                            event: X11TouchFrameEvent { time },
+                        }),
+                        &mut (),
+                    );
+                }
+            }
+
+            x11::Event::XinputMotion(motion) => {
+                if let Some(event) = X11Inner::tablet_event(inner, &motion, None) {
+                    callback(Input(InputEvent::TabletToolAxis { event }), &mut ());
+                }
+            }
+
+            // Button 1 of a tablet is the tip of the tool touching the tablet.
+            x11::Event::XinputButtonPress(press) if press.detail == 1 => {
+                if let Some(event) = X11Inner::tablet_event(inner, &press, Some(true)) {
+                    callback(Input(InputEvent::TabletToolTip { event }), &mut ());
+                }
+            }
+
+            x11::Event::XinputButtonRelease(release) if release.detail == 1 => {
+                if let Some(event) = X11Inner::tablet_event(inner, &release, Some(false)) {
+                    callback(Input(InputEvent::TabletToolTip { event }), &mut ());
+                }
+            }
+
             x11::Event::ConfigureNotify(configure_notify) => {
                 if let Some(window) =
                     X11Inner::window_ref_from_id(inner, &configure_notify.window).and_then(|w| w.upgrade())
@@ -900,10 +1243,21 @@ impl X11Inner {
                     X11Inner::window_ref_from_id(inner, &complete_notify.window).and_then(|w| w.upgrade())
                 {
                     window.last_msc.store(complete_notify.msc, Ordering::SeqCst);
+                    let feedback = {
+                        let mut last_presentation = window.last_presentation.lock().unwrap();
+                        let feedback = X11PresentationFeedback::next(
+                            *last_presentation,
+                            complete_notify.ust,
+                            complete_notify.msc,
+                        );
+                        *last_presentation = Some(feedback);
+                        feedback
+                    };
 
                     (callback)(
                         X11Event::PresentCompleted {
                             window_id: complete_notify.window,
+                            feedback,
                         },
                         &mut (),
                     );
@@ -942,6 +1296,15 @@ fn egl_init(_: &X11Inner) -> Result<(DrmNode, OwnedFd), EGLInitError> {
 fn dri3_init(x11: &X11Inner) -> Result<(DrmNode, OwnedFd), X11Error> {
     let connection = &x11.connection;
 
+    if x11.extensions.dri3.is_none() {
+        return Err(MissingExtensionError::NotFound {
+            name: x11rb::protocol::dri3::X11_EXTENSION_NAME,
+            major: 1,
+            minor: 0,
+        }
+        .into());
+    }
+
     // Determine which drm-device the Display is using.
     let screen = &connection.setup().roots[x11.screen_number];
     // provider being NONE tells the X server to use the RandR provider.
@@ -1005,3 +1368,85 @@ fn dri3_init(x11: &X11Inner) -> Result<(DrmNode, OwnedFd), X11Error> {
 
     Ok((dri_node, unsafe { OwnedFd::from_raw_fd(fd) }))
 }
+
+/// Queries the modifiers the X server can present on the given window using dri3 1.2.
+fn supported_modifiers(connection: &RustConnection, window: &Window) -> Option<Vec<DrmModifier>> {
+    let bpp = match window.format() {
+        DrmFourcc::Argb8888 => 32,
+        DrmFourcc::Xrgb8888 => 24,
+        _ => unreachable!(),
+    };
+    let reply = match connection
+        .dri3_get_supported_modifiers(window.id(), window.depth(), bpp)
+        .map_err(ReplyError::from)
+        .and_then(|cookie| cookie.reply())
+    {
+        Ok(reply) => reply,
+        Err(err) => {
+            warn!("Failed to query supported modifiers of the X server: {}", err);
+            return None;
+        }
+    };
+
+    // window modifiers are directly scanout-capable, screen modifiers may still require a copy.
+    Some(
+        reply
+            .window_modifiers
+            .into_iter()
+            .chain(reply.screen_modifiers)
+            .map(DrmModifier::from)
+            .collect(),
+    )
+}
+
+/// Queries the tablet devices of the X server and the valuators they report.
+fn query_tablets(connection: &RustConnection, atoms: &Atoms) -> Result<HashMap<u16, TabletState>, X11Error> {
+    let reply = connection
+        .xinput_xi_query_device(u16::from(xinput::Device::ALL))?
+        .reply()?;
+
+    let mut tablets = HashMap::new();
+    for info in reply.infos {
+        if info.type_ != xinput::DeviceType::SLAVE_POINTER {
+            continue;
+        }
+
+        let mut state = TabletState::default();
+        for class in &info.classes {
+            if let xinput::DeviceClassData::Valuator(valuator) = &class.data {
+                let range = Valuator {
+                    number: valuator.number,
+                    min: fp3232_to_f64(&valuator.min),
+                    max: fp3232_to_f64(&valuator.max),
+                };
+                if valuator.label == atoms.ABS_PRESSURE {
+                    state.pressure = Some(range);
+                } else if valuator.label == atoms.ABS_TILT_X {
+                    state.tilt_x = Some(range);
+                } else if valuator.label == atoms.ABS_TILT_Y {
+                    state.tilt_y = Some(range);
+                }
+            }
+        }
+
+        // Only tablets report the pressure of the tool.
+        if state.pressure.is_some() {
+            info!(
+                device = %String::from_utf8_lossy(&info.name),
+                id = info.deviceid,
+                "Found tablet device"
+            );
+            tablets.insert(info.deviceid, state);
+        }
+    }
+
+    Ok(tablets)
+}
+
+fn fp1616_to_f64(value: xinput::Fp1616) -> f64 {
+    value as f64 / 65536.0
+}
+
+fn fp3232_to_f64(value: &xinput::Fp3232) -> f64 {
+    value.integral as f64 + value.frac as f64 / (1u64 << 32) as f64
+}
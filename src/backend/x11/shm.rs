@@ -0,0 +1,203 @@
+//! Presentation of buffers in shared memory through the `MIT-SHM` extension.
+//!
+//! This path does not require any gpu and may be used to display the content of a software
+//! renderer, e.g. if no usable gpu is available to the X server.
+
+use std::{
+    num::NonZeroUsize,
+    os::unix::io::AsRawFd,
+    slice,
+    sync::{mpsc::Receiver, Weak},
+};
+
+use drm_fourcc::DrmFourcc;
+use nix::sys::mman;
+use tracing::{instrument, warn};
+use x11rb::{
+    connection::Connection,
+    protocol::{shm::ConnectionExt as _, xproto::PixmapWrapper},
+    rust_connection::RustConnection,
+};
+
+use crate::{
+    backend::x11::{buffer::PixmapWrapperExt, window_inner::WindowInner, AllocateBuffersError, Window},
+    utils::{Logical, Size},
+};
+
+use super::{WindowTemporary, X11Error};
+
+/// Number of buffers used by an [`X11ShmSurface`].
+const BUFFER_COUNT: usize = 2;
+
+/// An X11 surface presenting buffers in shared memory.
+///
+/// Unlike the [`X11Surface`](super::X11Surface) this does not require a gpu.
+/// The buffers use the [format](Self::format) of the window with a stride of
+/// four bytes per pixel.
+#[derive(Debug)]
+pub struct X11ShmSurface {
+    pub(crate) connection: Weak<RustConnection>,
+    pub(crate) window: Weak<WindowInner>,
+    pub(crate) resize: Receiver<Size<u16, Logical>>,
+    pub(crate) format: DrmFourcc,
+    pub(crate) size: Size<u16, Logical>,
+    pub(crate) buffers: Vec<ShmBuffer>,
+    pub(crate) current: usize,
+    pub(crate) span: tracing::Span,
+}
+
+#[derive(Debug)]
+pub(crate) struct ShmBuffer {
+    seg: u32,
+    ptr: *mut u8,
+    len: usize,
+    age: u8,
+}
+
+impl ShmBuffer {
+    fn new(connection: &RustConnection, size: Size<u16, Logical>) -> Result<ShmBuffer, X11Error> {
+        let len = size.w as usize * size.h as usize * 4;
+        let map_len = NonZeroUsize::new(len.max(4)).unwrap();
+
+        // Let the X server create the segment, so we do not need to care about the file.
+        let seg = connection.generate_id()?;
+        let reply = connection
+            .shm_create_segment(seg, map_len.get() as u32, false)?
+            .reply()?;
+
+        // SAFETY: The segment was just created for us and is only accessed through this mapping
+        // until the X server reads it on presentation.
+        let ptr = unsafe {
+            mman::mmap(
+                None,
+                map_len,
+                mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE,
+                mman::MapFlags::MAP_SHARED,
+                reply.shm_fd.as_raw_fd(),
+                0,
+            )
+        };
+        let ptr = match ptr {
+            Ok(ptr) => ptr as *mut u8,
+            Err(err) => {
+                let _ = connection.shm_detach(seg);
+                return Err(AllocateBuffersError::from(err).into());
+            }
+        };
+
+        Ok(ShmBuffer {
+            seg,
+            ptr,
+            len,
+            age: 0,
+        })
+    }
+
+    fn destroy(self, connection: Option<&RustConnection>) {
+        if let Some(connection) = connection {
+            let _ = connection.shm_detach(self.seg);
+        }
+        // SAFETY: The mapping was created in `ShmBuffer::new` and is not used afterwards.
+        if let Err(err) = unsafe { mman::munmap(self.ptr as *mut _, self.len.max(4)) } {
+            warn!("Failed to unmap shm buffer: {}", err);
+        }
+    }
+}
+
+impl X11ShmSurface {
+    /// Returns the window the surface presents to.
+    ///
+    /// This will return [`None`] if the window has been destroyed.
+    pub fn window(&self) -> Option<impl AsRef<Window> + '_> {
+        self.window.upgrade().map(Window).map(WindowTemporary)
+    }
+
+    /// Returns the format of the buffers the surface accepts.
+    pub fn format(&self) -> DrmFourcc {
+        self.format
+    }
+
+    /// Returns the size of the buffers.
+    pub fn size(&self) -> Size<u16, Logical> {
+        self.size
+    }
+
+    /// Returns the stride of the buffers in bytes.
+    pub fn stride(&self) -> usize {
+        self.size.w as usize * 4
+    }
+
+    /// Returns the memory of the next buffer that will be presented to the Window and its age.
+    ///
+    /// This function will return the same buffer until [`submit`](Self::submit) is called
+    /// or [`reset_buffers`](Self::reset_buffers) is used to reset the buffers.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn buffer(&mut self) -> Result<(&mut [u8], u8), X11Error> {
+        if let Some(new_size) = self.resize.try_iter().last() {
+            self.size = new_size;
+            self.reset_buffers();
+        }
+
+        if self.buffers.is_empty() {
+            let connection = self.connection.upgrade().ok_or(X11Error::ConnectionLost)?;
+            for _ in 0..BUFFER_COUNT {
+                let buffer = ShmBuffer::new(&connection, self.size)?;
+                self.buffers.push(buffer);
+            }
+            self.current = 0;
+        }
+
+        let buffer = &mut self.buffers[self.current];
+        // SAFETY: The mapping is valid for the lifetime of the buffer, which is borrowed
+        // through the surface for the lifetime of the returned slice.
+        let memory = unsafe { slice::from_raw_parts_mut(buffer.ptr, buffer.len) };
+        Ok((memory, buffer.age))
+    }
+
+    /// Submit the current buffer to the window.
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn submit(&mut self) -> Result<(), X11Error> {
+        if self.buffers.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(connection) = self.connection.upgrade() {
+            {
+                let window = self.window().ok_or(AllocateBuffersError::WindowDestroyed)?;
+                let seg = self.buffers[self.current].seg;
+                let pixmap = PixmapWrapper::with_shm(&*connection, window.as_ref(), seg, self.size)?;
+
+                // Now present the current buffer
+                let _ = pixmap.present(&*connection, window.as_ref())?;
+            }
+
+            for (idx, buffer) in self.buffers.iter_mut().enumerate() {
+                if idx == self.current {
+                    buffer.age = 1;
+                } else if buffer.age > 0 {
+                    buffer.age = buffer.age.saturating_add(1);
+                }
+            }
+            self.current = (self.current + 1) % self.buffers.len();
+
+            // Flush the connection after presenting to the window to ensure we don't run out of buffer space in the X11 connection.
+            let _ = connection.flush();
+        }
+        Ok(())
+    }
+
+    /// Resets the internal buffers, e.g. to reset age values
+    #[instrument(level = "trace", parent = &self.span, skip(self))]
+    pub fn reset_buffers(&mut self) {
+        let connection = self.connection.upgrade();
+        for buffer in self.buffers.drain(..) {
+            buffer.destroy(connection.as_deref());
+        }
+    }
+}
+
+impl Drop for X11ShmSurface {
+    fn drop(&mut self) {
+        self.reset_buffers();
+    }
+}
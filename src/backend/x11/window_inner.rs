@@ -11,7 +11,7 @@ A link to the ICCCM Section 4: https://tronche.com/gui/x/icccm/sec-4.html
 */
 use crate::utils::{Logical, Size};
 
-use super::{extension::Extensions, Atoms, Window, X11Error};
+use super::{extension::Extensions, Atoms, Window, X11Error, X11PresentationFeedback};
 use drm_fourcc::DrmFourcc;
 use std::sync::{
     atomic::{AtomicU32, AtomicU64},
@@ -67,6 +67,7 @@ pub(crate) struct WindowInner {
     pub resize: Mutex<Option<Sender<Size<u16, Logical>>>>,
     pub next_serial: AtomicU32,
     pub last_msc: Arc<AtomicU64>,
+    pub last_presentation: Mutex<Option<X11PresentationFeedback>>,
     pub format: DrmFourcc,
     pub depth: Depth,
     pub extensions: Extensions,
@@ -156,6 +157,7 @@ impl WindowInner {
             size: Mutex::new(size),
             next_serial: AtomicU32::new(0),
             last_msc: Arc::new(AtomicU64::new(0)),
+            last_presentation: Mutex::new(None),
             format,
             depth,
             extensions,
@@ -0,0 +1,420 @@
+//! Tiling layout helpers
+//!
+//! A [`TilingLayout`] keeps an ordered list of elements and arranges them in a region of a
+//! [`Space`] according to a [`TilingStrategy`]. Applying the layout configures every element
+//! with its new geometry through the [`TiledElement`] trait, which is implemented for
+//! [`Window`](crate::desktop::Window), and maps it at the matching location.
+//!
+//! ```no_run
+//! # use smithay::desktop::{Space, Window, layout::{Gaps, TilingLayout, TilingStrategy}};
+//! # use smithay::utils::Rectangle;
+//! # let mut space: Space<Window> = Space::default();
+//! # let window: Window = unimplemented!();
+//! let mut layout = TilingLayout::new(TilingStrategy::MasterStack {
+//!     master_count: 1,
+//!     master_ratio: 0.6,
+//! });
+//! layout.set_gaps(Gaps { inner: 8, outer: 8 });
+//! layout.push(window);
+//! layout.apply(&mut space, Rectangle::from_loc_and_size((0, 0), (1920, 1080)));
+//! ```
+//!
+//! Elements can be excluded from tiling by giving them a manual geometry through
+//! [`TilingLayout::set_override`], e.g. for floating dialogs.
+
+use crate::{
+    desktop::{space::SpaceElement, Space},
+    utils::{Logical, Rectangle},
+};
+
+#[cfg(feature = "wayland_frontend")]
+mod wayland;
+
+/// Element that can be arranged by a [`TilingLayout`]
+pub trait TiledElement: SpaceElement + PartialEq + Clone {
+    /// Configure the element to take the given geometry
+    ///
+    /// The geometry is given in the global space coordinates. Implementations are expected to
+    /// request the new size from the client and to mark the element as tiled.
+    fn configure_tiled(&self, geometry: Rectangle<i32, Logical>);
+}
+
+/// Strategy used to arrange the tiled elements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TilingStrategy {
+    /// Recursively split the remaining area in two, along its longer side
+    ///
+    /// The first element takes `ratio` of the area, the following elements share the rest.
+    BinarySplit {
+        /// Ratio of the area taken by the first element of each split, in the range `[0, 1]`
+        ratio: f64,
+    },
+    /// Place the first elements in a master column, stacking the rest next to it
+    MasterStack {
+        /// Number of elements in the master column
+        master_count: usize,
+        /// Ratio of the width taken by the master column, in the range `[0, 1]`
+        master_ratio: f64,
+    },
+    /// Arrange the elements in a grid of equally sized cells
+    ///
+    /// The last row is stretched if it is not fully occupied.
+    Grid,
+}
+
+impl Default for TilingStrategy {
+    fn default() -> Self {
+        TilingStrategy::BinarySplit { ratio: 0.5 }
+    }
+}
+
+/// Gaps inserted by a [`TilingLayout`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Gaps {
+    /// Gap between two adjacent elements
+    pub inner: i32,
+    /// Gap between the elements and the border of the tiled region
+    pub outer: i32,
+}
+
+/// Layout arranging elements of a [`Space`] in tiles
+#[derive(Debug)]
+pub struct TilingLayout<E> {
+    strategy: TilingStrategy,
+    gaps: Gaps,
+    elements: Vec<(E, Option<Rectangle<i32, Logical>>)>,
+}
+
+impl<E> Default for TilingLayout<E> {
+    fn default() -> Self {
+        TilingLayout::new(TilingStrategy::default())
+    }
+}
+
+impl<E> TilingLayout<E> {
+    /// Create an empty layout using the given strategy
+    pub fn new(strategy: TilingStrategy) -> Self {
+        TilingLayout {
+            strategy,
+            gaps: Gaps::default(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// The strategy used to arrange the elements
+    pub fn strategy(&self) -> TilingStrategy {
+        self.strategy
+    }
+
+    /// Set the strategy used to arrange the elements
+    pub fn set_strategy(&mut self, strategy: TilingStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// The gaps inserted between the elements
+    pub fn gaps(&self) -> Gaps {
+        self.gaps
+    }
+
+    /// Set the gaps inserted between the elements
+    pub fn set_gaps(&mut self, gaps: Gaps) {
+        self.gaps = gaps;
+    }
+
+    /// The elements of this layout, in tiling order
+    pub fn elements(&self) -> impl DoubleEndedIterator<Item = &E> {
+        self.elements.iter().map(|(element, _)| element)
+    }
+}
+
+impl<E: TiledElement> TilingLayout<E> {
+    /// Append an element to the layout
+    ///
+    /// Does nothing if the element is already part of the layout.
+    pub fn push(&mut self, element: E) {
+        if self.position(&element).is_none() {
+            self.elements.push((element, None));
+        }
+    }
+
+    /// Insert an element at the given position of the tiling order
+    ///
+    /// An element already part of the layout is moved to the new position.
+    /// The index is clamped to the number of elements.
+    pub fn insert(&mut self, index: usize, element: E) {
+        let geometry = self
+            .position(&element)
+            .and_then(|pos| self.elements.remove(pos).1);
+        let index = index.min(self.elements.len());
+        self.elements.insert(index, (element, geometry));
+    }
+
+    /// Remove an element from the layout
+    ///
+    /// Returns `false` if the element was not part of the layout.
+    pub fn remove(&mut self, element: &E) -> bool {
+        match self.position(element) {
+            Some(pos) => {
+                self.elements.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Swap the positions of two elements in the tiling order
+    ///
+    /// Returns `false` if any of the elements is not part of the layout.
+    pub fn swap(&mut self, a: &E, b: &E) -> bool {
+        match (self.position(a), self.position(b)) {
+            (Some(a), Some(b)) => {
+                self.elements.swap(a, b);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Set a manual geometry for an element, excluding it from tiling
+    ///
+    /// Passing `None` returns the element to the tiled elements.
+    /// Returns `false` if the element is not part of the layout.
+    pub fn set_override(&mut self, element: &E, geometry: Option<Rectangle<i32, Logical>>) -> bool {
+        match self.position(element) {
+            Some(pos) => {
+                self.elements[pos].1 = geometry;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The manual geometry of an element, if set
+    pub fn override_geometry(&self, element: &E) -> Option<Rectangle<i32, Logical>> {
+        self.position(element).and_then(|pos| self.elements[pos].1)
+    }
+
+    /// Compute the geometries of all elements inside the given region
+    ///
+    /// Elements with a manual geometry keep it, the remaining elements are tiled.
+    pub fn geometries(&self, region: Rectangle<i32, Logical>) -> Vec<(&E, Rectangle<i32, Logical>)> {
+        let tiled = self
+            .elements
+            .iter()
+            .filter(|(_, geometry)| geometry.is_none())
+            .count();
+        let mut tiles = tile(self.strategy, self.gaps, tiled, region).into_iter();
+        self.elements
+            .iter()
+            .map(|(element, geometry)| {
+                let geometry = geometry.unwrap_or_else(|| tiles.next().unwrap());
+                (element, geometry)
+            })
+            .collect()
+    }
+
+    /// Arrange the elements inside the given region of a space
+    ///
+    /// Every element is configured with its new geometry and mapped accordingly, without
+    /// activating it. Note that mapping an element raises it to the top of the stack.
+    pub fn apply(&self, space: &mut Space<E>, region: Rectangle<i32, Logical>) {
+        for (element, geometry) in self.geometries(region) {
+            element.configure_tiled(geometry);
+            // the geometry of the element may be offset from its location, e.g. by client-side shadows
+            let location = geometry.loc - element.geometry().loc;
+            space.map_element(element.clone(), location, false);
+        }
+    }
+
+    fn position(&self, element: &E) -> Option<usize> {
+        self.elements.iter().position(|(e, _)| e == element)
+    }
+}
+
+fn tile(
+    strategy: TilingStrategy,
+    gaps: Gaps,
+    count: usize,
+    region: Rectangle<i32, Logical>,
+) -> Vec<Rectangle<i32, Logical>> {
+    // tile an area extended by half the inner gap, so that shrinking every tile by the inner gap
+    // aligns the outer tiles with the region inset by the outer gap
+    let half = gaps.inner / 2;
+    let area = Rectangle::from_loc_and_size(
+        (region.loc.x + gaps.outer - half, region.loc.y + gaps.outer - half),
+        (
+            region.size.w - 2 * gaps.outer + gaps.inner,
+            region.size.h - 2 * gaps.outer + gaps.inner,
+        ),
+    );
+
+    let tiles = match strategy {
+        TilingStrategy::BinarySplit { ratio } => binary_split(area, count, ratio),
+        TilingStrategy::MasterStack {
+            master_count,
+            master_ratio,
+        } => master_stack(area, count, master_count, master_ratio),
+        TilingStrategy::Grid => grid(area, count),
+    };
+
+    tiles
+        .into_iter()
+        .map(|tile| {
+            Rectangle::from_loc_and_size(
+                (tile.loc.x + half, tile.loc.y + half),
+                (
+                    (tile.size.w - gaps.inner).max(0),
+                    (tile.size.h - gaps.inner).max(0),
+                ),
+            )
+        })
+        .collect()
+}
+
+// Splits a length into `count` parts differing at most by one
+fn split(start: i32, length: i32, count: usize) -> impl Iterator<Item = (i32, i32)> {
+    let count = count.max(1) as i64;
+    (0..count).map(move |i| {
+        let from = length as i64 * i / count;
+        let to = length as i64 * (i + 1) / count;
+        (start + from as i32, (to - from) as i32)
+    })
+}
+
+fn columns(area: Rectangle<i32, Logical>, count: usize) -> impl Iterator<Item = Rectangle<i32, Logical>> {
+    split(area.loc.x, area.size.w, count)
+        .map(move |(x, w)| Rectangle::from_loc_and_size((x, area.loc.y), (w, area.size.h)))
+}
+
+fn rows(area: Rectangle<i32, Logical>, count: usize) -> impl Iterator<Item = Rectangle<i32, Logical>> {
+    split(area.loc.y, area.size.h, count)
+        .map(move |(y, h)| Rectangle::from_loc_and_size((area.loc.x, y), (area.size.w, h)))
+}
+
+fn binary_split(area: Rectangle<i32, Logical>, count: usize, ratio: f64) -> Vec<Rectangle<i32, Logical>> {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let mut tiles = Vec::with_capacity(count);
+    let mut remaining = area;
+    for i in 0..count {
+        if i + 1 == count {
+            tiles.push(remaining);
+            break;
+        }
+        if remaining.size.w >= remaining.size.h {
+            let w = (remaining.size.w as f64 * ratio).round() as i32;
+            tiles.push(Rectangle::from_loc_and_size(remaining.loc, (w, remaining.size.h)));
+            remaining.loc.x += w;
+            remaining.size.w -= w;
+        } else {
+            let h = (remaining.size.h as f64 * ratio).round() as i32;
+            tiles.push(Rectangle::from_loc_and_size(remaining.loc, (remaining.size.w, h)));
+            remaining.loc.y += h;
+            remaining.size.h -= h;
+        }
+    }
+    tiles
+}
+
+fn master_stack(
+    area: Rectangle<i32, Logical>,
+    count: usize,
+    master_count: usize,
+    master_ratio: f64,
+) -> Vec<Rectangle<i32, Logical>> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let masters = master_count.min(count);
+    if masters == 0 || masters == count {
+        return rows(area, count).collect();
+    }
+
+    let w = (area.size.w as f64 * master_ratio.clamp(0.0, 1.0)).round() as i32;
+    let master = Rectangle::from_loc_and_size(area.loc, (w, area.size.h));
+    let stack = Rectangle::from_loc_and_size((area.loc.x + w, area.loc.y), (area.size.w - w, area.size.h));
+    rows(master, masters)
+        .chain(rows(stack, count - masters))
+        .collect()
+}
+
+fn grid(area: Rectangle<i32, Logical>, count: usize) -> Vec<Rectangle<i32, Logical>> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let mut columns_count = 1;
+    while columns_count * columns_count < count {
+        columns_count += 1;
+    }
+    let rows_count = (count + columns_count - 1) / columns_count;
+
+    rows(area, rows_count)
+        .enumerate()
+        .flat_map(|(row, area)| {
+            let cells = (count - row * columns_count).min(columns_count);
+            columns(area, cells)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn binary_split_alternates() {
+        let tiles = tile(
+            TilingStrategy::BinarySplit { ratio: 0.5 },
+            Gaps::default(),
+            3,
+            rect(0, 0, 100, 60),
+        );
+        assert_eq!(
+            tiles,
+            vec![rect(0, 0, 50, 60), rect(50, 0, 50, 30), rect(50, 30, 50, 30)]
+        );
+    }
+
+    #[test]
+    fn master_stack_with_gaps() {
+        let tiles = tile(
+            TilingStrategy::MasterStack {
+                master_count: 1,
+                master_ratio: 0.5,
+            },
+            Gaps { inner: 10, outer: 5 },
+            3,
+            rect(0, 0, 110, 110),
+        );
+        assert_eq!(
+            tiles,
+            vec![rect(5, 5, 45, 100), rect(60, 5, 45, 45), rect(60, 60, 45, 45)]
+        );
+    }
+
+    #[test]
+    fn grid_stretches_last_row() {
+        let tiles = tile(TilingStrategy::Grid, Gaps::default(), 3, rect(0, 0, 100, 100));
+        assert_eq!(
+            tiles,
+            vec![rect(0, 0, 50, 50), rect(50, 0, 50, 50), rect(0, 50, 100, 50)]
+        );
+    }
+
+    #[test]
+    fn no_elements() {
+        for strategy in [
+            TilingStrategy::default(),
+            TilingStrategy::MasterStack {
+                master_count: 1,
+                master_ratio: 0.5,
+            },
+            TilingStrategy::Grid,
+        ] {
+            assert!(tile(strategy, Gaps::default(), 0, rect(0, 0, 100, 100)).is_empty());
+        }
+    }
+}
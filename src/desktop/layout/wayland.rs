@@ -0,0 +1,26 @@
+use wayland_protocols::xdg::shell::server::xdg_toplevel;
+
+use crate::{
+    desktop::Window,
+    utils::{Logical, Rectangle},
+};
+
+use super::TiledElement;
+
+impl TiledElement for Window {
+    fn configure_tiled(&self, geometry: Rectangle<i32, Logical>) {
+        let toplevel = self.toplevel();
+        toplevel.with_pending_state(|state| {
+            state.size = Some(geometry.size);
+            for tiled in [
+                xdg_toplevel::State::TiledLeft,
+                xdg_toplevel::State::TiledRight,
+                xdg_toplevel::State::TiledTop,
+                xdg_toplevel::State::TiledBottom,
+            ] {
+                state.states.set(tiled);
+            }
+        });
+        toplevel.send_pending_configure();
+    }
+}
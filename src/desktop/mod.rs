@@ -28,6 +28,14 @@
 //! Elements get a position and stacking order through mapping. Outputs become views of a part of the [`Space`]
 //! and can be rendered via [`render_output`](crate::desktop::space::render_output).
 //!
+//! Compositor-side content like wallpapers or on-screen displays can be mapped as well by wrapping it
+//! into a [`CustomElement`](space::CustomElement).
+//!
+//! ### Tiling
+//!
+//! The [`layout`] module provides a [`TilingLayout`](layout::TilingLayout), arranging the elements of a
+//! [`Space`] in tiles using one of several strategies, as a foundation for tiling compositors.
+//!
 //! ### Layer Shell
 //!
 //! A [`LayerSurface`] represents a surface as provided by e.g. the layer-shell protocol.
@@ -43,17 +51,33 @@
 //! relations to one-another. Popups are then automatically rendered with their matching toplevel surfaces,
 //! when either [`draw_window`], [`draw_layer_surface`] or [`render_output`](crate::desktop::space::render_output) is called.
 //!
+//! ### Server-side decorations
+//!
+//! The [`decoration`] module provides [`WindowDecorations`](decoration::WindowDecorations), drawing titlebars
+//! and borders around windows, which negotiated server-side decorations, and classifying pointer input on them.
+//!
+//! ### Cursors
+//!
+//! With the `xcursor` feature enabled, the [`cursor`] module provides a [`CursorManager`](cursor::CursorManager)
+//! loading XCursor themes and rendering the cursor image requested by clients.
+//!
 //! ## Remarks
 //!
 //! Note that the desktop abstractions are concerned with easing rendering different clients and therefore need to be able
 //! to manage client buffers to do so. If you plan to use the provided drawing functions, you need to use
 //! [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler).
 
+pub mod layout;
 pub mod space;
 pub use self::space::Space;
 
+#[cfg(all(feature = "wayland_frontend", feature = "xcursor"))]
+pub use self::wayland::cursor;
+#[cfg(feature = "wayland_frontend")]
+pub use self::wayland::decoration;
 #[cfg(feature = "wayland_frontend")]
 pub use self::wayland::{
+    dnd_icon::DndIcon,
     layer::{layer_map_for_output, LayerMap, LayerSurface},
     popup::*,
     utils,
@@ -61,6 +85,10 @@ pub use self::wayland::{
 };
 #[cfg(feature = "wayland_frontend")]
 mod wayland {
+    #[cfg(feature = "xcursor")]
+    pub mod cursor;
+    pub mod decoration;
+    pub(crate) mod dnd_icon;
     pub(crate) mod layer;
     pub mod popup;
     pub mod utils;
@@ -0,0 +1,181 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    backend::renderer::{element::AsRenderElements, Renderer},
+    output::Output,
+    utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+use super::{RenderZindex, SpaceElement};
+
+/// Compositor-side content mappable onto a [`Space`](crate::desktop::Space)
+///
+/// Wraps anything implementing [`AsRenderElements`], e.g. a
+/// [`SolidColorBuffer`](crate::backend::renderer::element::solid::SolidColorBuffer),
+/// to place wallpapers, on-screen displays, decorations or drag icons alongside client windows.
+/// The element has a size of its own, an overridable z-index and does not accept input by default.
+///
+/// Clones refer to the same element. The element stays alive until [`CustomElement::destroy`]
+/// is called, after which it is removed from spaces on their next [`refresh`](crate::desktop::Space::refresh).
+///
+/// To map custom elements next to windows, combine them into a single element type
+/// with the [`space_elements!`](crate::space_elements)-macro.
+#[derive(Debug)]
+pub struct CustomElement<T>(Arc<CustomElementInner<T>>);
+
+#[derive(Debug)]
+struct CustomElementInner<T> {
+    content: Mutex<T>,
+    size: Mutex<Size<i32, Logical>>,
+    z_index: AtomicU8,
+    accepts_input: AtomicBool,
+    alive: AtomicBool,
+}
+
+impl<T> CustomElement<T> {
+    /// Create a new element of the given size
+    pub fn new(content: T, size: impl Into<Size<i32, Logical>>) -> Self {
+        CustomElement(Arc::new(CustomElementInner {
+            content: Mutex::new(content),
+            size: Mutex::new(size.into()),
+            z_index: AtomicU8::new(RenderZindex::Shell as u8),
+            accepts_input: AtomicBool::new(false),
+            alive: AtomicBool::new(true),
+        }))
+    }
+
+    /// Access the content of this element
+    pub fn with_content<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        f(&mut self.0.content.lock().unwrap())
+    }
+
+    /// Returns the size of this element
+    pub fn size(&self) -> Size<i32, Logical> {
+        *self.0.size.lock().unwrap()
+    }
+
+    /// Set the size of this element
+    pub fn set_size(&self, size: impl Into<Size<i32, Logical>>) {
+        *self.0.size.lock().unwrap() = size.into();
+    }
+
+    /// Override the z_index of this element
+    ///
+    /// Defaults to [`RenderZindex::Shell`].
+    pub fn override_z_index(&self, z_index: u8) {
+        self.0.z_index.store(z_index, Ordering::SeqCst);
+    }
+
+    /// Returns whenever this element accepts input
+    pub fn accepts_input(&self) -> bool {
+        self.0.accepts_input.load(Ordering::SeqCst)
+    }
+
+    /// Set whenever this element accepts input
+    ///
+    /// Elements not accepting input are ignored by [`Space::element_under`](crate::desktop::Space::element_under).
+    pub fn set_accepts_input(&self, accepts_input: bool) {
+        self.0.accepts_input.store(accepts_input, Ordering::SeqCst);
+    }
+
+    /// Destroy this element, causing it to be removed from spaces
+    pub fn destroy(&self) {
+        self.0.alive.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<T> Clone for CustomElement<T> {
+    fn clone(&self) -> Self {
+        CustomElement(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for CustomElement<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Eq for CustomElement<T> {}
+
+impl<T> Hash for CustomElement<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl<T> IsAlive for CustomElement<T> {
+    fn alive(&self) -> bool {
+        self.0.alive.load(Ordering::SeqCst)
+    }
+}
+
+impl<T> SpaceElement for CustomElement<T> {
+    fn bbox(&self) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((0, 0), self.size())
+    }
+
+    fn is_in_input_region(&self, point: &Point<f64, Logical>) -> bool {
+        self.accepts_input() && self.bbox().to_f64().contains(*point)
+    }
+
+    fn z_index(&self) -> u8 {
+        self.0.z_index.load(Ordering::SeqCst)
+    }
+
+    fn set_activate(&self, _activated: bool) {}
+    fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+    fn output_leave(&self, _output: &Output) {}
+}
+
+impl<R, T> AsRenderElements<R> for CustomElement<T>
+where
+    R: Renderer,
+    T: AsRenderElements<R>,
+{
+    type RenderElement = T::RenderElement;
+
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        self.0
+            .content
+            .lock()
+            .unwrap()
+            .render_elements(renderer, location, scale, alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_region() {
+        let element = CustomElement::new((), (100, 50));
+        assert!(!element.is_in_input_region(&(10.0, 10.0).into()));
+
+        element.set_accepts_input(true);
+        assert!(element.is_in_input_region(&(10.0, 10.0).into()));
+        assert!(!element.is_in_input_region(&(10.0, 60.0).into()));
+
+        let clone = element.clone();
+        assert_eq!(element, clone);
+        assert_ne!(element, CustomElement::new((), (100, 50)));
+        clone.destroy();
+        assert!(!element.alive());
+    }
+}
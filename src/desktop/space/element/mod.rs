@@ -2,15 +2,18 @@ use crate::{
     backend::renderer::{element::Wrap, Renderer},
     desktop::space::*,
     output::Output,
-    utils::{Logical, Physical, Point, Rectangle, Scale},
+    utils::{Logical, Physical, Point, Rectangle, Region, Scale},
 };
 use std::hash::Hash;
 
+mod custom;
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
 #[cfg(feature = "wayland_frontend")]
 pub use self::wayland::SurfaceTree;
 
+pub use self::custom::CustomElement;
+
 /// Indicates default values for some zindexs inside smithay
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u8)]
@@ -39,6 +42,33 @@ impl From<RenderZindex> for Option<u8> {
     }
 }
 
+/// Visibility of an element mapped in a [`Space`](super::Space)
+///
+/// Elements not [`Visible`](Visibility::Visible) can be suspended, e.g. by throttling
+/// their frame callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Visibility {
+    /// The element is at least partially visible on an enabled output
+    #[default]
+    Visible,
+    /// The element is not visible on any output, as it is fully covered
+    /// by the opaque regions of other elements or outside of all outputs
+    Occluded,
+    /// The element is only shown on disabled outputs
+    OutputDisabled,
+    /// The element is hidden, e.g. because it is on an inactive workspace
+    ///
+    /// See [`Space::hide_element`](super::Space::hide_element).
+    Hidden,
+}
+
+impl Visibility {
+    /// Returns whether the element is not visible at all
+    pub fn is_suspended(&self) -> bool {
+        *self != Visibility::Visible
+    }
+}
+
 /// Element mappable onto a [`Space`](super::Space)
 pub trait SpaceElement: IsAlive {
     /// Returns the geometry of this element.
@@ -65,6 +95,24 @@ pub trait SpaceElement: IsAlive {
     fn output_enter(&self, output: &Output, overlap: Rectangle<i32, Logical>);
     /// The element left a given output
     fn output_leave(&self, output: &Output);
+    /// The primary output of the element changed
+    ///
+    /// The primary output is the output showing the largest part of the element,
+    /// see [`Space::element_primary_output`](super::Space::element_primary_output).
+    /// It is the preferred output to pick e.g. the scale of the element from.
+    ///
+    /// This is also called, if the scale or transform of the primary output changed,
+    /// unless vetoed through [`Space::set_output_update_filter`](super::Space::set_output_update_filter).
+    fn primary_output_changed(&self, _output: Option<&Output>) {}
+    /// Returns the opaque region of this element, relative to the elements origin
+    ///
+    /// The opaque region is used to determine which elements are occluded.
+    /// Defaults to an empty region.
+    fn opaque_region(&self) -> Region<i32, Logical> {
+        Region::new()
+    }
+    /// The visibility of the element changed
+    fn visibility_changed(&self, _visibility: Visibility) {}
     /// Periodically called to update internal state, if necessary
     fn refresh(&self) {}
 }
@@ -92,6 +140,15 @@ impl<T: SpaceElement> SpaceElement for &T {
     fn output_leave(&self, output: &Output) {
         SpaceElement::output_leave(*self, output)
     }
+    fn primary_output_changed(&self, output: Option<&Output>) {
+        SpaceElement::primary_output_changed(*self, output)
+    }
+    fn opaque_region(&self) -> Region<i32, Logical> {
+        SpaceElement::opaque_region(*self)
+    }
+    fn visibility_changed(&self, visibility: Visibility) {
+        SpaceElement::visibility_changed(*self, visibility)
+    }
     fn refresh(&self) {
         SpaceElement::refresh(*self)
     }
@@ -353,6 +410,46 @@ macro_rules! space_elements_internal {
                 Self::_GenericCatcher(_) => unreachable!(),
             }
         }
+        fn primary_output_changed(&self, output: Option<&$crate::output::Output>) {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => {
+                        $crate::space_elements_internal!(@call primary_output_changed; x, output)
+                    }
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+        fn opaque_region(&self) -> $crate::utils::Region<i32, $crate::utils::Logical> {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::space_elements_internal!(@call opaque_region; x)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
+        fn visibility_changed(&self, visibility: $crate::desktop::space::Visibility) {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => {
+                        $crate::space_elements_internal!(@call visibility_changed; x, visibility)
+                    }
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
         fn refresh(&self) {
             match self {
                 $(
@@ -0,0 +1,181 @@
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error as OutputDamageTrackerError, OutputDamageTracker, OutputNoMode},
+            element::{texture::TextureRenderBuffer, texture::TextureRenderElement, RenderElement},
+            Bind, Offscreen, Renderer, Texture, Unbind,
+        },
+    },
+    output::Output,
+    utils::{Buffer, Physical, Point, Rectangle, Size, Transform},
+};
+
+/// Rendered contents of an [`Output`] to be displayed on its mirrors
+///
+/// The render elements of the source output are composited into an offscreen texture
+/// once, which can then be displayed on any number of mirrors (see
+/// [`Output::set_mirror_source`]) through [`OutputMirror::render_element`].
+/// The contents are scaled to fit the mode of the mirror, keeping their aspect ratio.
+/// The remaining area is left empty (letterboxed), so it shows the clear color of the mirror.
+///
+/// Only the damaged parts of the texture are redrawn and the damage is forwarded
+/// to the mirrors, so idle mirrors do not need to be redrawn.
+#[derive(Debug)]
+pub struct OutputMirror<T> {
+    buffer: Option<TextureRenderBuffer<T>>,
+    // the damage tracker is only valid for the size and scale the source was rendered with
+    damage_tracker: Option<(Size<i32, Physical>, f64, OutputDamageTracker)>,
+}
+
+impl<T> Default for OutputMirror<T> {
+    fn default() -> Self {
+        OutputMirror {
+            buffer: None,
+            damage_tracker: None,
+        }
+    }
+}
+
+impl<T> OutputMirror<T> {
+    /// Create a new empty mirror
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the size of the mirrored contents
+    ///
+    /// This is the size of the mode of the source output after applying its transform,
+    /// or `None` if nothing was rendered yet.
+    pub fn size(&self) -> Option<Size<i32, Physical>> {
+        self.damage_tracker.as_ref().map(|(size, _, _)| *size)
+    }
+
+    /// Drop the rendered contents
+    ///
+    /// The contents are rendered completely on the next update.
+    pub fn reset(&mut self) {
+        self.buffer = None;
+        self.damage_tracker = None;
+    }
+}
+
+impl<T: Texture + Clone> OutputMirror<T> {
+    /// Update the mirrored contents
+    ///
+    /// `elements` are expected to be the render elements used to render `source`, e.g. as
+    /// returned by [`space_render_elements`](super::space_render_elements), so the composition
+    /// of the source is reused instead of rendering the space again for every mirror.
+    ///
+    /// Returns the damage of the mirrored contents, `None` if nothing changed.
+    pub fn update<R, E>(
+        &mut self,
+        renderer: &mut R,
+        source: &Output,
+        elements: &[E],
+        clear_color: [f32; 4],
+    ) -> Result<Option<Vec<Rectangle<i32, Physical>>>, OutputDamageTrackerError<R>>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+        E: RenderElement<R>,
+    {
+        let mode = source.current_mode().ok_or(OutputNoMode)?;
+        // render the contents upright, so they can be displayed with the transform of the mirror
+        let size = source.current_transform().transform_size(mode.size);
+        let scale = source.current_scale().fractional_scale();
+
+        let valid = matches!(
+            self.damage_tracker,
+            Some((tracker_size, tracker_scale, _)) if tracker_size == size && tracker_scale == scale
+        );
+        if !valid {
+            self.reset();
+            self.damage_tracker = Some((
+                size,
+                scale,
+                OutputDamageTracker::new(size, scale, Transform::Normal),
+            ));
+        }
+
+        // the texture keeps its contents between updates
+        let age = if self.buffer.is_some() { 1 } else { 0 };
+        if self.buffer.is_none() {
+            let texture = renderer
+                .create_buffer(Fourcc::Abgr8888, (size.w, size.h).into())
+                .map_err(OutputDamageTrackerError::Rendering)?;
+            self.buffer = Some(TextureRenderBuffer::from_texture(
+                renderer,
+                texture,
+                1,
+                Transform::Normal,
+                None,
+            ));
+        }
+        let buffer = self.buffer.as_mut().unwrap();
+        let (_, _, damage_tracker) = self.damage_tracker.as_mut().unwrap();
+
+        let mut damage = None;
+        buffer.render().draw(|texture| {
+            renderer
+                .bind(texture.clone())
+                .map_err(OutputDamageTrackerError::Rendering)?;
+            let res = damage_tracker.render_output(renderer, age, elements, clear_color);
+            renderer.unbind().map_err(OutputDamageTrackerError::Rendering)?;
+
+            let (rendered_damage, _) = res?;
+            damage = rendered_damage;
+            Ok(damage
+                .iter()
+                .flatten()
+                .map(|rect| {
+                    Rectangle::<i32, Buffer>::from_loc_and_size(
+                        (rect.loc.x, rect.loc.y),
+                        (rect.size.w, rect.size.h),
+                    )
+                })
+                .collect())
+        })?;
+
+        Ok(damage)
+    }
+}
+
+impl<T: Texture + Clone + 'static> OutputMirror<T> {
+    /// Returns the render element displaying the mirrored contents on `mirror`
+    ///
+    /// The element is positioned in the physical coordinate space of `mirror`,
+    /// centered and scaled to fit its mode. `None` is returned if nothing was rendered yet.
+    pub fn render_element(
+        &self,
+        mirror: &Output,
+        alpha: f32,
+    ) -> Result<Option<TextureRenderElement<T>>, OutputNoMode> {
+        let mode = mirror.current_mode().ok_or(OutputNoMode)?;
+        let (Some(buffer), Some(size)) = (self.buffer.as_ref(), self.size()) else {
+            return Ok(None);
+        };
+        if size.w <= 0 || size.h <= 0 {
+            return Ok(None);
+        }
+
+        let mirror_size = mirror.current_transform().transform_size(mode.size);
+        let mirror_scale = mirror.current_scale().fractional_scale();
+        let factor = f64::min(
+            mirror_size.w as f64 / size.w as f64,
+            mirror_size.h as f64 / size.h as f64,
+        );
+        let scaled_size = size.to_f64().upscale(factor).to_i32_round::<i32>();
+        let location = Point::<i32, Physical>::from((
+            (mirror_size.w - scaled_size.w) / 2,
+            (mirror_size.h - scaled_size.h) / 2,
+        ));
+
+        Ok(Some(TextureRenderElement::from_texture_render_buffer(
+            location.to_f64(),
+            buffer,
+            Some(alpha),
+            None,
+            Some(scaled_size.to_f64().to_logical(mirror_scale).to_i32_round()),
+        )))
+    }
+}
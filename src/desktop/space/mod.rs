@@ -10,7 +10,7 @@ use crate::{
         Renderer, Texture,
     },
     output::Output,
-    utils::{IsAlive, Logical, Physical, Point, Rectangle, Scale, Transform},
+    utils::{IsAlive, Logical, Physical, Point, Rectangle, Region, Scale, Size, Transform},
 };
 #[cfg(feature = "wayland_frontend")]
 use crate::{
@@ -24,14 +24,18 @@ use tracing::{debug, debug_span, instrument};
 use wayland_server::protocol::wl_surface::WlSurface;
 
 mod element;
+mod mirror;
 mod output;
+mod thumbnail;
 mod utils;
 
 #[cfg(feature = "wayland_frontend")]
 mod wayland;
 
 pub use self::element::*;
+pub use self::mirror::OutputMirror;
 use self::output::*;
+pub use self::thumbnail::Thumbnail;
 pub use self::utils::*;
 
 crate::utils::ids::id_gen!(next_space_id, SPACE_ID, SPACE_IDS);
@@ -40,7 +44,96 @@ crate::utils::ids::id_gen!(next_space_id, SPACE_ID, SPACE_IDS);
 struct InnerElement<E> {
     element: E,
     location: Point<i32, Logical>,
+    band: StackingBand,
     outputs: HashMap<Output, Rectangle<i32, Logical>>,
+    primary_output: Option<Output>,
+    // scale and transform of the primary output last notified to the element
+    primary_state: Option<(f64, Transform)>,
+    visibility: Visibility,
+}
+
+/// Stacking band of an element mapped in a [`Space`]
+///
+/// Elements of a higher band are always stacked above elements of a lower band,
+/// raising an element only moves it to the top of its own band.
+/// Bands are ordered below the [z-index](SpaceElement::z_index) of an element,
+/// so they only affect the order of elements sharing the same z-index.
+///
+/// Besides the predefined bands any custom value may be used, e.g.
+/// `StackingBand(StackingBand::ABOVE.0 + 1)` to stack docks above other always-on-top windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StackingBand(pub i32);
+
+impl StackingBand {
+    /// Band for windows kept below all other windows, e.g. desktop icons
+    pub const BELOW: StackingBand = StackingBand(-100);
+    /// Default band of newly mapped elements
+    pub const NORMAL: StackingBand = StackingBand(0);
+    /// Band for always-on-top windows
+    pub const ABOVE: StackingBand = StackingBand(100);
+    /// Band for ephemeral overlays, e.g. notifications or on-screen displays
+    pub const OVERLAY: StackingBand = StackingBand(200);
+}
+
+impl Default for StackingBand {
+    fn default() -> Self {
+        StackingBand::NORMAL
+    }
+}
+
+/// Minimum overlap of an element with an output for the element to enter the output
+///
+/// Regardless of the threshold, an element always enters its
+/// [primary output](Space::element_primary_output), if it overlaps with any output.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputOverlapThreshold {
+    /// Any overlap, no matter how small
+    #[default]
+    Any,
+    /// A minimum overlapping area in square logical pixels
+    Area(i32),
+    /// A minimum fraction of the area of the bounding box of the element
+    Fraction(f64),
+}
+
+/// Decision about notifying an element of a changed scale or transform of its primary output
+///
+/// Returned by the callback set with [`Space::set_output_update_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputUpdate {
+    /// Notify the element now
+    #[default]
+    Apply,
+    /// Do not notify the element yet, the update is retried on the next [`Space::refresh`]
+    ///
+    /// This can be used to stagger updates of many elements over multiple frames.
+    Defer,
+    /// Do not notify the element about this change
+    Skip,
+}
+
+type OutputUpdateCallback<E> = dyn FnMut(&E, &Output) -> OutputUpdate;
+
+struct OutputUpdateFilter<E>(Box<OutputUpdateCallback<E>>);
+
+impl<E> fmt::Debug for OutputUpdateFilter<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputUpdateFilter").finish_non_exhaustive()
+    }
+}
+
+impl OutputOverlapThreshold {
+    fn is_reached(&self, overlap: Size<i32, Logical>, bbox: Size<i32, Logical>) -> bool {
+        let area = overlap.w * overlap.h;
+        match *self {
+            OutputOverlapThreshold::Any => true,
+            OutputOverlapThreshold::Area(min) => area >= min,
+            OutputOverlapThreshold::Fraction(min) => {
+                let bbox_area = bbox.w * bbox.h;
+                bbox_area > 0 && area as f64 / bbox_area as f64 >= min
+            }
+        }
+    }
 }
 
 /// Represents two dimensional plane to map windows and outputs upon.
@@ -54,7 +147,10 @@ pub struct Space<E: SpaceElement> {
     pub(super) id: usize,
     // in z-order, back to front
     elements: Vec<InnerElement<E>>,
+    hidden: Vec<InnerElement<E>>,
     outputs: Vec<Output>,
+    overlap_threshold: OutputOverlapThreshold,
+    output_update_filter: Option<OutputUpdateFilter<E>>,
     span: tracing::Span,
 }
 
@@ -78,7 +174,10 @@ impl<E: SpaceElement> Default for Space<E> {
         Self {
             id,
             elements: Default::default(),
+            hidden: Default::default(),
             outputs: Default::default(),
+            overlap_threshold: Default::default(),
+            output_update_filter: None,
             span,
         }
     }
@@ -95,6 +194,10 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     /// This can safely be called on an already mapped window
     /// to update its location inside the space.
     ///
+    /// Newly mapped elements are put into [`StackingBand::NORMAL`], already mapped
+    /// elements keep their band and are moved to the top of it.
+    /// Hidden elements are shown again.
+    ///
     /// If activate is true it will set the new windows state
     /// to be activate and removes that state from every
     /// other mapped window.
@@ -102,21 +205,72 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     where
         P: Into<Point<i32, Logical>>,
     {
-        let outputs = if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
-            self.elements.remove(pos).outputs
-        } else {
-            HashMap::new()
-        };
+        let band = self.element_band(&element).unwrap_or_default();
+        self.map_element_in_band(element, location, band, activate);
+    }
+
+    /// Map a [`SpaceElement`] and move it to top of the given [`StackingBand`]
+    ///
+    /// See [`Space::map_element`].
+    pub fn map_element_in_band<P>(&mut self, element: E, location: P, band: StackingBand, activate: bool)
+    where
+        P: Into<Point<i32, Logical>>,
+    {
+        let (outputs, primary_output, primary_state) =
+            if let Some(pos) = self.elements.iter().position(|inner| inner.element == element) {
+                let inner = self.elements.remove(pos);
+                (inner.outputs, inner.primary_output, inner.primary_state)
+            } else {
+                self.hidden.retain(|inner| inner.element != element);
+                (HashMap::new(), None, None)
+            };
 
         let inner = InnerElement {
             element,
             location: location.into(),
+            band,
             outputs,
+            primary_output,
+            primary_state,
+            visibility: Visibility::Visible,
         };
         self.insert_elem(inner, activate);
     }
 
-    /// Moves an already mapped [`SpaceElement`] to top of the stack
+    /// Moves an already mapped [`SpaceElement`] into another [`StackingBand`]
+    ///
+    /// The element is put on top of its new band.
+    /// This function does nothing for unmapped elements.
+    pub fn set_element_band(&mut self, element: &E, band: StackingBand) {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let mut inner = self.elements.remove(pos);
+            inner.band = band;
+            self.insert_elem(inner, false);
+        } else if let Some(inner) = self.hidden.iter_mut().find(|inner| &inner.element == element) {
+            inner.band = band;
+        }
+    }
+
+    /// Returns the [`StackingBand`] of a mapped [`SpaceElement`]
+    ///
+    /// This includes hidden elements.
+    pub fn element_band(&self, element: &E) -> Option<StackingBand> {
+        self.elements
+            .iter()
+            .chain(self.hidden.iter())
+            .find(|inner| &inner.element == element)
+            .map(|inner| inner.band)
+    }
+
+    /// Iterate the elements of a [`StackingBand`] in z-order back to front
+    pub fn elements_in_band(&self, band: StackingBand) -> impl DoubleEndedIterator<Item = &E> {
+        self.elements
+            .iter()
+            .filter(move |inner| inner.band == band)
+            .map(|inner| &inner.element)
+    }
+
+    /// Moves an already mapped [`SpaceElement`] to top of its [`StackingBand`]
     ///
     /// This function does nothing for unmapped windows.
     ///
@@ -139,8 +293,9 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         }
 
         self.elements.push(elem);
+        // stable sort, keeps the order inside of a band and puts the new element on top of its band
         self.elements
-            .sort_by(|e1, e2| e1.element.z_index().cmp(&e2.element.z_index()));
+            .sort_by_key(|inner| (inner.element.z_index(), inner.band));
     }
 
     /// Unmap a [`SpaceElement`] from this space.
@@ -152,9 +307,68 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             for output in elem.outputs.keys() {
                 elem.element.output_leave(output);
             }
+        } else {
+            self.hidden.retain(|inner| &inner.element != element);
+        }
+    }
+
+    /// Hide a mapped [`SpaceElement`], e.g. to minimize a window
+    ///
+    /// Hidden elements are excluded from rendering and input and leave all outputs,
+    /// but keep their location and [`StackingBand`] until they are shown again.
+    /// They are not returned by [`Space::elements`] and any other query of mapped elements,
+    /// use [`Space::hidden_elements`] to iterate them.
+    ///
+    /// As hidden elements are not on any output, the compositor is expected to keep
+    /// sending them throttled frame callbacks, e.g. by calling
+    /// [`Window::send_frame`](crate::desktop::Window::send_frame) with a throttle duration
+    /// and no primary scan-out output.
+    ///
+    /// This function does nothing for unmapped or already hidden elements.
+    pub fn hide_element(&mut self, element: &E) {
+        if let Some(pos) = self.elements.iter().position(|inner| &inner.element == element) {
+            let mut inner = self.elements.remove(pos);
+            for output in inner.outputs.keys() {
+                inner.element.output_leave(output);
+            }
+            inner.outputs.clear();
+            inner.primary_state = None;
+            if inner.primary_output.take().is_some() {
+                inner.element.primary_output_changed(None);
+            }
+            inner.element.set_activate(false);
+            inner.visibility = Visibility::Hidden;
+            inner.element.visibility_changed(Visibility::Hidden);
+            self.hidden.push(inner);
         }
     }
 
+    /// Show a hidden [`SpaceElement`] again
+    ///
+    /// The element is restored at its previous location on top of its [`StackingBand`].
+    ///
+    /// If activate is true it will set the new windows state
+    /// to be activate and removes that state from every
+    /// other mapped window.
+    ///
+    /// This function does nothing for elements, that are not hidden.
+    pub fn show_element(&mut self, element: &E, activate: bool) {
+        if let Some(pos) = self.hidden.iter().position(|inner| &inner.element == element) {
+            let inner = self.hidden.remove(pos);
+            self.insert_elem(inner, activate);
+        }
+    }
+
+    /// Returns if a [`SpaceElement`] is hidden
+    pub fn is_element_hidden(&self, element: &E) -> bool {
+        self.hidden.iter().any(|inner| &inner.element == element)
+    }
+
+    /// Iterate hidden elements in the order they were hidden
+    pub fn hidden_elements(&self) -> impl DoubleEndedIterator<Item = &E> {
+        self.hidden.iter().map(|e| &e.element)
+    }
+
     /// Iterate elements in z-order back to front
     pub fn elements(&self) -> impl DoubleEndedIterator<Item = &E> {
         self.elements.iter().map(|e| &e.element)
@@ -198,9 +412,14 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     }
 
     /// Get a reference to the outputs under a given point
+    ///
+    /// Mirrors of mapped outputs are never returned, see [`Space::mirrored_output`].
     pub fn output_under<P: Into<Point<f64, Logical>>>(&self, point: P) -> impl Iterator<Item = &Output> {
         let point = point.into();
         self.outputs.iter().rev().filter(move |o| {
+            if self.mirrored_output(o).is_some() {
+                return false;
+            }
             let bbox = self.output_geometry(o);
             bbox.map(|bbox| bbox.to_f64().contains(point)).unwrap_or(false)
         })
@@ -286,10 +505,14 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     ///
     /// The size is matching the amount of logical pixels of the space visible on the output
     /// given is current mode and scale.
+    ///
+    /// Mirrors of mapped outputs show the same part of the space as their source,
+    /// so the geometry of the source is returned for them.
     pub fn output_geometry(&self, o: &Output) -> Option<Rectangle<i32, Logical>> {
         if !self.outputs.contains(o) {
             return None;
         }
+        let o = &self.mirrored_output(o).unwrap_or_else(|| o.clone());
 
         let transform: Transform = o.current_transform();
         let state = output_state(self.id, o);
@@ -305,6 +528,34 @@ impl<E: SpaceElement + PartialEq> Space<E> {
         })
     }
 
+    /// Returns the output mirrored by a mapped output
+    ///
+    /// Returns `None` if the output is not mapped, is no mirror (see [`Output::set_mirror_source`])
+    /// or its source is not mapped to this space. Otherwise the mirror shows the same
+    /// elements as the source: elements entering the source enter the mirror as well,
+    /// but the mirror is never the [primary output](Space::element_primary_output) of an element,
+    /// so feedback like frame pacing stays tied to the source.
+    pub fn mirrored_output(&self, o: &Output) -> Option<Output> {
+        if !self.outputs.contains(o) {
+            return None;
+        }
+        o.mirror_source().filter(|source| self.outputs.contains(source))
+    }
+
+    /// Returns the work area of the output including it's relative position inside the space.
+    ///
+    /// The work area is the part of the output not covered by the exclusive zones of
+    /// layer surfaces, as computed by the [`LayerMap`](crate::desktop::LayerMap) of the output
+    /// (see [`LayerMap::non_exclusive_zone`](crate::desktop::LayerMap::non_exclusive_zone)).
+    /// It is the area maximized windows or popups should be constrained to.
+    #[cfg(feature = "wayland_frontend")]
+    pub fn output_work_area(&self, o: &Output) -> Option<Rectangle<i32, Logical>> {
+        let geometry = self.output_geometry(o)?;
+        let mut zone = layer_map_for_output(o).non_exclusive_zone();
+        zone.loc += geometry.loc;
+        Some(zone)
+    }
+
     /// Returns all [`Output`]s a [`SpaceElement`] overlaps with.
     pub fn outputs_for_element(&self, elem: &E) -> Vec<Output> {
         if !self.elements.iter().any(|e| &e.element == elem) {
@@ -321,6 +572,68 @@ impl<E: SpaceElement + PartialEq> Space<E> {
             .collect()
     }
 
+    /// Returns the [`Output`] showing the largest part of a [`SpaceElement`].
+    ///
+    /// This is the output whose scale and transform the element should preferably be
+    /// rendered for. While the element is moved across outputs, the primary output
+    /// changes as soon as another output shows a larger part of the element.
+    /// If multiple outputs show an equally large part, the output mapped first is returned.
+    ///
+    /// The overlap of elements and outputs is updated by [`Space::refresh`], which
+    /// notifies elements through [`SpaceElement::primary_output_changed`].
+    pub fn element_primary_output(&self, elem: &E) -> Option<Output> {
+        self.elements
+            .iter()
+            .find(|e| &e.element == elem)?
+            .primary_output
+            .clone()
+    }
+
+    /// Returns the [`Visibility`] of an element
+    ///
+    /// The visibility of elements is updated by [`Space::refresh`], which notifies elements
+    /// through [`SpaceElement::visibility_changed`].
+    pub fn element_visibility(&self, elem: &E) -> Option<Visibility> {
+        self.elements
+            .iter()
+            .chain(self.hidden.iter())
+            .find(|e| &e.element == elem)
+            .map(|e| e.visibility)
+    }
+
+    /// Returns the minimum overlap for elements to enter an output
+    pub fn overlap_threshold(&self) -> OutputOverlapThreshold {
+        self.overlap_threshold
+    }
+
+    /// Set the minimum overlap for elements to enter an output
+    ///
+    /// Takes effect on the next [`Space::refresh`].
+    pub fn set_overlap_threshold(&mut self, threshold: OutputOverlapThreshold) {
+        self.overlap_threshold = threshold;
+    }
+
+    /// Set a callback deciding when elements are notified about changes of their primary output
+    ///
+    /// Elements are notified through [`SpaceElement::primary_output_changed`] when the scale
+    /// or transform of their primary output changes, so they can e.g. update the preferred
+    /// scale of their surfaces. The callback is invoked on [`Space::refresh`] for every element
+    /// affected by such a change and may veto or postpone the notification (see [`OutputUpdate`]),
+    /// e.g. to not resize all clients at once.
+    ///
+    /// Changes of the primary output itself are always notified.
+    pub fn set_output_update_filter<F>(&mut self, filter: F)
+    where
+        F: FnMut(&E, &Output) -> OutputUpdate + 'static,
+    {
+        self.output_update_filter = Some(OutputUpdateFilter(Box::new(filter)));
+    }
+
+    /// Remove the callback set with [`Space::set_output_update_filter`]
+    pub fn clear_output_update_filter(&mut self) {
+        self.output_update_filter = None;
+    }
+
     /// Refresh some internal values and update client state,
     /// meaning this will handle output enter and leave events
     /// for mapped outputs and windows based on their position.
@@ -329,6 +642,7 @@ impl<E: SpaceElement + PartialEq> Space<E> {
     /// wayland socket flush.
     pub fn refresh(&mut self) {
         self.elements.retain(|e| e.alive());
+        self.hidden.retain(|e| e.alive());
 
         let outputs = self
             .outputs
@@ -338,36 +652,140 @@ impl<E: SpaceElement + PartialEq> Space<E> {
                 let geo = self
                     .output_geometry(&o)
                     .unwrap_or_else(|| Rectangle::from_loc_and_size((0, 0), (0, 0)));
-                (o, geo)
+                let mirrored = self.mirrored_output(&o);
+                (o, geo, mirrored)
             })
             .collect::<Vec<_>>();
+        // mirrors are never the primary output of an element
+        let primary_candidates = outputs
+            .iter()
+            .filter(|(_, _, mirrored)| mirrored.is_none())
+            .map(|(o, _, _)| o.clone())
+            .collect::<Vec<_>>();
         for e in &mut self.elements {
             let bbox = e.bbox();
 
-            for (output, output_geometry) in &outputs {
-                // Check if the bounding box of the toplevel intersects with the output
-                if let Some(mut overlap) = output_geometry.intersection(bbox) {
-                    // output_enter expects the overlap to be relative to the element
-                    overlap.loc -= bbox.loc;
-                    let old = e.outputs.insert(output.clone(), overlap);
-                    if old.is_none() || matches!(old, Some(old_overlap) if old_overlap != overlap) {
-                        e.element.output_enter(output, overlap);
+            // Check if the bounding box of the toplevel intersects with the outputs
+            let overlaps = outputs
+                .iter()
+                .filter_map(|(output, output_geometry, _)| {
+                    output_geometry.intersection(bbox).map(|mut overlap| {
+                        // output_enter expects the overlap to be relative to the element
+                        overlap.loc -= bbox.loc;
+                        (output.clone(), overlap)
+                    })
+                })
+                .collect::<HashMap<_, _>>();
+            let primary_output = largest_overlap(&primary_candidates, &overlaps).cloned();
+
+            for (output, _, mirrored) in &outputs {
+                // mirrors show an element whenever their source does
+                let shown_on = mirrored.as_ref().unwrap_or(output);
+                match overlaps.get(output) {
+                    Some(overlap)
+                        if primary_output.as_ref() == Some(shown_on)
+                            || self.overlap_threshold.is_reached(overlap.size, bbox.size) =>
+                    {
+                        let old = e.outputs.insert(output.clone(), *overlap);
+                        if old.is_none() || matches!(old, Some(old_overlap) if old_overlap != *overlap) {
+                            e.element.output_enter(output, *overlap);
+                        }
+                    }
+                    _ => {
+                        if e.outputs.remove(output).is_some() {
+                            e.element.output_leave(output);
+                        }
                     }
-                } else if e.outputs.remove(output).is_some() {
-                    e.element.output_leave(output);
                 }
             }
             e.outputs.retain(|output, _| {
-                if !outputs.iter().any(|(o, _)| o == output) {
+                if !outputs.iter().any(|(o, _, _)| o == output) {
                     e.element.output_leave(output);
                     false
                 } else {
                     true
                 }
             });
+
+            let primary_state = primary_output.as_ref().map(|output| {
+                (
+                    output.current_scale().fractional_scale(),
+                    output.current_transform(),
+                )
+            });
+            if e.primary_output != primary_output {
+                e.primary_output = primary_output;
+                e.primary_state = primary_state;
+                e.element.primary_output_changed(e.primary_output.as_ref());
+            } else if e.primary_state != primary_state {
+                // the scale or transform of the primary output changed
+                if let Some(output) = e.primary_output.as_ref() {
+                    let update = self
+                        .output_update_filter
+                        .as_mut()
+                        .map(|filter| (filter.0)(&e.element, output))
+                        .unwrap_or_default();
+                    match update {
+                        OutputUpdate::Apply => {
+                            e.primary_state = primary_state;
+                            e.element.primary_output_changed(Some(output));
+                        }
+                        OutputUpdate::Defer => {}
+                        OutputUpdate::Skip => e.primary_state = primary_state,
+                    }
+                }
+            }
         }
 
-        self.elements.iter().for_each(|e| e.element.refresh());
+        self.refresh_visibility(&outputs);
+
+        self.elements
+            .iter()
+            .chain(self.hidden.iter())
+            .for_each(|e| e.element.refresh());
+    }
+
+    // update the visibility of all elements, from top to bottom
+    fn refresh_visibility(&mut self, outputs: &[(Output, Rectangle<i32, Logical>, Option<Output>)]) {
+        let mut enabled_region = Region::new();
+        let mut disabled_region = Region::new();
+        for (output, geometry, _) in outputs {
+            if output.is_enabled() {
+                enabled_region.union_rect(*geometry);
+            } else {
+                disabled_region.union_rect(*geometry);
+            }
+        }
+
+        let mut opaque_above = Region::new();
+        for e in self.elements.iter_mut().rev() {
+            let bbox = e.bbox();
+            let mut visible = Region::from(bbox);
+            visible.intersect(&enabled_region);
+            let visibility = if visible.is_empty() {
+                if disabled_region.overlaps(bbox) {
+                    Visibility::OutputDisabled
+                } else {
+                    Visibility::Occluded
+                }
+            } else {
+                visible.subtract(&opaque_above);
+                if visible.is_empty() {
+                    Visibility::Occluded
+                } else {
+                    Visibility::Visible
+                }
+            };
+
+            let mut opaque_region = e.element.opaque_region();
+            opaque_region.translate(e.render_location());
+            opaque_above.union(&opaque_region);
+
+            if e.visibility != visibility {
+                e.visibility = visibility;
+                e.element.visibility_changed(visibility);
+            }
+        }
     }
 
     /// Retrieve the render elements for a given region of the space.
@@ -507,6 +925,24 @@ impl<E: SpaceElement> InnerElement<E> {
     }
 }
 
+// the first of `keys` with the largest overlap
+fn largest_overlap<'a, K: Eq + std::hash::Hash>(
+    keys: &'a [K],
+    overlaps: &HashMap<K, Rectangle<i32, Logical>>,
+) -> Option<&'a K> {
+    keys.iter()
+        .filter_map(|key| {
+            overlaps
+                .get(key)
+                .map(|overlap| (key, overlap.size.w * overlap.size.h))
+        })
+        .fold(None, |largest: Option<(&K, i32)>, (key, area)| match largest {
+            Some((_, largest_area)) if largest_area >= area => largest,
+            _ => Some((key, area)),
+        })
+        .map(|(key, _)| key)
+}
+
 #[cfg(feature = "wayland_frontend")]
 crate::backend::renderer::element::render_elements! {
     /// Defines the render elements used internally by a [`Space`]
@@ -657,6 +1093,11 @@ where
 ///
 /// If multiple spaces are given their elements will be stacked
 /// the same way.
+///
+/// Only the damaged parts of the output are redrawn, elements without any damage since the
+/// buffer of the given `age` was rendered are skipped entirely. The returned damage is the
+/// minimal damage of the rendered buffer, suitable to be passed on to the backend on submission.
+/// `None` is returned if nothing changed, in which case nothing was rendered.
 #[allow(clippy::too_many_arguments)]
 pub fn render_output<
     'a,
@@ -695,3 +1136,247 @@ where
 
     damage_tracker.render_output(renderer, age, &render_elements, clear_color)
 }
+
+/// Damage a output without rendering it
+///
+/// Returns the same damage [`render_output`] would, without actually drawing anything.
+/// This can be used to find out whether the output needs to be redrawn or to
+/// feed the damage into a custom rendering path.
+///
+/// Note that the damage tracker treats the returned damage as rendered.
+pub fn damage_output<
+    'a,
+    #[cfg(feature = "wayland_frontend")] R: Renderer + ImportAll,
+    #[cfg(not(feature = "wayland_frontend"))] R: Renderer,
+    C: RenderElement<R>,
+    E: SpaceElement + PartialEq + AsRenderElements<R> + 'a,
+    S: IntoIterator<Item = &'a Space<E>>,
+>(
+    output: &Output,
+    renderer: &mut R,
+    age: usize,
+    spaces: S,
+    custom_elements: &'a [C],
+    damage_tracker: &mut OutputDamageTracker,
+) -> Result<(Option<Vec<Rectangle<i32, Physical>>>, RenderElementStates), OutputDamageTrackerError<R>>
+where
+    <R as Renderer>::TextureId: Texture + 'static,
+    <E as AsRenderElements<R>>::RenderElement: 'a,
+    SpaceRenderElements<R, <E as AsRenderElements<R>>::RenderElement>:
+        From<Wrap<<E as AsRenderElements<R>>::RenderElement>>,
+{
+    if let OutputDamageTrackerMode::Auto(renderer_output) = damage_tracker.mode() {
+        assert!(renderer_output == output);
+    }
+
+    let space_render_elements = space_render_elements(renderer, spaces, output, 1.0)?;
+
+    let mut render_elements: Vec<OutputRenderElements<'a, R, <E as AsRenderElements<R>>::RenderElement, C>> =
+        Vec::with_capacity(custom_elements.len() + space_render_elements.len());
+
+    render_elements.extend(custom_elements.iter().map(OutputRenderElements::Custom));
+    render_elements.extend(space_render_elements.into_iter().map(OutputRenderElements::Space));
+
+    damage_tracker
+        .damage_output(age, &render_elements)
+        .map_err(OutputDamageTrackerError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct TestElement(u32);
+
+    impl IsAlive for TestElement {
+        fn alive(&self) -> bool {
+            true
+        }
+    }
+
+    impl SpaceElement for TestElement {
+        fn bbox(&self) -> Rectangle<i32, Logical> {
+            Rectangle::from_loc_and_size((0, 0), (10, 10))
+        }
+        fn is_in_input_region(&self, _point: &Point<f64, Logical>) -> bool {
+            true
+        }
+        fn set_activate(&self, _activated: bool) {}
+        fn output_enter(&self, _output: &Output, _overlap: Rectangle<i32, Logical>) {}
+        fn output_leave(&self, _output: &Output) {}
+    }
+
+    fn order(space: &Space<TestElement>) -> Vec<u32> {
+        space.elements().map(|e| e.0).collect()
+    }
+
+    #[test]
+    fn stacking_bands() {
+        let mut space = Space::default();
+        space.map_element_in_band(TestElement(0), (0, 0), StackingBand::ABOVE, false);
+        space.map_element(TestElement(1), (0, 0), false);
+        space.map_element_in_band(TestElement(2), (0, 0), StackingBand::BELOW, false);
+        space.map_element(TestElement(3), (0, 0), false);
+        assert_eq!(order(&space), vec![2, 1, 3, 0]);
+
+        // raising keeps elements inside of their band
+        space.raise_element(&TestElement(1), false);
+        space.raise_element(&TestElement(2), false);
+        assert_eq!(order(&space), vec![2, 3, 1, 0]);
+
+        // remapping keeps the band
+        space.map_element(TestElement(0), (10, 10), false);
+        assert_eq!(space.element_band(&TestElement(0)), Some(StackingBand::ABOVE));
+
+        space.set_element_band(&TestElement(3), StackingBand::OVERLAY);
+        space.set_element_band(&TestElement(2), StackingBand::NORMAL);
+        assert_eq!(order(&space), vec![1, 2, 0, 3]);
+        assert_eq!(
+            space
+                .elements_in_band(StackingBand::NORMAL)
+                .map(|e| e.0)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn hidden_elements() {
+        let mut space = Space::default();
+        space.map_element(TestElement(0), (0, 0), false);
+        space.map_element_in_band(TestElement(1), (20, 20), StackingBand::ABOVE, false);
+        space.map_element(TestElement(2), (0, 0), false);
+
+        space.hide_element(&TestElement(1));
+        assert!(space.is_element_hidden(&TestElement(1)));
+        assert_eq!(order(&space), vec![0, 2]);
+        assert!(space.element_under((25.0, 25.0)).is_none());
+
+        space.show_element(&TestElement(1), false);
+        assert!(!space.is_element_hidden(&TestElement(1)));
+        assert_eq!(order(&space), vec![0, 2, 1]);
+        assert_eq!(space.element_location(&TestElement(1)), Some((20, 20).into()));
+
+        space.hide_element(&TestElement(0));
+        space.unmap_elem(&TestElement(0));
+        assert_eq!(space.hidden_elements().count(), 0);
+    }
+
+    #[test]
+    fn largest_overlap_wins() {
+        let keys = ["a", "b", "c"];
+        let mut overlaps = HashMap::new();
+        assert_eq!(largest_overlap(&keys, &overlaps), None);
+
+        overlaps.insert("a", Rectangle::from_loc_and_size((0, 0), (40, 100)));
+        overlaps.insert("b", Rectangle::from_loc_and_size((40, 0), (60, 100)));
+        assert_eq!(largest_overlap(&keys, &overlaps), Some(&"b"));
+
+        // ties prefer the first key
+        overlaps.insert("c", Rectangle::from_loc_and_size((0, 0), (60, 100)));
+        overlaps.insert("a", Rectangle::from_loc_and_size((0, 0), (60, 100)));
+        assert_eq!(largest_overlap(&keys, &overlaps), Some(&"a"));
+    }
+
+    #[test]
+    fn mirrored_outputs() {
+        use crate::output::{Mode, PhysicalProperties, Subpixel};
+
+        let output = |name: &str, size: (i32, i32)| {
+            let output = Output::new(
+                name.into(),
+                PhysicalProperties {
+                    size: (0, 0).into(),
+                    subpixel: Subpixel::Unknown,
+                    make: "Smithay".into(),
+                    model: "Test".into(),
+                },
+            );
+            let mode = Mode {
+                size: size.into(),
+                refresh: 60_000,
+            };
+            output.change_current_state(Some(mode), None, None, None);
+            output
+        };
+        let source = output("source", (100, 100));
+        let mirror = output("mirror", (200, 150));
+        mirror.set_mirror_source(Some(&source));
+
+        let mut space = Space::default();
+        space.map_output(&mirror, (500, 500));
+        // the source is not mapped, so the mirror acts like any other output
+        assert_eq!(space.mirrored_output(&mirror), None);
+        assert_eq!(
+            space.output_geometry(&mirror),
+            Some(Rectangle::from_loc_and_size((500, 500), (200, 150)))
+        );
+
+        space.map_output(&source, (0, 0));
+        assert_eq!(space.mirrored_output(&mirror), Some(source.clone()));
+        assert_eq!(space.output_geometry(&mirror), space.output_geometry(&source));
+        assert_eq!(
+            space.output_under((50.0, 50.0)).collect::<Vec<_>>(),
+            vec![&source]
+        );
+
+        space.map_element(TestElement(0), (0, 0), false);
+        space.refresh();
+        let outputs = space.outputs_for_element(&TestElement(0));
+        assert!(outputs.contains(&source) && outputs.contains(&mirror));
+        assert_eq!(
+            space.element_primary_output(&TestElement(0)),
+            Some(source.clone())
+        );
+
+        mirror.set_mirror_source(Some(&mirror));
+        assert_eq!(mirror.mirror_source(), None);
+    }
+
+    #[test]
+    fn output_update_filter() {
+        use crate::output::{Mode, PhysicalProperties, Scale as OutputScale, Subpixel};
+
+        let output = Output::new(
+            "output".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Test".into(),
+            },
+        );
+        let mode = Mode {
+            size: (100, 100).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, Some(OutputScale::Integer(1)), None);
+
+        let mut space = Space::default();
+        space.map_output(&output, (0, 0));
+        space.map_element(TestElement(0), (0, 0), false);
+        space.refresh();
+        assert_eq!(space.elements[0].primary_state, Some((1.0, Transform::Normal)));
+
+        space.set_output_update_filter(|_, _| OutputUpdate::Defer);
+        output.change_current_state(None, Some(Transform::_90), Some(OutputScale::Integer(2)), None);
+        space.refresh();
+        assert_eq!(space.elements[0].primary_state, Some((1.0, Transform::Normal)));
+
+        space.clear_output_update_filter();
+        space.refresh();
+        assert_eq!(space.elements[0].primary_state, Some((2.0, Transform::_90)));
+    }
+
+    #[test]
+    fn overlap_threshold() {
+        let bbox = Size::from((100, 100));
+        let overlap = Size::from((10, 100));
+        assert!(OutputOverlapThreshold::Any.is_reached(overlap, bbox));
+        assert!(OutputOverlapThreshold::Area(1000).is_reached(overlap, bbox));
+        assert!(!OutputOverlapThreshold::Area(1001).is_reached(overlap, bbox));
+        assert!(OutputOverlapThreshold::Fraction(0.1).is_reached(overlap, bbox));
+        assert!(!OutputOverlapThreshold::Fraction(0.5).is_reached(overlap, bbox));
+    }
+}
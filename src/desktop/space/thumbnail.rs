@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            damage::{Error as OutputDamageTrackerError, OutputDamageTracker},
+            element::{
+                texture::{TextureRenderBuffer, TextureRenderElement},
+                AsRenderElements,
+            },
+            Bind, Offscreen, Renderer, Texture, Unbind,
+        },
+    },
+    utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
+};
+
+use super::SpaceElement;
+
+/// Scaled-down live preview of a [`SpaceElement`]
+///
+/// The element is rendered into an offscreen texture of a fixed size, keeping its aspect ratio.
+/// Updates only redraw the damaged parts of the element and can be throttled to a minimum
+/// interval. The preview is exposed as a [`TextureRenderElement`] through [`AsRenderElements`],
+/// forwarding the damage of the preview, so window switchers or overviews showing many windows
+/// at once do not need to redraw the full resolution contents of every window.
+///
+/// The size of the thumbnail is given in physical pixels of the output it is displayed on.
+#[derive(Debug)]
+pub struct Thumbnail<T> {
+    size: Size<i32, Physical>,
+    throttle: Option<Duration>,
+    last_update: Option<Duration>,
+    buffer: Option<TextureRenderBuffer<T>>,
+    // the damage tracker is only valid for the scale the element was rendered with
+    damage_tracker: Option<(f64, OutputDamageTracker)>,
+}
+
+impl<T> Thumbnail<T> {
+    /// Create a new thumbnail of the given size
+    ///
+    /// If a `throttle` duration is given, the thumbnail is updated at most once per duration.
+    pub fn new(size: impl Into<Size<i32, Physical>>, throttle: Option<Duration>) -> Self {
+        Thumbnail {
+            size: size.into(),
+            throttle,
+            last_update: None,
+            buffer: None,
+            damage_tracker: None,
+        }
+    }
+
+    /// Returns the size of this thumbnail
+    pub fn size(&self) -> Size<i32, Physical> {
+        self.size
+    }
+
+    /// Set the size of this thumbnail
+    ///
+    /// The thumbnail is redrawn completely on the next update.
+    pub fn set_size(&mut self, size: impl Into<Size<i32, Physical>>) {
+        let size = size.into();
+        if self.size != size {
+            self.size = size;
+            self.buffer = None;
+            self.damage_tracker = None;
+            self.last_update = None;
+        }
+    }
+}
+
+impl<T: Texture + Clone> Thumbnail<T> {
+    /// Update the thumbnail from the current contents of an element
+    ///
+    /// `time` is used for throttling and is expected to be monotonic, e.g. the time
+    /// passed to [`Window::send_frame`](crate::desktop::Window::send_frame).
+    ///
+    /// Returns `true` if the thumbnail was updated, `false` if the update was throttled,
+    /// or the element or the thumbnail have no size.
+    pub fn update<R, E>(
+        &mut self,
+        renderer: &mut R,
+        element: &E,
+        time: impl Into<Duration>,
+    ) -> Result<bool, OutputDamageTrackerError<R>>
+    where
+        R: Renderer<TextureId = T> + Offscreen<T>,
+        E: SpaceElement + AsRenderElements<R>,
+    {
+        let time = time.into();
+        if let (Some(throttle), Some(last_update)) = (self.throttle, self.last_update) {
+            if time.saturating_sub(last_update) < throttle {
+                return Ok(false);
+            }
+        }
+
+        let bbox = element.bbox();
+        if bbox.is_empty() || self.size.w <= 0 || self.size.h <= 0 {
+            return Ok(false);
+        }
+        self.last_update = Some(time);
+
+        let scale = f64::min(
+            self.size.w as f64 / bbox.size.w as f64,
+            self.size.h as f64 / bbox.size.h as f64,
+        );
+        // center the element inside of the thumbnail
+        let scaled_size = bbox.size.to_f64().to_physical(scale).to_i32_round::<i32>();
+        let offset = Point::<i32, Physical>::from((
+            (self.size.w - scaled_size.w) / 2,
+            (self.size.h - scaled_size.h) / 2,
+        ));
+        let location = offset - bbox.loc.to_f64().to_physical(scale).to_i32_round();
+
+        // the texture keeps its contents between updates
+        let age = if self.buffer.is_some() { 1 } else { 0 };
+        if self.buffer.is_none() {
+            let texture = renderer
+                .create_buffer(Fourcc::Abgr8888, (self.size.w, self.size.h).into())
+                .map_err(OutputDamageTrackerError::Rendering)?;
+            self.buffer = Some(TextureRenderBuffer::from_texture(
+                renderer,
+                texture,
+                1,
+                Transform::Normal,
+                None,
+            ));
+        }
+        if !matches!(self.damage_tracker, Some((tracker_scale, _)) if tracker_scale == scale) {
+            self.damage_tracker = Some((
+                scale,
+                OutputDamageTracker::new(self.size, scale, Transform::Normal),
+            ));
+        }
+        let buffer = self.buffer.as_mut().unwrap();
+        let (_, damage_tracker) = self.damage_tracker.as_mut().unwrap();
+
+        let elements =
+            element.render_elements::<E::RenderElement>(renderer, location, Scale::from(scale), 1.0);
+        buffer.render().draw(|texture| {
+            renderer
+                .bind(texture.clone())
+                .map_err(OutputDamageTrackerError::Rendering)?;
+            let res = damage_tracker.render_output(renderer, age, &elements, [0.0, 0.0, 0.0, 0.0]);
+            renderer.unbind().map_err(OutputDamageTrackerError::Rendering)?;
+
+            let (damage, _) = res?;
+            Ok(damage
+                .unwrap_or_default()
+                .into_iter()
+                .map(|rect| {
+                    Rectangle::<i32, Buffer>::from_loc_and_size(
+                        (rect.loc.x, rect.loc.y),
+                        (rect.size.w, rect.size.h),
+                    )
+                })
+                .collect())
+        })?;
+
+        Ok(true)
+    }
+}
+
+impl<R, T> AsRenderElements<R> for Thumbnail<T>
+where
+    R: Renderer<TextureId = T>,
+    T: Texture + Clone + 'static,
+{
+    type RenderElement = TextureRenderElement<T>;
+
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        _renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        let Some(buffer) = self.buffer.as_ref() else {
+            return Vec::new();
+        };
+
+        // display the thumbnail pixel-exact regardless of the output scale
+        let size = self.size.to_f64().to_logical(scale).to_i32_round();
+        vec![TextureRenderElement::from_texture_render_buffer(
+            location.to_f64(),
+            buffer,
+            Some(alpha),
+            None,
+            Some(size),
+        )
+        .into()]
+    }
+}
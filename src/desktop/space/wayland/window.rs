@@ -6,9 +6,15 @@ use crate::{
         },
         ImportAll, Renderer,
     },
-    desktop::{space::SpaceElement, PopupManager, Window, WindowSurfaceType},
+    desktop::{
+        space::{SpaceElement, Visibility},
+        utils::{
+            opaque_region_from_surface_tree, set_surface_tree_visibility, update_preferred_buffer_state,
+        },
+        PopupManager, Space, Window, WindowSurfaceType,
+    },
     output::Output,
-    utils::{Logical, Physical, Point, Rectangle, Scale},
+    utils::{Logical, Physical, Point, Rectangle, Region, Scale},
     wayland::compositor::{with_surface_tree_downward, TraversalAction},
 };
 
@@ -76,6 +82,25 @@ impl SpaceElement for Window {
         }
     }
 
+    fn primary_output_changed(&self, output: Option<&Output>) {
+        let Some(output) = output else { return };
+        self.with_surfaces(|_, states| {
+            update_preferred_buffer_state(states, output);
+        });
+    }
+
+    fn opaque_region(&self) -> Region<i32, Logical> {
+        opaque_region_from_surface_tree(self.toplevel().wl_surface(), (0, 0))
+    }
+
+    fn visibility_changed(&self, visibility: Visibility) {
+        let surface = self.toplevel().wl_surface();
+        set_surface_tree_visibility(surface, visibility);
+        for (popup, _) in PopupManager::popups_for_surface(surface) {
+            set_surface_tree_visibility(popup.wl_surface(), visibility);
+        }
+    }
+
     fn refresh(&self) {
         self.user_data().insert_if_missing(WindowOutputUserData::default);
         let state = self.user_data().get::<WindowOutputUserData>().unwrap().borrow();
@@ -134,3 +159,32 @@ where
         render_elements
     }
 }
+
+impl Space<Window> {
+    /// Iterate windows with the given app id
+    ///
+    /// Mapped windows are returned in z-order back to front, followed by hidden windows.
+    pub fn windows_with_app_id<'a>(&'a self, app_id: &'a str) -> impl Iterator<Item = &'a Window> {
+        self.windows()
+            .filter(move |w| w.app_id().as_deref() == Some(app_id))
+    }
+
+    /// Iterate windows with the given title
+    ///
+    /// Mapped windows are returned in z-order back to front, followed by hidden windows.
+    pub fn windows_with_title<'a>(&'a self, title: &'a str) -> impl Iterator<Item = &'a Window> {
+        self.windows()
+            .filter(move |w| w.title().as_deref() == Some(title))
+    }
+
+    /// Iterate windows with the given tag
+    ///
+    /// Mapped windows are returned in z-order back to front, followed by hidden windows.
+    pub fn windows_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Window> {
+        self.windows().filter(move |w| w.has_tag(tag))
+    }
+
+    fn windows(&self) -> impl Iterator<Item = &Window> {
+        self.elements().chain(self.hidden_elements())
+    }
+}
@@ -6,8 +6,15 @@ use crate::{
         element::surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
         ImportAll, Renderer,
     },
-    desktop::{space::SpaceElement, utils::under_from_surface_tree, WindowSurfaceType},
-    utils::{Logical, Physical, Point, Rectangle, Scale},
+    desktop::{
+        space::{SpaceElement, Visibility},
+        utils::{
+            opaque_region_from_surface_tree, set_surface_tree_visibility, under_from_surface_tree,
+            update_preferred_buffer_state, with_surfaces_surface_tree,
+        },
+        WindowSurfaceType,
+    },
+    utils::{Logical, Physical, Point, Rectangle, Region, Scale},
     wayland::seat::WaylandFocus,
     xwayland::X11Surface,
 };
@@ -70,6 +77,26 @@ impl SpaceElement for X11Surface {
         );
     }
 
+    fn primary_output_changed(&self, output: Option<&Output>) {
+        let Some(output) = output else { return };
+        let Some(surface) = X11Surface::wl_surface(self) else { return };
+        with_surfaces_surface_tree(&surface, |_, states| {
+            update_preferred_buffer_state(states, output);
+        });
+    }
+
+    fn opaque_region(&self) -> Region<i32, Logical> {
+        X11Surface::wl_surface(self)
+            .map(|surface| opaque_region_from_surface_tree(&surface, (0, 0)))
+            .unwrap_or_default()
+    }
+
+    fn visibility_changed(&self, visibility: Visibility) {
+        if let Some(surface) = X11Surface::wl_surface(self) {
+            set_surface_tree_visibility(&surface, visibility);
+        }
+    }
+
     fn refresh(&self) {
         self.user_data().insert_if_missing(WindowOutputUserData::default);
         let wo_state = self.user_data().get::<WindowOutputUserData>().unwrap().borrow();
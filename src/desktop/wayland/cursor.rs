@@ -0,0 +1,470 @@
+//! Cursor theme and cursor image management
+//!
+//! This module provides helpers to load XCursor themes ([`XCursorTheme`]) and to keep track of the
+//! image of a pointer ([`CursorManager`]). The cursor image is either a named cursor of the theme
+//! (e.g. `"default"` or `"text"`), possibly animated and available in multiple sizes, or a surface
+//! provided by a client, as reported through [`SeatHandler::cursor_image`](crate::input::SeatHandler::cursor_image).
+//!
+//! The current cursor can be turned into render elements through [`CursorManager::render_elements`],
+//! or, if it is a themed cursor, retrieved as a raw image for use with hardware cursor planes through
+//! [`CursorManager::image`].
+//!
+//! ```no_run
+//! use smithay::desktop::cursor::{CursorManager, XCursorTheme};
+//! # use smithay::input::pointer::CursorImageStatus;
+//! # use std::time::Duration;
+//!
+//! // load the theme configured through `XCURSOR_THEME` and `XCURSOR_SIZE`
+//! let mut cursor = CursorManager::new(XCursorTheme::load_from_env());
+//!
+//! // whenever a client requests a cursor image
+//! # let status: CursorImageStatus = unimplemented!();
+//! cursor.set_status(status);
+//!
+//! // e.g. to put it on a hardware cursor plane of an output with a scale of 2
+//! let image = cursor.image(2, Duration::ZERO);
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tracing::warn;
+use xcursor::parser::{parse_xcursor, Image};
+
+use crate::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            },
+            ImportAll, ImportMem, Renderer,
+        },
+    },
+    input::pointer::{CursorImageAttributes, CursorImageStatus},
+    utils::{IsAlive, Logical, Physical, Point, Scale, Transform},
+    wayland::compositor::with_states,
+};
+
+/// Errors that can occur when loading a cursor from a theme
+#[derive(Debug, thiserror::Error)]
+pub enum XCursorError {
+    /// The theme does not provide a cursor of the given name
+    #[error("The theme has no cursor named {0}")]
+    NotFound(String),
+    /// The cursor file could not be read
+    #[error("Error opening xcursor file: {0}")]
+    Io(#[from] io::Error),
+    /// The cursor file is not a valid XCursor file
+    #[error("Failed to parse xcursor file")]
+    Parse,
+}
+
+/// A named cursor of a theme
+///
+/// A cursor consists of images in one or multiple sizes, every size may consist of
+/// multiple frames for animated cursors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XCursor {
+    images: Arc<Vec<Image>>,
+}
+
+impl XCursor {
+    /// Create a cursor from a list of images
+    ///
+    /// This can be used to provide a fallback cursor not backed by a theme.
+    /// Returns `None` if `images` is empty.
+    pub fn from_images(images: Vec<Image>) -> Option<Self> {
+        if images.is_empty() {
+            return None;
+        }
+        Some(XCursor {
+            images: Arc::new(images),
+        })
+    }
+
+    /// Access all images of this cursor
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Whether this cursor consists of multiple frames at the given nominal size
+    pub fn is_animated(&self, size: u32) -> bool {
+        self.nearest_images(size).nth(1).is_some()
+    }
+
+    /// Get the frame of this cursor to display at the given nominal size and animation time
+    ///
+    /// The image closest to the requested size is chosen.
+    pub fn frame(&self, size: u32, time: Duration) -> &Image {
+        &self.images[self.frame_index(size, time)]
+    }
+
+    fn nearest_images(&self, size: u32) -> impl Iterator<Item = (usize, &Image)> {
+        // Follow the nominal size of the cursor to choose the nearest
+        let nearest_image = self
+            .images
+            .iter()
+            .min_by_key(|image| (size as i32 - image.size as i32).abs())
+            .unwrap();
+
+        self.images.iter().enumerate().filter(move |(_, image)| {
+            image.width == nearest_image.width && image.height == nearest_image.height
+        })
+    }
+
+    fn frame_index(&self, size: u32, time: Duration) -> usize {
+        let total = self
+            .nearest_images(size)
+            .fold(0, |acc, (_, image)| acc + image.delay);
+        let mut millis = if total == 0 {
+            0
+        } else {
+            (time.as_millis() % total as u128) as u32
+        };
+
+        for (idx, image) in self.nearest_images(size) {
+            if millis < image.delay {
+                return idx;
+            }
+            millis -= image.delay;
+        }
+
+        // only reached for cursors without any delay
+        self.nearest_images(size).next().unwrap().0
+    }
+}
+
+/// An XCursor theme
+///
+/// Cursors are loaded lazily and cached once loaded.
+pub struct XCursorTheme {
+    name: String,
+    size: u32,
+    theme: xcursor::CursorTheme,
+    cursors: Mutex<HashMap<String, Option<XCursor>>>,
+}
+
+impl fmt::Debug for XCursorTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XCursorTheme")
+            .field("name", &self.name)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl XCursorTheme {
+    /// Load a theme by name, using the given nominal cursor size in logical pixels
+    pub fn load(name: impl Into<String>, size: u32) -> Self {
+        let name = name.into();
+        let theme = xcursor::CursorTheme::load(&name);
+        XCursorTheme {
+            name,
+            size,
+            theme,
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Load the theme configured through the `XCURSOR_THEME` and `XCURSOR_SIZE` environment variables
+    ///
+    /// Defaults to the `"default"` theme with a size of 24.
+    pub fn load_from_env() -> Self {
+        let name = std::env::var("XCURSOR_THEME")
+            .ok()
+            .unwrap_or_else(|| "default".into());
+        let size = std::env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(24);
+        Self::load(name, size)
+    }
+
+    /// Name of this theme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Nominal cursor size of this theme in logical pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Load a cursor of this theme by name
+    ///
+    /// Cursors failing to load are remembered and reported as [`XCursorError::NotFound`]
+    /// on subsequent calls.
+    pub fn get_cursor(&self, icon: &str) -> Result<XCursor, XCursorError> {
+        let mut cursors = self.cursors.lock().unwrap();
+        if let Some(cursor) = cursors.get(icon) {
+            return cursor
+                .clone()
+                .ok_or_else(|| XCursorError::NotFound(icon.to_string()));
+        }
+
+        let cursor = self.load_cursor(icon);
+        if let Err(ref err) = cursor {
+            warn!(theme = %self.name, icon, "Unable to load xcursor: {}", err);
+        }
+        cursors.insert(icon.to_string(), cursor.as_ref().ok().cloned());
+        cursor
+    }
+
+    fn load_cursor(&self, icon: &str) -> Result<XCursor, XCursorError> {
+        let path = self
+            .theme
+            .load_icon(icon)
+            .ok_or_else(|| XCursorError::NotFound(icon.to_string()))?;
+        let data = std::fs::read(path)?;
+        parse_xcursor(&data)
+            .and_then(XCursor::from_images)
+            .ok_or(XCursorError::Parse)
+    }
+}
+
+crate::backend::renderer::element::render_elements! {
+    /// Render elements of a [`CursorManager`]
+    pub CursorRenderElement<R> where
+        R: ImportAll + ImportMem;
+    /// A cursor surface provided by a client
+    Surface=WaylandSurfaceRenderElement<R>,
+    /// An image of a themed cursor
+    Image=MemoryRenderBufferRenderElement<R>,
+}
+
+impl<R: Renderer + ImportAll + ImportMem> fmt::Debug for CursorRenderElement<R>
+where
+    <R as Renderer>::TextureId: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Surface(arg0) => f.debug_tuple("Surface").field(arg0).finish(),
+            Self::Image(arg0) => f.debug_tuple("Image").field(arg0).finish(),
+            Self::_GenericCatcher(_) => unreachable!(),
+        }
+    }
+}
+
+/// Helper keeping track of the cursor image of a pointer
+///
+/// The manager displays the cursor requested by clients through [`CursorImageStatus`] and falls back
+/// to a named cursor of its [`XCursorTheme`] for [`CursorImageStatus::Default`].
+pub struct CursorManager {
+    theme: XCursorTheme,
+    status: CursorImageStatus,
+    icon: String,
+    fallback: Option<XCursor>,
+    buffers: HashMap<(String, usize), MemoryRenderBuffer>,
+}
+
+impl fmt::Debug for CursorManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CursorManager")
+            .field("theme", &self.theme)
+            .field("status", &self.status)
+            .field("icon", &self.icon)
+            .field("fallback", &self.fallback.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CursorManager {
+    /// Create a new cursor manager displaying the `"default"` cursor of the given theme
+    pub fn new(theme: XCursorTheme) -> Self {
+        CursorManager {
+            theme,
+            status: CursorImageStatus::Default,
+            icon: String::from("default"),
+            fallback: None,
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Access the theme of this manager
+    pub fn theme(&self) -> &XCursorTheme {
+        &self.theme
+    }
+
+    /// Replace the theme of this manager
+    pub fn set_theme(&mut self, theme: XCursorTheme) {
+        self.theme = theme;
+        self.buffers.clear();
+    }
+
+    /// Set a cursor to use if the theme does not provide the requested cursor
+    pub fn set_fallback(&mut self, cursor: Option<XCursor>) {
+        self.fallback = cursor;
+        self.buffers.clear();
+    }
+
+    /// The current cursor image status
+    pub fn status(&self) -> &CursorImageStatus {
+        &self.status
+    }
+
+    /// Set the cursor image status, e.g. as requested by a client
+    pub fn set_status(&mut self, status: CursorImageStatus) {
+        self.status = status;
+    }
+
+    /// Name of the themed cursor displayed for [`CursorImageStatus::Default`]
+    pub fn icon(&self) -> &str {
+        &self.icon
+    }
+
+    /// Set the name of the themed cursor displayed for [`CursorImageStatus::Default`]
+    ///
+    /// If the theme does not provide a cursor of this name, its `"default"` cursor is used instead.
+    pub fn set_icon(&mut self, icon: impl Into<String>) {
+        self.icon = icon.into();
+    }
+
+    /// The themed cursor currently displayed, if any
+    ///
+    /// Returns `None` if the cursor is hidden or a client surface is displayed.
+    pub fn current_cursor(&mut self) -> Option<XCursor> {
+        self.check_surface_alive();
+        match self.status {
+            CursorImageStatus::Default => self.themed_cursor().map(|(_, cursor)| cursor),
+            _ => None,
+        }
+    }
+
+    /// The image of the themed cursor currently displayed, if any
+    ///
+    /// The image is chosen for the given integer scale and animation time, its size matches
+    /// the nominal size of the theme multiplied by the scale as closely as possible.
+    /// The image is not available if the cursor is hidden or a client surface is displayed,
+    /// in which case `None` is returned.
+    pub fn image(&mut self, scale: u32, time: Duration) -> Option<Image> {
+        let size = self.theme.size * scale.max(1);
+        self.current_cursor()
+            .map(|cursor| cursor.frame(size, time).clone())
+    }
+
+    /// The hotspot of the current cursor in logical coordinates relative to its top-left corner
+    pub fn hotspot(&mut self, scale: u32, time: Duration) -> Point<i32, Logical> {
+        self.check_surface_alive();
+        match self.status {
+            CursorImageStatus::Hidden => Point::default(),
+            CursorImageStatus::Default => {
+                let size = self.theme.size * scale.max(1);
+                self.current_cursor()
+                    .map(|cursor| {
+                        let image = cursor.frame(size, time);
+                        let buffer_scale = self.buffer_scale(image);
+                        Point::from((image.xhot as i32, image.yhot as i32)).downscale(buffer_scale)
+                    })
+                    .unwrap_or_default()
+            }
+            CursorImageStatus::Surface(ref surface) => with_states(surface, |states| {
+                states
+                    .data_map
+                    .get::<Mutex<CursorImageAttributes>>()
+                    .map(|attrs| attrs.lock().unwrap().hotspot)
+                    .unwrap_or_default()
+            }),
+        }
+    }
+
+    /// Render the current cursor
+    ///
+    /// `location` is the location of the pointer, the cursor is placed relative to it
+    /// according to its hotspot. `time` is used to animate themed cursors.
+    pub fn render_elements<R, C>(
+        &mut self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: impl Into<Scale<f64>>,
+        alpha: f32,
+        time: Duration,
+    ) -> Vec<C>
+    where
+        R: Renderer + ImportAll + ImportMem,
+        <R as Renderer>::TextureId: 'static,
+        C: From<CursorRenderElement<R>>,
+    {
+        let scale = scale.into();
+        let integer_scale = scale.x.max(scale.y).ceil().max(1.0) as u32;
+        let hotspot = self.hotspot(integer_scale, time).to_physical_precise_round(scale);
+
+        match self.status {
+            CursorImageStatus::Hidden => Vec::new(),
+            CursorImageStatus::Surface(ref surface) => {
+                render_elements_from_surface_tree(renderer, surface, location - hotspot, scale, alpha)
+                    .into_iter()
+                    .map(CursorRenderElement::Surface)
+                    .map(C::from)
+                    .collect()
+            }
+            CursorImageStatus::Default => {
+                let (icon, cursor) = match self.themed_cursor() {
+                    Some(cursor) => cursor,
+                    None => return Vec::new(),
+                };
+                let size = self.theme.size * integer_scale;
+                let index = cursor.frame_index(size, time);
+                let image = &cursor.images()[index];
+                let buffer_scale = self.buffer_scale(image);
+                let buffer = self.buffers.entry((icon, index)).or_insert_with(|| {
+                    MemoryRenderBuffer::from_memory(
+                        &image.pixels_rgba,
+                        Fourcc::Abgr8888,
+                        (image.width as i32, image.height as i32),
+                        buffer_scale,
+                        Transform::Normal,
+                        None,
+                    )
+                });
+
+                match MemoryRenderBufferRenderElement::from_buffer(
+                    renderer,
+                    (location - hotspot).to_f64(),
+                    buffer,
+                    Some(alpha),
+                    None,
+                    None,
+                ) {
+                    Ok(element) => vec![C::from(CursorRenderElement::Image(element))],
+                    Err(err) => {
+                        warn!("Failed to import cursor image: {}", err);
+                        Vec::new()
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_surface_alive(&mut self) {
+        if let CursorImageStatus::Surface(ref surface) = self.status {
+            if !surface.alive() {
+                self.status = CursorImageStatus::Default;
+            }
+        }
+    }
+
+    // Returns the displayed themed cursor and the name it is cached under
+    fn themed_cursor(&self) -> Option<(String, XCursor)> {
+        if let Ok(cursor) = self.theme.get_cursor(&self.icon) {
+            return Some((self.icon.clone(), cursor));
+        }
+        if self.icon != "default" {
+            if let Ok(cursor) = self.theme.get_cursor("default") {
+                return Some((String::from("default"), cursor));
+            }
+        }
+        self.fallback.clone().map(|cursor| (String::new(), cursor))
+    }
+
+    fn buffer_scale(&self, image: &Image) -> i32 {
+        (image.size as f64 / self.theme.size.max(1) as f64)
+            .round()
+            .max(1.0) as i32
+    }
+}
@@ -0,0 +1,425 @@
+//! Server-side decorations
+//!
+//! This module provides [`WindowDecorations`], drawing a simple frame consisting of a titlebar with
+//! buttons and borders around toplevels, which negotiated server-side decorations through the
+//! xdg-decoration protocol (see [`has_server_side_decorations`]).
+//!
+//! The decorations are placed around the window geometry. [`WindowDecorations::window_offset`] and
+//! [`WindowDecorations::frame_geometry`] convert between the location of the window and the frame,
+//! [`WindowDecorations::window_size_for_frame`] returns the size a window may occupy inside of a frame,
+//! e.g. to configure maximized windows. Pointer input on the frame is classified by
+//! [`WindowDecorations::hit_test`] into moving, resizing and button presses.
+//!
+//! ```no_run
+//! use smithay::desktop::decoration::{DecorationHit, DecorationStyle, WindowDecorations};
+//! # use smithay::desktop::Window;
+//! # let window: Window = unimplemented!();
+//!
+//! let mut decorations = WindowDecorations::new(DecorationStyle::default());
+//! // on every commit of the window
+//! decorations.update(window.geometry().size, true);
+//!
+//! // on a button press, relative to the origin of the frame
+//! match decorations.hit_test((5.0, 10.0).into()) {
+//!     Some(DecorationHit::Titlebar) => { /* start an interactive move */ }
+//!     Some(DecorationHit::Resize(edges)) => { /* start an interactive resize */ }
+//!     Some(DecorationHit::Button(button)) => { /* close, maximize or minimize */ }
+//!     None => { /* forward the event to the window */ }
+//! }
+//! ```
+
+use wayland_protocols::xdg::{
+    decoration::zv1::server::zxdg_toplevel_decoration_v1, shell::server::xdg_toplevel,
+};
+
+use crate::{
+    backend::renderer::{
+        element::{
+            solid::{SolidColorBuffer, SolidColorRenderElement},
+            AsRenderElements,
+        },
+        Renderer,
+    },
+    desktop::Window,
+    utils::{Logical, Physical, Point, Rectangle, Scale, Size},
+};
+
+/// Returns whenever the toplevel of a window uses server-side decorations
+pub fn has_server_side_decorations(window: &Window) -> bool {
+    window.toplevel().current_state().decoration_mode == Some(zxdg_toplevel_decoration_v1::Mode::ServerSide)
+}
+
+/// Buttons placed in the titlebar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecorationButton {
+    /// Close the window
+    Close,
+    /// Toggle the maximized state of the window
+    Maximize,
+    /// Minimize the window
+    Minimize,
+}
+
+/// Part of the decorations hit by the pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationHit {
+    /// The titlebar, usually starting an interactive move
+    Titlebar,
+    /// The border, usually starting an interactive resize along the given edges
+    Resize(xdg_toplevel::ResizeEdge),
+    /// A button of the titlebar
+    Button(DecorationButton),
+}
+
+/// Appearance of the decorations
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecorationStyle {
+    /// Height of the titlebar
+    pub titlebar_height: i32,
+    /// Width of the border around the titlebar and window
+    pub border_width: i32,
+    /// Size of the square titlebar buttons
+    pub button_size: i32,
+    /// Space between the buttons and the end of the titlebar
+    pub button_spacing: i32,
+    /// Buttons of the titlebar, placed from the right to the left
+    pub buttons: Vec<DecorationButton>,
+    /// Color of the titlebar and border of the active window
+    pub active_color: [f32; 4],
+    /// Color of the titlebar and border of inactive windows
+    pub inactive_color: [f32; 4],
+    /// Color of the close button
+    pub close_color: [f32; 4],
+    /// Color of the maximize button
+    pub maximize_color: [f32; 4],
+    /// Color of the minimize button
+    pub minimize_color: [f32; 4],
+}
+
+impl Default for DecorationStyle {
+    fn default() -> Self {
+        DecorationStyle {
+            titlebar_height: 24,
+            border_width: 2,
+            button_size: 16,
+            button_spacing: 4,
+            buttons: vec![
+                DecorationButton::Close,
+                DecorationButton::Maximize,
+                DecorationButton::Minimize,
+            ],
+            active_color: [0.25, 0.25, 0.3, 1.0],
+            inactive_color: [0.4, 0.4, 0.4, 1.0],
+            close_color: [0.8, 0.2, 0.2, 1.0],
+            maximize_color: [0.8, 0.7, 0.2, 1.0],
+            minimize_color: [0.2, 0.7, 0.3, 1.0],
+        }
+    }
+}
+
+impl DecorationStyle {
+    fn button_color(&self, button: DecorationButton) -> [f32; 4] {
+        match button {
+            DecorationButton::Close => self.close_color,
+            DecorationButton::Maximize => self.maximize_color,
+            DecorationButton::Minimize => self.minimize_color,
+        }
+    }
+}
+
+/// Server-side decorations of a single window
+///
+/// All geometry is relative to the origin of the frame, unless stated otherwise.
+/// The render elements are generated with the origin of the frame at the given location.
+#[derive(Debug)]
+pub struct WindowDecorations {
+    style: DecorationStyle,
+    window_size: Size<i32, Logical>,
+    active: bool,
+    titlebar: SolidColorBuffer,
+    // top, bottom, left, right
+    borders: [SolidColorBuffer; 4],
+    buttons: Vec<(DecorationButton, SolidColorBuffer)>,
+}
+
+impl WindowDecorations {
+    /// Create new decorations using the given style
+    pub fn new(style: DecorationStyle) -> Self {
+        let mut decorations = WindowDecorations {
+            style: DecorationStyle::default(),
+            window_size: Size::default(),
+            active: false,
+            titlebar: SolidColorBuffer::default(),
+            borders: Default::default(),
+            buttons: Vec::new(),
+        };
+        decorations.set_style(style);
+        decorations
+    }
+
+    /// Returns the style of the decorations
+    pub fn style(&self) -> &DecorationStyle {
+        &self.style
+    }
+
+    /// Change the style of the decorations
+    pub fn set_style(&mut self, style: DecorationStyle) {
+        self.buttons = style
+            .buttons
+            .iter()
+            .map(|button| (*button, SolidColorBuffer::default()))
+            .collect();
+        self.style = style;
+        self.update_buffers();
+    }
+
+    /// Update the decorations for the size of the window geometry and its activation state
+    ///
+    /// Does not cause any damage, if nothing changed.
+    pub fn update(&mut self, window_size: impl Into<Size<i32, Logical>>, active: bool) {
+        self.window_size = window_size.into();
+        self.active = active;
+        self.update_buffers();
+    }
+
+    /// Returns the size of the window geometry the decorations are drawn for
+    pub fn window_size(&self) -> Size<i32, Logical> {
+        self.window_size
+    }
+
+    /// Returns the size of the frame, including the window
+    pub fn frame_size(&self) -> Size<i32, Logical> {
+        let border = self.style.border_width;
+        (
+            self.window_size.w + 2 * border,
+            self.window_size.h + self.style.titlebar_height + 2 * border,
+        )
+            .into()
+    }
+
+    /// Returns the location of the window geometry inside of the frame
+    pub fn window_offset(&self) -> Point<i32, Logical> {
+        let border = self.style.border_width;
+        (border, border + self.style.titlebar_height).into()
+    }
+
+    /// Returns the geometry of the frame for a window geometry in any coordinate space
+    pub fn frame_geometry(&self, window_geometry: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size(window_geometry.loc - self.window_offset(), self.frame_size())
+    }
+
+    /// Returns the size of the window geometry fitting into a frame of the given size
+    pub fn window_size_for_frame(&self, frame_size: impl Into<Size<i32, Logical>>) -> Size<i32, Logical> {
+        let frame_size = frame_size.into();
+        let border = self.style.border_width;
+        (
+            i32::max(frame_size.w - 2 * border, 0),
+            i32::max(frame_size.h - self.style.titlebar_height - 2 * border, 0),
+        )
+            .into()
+    }
+
+    /// Returns the geometry of the titlebar
+    pub fn titlebar_geometry(&self) -> Rectangle<i32, Logical> {
+        let border = self.style.border_width;
+        Rectangle::from_loc_and_size((border, border), (self.window_size.w, self.style.titlebar_height))
+    }
+
+    /// Returns the geometry of a button, if it is part of the titlebar
+    pub fn button_geometry(&self, button: DecorationButton) -> Option<Rectangle<i32, Logical>> {
+        let idx = self.style.buttons.iter().position(|b| *b == button)?;
+        Some(self.button_geometry_at(idx))
+    }
+
+    fn button_geometry_at(&self, idx: usize) -> Rectangle<i32, Logical> {
+        let titlebar = self.titlebar_geometry();
+        let size = self.style.button_size;
+        let spacing = self.style.button_spacing;
+        let x = titlebar.loc.x + titlebar.size.w - (idx as i32 + 1) * (size + spacing);
+        let y = titlebar.loc.y + (titlebar.size.h - size) / 2;
+        Rectangle::from_loc_and_size((x, y), (size, size))
+    }
+
+    fn border_geometries(&self) -> [Rectangle<i32, Logical>; 4] {
+        let size = self.frame_size();
+        let border = self.style.border_width;
+        [
+            Rectangle::from_loc_and_size((0, 0), (size.w, border)),
+            Rectangle::from_loc_and_size((0, size.h - border), (size.w, border)),
+            Rectangle::from_loc_and_size((0, border), (border, size.h - 2 * border)),
+            Rectangle::from_loc_and_size((size.w - border, border), (border, size.h - 2 * border)),
+        ]
+    }
+
+    /// Classify a point of the frame
+    ///
+    /// Returns `None` for points on the window itself or outside of the frame.
+    pub fn hit_test(&self, point: Point<f64, Logical>) -> Option<DecorationHit> {
+        let size = self.frame_size().to_f64();
+        if point.x < 0.0 || point.y < 0.0 || point.x >= size.w || point.y >= size.h {
+            return None;
+        }
+
+        let border = self.style.border_width as f64;
+        // corners are easier to grab if they extend along the edges
+        let corner = f64::max(border, self.style.titlebar_height as f64);
+        let mut top = point.y < border;
+        let mut bottom = point.y >= size.h - border;
+        let mut left = point.x < border;
+        let mut right = point.x >= size.w - border;
+        if top || bottom {
+            left |= point.x < corner;
+            right |= point.x >= size.w - corner;
+        }
+        if left || right {
+            top |= point.y < corner;
+            bottom |= point.y >= size.h - corner;
+        }
+
+        let edge = match (top, bottom, left, right) {
+            (true, _, true, _) => Some(xdg_toplevel::ResizeEdge::TopLeft),
+            (true, _, _, true) => Some(xdg_toplevel::ResizeEdge::TopRight),
+            (_, true, true, _) => Some(xdg_toplevel::ResizeEdge::BottomLeft),
+            (_, true, _, true) => Some(xdg_toplevel::ResizeEdge::BottomRight),
+            (true, _, _, _) => Some(xdg_toplevel::ResizeEdge::Top),
+            (_, true, _, _) => Some(xdg_toplevel::ResizeEdge::Bottom),
+            (_, _, true, _) => Some(xdg_toplevel::ResizeEdge::Left),
+            (_, _, _, true) => Some(xdg_toplevel::ResizeEdge::Right),
+            _ => None,
+        };
+        if let Some(edge) = edge {
+            return Some(DecorationHit::Resize(edge));
+        }
+
+        if let Some(button) = self
+            .style
+            .buttons
+            .iter()
+            .enumerate()
+            .find(|(idx, _)| self.button_geometry_at(*idx).to_f64().contains(point))
+            .map(|(_, button)| *button)
+        {
+            return Some(DecorationHit::Button(button));
+        }
+
+        if self.titlebar_geometry().to_f64().contains(point) {
+            return Some(DecorationHit::Titlebar);
+        }
+
+        None
+    }
+
+    fn update_buffers(&mut self) {
+        let color = if self.active {
+            self.style.active_color
+        } else {
+            self.style.inactive_color
+        };
+
+        self.titlebar.update(self.titlebar_geometry().size, color);
+        let borders = self.border_geometries();
+        for (buffer, geometry) in self.borders.iter_mut().zip(borders) {
+            buffer.update(geometry.size, color);
+        }
+        let size = self.style.button_size;
+        for (button, buffer) in &mut self.buttons {
+            buffer.update((size, size), self.style.button_color(*button));
+        }
+    }
+}
+
+impl<R: Renderer> AsRenderElements<R> for WindowDecorations {
+    type RenderElement = SolidColorRenderElement;
+
+    fn render_elements<C: From<Self::RenderElement>>(
+        &self,
+        _renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        let element = |buffer: &SolidColorBuffer, geometry: Rectangle<i32, Logical>| -> C {
+            SolidColorRenderElement::from_buffer(
+                buffer,
+                location + geometry.loc.to_physical_precise_round(scale),
+                scale,
+                alpha,
+            )
+            .into()
+        };
+
+        // front to back
+        let mut elements = Vec::with_capacity(self.buttons.len() + 5);
+        elements.extend(
+            self.buttons
+                .iter()
+                .enumerate()
+                .map(|(idx, (_, buffer))| element(buffer, self.button_geometry_at(idx))),
+        );
+        elements.push(element(&self.titlebar, self.titlebar_geometry()));
+        elements.extend(
+            self.borders
+                .iter()
+                .zip(self.border_geometries())
+                .map(|(buffer, geometry)| element(buffer, geometry)),
+        );
+        elements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decorations() -> WindowDecorations {
+        let mut decorations = WindowDecorations::new(DecorationStyle::default());
+        decorations.update((200, 100), true);
+        decorations
+    }
+
+    #[test]
+    fn frame_geometry() {
+        let decorations = decorations();
+        assert_eq!(decorations.frame_size(), (204, 128).into());
+        assert_eq!(decorations.window_offset(), (2, 26).into());
+        assert_eq!(
+            decorations.frame_geometry(Rectangle::from_loc_and_size((50, 50), (200, 100))),
+            Rectangle::from_loc_and_size((48, 24), (204, 128))
+        );
+        assert_eq!(decorations.window_size_for_frame((204, 128)), (200, 100).into());
+    }
+
+    #[test]
+    fn hit_test() {
+        let decorations = decorations();
+        let hit = |x, y| decorations.hit_test((x, y).into());
+
+        assert_eq!(
+            hit(100.0, 0.0),
+            Some(DecorationHit::Resize(xdg_toplevel::ResizeEdge::Top))
+        );
+        assert_eq!(
+            hit(0.0, 60.0),
+            Some(DecorationHit::Resize(xdg_toplevel::ResizeEdge::Left))
+        );
+        assert_eq!(
+            hit(10.0, 0.0),
+            Some(DecorationHit::Resize(xdg_toplevel::ResizeEdge::TopLeft))
+        );
+        assert_eq!(
+            hit(203.0, 120.0),
+            Some(DecorationHit::Resize(xdg_toplevel::ResizeEdge::BottomRight))
+        );
+        assert_eq!(hit(100.0, 10.0), Some(DecorationHit::Titlebar));
+        assert_eq!(
+            hit(190.0, 10.0),
+            Some(DecorationHit::Button(DecorationButton::Close))
+        );
+        assert_eq!(
+            hit(170.0, 10.0),
+            Some(DecorationHit::Button(DecorationButton::Maximize))
+        );
+        assert_eq!(hit(100.0, 60.0), None);
+        assert_eq!(hit(300.0, 60.0), None);
+    }
+}
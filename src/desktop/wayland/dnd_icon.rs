@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+
+use wayland_server::{protocol::wl_surface::WlSurface, Resource};
+
+use crate::{
+    backend::renderer::{
+        element::{
+            surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            AsRenderElements,
+        },
+        utils::RendererSurfaceStateUserData,
+        ImportAll, Renderer,
+    },
+    utils::{IsAlive, Logical, Physical, Point, Scale},
+    wayland::{
+        compositor::{self, with_states},
+        data_device::DND_ICON_ROLE,
+    },
+};
+
+type DndIconOffset = RefCell<Point<i32, Logical>>;
+
+/// Icon of an ongoing drag'n'drop operation
+///
+/// The icon is provided by the client when starting a drag (see
+/// [`ClientDndGrabHandler::started`](crate::wayland::data_device::ClientDndGrabHandler::started))
+/// and is supposed to follow the pointer. Clients can move the icon relative to the
+/// pointer by attaching buffers with an offset, which is accumulated by this type.
+///
+/// Rendering the icon through its [`AsRenderElements`] implementation expects the
+/// location of the pointer and takes care of applying this offset.
+///
+/// Note that the offset is derived from the [`RendererSurfaceState`](crate::backend::renderer::utils::RendererSurfaceState)
+/// of the surface, so [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler)
+/// has to be called on commit of the icon surface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DndIcon {
+    surface: WlSurface,
+}
+
+impl DndIcon {
+    /// Create a new drag'n'drop icon from a surface
+    ///
+    /// Fails if the surface has any role besides the [`DND_ICON_ROLE`], surfaces without a role
+    /// will be assigned the drag'n'drop icon role.
+    pub fn new(surface: WlSurface) -> Result<Self, compositor::AlreadyHasRole> {
+        match compositor::get_role(&surface) {
+            Some(DND_ICON_ROLE) => {}
+            Some(_) => return Err(compositor::AlreadyHasRole),
+            None => compositor::give_role(&surface, DND_ICON_ROLE)?,
+        }
+        // the surface might have been the icon of a previous drag
+        with_states(&surface, |states| {
+            if let Some(offset) = states.data_map.get::<DndIconOffset>() {
+                *offset.borrow_mut() = Point::default();
+            }
+        });
+        Ok(DndIcon { surface })
+    }
+
+    /// Access the underlying [`WlSurface`]
+    pub fn wl_surface(&self) -> &WlSurface {
+        &self.surface
+    }
+
+    /// Current offset of the icon relative to the pointer location
+    ///
+    /// This accumulates all offsets the client attached buffers with since the icon was created.
+    pub fn offset(&self) -> Point<i32, Logical> {
+        if !self.surface.alive() {
+            return Point::default();
+        }
+
+        with_states(&self.surface, |states| {
+            states.data_map.insert_if_missing(DndIconOffset::default);
+            let mut offset = states.data_map.get::<DndIconOffset>().unwrap().borrow_mut();
+            if let Some(data) = states.data_map.get::<RendererSurfaceStateUserData>() {
+                *offset += data.borrow_mut().take_accumulated_buffer_delta();
+            }
+            *offset
+        })
+    }
+}
+
+impl IsAlive for DndIcon {
+    fn alive(&self) -> bool {
+        self.surface.alive()
+    }
+}
+
+impl<R> AsRenderElements<R> for DndIcon
+where
+    R: Renderer + ImportAll,
+    <R as Renderer>::TextureId: 'static,
+{
+    type RenderElement = WaylandSurfaceRenderElement<R>;
+
+    fn render_elements<C: From<WaylandSurfaceRenderElement<R>>>(
+        &self,
+        renderer: &mut R,
+        location: Point<i32, Physical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Vec<C> {
+        if !self.alive() {
+            return Vec::new();
+        }
+
+        let offset = self.offset().to_f64().to_physical(scale).to_i32_round();
+        render_elements_from_surface_tree(renderer, &self.surface, location + offset, scale, alpha)
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
         Seat, SeatHandler,
     },
     output::{Output, WeakOutput},
-    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle, Serial},
+    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle, Serial, Size},
     wayland::{
         compositor::{with_states, with_surface_tree_downward, SurfaceData, TraversalAction},
         dmabuf::DmabufFeedback,
@@ -45,6 +45,7 @@ pub struct LayerMap {
     layers: IndexSet<LayerSurface>,
     output: WeakOutput,
     zone: Rectangle<i32, Logical>,
+    zone_changed: bool,
     // surfaces for tracking enter and leave events
     surfaces: HashSet<Weak<WlSurface>>,
 }
@@ -64,25 +65,28 @@ pub fn layer_map_for_output(o: &Output) -> RefMut<'_, LayerMap> {
         RefCell::new(LayerMap {
             layers: IndexSet::new(),
             output: o.downgrade(),
-            zone: Rectangle::from_loc_and_size(
-                (0, 0),
-                o.current_mode()
-                    .map(|mode| {
-                        let logical_size = mode
-                            .size
-                            .to_f64()
-                            .to_logical(o.current_scale().fractional_scale())
-                            .to_i32_round();
-                        o.current_transform().transform_size(logical_size)
-                    })
-                    .unwrap_or_else(|| (0, 0).into()),
-            ),
+            zone: Rectangle::from_loc_and_size((0, 0), output_logical_size(o)),
+            zone_changed: false,
             surfaces: HashSet::new(),
         })
     });
     userdata.get::<RefCell<LayerMap>>().unwrap().borrow_mut()
 }
 
+fn output_logical_size(output: &Output) -> Size<i32, Logical> {
+    output
+        .current_mode()
+        .map(|mode| {
+            let logical_size = mode
+                .size
+                .to_f64()
+                .to_logical(output.current_scale().fractional_scale())
+                .to_i32_round();
+            output.current_transform().transform_size(logical_size)
+        })
+        .unwrap_or_else(|| (0, 0).into())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum LayerError {
     #[error("Layer is already mapped to a different map")]
@@ -149,10 +153,22 @@ impl LayerMap {
     }
 
     /// Return the area of this output, that is not exclusive to any [`LayerSurface`]s.
+    ///
+    /// The area is relative to the output and updated whenever the layer surfaces are
+    /// [arranged](LayerMap::arrange). See [`Space::output_work_area`](crate::desktop::Space::output_work_area)
+    /// for the area in global coordinates.
     pub fn non_exclusive_zone(&self) -> Rectangle<i32, Logical> {
         self.zone
     }
 
+    /// Returns the new non-exclusive zone, if it changed since the last call.
+    ///
+    /// This can be used to re-layout windows depending on the work area of the output,
+    /// e.g. maximized or tiled windows, once the exclusive zones of the layer surfaces change.
+    pub fn take_zone_change(&mut self) -> Option<Rectangle<i32, Logical>> {
+        std::mem::take(&mut self.zone_changed).then_some(self.zone)
+    }
+
     /// Returns the geometry of a given mapped [`LayerSurface`].
     ///
     /// If the surface was not previously mapped onto this layer map,
@@ -167,6 +183,38 @@ impl LayerMap {
         Some(bbox)
     }
 
+    /// Returns the area popups of a given mapped [`LayerSurface`] should be constrained to.
+    ///
+    /// Layer surfaces have no window geometry, so popups are positioned relative to the
+    /// origin of the layer surface itself. The returned rectangle is the output area in this
+    /// coordinate space and can be used to unconstrain the popups of the layer surface.
+    ///
+    /// If the surface was not previously mapped onto this layer map,
+    /// this function return `None`.
+    pub fn popup_constraint_area(&self, layer: &LayerSurface) -> Option<Rectangle<i32, Logical>> {
+        if !self.layers.contains(layer) {
+            return None;
+        }
+        let output = self.output()?;
+        let location = layer_state(layer).location;
+        Some(Rectangle::from_loc_and_size(
+            (-location.x, -location.y),
+            output_logical_size(&output),
+        ))
+    }
+
+    /// Returns the top-most [`LayerSurface`] requesting exclusive keyboard focus, if any.
+    ///
+    /// Only surfaces on the [`WlrLayer::Overlay`] and [`WlrLayer::Top`] layers are considered,
+    /// for the bottom and background layers normal focus semantics apply
+    /// (see [`KeyboardInteractivity::Exclusive`]).
+    pub fn exclusive_keyboard_focus(&self) -> Option<&LayerSurface> {
+        self.layers_on(WlrLayer::Overlay)
+            .rev()
+            .chain(self.layers_on(WlrLayer::Top).rev())
+            .find(|l| l.keyboard_interactivity() == KeyboardInteractivity::Exclusive)
+    }
+
     /// Returns a [`LayerSurface`] under a given point and on a given layer, if any.
     pub fn layer_under<P: Into<Point<f64, Logical>>>(
         &self,
@@ -247,26 +295,16 @@ impl LayerMap {
 
     /// Force re-arranging the layer surfaces, e.g. when the output size changes.
     ///
+    /// This also updates the [preferred buffer state](crate::desktop::utils::PreferredBufferState)
+    /// of the layer surfaces, so it should be called when the scale or transform of the output changes.
+    ///
     /// Note: Mapping or unmapping a layer surface will automatically cause a re-arrangement.
     pub fn arrange(&mut self) {
         if let Some(output) = self.output() {
             let span = debug_span!("layer_map", output = output.name());
             let _guard = span.enter();
 
-            let output_rect = Rectangle::from_loc_and_size(
-                (0, 0),
-                output
-                    .current_mode()
-                    .map(|mode| {
-                        let logical_size = mode
-                            .size
-                            .to_f64()
-                            .to_logical(output.current_scale().fractional_scale())
-                            .to_i32_round();
-                        output.current_transform().transform_size(logical_size)
-                    })
-                    .unwrap_or_else(|| (0, 0).into()),
-            );
+            let output_rect = Rectangle::from_loc_and_size((0, 0), output_logical_size(&output));
             let mut zone = output_rect;
             trace!("Arranging layers into {:?}", output_rect.size);
 
@@ -278,12 +316,13 @@ impl LayerMap {
                     surface,
                     (),
                     |_, _, _| TraversalAction::DoChildren(()),
-                    |wl_surface, _, _| {
+                    |wl_surface, states, _| {
                         let weak = wl_surface.downgrade();
                         if !surfaces_ref.contains(&weak) {
                             output.enter(wl_surface);
                             surfaces_ref.insert(weak);
                         }
+                        update_preferred_buffer_state(states, &output);
                     },
                     |_, _, _| true,
                 );
@@ -293,12 +332,13 @@ impl LayerMap {
                         surface,
                         (),
                         |_, _, _| TraversalAction::DoChildren(()),
-                        |wl_surface, _, _| {
+                        |wl_surface, states, _| {
                             let weak = wl_surface.downgrade();
                             if !surfaces_ref.contains(&weak) {
                                 output.enter(wl_surface);
                                 surfaces_ref.insert(weak);
                             }
+                            update_preferred_buffer_state(states, &output);
                         },
                         |_, _, _| true,
                     )
@@ -361,47 +401,18 @@ impl LayerMap {
 
                 let location: Point<i32, Logical> = (x, y).into();
 
-                if let ExclusiveZone::Exclusive(amount) = data.exclusive_zone {
-                    match data.anchor {
-                        x if x.contains(Anchor::TOP) && x.contains(Anchor::BOTTOM) => {
-                            zone.size.w -= amount as i32;
-                            if x.contains(Anchor::LEFT) {
-                                zone.loc.x += amount as i32 + data.margin.left;
-                                zone.size.w -= data.margin.left;
-                            }
-                            if x.contains(Anchor::RIGHT) {
-                                zone.size.w -= data.margin.right
-                            }
-                        }
-                        x if x.contains(Anchor::LEFT) && x.contains(Anchor::RIGHT) => {
-                            zone.size.h -= amount as i32;
-                            if x.contains(Anchor::TOP) {
-                                zone.loc.y += amount as i32 + data.margin.top;
-                                zone.size.h -= data.margin.top
-                            }
-                            if x.contains(Anchor::BOTTOM) {
-                                zone.size.h -= data.margin.bottom
-                            }
-                        }
-                        x if x == Anchor::all() => {
-                            zone.size.w = 0;
-                            zone.size.h = 0;
-                        }
-                        x if x.contains(Anchor::LEFT) && !x.contains(Anchor::RIGHT) => {
-                            zone.loc.x += amount as i32 + data.margin.left;
-                            zone.size.w -= amount as i32 + data.margin.left;
-                        }
-                        x if x.contains(Anchor::TOP) && !x.contains(Anchor::BOTTOM) => {
-                            zone.loc.y += amount as i32 + data.margin.top;
-                            zone.size.h -= amount as i32 + data.margin.top;
-                        }
-                        x if x.contains(Anchor::RIGHT) && !x.contains(Anchor::LEFT) => {
-                            zone.size.w -= amount as i32 + data.margin.right;
-                        }
-                        x if x.contains(Anchor::BOTTOM) && !x.contains(Anchor::TOP) => {
-                            zone.size.h -= amount as i32 + data.margin.bottom;
-                        }
-                        _ => {}
+                if let (Some(amount), Some(edge)) = (data.exclusive_zone_size(), data.exclusive_edge()) {
+                    let amount = amount as i32;
+                    if edge == Anchor::LEFT {
+                        zone.loc.x += amount + data.margin.left;
+                        zone.size.w -= amount + data.margin.left;
+                    } else if edge == Anchor::RIGHT {
+                        zone.size.w -= amount + data.margin.right;
+                    } else if edge == Anchor::TOP {
+                        zone.loc.y += amount + data.margin.top;
+                        zone.size.h -= amount + data.margin.top;
+                    } else if edge == Anchor::BOTTOM {
+                        zone.size.h -= amount + data.margin.bottom;
                     }
                 }
 
@@ -433,7 +444,10 @@ impl LayerMap {
             }
 
             trace!("Remaining zone {:?}", zone);
-            self.zone = zone;
+            if self.zone != zone {
+                self.zone = zone;
+                self.zone_changed = true;
+            }
         }
     }
 
@@ -560,6 +574,21 @@ impl LayerSurface {
         })
     }
 
+    /// Returns the keyboard interactivity requested by this surface.
+    ///
+    /// Surfaces requesting [`KeyboardInteractivity::OnDemand`] should be focused and unfocused
+    /// by the usual focus mechanism of the compositor (e.g. click to focus), while surfaces requesting
+    /// [`KeyboardInteractivity::Exclusive`] on the top or overlay layer should be given the keyboard focus
+    /// exclusively (see [`LayerMap::exclusive_keyboard_focus`]).
+    pub fn keyboard_interactivity(&self) -> KeyboardInteractivity {
+        with_states(self.0.surface.wl_surface(), |states| {
+            states
+                .cached_state
+                .current::<LayerSurfaceCachedState>()
+                .keyboard_interactivity
+        })
+    }
+
     /// Returns the layer this surface resides on, if any yet.
     pub fn layer(&self) -> WlrLayer {
         with_states(self.0.surface.wl_surface(), |states| {
@@ -1,10 +1,10 @@
 use crate::{
     input::{Seat, SeatHandler},
-    utils::{DeadResource, IsAlive, Logical, Point, Serial},
+    utils::{DeadResource, IsAlive, Logical, Point, Rectangle, Serial},
     wayland::{
         compositor::{get_role, with_states},
         seat::WaylandFocus,
-        shell::xdg::{XdgPopupSurfaceData, XDG_POPUP_ROLE},
+        shell::xdg::{PopupSurface, PositionerState, XdgPopupSurfaceData, XDG_POPUP_ROLE},
     },
 };
 use std::sync::{Arc, Mutex};
@@ -179,6 +179,74 @@ impl PopupManager {
         })
     }
 
+    /// Applies the constraint adjustments of the pending positioner of a popup, to fit it
+    /// into the given target rectangle.
+    ///
+    /// The `target` is given relative to the window geometry of the root surface of the popup
+    /// (see [`find_popup_root_surface`]), and usually is the work area of the output the root
+    /// surface is displayed on. The resulting geometry is stored in the pending state of the
+    /// popup, use [`PopupSurface::send_configure`] or [`PopupSurface::send_pending_configure`]
+    /// to notify the client.
+    pub fn unconstrain_popup(
+        popup: &PopupSurface,
+        target: Rectangle<i32, Logical>,
+    ) -> Result<(), DeadResource> {
+        if !popup.alive() {
+            return Err(DeadResource);
+        }
+        let mut target = target;
+        target.loc -= get_popup_toplevel_coords(&PopupKind::Xdg(popup.clone()));
+        popup.with_pending_state(|state| {
+            state.geometry = state.positioner.get_unconstrained_geometry(target);
+        });
+        Ok(())
+    }
+
+    /// Handles a `xdg_popup.reposition` request
+    ///
+    /// Stores the new positioner, positions the popup inside the given target rectangle
+    /// (see [`PopupManager::unconstrain_popup`]) and sends the `repositioned` event along with
+    /// the new configuration.
+    ///
+    /// This is meant to be called from [`XdgShellHandler::reposition_request`](crate::wayland::shell::xdg::XdgShellHandler::reposition_request).
+    pub fn reposition_popup(
+        popup: &PopupSurface,
+        positioner: PositionerState,
+        token: u32,
+        target: Rectangle<i32, Logical>,
+    ) -> Result<Serial, DeadResource> {
+        if !popup.alive() {
+            return Err(DeadResource);
+        }
+        popup.with_pending_state(|state| {
+            state.positioner = positioner;
+        });
+        PopupManager::unconstrain_popup(popup, target)?;
+        Ok(popup.send_repositioned(token))
+    }
+
+    /// Repositions the reactive popups of a given surface
+    ///
+    /// Should be called when the conditions used for constraining the popups changed, e.g. when
+    /// the root surface was moved or resized. Popups whose positioner is reactive are
+    /// reconstrained to the given target rectangle (see [`PopupManager::unconstrain_popup`]),
+    /// and a configure is sent if their geometry changed.
+    pub fn reconstrain_popups(surface: &WlSurface, target: Rectangle<i32, Logical>) {
+        // parents are positioned before their children
+        let mut popups = PopupManager::popups_for_surface(surface).collect::<Vec<_>>();
+        popups.reverse();
+        for (popup, _) in popups {
+            let PopupKind::Xdg(popup) = popup;
+            let reactive = popup.with_pending_state(|state| state.positioner.reactive);
+            if !reactive || PopupManager::unconstrain_popup(&popup, target).is_err() {
+                continue;
+            }
+            if let Err(err) = popup.send_pending_configure() {
+                trace!(?err, "Failed to reconstrain popup");
+            }
+        }
+    }
+
     pub(crate) fn dismiss_popup(surface: &WlSurface, popup: &PopupKind) -> Result<(), DeadResource> {
         if !surface.alive() {
             return Err(DeadResource);
@@ -228,6 +296,36 @@ pub fn find_popup_root_surface(popup: &PopupKind) -> Result<WlSurface, DeadResou
     Ok(parent)
 }
 
+/// Computes the location of the parent of a popup, relative to the window geometry
+/// of the root surface of the popup.
+///
+/// See [`find_popup_root_surface`].
+pub fn get_popup_toplevel_coords(popup: &PopupKind) -> Point<i32, Logical> {
+    let mut parent = match popup.parent() {
+        Some(parent) => parent,
+        None => return (0, 0).into(),
+    };
+
+    let mut offset = Point::<i32, Logical>::from((0, 0));
+    while get_role(&parent) == Some(XDG_POPUP_ROLE) {
+        let (location, next) = with_states(&parent, |states| {
+            let attributes = states
+                .data_map
+                .get::<XdgPopupSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap();
+            (attributes.current.geometry.loc, attributes.parent.clone())
+        });
+        offset += location;
+        parent = match next {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    offset
+}
+
 #[derive(Debug, Default, Clone)]
 struct PopupTree(Arc<Mutex<Vec<PopupNode>>>);
 
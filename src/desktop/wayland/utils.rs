@@ -1,5 +1,10 @@
 //! Helper functions to ease dealing with surface trees
 
+#[cfg(feature = "backend_drm")]
+use crate::{
+    backend::drm::{DrmEventMetadata, DrmEventTime},
+    utils::{Clock, Monotonic},
+};
 use crate::{
     backend::renderer::{
         element::{
@@ -7,12 +12,13 @@ use crate::{
         },
         utils::RendererSurfaceState,
     },
-    desktop::WindowSurfaceType,
+    desktop::{space::Visibility, WindowSurfaceType},
     output::{Output, WeakOutput},
-    utils::{Logical, Point, Rectangle, Time},
+    utils::{Logical, Point, Rectangle, Region, Time, Transform},
     wayland::{
         compositor::{with_surface_tree_downward, SurfaceAttributes, SurfaceData, TraversalAction},
         dmabuf::{DmabufFeedback, SurfaceDmabufFeedbackState},
+        fractional_scale::with_fractional_scale,
         presentation::{PresentationFeedbackCachedState, PresentationFeedbackCallback},
     },
 };
@@ -21,7 +27,7 @@ use wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
 use wayland_server::protocol::wl_surface;
 
 impl RendererSurfaceState {
-    fn contains_point<P: Into<Point<f64, Logical>>>(&self, attrs: &SurfaceAttributes, point: P) -> bool {
+    fn contains_point<P: Into<Point<f64, Logical>>>(&self, point: P) -> bool {
         let point = point.into();
         let size = match self.surface_view.map(|view| view.dst) {
             None => return false, // If the surface has no size, it can't have an input region.
@@ -41,15 +47,23 @@ impl RendererSurfaceState {
         }
 
         // If there's no input region, we're done.
-        if attrs.input_region.is_none() {
-            return true;
+        match self.input_region.as_ref() {
+            Some(input_region) => input_region.contains(point.to_i32_round()),
+            None => true,
         }
+    }
 
-        attrs
-            .input_region
-            .as_ref()
-            .unwrap()
-            .contains(point.to_i32_round())
+    /// Input region of the surface clipped to its size, in surface-local coordinates
+    fn effective_input_region(&self) -> Option<Region<i32, Logical>> {
+        let surface_rect = Rectangle::from_loc_and_size((0, 0), self.surface_view?.dst);
+        Some(match self.input_region.as_ref() {
+            Some(input_region) => {
+                let mut input_region = input_region.clone();
+                input_region.intersect_rect(surface_rect);
+                input_region
+            }
+            None => Region::from(surface_rect),
+        })
     }
 }
 
@@ -87,6 +101,85 @@ where
     bounding_box
 }
 
+/// Returns the opaque region of a given surface and all its subsurfaces.
+///
+/// The region is relative to (0, 0) of the given surface and takes subsurface positions,
+/// buffer transforms and viewports into account. This can be used for occlusion culling.
+///
+/// - `location` can be set to offset the returned region.
+pub fn opaque_region_from_surface_tree<P>(
+    surface: &wl_surface::WlSurface,
+    location: P,
+) -> Region<i32, Logical>
+where
+    P: Into<Point<i32, Logical>>,
+{
+    let mut opaque_region = Region::new();
+    with_surface_tree_downward(
+        surface,
+        location.into(),
+        |_, states, loc: &Point<i32, Logical>| {
+            let data = states.data_map.get::<RefCell<RendererSurfaceState>>();
+            match data.and_then(|d| d.borrow().surface_view) {
+                Some(surface_view) => TraversalAction::DoChildren(*loc + surface_view.offset),
+                None => TraversalAction::SkipChildren,
+            }
+        },
+        |_, states, loc: &Point<i32, Logical>| {
+            let data = states.data_map.get::<RefCell<RendererSurfaceState>>();
+            if let Some(data) = data {
+                let data = data.borrow();
+                if let Some(surface_view) = data.surface_view {
+                    let mut region = data.opaque_regions.clone();
+                    region.translate(*loc + surface_view.offset);
+                    opaque_region.union(&region);
+                }
+            }
+        },
+        |_, _, _| true,
+    );
+    opaque_region
+}
+
+/// Returns the input region of a given surface and all its subsurfaces.
+///
+/// The region is relative to (0, 0) of the given surface and takes subsurface positions,
+/// buffer transforms and viewports into account. The input region of every surface
+/// is clipped to the surface size. This can be used for input hit-testing.
+///
+/// - `location` can be set to offset the returned region.
+pub fn input_region_from_surface_tree<P>(surface: &wl_surface::WlSurface, location: P) -> Region<i32, Logical>
+where
+    P: Into<Point<i32, Logical>>,
+{
+    let mut input_region = Region::new();
+    with_surface_tree_downward(
+        surface,
+        location.into(),
+        |_, states, loc: &Point<i32, Logical>| {
+            let data = states.data_map.get::<RefCell<RendererSurfaceState>>();
+            match data.and_then(|d| d.borrow().surface_view) {
+                Some(surface_view) => TraversalAction::DoChildren(*loc + surface_view.offset),
+                None => TraversalAction::SkipChildren,
+            }
+        },
+        |_, states, loc: &Point<i32, Logical>| {
+            let data = states.data_map.get::<RefCell<RendererSurfaceState>>();
+            if let Some(data) = data {
+                let data = data.borrow();
+                if let (Some(surface_view), Some(mut region)) =
+                    (data.surface_view, data.effective_input_region())
+                {
+                    region.translate(*loc + surface_view.offset);
+                    input_region.union(&region);
+                }
+            }
+        },
+        |_, _, _| true,
+    );
+    input_region
+}
+
 /// Returns the topmost (sub-)surface under a given position matching the input regions of the surface.
 ///
 /// In case no surface input region matches the point [`None`] is returned.
@@ -115,10 +208,7 @@ where
 
                 if states.role == Some("subsurface") || surface_type.contains(WindowSurfaceType::TOPLEVEL) {
                     let contains_the_point = data
-                        .map(|data| {
-                            data.borrow()
-                                .contains_point(&states.cached_state.current(), point - location.to_f64())
-                        })
+                        .map(|data| data.borrow().contains_point(point - location.to_f64()))
                         .unwrap_or(false);
                     if contains_the_point {
                         *found.borrow_mut() = Some((wl_surface.clone(), location));
@@ -202,6 +292,42 @@ where
         .update_from_render_element_states(surface, output, states, compare)
 }
 
+#[derive(Debug, Default)]
+struct SurfaceVisibilityState(Mutex<Visibility>);
+
+/// Retrieve the [`Visibility`] of a surface
+///
+/// Surfaces are [`Visible`](Visibility::Visible) unless set otherwise
+/// with [`set_surface_tree_visibility`].
+pub fn surface_visibility(states: &SurfaceData) -> Visibility {
+    states
+        .data_map
+        .get::<SurfaceVisibilityState>()
+        .map(|state| *state.0.lock().unwrap())
+        .unwrap_or_default()
+}
+
+/// Set the [`Visibility`] of a surface and its subsurfaces
+///
+/// Suspended surfaces only receive throttled frame callbacks from [`send_frames_surface_tree`]
+/// and their presentation feedback is discarded by [`take_presentation_feedback_surface_tree`].
+/// [`Window`](crate::desktop::Window)s mapped in a [`Space`](crate::desktop::Space) are updated
+/// automatically on [`Space::refresh`](crate::desktop::Space::refresh).
+pub fn set_surface_tree_visibility(surface: &wl_surface::WlSurface, visibility: Visibility) {
+    with_surfaces_surface_tree(surface, |_, states| {
+        states
+            .data_map
+            .insert_if_missing_threadsafe(SurfaceVisibilityState::default);
+        *states
+            .data_map
+            .get::<SurfaceVisibilityState>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap() = visibility;
+    });
+}
+
 /// Sends frame callbacks for a surface and its subsurfaces with the given `time`.
 ///
 /// The frame callbacks for a [`WlSurface`](wl_surface::WlSurface) will only be sent if the
@@ -211,6 +337,8 @@ where
 /// throttle threshold. If the threshold is `None` this will never send frame callbacks
 /// for a surface that is not visible. Specifying [`Duration::ZERO`] as the throttle threshold
 /// will always send frame callbacks for non visible surfaces.
+///
+/// Surfaces suspended according to their [`surface_visibility`] are always considered not visible.
 pub fn send_frames_surface_tree<T, F>(
     surface: &wl_surface::WlSurface,
     output: &Output,
@@ -234,9 +362,10 @@ pub fn send_frames_surface_tree<T, F>(
             let surface_frame_throttling_state =
                 states.data_map.get::<SurfaceFrameThrottlingState>().unwrap();
 
-            let on_primary_scanout_output = primary_scan_out_output(surface, states)
-                .map(|preferred_output| preferred_output == *output)
-                .unwrap_or(false);
+            let on_primary_scanout_output = !surface_visibility(states).is_suspended()
+                && primary_scan_out_output(surface, states)
+                    .map(|preferred_output| preferred_output == *output)
+                    .unwrap_or(false);
 
             let frame_overdue = surface_frame_throttling_state.update(time, throttle);
 
@@ -262,6 +391,104 @@ pub fn send_frames_surface_tree<T, F>(
     );
 }
 
+/// Schedules frame callbacks aligned to the refresh of the outputs
+///
+/// Instead of sending frame callbacks ad hoc, the scheduler sends the frame callbacks of surfaces
+/// once per refresh cycle of the output they are shown on. Surfaces only get frame callbacks from
+/// their primary scan-out output (see [`surface_primary_scanout_output`]), so occluded surfaces
+/// are skipped and only receive throttled frame callbacks, if a throttle is configured.
+///
+/// Call [`FrameCallbackScheduler::send_frames`] after an output presented a frame. If the output
+/// does not render, e.g. because nothing is damaged, frame callbacks of the surfaces on it are
+/// still due at [`FrameCallbackScheduler::next_frame_time`], which can be used to arm a timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameCallbackScheduler {
+    throttle: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct OutputFrameCallbackState(Mutex<Option<Duration>>);
+
+impl FrameCallbackScheduler {
+    /// Create a new scheduler
+    ///
+    /// `throttle` is the interval in which frame callbacks are sent to surfaces not visible on any
+    /// output, see [`send_frames_surface_tree`]. If `None` such surfaces receive no frame callbacks.
+    pub fn new(throttle: Option<Duration>) -> Self {
+        FrameCallbackScheduler { throttle }
+    }
+
+    /// Returns the configured throttle interval for surfaces not visible on any output
+    pub fn throttle(&self) -> Option<Duration> {
+        self.throttle
+    }
+
+    /// Returns the refresh interval of the current mode of the output
+    pub fn refresh_interval(output: &Output) -> Option<Duration> {
+        output
+            .current_mode()
+            .filter(|mode| mode.refresh > 0)
+            .map(|mode| Duration::from_nanos(1_000_000_000_000 / mode.refresh as u64))
+    }
+
+    /// Returns the time frame callbacks were last sent for the output
+    pub fn last_frame_time(output: &Output) -> Option<Duration> {
+        output
+            .user_data()
+            .get::<OutputFrameCallbackState>()
+            .and_then(|state| *state.0.lock().unwrap())
+    }
+
+    /// Returns the time the next frame callbacks of the surfaces on the output are due
+    ///
+    /// Returns `None` if no frame callbacks were sent for the output yet or the output
+    /// has no current mode.
+    pub fn next_frame_time(output: &Output) -> Option<Duration> {
+        Some(Self::last_frame_time(output)? + Self::refresh_interval(output)?)
+    }
+
+    /// Checks if frame callbacks for the surfaces on the output are due at `time`
+    pub fn frames_due(output: &Output, time: impl Into<Duration>) -> bool {
+        Self::next_frame_time(output)
+            .map(|next| time.into() >= next)
+            .unwrap_or(true)
+    }
+
+    /// Sends the frame callbacks of the given surface trees shown on an output
+    ///
+    /// This should be called once per refresh cycle of the output, usually after the output
+    /// presented a frame, with all surfaces mapped on the output. The frame callbacks are sent
+    /// with [`send_frames_surface_tree`].
+    pub fn send_frames<'a, T>(
+        &self,
+        output: &Output,
+        time: T,
+        surfaces: impl IntoIterator<Item = &'a wl_surface::WlSurface>,
+    ) where
+        T: Into<Duration>,
+    {
+        let time = time.into();
+        for surface in surfaces {
+            send_frames_surface_tree(
+                surface,
+                output,
+                time,
+                self.throttle,
+                surface_primary_scanout_output,
+            );
+        }
+
+        let user_data = output.user_data();
+        user_data.insert_if_missing_threadsafe(OutputFrameCallbackState::default);
+        *user_data
+            .get::<OutputFrameCallbackState>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap() = Some(time);
+    }
+}
+
 /// Sends dmabuf feedback for a surface and its subsurfaces with the given select function.
 ///
 /// The dmabuf feedback for a [`WlSurface`](wl_surface::WlSurface) will only be sent if the
@@ -410,6 +637,39 @@ impl OutputPresentationFeedback {
         }
     }
 
+    /// Mark all stored [`SurfacePresentationFeedback`]s as presented by a drm page flip
+    ///
+    /// The presentation time and sequence are taken from the [`DrmEventMetadata`] of the
+    /// [`DrmEvent::VBlank`](crate::backend::drm::DrmEvent::VBlank) completing the flip, the
+    /// refresh interval is derived from the current mode of the output.
+    /// Realtime timestamps are converted into the monotonic clock domain. If no timestamp is
+    /// available, the current time of `clock` is used instead. In both cases the feedback
+    /// is not flagged as hardware clock and completion.
+    ///
+    /// Per surface flags like [`wp_presentation_feedback::Kind::ZeroCopy`] are kept,
+    /// see [`surface_presentation_feedback_flags_from_states`].
+    #[cfg(feature = "backend_drm")]
+    pub fn presented_drm(&mut self, clock: &Clock<Monotonic>, metadata: Option<&DrmEventMetadata>) {
+        let (time, flags) = match metadata.map(|metadata| &metadata.time) {
+            Some(DrmEventTime::Monotonic(tp)) => (
+                Time::<Monotonic>::from(*tp),
+                wp_presentation_feedback::Kind::Vsync
+                    | wp_presentation_feedback::Kind::HwClock
+                    | wp_presentation_feedback::Kind::HwCompletion,
+            ),
+            Some(time) => (time.to_monotonic(), wp_presentation_feedback::Kind::Vsync),
+            None => (clock.now(), wp_presentation_feedback::Kind::Vsync),
+        };
+        let seq = metadata.map(|metadata| metadata.sequence as u64).unwrap_or(0);
+        let refresh = self
+            .output()
+            .and_then(|output| output.current_mode())
+            .map(|mode| refresh_interval(mode.refresh))
+            .unwrap_or(0);
+
+        self.presented(time, refresh, seq, flags);
+    }
+
     /// Mark all stored [`SurfacePresentationFeedback`]s as discarded
     pub fn discarded(&mut self) {
         for mut callback in self.callbacks.drain(..) {
@@ -418,6 +678,79 @@ impl OutputPresentationFeedback {
     }
 }
 
+// the refresh interval in nanoseconds of a refresh rate in millihertz, zero if unknown
+#[cfg(feature = "backend_drm")]
+fn refresh_interval(refresh: i32) -> u32 {
+    if refresh <= 0 {
+        return 0;
+    }
+    (1_000_000_000_000u64 / refresh as u64) as u32
+}
+
+/// Preferred scale and transform for the buffers of a surface
+///
+/// This is derived from the output the surface is primarily displayed on,
+/// see [`update_preferred_buffer_state`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreferredBufferState {
+    /// Preferred integer scale
+    pub scale: i32,
+    /// Preferred fractional scale
+    pub fractional_scale: f64,
+    /// Preferred buffer transform
+    pub transform: Transform,
+}
+
+impl PreferredBufferState {
+    /// Returns the preferred state for surfaces displayed on an output
+    pub fn from_output(output: &Output) -> Self {
+        let scale = output.current_scale();
+        PreferredBufferState {
+            scale: scale.integer_scale(),
+            fractional_scale: scale.fractional_scale(),
+            transform: output.current_transform(),
+        }
+    }
+}
+
+type PreferredBufferStateUserData = RefCell<Option<PreferredBufferState>>;
+
+/// Returns the preferred buffer state of a surface
+///
+/// Returns `None` if the state was never set through [`update_preferred_buffer_state`].
+pub fn preferred_buffer_state(states: &SurfaceData) -> Option<PreferredBufferState> {
+    states
+        .data_map
+        .get::<PreferredBufferStateUserData>()
+        .and_then(|state| *state.borrow())
+}
+
+/// Update the preferred buffer state of a surface for the output it is primarily displayed on
+///
+/// This also sets the preferred scale of the `wp_fractional_scale_v1` object of the surface, if any.
+/// [`Window`](crate::desktop::Window)s and [`X11Surface`](crate::xwayland::X11Surface)s mapped in a
+/// [`Space`](crate::desktop::Space) call this automatically, when their primary output or its scale
+/// or transform changes.
+///
+/// Returns `true` if the state changed.
+pub fn update_preferred_buffer_state(states: &SurfaceData, output: &Output) -> bool {
+    let state = PreferredBufferState::from_output(output);
+    states
+        .data_map
+        .insert_if_missing(PreferredBufferStateUserData::default);
+    let changed = states
+        .data_map
+        .get::<PreferredBufferStateUserData>()
+        .unwrap()
+        .replace(Some(state))
+        != Some(state);
+
+    with_fractional_scale(states, |fractional_scale| {
+        fractional_scale.set_preferred_scale(state.fractional_scale);
+    });
+    changed
+}
+
 /// Takes the [`PresentationFeedbackCallback`]s from the surface tree
 ///
 /// This moves the [`PresentationFeedbackCallback`]s from the surfaces
@@ -425,6 +758,8 @@ impl OutputPresentationFeedback {
 /// to the [`OutputPresentationFeedback`]
 ///
 /// The flags closure can be used to set special flags per surface like [`wp_presentation_feedback::Kind::ZeroCopy`]
+///
+/// The feedback of surfaces suspended according to their [`surface_visibility`] is discarded.
 pub fn take_presentation_feedback_surface_tree<F1, F2>(
     surface: &wl_surface::WlSurface,
     output_feedback: &mut OutputPresentationFeedback,
@@ -439,6 +774,16 @@ pub fn take_presentation_feedback_surface_tree<F1, F2>(
         (),
         |_, _, &()| TraversalAction::DoChildren(()),
         |surface, states, &()| {
+            if surface_visibility(states).is_suspended() {
+                // suspended surfaces are not presented anywhere
+                if let Some(mut feedback) =
+                    SurfacePresentationFeedback::from_states(states, wp_presentation_feedback::Kind::empty())
+                {
+                    feedback.discarded();
+                }
+                return;
+            }
+
             let on_primary_scanout_output = primary_scan_out_output(surface, states)
                 .map(|preferred_output| preferred_output == output_feedback.output)
                 .unwrap_or(false);
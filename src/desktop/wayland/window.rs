@@ -12,10 +12,13 @@ use crate::{
         compositor::{with_states, SurfaceData},
         dmabuf::DmabufFeedback,
         seat::WaylandFocus,
-        shell::xdg::{SurfaceCachedState, ToplevelSurface},
+        shell::xdg::{
+            SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceData, XdgToplevelSurfaceRoleAttributes,
+        },
     },
 };
 use std::{
+    collections::BTreeSet,
     hash::{Hash, Hasher},
     sync::{
         atomic::{AtomicU8, Ordering},
@@ -37,6 +40,7 @@ pub(crate) struct WindowInner {
     bbox: Mutex<Rectangle<i32, Logical>>,
     pub(crate) z_index: AtomicU8,
     focused_surface: Mutex<Option<wl_surface::WlSurface>>,
+    tags: Mutex<BTreeSet<String>>,
     user_data: UserDataMap,
 }
 
@@ -97,6 +101,7 @@ impl Window {
             bbox: Mutex::new(Rectangle::from_loc_and_size((0, 0), (0, 0))),
             z_index: AtomicU8::new(RenderZindex::Shell as u8),
             focused_surface: Mutex::new(None),
+            tags: Mutex::new(BTreeSet::new()),
             user_data: UserDataMap::new(),
         }))
     }
@@ -281,6 +286,53 @@ impl Window {
     pub fn user_data(&self) -> &UserDataMap {
         &self.0.user_data
     }
+
+    /// Returns the title of the toplevel, if set
+    pub fn title(&self) -> Option<String> {
+        self.with_toplevel_data(|data| data.title.clone())
+    }
+
+    /// Returns the app id of the toplevel, if set
+    pub fn app_id(&self) -> Option<String> {
+        self.with_toplevel_data(|data| data.app_id.clone())
+    }
+
+    fn with_toplevel_data<T>(
+        &self,
+        f: impl FnOnce(&XdgToplevelSurfaceRoleAttributes) -> Option<T>,
+    ) -> Option<T> {
+        with_states(self.0.toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .and_then(|data| f(&data.lock().unwrap()))
+        })
+    }
+
+    /// Tag this window, e.g. to mark windows matched by a window rule
+    ///
+    /// Returns `false` if the window already had the tag.
+    /// Typed data can be attached through [`Window::user_data`] instead.
+    pub fn add_tag(&self, tag: impl Into<String>) -> bool {
+        self.0.tags.lock().unwrap().insert(tag.into())
+    }
+
+    /// Remove a tag from this window
+    ///
+    /// Returns `false` if the window did not have the tag.
+    pub fn remove_tag(&self, tag: &str) -> bool {
+        self.0.tags.lock().unwrap().remove(tag)
+    }
+
+    /// Returns whenever this window has the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.0.tags.lock().unwrap().contains(tag)
+    }
+
+    /// Returns all tags of this window in alphabetical order
+    pub fn tags(&self) -> Vec<String> {
+        self.0.tags.lock().unwrap().iter().cloned().collect()
+    }
 }
 
 impl<D: SeatHandler + 'static> PointerTarget<D> for Window {
@@ -0,0 +1,518 @@
+//! Recognition of compositor-level touch gestures
+//!
+//! [`GestureRecognizer`] consumes the raw touch events of a touchscreen and detects gestures the
+//! compositor might want to handle itself instead of forwarding them to clients, e.g. to switch
+//! workspaces or to open a panel:
+//!
+//! - edge swipes, a single finger moving inward from an edge of the touchscreen area
+//! - swipes of multiple fingers moving in the same direction
+//! - pinches of multiple fingers moving towards or away from each other
+//!
+//! Touch events are usually forwarded to clients as long as no gesture was recognized. Once a
+//! gesture begins, [`GestureRecognizer::is_active`] returns `true` and the compositor should cancel
+//! the touch session of the clients (see [`TouchHandle::cancel`](crate::wayland::seat::TouchHandle::cancel))
+//! and stop forwarding touch events until all fingers were lifted.
+//!
+//! ```
+//! use smithay::input::gesture::{Edge, GestureConfig, GestureEvent, GestureKind, GestureRecognizer};
+//! use smithay::utils::Rectangle;
+//! # let slot = Default::default();
+//!
+//! let mut gestures = GestureRecognizer::new(
+//!     GestureConfig::default(),
+//!     Rectangle::from_loc_and_size((0.0, 0.0), (1920.0, 1080.0)),
+//! );
+//!
+//! // on touch input
+//! gestures.down(slot, (500.0, 0.0).into(), 0);
+//! if let Some(GestureEvent::Begin {
+//!     kind: GestureKind::EdgeSwipe { edge: Edge::Top },
+//!     ..
+//! }) = gestures.motion(slot, (500.0, 100.0).into(), 10)
+//! {
+//!     // e.g. open a notification panel
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::backend::input::TouchSlot;
+use crate::utils::{Logical, Point, Rectangle};
+
+/// Thresholds used to recognize gestures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// Distance from an edge of the touchscreen area in which edge swipes have to start
+    pub edge_size: f64,
+    /// Distance the fingers have to move before a swipe is recognized
+    pub swipe_threshold: f64,
+    /// Relative change of the distance between the fingers before a pinch is recognized
+    pub pinch_threshold: f64,
+    /// Minimum number of fingers of swipe and pinch gestures
+    pub min_fingers: u32,
+    /// Time in milliseconds in which all fingers of a swipe or pinch have to touch down
+    pub max_start_interval: u32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            edge_size: 20.0,
+            swipe_threshold: 50.0,
+            pinch_threshold: 0.2,
+            min_fingers: 3,
+            max_start_interval: 150,
+        }
+    }
+}
+
+/// Edge of the touchscreen area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// Top edge
+    Top,
+    /// Bottom edge
+    Bottom,
+    /// Left edge
+    Left,
+    /// Right edge
+    Right,
+}
+
+/// Kind of a recognized gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GestureKind {
+    /// A single finger swiping inward from an edge
+    EdgeSwipe {
+        /// The edge the swipe started at
+        edge: Edge,
+    },
+    /// Multiple fingers moving in the same direction
+    Swipe {
+        /// Number of fingers
+        fingers: u32,
+    },
+    /// Multiple fingers moving towards or away from each other
+    Pinch {
+        /// Number of fingers
+        fingers: u32,
+    },
+}
+
+/// Progress of a gesture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureProgress {
+    /// Movement of the center of the fingers since the gesture started
+    pub delta: Point<f64, Logical>,
+    /// Current velocity of the center of the fingers in logical pixels per millisecond
+    pub velocity: Point<f64, Logical>,
+    /// Distance between the fingers relative to the start of the gesture
+    ///
+    /// This is always `1.0` for swipes.
+    pub scale: f64,
+}
+
+/// Events emitted by the [`GestureRecognizer`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GestureEvent {
+    /// A gesture was recognized
+    Begin {
+        /// Kind of the gesture
+        kind: GestureKind,
+        /// Progress of the gesture
+        progress: GestureProgress,
+    },
+    /// The fingers of an active gesture moved
+    Update {
+        /// Kind of the gesture
+        kind: GestureKind,
+        /// Progress of the gesture
+        progress: GestureProgress,
+    },
+    /// A gesture ended
+    End {
+        /// Kind of the gesture
+        kind: GestureKind,
+        /// Progress of the gesture
+        progress: GestureProgress,
+        /// Whether the touch session was cancelled instead of the fingers being lifted
+        cancelled: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    // no finger is down
+    Idle,
+    // fingers are down, but no gesture was recognized yet
+    Pending,
+    Active(GestureKind),
+    // the fingers do not form a gesture, wait until all of them are lifted
+    Rejected,
+}
+
+/// Recognizer of touch gestures
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    area: Rectangle<f64, Logical>,
+    state: State,
+    points: HashMap<TouchSlot, Point<f64, Logical>>,
+    first_down: u32,
+    // center and spread of the fingers when the set of fingers last changed
+    start_center: Point<f64, Logical>,
+    start_spread: f64,
+    last_center: Point<f64, Logical>,
+    last_time: u32,
+    progress: GestureProgress,
+}
+
+impl GestureRecognizer {
+    /// Create a new recognizer for a touchscreen covering the given area
+    ///
+    /// The area is used to recognize edge swipes and is given in the same coordinate space as the
+    /// locations of the touch events.
+    pub fn new(config: GestureConfig, area: Rectangle<f64, Logical>) -> Self {
+        GestureRecognizer {
+            config,
+            area,
+            state: State::Idle,
+            points: HashMap::new(),
+            first_down: 0,
+            start_center: Point::default(),
+            start_spread: 0.0,
+            last_center: Point::default(),
+            last_time: 0,
+            progress: GestureProgress {
+                delta: Point::default(),
+                velocity: Point::default(),
+                scale: 1.0,
+            },
+        }
+    }
+
+    /// Returns the thresholds used by this recognizer
+    pub fn config(&self) -> GestureConfig {
+        self.config
+    }
+
+    /// Change the thresholds used by this recognizer
+    ///
+    /// Gestures in progress are not affected.
+    pub fn set_config(&mut self, config: GestureConfig) {
+        self.config = config;
+    }
+
+    /// Change the area of the touchscreen, e.g. after the output it is mapped to changed its mode
+    pub fn set_area(&mut self, area: Rectangle<f64, Logical>) {
+        self.area = area;
+    }
+
+    /// Returns true while a gesture is in progress
+    ///
+    /// Touch events should not be forwarded to clients during that time.
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, State::Active(_))
+    }
+
+    /// Process a touch down event
+    ///
+    /// Gestures are only recognized once the fingers start moving.
+    pub fn down(&mut self, slot: TouchSlot, location: Point<f64, Logical>, time: u32) {
+        match self.state {
+            State::Idle => {
+                self.state = State::Pending;
+                self.first_down = time;
+            }
+            State::Pending if time.wrapping_sub(self.first_down) > self.config.max_start_interval => {
+                // a finger touching down later is not part of a gesture
+                self.state = State::Rejected;
+            }
+            _ => {}
+        }
+
+        self.points.insert(slot, location);
+        if self.state == State::Pending {
+            self.reset_start(time);
+        }
+    }
+
+    /// Process a touch motion event
+    pub fn motion(
+        &mut self,
+        slot: TouchSlot,
+        location: Point<f64, Logical>,
+        time: u32,
+    ) -> Option<GestureEvent> {
+        match self.points.get_mut(&slot) {
+            Some(point) => *point = location,
+            None => return None,
+        }
+
+        match self.state {
+            State::Pending => {
+                self.update_progress(time);
+                let kind = self.recognize()?;
+                self.state = State::Active(kind);
+                Some(GestureEvent::Begin {
+                    kind,
+                    progress: self.progress,
+                })
+            }
+            State::Active(kind) => {
+                self.update_progress(time);
+                Some(GestureEvent::Update {
+                    kind,
+                    progress: self.progress,
+                })
+            }
+            State::Idle | State::Rejected => None,
+        }
+    }
+
+    /// Process a touch up event
+    ///
+    /// Lifting any finger of an active gesture ends it.
+    pub fn up(&mut self, slot: TouchSlot, time: u32) -> Option<GestureEvent> {
+        self.points.remove(&slot)?;
+
+        let event = match self.state {
+            State::Active(kind) => {
+                self.state = State::Rejected;
+                Some(GestureEvent::End {
+                    kind,
+                    progress: self.progress,
+                    cancelled: false,
+                })
+            }
+            State::Pending if !self.points.is_empty() => {
+                self.reset_start(time);
+                None
+            }
+            _ => None,
+        };
+
+        if self.points.is_empty() {
+            self.state = State::Idle;
+        }
+        event
+    }
+
+    /// Process a touch cancel event
+    pub fn cancel(&mut self) -> Option<GestureEvent> {
+        self.points.clear();
+        match std::mem::replace(&mut self.state, State::Idle) {
+            State::Active(kind) => Some(GestureEvent::End {
+                kind,
+                progress: self.progress,
+                cancelled: true,
+            }),
+            _ => None,
+        }
+    }
+
+    fn center(&self) -> Point<f64, Logical> {
+        let sum = self
+            .points
+            .values()
+            .fold(Point::default(), |sum: Point<f64, Logical>, point| sum + *point);
+        sum.downscale(self.points.len().max(1) as f64)
+    }
+
+    fn spread(&self, center: Point<f64, Logical>) -> f64 {
+        let sum: f64 = self
+            .points
+            .values()
+            .map(|point| {
+                let offset = *point - center;
+                offset.x.hypot(offset.y)
+            })
+            .sum();
+        sum / self.points.len().max(1) as f64
+    }
+
+    fn reset_start(&mut self, time: u32) {
+        self.start_center = self.center();
+        self.start_spread = self.spread(self.start_center);
+        self.last_center = self.start_center;
+        self.last_time = time;
+        self.progress = GestureProgress {
+            delta: Point::default(),
+            velocity: Point::default(),
+            scale: 1.0,
+        };
+    }
+
+    fn update_progress(&mut self, time: u32) {
+        let center = self.center();
+        let elapsed = time.wrapping_sub(self.last_time);
+        if elapsed > 0 {
+            self.progress.velocity = (center - self.last_center).downscale(elapsed as f64);
+            self.last_center = center;
+            self.last_time = time;
+        }
+        self.progress.delta = center - self.start_center;
+        self.progress.scale = match self.state {
+            State::Active(GestureKind::Pinch { .. }) | State::Pending if self.start_spread > 0.0 => {
+                self.spread(center) / self.start_spread
+            }
+            _ => 1.0,
+        };
+    }
+
+    fn recognize(&mut self) -> Option<GestureKind> {
+        let fingers = self.points.len() as u32;
+        let delta = self.progress.delta;
+
+        if fingers == 1 {
+            let edge = self.start_edge()?;
+            let inward = match edge {
+                Edge::Top => delta.y,
+                Edge::Bottom => -delta.y,
+                Edge::Left => delta.x,
+                Edge::Right => -delta.x,
+            };
+            return (inward >= self.config.swipe_threshold).then_some(GestureKind::EdgeSwipe { edge });
+        }
+
+        if fingers < self.config.min_fingers {
+            return None;
+        }
+
+        if (self.progress.scale - 1.0).abs() >= self.config.pinch_threshold {
+            Some(GestureKind::Pinch { fingers })
+        } else if delta.x.hypot(delta.y) >= self.config.swipe_threshold {
+            self.progress.scale = 1.0;
+            Some(GestureKind::Swipe { fingers })
+        } else {
+            None
+        }
+    }
+
+    fn start_edge(&self) -> Option<Edge> {
+        let start = self.start_center;
+        let area = self.area;
+        let edge_size = self.config.edge_size;
+
+        if start.y - area.loc.y < edge_size {
+            Some(Edge::Top)
+        } else if area.loc.y + area.size.h - start.y < edge_size {
+            Some(Edge::Bottom)
+        } else if start.x - area.loc.x < edge_size {
+            Some(Edge::Left)
+        } else if area.loc.x + area.size.w - start.x < edge_size {
+            Some(Edge::Right)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognizer() -> GestureRecognizer {
+        GestureRecognizer::new(
+            GestureConfig::default(),
+            Rectangle::from_loc_and_size((0.0, 0.0), (1000.0, 1000.0)),
+        )
+    }
+
+    fn slot(id: u32) -> TouchSlot {
+        Some(id).into()
+    }
+
+    #[test]
+    fn edge_swipe() {
+        let mut gestures = recognizer();
+        gestures.down(slot(0), (500.0, 995.0).into(), 0);
+        assert_eq!(gestures.motion(slot(0), (500.0, 980.0).into(), 10), None);
+        assert!(matches!(
+            gestures.motion(slot(0), (500.0, 900.0).into(), 20),
+            Some(GestureEvent::Begin {
+                kind: GestureKind::EdgeSwipe { edge: Edge::Bottom },
+                ..
+            })
+        ));
+        assert!(gestures.is_active());
+        assert!(matches!(
+            gestures.up(slot(0), 30),
+            Some(GestureEvent::End { cancelled: false, .. })
+        ));
+        assert!(!gestures.is_active());
+    }
+
+    #[test]
+    fn single_finger_is_no_gesture() {
+        let mut gestures = recognizer();
+        gestures.down(slot(0), (500.0, 500.0).into(), 0);
+        assert_eq!(gestures.motion(slot(0), (500.0, 200.0).into(), 10), None);
+        assert_eq!(gestures.up(slot(0), 20), None);
+    }
+
+    #[test]
+    fn swipe() {
+        let mut gestures = recognizer();
+        for i in 0..3 {
+            gestures.down(slot(i), (400.0 + i as f64 * 50.0, 500.0).into(), i);
+        }
+        let mut event = None;
+        for step in 1..=10 {
+            for i in 0..3 {
+                let location = (400.0 + i as f64 * 50.0, 500.0 - step as f64 * 10.0);
+                event = event.or(gestures.motion(slot(i), location.into(), step * 10));
+            }
+        }
+        assert!(matches!(
+            event,
+            Some(GestureEvent::Begin {
+                kind: GestureKind::Swipe { fingers: 3 },
+                ..
+            })
+        ));
+        assert_eq!(gestures.progress.delta, (0.0, -100.0).into());
+        assert!(matches!(
+            gestures.cancel(),
+            Some(GestureEvent::End { cancelled: true, .. })
+        ));
+    }
+
+    #[test]
+    fn pinch() {
+        let mut gestures = recognizer();
+        let fingers = [(450.0, 450.0), (550.0, 450.0), (500.0, 550.0)];
+        for (i, location) in fingers.iter().enumerate() {
+            gestures.down(slot(i as u32), (*location).into(), 0);
+        }
+        let mut event = None;
+        for (i, (x, y)) in fingers.iter().enumerate() {
+            // move every finger away from the center
+            let location = (500.0 + (x - 500.0) * 2.0, 500.0 + (y - 500.0) * 2.0);
+            event = event.or(gestures.motion(slot(i as u32), location.into(), 100));
+        }
+        assert!(matches!(
+            event,
+            Some(GestureEvent::Begin {
+                kind: GestureKind::Pinch { fingers: 3 },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn late_finger_rejects() {
+        let mut gestures = recognizer();
+        gestures.down(slot(0), (400.0, 500.0).into(), 0);
+        gestures.down(slot(1), (450.0, 500.0).into(), 10);
+        gestures.down(slot(2), (500.0, 500.0).into(), 1000);
+        for i in 0..3 {
+            assert_eq!(
+                gestures.motion(slot(i), (400.0 + i as f64 * 50.0, 300.0).into(), 1100),
+                None
+            );
+        }
+    }
+}
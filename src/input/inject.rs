@@ -0,0 +1,283 @@
+//! Injection of synthetic input events into a seat
+//!
+//! Input events usually originate from an input backend, which provides their timestamps, while
+//! the compositor generates a serial for each of them. [`InputInjector`] allows to feed input
+//! events into a [`Seat`] without any backend, e.g. for remote desktop implementations, automation
+//! interfaces or test harnesses, and takes care of generating serials and timestamps.
+//!
+//! Timestamps are taken from the monotonic clock, matching the timestamps of the input backends
+//! provided by smithay.
+//!
+//! The events are delivered through the regular [`KeyboardHandle`](super::keyboard::KeyboardHandle)
+//! and [`PointerHandle`](super::pointer::PointerHandle) of the seat, so active grabs and key bindings
+//! of the compositor apply to them as well. Methods return `None` or `false` if the seat lacks the
+//! required capability.
+
+use std::{fmt, io, time::Duration};
+
+use crate::backend::input::{Axis, AxisSource, ButtonState, KeyState};
+use crate::utils::{Clock, Logical, Monotonic, Point, SERIAL_COUNTER};
+
+use super::{
+    keyboard::{FilterResult, KeysymHandle, ModifiersState},
+    pointer::{AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent},
+    Seat, SeatHandler,
+};
+
+/// Injector of synthetic input events into a seat
+///
+/// See the [module-level documentation](self) for details.
+pub struct InputInjector<D: SeatHandler> {
+    seat: Seat<D>,
+    clock: Clock<Monotonic>,
+}
+
+impl<D: SeatHandler> fmt::Debug for InputInjector<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InputInjector")
+            .field("seat", &self.seat.name())
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+impl<D: SeatHandler + 'static> InputInjector<D> {
+    /// Create a new injector for the given seat
+    ///
+    /// Fails if the monotonic clock is not available.
+    pub fn new(seat: &Seat<D>) -> io::Result<Self> {
+        Ok(InputInjector {
+            seat: seat.clone(),
+            clock: Clock::new()?,
+        })
+    }
+
+    /// Access the seat events are injected into
+    pub fn seat(&self) -> &Seat<D> {
+        &self.seat
+    }
+
+    /// The current timestamp in milliseconds, as used for injected events
+    pub fn time(&self) -> u32 {
+        (self.utime() / 1000) as u32
+    }
+
+    fn utime(&self) -> u64 {
+        Duration::from(self.clock.now()).as_micros() as u64
+    }
+
+    /// Inject a keystroke
+    ///
+    /// The keystroke is passed to the `filter` like any other keystroke, see
+    /// [`KeyboardHandle::input`](super::keyboard::KeyboardHandle::input).
+    pub fn key<T, F>(&self, data: &mut D, keycode: u32, state: KeyState, filter: F) -> Option<T>
+    where
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
+    {
+        let keyboard = self.seat.get_keyboard()?;
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard.input(data, keycode, state, serial, self.time(), filter)
+    }
+
+    /// Inject a keystroke, bypassing any key bindings
+    ///
+    /// Returns `false` if the seat has no keyboard.
+    pub fn key_forward(&self, data: &mut D, keycode: u32, state: KeyState) -> bool {
+        let keyboard = match self.seat.get_keyboard() {
+            Some(keyboard) => keyboard,
+            None => return false,
+        };
+        let serial = SERIAL_COUNTER.next_serial();
+        keyboard.input::<(), _>(data, keycode, state, serial, self.time(), |_, _, _| {
+            FilterResult::Forward
+        });
+        true
+    }
+
+    /// Inject an absolute pointer motion
+    ///
+    /// `location` is given in the global compositor space, `focus` is the target under this
+    /// location and the coordinates of its origin, just like for
+    /// [`PointerHandle::motion`](super::pointer::PointerHandle::motion).
+    ///
+    /// Returns `false` if the seat has no pointer.
+    pub fn pointer_motion(
+        &self,
+        data: &mut D,
+        location: Point<f64, Logical>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+    ) -> bool {
+        let pointer = match self.seat.get_pointer() {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let event = MotionEvent {
+            location,
+            serial: SERIAL_COUNTER.next_serial(),
+            time: self.time(),
+        };
+        pointer.motion(data, focus, &event);
+        true
+    }
+
+    /// Inject a relative pointer motion
+    ///
+    /// This only notifies clients using the relative pointer protocol, the absolute location of
+    /// the pointer has to be updated through [`InputInjector::pointer_motion`].
+    ///
+    /// Returns `false` if the seat has no pointer.
+    pub fn pointer_relative_motion(
+        &self,
+        data: &mut D,
+        delta: Point<f64, Logical>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+    ) -> bool {
+        let pointer = match self.seat.get_pointer() {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let event = RelativeMotionEvent {
+            delta,
+            delta_unaccel: delta,
+            utime: self.utime(),
+        };
+        pointer.relative_motion(data, focus, &event);
+        true
+    }
+
+    /// Inject a pointer button press or release
+    ///
+    /// Returns `false` if the seat has no pointer.
+    pub fn pointer_button(&self, data: &mut D, button: u32, state: ButtonState) -> bool {
+        let pointer = match self.seat.get_pointer() {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let event = ButtonEvent {
+            serial: SERIAL_COUNTER.next_serial(),
+            time: self.time(),
+            button,
+            state,
+        };
+        pointer.button(data, &event);
+        true
+    }
+
+    /// Inject a scroll event
+    ///
+    /// `horizontal` and `vertical` are the scroll amounts in logical pixels, axes without any
+    /// movement are left out of the frame.
+    ///
+    /// Returns `false` if the seat has no pointer.
+    pub fn pointer_axis(&self, data: &mut D, source: AxisSource, horizontal: f64, vertical: f64) -> bool {
+        let pointer = match self.seat.get_pointer() {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let mut frame = AxisFrame::new(self.time()).source(source);
+        if horizontal != 0.0 {
+            frame = frame.value(Axis::Horizontal, horizontal);
+        }
+        if vertical != 0.0 {
+            frame = frame.value(Axis::Vertical, vertical);
+        }
+        pointer.axis(data, frame);
+        true
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+mod touch {
+    use crate::backend::input::TouchSlot;
+    use crate::input::SeatHandler;
+    use crate::utils::{Logical, Point, SERIAL_COUNTER};
+    use crate::wayland::seat::touch::{DownEvent, MotionEvent, TouchTarget, UpEvent};
+
+    use super::InputInjector;
+
+    impl<D> InputInjector<D>
+    where
+        D: SeatHandler + 'static,
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        /// Inject a new touch point
+        ///
+        /// `location` is given in the global compositor space, `focus` is the target under this
+        /// location and the coordinates of its origin, just like for
+        /// [`TouchHandle::down`](crate::wayland::seat::TouchHandle::down).
+        ///
+        /// Returns `false` if the seat has no touch capability.
+        pub fn touch_down(
+            &self,
+            data: &mut D,
+            slot: TouchSlot,
+            location: Point<f64, Logical>,
+            focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        ) -> bool {
+            let touch = match self.seat.get_touch() {
+                Some(touch) => touch,
+                None => return false,
+            };
+            let event = DownEvent {
+                slot,
+                location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: self.time(),
+            };
+            touch.down(data, focus, &event);
+            true
+        }
+
+        /// Inject a touch point motion
+        ///
+        /// Returns `false` if the seat has no touch capability.
+        pub fn touch_motion(
+            &self,
+            data: &mut D,
+            slot: TouchSlot,
+            location: Point<f64, Logical>,
+            focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        ) -> bool {
+            let touch = match self.seat.get_touch() {
+                Some(touch) => touch,
+                None => return false,
+            };
+            let event = MotionEvent {
+                slot,
+                location,
+                time: self.time(),
+            };
+            touch.motion(data, focus, &event);
+            true
+        }
+
+        /// Inject the removal of a touch point
+        ///
+        /// Returns `false` if the seat has no touch capability.
+        pub fn touch_up(&self, data: &mut D, slot: TouchSlot) -> bool {
+            let touch = match self.seat.get_touch() {
+                Some(touch) => touch,
+                None => return false,
+            };
+            let event = UpEvent {
+                slot,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: self.time(),
+            };
+            touch.up(data, &event);
+            true
+        }
+
+        /// Inject the cancellation of all touch points
+        ///
+        /// Returns `false` if the seat has no touch capability.
+        pub fn touch_cancel(&self, data: &mut D) -> bool {
+            let touch = match self.seat.get_touch() {
+                Some(touch) => touch,
+                None => return false,
+            };
+            touch.cancel(data);
+            true
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+
+use xkbcommon::xkb;
+
+use crate::backend::input::KeyState;
+
+/// Configuration of the accessibility filters of a keyboard
+///
+/// The filters are applied to key events passed to [`KeyboardHandle::input`](super::KeyboardHandle::input)
+/// before they are interpreted by the keymap, i.e. before compositor key bindings are checked and
+/// before the events reach clients. All filters are disabled by default.
+///
+/// Delays are given in milliseconds and compared against the timestamps of the key events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AccessibilityConfig {
+    /// Sticky keys
+    ///
+    /// Pressing and releasing a modifier key latches the modifier until the next key is released,
+    /// pressing it a second time locks it, a third time unlocks it again.
+    pub sticky_keys: bool,
+    /// Slow keys
+    ///
+    /// Key presses are only accepted once the key was held for at least the given delay.
+    /// Keystrokes passed to [`KeyboardHandle::input_with_repeat`](super::KeyboardHandle::input_with_repeat)
+    /// start a timer accepting the held key once the delay elapsed. Otherwise the filter is driven
+    /// by the timestamps of key events and a held key is accepted when it is released or when the
+    /// next key event is processed after the delay elapsed.
+    pub slow_keys: Option<u32>,
+    /// Bounce keys
+    ///
+    /// Presses of a key within the given delay after it was released are ignored.
+    pub bounce_keys: Option<u32>,
+    /// Toggle sticky keys by pressing shift five times in a row
+    pub shift_toggles_sticky_keys: bool,
+}
+
+/// Changes of the accessibility state of a keyboard
+///
+/// See [`SeatHandler::keyboard_accessibility_changed`](crate::input::SeatHandler::keyboard_accessibility_changed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AccessibilityEvent {
+    /// The configuration was changed through a keyboard shortcut
+    ConfigChanged(AccessibilityConfig),
+    /// The modifiers latched or locked by sticky keys changed
+    ///
+    /// The masks are serialized in the same way as the modifiers sent to clients.
+    StickyModifiersChanged {
+        /// Latched modifiers
+        latched: xkb::ModMask,
+        /// Locked modifiers
+        locked: xkb::ModMask,
+    },
+    /// A key was pressed, but is not yet accepted by slow keys
+    SlowKeyPressed(u32),
+    /// A key was held long enough and accepted by slow keys
+    SlowKeyAccepted(u32),
+    /// A key was released too early and rejected by slow keys
+    SlowKeyRejected(u32),
+    /// A key press was ignored by bounce keys
+    BounceKeyRejected(u32),
+}
+
+const SHIFT_TOGGLE_PRESSES: u32 = 5;
+
+#[derive(Debug, Default)]
+pub(super) struct AccessibilityState {
+    pub(super) config: AccessibilityConfig,
+    // pending key press of slow keys and its timestamp
+    slow_pending: Option<(u32, u32)>,
+    // timestamps of the last release of keys for bounce keys
+    released: HashMap<u32, u32>,
+    // keys whose press was swallowed, so their release has to be swallowed as well
+    rejected: HashSet<u32>,
+    // modifier key pressed without any other key and the modifiers it activated
+    sticky_candidate: Option<(u32, xkb::ModMask)>,
+    sticky_modifier_keys: HashSet<u32>,
+    pub(super) sticky_latched: xkb::ModMask,
+    pub(super) sticky_locked: xkb::ModMask,
+    // the masks last applied to the xkb state
+    pub(super) applied_latched: xkb::ModMask,
+    pub(super) applied_locked: xkb::ModMask,
+    shift_presses: u32,
+}
+
+impl AccessibilityState {
+    pub(super) fn set_config(&mut self, config: AccessibilityConfig) {
+        if config.slow_keys.is_none() {
+            if let Some((keycode, _)) = self.slow_pending.take() {
+                self.rejected.insert(keycode);
+            }
+        }
+        if config.bounce_keys.is_none() {
+            self.released.clear();
+        }
+        if !config.sticky_keys {
+            self.sticky_candidate = None;
+            self.sticky_latched = 0;
+            self.sticky_locked = 0;
+        }
+        self.config = config;
+    }
+
+    /// Filter a key event through slow and bounce keys
+    ///
+    /// Returns the key events to process in order, which might include a previously
+    /// delayed key press.
+    pub(super) fn filter_key(
+        &mut self,
+        keycode: u32,
+        state: KeyState,
+        time: u32,
+        events: &mut Vec<AccessibilityEvent>,
+    ) -> Vec<(u32, KeyState)> {
+        let mut keys = Vec::with_capacity(2);
+
+        // a held key is accepted before processing any later event
+        if let (Some(delay), Some((pending, pressed_at))) = (self.config.slow_keys, self.slow_pending) {
+            if time.wrapping_sub(pressed_at) >= delay {
+                self.slow_pending = None;
+                events.push(AccessibilityEvent::SlowKeyAccepted(pending));
+                keys.push((pending, KeyState::Pressed));
+            }
+        }
+
+        if let Some(delay) = self.config.bounce_keys {
+            self.released
+                .retain(|_, released_at| time.wrapping_sub(*released_at) < delay);
+        }
+
+        match state {
+            KeyState::Pressed => {
+                if self.config.bounce_keys.is_some() && self.released.contains_key(&keycode) {
+                    self.rejected.insert(keycode);
+                    events.push(AccessibilityEvent::BounceKeyRejected(keycode));
+                    return keys;
+                }
+
+                if self.config.slow_keys.is_some() {
+                    // only one key can be pending, an earlier one was not held long enough
+                    if let Some((pending, _)) = self.slow_pending.replace((keycode, time)) {
+                        self.rejected.insert(pending);
+                        events.push(AccessibilityEvent::SlowKeyRejected(pending));
+                    }
+                    events.push(AccessibilityEvent::SlowKeyPressed(keycode));
+                    return keys;
+                }
+
+                keys.push((keycode, KeyState::Pressed));
+            }
+            KeyState::Released => {
+                if self.rejected.remove(&keycode) {
+                    return keys;
+                }
+
+                if matches!(self.slow_pending, Some((pending, _)) if pending == keycode) {
+                    self.slow_pending = None;
+                    events.push(AccessibilityEvent::SlowKeyRejected(keycode));
+                    return keys;
+                }
+
+                if self.config.bounce_keys.is_some() {
+                    self.released.insert(keycode, time);
+                }
+
+                keys.push((keycode, KeyState::Released));
+            }
+        }
+
+        keys
+    }
+
+    /// The delay after which slow keys accepts the key pressed at `time`, if the press is pending
+    pub(super) fn slow_key_delay(&self, keycode: u32, time: u32) -> Option<u32> {
+        match (self.config.slow_keys, self.slow_pending) {
+            (Some(delay), Some(pending)) if pending == (keycode, time) => Some(delay),
+            _ => None,
+        }
+    }
+
+    /// Accept the pending key press of slow keys once its delay elapsed without further input
+    ///
+    /// Returns false if the key pressed at `pressed_at` is no longer pending, because it was
+    /// released, replaced by another key or accepted by a later key event.
+    pub(super) fn accept_slow_key(
+        &mut self,
+        keycode: u32,
+        pressed_at: u32,
+        events: &mut Vec<AccessibilityEvent>,
+    ) -> bool {
+        if self.config.slow_keys.is_none() || self.slow_pending != Some((keycode, pressed_at)) {
+            return false;
+        }
+        self.slow_pending = None;
+        events.push(AccessibilityEvent::SlowKeyAccepted(keycode));
+        true
+    }
+
+    /// Track presses of shift to toggle sticky keys
+    ///
+    /// Returns true if the configuration was changed.
+    pub(super) fn track_shift(&mut self, is_shift: bool) -> bool {
+        if !self.config.shift_toggles_sticky_keys {
+            return false;
+        }
+
+        if !is_shift {
+            self.shift_presses = 0;
+            return false;
+        }
+
+        self.shift_presses += 1;
+        if self.shift_presses < SHIFT_TOGGLE_PRESSES {
+            return false;
+        }
+
+        self.shift_presses = 0;
+        let config = AccessibilityConfig {
+            sticky_keys: !self.config.sticky_keys,
+            ..self.config
+        };
+        self.set_config(config);
+        true
+    }
+
+    /// Update the sticky modifiers after the xkb state processed a key
+    ///
+    /// `activated` are the modifiers that became depressed by this key.
+    /// Returns true if the sticky modifiers changed.
+    pub(super) fn sticky_key(&mut self, keycode: u32, state: KeyState, activated: xkb::ModMask) -> bool {
+        if !self.config.sticky_keys {
+            return false;
+        }
+
+        match state {
+            KeyState::Pressed => {
+                if activated != 0 {
+                    self.sticky_modifier_keys.insert(keycode);
+                    self.sticky_candidate = Some((keycode, activated));
+                } else {
+                    // modifiers used as part of a chord are not latched
+                    self.sticky_candidate = None;
+                }
+                false
+            }
+            KeyState::Released => {
+                if self.sticky_modifier_keys.remove(&keycode) {
+                    match self.sticky_candidate.take() {
+                        Some((candidate, mask)) if candidate == keycode => {
+                            if self.sticky_locked & mask == mask {
+                                self.sticky_locked &= !mask;
+                            } else if self.sticky_latched & mask == mask {
+                                self.sticky_latched &= !mask;
+                                self.sticky_locked |= mask;
+                            } else {
+                                self.sticky_latched |= mask;
+                            }
+                            true
+                        }
+                        _ => false,
+                    }
+                } else if self.sticky_latched != 0 {
+                    // latched modifiers apply to a single key
+                    self.sticky_latched = 0;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Forget the sticky modifiers, e.g. because modifier indices changed with a new keymap
+    pub(super) fn reset_sticky(&mut self) {
+        self.sticky_candidate = None;
+        self.sticky_modifier_keys.clear();
+        self.sticky_latched = 0;
+        self.sticky_locked = 0;
+        self.applied_latched = 0;
+        self.applied_locked = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(
+        a11y: &mut AccessibilityState,
+        keycode: u32,
+        state: KeyState,
+        time: u32,
+    ) -> Vec<(u32, KeyState)> {
+        a11y.filter_key(keycode, state, time, &mut Vec::new())
+    }
+
+    #[test]
+    fn slow_keys() {
+        let mut a11y = AccessibilityState::default();
+        a11y.set_config(AccessibilityConfig {
+            slow_keys: Some(300),
+            ..Default::default()
+        });
+
+        // released too early
+        assert!(filter(&mut a11y, 30, KeyState::Pressed, 0).is_empty());
+        assert!(filter(&mut a11y, 30, KeyState::Released, 100).is_empty());
+
+        // held long enough
+        assert!(filter(&mut a11y, 30, KeyState::Pressed, 1000).is_empty());
+        assert_eq!(
+            filter(&mut a11y, 30, KeyState::Released, 1500),
+            vec![(30, KeyState::Pressed), (30, KeyState::Released)]
+        );
+    }
+
+    #[test]
+    fn slow_keys_timer() {
+        let mut a11y = AccessibilityState::default();
+        a11y.set_config(AccessibilityConfig {
+            slow_keys: Some(300),
+            ..Default::default()
+        });
+
+        // held past the delay without further input
+        assert!(filter(&mut a11y, 30, KeyState::Pressed, 0).is_empty());
+        assert_eq!(a11y.slow_key_delay(30, 0), Some(300));
+        let mut events = Vec::new();
+        assert!(a11y.accept_slow_key(30, 0, &mut events));
+        assert_eq!(events, vec![AccessibilityEvent::SlowKeyAccepted(30)]);
+        // the press is not emitted a second time on release
+        assert_eq!(
+            filter(&mut a11y, 30, KeyState::Released, 2000),
+            vec![(30, KeyState::Released)]
+        );
+
+        // the timer of a key released too early does nothing
+        assert!(filter(&mut a11y, 31, KeyState::Pressed, 3000).is_empty());
+        assert!(filter(&mut a11y, 31, KeyState::Released, 3100).is_empty());
+        assert_eq!(a11y.slow_key_delay(31, 3000), None);
+        assert!(!a11y.accept_slow_key(31, 3000, &mut Vec::new()));
+    }
+
+    #[test]
+    fn bounce_keys() {
+        let mut a11y = AccessibilityState::default();
+        a11y.set_config(AccessibilityConfig {
+            bounce_keys: Some(300),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            filter(&mut a11y, 30, KeyState::Pressed, 0),
+            vec![(30, KeyState::Pressed)]
+        );
+        assert_eq!(
+            filter(&mut a11y, 30, KeyState::Released, 50),
+            vec![(30, KeyState::Released)]
+        );
+        // bounced press and its release are ignored
+        assert!(filter(&mut a11y, 30, KeyState::Pressed, 100).is_empty());
+        assert!(filter(&mut a11y, 30, KeyState::Released, 150).is_empty());
+        // other keys are not affected
+        assert_eq!(
+            filter(&mut a11y, 31, KeyState::Pressed, 160),
+            vec![(31, KeyState::Pressed)]
+        );
+        assert_eq!(
+            filter(&mut a11y, 30, KeyState::Pressed, 500),
+            vec![(30, KeyState::Pressed)]
+        );
+    }
+
+    #[test]
+    fn sticky_keys() {
+        let mut a11y = AccessibilityState::default();
+        a11y.set_config(AccessibilityConfig {
+            sticky_keys: true,
+            ..Default::default()
+        });
+
+        // shift latches, then applies to a single key
+        assert!(!a11y.sticky_key(42, KeyState::Pressed, 0b1));
+        assert!(a11y.sticky_key(42, KeyState::Released, 0));
+        assert_eq!(a11y.sticky_latched, 0b1);
+        assert!(!a11y.sticky_key(30, KeyState::Pressed, 0));
+        assert!(a11y.sticky_key(30, KeyState::Released, 0));
+        assert_eq!(a11y.sticky_latched, 0);
+
+        // pressing twice locks, a third time unlocks
+        for _ in 0..2 {
+            a11y.sticky_key(42, KeyState::Pressed, 0b1);
+            a11y.sticky_key(42, KeyState::Released, 0);
+        }
+        assert_eq!((a11y.sticky_latched, a11y.sticky_locked), (0, 0b1));
+        a11y.sticky_key(42, KeyState::Pressed, 0b1);
+        a11y.sticky_key(42, KeyState::Released, 0);
+        assert_eq!((a11y.sticky_latched, a11y.sticky_locked), (0, 0));
+
+        // chords do not latch
+        a11y.sticky_key(42, KeyState::Pressed, 0b1);
+        a11y.sticky_key(30, KeyState::Pressed, 0);
+        a11y.sticky_key(30, KeyState::Released, 0);
+        a11y.sticky_key(42, KeyState::Released, 0);
+        assert_eq!(a11y.sticky_latched, 0);
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use xkbcommon::xkb;
+
+/// Policy deciding how the keyboard state of multiple physical keyboards of a seat is tracked
+///
+/// See [`KeyboardHandle::set_device_state_policy`](super::KeyboardHandle::set_device_state_policy).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeviceStatePolicy {
+    /// All keyboards share a single xkb state
+    ///
+    /// Modifiers, locks and the active layout changed on one keyboard apply to all of them.
+    #[default]
+    Shared,
+    /// Every keyboard has an xkb state of its own
+    ///
+    /// Latched and locked modifiers as well as the active layout are tracked per keyboard, and
+    /// the state of the keyboard that was used last is presented to clients. Depressed modifiers
+    /// of all keyboards are combined, so a modifier held on one keyboard applies to keys pressed
+    /// on another one.
+    PerDevice,
+}
+
+/// Modifier and layout masks to apply to the xkb state presented to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct MergedState {
+    pub(super) depressed_mods: xkb::ModMask,
+    pub(super) latched_mods: xkb::ModMask,
+    pub(super) locked_mods: xkb::ModMask,
+    pub(super) depressed_layout: xkb::LayoutIndex,
+    pub(super) latched_layout: xkb::LayoutIndex,
+    pub(super) locked_layout: xkb::LayoutIndex,
+}
+
+pub(super) struct DeviceStates {
+    pub(super) policy: DeviceStatePolicy,
+    states: HashMap<String, xkb::State>,
+}
+
+impl std::fmt::Debug for DeviceStates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceStates")
+            .field("policy", &self.policy)
+            .field("devices", &self.states.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl DeviceStates {
+    pub(super) fn new() -> Self {
+        DeviceStates {
+            policy: DeviceStatePolicy::Shared,
+            states: HashMap::new(),
+        }
+    }
+
+    fn state(&mut self, keymap: &xkb::Keymap, device: &str) -> &mut xkb::State {
+        self.states
+            .entry(device.to_owned())
+            .or_insert_with(|| xkb::State::new(keymap))
+    }
+
+    /// Update the state of a device with a key event
+    ///
+    /// `keycode` is the xkb keycode, i.e. already offset by 8.
+    pub(super) fn update_key(
+        &mut self,
+        keymap: &xkb::Keymap,
+        device: &str,
+        keycode: u32,
+        direction: xkb::KeyDirection,
+    ) -> MergedState {
+        self.state(keymap, device).update_key(keycode, direction);
+        self.merged(keymap, device)
+    }
+
+    /// Lock a layout on a device
+    pub(super) fn set_layout(
+        &mut self,
+        keymap: &xkb::Keymap,
+        device: &str,
+        layout: xkb::LayoutIndex,
+    ) -> MergedState {
+        let state = self.state(keymap, device);
+        let depressed_mods = state.serialize_mods(xkb::STATE_MODS_DEPRESSED);
+        let latched_mods = state.serialize_mods(xkb::STATE_MODS_LATCHED);
+        let locked_mods = state.serialize_mods(xkb::STATE_MODS_LOCKED);
+        state.update_mask(depressed_mods, latched_mods, locked_mods, 0, 0, layout);
+        self.merged(keymap, device)
+    }
+
+    /// The currently active layout of a device, if it was used before
+    pub(super) fn layout(&self, device: &str) -> Option<xkb::LayoutIndex> {
+        self.states
+            .get(device)
+            .map(|state| state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE))
+    }
+
+    pub(super) fn remove(&mut self, device: &str) {
+        self.states.remove(device);
+    }
+
+    /// Forget the state of all devices, e.g. because the keymap changed
+    pub(super) fn reset(&mut self) {
+        self.states.clear();
+    }
+
+    fn merged(&mut self, keymap: &xkb::Keymap, device: &str) -> MergedState {
+        let depressed_mods = self.states.values().fold(0, |mods, state| {
+            mods | state.serialize_mods(xkb::STATE_MODS_DEPRESSED)
+        });
+        let state = self.state(keymap, device);
+        MergedState {
+            depressed_mods,
+            latched_mods: state.serialize_mods(xkb::STATE_MODS_LATCHED),
+            locked_mods: state.serialize_mods(xkb::STATE_MODS_LOCKED),
+            depressed_layout: state.serialize_layout(xkb::STATE_LAYOUT_DEPRESSED),
+            latched_layout: state.serialize_layout(xkb::STATE_LAYOUT_LATCHED),
+            locked_layout: state.serialize_layout(xkb::STATE_LAYOUT_LOCKED),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYMAP: &str = r#"
+xkb_keymap {
+    xkb_keycodes "test" {
+        minimum = 8;
+        maximum = 255;
+        <AC01> = 38;
+        <LFSH> = 50;
+        <CAPS> = 66;
+    };
+    xkb_types "test" {
+        type "ONE_LEVEL" {
+            modifiers = none;
+            level_name[Level1] = "Any";
+        };
+        type "TWO_LEVEL" {
+            modifiers = Shift;
+            map[Shift] = Level2;
+            level_name[Level1] = "Base";
+            level_name[Level2] = "Shift";
+        };
+    };
+    xkb_compat "test" {
+        interpret Shift_L {
+            action = SetMods(modifiers = Shift);
+        };
+        interpret Caps_Lock {
+            action = LockMods(modifiers = Lock);
+        };
+    };
+    xkb_symbols "test" {
+        key <AC01> {
+            symbols[Group1] = [ a, A ],
+            symbols[Group2] = [ q, Q ]
+        };
+        key <LFSH> { [ Shift_L ] };
+        key <CAPS> { [ Caps_Lock ] };
+    };
+};
+"#;
+
+    // xkb keycodes of the keymap above
+    const KEY_A: u32 = 38;
+    const KEY_SHIFT: u32 = 50;
+    const KEY_CAPS: u32 = 66;
+
+    fn keymap() -> xkb::Keymap {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        xkb::Keymap::new_from_string(
+            &context,
+            KEYMAP.to_owned(),
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .unwrap()
+    }
+
+    fn per_device() -> DeviceStates {
+        let mut devices = DeviceStates::new();
+        devices.policy = DeviceStatePolicy::PerDevice;
+        devices
+    }
+
+    fn tap(devices: &mut DeviceStates, keymap: &xkb::Keymap, device: &str, keycode: u32) -> MergedState {
+        devices.update_key(keymap, device, keycode, xkb::KeyDirection::Down);
+        devices.update_key(keymap, device, keycode, xkb::KeyDirection::Up)
+    }
+
+    #[test]
+    fn per_device_modifiers() {
+        let keymap = keymap();
+        let shift = 1 << keymap.mod_get_index(&xkb::MOD_NAME_SHIFT);
+        let caps = 1 << keymap.mod_get_index(&xkb::MOD_NAME_CAPS);
+        let mut devices = per_device();
+
+        // locks only apply to the keyboard they were toggled on
+        assert_eq!(tap(&mut devices, &keymap, "kbd1", KEY_CAPS).locked_mods, caps);
+        assert_eq!(tap(&mut devices, &keymap, "kbd2", KEY_A).locked_mods, 0);
+        assert_eq!(tap(&mut devices, &keymap, "kbd1", KEY_A).locked_mods, caps);
+
+        // depressed modifiers of all keyboards are combined
+        devices.update_key(&keymap, "kbd1", KEY_SHIFT, xkb::KeyDirection::Down);
+        let merged = devices.update_key(&keymap, "kbd2", KEY_A, xkb::KeyDirection::Down);
+        assert_eq!(merged.depressed_mods, shift);
+        assert_eq!(merged.locked_mods, 0);
+        let merged = devices.update_key(&keymap, "kbd1", KEY_SHIFT, xkb::KeyDirection::Up);
+        assert_eq!(merged.depressed_mods, 0);
+    }
+
+    #[test]
+    fn per_device_layout() {
+        let keymap = keymap();
+        let mut devices = per_device();
+
+        assert_eq!(devices.layout("kbd1"), None);
+        assert_eq!(devices.set_layout(&keymap, "kbd1", 1).locked_layout, 1);
+        assert_eq!(devices.layout("kbd1"), Some(1));
+
+        // other keyboards keep their layout
+        assert_eq!(tap(&mut devices, &keymap, "kbd2", KEY_A).locked_layout, 0);
+        assert_eq!(devices.layout("kbd2"), Some(0));
+        assert_eq!(tap(&mut devices, &keymap, "kbd1", KEY_A).locked_layout, 1);
+    }
+
+    #[test]
+    fn remove_device() {
+        let keymap = keymap();
+        let shift = 1 << keymap.mod_get_index(&xkb::MOD_NAME_SHIFT);
+        let mut devices = per_device();
+
+        devices.set_layout(&keymap, "kbd1", 1);
+        devices.update_key(&keymap, "kbd1", KEY_SHIFT, xkb::KeyDirection::Down);
+        assert_eq!(tap(&mut devices, &keymap, "kbd2", KEY_A).depressed_mods, shift);
+
+        // modifiers held on an unplugged keyboard no longer apply
+        devices.remove("kbd1");
+        assert_eq!(devices.layout("kbd1"), None);
+        assert_eq!(tap(&mut devices, &keymap, "kbd2", KEY_A).depressed_mods, 0);
+
+        // the keyboard starts over with a fresh state once it is used again
+        assert_eq!(tap(&mut devices, &keymap, "kbd1", KEY_A).locked_layout, 0);
+    }
+}
@@ -2,14 +2,19 @@
 
 use crate::backend::input::KeyState;
 use crate::utils::{IsAlive, Serial, SERIAL_COUNTER};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
 use std::collections::HashSet;
 use std::{
     default::Default,
     fmt, io,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use thiserror::Error;
-use tracing::{debug, error, info, info_span, instrument, trace};
+use tracing::{debug, error, info, info_span, instrument, trace, warn};
 
 pub use xkbcommon::xkb::{self, keysyms, Keysym};
 
@@ -20,12 +25,24 @@ mod keymap_file;
 #[cfg(feature = "wayland_frontend")]
 pub use keymap_file::KeymapFile;
 
+mod accessibility;
+use accessibility::AccessibilityState;
+pub use accessibility::{AccessibilityConfig, AccessibilityEvent};
+
+mod device_state;
+pub use device_state::DeviceStatePolicy;
+use device_state::{DeviceStates, MergedState};
+
 mod modifiers_state;
 pub use modifiers_state::ModifiersState;
 
 mod xkb_config;
+use xkb_config::OwnedXkbConfig;
 pub use xkb_config::XkbConfig;
 
+mod xkb_watcher;
+pub use xkb_watcher::XkbConfigWatcher;
+
 /// Trait representing object that can receive keyboard interactions
 pub trait KeyboardTarget<D>: IsAlive + PartialEq + Clone + Send
 where
@@ -61,13 +78,37 @@ pub(crate) struct KbdInternal<D: SeatHandler> {
     pub(crate) pressed_keys: HashSet<u32>,
     pub(crate) mods_state: ModifiersState,
     context: xkb::Context,
+    // the config the keymap was compiled from, if it was not loaded from a string
+    xkb_config: Option<OwnedXkbConfig>,
     pub(crate) keymap: xkb::Keymap,
     pub(crate) state: xkb::State,
     pub(crate) repeat_rate: i32,
     pub(crate) repeat_delay: i32,
+    repeat: Option<KeyRepeat>,
+    a11y: AccessibilityState,
+    devices: DeviceStates,
     grab: GrabStatus<D>,
 }
 
+/// Key repeated by a timer of [`KeyboardHandle::input_with_repeat`]
+///
+/// The timer is owned by the event loop of the compositor and removes itself, once it
+/// no longer matches the repeat stored here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyRepeat {
+    keycode: u32,
+    serial: Serial,
+}
+
+/// Stage of a timer inserted by [`KeyboardHandle::input_with_repeat`]
+#[derive(Debug, Clone, Copy)]
+enum KeyTimer {
+    /// Waiting for slow keys to accept the key pressed at the given time
+    SlowKey { pressed_at: u32, delay: u32 },
+    /// Repeating the held key
+    Repeat(KeyRepeat),
+}
+
 // focus_hook does not implement debug, so we have to impl Debug manually
 impl<D: SeatHandler> fmt::Debug for KbdInternal<D>
 where
@@ -83,6 +124,9 @@ where
             .field("state", &self.state.get_raw_ptr())
             .field("repeat_rate", &self.repeat_rate)
             .field("repeat_delay", &self.repeat_delay)
+            .field("repeat", &self.repeat)
+            .field("accessibility", &self.a11y)
+            .field("devices", &self.devices)
             .finish()
     }
 }
@@ -108,16 +152,44 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             pressed_keys: HashSet::new(),
             mods_state: ModifiersState::default(),
             context,
+            xkb_config: Some(OwnedXkbConfig::from(&xkb_config)),
             keymap,
             state,
             repeat_rate,
             repeat_delay,
+            repeat: None,
+            a11y: AccessibilityState::default(),
+            devices: DeviceStates::new(),
             grab: GrabStatus::None,
         })
     }
 
+    fn cancel_repeat(&mut self) {
+        self.repeat = None;
+    }
+
+    // start repeating a held key, returns the repeat and the delay until the first repetition
+    fn start_repeat(&mut self, keycode: u32) -> Option<(KeyRepeat, Duration)> {
+        self.cancel_repeat();
+        // Offset the keycode by 8, as the evdev XKB rules reflect X's
+        // broken keycode system, which starts at 8.
+        if self.repeat_rate <= 0
+            || !self.pressed_keys.contains(&keycode)
+            || !self.keymap.key_repeats(keycode + 8)
+        {
+            return None;
+        }
+
+        let repeat = KeyRepeat {
+            keycode,
+            serial: SERIAL_COUNTER.next_serial(),
+        };
+        self.repeat = Some(repeat);
+        Some((repeat, Duration::from_millis(self.repeat_delay.max(0) as u64)))
+    }
+
     // return true if modifier state has changed
-    fn key_input(&mut self, keycode: u32, state: KeyState) -> bool {
+    fn key_input(&mut self, device: Option<&str>, keycode: u32, state: KeyState) -> bool {
         // track pressed keys as xkbcommon does not seem to expose it :(
         let direction = match state {
             KeyState::Pressed => {
@@ -130,6 +202,15 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             }
         };
 
+        if let (DeviceStatePolicy::PerDevice, Some(device)) = (self.devices.policy, device) {
+            // Offset the keycode by 8, as the evdev XKB rules reflect X's
+            // broken keycode system, which starts at 8.
+            let merged = self
+                .devices
+                .update_key(&self.keymap, device, keycode + 8, direction);
+            return self.apply_merged_state(merged);
+        }
+
         // update state
         // Offset the keycode by 8, as the evdev XKB rules reflect X's
         // broken keycode system, which starts at 8.
@@ -143,6 +224,50 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
         }
     }
 
+    // present the state of a device to clients, return true if modifier state has changed
+    fn apply_merged_state(&mut self, merged: MergedState) -> bool {
+        // modifiers of sticky keys are shared by all devices
+        self.state.update_mask(
+            merged.depressed_mods,
+            merged.latched_mods | self.a11y.applied_latched,
+            merged.locked_mods | self.a11y.applied_locked,
+            merged.depressed_layout,
+            merged.latched_layout,
+            merged.locked_layout,
+        );
+        let old_mods = self.mods_state;
+        self.mods_state.update_with(&self.state);
+        self.mods_state != old_mods
+    }
+
+    // return true if the sticky modifiers differ from the ones applied to the xkb state
+    fn sticky_mods_outdated(&self) -> bool {
+        self.a11y.sticky_latched != self.a11y.applied_latched
+            || self.a11y.sticky_locked != self.a11y.applied_locked
+    }
+
+    fn apply_sticky_mods(&mut self) {
+        let depressed_mods = self.state.serialize_mods(xkb::STATE_MODS_DEPRESSED);
+        let latched_mods = (self.state.serialize_mods(xkb::STATE_MODS_LATCHED) & !self.a11y.applied_latched)
+            | self.a11y.sticky_latched;
+        let locked_mods = (self.state.serialize_mods(xkb::STATE_MODS_LOCKED) & !self.a11y.applied_locked)
+            | self.a11y.sticky_locked;
+        let depressed_layout = self.state.serialize_layout(xkb::STATE_LAYOUT_DEPRESSED);
+        let latched_layout = self.state.serialize_layout(xkb::STATE_LAYOUT_LATCHED);
+        let locked_layout = self.state.serialize_layout(xkb::STATE_LAYOUT_LOCKED);
+        self.state.update_mask(
+            depressed_mods,
+            latched_mods,
+            locked_mods,
+            depressed_layout,
+            latched_layout,
+            locked_layout,
+        );
+        self.a11y.applied_latched = self.a11y.sticky_latched;
+        self.a11y.applied_locked = self.a11y.sticky_locked;
+        self.mods_state.update_with(&self.state);
+    }
+
     fn with_grab<F>(&mut self, seat: &Seat<D>, f: F)
     where
         F: FnOnce(KeyboardInnerHandle<'_, D>, &mut dyn KeyboardGrab<D>),
@@ -407,7 +532,6 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         keymap_file.change_keymap(keymap);
 
         use std::os::unix::io::AsRawFd;
-        use tracing::warn;
         use wayland_server::{protocol::wl_keyboard::KeymapFormat, Resource};
         let known_kbds = &self.arc.known_kbds;
         for kbd in &*known_kbds.lock().unwrap() {
@@ -424,6 +548,10 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     }
 
     /// Change the [`XkbConfig`] used by the keyboard.
+    ///
+    /// The new keymap is sent to all clients and the focused client receives the updated
+    /// modifiers. Currently pressed keys stay pressed, and locked modifiers as well as
+    /// the locked layout are carried over if the new keymap provides them.
     pub fn set_xkb_config(&self, data: &mut D, xkb_config: XkbConfig<'_>) -> Result<(), Error> {
         let mut internal = self.arc.internal.lock().unwrap();
 
@@ -431,25 +559,174 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
             debug!("Loading keymap failed");
             Error::BadKeymap
         })?;
+        internal.xkb_config = Some(OwnedXkbConfig::from(&xkb_config));
+        self.update_keymap(data, &mut internal, keymap);
+
+        Ok(())
+    }
+
+    /// Recompile the keymap of the keyboard from its current [`XkbConfig`].
+    ///
+    /// This picks up changes to the xkb configuration files and the `XKB_DEFAULT_*` environment
+    /// variables, and otherwise behaves like [`KeyboardHandle::set_xkb_config`]. See
+    /// [`XkbConfigWatcher`] to detect such changes.
+    ///
+    /// Does nothing if the keymap was set through [`KeyboardHandle::set_keymap_from_string`].
+    /// If the keymap fails to compile, the current one is kept.
+    pub fn reload_keymap(&self, data: &mut D) -> Result<(), Error> {
+        let mut internal = self.arc.internal.lock().unwrap();
+        let xkb_config = match internal.xkb_config.clone() {
+            Some(xkb_config) => xkb_config,
+            None => return Ok(()),
+        };
+
+        // the include paths of the context are derived from the environment on creation
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb_config.as_config().compile_keymap(&context).map_err(|_| {
+            debug!("Reloading keymap failed");
+            Error::BadKeymap
+        })?;
+        internal.context = context;
+        self.update_keymap(data, &mut internal, keymap);
+
+        Ok(())
+    }
+
+    /// Change the keymap used by the keyboard from a keymap in the xkb text format.
+    ///
+    /// Behaves like [`KeyboardHandle::set_xkb_config`], but allows to use a keymap that
+    /// was compiled elsewhere, e.g. by `xkbcomp`.
+    pub fn set_keymap_from_string(&self, data: &mut D, keymap: String) -> Result<(), Error> {
+        let mut internal = self.arc.internal.lock().unwrap();
+
+        let keymap = xkb::Keymap::new_from_string(
+            &internal.context,
+            keymap,
+            xkb::KEYMAP_FORMAT_TEXT_V1,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .ok_or_else(|| {
+            debug!("Loading keymap failed");
+            Error::BadKeymap
+        })?;
+        internal.xkb_config = None;
+        self.update_keymap(data, &mut internal, keymap);
+
+        Ok(())
+    }
+
+    fn update_keymap(&self, data: &mut D, internal: &mut KbdInternal<D>, keymap: xkb::Keymap) {
+        // the held key might not repeat or produce different keysyms with the new keymap
+        internal.cancel_repeat();
+
+        // modifier indices may differ between keymaps
+        internal.a11y.reset_sticky();
+        internal.devices.reset();
+
         let mut state = xkb::State::new(&keymap);
         for key in &internal.pressed_keys {
-            state.update_key(*key, xkb::KeyDirection::Down);
+            // Offset the keycode by 8, as the evdev XKB rules reflect X's
+            // broken keycode system, which starts at 8.
+            state.update_key(*key + 8, xkb::KeyDirection::Down);
         }
 
+        // carry over locked modifiers and layout, modifier indices may differ between keymaps
+        let mut locked_mods = 0;
+        for name in [xkb::MOD_NAME_CAPS, xkb::MOD_NAME_NUM] {
+            let index = keymap.mod_get_index(&name);
+            if index != xkb::MOD_INVALID && internal.state.mod_name_is_active(&name, xkb::STATE_MODS_LOCKED) {
+                locked_mods |= 1 << index;
+            }
+        }
+        let locked_layout = internal.state.serialize_layout(xkb::STATE_LAYOUT_LOCKED);
+        let locked_layout = if locked_layout < keymap.num_layouts() {
+            locked_layout
+        } else {
+            0
+        };
+        let depressed_mods = state.serialize_mods(xkb::STATE_MODS_DEPRESSED);
+        let latched_mods = state.serialize_mods(xkb::STATE_MODS_LATCHED);
+        let depressed_layout = state.serialize_layout(xkb::STATE_LAYOUT_DEPRESSED);
+        let latched_layout = state.serialize_layout(xkb::STATE_LAYOUT_LATCHED);
+        state.update_mask(
+            depressed_mods,
+            latched_mods,
+            locked_mods,
+            depressed_layout,
+            latched_layout,
+            locked_layout,
+        );
+
+        let old_layout = internal.mods_state.serialized.layout_effective;
         internal.mods_state.update_with(&state);
         internal.state = state;
         internal.keymap = keymap.clone();
+        info!(name = internal.keymap.layouts().next(), "Loaded Keymap");
+
+        // clients have to know about the new keymap before receiving modifiers referring to it
+        #[cfg(feature = "wayland_frontend")]
+        self.change_keymap(keymap);
 
         let mods = internal.mods_state;
         let seat = self.get_seat(data);
         if let Some((focus, _)) = internal.focus.as_mut() {
             focus.modifiers(&seat, data, mods, SERIAL_COUNTER.next_serial());
         };
+        if mods.serialized.layout_effective != old_layout {
+            data.keyboard_layout_changed(&seat, mods.serialized.layout_effective);
+        }
+    }
 
-        #[cfg(feature = "wayland_frontend")]
-        self.change_keymap(keymap);
+    /// Get the index of the currently active layout
+    ///
+    /// The index refers to the layouts of the current keymap, see [`KeyboardHandle::layout_names`].
+    pub fn active_layout(&self) -> u32 {
+        self.arc
+            .internal
+            .lock()
+            .unwrap()
+            .mods_state
+            .serialized
+            .layout_effective
+    }
 
-        Ok(())
+    /// Get the names of the layouts of the current keymap
+    pub fn layout_names(&self) -> Vec<String> {
+        self.arc
+            .internal
+            .lock()
+            .unwrap()
+            .keymap
+            .layouts()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Switch to the layout with the given index
+    ///
+    /// The layout is locked, as if it was switched to using the keyboard. Indices not
+    /// referring to a layout of the current keymap are ignored.
+    pub fn set_layout(&self, data: &mut D, layout: u32) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        let internal = &mut *guard;
+        if layout >= internal.keymap.num_layouts()
+            || layout == internal.mods_state.serialized.layout_effective
+        {
+            return;
+        }
+
+        let mods = internal.mods_state.serialized;
+        internal
+            .state
+            .update_mask(mods.depressed, mods.latched, mods.locked, 0, 0, layout);
+        internal.mods_state.update_with(&internal.state);
+
+        let mods = internal.mods_state;
+        let seat = self.get_seat(data);
+        if let Some((focus, _)) = internal.focus.as_mut() {
+            focus.modifiers(&seat, data, mods, SERIAL_COUNTER.next_serial());
+        };
+        data.keyboard_layout_changed(&seat, mods.serialized.layout_effective);
     }
 
     /// Change the current grab on this keyboard to the provided grab
@@ -499,6 +776,13 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     /// [`FilterResult::Intercept`] a value can be passed to be returned by the whole function.
     /// This mechanism can be used to implement compositor-level key bindings for example.
     ///
+    /// If accessibility filters are enabled (see [`KeyboardHandle::set_accessibility_config`]),
+    /// the keystroke might be dropped or delayed before reaching `filter`. A key press delayed by
+    /// slow keys is processed along with the next keystroke once accepted. `filter` is only
+    /// invoked for the given keystroke, the delayed press is forwarded as is.
+    /// [`KeyboardHandle::input_from_device`] also passes delayed presses to `filter`, while
+    /// [`KeyboardHandle::input_with_repeat`] accepts a held key with a timer.
+    ///
     /// The module [`crate::wayland::seat::keysyms`] exposes definitions of all possible keysyms
     /// to be compared against. This includes non-character keysyms, such as XF86 special keys.
     #[instrument(level = "trace", parent = &self.arc.span, skip(self, data, filter))]
@@ -513,10 +797,172 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
     ) -> Option<T>
     where
         F: FnOnce(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
+    {
+        let mut filter = Some(filter);
+        self.input_inner(
+            data,
+            None,
+            keycode,
+            state,
+            serial,
+            time,
+            false,
+            |data, modifiers, handle| match filter.take() {
+                Some(filter) => filter(data, modifiers, handle),
+                None => FilterResult::Forward,
+            },
+        )
+    }
+
+    /// Handle a keystroke of a specific physical keyboard
+    ///
+    /// This behaves like [`KeyboardHandle::input`], but additionally identifies the keyboard the
+    /// keystroke originates from, e.g. by the [`id`](crate::backend::input::Device::id) of the
+    /// input device. If the [`DeviceStatePolicy::PerDevice`] policy is set, the keystroke only
+    /// affects the state of this keyboard, otherwise the device is ignored.
+    ///
+    /// Unlike [`KeyboardHandle::input`], `filter` is also invoked for key presses delayed by slow
+    /// keys, before the given keystroke. These can be told apart by their
+    /// [raw keycode](KeysymHandle::raw_code).
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(level = "trace", parent = &self.arc.span, skip(self, data, filter))]
+    pub fn input_from_device<T, F>(
+        &self,
+        data: &mut D,
+        device: &str,
+        keycode: u32,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+        filter: F,
+    ) -> Option<T>
+    where
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
+    {
+        self.input_inner(data, Some(device), keycode, state, serial, time, true, filter)
+    }
+
+    // `filter_delayed` controls if `filter` is invoked for keystrokes delayed by the accessibility
+    // filters, otherwise these are forwarded and `filter` is only invoked for the given keystroke
+    #[allow(clippy::too_many_arguments)]
+    fn input_inner<T, F>(
+        &self,
+        data: &mut D,
+        device: Option<&str>,
+        keycode: u32,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+        filter_delayed: bool,
+        mut filter: F,
+    ) -> Option<T>
+    where
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
     {
         trace!("Handling keystroke");
         let mut guard = self.arc.internal.lock().unwrap();
-        let mods_changed = guard.key_input(keycode, state);
+        let mut events = Vec::new();
+        let keys = guard.a11y.filter_key(keycode, state, time, &mut events);
+        if !events.is_empty() {
+            let seat = self.get_seat(data);
+            for event in events {
+                data.keyboard_accessibility_changed(&seat, event);
+            }
+        }
+
+        let mut result = None;
+        let count = keys.len();
+        for (idx, (keycode, state)) in keys.into_iter().enumerate() {
+            // delayed keystrokes are processed before the current one and get a serial of their own
+            let delayed = idx + 1 != count;
+            let serial = if delayed {
+                SERIAL_COUNTER.next_serial()
+            } else {
+                serial
+            };
+            let val = if delayed && !filter_delayed {
+                let mut forward = |_: &mut D, _: &ModifiersState, _: KeysymHandle<'_>| FilterResult::Forward;
+                self.process_key(
+                    data,
+                    &mut guard,
+                    device,
+                    keycode,
+                    state,
+                    serial,
+                    time,
+                    &mut forward,
+                )
+            } else {
+                self.process_key(
+                    data,
+                    &mut guard,
+                    device,
+                    keycode,
+                    state,
+                    serial,
+                    time,
+                    &mut filter,
+                )
+            };
+            if let Some(val) = val {
+                result = Some(val);
+            }
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_key<T, F>(
+        &self,
+        data: &mut D,
+        guard: &mut KbdInternal<D>,
+        device: Option<&str>,
+        keycode: u32,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+        filter: &mut F,
+    ) -> Option<T>
+    where
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
+    {
+        let old_layout = guard.mods_state.serialized.layout_effective;
+        let old_depressed = guard.state.serialize_mods(xkb::STATE_MODS_DEPRESSED);
+        let mut mods_changed = guard.key_input(device, keycode, state);
+
+        let mut events = Vec::new();
+        if state == KeyState::Pressed {
+            // Offset the keycode by 8, as the evdev XKB rules reflect X's
+            // broken keycode system, which starts at 8.
+            let sym = guard.state.key_get_one_sym(keycode + 8);
+            if guard
+                .a11y
+                .track_shift(sym == keysyms::KEY_Shift_L || sym == keysyms::KEY_Shift_R)
+            {
+                events.push(AccessibilityEvent::ConfigChanged(guard.a11y.config));
+            }
+        }
+        let activated = guard.state.serialize_mods(xkb::STATE_MODS_DEPRESSED) & !old_depressed;
+        let sticky_changed = guard.a11y.sticky_key(keycode, state, activated);
+        if sticky_changed || guard.sticky_mods_outdated() {
+            guard.apply_sticky_mods();
+            mods_changed = true;
+            events.push(AccessibilityEvent::StickyModifiersChanged {
+                latched: guard.a11y.sticky_latched,
+                locked: guard.a11y.sticky_locked,
+            });
+        }
+
+        if !events.is_empty() || guard.mods_state.serialized.layout_effective != old_layout {
+            let seat = self.get_seat(data);
+            if guard.mods_state.serialized.layout_effective != old_layout {
+                data.keyboard_layout_changed(&seat, guard.mods_state.serialized.layout_effective);
+            }
+            for event in events {
+                data.keyboard_accessibility_changed(&seat, event);
+            }
+        }
+
         let key_handle = KeysymHandle {
             // Offset the keycode by 8, as the evdev XKB rules reflect X's
             // broken keycode system, which starts at 8.
@@ -548,6 +994,165 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         None
     }
 
+    /// Handle a keystroke and repeat it while the key is held
+    ///
+    /// This behaves like [`KeyboardHandle::input`], but additionally inserts a timer into the
+    /// given event loop when a key, that repeats according to the keymap, is pressed. After the
+    /// repeat delay of this keyboard, `filter` is invoked again for the held key at the configured
+    /// repeat rate. Values intercepted by the filter for repeated events are passed to `on_repeat`.
+    ///
+    /// Repeated events forwarded by the filter are not sent to clients, as wayland clients
+    /// implement key repeat themselves based on the repeat info of the keyboard.
+    ///
+    /// If a key press is delayed by slow keys, the timer instead accepts the held key once the
+    /// delay elapsed. The press is then processed like any other keystroke, with values intercepted
+    /// by the filter passed to `on_repeat`, and the key repeats afterwards.
+    ///
+    /// Repetition stops once the key is released, another key is pressed, the keyboard focus
+    /// changes or the keymap is changed. The timer then removes itself from the event loop,
+    /// the next time it fires.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(level = "trace", parent = &self.arc.span, skip(self, data, loop_handle, filter, on_repeat))]
+    pub fn input_with_repeat<T, F, R>(
+        &self,
+        data: &mut D,
+        loop_handle: &LoopHandle<'static, D>,
+        keycode: u32,
+        state: KeyState,
+        serial: Serial,
+        time: u32,
+        mut filter: F,
+        mut on_repeat: R,
+    ) -> Option<T>
+    where
+        T: 'static,
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T> + 'static,
+        R: FnMut(&mut D, T) + 'static,
+    {
+        let result = self.input_inner(data, None, keycode, state, serial, time, true, &mut filter);
+
+        let mut guard = self.arc.internal.lock().unwrap();
+        let timer = match state {
+            KeyState::Released => {
+                if guard.repeat.map(|repeat| repeat.keycode) == Some(keycode) {
+                    guard.cancel_repeat();
+                }
+                None
+            }
+            KeyState::Pressed => match guard.a11y.slow_key_delay(keycode, time) {
+                Some(delay) => {
+                    guard.cancel_repeat();
+                    let stage = KeyTimer::SlowKey {
+                        pressed_at: time,
+                        delay,
+                    };
+                    Some((stage, Duration::from_millis(delay as u64)))
+                }
+                None => guard
+                    .start_repeat(keycode)
+                    .map(|(repeat, delay)| (KeyTimer::Repeat(repeat), delay)),
+            },
+        };
+        drop(guard);
+
+        if let Some((mut stage, delay)) = timer {
+            let keyboard = Arc::downgrade(&self.arc);
+            let timer = loop_handle.insert_source(Timer::from_duration(delay), move |_, _, data| {
+                let keyboard = match keyboard.upgrade() {
+                    Some(arc) => KeyboardHandle { arc },
+                    None => return TimeoutAction::Drop,
+                };
+                let (intercepted, next) = keyboard.key_timer_expired(data, keycode, &mut stage, &mut filter);
+                if let Some(val) = intercepted {
+                    on_repeat(data, val);
+                }
+                match next {
+                    Some(duration) => TimeoutAction::ToDuration(duration),
+                    None => TimeoutAction::Drop,
+                }
+            });
+            if let Err(err) = timer {
+                warn!(?err, "Failed to insert key repeat timer");
+                self.arc.internal.lock().unwrap().cancel_repeat();
+            }
+        }
+
+        result
+    }
+
+    // handle an expired timer of `input_with_repeat`,
+    // returns the value intercepted by the filter and the time until the timer fires again
+    fn key_timer_expired<T, F>(
+        &self,
+        data: &mut D,
+        keycode: u32,
+        stage: &mut KeyTimer,
+        filter: &mut F,
+    ) -> (Option<T>, Option<Duration>)
+    where
+        F: FnMut(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
+    {
+        let mut guard = self.arc.internal.lock().unwrap();
+        match *stage {
+            KeyTimer::SlowKey { pressed_at, delay } => {
+                let mut events = Vec::new();
+                if !guard.a11y.accept_slow_key(keycode, pressed_at, &mut events) {
+                    return (None, None);
+                }
+                let seat = self.get_seat(data);
+                for event in events {
+                    data.keyboard_accessibility_changed(&seat, event);
+                }
+
+                let time = pressed_at.wrapping_add(delay);
+                let serial = SERIAL_COUNTER.next_serial();
+                let intercepted = self.process_key(
+                    data,
+                    &mut guard,
+                    None,
+                    keycode,
+                    KeyState::Pressed,
+                    serial,
+                    time,
+                    filter,
+                );
+
+                // the accepted key repeats like any other held key
+                let next = guard.start_repeat(keycode).map(|(repeat, delay)| {
+                    *stage = KeyTimer::Repeat(repeat);
+                    delay
+                });
+                (intercepted, next)
+            }
+            KeyTimer::Repeat(repeat) => {
+                // the repeat was cancelled or replaced by another key
+                if guard.repeat != Some(repeat) {
+                    return (None, None);
+                }
+                if !guard.pressed_keys.contains(&keycode) || guard.repeat_rate <= 0 {
+                    guard.cancel_repeat();
+                    return (None, None);
+                }
+
+                let key_handle = KeysymHandle {
+                    keycode: keycode + 8,
+                    state: &guard.state,
+                    keymap: &guard.keymap,
+                };
+                trace!(
+                    sym = xkb::keysym_get_name(key_handle.modified_sym()),
+                    "Repeating key"
+                );
+                let intercepted = match filter(data, &guard.mods_state, key_handle) {
+                    FilterResult::Intercept(val) => Some(val),
+                    FilterResult::Forward => None,
+                };
+                let interval = Duration::from_micros(1_000_000 / guard.repeat_rate as u64);
+                (intercepted, Some(interval))
+            }
+        }
+    }
+
     /// Set the current focus of this keyboard
     ///
     /// If the new focus is different from the previous one, any previous focus
@@ -595,6 +1200,28 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         self.arc.internal.lock().unwrap().focus.is_some()
     }
 
+    /// Get the accessibility configuration of this keyboard
+    pub fn accessibility_config(&self) -> AccessibilityConfig {
+        self.arc.internal.lock().unwrap().a11y.config
+    }
+
+    /// Change the accessibility configuration of this keyboard
+    ///
+    /// Modifiers latched or locked through sticky keys are released if sticky keys get disabled.
+    #[instrument(parent = &self.arc.span, skip(self, data))]
+    pub fn set_accessibility_config(&self, data: &mut D, config: AccessibilityConfig) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.a11y.set_config(config);
+        if guard.sticky_mods_outdated() {
+            guard.apply_sticky_mods();
+            let mods = guard.mods_state;
+            let seat = self.get_seat(data);
+            if let Some((focus, _)) = guard.focus.as_mut() {
+                focus.modifiers(&seat, data, mods, SERIAL_COUNTER.next_serial());
+            }
+        }
+    }
+
     /// Change the repeat info configured for this keyboard
     #[instrument(parent = &self.arc.span, skip(self))]
     pub fn change_repeat_info(&self, rate: i32, delay: i32) {
@@ -607,6 +1234,64 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         }
     }
 
+    /// Get the policy deciding how the state of multiple physical keyboards is tracked
+    pub fn device_state_policy(&self) -> DeviceStatePolicy {
+        self.arc.internal.lock().unwrap().devices.policy
+    }
+
+    /// Change the policy deciding how the state of multiple physical keyboards is tracked
+    ///
+    /// Keystrokes have to be passed to [`KeyboardHandle::input_from_device`] for the state
+    /// to be tracked per device. Changing the policy forgets the state of all devices.
+    #[instrument(parent = &self.arc.span, skip(self))]
+    pub fn set_device_state_policy(&self, policy: DeviceStatePolicy) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.devices.policy = policy;
+        guard.devices.reset();
+    }
+
+    /// Get the index of the active layout of a physical keyboard
+    ///
+    /// Returns `None` unless the [`DeviceStatePolicy::PerDevice`] policy is set and the keyboard
+    /// was used before.
+    pub fn device_layout(&self, device: &str) -> Option<u32> {
+        self.arc.internal.lock().unwrap().devices.layout(device)
+    }
+
+    /// Switch a physical keyboard to the layout with the given index
+    ///
+    /// The layout is locked on this keyboard only, and presented to clients right away.
+    /// Does nothing unless the [`DeviceStatePolicy::PerDevice`] policy is set. Indices not
+    /// referring to a layout of the current keymap are ignored.
+    pub fn set_device_layout(&self, data: &mut D, device: &str, layout: u32) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        let internal = &mut *guard;
+        if internal.devices.policy != DeviceStatePolicy::PerDevice || layout >= internal.keymap.num_layouts()
+        {
+            return;
+        }
+
+        let old_layout = internal.mods_state.serialized.layout_effective;
+        let merged = internal.devices.set_layout(&internal.keymap, device, layout);
+        if !internal.apply_merged_state(merged) {
+            return;
+        }
+
+        let mods = internal.mods_state;
+        let seat = self.get_seat(data);
+        if let Some((focus, _)) = internal.focus.as_mut() {
+            focus.modifiers(&seat, data, mods, SERIAL_COUNTER.next_serial());
+        }
+        if mods.serialized.layout_effective != old_layout {
+            data.keyboard_layout_changed(&seat, mods.serialized.layout_effective);
+        }
+    }
+
+    /// Forget the state of a physical keyboard, e.g. because it was unplugged
+    pub fn remove_device(&self, device: &str) {
+        self.arc.internal.lock().unwrap().devices.remove(device);
+    }
+
     fn get_seat(&self, data: &mut D) -> Seat<D> {
         let seat_state = data.seat_state();
         seat_state
@@ -757,6 +1442,9 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
             .unwrap_or(false);
 
         if !same {
+            // held keys no longer repeat for the new focus
+            self.inner.cancel_repeat();
+
             // unset old focus
             if let Some((focus, _)) = self.inner.focus.as_mut() {
                 focus.leave(self.seat, data, serial);
@@ -43,3 +43,37 @@ impl<'a> XkbConfig<'a> {
         .ok_or(())
     }
 }
+
+/// Owned copy of an [`XkbConfig`], kept to recompile the keymap on reload
+#[derive(Clone, Debug, Default)]
+pub(super) struct OwnedXkbConfig {
+    rules: String,
+    model: String,
+    layout: String,
+    variant: String,
+    options: Option<String>,
+}
+
+impl OwnedXkbConfig {
+    pub(super) fn as_config(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            rules: &self.rules,
+            model: &self.model,
+            layout: &self.layout,
+            variant: &self.variant,
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<'a> From<&XkbConfig<'a>> for OwnedXkbConfig {
+    fn from(config: &XkbConfig<'a>) -> Self {
+        OwnedXkbConfig {
+            rules: config.rules.to_owned(),
+            model: config.model.to_owned(),
+            layout: config.layout.to_owned(),
+            variant: config.variant.to_owned(),
+            options: config.options.clone(),
+        }
+    }
+}
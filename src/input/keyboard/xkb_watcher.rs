@@ -0,0 +1,205 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    InsertError, LoopHandle, RegistrationToken,
+};
+use tracing::debug;
+
+// Environment variables affecting the compilation of keymaps by libxkbcommon
+const XKB_ENV_VARS: &[&str] = &[
+    "XKB_DEFAULT_RULES",
+    "XKB_DEFAULT_MODEL",
+    "XKB_DEFAULT_LAYOUT",
+    "XKB_DEFAULT_VARIANT",
+    "XKB_DEFAULT_OPTIONS",
+    "XKB_CONFIG_ROOT",
+    "XKB_CONFIG_EXTRA_PATH",
+    "XDG_CONFIG_HOME",
+    "HOME",
+];
+
+// Limits the recursion into the watched directories, in case of symlink loops
+const MAX_DEPTH: usize = 8;
+
+/// Watcher detecting changes to the xkb configuration of the user
+///
+/// The watcher periodically checks the xkb configuration directories of the user and the
+/// `XKB_DEFAULT_*` and related environment variables of the compositor for changes. Once a
+/// change is detected, the keymaps should be rebuilt through
+/// [`KeyboardHandle::reload_keymap`](super::KeyboardHandle::reload_keymap), e.g.:
+///
+/// ```ignore
+/// XkbConfigWatcher::new().insert_into(&loop_handle, Duration::from_secs(1), |state| {
+///     let keyboard = state.seat.get_keyboard().unwrap();
+///     if let Err(err) = keyboard.reload_keymap(state) {
+///         warn!(?err, "Failed to reload keymap");
+///     }
+/// })?;
+/// ```
+///
+/// The system wide xkb data (`XKB_CONFIG_ROOT`) is not watched, as it is only expected to
+/// change on system updates.
+#[derive(Debug)]
+pub struct XkbConfigWatcher {
+    paths: Vec<PathBuf>,
+    snapshot: Snapshot,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Snapshot {
+    env: Vec<Option<OsString>>,
+    files: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+impl XkbConfigWatcher {
+    /// Create a watcher for the default xkb configuration directories
+    ///
+    /// These are the directories libxkbcommon includes user configuration from, i.e.
+    /// `$XDG_CONFIG_HOME/xkb` (or `$HOME/.config/xkb`), `$HOME/.xkb` and `$XKB_CONFIG_EXTRA_PATH`
+    /// (or `/etc/xkb`).
+    pub fn new() -> Self {
+        XkbConfigWatcher::with_paths(default_paths())
+    }
+
+    /// Create a watcher for the given files and directories
+    ///
+    /// Directories are watched recursively. Paths are not required to exist, their creation
+    /// is detected as a change as well.
+    pub fn with_paths(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let paths = paths.into_iter().collect::<Vec<_>>();
+        let snapshot = Snapshot::take(&paths);
+        XkbConfigWatcher { paths, snapshot }
+    }
+
+    /// The files and directories watched
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Check for changes since the watcher was created or last checked
+    pub fn check(&mut self) -> bool {
+        let snapshot = Snapshot::take(&self.paths);
+        if snapshot != self.snapshot {
+            self.snapshot = snapshot;
+            debug!("Xkb configuration changed");
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Insert the watcher into an event loop, checking for changes in the given interval
+    ///
+    /// The `callback` is invoked whenever a change was detected.
+    pub fn insert_into<'l, D, F>(
+        mut self,
+        handle: &LoopHandle<'l, D>,
+        interval: Duration,
+        mut callback: F,
+    ) -> Result<RegistrationToken, InsertError<Timer>>
+    where
+        F: FnMut(&mut D) + 'l,
+    {
+        handle.insert_source(Timer::from_duration(interval), move |_, _, data| {
+            if self.check() {
+                callback(data);
+            }
+            TimeoutAction::ToDuration(interval)
+        })
+    }
+}
+
+impl Default for XkbConfigWatcher {
+    fn default() -> Self {
+        XkbConfigWatcher::new()
+    }
+}
+
+impl Snapshot {
+    fn take(paths: &[PathBuf]) -> Self {
+        let env = XKB_ENV_VARS.iter().map(env::var_os).collect();
+        let mut files = Vec::new();
+        for path in paths {
+            collect_files(path, 0, &mut files);
+        }
+        Snapshot { env, files }
+    }
+}
+
+fn collect_files(path: &Path, depth: usize, files: &mut Vec<(PathBuf, Option<SystemTime>, u64)>) {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    files.push((path.to_owned(), metadata.modified().ok(), metadata.len()));
+
+    if metadata.is_dir() && depth < MAX_DEPTH {
+        let mut entries = match fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        entries.sort();
+        for entry in entries {
+            collect_files(&entry, depth + 1, files);
+        }
+    }
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let mut paths = Vec::new();
+
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(config) => paths.push(PathBuf::from(config).join("xkb")),
+        None => paths.extend(home.as_ref().map(|home| home.join(".config/xkb"))),
+    }
+    paths.extend(home.map(|home| home.join(".xkb")));
+    paths.push(
+        env::var_os("XKB_CONFIG_EXTRA_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/etc/xkb")),
+    );
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_file_changes() {
+        let dir = env::temp_dir().join(format!("smithay-xkb-watcher-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut watcher = XkbConfigWatcher::with_paths([dir.clone()]);
+        assert!(!watcher.check());
+
+        fs::create_dir_all(dir.join("symbols")).unwrap();
+        assert!(watcher.check());
+        assert!(!watcher.check());
+
+        fs::write(dir.join("symbols/custom"), "xkb_symbols \"basic\" {};").unwrap();
+        assert!(watcher.check());
+        assert!(!watcher.check());
+
+        fs::write(
+            dir.join("symbols/custom"),
+            "xkb_symbols \"basic\" { include \"us\" };",
+        )
+        .unwrap();
+        assert!(watcher.check());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(watcher.check());
+    }
+}
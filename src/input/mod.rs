@@ -109,7 +109,10 @@ use self::keyboard::{Error as KeyboardError, KeyboardHandle, KeyboardTarget};
 use self::pointer::{CursorImageStatus, PointerHandle, PointerTarget};
 use crate::utils::user_data::UserDataMap;
 
+pub mod gesture;
+pub mod inject;
 pub mod keyboard;
+pub mod multiseat;
 pub mod pointer;
 
 /// Handler trait for Seats
@@ -127,6 +130,24 @@ pub trait SeatHandler: Sized {
 
     /// Callback that will be notified whenever a client requests to set a custom cursor image.
     fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+
+    /// Callback that will be notified whenever the active keyboard layout of the seat changes.
+    ///
+    /// `layout` is the index of the new layout in the current keymap, see
+    /// [`KeyboardHandle::layout_names`](keyboard::KeyboardHandle::layout_names).
+    /// The keyboard is locked during this callback, so it must not be accessed.
+    fn keyboard_layout_changed(&mut self, _seat: &Seat<Self>, _layout: u32) {}
+
+    /// Callback that will be notified whenever the accessibility state of the keyboard of the seat changes.
+    ///
+    /// See [`KeyboardHandle::set_accessibility_config`](keyboard::KeyboardHandle::set_accessibility_config).
+    /// The keyboard is locked during this callback, so it must not be accessed.
+    fn keyboard_accessibility_changed(&mut self, _seat: &Seat<Self>, _event: keyboard::AccessibilityEvent) {}
+
+    /// Callback that will be notified whenever the motion of the pointer of the seat hits a barrier.
+    ///
+    /// See [`PointerHandle::apply_barriers`](pointer::PointerHandle::apply_barriers).
+    fn pointer_barrier_hit(&mut self, _seat: &Seat<Self>, _event: &pointer::BarrierEvent) {}
 }
 /// Delegate type for all [Seat] globals.
 ///
@@ -185,7 +206,7 @@ pub(crate) struct Inner<D: SeatHandler> {
     pub(crate) keyboard: Option<KeyboardHandle<D>>,
 
     #[cfg(feature = "wayland_frontend")]
-    pub(crate) touch: Option<crate::wayland::seat::TouchHandle>,
+    pub(crate) touch: Option<crate::wayland::seat::TouchHandle<D>>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) global: Option<wayland_server::backend::GlobalId>,
     #[cfg(feature = "wayland_frontend")]
@@ -293,6 +314,13 @@ impl<D: SeatHandler> SeatState<D> {
 
         Seat { arc }
     }
+
+    /// Remove a seat
+    ///
+    /// The handles of the capabilities of a removed seat must no longer be used to send input events.
+    pub fn remove_seat(&mut self, seat: &Seat<D>) {
+        self.seats.retain(|s| s != seat);
+    }
 }
 
 impl<D: SeatHandler + 'static> Seat<D> {
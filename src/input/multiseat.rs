@@ -0,0 +1,302 @@
+//! Helpers for compositors driving multiple seats
+//!
+//! A compositor may expose several [`Seat`]s at once, each with its own set of input devices,
+//! e.g. to let multiple users work side by side. [`SeatManager`] keeps track of which input
+//! device belongs to which seat, based on a [`SeatRule`] provided for every seat, and stores
+//! focus and cursor state per seat.
+//!
+//! When seats are added or removed at runtime, devices are re-assigned according to the rules
+//! of the remaining seats. The resulting [`DeviceMigration`]s are returned to the compositor,
+//! which can use them to e.g. release keys still held on the previous seat.
+//!
+//! ```no_run
+//! # use smithay::input::{Seat, SeatState, SeatHandler, pointer::CursorImageStatus};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//! use smithay::input::multiseat::{SeatManager, SeatRule};
+//! #
+//! # struct State { seat_state: SeatState<Self> }
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { unimplemented!() }
+//! #     fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) { unimplemented!() }
+//! #     fn cursor_image(&mut self, seat: &Seat<Self>, image: CursorImageStatus) { unimplemented!() }
+//! # }
+//! # let mut seat_state: SeatState<State> = unimplemented!();
+//! # let device: smithay::reexports::input::Device = unimplemented!();
+//! let mut seats = SeatManager::new();
+//!
+//! // devices of the first udev seat, as well as all devices not claimed by another seat
+//! seats.add_seat(seat_state.new_seat("seat0"), SeatRule::Fallback);
+//! // devices explicitly tagged for a second seat
+//! seats.add_seat(seat_state.new_seat("seat1"), SeatRule::UdevSeat("seat1".into()));
+//!
+//! // when the backend reports a new device
+//! let seat = seats.add_device(device);
+//! ```
+
+use std::fmt;
+
+use crate::backend::input::{Device, Event, InputBackend};
+
+use super::{pointer::CursorImageStatus, Seat, SeatHandler};
+
+/// Rule deciding which input devices are assigned to a seat
+pub enum SeatRule<Dev> {
+    /// Assign all devices belonging to the given udev seat
+    ///
+    /// The udev seat of a device is determined by its `ID_SEAT` property, devices without
+    /// this property belong to `"seat0"`. If the `backend_udev` feature is disabled or the
+    /// device has no syspath, the device is considered to belong to `"seat0"`.
+    UdevSeat(String),
+    /// Assign all devices for which the callback returns `true`
+    Matching(Box<dyn Fn(&Dev) -> bool>),
+    /// Assign all devices that are not claimed by any other seat
+    Fallback,
+}
+
+impl<Dev> fmt::Debug for SeatRule<Dev> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeatRule::UdevSeat(name) => f.debug_tuple("SeatRule::UdevSeat").field(name).finish(),
+            SeatRule::Matching(_) => f.debug_tuple("SeatRule::Matching").field(&"...").finish(),
+            SeatRule::Fallback => f.debug_tuple("SeatRule::Fallback").finish(),
+        }
+    }
+}
+
+impl<Dev: Device> SeatRule<Dev> {
+    fn matches(&self, device: &Dev) -> bool {
+        match self {
+            SeatRule::UdevSeat(name) => udev_seat(device) == *name,
+            SeatRule::Matching(callback) => callback(device),
+            SeatRule::Fallback => false,
+        }
+    }
+}
+
+#[cfg(feature = "backend_udev")]
+fn udev_seat<Dev: Device>(device: &Dev) -> String {
+    device
+        .syspath()
+        .and_then(|path| crate::backend::udev::device_seat(path).ok())
+        .unwrap_or_else(|| String::from("seat0"))
+}
+
+#[cfg(not(feature = "backend_udev"))]
+fn udev_seat<Dev: Device>(_device: &Dev) -> String {
+    String::from("seat0")
+}
+
+/// An input device that was moved from one seat to another
+pub struct DeviceMigration<D: SeatHandler, Dev> {
+    /// The device that was moved
+    pub device: Dev,
+    /// The seat the device was previously assigned to, if any
+    pub from: Option<Seat<D>>,
+    /// The seat the device is now assigned to, if any
+    pub to: Option<Seat<D>>,
+}
+
+impl<D: SeatHandler + 'static, Dev: fmt::Debug> fmt::Debug for DeviceMigration<D, Dev> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceMigration")
+            .field("device", &self.device)
+            .field("from", &self.from.as_ref().map(|s| s.name()))
+            .field("to", &self.to.as_ref().map(|s| s.name()))
+            .finish()
+    }
+}
+
+struct ManagedSeat<D: SeatHandler, Dev> {
+    seat: Seat<D>,
+    rule: SeatRule<Dev>,
+    focus: Option<<D as SeatHandler>::KeyboardFocus>,
+    cursor_image: CursorImageStatus,
+}
+
+/// Manager assigning input devices to multiple seats
+///
+/// See the [module-level documentation](self) for details.
+pub struct SeatManager<D: SeatHandler, Dev> {
+    seats: Vec<ManagedSeat<D, Dev>>,
+    devices: Vec<(Dev, Option<Seat<D>>)>,
+}
+
+impl<D: SeatHandler + 'static, Dev: fmt::Debug> fmt::Debug for SeatManager<D, Dev> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeatManager")
+            .field(
+                "seats",
+                &self
+                    .seats
+                    .iter()
+                    .map(|s| (s.seat.name(), &s.rule))
+                    .collect::<Vec<_>>(),
+            )
+            .field(
+                "devices",
+                &self
+                    .devices
+                    .iter()
+                    .map(|(device, seat)| (device, seat.as_ref().map(|s| s.name())))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<D: SeatHandler + 'static, Dev: Device + Clone> Default for SeatManager<D, Dev> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: SeatHandler + 'static, Dev: Device + Clone> SeatManager<D, Dev> {
+    /// Create a new manager without any seats or devices
+    pub fn new() -> Self {
+        SeatManager {
+            seats: Vec::new(),
+            devices: Vec::new(),
+        }
+    }
+
+    /// Add a seat to this manager
+    ///
+    /// Seats added earlier take precedence if the rules of several seats match a device.
+    /// Devices matching the rule of the new seat are moved to it, the returned list contains
+    /// all devices that changed their seat.
+    pub fn add_seat(&mut self, seat: Seat<D>, rule: SeatRule<Dev>) -> Vec<DeviceMigration<D, Dev>> {
+        self.seats.retain(|s| s.seat != seat);
+        self.seats.push(ManagedSeat {
+            seat,
+            rule,
+            focus: None,
+            cursor_image: CursorImageStatus::Default,
+        });
+        self.reassign_devices()
+    }
+
+    /// Remove a seat from this manager
+    ///
+    /// The devices of the removed seat are re-assigned to the remaining seats, the returned
+    /// list contains all devices that changed their seat.
+    pub fn remove_seat(&mut self, seat: &Seat<D>) -> Vec<DeviceMigration<D, Dev>> {
+        self.seats.retain(|s| s.seat != *seat);
+        self.reassign_devices()
+    }
+
+    /// Iterate over the seats of this manager
+    pub fn seats(&self) -> impl Iterator<Item = &Seat<D>> {
+        self.seats.iter().map(|s| &s.seat)
+    }
+
+    /// Add a new input device
+    ///
+    /// Returns the seat the device was assigned to, if any.
+    pub fn add_device(&mut self, device: Dev) -> Option<Seat<D>> {
+        let seat = self.seat_for_rules(&device);
+        self.devices.retain(|(d, _)| *d != device);
+        self.devices.push((device, seat.clone()));
+        seat
+    }
+
+    /// Remove an input device
+    ///
+    /// Returns the seat the device was assigned to, if any.
+    pub fn remove_device(&mut self, device: &Dev) -> Option<Seat<D>> {
+        let idx = self.devices.iter().position(|(d, _)| d == device)?;
+        self.devices.remove(idx).1
+    }
+
+    /// Returns the seat a device is assigned to, if any
+    pub fn seat_for_device(&self, device: &Dev) -> Option<&Seat<D>> {
+        self.devices
+            .iter()
+            .find(|(d, _)| d == device)
+            .and_then(|(_, seat)| seat.as_ref())
+    }
+
+    /// Returns the seat the device that generated an input event is assigned to, if any
+    pub fn seat_for_event<B, E>(&self, event: &E) -> Option<&Seat<D>>
+    where
+        B: InputBackend<Device = Dev>,
+        E: Event<B>,
+    {
+        self.seat_for_device(&event.device())
+    }
+
+    /// Iterate over the devices assigned to a seat
+    pub fn devices<'a>(&'a self, seat: &'a Seat<D>) -> impl Iterator<Item = &'a Dev> + 'a {
+        self.devices
+            .iter()
+            .filter(move |(_, s)| s.as_ref() == Some(seat))
+            .map(|(d, _)| d)
+    }
+
+    /// Iterate over the devices not assigned to any seat
+    pub fn unassigned_devices(&self) -> impl Iterator<Item = &Dev> {
+        self.devices.iter().filter(|(_, s)| s.is_none()).map(|(d, _)| d)
+    }
+
+    /// Record the keyboard focus of a seat
+    ///
+    /// This is meant to be called from [`SeatHandler::focus_changed`].
+    pub fn focus_changed(&mut self, seat: &Seat<D>, focused: Option<&<D as SeatHandler>::KeyboardFocus>) {
+        if let Some(managed) = self.seats.iter_mut().find(|s| s.seat == *seat) {
+            managed.focus = focused.cloned();
+        }
+    }
+
+    /// Returns the last recorded keyboard focus of a seat
+    pub fn focus(&self, seat: &Seat<D>) -> Option<&<D as SeatHandler>::KeyboardFocus> {
+        self.seats
+            .iter()
+            .find(|s| s.seat == *seat)
+            .and_then(|s| s.focus.as_ref())
+    }
+
+    /// Record the cursor image of a seat
+    ///
+    /// This is meant to be called from [`SeatHandler::cursor_image`].
+    pub fn set_cursor_image(&mut self, seat: &Seat<D>, image: CursorImageStatus) {
+        if let Some(managed) = self.seats.iter_mut().find(|s| s.seat == *seat) {
+            managed.cursor_image = image;
+        }
+    }
+
+    /// Returns the last recorded cursor image of a seat
+    ///
+    /// Seats default to [`CursorImageStatus::Default`].
+    pub fn cursor_image(&self, seat: &Seat<D>) -> Option<&CursorImageStatus> {
+        self.seats
+            .iter()
+            .find(|s| s.seat == *seat)
+            .map(|s| &s.cursor_image)
+    }
+
+    fn seat_for_rules(&self, device: &Dev) -> Option<Seat<D>> {
+        self.seats
+            .iter()
+            .find(|s| s.rule.matches(device))
+            .or_else(|| self.seats.iter().find(|s| matches!(s.rule, SeatRule::Fallback)))
+            .map(|s| s.seat.clone())
+    }
+
+    fn reassign_devices(&mut self) -> Vec<DeviceMigration<D, Dev>> {
+        let mut migrations = Vec::new();
+        for idx in 0..self.devices.len() {
+            let seat = self.seat_for_rules(&self.devices[idx].0);
+            let (device, current) = &mut self.devices[idx];
+            if *current != seat {
+                let from = std::mem::replace(current, seat.clone());
+                migrations.push(DeviceMigration {
+                    device: device.clone(),
+                    from,
+                    to: seat,
+                });
+            }
+        }
+        migrations
+    }
+}
@@ -0,0 +1,408 @@
+use crate::utils::{Logical, Point};
+
+bitflags::bitflags! {
+    /// Directions of pointer motion a [`Barrier`] applies to
+    ///
+    /// Positive directions point towards increasing coordinates, i.e. left-to-right motion
+    /// crossing a vertical barrier or top-to-bottom motion crossing a horizontal barrier.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct BarrierDirections: u32 {
+        /// Motion towards increasing coordinates
+        const POSITIVE = 1;
+        /// Motion towards decreasing coordinates
+        const NEGATIVE = 2;
+        /// Motion in both directions
+        const BOTH = Self::POSITIVE.bits() | Self::NEGATIVE.bits();
+    }
+}
+
+/// How a [`Barrier`] affects the pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BarrierKind {
+    /// The pointer can not cross the barrier
+    ///
+    /// Motion crossing the barrier is stopped in front of it, the part of the motion parallel to
+    /// the barrier is retained, so the pointer slides along the barrier.
+    Blocking,
+    /// The pointer crosses the barrier unhindered, crossing it is only reported
+    Passive,
+}
+
+/// A pointer barrier
+///
+/// Barriers are horizontal or vertical line segments in the global compositor space, which stop
+/// or report pointer motion crossing them. The line is placed at the boundary between two logical
+/// pixels, a vertical barrier at `x` separates the pointer locations left of `x` from those at or
+/// right of `x`. The pointer stopped by a barrier is kept one logical pixel left of the barrier,
+/// or at its coordinate, depending on the direction of the motion.
+///
+/// Can be used with the builder pattern, e.g.:
+///
+/// ```ignore
+/// Barrier::vertical(1920.0, 0.0, 1080.0)
+///     .kind(BarrierKind::Passive)
+///     .directions(BarrierDirections::POSITIVE);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Barrier {
+    vertical: bool,
+    position: f64,
+    start: f64,
+    end: f64,
+    kind: BarrierKind,
+    directions: BarrierDirections,
+}
+
+impl Barrier {
+    /// Create a new blocking vertical barrier at `x`, spanning from `y1` to `y2`
+    pub fn vertical(x: f64, y1: f64, y2: f64) -> Self {
+        Barrier {
+            vertical: true,
+            position: x,
+            start: y1.min(y2),
+            end: y1.max(y2),
+            kind: BarrierKind::Blocking,
+            directions: BarrierDirections::BOTH,
+        }
+    }
+
+    /// Create a new blocking horizontal barrier at `y`, spanning from `x1` to `x2`
+    pub fn horizontal(y: f64, x1: f64, x2: f64) -> Self {
+        Barrier {
+            vertical: false,
+            ..Barrier::vertical(y, x1, x2)
+        }
+    }
+
+    /// Set how the barrier affects the pointer
+    pub fn kind(mut self, kind: BarrierKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Restrict the directions of motion the barrier applies to
+    ///
+    /// Motion in any other direction crosses the barrier unhindered and unreported.
+    pub fn directions(mut self, directions: BarrierDirections) -> Self {
+        self.directions = directions;
+        self
+    }
+
+    /// Returns `true` if this is a vertical barrier
+    pub fn is_vertical(&self) -> bool {
+        self.vertical
+    }
+
+    /// Start point of the barrier, in the global compositor space
+    pub fn start(&self) -> Point<f64, Logical> {
+        self.point(self.position, self.start)
+    }
+
+    /// End point of the barrier, in the global compositor space
+    pub fn end(&self) -> Point<f64, Logical> {
+        self.point(self.position, self.end)
+    }
+
+    /// How the barrier affects the pointer
+    pub fn barrier_kind(&self) -> BarrierKind {
+        self.kind
+    }
+
+    /// Directions of motion the barrier applies to
+    pub fn barrier_directions(&self) -> BarrierDirections {
+        self.directions
+    }
+
+    // Converts (coordinate across, coordinate along) the barrier into a point
+    fn point(&self, across: f64, along: f64) -> Point<f64, Logical> {
+        if self.vertical {
+            (across, along).into()
+        } else {
+            (along, across).into()
+        }
+    }
+
+    // Splits a point into (coordinate across, coordinate along) the barrier
+    fn split(&self, point: Point<f64, Logical>) -> (f64, f64) {
+        if self.vertical {
+            (point.x, point.y)
+        } else {
+            (point.y, point.x)
+        }
+    }
+
+    /// Returns the fraction of the motion at which it crosses the barrier and its direction
+    fn crossing(
+        &self,
+        from: Point<f64, Logical>,
+        to: Point<f64, Logical>,
+    ) -> Option<(f64, BarrierDirections)> {
+        let (from_across, from_along) = self.split(from);
+        let (to_across, to_along) = self.split(to);
+        let direction = if from_across < self.position && to_across >= self.position {
+            BarrierDirections::POSITIVE
+        } else if from_across >= self.position && to_across < self.position {
+            BarrierDirections::NEGATIVE
+        } else {
+            return None;
+        };
+
+        let t = (self.position - from_across) / (to_across - from_across);
+        let along = from_along + (to_along - from_along) * t;
+        if along < self.start || along > self.end {
+            return None;
+        }
+        Some((t, direction))
+    }
+}
+
+/// Identifier of a barrier added to a pointer
+///
+/// See [`PointerHandle::add_barrier`](super::PointerHandle::add_barrier).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarrierId(u64);
+
+/// Pointer motion hitting a barrier
+///
+/// See [`SeatHandler::pointer_barrier_hit`](crate::input::SeatHandler::pointer_barrier_hit).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarrierEvent {
+    /// The barrier that was hit
+    pub barrier: BarrierId,
+    /// Location of the pointer at the barrier, in the global compositor space
+    ///
+    /// For blocking barriers this is the location the pointer was stopped at.
+    pub location: Point<f64, Logical>,
+    /// Direction of the motion crossing the barrier
+    pub direction: BarrierDirections,
+    /// Whether the motion was stopped by the barrier
+    pub blocked: bool,
+    /// Distance the motion would have moved the pointer past the barrier
+    ///
+    /// Accumulating this value allows to implement pressure thresholds, e.g. for hot corners.
+    pub overshoot: f64,
+}
+
+#[derive(Debug)]
+struct BarrierEntry {
+    id: BarrierId,
+    barrier: Barrier,
+    released: bool,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Barriers {
+    next_id: u64,
+    entries: Vec<BarrierEntry>,
+}
+
+impl Barriers {
+    pub(super) fn add(&mut self, barrier: Barrier) -> BarrierId {
+        let id = BarrierId(self.next_id);
+        self.next_id += 1;
+        self.entries.push(BarrierEntry {
+            id,
+            barrier,
+            released: false,
+        });
+        id
+    }
+
+    pub(super) fn remove(&mut self, id: BarrierId) -> Option<Barrier> {
+        let idx = self.entries.iter().position(|entry| entry.id == id)?;
+        Some(self.entries.remove(idx).barrier)
+    }
+
+    pub(super) fn get(&self, id: BarrierId) -> Option<Barrier> {
+        self.entries
+            .iter()
+            .find(|entry| entry.id == id)
+            .map(|entry| entry.barrier)
+    }
+
+    pub(super) fn ids(&self) -> Vec<BarrierId> {
+        self.entries.iter().map(|entry| entry.id).collect()
+    }
+
+    pub(super) fn release(&mut self, id: BarrierId) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.released = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the barriers to a motion, returning the resulting location and the barriers hit
+    pub(super) fn apply(
+        &mut self,
+        from: Point<f64, Logical>,
+        to: Point<f64, Logical>,
+    ) -> (Point<f64, Logical>, Vec<BarrierEvent>) {
+        let mut position = from;
+        let mut target = to;
+        let mut handled = Vec::new();
+        let mut events = Vec::new();
+
+        // Barriers are handled in the order they are crossed, as blocking one changes the
+        // remaining motion, which may then cross others while sliding along it.
+        loop {
+            let nearest = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| !handled.contains(&entry.id))
+                .filter_map(|(idx, entry)| {
+                    entry
+                        .barrier
+                        .crossing(position, target)
+                        .map(|(t, direction)| (idx, t, direction))
+                })
+                .min_by(|(_, t1, _), (_, t2, _)| t1.total_cmp(t2));
+            let (idx, t, direction) = match nearest {
+                Some(nearest) => nearest,
+                None => break,
+            };
+
+            let entry = &self.entries[idx];
+            handled.push(entry.id);
+            if entry.released || !entry.barrier.directions.contains(direction) {
+                continue;
+            }
+
+            let barrier = entry.barrier;
+            let (position_across, _) = barrier.split(position);
+            let (target_across, target_along) = barrier.split(target);
+            let crossing_along = barrier.split(position + (target - position).upscale(t)).1;
+            let overshoot = (target_across - barrier.position).abs();
+
+            match barrier.kind {
+                BarrierKind::Passive => {
+                    events.push(BarrierEvent {
+                        barrier: entry.id,
+                        location: barrier.point(barrier.position, crossing_along),
+                        direction,
+                        blocked: false,
+                        overshoot,
+                    });
+                }
+                BarrierKind::Blocking => {
+                    let stop = if direction == BarrierDirections::POSITIVE {
+                        (barrier.position - 1.0).max(position_across)
+                    } else {
+                        barrier.position
+                    };
+                    position = barrier.point(stop, crossing_along);
+                    target = barrier.point(stop, target_along);
+                    events.push(BarrierEvent {
+                        barrier: entry.id,
+                        location: position,
+                        direction,
+                        blocked: true,
+                        overshoot,
+                    });
+                }
+            }
+        }
+
+        // a release only lasts for a single motion
+        for entry in &mut self.entries {
+            entry.released = false;
+        }
+
+        (target, events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(
+        barriers: &mut Barriers,
+        from: (f64, f64),
+        to: (f64, f64),
+    ) -> (Point<f64, Logical>, Vec<BarrierEvent>) {
+        barriers.apply(from.into(), to.into())
+    }
+
+    #[test]
+    fn blocking_barrier_stops_motion() {
+        let mut barriers = Barriers::default();
+        let id = barriers.add(Barrier::vertical(100.0, 0.0, 100.0));
+
+        let (location, events) = apply(&mut barriers, (90.0, 50.0), (110.0, 60.0));
+        assert_eq!(location, (99.0, 60.0).into());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].barrier, id);
+        assert!(events[0].blocked);
+        assert_eq!(events[0].direction, BarrierDirections::POSITIVE);
+        assert_eq!(events[0].overshoot, 10.0);
+
+        let (location, events) = apply(&mut barriers, (110.0, 50.0), (90.0, 50.0));
+        assert_eq!(location, (100.0, 50.0).into());
+        assert_eq!(events[0].direction, BarrierDirections::NEGATIVE);
+    }
+
+    #[test]
+    fn motion_outside_of_barrier() {
+        let mut barriers = Barriers::default();
+        barriers.add(Barrier::horizontal(100.0, 0.0, 100.0));
+
+        let (location, events) = apply(&mut barriers, (150.0, 90.0), (150.0, 110.0));
+        assert_eq!(location, (150.0, 110.0).into());
+        assert!(events.is_empty());
+
+        let (location, events) = apply(&mut barriers, (50.0, 50.0), (60.0, 90.0));
+        assert_eq!(location, (60.0, 90.0).into());
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn directions_and_passive_barriers() {
+        let mut barriers = Barriers::default();
+        barriers.add(Barrier::vertical(100.0, 0.0, 100.0).directions(BarrierDirections::NEGATIVE));
+
+        let (location, events) = apply(&mut barriers, (90.0, 50.0), (110.0, 50.0));
+        assert_eq!(location, (110.0, 50.0).into());
+        assert!(events.is_empty());
+
+        let mut barriers = Barriers::default();
+        let id = barriers.add(Barrier::vertical(100.0, 0.0, 100.0).kind(BarrierKind::Passive));
+        let (location, events) = apply(&mut barriers, (90.0, 50.0), (110.0, 70.0));
+        assert_eq!(location, (110.0, 70.0).into());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].barrier, id);
+        assert_eq!(events[0].location, (100.0, 60.0).into());
+        assert!(!events[0].blocked);
+    }
+
+    #[test]
+    fn sliding_into_corner() {
+        let mut barriers = Barriers::default();
+        barriers.add(Barrier::vertical(100.0, 0.0, 100.0));
+        barriers.add(Barrier::horizontal(100.0, 0.0, 100.0));
+
+        let (location, events) = apply(&mut barriers, (95.0, 95.0), (105.0, 120.0));
+        assert_eq!(location, (99.0, 99.0).into());
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn released_barrier() {
+        let mut barriers = Barriers::default();
+        let id = barriers.add(Barrier::vertical(100.0, 0.0, 100.0));
+        assert!(barriers.release(id));
+
+        let (location, events) = apply(&mut barriers, (90.0, 50.0), (110.0, 50.0));
+        assert_eq!(location, (110.0, 50.0).into());
+        assert!(events.is_empty());
+
+        let (location, _) = apply(&mut barriers, (110.0, 50.0), (90.0, 50.0));
+        assert_eq!(location, (100.0, 50.0).into());
+
+        assert!(barriers.remove(id).is_some());
+        assert!(barriers.ids().is_empty());
+    }
+}
@@ -28,7 +28,8 @@ use super::{AxisFrame, ButtonEvent, Focus, MotionEvent, PointerInnerHandle, Rela
 ///
 /// When your grab ends (either as you requested it or if it was forcefully cancelled by the server),
 /// the struct implementing this trait will be dropped. As such you should put clean-up logic in the destructor,
-/// rather than trying to guess when the grab will end.
+/// rather than trying to guess when the grab will end. Clean-up logic requiring access to the compositor
+/// state can be put into [`PointerGrab::unset`] instead.
 pub trait PointerGrab<D: SeatHandler>: Send {
     /// A motion was reported
     ///
@@ -71,6 +72,13 @@ pub trait PointerGrab<D: SeatHandler>: Send {
     fn axis(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, details: AxisFrame);
     /// The data about the event that started the grab.
     fn start_data(&self) -> &GrabStartData<D>;
+    /// The grab was ended from outside of the grab
+    ///
+    /// This is invoked when the grab is unset or replaced by another grab through the
+    /// [`PointerHandle`](super::PointerHandle), but not when the grab ends itself through
+    /// the [`PointerInnerHandle`] passed to its methods.
+    #[allow(unused_variables)]
+    fn unset(&mut self, data: &mut D) {}
 }
 
 /// Data about the event that started the grab.
@@ -6,12 +6,16 @@ use std::{
 };
 
 use crate::{
-    backend::input::{Axis, AxisSource, ButtonState},
+    backend::input::{Axis, AxisRelativeDirection, AxisSource, ButtonState},
     input::{Seat, SeatHandler},
     utils::Serial,
     utils::{IsAlive, Logical, Point},
 };
 
+mod barrier;
+use barrier::Barriers;
+pub use barrier::{Barrier, BarrierDirections, BarrierEvent, BarrierId, BarrierKind};
+
 mod cursor_image;
 pub use cursor_image::{CursorImageAttributes, CursorImageStatus, CursorImageSurfaceData};
 
@@ -249,6 +253,58 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         self.inner.lock().unwrap().location
     }
 
+    /// Add a barrier restricting the motion of this pointer
+    ///
+    /// Barriers only take effect on motion passed through [`PointerHandle::apply_barriers`].
+    pub fn add_barrier(&self, barrier: Barrier) -> BarrierId {
+        self.inner.lock().unwrap().barriers.add(barrier)
+    }
+
+    /// Remove a barrier from this pointer, returning it if it existed
+    pub fn remove_barrier(&self, id: BarrierId) -> Option<Barrier> {
+        self.inner.lock().unwrap().barriers.remove(id)
+    }
+
+    /// Access a barrier of this pointer
+    pub fn barrier(&self, id: BarrierId) -> Option<Barrier> {
+        self.inner.lock().unwrap().barriers.get(id)
+    }
+
+    /// List the barriers of this pointer
+    pub fn barriers(&self) -> Vec<BarrierId> {
+        self.inner.lock().unwrap().barriers.ids()
+    }
+
+    /// Let the next motion of the pointer pass a barrier
+    ///
+    /// This is typically done in response to a [`BarrierEvent`], e.g. once the pointer pushed
+    /// against the barrier hard enough. Returns `false` if the barrier does not exist.
+    pub fn release_barrier(&self, id: BarrierId) -> bool {
+        self.inner.lock().unwrap().barriers.release(id)
+    }
+
+    /// Apply the barriers of this pointer to a motion
+    ///
+    /// `location` is the location the pointer would move to from its current location, in the
+    /// global compositor space. Returns the location the pointer is allowed to move to, which
+    /// should be used to determine the new focus and passed to [`PointerHandle::motion`].
+    ///
+    /// [`SeatHandler::pointer_barrier_hit`] is invoked for every barrier the motion hits.
+    pub fn apply_barriers(&self, data: &mut D, location: Point<f64, Logical>) -> Point<f64, Logical> {
+        let (location, events) = {
+            let mut inner = self.inner.lock().unwrap();
+            let from = inner.location;
+            inner.barriers.apply(from, location)
+        };
+        if !events.is_empty() {
+            let seat = self.get_seat(data);
+            for event in events {
+                data.pointer_barrier_hit(&seat, &event);
+            }
+        }
+        location
+    }
+
     fn get_seat(&self, data: &mut D) -> Seat<D> {
         let seat_state = data.seat_state();
         seat_state
@@ -390,6 +446,7 @@ pub(crate) struct PointerInternal<D: SeatHandler> {
     location: Point<f64, Logical>,
     grab: GrabStatus<D>,
     pressed_buttons: Vec<u32>,
+    barriers: Barriers,
 }
 
 // image_callback does not implement debug, so we have to impl Debug manually
@@ -404,6 +461,7 @@ where
             .field("location", &self.location)
             .field("grab", &self.grab)
             .field("pressed_buttons", &self.pressed_buttons)
+            .field("barriers", &self.barriers)
             .field("image_callback", &"...")
             .finish()
     }
@@ -417,6 +475,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
             location: (0.0, 0.0).into(),
             grab: GrabStatus::None,
             pressed_buttons: Vec::new(),
+            barriers: Barriers::default(),
         }
     }
 
@@ -428,7 +487,10 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
         grab: G,
         focus: Focus,
     ) {
-        self.grab = GrabStatus::Active(serial, Box::new(grab));
+        let previous = ::std::mem::replace(&mut self.grab, GrabStatus::Active(serial, Box::new(grab)));
+        if let GrabStatus::Active(_, mut previous) = previous {
+            previous.unset(data);
+        }
 
         if matches!(focus, Focus::Clear) {
             let location = self.location;
@@ -446,7 +508,9 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
     }
 
     fn unset_grab(&mut self, data: &mut D, seat: &Seat<D>, serial: Serial, time: u32) {
-        self.grab = GrabStatus::None;
+        if let GrabStatus::Active(_, mut grab) = ::std::mem::replace(&mut self.grab, GrabStatus::None) {
+            grab.unset(data);
+        }
         // restore the focus
         let location = self.location;
         let focus = self.pending_focus.clone();
@@ -624,6 +688,12 @@ pub struct AxisFrame {
     pub axis: (f64, f64),
     /// Discrete representation of scroll value per axis, if available
     pub discrete: Option<(i32, i32)>,
+    /// High-resolution wheel scroll value per axis, if available
+    ///
+    /// A value of 120 represents one discrete step.
+    pub v120: Option<(i32, i32)>,
+    /// Direction of the physical motion relative to the scroll direction per axis
+    pub relative_direction: (AxisRelativeDirection, AxisRelativeDirection),
     /// If the axis is considered having stoped movement
     ///
     /// Only useful in conjunction of AxisSource::Finger events
@@ -638,6 +708,8 @@ impl AxisFrame {
             time,
             axis: (0.0, 0.0),
             discrete: None,
+            v120: None,
+            relative_direction: (AxisRelativeDirection::Identical, AxisRelativeDirection::Identical),
             stop: (false, false),
         }
     }
@@ -672,6 +744,40 @@ impl AxisFrame {
         self
     }
 
+    /// Specify high-resolution wheel scroll steps additionally to the computed value.
+    ///
+    /// A value of 120 represents one logical scroll step, fractions of it may be sent
+    /// by devices supporting high-resolution scrolling. Like [`AxisFrame::discrete`],
+    /// this event is optional.
+    pub fn v120(mut self, axis: Axis, value: i32) -> Self {
+        let v120 = self.v120.get_or_insert_with(Default::default);
+        match axis {
+            Axis::Horizontal => {
+                v120.0 = value;
+            }
+            Axis::Vertical => {
+                v120.1 = value;
+            }
+        };
+        self
+    }
+
+    /// Specify the direction of the physical motion relative to the scroll direction.
+    ///
+    /// This can be used by clients to scroll widgets like sliders in the direction of
+    /// the physical motion, regardless of natural scrolling being enabled.
+    pub fn relative_direction(mut self, axis: Axis, direction: AxisRelativeDirection) -> Self {
+        match axis {
+            Axis::Horizontal => {
+                self.relative_direction.0 = direction;
+            }
+            Axis::Vertical => {
+                self.relative_direction.1 = direction;
+            }
+        };
+        self
+    }
+
     /// The actual scroll value. This event is the only required one, but can also
     /// be send multiple times. The values off one frame will be accumulated by the client.
     pub fn value(mut self, axis: Axis, value: f64) -> Self {
@@ -20,6 +20,9 @@
 //!
 //! You can attach additional properties to your `Output`s by using [`Output::user_data`].
 //!
+//! Changes to multiple outputs can be tested and applied at once using an [`OutputConfiguration`].
+//! When to render an output can be decided with a [`FrameScheduler`].
+//!
 //! ```
 //! # extern crate smithay;
 //! use smithay::output::{Output, PhysicalProperties, Scale, Mode, Subpixel};
@@ -65,6 +68,11 @@ use wayland_server::{backend::WeakHandle, protocol::wl_output::WlOutput};
 
 use crate::utils::{user_data::UserDataMap, Logical, Physical, Point, Raw, Size, Transform};
 
+mod configuration;
+pub use self::configuration::*;
+mod scheduler;
+pub use self::scheduler::*;
+
 /// An output mode
 ///
 /// A possible combination of dimensions and refresh rate for an output.
@@ -200,6 +208,9 @@ pub(crate) struct Inner {
     pub(crate) modes: Vec<Mode>,
     pub(crate) current_mode: Option<Mode>,
     pub(crate) preferred_mode: Option<Mode>,
+    pub(crate) mirror_source: Option<WeakOutput>,
+    pub(crate) enabled: bool,
+    pub(crate) adaptive_sync: bool,
 
     // used by the wayland::output module.
     #[cfg(feature = "wayland_frontend")]
@@ -252,6 +263,9 @@ impl Output {
                 modes: Vec::new(),
                 current_mode: None,
                 preferred_mode: None,
+                mirror_source: None,
+                enabled: true,
+                adaptive_sync: false,
                 #[cfg(feature = "wayland_frontend")]
                 xdg_output: None,
             }),
@@ -306,16 +320,49 @@ impl Output {
         self.inner.0.lock().unwrap().location
     }
 
+    /// Returns if the output is enabled
+    ///
+    /// Outputs are enabled by default and can be disabled through an [`OutputConfiguration`].
+    pub fn is_enabled(&self) -> bool {
+        self.inner.0.lock().unwrap().enabled
+    }
+
+    /// Returns if adaptive sync (VRR) is enabled for the output
+    ///
+    /// Adaptive sync is disabled by default and can be enabled through an [`OutputConfiguration`].
+    pub fn adaptive_sync(&self) -> bool {
+        self.inner.0.lock().unwrap().adaptive_sync
+    }
+
     /// Returns the name of the output
     pub fn name(&self) -> String {
         self.inner.0.lock().unwrap().name.clone()
     }
 
-    /// Returns the description of the output, if xdg-output is initialized
+    /// Returns the description of the output
     pub fn description(&self) -> String {
         self.inner.0.lock().unwrap().description.clone()
     }
 
+    /// Change the human-readable description of the output
+    ///
+    /// By default the description is derived from the make, model and name of the output.
+    /// The new description is advertised to clients binding to `wl_output` version 4
+    /// and `xdg_output` version 3 or later.
+    pub fn set_description(&self, description: impl Into<String>) {
+        let description = description.into();
+        {
+            let mut inner = self.inner.0.lock().unwrap();
+            if inner.description == description {
+                return;
+            }
+            inner.description = description;
+        }
+
+        #[cfg(feature = "wayland_frontend")]
+        self.wl_change_description()
+    }
+
     /// Returns the physical properties of the output
     pub fn physical_properties(&self) -> PhysicalProperties {
         self.inner.0.lock().unwrap().physical.clone()
@@ -381,6 +428,37 @@ impl Output {
         self.wl_change_current_state(new_mode, new_transform.map(Into::into), new_scale, new_location)
     }
 
+    /// Mark this output as a mirror of another output
+    ///
+    /// A mirror displays the same contents as its source output, scaled to its own mode.
+    /// The desktop abstractions show the part of a [`Space`](crate::desktop::Space) visible on
+    /// the source on the mirror as well, see
+    /// [`OutputMirror`](crate::desktop::space::OutputMirror) to reuse the rendered contents of the source.
+    ///
+    /// Passing `None` or the output itself stops mirroring.
+    pub fn set_mirror_source(&self, source: Option<&Output>) {
+        let source = source.filter(|source| *source != self);
+        info!(
+            output = self.name(),
+            source = ?source.map(Output::name),
+            "Changing mirror source"
+        );
+        self.inner.0.lock().unwrap().mirror_source = source.map(Output::downgrade);
+    }
+
+    /// Returns the output this output mirrors, if any
+    ///
+    /// Mirroring is not resolved transitively, the returned output may be a mirror itself.
+    pub fn mirror_source(&self) -> Option<Output> {
+        self.inner
+            .0
+            .lock()
+            .unwrap()
+            .mirror_source
+            .as_ref()
+            .and_then(WeakOutput::upgrade)
+    }
+
     /// Returns the user data of this output
     pub fn user_data(&self) -> &UserDataMap {
         &self.inner.1
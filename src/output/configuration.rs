@@ -0,0 +1,384 @@
+//! Atomic configuration of multiple outputs
+//!
+//! An [`OutputConfiguration`] batches changes of the mode, location, scale, transform,
+//! enabled state and adaptive sync of any number of [`Output`]s. Applying it first validates
+//! the configuration, then lets an [`OutputConfigurationBackend`] test and apply it, rolling
+//! back to the previous configuration, if applying fails. Only if the backend succeeded,
+//! the new state is stored on the outputs and advertised to clients.
+//!
+//! This maps directly onto protocols like `wlr-output-management`, which require a
+//! configuration to be tested or applied as a whole.
+//!
+//! ```
+//! # extern crate smithay;
+//! use smithay::output::{
+//!     Mode, Output, OutputConfiguration, OutputConfigurationBackend, PhysicalProperties, Subpixel,
+//! };
+//!
+//! struct Backend;
+//!
+//! impl OutputConfigurationBackend for Backend {
+//!     type Error = std::io::Error;
+//!
+//!     fn test(&mut self, configuration: &OutputConfiguration) -> Result<(), Self::Error> {
+//!         // e.g. do a TEST_ONLY atomic commit with the new modes
+//!         Ok(())
+//!     }
+//!
+//!     fn apply(&mut self, configuration: &OutputConfiguration) -> Result<(), Self::Error> {
+//!         // e.g. commit the new modes and map the outputs into your space
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # let output = Output::new("output-0".into(), PhysicalProperties {
+//! #     size: (0, 0).into(),
+//! #     subpixel: Subpixel::Unknown,
+//! #     make: "".into(),
+//! #     model: "".into(),
+//! # });
+//! let mut configuration = OutputConfiguration::new();
+//! let head = configuration.configure(&output);
+//! head.mode = Some(Mode { size: (1920, 1080).into(), refresh: 60000 });
+//! head.location = (1920, 0).into();
+//! configuration.apply(&mut Backend).expect("Failed to configure outputs");
+//! assert_eq!(output.current_location(), (1920, 0).into());
+//! ```
+
+use tracing::{error, info, instrument};
+
+use super::{Mode, Output, Scale};
+use crate::utils::{Logical, Point, Transform};
+
+/// The configurable state of a single [`Output`]
+#[derive(Debug, Clone, Copy)]
+pub struct OutputHeadState {
+    /// Whether the output is enabled
+    pub enabled: bool,
+    /// Mode of the output, required for enabled outputs
+    pub mode: Option<Mode>,
+    /// Location of the output
+    pub location: Point<i32, Logical>,
+    /// Scale of the output
+    pub scale: Scale,
+    /// Transform of the output
+    pub transform: Transform,
+    /// Whether adaptive sync (VRR) is enabled
+    pub adaptive_sync: bool,
+}
+
+impl OutputHeadState {
+    /// Returns the current state of an output
+    pub fn current(output: &Output) -> OutputHeadState {
+        let inner = output.inner.0.lock().unwrap();
+        OutputHeadState {
+            enabled: inner.enabled,
+            mode: inner.current_mode,
+            location: inner.location,
+            scale: inner.scale,
+            transform: inner.transform,
+            adaptive_sync: inner.adaptive_sync,
+        }
+    }
+}
+
+/// Errors of an invalid [`OutputConfiguration`]
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidOutputConfiguration {
+    /// An enabled output has no mode
+    #[error("Enabled output {0} has no mode")]
+    NoMode(String),
+    /// An output has a mode with an empty size or negative refresh rate
+    #[error("Output {output} has an invalid mode {mode:?}")]
+    InvalidMode {
+        /// Name of the output
+        output: String,
+        /// The invalid mode
+        mode: Mode,
+    },
+    /// An output has a scale, that is not a positive number
+    #[error("Output {output} has an invalid scale {scale}")]
+    InvalidScale {
+        /// Name of the output
+        output: String,
+        /// The invalid scale
+        scale: f64,
+    },
+}
+
+/// Errors of [`OutputConfiguration::apply`]
+#[derive(Debug, thiserror::Error)]
+pub enum OutputConfigurationError<E: std::error::Error + 'static> {
+    /// The configuration is invalid
+    #[error(transparent)]
+    Invalid(#[from] InvalidOutputConfiguration),
+    /// The backend rejected the configuration, nothing was changed
+    #[error("The configuration was rejected by the backend")]
+    TestFailed(#[source] E),
+    /// The backend failed to apply the configuration
+    ///
+    /// The backend was asked to restore the previous configuration.
+    #[error("Failed to apply the configuration")]
+    ApplyFailed(#[source] E),
+}
+
+/// Backend testing and applying [`OutputConfiguration`]s
+///
+/// For the drm backend this usually means testing the modes of the affected
+/// [`DrmSurface`](crate::backend::drm::DrmSurface)s with atomic test commits
+/// (see [`DrmSurface::use_mode`](crate::backend::drm::DrmSurface::use_mode) and
+/// [`DrmSurface::test_state`](crate::backend::drm::DrmSurface::test_state)),
+/// before committing them.
+pub trait OutputConfigurationBackend {
+    /// Error type of the backend
+    type Error: std::error::Error + 'static;
+
+    /// Test if the configuration can be applied without changing anything
+    fn test(&mut self, configuration: &OutputConfiguration) -> Result<(), Self::Error>;
+
+    /// Apply the configuration
+    ///
+    /// If this fails, `apply` is called again with the previous configuration to roll back
+    /// any changes already made.
+    fn apply(&mut self, configuration: &OutputConfiguration) -> Result<(), Self::Error>;
+}
+
+/// A batch of changes to multiple [`Output`]s applied at once
+///
+/// See the [module documentation](self) for details.
+#[derive(Debug, Default, Clone)]
+pub struct OutputConfiguration {
+    heads: Vec<(Output, OutputHeadState)>,
+}
+
+impl OutputConfiguration {
+    /// Create a new empty configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the state of an output to be configured
+    ///
+    /// The state is initialized with the current state of the output,
+    /// if the output was not configured before.
+    pub fn configure(&mut self, output: &Output) -> &mut OutputHeadState {
+        let idx = match self.heads.iter().position(|(o, _)| o == output) {
+            Some(idx) => idx,
+            None => {
+                self.heads
+                    .push((output.clone(), OutputHeadState::current(output)));
+                self.heads.len() - 1
+            }
+        };
+        &mut self.heads[idx].1
+    }
+
+    /// Returns the configured state of an output, if it is part of this configuration
+    pub fn head(&self, output: &Output) -> Option<&OutputHeadState> {
+        self.heads
+            .iter()
+            .find(|(o, _)| o == output)
+            .map(|(_, state)| state)
+    }
+
+    /// Iterate over all configured outputs and their new state
+    pub fn heads(&self) -> impl Iterator<Item = (&Output, &OutputHeadState)> {
+        self.heads.iter().map(|(output, state)| (output, state))
+    }
+
+    /// Check the configuration for invalid values
+    pub fn validate(&self) -> Result<(), InvalidOutputConfiguration> {
+        for (output, state) in &self.heads {
+            match state.mode {
+                Some(mode) if mode.size.w <= 0 || mode.size.h <= 0 || mode.refresh < 0 => {
+                    return Err(InvalidOutputConfiguration::InvalidMode {
+                        output: output.name(),
+                        mode,
+                    });
+                }
+                None if state.enabled => return Err(InvalidOutputConfiguration::NoMode(output.name())),
+                _ => {}
+            }
+
+            let scale = state.scale.fractional_scale();
+            if !scale.is_finite() || scale <= 0.0 || state.scale.integer_scale() <= 0 {
+                return Err(InvalidOutputConfiguration::InvalidScale {
+                    output: output.name(),
+                    scale,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate and test the configuration without applying it
+    pub fn test<B: OutputConfigurationBackend>(
+        &self,
+        backend: &mut B,
+    ) -> Result<(), OutputConfigurationError<B::Error>> {
+        self.validate()?;
+        backend.test(self).map_err(OutputConfigurationError::TestFailed)
+    }
+
+    /// Validate, test and apply the configuration
+    ///
+    /// On success the new state is stored on the outputs and advertised to clients.
+    /// Otherwise the outputs keep their previous state.
+    #[instrument(skip_all)]
+    pub fn apply<B: OutputConfigurationBackend>(
+        self,
+        backend: &mut B,
+    ) -> Result<(), OutputConfigurationError<B::Error>> {
+        self.test(backend)?;
+
+        let previous = OutputConfiguration {
+            heads: self
+                .heads
+                .iter()
+                .map(|(output, _)| (output.clone(), OutputHeadState::current(output)))
+                .collect(),
+        };
+        if let Err(err) = backend.apply(&self) {
+            error!("Failed to apply output configuration, rolling back: {}", err);
+            if let Err(err) = backend.apply(&previous) {
+                error!("Failed to restore previous output configuration: {}", err);
+            }
+            return Err(OutputConfigurationError::ApplyFailed(err));
+        }
+
+        for (output, state) in self.heads {
+            info!(output = output.name(), ?state, "Applying output configuration");
+            {
+                let mut inner = output.inner.0.lock().unwrap();
+                inner.enabled = state.enabled;
+                inner.adaptive_sync = state.adaptive_sync;
+            }
+            output.change_current_state(
+                state.mode,
+                Some(state.transform),
+                Some(state.scale),
+                Some(state.location),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{PhysicalProperties, Subpixel};
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("rejected")]
+    struct Rejected;
+
+    #[derive(Default)]
+    struct TestBackend {
+        reject_test: bool,
+        reject_apply: bool,
+        applied: Vec<Vec<Point<i32, Logical>>>,
+    }
+
+    impl OutputConfigurationBackend for TestBackend {
+        type Error = Rejected;
+
+        fn test(&mut self, _configuration: &OutputConfiguration) -> Result<(), Rejected> {
+            if self.reject_test {
+                Err(Rejected)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn apply(&mut self, configuration: &OutputConfiguration) -> Result<(), Rejected> {
+            self.applied
+                .push(configuration.heads().map(|(_, state)| state.location).collect());
+            if std::mem::take(&mut self.reject_apply) {
+                Err(Rejected)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn output(name: &str) -> Output {
+        let output = Output::new(
+            name.into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Test".into(),
+            },
+        );
+        let mode = Mode {
+            size: (1920, 1080).into(),
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+        output
+    }
+
+    #[test]
+    fn invalid_configuration() {
+        let output = output("output");
+        let mut backend = TestBackend::default();
+
+        let mut configuration = OutputConfiguration::new();
+        configuration.configure(&output).mode = None;
+        assert!(matches!(
+            configuration.clone().apply(&mut backend),
+            Err(OutputConfigurationError::Invalid(
+                InvalidOutputConfiguration::NoMode(_)
+            ))
+        ));
+        // disabled outputs do not need a mode
+        configuration.configure(&output).enabled = false;
+        assert!(configuration.validate().is_ok());
+
+        configuration.configure(&output).scale = Scale::Fractional(0.0);
+        assert!(matches!(
+            configuration.validate(),
+            Err(InvalidOutputConfiguration::InvalidScale { .. })
+        ));
+        assert!(backend.applied.is_empty());
+    }
+
+    #[test]
+    fn apply_and_rollback() {
+        let first = output("first");
+        let second = output("second");
+        let mut backend = TestBackend::default();
+
+        let mut configuration = OutputConfiguration::new();
+        configuration.configure(&second).location = (1920, 0).into();
+        configuration.configure(&first).adaptive_sync = true;
+        configuration.apply(&mut backend).unwrap();
+        assert_eq!(second.current_location(), (1920, 0).into());
+        assert!(first.adaptive_sync());
+
+        backend.reject_test = true;
+        let mut configuration = OutputConfiguration::new();
+        configuration.configure(&second).location = (0, 1080).into();
+        assert!(matches!(
+            configuration.clone().apply(&mut backend),
+            Err(OutputConfigurationError::TestFailed(_))
+        ));
+        assert_eq!(backend.applied.len(), 1);
+
+        backend.reject_test = false;
+        backend.reject_apply = true;
+        assert!(matches!(
+            configuration.apply(&mut backend),
+            Err(OutputConfigurationError::ApplyFailed(_))
+        ));
+        // the previous configuration was restored
+        assert_eq!(
+            backend.applied[1..],
+            [vec![(0, 1080).into()], vec![(1920, 0).into()]]
+        );
+        assert_eq!(second.current_location(), (1920, 0).into());
+    }
+}
@@ -0,0 +1,291 @@
+//! Scheduling of output repaints
+
+use std::{collections::VecDeque, time::Duration};
+
+use calloop::timer::Timer;
+use tracing::trace;
+
+use super::Output;
+use crate::utils::{Monotonic, Time};
+
+const DEFAULT_RENDER_TIME_SAMPLES: usize = 16;
+const DEFAULT_SLACK: Duration = Duration::from_millis(1);
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Per-output scheduler deciding when to start rendering
+///
+/// Rendering right after the vblank of an output gives the compositor the most time to finish
+/// a frame, but adds up to a full refresh cycle of latency between a client committing new
+/// content and it showing up on screen. A [`FrameScheduler`] instead starts rendering as late
+/// as possible: it predicts the next vblank from the presentation timestamps of previous frames
+/// and subtracts the render times measured for recent frames, plus some slack.
+///
+/// The scheduler is driven by the compositor:
+///
+/// - [`request_repaint`](FrameScheduler::request_repaint) whenever the output got damaged,
+/// - [`frame_submitted`](FrameScheduler::frame_submitted) after queueing a frame, or
+///   [`frame_skipped`](FrameScheduler::frame_skipped) if rendering resulted in no damage,
+/// - [`record_render_time`](FrameScheduler::record_render_time) with the time rendering took,
+/// - [`frame_presented`](FrameScheduler::frame_presented) with the vblank timestamp of the
+///   presented frame, e.g. from a page flip event.
+///
+/// [`next_render_time`](FrameScheduler::next_render_time) then returns when to start rendering
+/// the next frame, [`timer`](FrameScheduler::timer) returns a calloop [`Timer`] firing at that time.
+///
+/// ```
+/// # extern crate smithay;
+/// use std::time::Duration;
+/// use smithay::output::FrameScheduler;
+/// use smithay::utils::{Monotonic, Time};
+///
+/// let mut scheduler = FrameScheduler::new(Duration::from_millis(16));
+/// scheduler.set_slack(Duration::ZERO);
+/// scheduler.record_render_time(Duration::from_millis(4));
+///
+/// // the last frame was presented at 100ms
+/// scheduler.frame_presented(Time::<Monotonic>::from(Duration::from_millis(100)));
+///
+/// // a client commits new content at 103ms
+/// scheduler.request_repaint();
+/// let now = Time::<Monotonic>::from(Duration::from_millis(103));
+///
+/// // the next vblank is at 116ms, so rendering starts at 112ms
+/// let render_time = scheduler.next_render_time(now).unwrap();
+/// assert_eq!(Duration::from(render_time), Duration::from_millis(112));
+///
+/// // arm a timer with the event loop
+/// let timer = scheduler.timer(now).unwrap();
+/// # let _ = timer;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameScheduler {
+    refresh_interval: Duration,
+    last_presentation: Option<Time<Monotonic>>,
+    render_times: VecDeque<Duration>,
+    render_time_samples: usize,
+    slack: Duration,
+    repaint_requested: bool,
+    frame_pending: bool,
+}
+
+impl FrameScheduler {
+    /// Create a new scheduler for an output with the given refresh interval
+    pub fn new(refresh_interval: Duration) -> FrameScheduler {
+        FrameScheduler {
+            refresh_interval,
+            last_presentation: None,
+            render_times: VecDeque::with_capacity(DEFAULT_RENDER_TIME_SAMPLES),
+            render_time_samples: DEFAULT_RENDER_TIME_SAMPLES,
+            slack: DEFAULT_SLACK,
+            repaint_requested: false,
+            frame_pending: false,
+        }
+    }
+
+    /// Create a new scheduler using the refresh rate of the current mode of the output
+    ///
+    /// Outputs without a current mode or with an unknown refresh rate are assumed to refresh at 60Hz.
+    pub fn for_output(output: &Output) -> FrameScheduler {
+        let refresh_interval = output
+            .current_mode()
+            .filter(|mode| mode.refresh > 0)
+            .map(|mode| Duration::from_nanos(1_000_000_000_000 / mode.refresh as u64))
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+        FrameScheduler::new(refresh_interval)
+    }
+
+    /// Returns the refresh interval of the output
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    /// Set the refresh interval of the output, e.g. after a mode change
+    ///
+    /// This discards the last presentation time, so the next frame is rendered immediately.
+    pub fn set_refresh_interval(&mut self, refresh_interval: Duration) {
+        self.refresh_interval = refresh_interval;
+        self.last_presentation = None;
+    }
+
+    /// Returns the safety margin added to the estimated render time
+    pub fn slack(&self) -> Duration {
+        self.slack
+    }
+
+    /// Set the safety margin added to the estimated render time
+    ///
+    /// This accounts for the time between the scheduled and the actual start of rendering and
+    /// for the time needed to submit the frame. Defaults to 1ms.
+    pub fn set_slack(&mut self, slack: Duration) {
+        self.slack = slack;
+    }
+
+    /// Set the number of recent render times used to estimate the render time of the next frame
+    ///
+    /// Defaults to 16.
+    pub fn set_render_time_samples(&mut self, samples: usize) {
+        self.render_time_samples = samples.max(1);
+        while self.render_times.len() > self.render_time_samples {
+            self.render_times.pop_front();
+        }
+    }
+
+    /// Record the time it took to render a frame
+    ///
+    /// This should cover the whole time from starting to render until the frame is ready to be
+    /// presented, including the time the gpu takes to finish rendering, if known.
+    pub fn record_render_time(&mut self, render_time: Duration) {
+        if self.render_times.len() == self.render_time_samples {
+            self.render_times.pop_front();
+        }
+        self.render_times.push_back(render_time);
+    }
+
+    /// Returns the time reserved for rendering the next frame
+    ///
+    /// This is the longest of the recorded render times plus the [slack](FrameScheduler::slack),
+    /// capped at the refresh interval. Without any recorded render times the whole refresh
+    /// interval is reserved, which equals rendering right after the vblank.
+    pub fn estimated_render_time(&self) -> Duration {
+        self.render_times
+            .iter()
+            .max()
+            .map(|render_time| *render_time + self.slack)
+            .unwrap_or(self.refresh_interval)
+            .min(self.refresh_interval)
+    }
+
+    /// Request a repaint of the output, e.g. because a surface on it was committed
+    pub fn request_repaint(&mut self) {
+        self.repaint_requested = true;
+    }
+
+    /// Returns if a repaint was requested and not rendered yet
+    pub fn repaint_requested(&self) -> bool {
+        self.repaint_requested
+    }
+
+    /// Returns if a submitted frame waits to be presented
+    pub fn is_frame_pending(&self) -> bool {
+        self.frame_pending
+    }
+
+    /// Notify the scheduler that a frame was submitted for presentation
+    pub fn frame_submitted(&mut self) {
+        self.repaint_requested = false;
+        self.frame_pending = true;
+    }
+
+    /// Notify the scheduler that rendering resulted in no new frame, e.g. because there was no damage
+    pub fn frame_skipped(&mut self) {
+        self.repaint_requested = false;
+    }
+
+    /// Notify the scheduler that a frame was presented at the given vblank time
+    pub fn frame_presented(&mut self, time: Time<Monotonic>) {
+        trace!(?time, "Frame presented");
+        self.frame_pending = false;
+        self.last_presentation = Some(time);
+    }
+
+    /// Returns the predicted presentation time of a frame started rendering at `now`
+    ///
+    /// This is the first vblank at least the [estimated render time](FrameScheduler::estimated_render_time)
+    /// after `now`. Returns `None` if no frame was presented yet.
+    pub fn next_presentation_time(&self, now: Time<Monotonic>) -> Option<Time<Monotonic>> {
+        let last_presentation = Duration::from(self.last_presentation?);
+        let target = Duration::from(now) + self.estimated_render_time();
+
+        let refresh = self.refresh_interval.as_nanos().max(1);
+        let cycles = target
+            .checked_sub(last_presentation)
+            .map(|elapsed| (elapsed.as_nanos() + refresh - 1) / refresh)
+            .unwrap_or(0)
+            .max(1);
+
+        let next = last_presentation.as_nanos() + cycles * refresh;
+        Some(Duration::from_nanos(next as u64).into())
+    }
+
+    /// Returns the time to start rendering the next frame
+    ///
+    /// Returns `None` if no repaint was requested or a submitted frame was not presented yet,
+    /// in which case the scheduler has to be queried again after [`frame_presented`](FrameScheduler::frame_presented).
+    /// Returns `now`, if no frame was presented yet.
+    pub fn next_render_time(&self, now: Time<Monotonic>) -> Option<Time<Monotonic>> {
+        if !self.repaint_requested || self.frame_pending {
+            return None;
+        }
+
+        let Some(presentation) = self.next_presentation_time(now) else {
+            return Some(now);
+        };
+        let render_time = Duration::from(presentation)
+            .saturating_sub(self.estimated_render_time())
+            .max(Duration::from(now));
+        Some(render_time.into())
+    }
+
+    /// Returns a timer firing at the [next render time](FrameScheduler::next_render_time)
+    ///
+    /// The timer can be inserted into the event loop to start rendering the output.
+    pub fn timer(&self, now: Time<Monotonic>) -> Option<Timer> {
+        let render_time = self.next_render_time(now)?;
+        Some(Timer::from_duration(now.duration_since(render_time)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(millis: u64) -> Time<Monotonic> {
+        Duration::from_millis(millis).into()
+    }
+
+    #[test]
+    fn renders_as_late_as_possible() {
+        let mut scheduler = FrameScheduler::new(Duration::from_millis(10));
+        scheduler.set_slack(Duration::from_millis(1));
+        scheduler.request_repaint();
+        assert_eq!(scheduler.next_render_time(time(5)), Some(time(5)));
+
+        scheduler.frame_submitted();
+        assert_eq!(scheduler.next_render_time(time(6)), None);
+        scheduler.frame_presented(time(10));
+        scheduler.record_render_time(Duration::from_millis(2));
+        scheduler.record_render_time(Duration::from_millis(3));
+
+        scheduler.request_repaint();
+        assert_eq!(scheduler.next_render_time(time(11)), Some(time(16)));
+        assert_eq!(scheduler.next_presentation_time(time(16)), Some(time(20)));
+
+        // too late for the next vblank
+        assert_eq!(scheduler.next_render_time(time(17)), Some(time(26)));
+        assert_eq!(scheduler.next_render_time(time(42)), Some(time(46)));
+    }
+
+    #[test]
+    fn render_time_estimate() {
+        let mut scheduler = FrameScheduler::new(Duration::from_millis(10));
+        assert_eq!(scheduler.estimated_render_time(), Duration::from_millis(10));
+
+        scheduler.set_slack(Duration::ZERO);
+        scheduler.set_render_time_samples(2);
+        scheduler.record_render_time(Duration::from_millis(8));
+        scheduler.record_render_time(Duration::from_millis(2));
+        assert_eq!(scheduler.estimated_render_time(), Duration::from_millis(8));
+        scheduler.record_render_time(Duration::from_millis(3));
+        assert_eq!(scheduler.estimated_render_time(), Duration::from_millis(3));
+        scheduler.record_render_time(Duration::from_millis(30));
+        assert_eq!(scheduler.estimated_render_time(), Duration::from_millis(10));
+
+        // no estimate renders right after the vblank
+        let mut scheduler = FrameScheduler::new(Duration::from_millis(10));
+        scheduler.frame_presented(time(10));
+        scheduler.request_repaint();
+        assert_eq!(scheduler.next_render_time(time(12)), Some(time(20)));
+        scheduler.frame_skipped();
+        assert_eq!(scheduler.next_render_time(time(12)), None);
+    }
+}
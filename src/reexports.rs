@@ -26,3 +26,5 @@ pub use wayland_server;
 pub use winit;
 #[cfg(feature = "x11rb_event_source")]
 pub use x11rb;
+#[cfg(feature = "xcursor")]
+pub use xcursor;
@@ -31,6 +31,50 @@ pub trait ClockSource {
     fn id() -> libc::clockid_t;
 }
 
+/// Clock domain of presentation timestamps, selected at runtime
+///
+/// Drm devices report page flip timestamps either in the monotonic or realtime clock domain,
+/// see [`DrmDevice::presentation_clock`](crate::backend::drm::DrmDevice::presentation_clock).
+/// The [`id`](PresentationClock::id) of the clock can be advertised to clients through the
+/// [`PresentationState`](crate::wayland::presentation::PresentationState) global, or timestamps
+/// can be converted into another clock domain with [`Time::to_clock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PresentationClock {
+    /// `CLOCK_MONOTONIC`
+    Monotonic,
+    /// `CLOCK_REALTIME`
+    Realtime,
+}
+
+impl PresentationClock {
+    /// Gets the id of the clock
+    pub fn id(&self) -> libc::clockid_t {
+        match self {
+            PresentationClock::Monotonic => Monotonic::id(),
+            PresentationClock::Realtime => Realtime::id(),
+        }
+    }
+
+    /// Converts a timestamp of this clock into the monotonic clock domain
+    pub fn to_monotonic(&self, timestamp: Duration) -> Time<Monotonic> {
+        match self {
+            PresentationClock::Monotonic => Time::from(timestamp),
+            PresentationClock::Realtime => Time::<Realtime>::from(duration_to_timespec(timestamp)).to_clock(),
+        }
+    }
+
+    /// Converts a monotonic time into a timestamp of this clock
+    pub fn from_monotonic(&self, time: Time<Monotonic>) -> Duration {
+        match self {
+            PresentationClock::Monotonic => time.into(),
+            PresentationClock::Realtime => {
+                let time = time.to_clock::<Realtime>();
+                Duration::from_nanos(timespec_to_nanos(time.tp).max(0) as u64)
+            }
+        }
+    }
+}
+
 /// Defines a clock with a specific kind
 #[derive(Debug)]
 pub struct Clock<Kind> {
@@ -73,6 +117,44 @@ impl<Kind> Time<Kind> {
     pub fn duration_since(&self, later: Time<Kind>) -> Duration {
         saturating_sub_timespec(later.tp, self.tp).unwrap_or(Duration::ZERO)
     }
+
+    /// Splits the time into the high and low 32 bits of the seconds and the nanoseconds,
+    /// as used by the `wp_presentation_feedback.presented` event
+    pub fn to_presentation_timestamp(&self) -> (u32, u32, u32) {
+        let secs = self.tp.tv_sec as u64;
+        (
+            (secs >> 32) as u32,
+            (secs & 0xFFFFFFFF) as u32,
+            self.tp.tv_nsec as u32,
+        )
+    }
+
+    /// Returns the time in milliseconds, wrapping around at 32 bits,
+    /// as used by the timestamps of input events
+    pub fn as_millis(&self) -> u32 {
+        (timespec_to_nanos(self.tp) / 1_000_000) as u32
+    }
+
+    /// Returns the time in microseconds, as used by e.g. `zwp_relative_pointer_v1.relative_motion`
+    pub fn as_micros(&self) -> u64 {
+        (timespec_to_nanos(self.tp) / 1_000) as u64
+    }
+}
+
+impl<Kind: ClockSource> Time<Kind> {
+    /// Converts the time into the domain of another clock
+    ///
+    /// The conversion uses the current offset between both clocks, so it is only exact
+    /// if neither clock was adjusted since the time was taken.
+    pub fn to_clock<Other: ClockSource>(&self) -> Time<Other> {
+        let now = clock_get_time(Kind::id()).expect("failed to get clock time");
+        let other_now = clock_get_time(Other::id()).expect("failed to get clock time");
+        let nanos = timespec_to_nanos(self.tp) - timespec_to_nanos(now) + timespec_to_nanos(other_now);
+        Time {
+            tp: nanos_to_timespec(nanos),
+            _kind: PhantomData,
+        }
+    }
 }
 
 impl<Kind> Clone for Time<Kind> {
@@ -131,12 +213,18 @@ impl<Kind> Ord for Time<Kind> {
 
 impl<Kind: NonNegativeClockSource> From<Duration> for Time<Kind> {
     fn from(tp: Duration) -> Self {
-        let tp = libc::timespec {
-            tv_sec: tp.as_secs() as libc::time_t,
-            #[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
-            tv_nsec: tp.subsec_nanos() as i64,
-            #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
-            tv_nsec: tp.subsec_nanos() as std::os::raw::c_long,
+        Time {
+            tp: duration_to_timespec(tp),
+            _kind: PhantomData,
+        }
+    }
+}
+
+impl From<std::time::SystemTime> for Time<Realtime> {
+    fn from(time: std::time::SystemTime) -> Self {
+        let tp = match time.duration_since(std::time::UNIX_EPOCH) {
+            Ok(duration) => duration_to_timespec(duration),
+            Err(err) => nanos_to_timespec(-(err.duration().as_nanos() as i128)),
         };
         Time {
             tp,
@@ -160,6 +248,27 @@ const NANOS_PER_SEC: i64 = 1_000_000_000;
 #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
 const NANOS_PER_SEC: std::os::raw::c_long = 1_000_000_000;
 
+fn duration_to_timespec(duration: Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: duration.as_secs() as libc::time_t,
+        #[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
+        tv_nsec: duration.subsec_nanos() as i64,
+        #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
+        tv_nsec: duration.subsec_nanos() as std::os::raw::c_long,
+    }
+}
+
+fn timespec_to_nanos(tp: libc::timespec) -> i128 {
+    tp.tv_sec as i128 * NANOS_PER_SEC as i128 + tp.tv_nsec as i128
+}
+
+fn nanos_to_timespec(nanos: i128) -> libc::timespec {
+    libc::timespec {
+        tv_sec: nanos.div_euclid(NANOS_PER_SEC as i128) as libc::time_t,
+        tv_nsec: nanos.rem_euclid(NANOS_PER_SEC as i128) as _,
+    }
+}
+
 fn saturating_sub_timespec(lhs: libc::timespec, rhs: libc::timespec) -> Option<Duration> {
     if let Some(mut secs) = lhs.tv_sec.checked_sub(rhs.tv_sec) {
         let nanos = if lhs.tv_nsec >= rhs.tv_nsec {
@@ -194,7 +303,7 @@ fn clock_get_time(clk_id: libc::clockid_t) -> Result<libc::timespec, std::io::Er
 mod test {
     use std::time::Duration;
 
-    use crate::utils::{Clock, Monotonic, Time};
+    use crate::utils::{Clock, Monotonic, PresentationClock, Realtime, Time};
 
     #[test]
     fn monotonic() {
@@ -203,4 +312,35 @@ mod test {
         let zero = Time::<Monotonic>::from(Duration::ZERO);
         assert_eq!(zero.duration_since(now), now.into());
     }
+
+    #[test]
+    fn protocol_timestamps() {
+        let time = Time::<Monotonic>::from(Duration::new((7 << 32) + 5, 123_456_789));
+        assert_eq!(time.to_presentation_timestamp(), (7, 5, 123_456_789));
+
+        let time = Time::<Monotonic>::from(Duration::from_micros(1_234_567));
+        assert_eq!(time.as_millis(), 1_234);
+        assert_eq!(time.as_micros(), 1_234_567);
+    }
+
+    #[test]
+    fn clock_conversion() {
+        let monotonic: Clock<Monotonic> = Clock::new().unwrap();
+        let realtime: Clock<Realtime> = Clock::new().unwrap();
+
+        let now = realtime.now();
+        let converted = now.to_clock::<Monotonic>();
+        let after = monotonic.now();
+        assert!(converted <= after);
+        assert!(converted.duration_since(after) < Duration::from_secs(1));
+
+        let timestamp = PresentationClock::Realtime.from_monotonic(after);
+        let roundtrip = PresentationClock::Realtime.to_monotonic(timestamp);
+        let diff = if roundtrip > after {
+            after.duration_since(roundtrip)
+        } else {
+            roundtrip.duration_since(after)
+        };
+        assert!(diff < Duration::from_millis(100));
+    }
 }
@@ -546,6 +546,36 @@ impl<N: Coordinate> Point<N, Physical> {
             _kind: std::marker::PhantomData,
         }
     }
+
+    /// Convert this physical point to logical coordinate space according to given scale factor
+    /// and round the result
+    #[inline]
+    pub fn to_logical_precise_round<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Point<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_round()
+    }
+
+    /// Convert this physical point to logical coordinate space according to given scale factor
+    /// and ceil the result
+    #[inline]
+    pub fn to_logical_precise_ceil<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Point<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_ceil()
+    }
+
+    /// Convert this physical point to logical coordinate space according to given scale factor
+    /// and floor the result
+    #[inline]
+    pub fn to_logical_precise_floor<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Point<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_floor()
+    }
 }
 
 impl<N: Coordinate> Point<N, Buffer> {
@@ -879,6 +909,36 @@ impl<N: Coordinate> Size<N, Physical> {
             _kind: std::marker::PhantomData,
         }
     }
+
+    /// Convert this physical size to logical coordinate space according to given scale factor
+    /// and round the result
+    #[inline]
+    pub fn to_logical_precise_round<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Size<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_round()
+    }
+
+    /// Convert this physical size to logical coordinate space according to given scale factor
+    /// and ceil the result
+    #[inline]
+    pub fn to_logical_precise_ceil<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Size<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_ceil()
+    }
+
+    /// Convert this physical size to logical coordinate space according to given scale factor
+    /// and floor the result
+    #[inline]
+    pub fn to_logical_precise_floor<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Size<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_floor()
+    }
 }
 
 impl<N: Coordinate> Size<N, Buffer> {
@@ -1301,7 +1361,7 @@ impl<N: Coordinate> Rectangle<N, Logical> {
     /// Convert this logical rectangle to physical coordinate space according to given scale factor,
     /// returning the largest N-space rectangle fitting into the N-based rectangle
     ///
-    /// This will ceil the location and floor the size after applying the scale
+    /// This will ceil the location and floor the bottom-right corner after applying the scale
     #[inline]
     pub fn to_physical_precise_down<S: Coordinate, R: Coordinate>(
         &self,
@@ -1313,7 +1373,7 @@ impl<N: Coordinate> Rectangle<N, Logical> {
     /// Convert this logical rectangle to physical coordinate space according to given scale factor,
     /// returning the smallest N-space rectangle encapsulating the N-based rectangle
     ///
-    /// This will floor the location and ceil the size after applying the scale
+    /// This will floor the location and ceil the bottom-right corner after applying the scale
     #[inline]
     pub fn to_physical_precise_up<S: Coordinate, R: Coordinate>(
         &self,
@@ -1345,6 +1405,53 @@ impl<N: Coordinate> Rectangle<N, Logical> {
             },
         }
     }
+
+    /// Convert this logical rectangle to buffer coordinate space according to given scale factor
+    /// and transformation and round the result
+    ///
+    /// Contrary to [`to_buffer`](Rectangle::to_buffer) the conversion is done in floating point,
+    /// so fractional scales do not accumulate rounding errors.
+    #[inline]
+    pub fn to_buffer_precise_round<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Logical>,
+    ) -> Rectangle<R, Buffer> {
+        self.to_f64()
+            .to_buffer(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_round()
+    }
+
+    /// Convert this logical rectangle to buffer coordinate space according to given scale factor
+    /// and transformation, returning the largest buffer-space rectangle contained in the rectangle
+    #[inline]
+    pub fn to_buffer_precise_down<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Logical>,
+    ) -> Rectangle<R, Buffer> {
+        self.to_f64()
+            .to_buffer(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_down()
+    }
+
+    /// Convert this logical rectangle to buffer coordinate space according to given scale factor
+    /// and transformation, returning the smallest buffer-space rectangle enclosing the rectangle
+    ///
+    /// This is what should be used for damage.
+    #[inline]
+    pub fn to_buffer_precise_up<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Logical>,
+    ) -> Rectangle<R, Buffer> {
+        self.to_f64()
+            .to_buffer(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_up()
+    }
 }
 
 impl<N: Coordinate> Rectangle<N, Physical> {
@@ -1357,6 +1464,40 @@ impl<N: Coordinate> Rectangle<N, Physical> {
             size: self.size.to_logical(scale),
         }
     }
+
+    /// Convert this physical rectangle to logical coordinate space according to given scale factor
+    /// and round the result
+    #[inline]
+    pub fn to_logical_precise_round<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_round()
+    }
+
+    /// Convert this physical rectangle to logical coordinate space according to given scale factor,
+    /// returning the largest logical rectangle contained in the rectangle
+    ///
+    /// This will ceil the location and floor the bottom-right corner after applying the scale
+    #[inline]
+    pub fn to_logical_precise_down<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_down()
+    }
+
+    /// Convert this physical rectangle to logical coordinate space according to given scale factor,
+    /// returning the smallest logical rectangle enclosing the rectangle
+    ///
+    /// This will floor the location and ceil the bottom-right corner after applying the scale
+    #[inline]
+    pub fn to_logical_precise_up<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64().to_logical(scale.into().to_f64()).to_i32_up()
+    }
 }
 
 impl<N: Coordinate> Rectangle<N, Buffer> {
@@ -1383,6 +1524,48 @@ impl<N: Coordinate> Rectangle<N, Buffer> {
             },
         }
     }
+
+    /// Convert this buffer rectangle to logical coordinate space according to given scale factor
+    /// and transformation and round the result
+    #[inline]
+    pub fn to_logical_precise_round<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Buffer>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64()
+            .to_logical(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_round()
+    }
+
+    /// Convert this buffer rectangle to logical coordinate space according to given scale factor
+    /// and transformation, returning the largest logical rectangle contained in the rectangle
+    #[inline]
+    pub fn to_logical_precise_down<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Buffer>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64()
+            .to_logical(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_down()
+    }
+
+    /// Convert this buffer rectangle to logical coordinate space according to given scale factor
+    /// and transformation, returning the smallest logical rectangle enclosing the rectangle
+    #[inline]
+    pub fn to_logical_precise_up<S: Coordinate, R: Coordinate>(
+        self,
+        scale: impl Into<Scale<S>>,
+        transformation: Transform,
+        area: &Size<N, Buffer>,
+    ) -> Rectangle<R, Logical> {
+        self.to_f64()
+            .to_logical(scale.into().to_f64(), transformation, &area.to_f64())
+            .to_i32_up()
+    }
 }
 
 impl<N: fmt::Debug> fmt::Debug for Rectangle<N, Logical> {
@@ -1620,7 +1803,7 @@ impl From<Transform> for WlTransform {
 
 #[cfg(test)]
 mod tests {
-    use super::{Logical, Rectangle, Size, Transform};
+    use super::{Buffer, Logical, Physical, Point, Rectangle, Size, Transform};
 
     #[test]
     fn transform_rect_ident() {
@@ -1866,4 +2049,39 @@ mod tests {
         let main = Rectangle::<i32, Logical>::from_loc_and_size((0, 0), (800, 600));
         assert!(!main.overlaps(right));
     }
+
+    #[test]
+    fn rectangle_to_buffer_precise_90_fractional() {
+        let rect = Rectangle::<i32, Logical>::from_loc_and_size((1, 1), (3, 3));
+        let area = Size::from((10, 20));
+
+        let enclosing: Rectangle<i32, Buffer> = rect.to_buffer_precise_up(1.5, Transform::_90, &area);
+        assert_eq!(enclosing, Rectangle::from_loc_and_size((24, 1), (5, 5)));
+
+        let contained: Rectangle<i32, Buffer> = rect.to_buffer_precise_down(1.5, Transform::_90, &area);
+        assert_eq!(contained, Rectangle::from_loc_and_size((24, 2), (4, 4)));
+
+        let buffer_area = area.to_f64().to_buffer(1.5, Transform::_90).to_i32_round();
+        let back: Rectangle<i32, Logical> =
+            enclosing.to_logical_precise_up(1.5, Transform::_90, &buffer_area);
+        assert!(back.contains_rect(rect));
+        let back: Rectangle<i32, Logical> =
+            contained.to_logical_precise_down(1.5, Transform::_90, &buffer_area);
+        assert!(rect.contains_rect(back));
+    }
+
+    #[test]
+    fn physical_to_logical_precise() {
+        let rect = Rectangle::<i32, Physical>::from_loc_and_size((3, 3), (7, 7));
+
+        let enclosing: Rectangle<i32, Logical> = rect.to_logical_precise_up(2.0);
+        assert_eq!(enclosing, Rectangle::from_loc_and_size((1, 1), (4, 4)));
+        let contained: Rectangle<i32, Logical> = rect.to_logical_precise_down(2.0);
+        assert_eq!(contained, Rectangle::from_loc_and_size((2, 2), (3, 3)));
+
+        let point: Point<i32, Logical> = Point::<i32, Physical>::from((3, 5)).to_logical_precise_ceil(2.0);
+        assert_eq!(point, Point::from((2, 3)));
+        let size: Size<i32, Logical> = Size::<i32, Physical>::from((3, 5)).to_logical_precise_floor(2.0);
+        assert_eq!(size, Size::from((1, 2)));
+    }
 }
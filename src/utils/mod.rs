@@ -22,6 +22,9 @@ pub use self::geometry::{
     Buffer, Coordinate, Logical, Physical, Point, Raw, Rectangle, Scale, Size, Transform,
 };
 
+mod region;
+pub use self::region::Region;
+
 mod serial;
 pub use serial::*;
 
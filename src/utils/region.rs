@@ -0,0 +1,355 @@
+use std::{cmp::Ordering, fmt};
+
+use super::{Coordinate, Point, Rectangle};
+
+/// A set of non-overlapping rectangles
+///
+/// A [`Region`] describes an arbitrary area made up of rectangles, like the damage of an output or
+/// the opaque or input region of a surface. Contrary to a plain list of rectangles it supports
+/// set operations (union, subtraction and intersection) and keeps its rectangles simplified:
+/// The rectangles never overlap, empty rectangles are dropped and the area is split into horizontal
+/// bands, where rectangles in neighbouring bands covering the same horizontal spans are merged.
+/// The same area is therefore always described by the same rectangles.
+pub struct Region<N, Kind> {
+    rects: Vec<Rectangle<N, Kind>>,
+}
+
+impl<N: Coordinate, Kind> Region<N, Kind> {
+    /// Create a new empty region
+    pub fn new() -> Self {
+        Region { rects: Vec::new() }
+    }
+
+    /// Create a region from the union of a set of, possibly overlapping, rectangles
+    pub fn from_rects(rects: impl IntoIterator<Item = Rectangle<N, Kind>>) -> Self {
+        Region {
+            rects: simplify(rects.into_iter().collect()),
+        }
+    }
+
+    /// Returns the rectangles making up this region
+    pub fn rects(&self) -> &[Rectangle<N, Kind>] {
+        &self.rects
+    }
+
+    /// Converts the region into its rectangles
+    pub fn into_rects(self) -> Vec<Rectangle<N, Kind>> {
+        self.rects
+    }
+
+    /// Checks whether the region is empty
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Returns the area covered by the region
+    pub fn area(&self) -> N {
+        self.rects.iter().fold(N::default(), |area, rect| {
+            area.saturating_add(rect.size.w.saturating_mul(rect.size.h))
+        })
+    }
+
+    /// Returns the smallest rectangle containing the whole region
+    ///
+    /// An empty region returns an empty rectangle.
+    pub fn bounding_box(&self) -> Rectangle<N, Kind> {
+        self.rects
+            .iter()
+            .copied()
+            .reduce(|bbox, rect| bbox.merge(rect))
+            .unwrap_or_default()
+    }
+
+    /// Checks whether given [`Point`] is inside the region
+    pub fn contains(&self, point: impl Into<Point<N, Kind>>) -> bool {
+        let point = point.into();
+        self.rects.iter().any(|rect| rect.contains(point))
+    }
+
+    /// Checks whether given [`Rectangle`] is completely inside the region
+    pub fn contains_rect(&self, rect: impl Into<Rectangle<N, Kind>>) -> bool {
+        let mut remaining = Region::from(rect.into());
+        remaining.subtract(self);
+        remaining.is_empty()
+    }
+
+    /// Checks whether given [`Rectangle`] overlaps with the region
+    pub fn overlaps(&self, rect: impl Into<Rectangle<N, Kind>>) -> bool {
+        let rect = rect.into();
+        self.rects.iter().any(|r| r.overlaps(rect))
+    }
+
+    /// Moves the region by the given offset
+    pub fn translate(&mut self, offset: impl Into<Point<N, Kind>>) {
+        let offset = offset.into();
+        for rect in &mut self.rects {
+            rect.loc = rect.loc + offset;
+        }
+    }
+
+    /// Adds a rectangle to the region
+    pub fn union_rect(&mut self, rect: impl Into<Rectangle<N, Kind>>) {
+        self.union_rects([rect.into()]);
+    }
+
+    /// Adds a set of rectangles to the region
+    pub fn union_rects(&mut self, rects: impl IntoIterator<Item = Rectangle<N, Kind>>) {
+        let mut new_rects = std::mem::take(&mut self.rects);
+        let len = new_rects.len();
+        new_rects.extend(rects);
+        if new_rects.len() > len {
+            self.rects = simplify(new_rects);
+        } else {
+            self.rects = new_rects;
+        }
+    }
+
+    /// Adds another region to the region
+    pub fn union(&mut self, other: &Region<N, Kind>) {
+        self.union_rects(other.rects.iter().copied());
+    }
+
+    /// Removes a rectangle from the region
+    pub fn subtract_rect(&mut self, rect: impl Into<Rectangle<N, Kind>>) {
+        self.subtract_rects([rect.into()]);
+    }
+
+    /// Removes a set of rectangles from the region
+    pub fn subtract_rects(&mut self, rects: impl IntoIterator<Item = Rectangle<N, Kind>>) {
+        let mut changed = false;
+        for other in rects {
+            if !self.rects.iter().any(|rect| rect.overlaps(other)) {
+                continue;
+            }
+            self.rects = self
+                .rects
+                .iter()
+                .flat_map(|rect| rect.subtract_rect(other))
+                .collect();
+            changed = true;
+        }
+        if changed {
+            self.rects = simplify(std::mem::take(&mut self.rects));
+        }
+    }
+
+    /// Removes another region from the region
+    pub fn subtract(&mut self, other: &Region<N, Kind>) {
+        self.subtract_rects(other.rects.iter().copied());
+    }
+
+    /// Restricts the region to the area inside the given rectangle
+    pub fn intersect_rect(&mut self, rect: impl Into<Rectangle<N, Kind>>) {
+        let rect = rect.into();
+        let rects = self.rects.iter().filter_map(|r| r.intersection(rect)).collect();
+        self.rects = simplify(rects);
+    }
+
+    /// Restricts the region to the area also covered by another region
+    pub fn intersect(&mut self, other: &Region<N, Kind>) {
+        let rects = self
+            .rects
+            .iter()
+            .flat_map(|rect| other.rects.iter().filter_map(move |r| rect.intersection(*r)))
+            .collect();
+        self.rects = simplify(rects);
+    }
+}
+
+fn is_valid<N: Coordinate, Kind>(rect: &Rectangle<N, Kind>) -> bool {
+    rect.size.w > N::default() && rect.size.h > N::default()
+}
+
+fn compare<N: Coordinate>(a: &N, b: &N) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+// Splits the area covered by a set of rectangles into horizontal bands, merging the spans inside
+// every band and neighbouring bands with identical spans.
+fn simplify<N: Coordinate, Kind>(mut rects: Vec<Rectangle<N, Kind>>) -> Vec<Rectangle<N, Kind>> {
+    rects.retain(is_valid);
+    if rects.len() <= 1 {
+        return rects;
+    }
+
+    let mut edges = rects
+        .iter()
+        .flat_map(|rect| [rect.loc.y, rect.loc.y.saturating_add(rect.size.h)])
+        .collect::<Vec<_>>();
+    edges.sort_by(compare);
+    edges.dedup();
+
+    let mut result: Vec<Rectangle<N, Kind>> = Vec::with_capacity(rects.len());
+    let mut prev_spans: Vec<(N, N)> = Vec::new();
+    let mut prev_bottom = None;
+    let mut band_start = 0;
+    for band in edges.windows(2) {
+        let (top, bottom) = (band[0], band[1]);
+
+        let mut spans = rects
+            .iter()
+            .filter(|rect| rect.loc.y <= top && rect.loc.y.saturating_add(rect.size.h) >= bottom)
+            .map(|rect| (rect.loc.x, rect.loc.x.saturating_add(rect.size.w)))
+            .collect::<Vec<_>>();
+        spans.sort_by(|a, b| compare(&a.0, &b.0));
+        let spans = spans
+            .into_iter()
+            .fold(Vec::<(N, N)>::new(), |mut spans, (start, end)| {
+                match spans.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => spans.push((start, end)),
+                }
+                spans
+            });
+
+        if spans.is_empty() {
+            prev_bottom = None;
+            continue;
+        }
+
+        if prev_bottom == Some(top) && spans == prev_spans {
+            // extend the rectangles of the previous band
+            for rect in &mut result[band_start..] {
+                rect.size.h = bottom - rect.loc.y;
+            }
+        } else {
+            band_start = result.len();
+            result.extend(
+                spans
+                    .iter()
+                    .map(|&(start, end)| Rectangle::from_extemities((start, top), (end, bottom))),
+            );
+            prev_spans = spans;
+        }
+        prev_bottom = Some(bottom);
+    }
+
+    result
+}
+
+impl<N: Coordinate, Kind> Default for Region<N, Kind> {
+    fn default() -> Self {
+        Region::new()
+    }
+}
+
+impl<N: Coordinate, Kind> From<Rectangle<N, Kind>> for Region<N, Kind> {
+    fn from(rect: Rectangle<N, Kind>) -> Self {
+        Region::from_rects([rect])
+    }
+}
+
+impl<N: Coordinate, Kind> FromIterator<Rectangle<N, Kind>> for Region<N, Kind> {
+    fn from_iter<T: IntoIterator<Item = Rectangle<N, Kind>>>(iter: T) -> Self {
+        Region::from_rects(iter)
+    }
+}
+
+impl<N: Coordinate, Kind> Extend<Rectangle<N, Kind>> for Region<N, Kind> {
+    fn extend<T: IntoIterator<Item = Rectangle<N, Kind>>>(&mut self, iter: T) {
+        self.union_rects(iter);
+    }
+}
+
+impl<N: Clone, Kind> Clone for Region<N, Kind> {
+    fn clone(&self) -> Self {
+        Region {
+            rects: self.rects.clone(),
+        }
+    }
+}
+
+impl<N: PartialEq, Kind> PartialEq for Region<N, Kind> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rects == other.rects
+    }
+}
+
+impl<N: fmt::Debug, Kind> fmt::Debug for Region<N, Kind>
+where
+    Rectangle<N, Kind>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Region").field(&self.rects).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Region;
+    use crate::utils::{Logical, Rectangle};
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn union_simplifies() {
+        // two overlapping rectangles
+        let region = Region::from_rects([rect(0, 0, 10, 10), rect(5, 5, 10, 10)]);
+        assert_eq!(
+            region.rects(),
+            &[rect(0, 0, 10, 5), rect(0, 5, 15, 5), rect(5, 10, 10, 5)]
+        );
+        assert_eq!(region.area(), 175);
+
+        // touching rectangles are merged
+        let mut region = Region::from_rects([rect(0, 0, 10, 10), rect(10, 0, 10, 10)]);
+        assert_eq!(region.rects(), &[rect(0, 0, 20, 10)]);
+        region.union_rect(rect(0, 10, 20, 5));
+        assert_eq!(region.rects(), &[rect(0, 0, 20, 15)]);
+
+        // the same area always results in the same rectangles
+        let other = Region::from_rects([rect(0, 0, 5, 15), rect(5, 0, 15, 15)]);
+        assert_eq!(region, other);
+
+        // empty rectangles are dropped
+        assert!(Region::from_rects([rect(0, 0, 0, 10), rect(0, 0, 10, -5)]).is_empty());
+    }
+
+    #[test]
+    fn subtract() {
+        let mut region = Region::from(rect(0, 0, 30, 30));
+        region.subtract_rect(rect(10, 10, 10, 10));
+        assert_eq!(
+            region.rects(),
+            &[
+                rect(0, 0, 30, 10),
+                rect(0, 10, 10, 10),
+                rect(20, 10, 10, 10),
+                rect(0, 20, 30, 10)
+            ]
+        );
+        assert_eq!(region.area(), 800);
+        assert!(region.contains((5, 15)));
+        assert!(!region.contains((15, 15)));
+        assert!(!region.overlaps(rect(12, 12, 5, 5)));
+        assert!(region.contains_rect(rect(0, 0, 30, 10)));
+        assert!(!region.contains_rect(rect(0, 5, 30, 10)));
+
+        // filling the hole again restores the original rectangle
+        region.union_rect(rect(10, 10, 10, 10));
+        assert_eq!(region.rects(), &[rect(0, 0, 30, 30)]);
+
+        region.subtract(&Region::from_rects([rect(0, 0, 30, 15), rect(0, 15, 30, 15)]));
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn intersect() {
+        let mut region = Region::from_rects([rect(0, 0, 10, 10), rect(20, 0, 10, 10)]);
+        region.intersect_rect(rect(5, 5, 20, 20));
+        assert_eq!(region.rects(), &[rect(5, 5, 5, 5), rect(20, 5, 5, 5)]);
+
+        let mut region = Region::from(rect(0, 0, 30, 30));
+        region.intersect(&Region::from_rects([
+            rect(-10, -10, 20, 20),
+            rect(25, 25, 10, 10),
+        ]));
+        assert_eq!(region.rects(), &[rect(0, 0, 10, 10), rect(25, 25, 5, 5)]);
+        assert_eq!(region.bounding_box(), rect(0, 0, 30, 30));
+
+        region.translate((10, 10));
+        assert_eq!(region.rects(), &[rect(10, 10, 10, 10), rect(35, 35, 5, 5)]);
+    }
+}
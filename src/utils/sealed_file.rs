@@ -10,6 +10,8 @@ use std::{
     os::unix::io::{AsRawFd, FromRawFd, RawFd},
 };
 
+use tracing::debug;
+
 #[derive(Debug)]
 pub(crate) struct SealedFile {
     file: File,
@@ -21,8 +23,31 @@ impl SealedFile {
         Self::with_data(name, contents.as_bytes_with_nul())
     }
 
-    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+    /// Create a read-only file containing `data`
+    ///
+    /// A sealed memfd is used where available. If the kernel does not support memfds or sealing,
+    /// this falls back to an unlinked shm object re-opened read-only and finally to an unlinked
+    /// temporary file.
     pub fn with_data(name: CString, data: &[u8]) -> Result<Self, std::io::Error> {
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+        match Self::with_memfd(&name, data) {
+            Ok(file) => return Ok(file),
+            Err(err) => debug!("Failed to create sealed memfd, falling back to shm_open: {}", err),
+        }
+
+        match Self::with_shm(&name, data) {
+            Ok(file) => return Ok(file),
+            Err(err) => debug!(
+                "Failed to create shm file, falling back to a temporary file: {}",
+                err
+            ),
+        }
+
+        Self::with_tmpfile(data)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "android"))]
+    fn with_memfd(name: &CString, data: &[u8]) -> Result<Self, std::io::Error> {
         use nix::{
             fcntl::{FcntlArg, SealFlag},
             sys::memfd::MemFdCreateFlag,
@@ -30,7 +55,7 @@ impl SealedFile {
         use std::io::Seek;
 
         let fd = nix::sys::memfd::memfd_create(
-            &name,
+            name,
             MemFdCreateFlag::MFD_CLOEXEC | MemFdCreateFlag::MFD_ALLOW_SEALING,
         )?;
 
@@ -56,8 +81,7 @@ impl SealedFile {
         })
     }
 
-    #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "android")))]
-    pub fn with_data(name: CString, data: &[u8]) -> Result<Self, std::io::Error> {
+    fn with_shm(name: &CString, data: &[u8]) -> Result<Self, std::io::Error> {
         use nix::{
             errno::Errno,
             fcntl::OFlag,
@@ -71,13 +95,18 @@ impl SealedFile {
         // loop a couple times if it exists.
         let mut n = 0;
         let (shm_name, mut file) = loop {
-            let mut shm_name = name.as_bytes().to_owned();
+            let mut shm_name = Vec::with_capacity(name.as_bytes().len() + 9);
+            // portable shm names start with a slash
+            if name.as_bytes().first() != Some(&b'/') {
+                shm_name.push(b'/');
+            }
+            shm_name.extend_from_slice(name.as_bytes());
             shm_name.push(b'-');
             shm_name.extend((0..7).map(|_| rng.sample(Alphanumeric)));
             let fd = mman::shm_open(
                 shm_name.as_slice(),
-                OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_EXCL,
-                Mode::S_IRWXU,
+                OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_CLOEXEC,
+                Mode::S_IRUSR | Mode::S_IWUSR,
             );
             if fd != Err(Errno::EEXIST) || n > 3 {
                 break (shm_name, unsafe { File::from_raw_fd(fd?) });
@@ -86,12 +115,16 @@ impl SealedFile {
         };
 
         // Sealing isn't available, so re-open read-only.
-        let fd_rdonly = mman::shm_open(shm_name.as_slice(), OFlag::O_RDONLY, Mode::empty())?;
-        let file_rdonly = unsafe { File::from_raw_fd(fd_rdonly) };
+        let fd_rdonly = mman::shm_open(
+            shm_name.as_slice(),
+            OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+            Mode::empty(),
+        );
 
         // Unlink so another process can't open shm file.
         let _ = mman::shm_unlink(shm_name.as_slice());
 
+        let file_rdonly = unsafe { File::from_raw_fd(fd_rdonly?) };
         file.write_all(data)?;
         file.flush()?;
 
@@ -101,6 +134,20 @@ impl SealedFile {
         })
     }
 
+    fn with_tmpfile(data: &[u8]) -> Result<Self, std::io::Error> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        file.write_all(data)?;
+        file.flush()?;
+
+        // Re-open read-only, the file is unlinked once `file` is dropped.
+        let file_rdonly = File::open(file.path())?;
+
+        Ok(Self {
+            file: file_rdonly,
+            size: data.len(),
+        })
+    }
+
     // Only used in KeymapFile which is under the wayland_frontend feature
     pub fn size(&self) -> usize {
         self.size
@@ -1,17 +1,43 @@
 use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "wayland_frontend")]
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+#[cfg(feature = "wayland_frontend")]
+use wayland_server::backend::ClientId;
 
 /// A global [`SerialCounter`] for use in your compositor.
 ///
 /// Is is also used internally by some parts of Smithay.
-pub static SERIAL_COUNTER: SerialCounter = SerialCounter {
-    serial: AtomicU32::new(0),
-};
+pub static SERIAL_COUNTER: SerialCounter = SerialCounter::new();
 
 /// A serial type, whose comparison takes into account the wrapping-around behavior of the
 /// underlying counter.
 #[derive(Debug, Copy, Clone)]
 pub struct Serial(pub(crate) u32);
 
+impl Serial {
+    /// Returns the distance to another serial, taking wrap-around into account
+    ///
+    /// This is the number of serials issued between the two serials.
+    pub fn distance(&self, other: &Serial) -> u32 {
+        self.0.wrapping_sub(other.0).min(other.0.wrapping_sub(self.0))
+    }
+
+    /// Checks whether this serial was issued after or is equal to `other`,
+    /// taking wrap-around into account
+    pub fn is_no_older_than(&self, other: &Serial) -> bool {
+        self >= other
+    }
+
+    /// Checks whether this serial was issued after `other`, taking wrap-around into account
+    pub fn is_newer_than(&self, other: &Serial) -> bool {
+        self > other
+    }
+}
+
 impl PartialEq for Serial {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
@@ -20,6 +46,12 @@ impl PartialEq for Serial {
 
 impl Eq for Serial {}
 
+impl std::hash::Hash for Serial {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
 impl PartialOrd for Serial {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         let distance = if self.0 > other.0 {
@@ -62,12 +94,135 @@ pub struct SerialCounter {
 }
 
 impl SerialCounter {
+    /// Create a new counter
+    ///
+    /// Prefer the global [`SERIAL_COUNTER`], serials of different counters must not be mixed.
+    pub const fn new() -> Self {
+        SerialCounter {
+            serial: AtomicU32::new(0),
+        }
+    }
+
     /// Retrieve the next serial from the counter
+    ///
+    /// This is safe to call from multiple threads, every call returns a different serial
+    /// (until the counter wraps around).
     pub fn next_serial(&self) -> Serial {
         Serial(self.serial.fetch_add(1, Ordering::AcqRel))
     }
 }
 
+impl Default for SerialCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maximum number of clients tracked by a [`SerialValidator`]
+#[cfg(feature = "wayland_frontend")]
+const MAX_CLIENTS: usize = 64;
+
+/// Record of the serials recently sent to clients
+///
+/// Requests like `xdg_toplevel.move`, `xdg_toplevel.resize` or `xdg_activation_v1.activate` carry
+/// the serial of the input event, that triggered them. A [`SerialValidator`] remembers the last
+/// serials sent to every client, so these requests can cheaply be rejected, if the serial was
+/// never sent to the client (forged) or many other events were sent since then (stale).
+///
+/// Every [`Seat`](crate::input::Seat) records the serials of the `wl_pointer`, `wl_keyboard`
+/// and `wl_touch` events it sends, see [`Seat::serial_validator`](crate::input::Seat::serial_validator).
+#[cfg(feature = "wayland_frontend")]
+#[derive(Debug)]
+pub struct SerialValidator {
+    capacity: usize,
+    clients: Mutex<HashMap<ClientId, VecDeque<Serial>>>,
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl SerialValidator {
+    /// Default number of serials remembered per client
+    pub const DEFAULT_CAPACITY: usize = 16;
+
+    /// Create a new validator remembering the last `capacity` serials of every client
+    pub fn new(capacity: usize) -> Self {
+        SerialValidator {
+            capacity: capacity.max(1),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a serial sent to a client
+    pub fn record(&self, client: &ClientId, serial: Serial) {
+        let mut clients = self.clients.lock().unwrap();
+        if !clients.contains_key(client) && clients.len() >= MAX_CLIENTS {
+            // forget the client, that did not receive any serial for the longest time,
+            // usually because it is gone already.
+            let oldest = clients
+                .iter()
+                .filter_map(|(id, serials)| serials.back().map(|serial| (id, *serial)))
+                .reduce(|oldest, other| if other.1 < oldest.1 { other } else { oldest })
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                clients.remove(&oldest);
+            }
+        }
+
+        let serials = clients.entry(client.clone()).or_default();
+        if serials.back() == Some(&serial) {
+            return;
+        }
+        if serials.len() >= self.capacity {
+            serials.pop_front();
+        }
+        serials.push_back(serial);
+    }
+
+    /// Retrieve the next serial from `counter` and record it for a client
+    pub fn next_serial(&self, counter: &SerialCounter, client: &ClientId) -> Serial {
+        let serial = counter.next_serial();
+        self.record(client, serial);
+        serial
+    }
+
+    /// Checks whether the serial was recently sent to the client
+    pub fn validate(&self, client: &ClientId, serial: Serial) -> bool {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(client)
+            .map(|serials| serials.contains(&serial))
+            .unwrap_or(false)
+    }
+
+    /// Checks whether the serial was recently sent to the client and is not older than `since`
+    ///
+    /// This may be used to e.g. only accept serials issued after a surface gained focus.
+    pub fn validate_since(&self, client: &ClientId, serial: Serial, since: Serial) -> bool {
+        serial.is_no_older_than(&since) && self.validate(client, serial)
+    }
+
+    /// Returns the last serial sent to the client
+    pub fn last_serial(&self, client: &ClientId) -> Option<Serial> {
+        self.clients
+            .lock()
+            .unwrap()
+            .get(client)
+            .and_then(|serials| serials.back().copied())
+    }
+
+    /// Forget all serials of a client, e.g. because it disconnected
+    pub fn forget_client(&self, client: &ClientId) {
+        self.clients.lock().unwrap().remove(client);
+    }
+}
+
+#[cfg(feature = "wayland_frontend")]
+impl Default for SerialValidator {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,5 +272,21 @@ mod tests {
         assert!(serial2 == 0.into());
 
         assert!(serial1 < serial2);
+        assert!(serial2.is_newer_than(&serial1));
+        assert_eq!(serial1.distance(&serial2), 1);
+        assert_eq!(serial2.distance(&serial1), 1);
+    }
+
+    #[test]
+    fn serial_ordering_helpers() {
+        let serial1 = Serial::from(10);
+        let serial2 = Serial::from(25);
+
+        assert!(serial2.is_newer_than(&serial1));
+        assert!(!serial1.is_newer_than(&serial2));
+        assert!(serial1.is_no_older_than(&serial1));
+        assert!(!serial1.is_newer_than(&serial1));
+        assert_eq!(serial1.distance(&serial2), 15);
+        assert_eq!(serial1.distance(&u32::MAX.into()), 11);
     }
 }
@@ -23,8 +23,8 @@ use crate::utils::{
 use super::{
     cache::Cacheable,
     tree::{Location, PrivateSurfaceData},
-    AlreadyHasRole, BufferAssignment, CompositorHandler, CompositorState, Damage, Rectangle, RectangleKind,
-    RegionAttributes, SurfaceAttributes,
+    AlreadyHasRole, BufferAssignment, CommitVerdict, CompositorHandler, CompositorState, Damage, Rectangle,
+    RectangleKind, RegionAttributes, SurfaceAttributes,
 };
 
 use tracing::trace;
@@ -33,6 +33,10 @@ use tracing::trace;
  * wl_compositor
  */
 
+fn client_scale<D: CompositorHandler>(state: &D, client: &wayland_server::Client) -> i32 {
+    state.client_compositor_state(client).client_scale() as i32
+}
+
 impl<D> GlobalDispatch<WlCompositor, (), D> for CompositorState
 where
     D: GlobalDispatch<WlCompositor, ()>,
@@ -64,7 +68,7 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         _resource: &WlCompositor,
         request: wl_compositor::Request,
         _data: &(),
@@ -75,18 +79,25 @@ where
             wl_compositor::Request::CreateSurface { id } => {
                 trace!(id = ?id, "Creating a new wl_surface");
 
+                let scale = client_scale(state, client);
                 let surface = data_init.init(
                     id,
                     SurfaceUserData {
                         inner: PrivateSurfaceData::new(),
                         alive_tracker: Default::default(),
                         user_state_type: (std::any::TypeId::of::<D>(), std::any::type_name::<D>()),
+                        client_scale: scale,
                     },
                 );
 
                 state.compositor_state().surfaces.push(surface.clone());
 
                 PrivateSurfaceData::init(&surface);
+                if scale != 1 {
+                    PrivateSurfaceData::with_states(&surface, |states| {
+                        states.cached_state.pending::<SurfaceAttributes>().buffer_scale = scale;
+                    });
+                }
                 state.new_surface(&surface);
             }
             wl_compositor::Request::CreateRegion { id } => {
@@ -152,6 +163,8 @@ pub struct SurfaceUserData {
     pub(crate) inner: Mutex<PrivateSurfaceData>,
     alive_tracker: AliveTracker,
     pub(super) user_state_type: (std::any::TypeId, &'static str),
+    /// Client scale of the owning client at the time the surface was created
+    pub(super) client_scale: i32,
 }
 
 impl<D> Dispatch<WlSurface, SurfaceUserData, D> for CompositorState
@@ -163,7 +176,7 @@ where
 {
     fn request(
         state: &mut D,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         surface: &WlSurface,
         request: wl_surface::Request,
         _data: &SurfaceUserData,
@@ -172,7 +185,7 @@ where
     ) {
         match request {
             wl_surface::Request::Attach { buffer, x, y } => {
-                let offset: Point<i32, Logical> = (x, y).into();
+                let offset: Point<i32, Logical> = Point::from((x, y)).downscale(client_scale(state, client));
                 let offset = (x != 0 || y != 0).then_some(offset);
 
                 // If version predates 5 just use the offset
@@ -205,15 +218,16 @@ where
                 });
             }
             wl_surface::Request::Damage { x, y, width, height } => {
+                let damage = Rectangle::<i32, Logical>::from_loc_and_size((x, y), (width, height))
+                    .to_f64()
+                    .downscale(client_scale(state, client) as f64)
+                    .to_i32_up();
                 PrivateSurfaceData::with_states(surface, |states| {
                     states
                         .cached_state
                         .pending::<SurfaceAttributes>()
                         .damage
-                        .push(Damage::Surface(Rectangle::from_loc_and_size(
-                            (x, y),
-                            (width, height),
-                        )));
+                        .push(Damage::Surface(damage));
                 });
             }
             wl_surface::Request::Frame { callback } => {
@@ -246,6 +260,13 @@ where
                 });
             }
             wl_surface::Request::Commit => {
+                if PrivateSurfaceData::invoke_validation_hooks(state, handle, surface)
+                    == CommitVerdict::Reject
+                {
+                    tracing::debug!(surface = ?surface.id(), "Commit rejected by validation hook");
+                    return;
+                }
+
                 PrivateSurfaceData::invoke_pre_commit_hooks(state, handle, surface);
 
                 PrivateSurfaceData::commit(surface, handle, state);
@@ -261,6 +282,10 @@ where
                 }
             }
             wl_surface::Request::SetBufferScale { scale } => {
+                let Some(scale) = scale.checked_mul(client_scale(state, client)) else {
+                    surface.post_error(wl_surface::Error::InvalidScale, "Buffer scale is too large.");
+                    return;
+                };
                 PrivateSurfaceData::with_states(surface, |states| {
                     states.cached_state.pending::<SurfaceAttributes>().buffer_scale = scale;
                 });
@@ -340,23 +365,30 @@ where
     D: CompositorHandler,
 {
     fn request(
-        _state: &mut D,
-        _client: &wayland_server::Client,
+        state: &mut D,
+        client: &wayland_server::Client,
         _resource: &WlRegion,
         request: wl_region::Request,
         data: &RegionUserData,
         _dhandle: &DisplayHandle,
         _init: &mut wayland_server::DataInit<'_, D>,
     ) {
+        let scale = client_scale(state, client);
         let mut guard = data.inner.lock().unwrap();
         match request {
             wl_region::Request::Add { x, y, width, height } => guard.rects.push((
                 RectangleKind::Add,
-                Rectangle::from_loc_and_size((x, y), (width, height)),
+                Rectangle::from_loc_and_size((x, y), (width, height))
+                    .to_f64()
+                    .downscale(scale as f64)
+                    .to_i32_round(),
             )),
             wl_region::Request::Subtract { x, y, width, height } => guard.rects.push((
                 RectangleKind::Subtract,
-                Rectangle::from_loc_and_size((x, y), (width, height)),
+                Rectangle::from_loc_and_size((x, y), (width, height))
+                    .to_f64()
+                    .downscale(scale as f64)
+                    .to_i32_round(),
             )),
             wl_region::Request::Destroy => {
                 // all is handled by our destructor
@@ -513,8 +545,8 @@ where
     D: 'static,
 {
     fn request(
-        _state: &mut D,
-        _client: &wayland_server::Client,
+        state: &mut D,
+        client: &wayland_server::Client,
         subsurface: &WlSubsurface,
         request: wl_subsurface::Request,
         data: &SubsurfaceUserData,
@@ -523,8 +555,9 @@ where
     ) {
         match request {
             wl_subsurface::Request::SetPosition { x, y } => {
+                let location = Point::from((x, y)).downscale(client_scale(state, client));
                 PrivateSurfaceData::with_states(&data.surface, |state| {
-                    state.cached_state.pending::<SubsurfaceCachedState>().location = (x, y).into();
+                    state.cached_state.pending::<SubsurfaceCachedState>().location = location;
                 })
             }
             wl_subsurface::Request::PlaceAbove { sibling } => {
@@ -77,23 +77,27 @@
 //! On commit of a surface several steps are taken to update the state of the surface. Actions
 //! are taken by smithay in the following order:
 //!
-//! 1. Pre Commit hooks registered to this surface are invoked. Such hooks can be registered using
+//! 1. Validation hooks registered to this surface are invoked. Such hooks can be registered using
+//!    the [`add_validation_hook`] function. They can enforce policies on the pending state, like
+//!    refusing too large buffers, and reject the commit. A rejected commit is ignored and the
+//!    remaining steps are skipped.
+//! 2. Pre Commit hooks registered to this surface are invoked. Such hooks can be registered using
 //!    the [`add_pre_commit_hook`] function. They are typically used by protocol extensions that
 //!    add state to a surface and need to check on commit that client did not request an
 //!    illegal state before it is applied on commit.
-//! 2. The pending state is either applied and made current, or cached for later application
+//! 3. The pending state is either applied and made current, or cached for later application
 //!    is the surface is a synchronize subsurface. If the current state is applied, state
 //!    of the synchronized children subsurface are applied as well at this point.
-//! 3. Post Commit hooks registered to this surface are invoked. Such hooks can be registered using
+//! 4. Post Commit hooks registered to this surface are invoked. Such hooks can be registered using
 //!    the [`add_post_commit_hook`] function. They are typically used by abstractions that further process
 //!    the state.
-//! 4. Your implementation of [`CompositorHandler::commit`] is invoked, so that you can access
+//! 5. Your implementation of [`CompositorHandler::commit`] is invoked, so that you can access
 //!    the new current state of the surface. The state of sync children subsurfaces of your
 //!    surface may have changed as well, so this is the place to check it, using functions
 //!    like [`with_surface_tree_upward`] or [`with_surface_tree_downward`]. On the other hand,
 //!    if the surface is a sync subsurface, its current state will note have changed as
 //!    the result of that commit. You can check if it is using [`is_sync_subsurface`].
-//! 5. If the surface is destroyed, destruction hooks are invoked. Such hooks can be registered
+//! 6. If the surface is destroyed, destruction hooks are invoked. Such hooks can be registered
 //!    using the [`add_destruction_hook`] function. They are typically used to cleanup associated
 //!    state.
 //!
@@ -104,21 +108,34 @@
 //! Smithay represents this role as a `&'static str` identifier, that can only be set once
 //! on a surface. See [`give_role`] and [`get_role`] for details. This module manages the
 //! subsurface role, which is identified by the string `"subsurface"`.
+//!
+//! Protocol extensions defining their own roles can implement the [`SurfaceRole`] trait, which
+//! associates typed role data and lifecycle hooks with the role identifier. Such a role is
+//! assigned with [`give_role_with_data`] and its data accessed with [`with_role_data`].
 
 mod cache;
 mod handlers;
+mod role;
 mod transaction;
 mod tree;
 
-use std::{any::Any, sync::Mutex};
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
 
 pub use self::cache::{Cacheable, MultiCache};
 pub use self::handlers::{RegionUserData, SubsurfaceCachedState, SubsurfaceUserData, SurfaceUserData};
+pub use self::role::{give_role_with_data, has_role, with_role_data, SurfaceRole};
 use self::transaction::TransactionQueue;
-pub use self::transaction::{Blocker, BlockerState};
+pub use self::transaction::{Blocker, BlockerState, DeadlineAction, DeadlineBlocker};
 use self::tree::PrivateSurfaceData;
 pub use self::tree::{AlreadyHasRole, TraversalAction};
-use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle};
+use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle, Region};
 use wayland_server::backend::GlobalId;
 use wayland_server::protocol::wl_compositor::WlCompositor;
 use wayland_server::protocol::wl_subcompositor::WlSubcompositor;
@@ -300,6 +317,18 @@ impl RegionAttributes {
         }
         contains
     }
+
+    /// Computes the area described by the rectangles of this region
+    pub fn to_region(&self) -> Region<i32, Logical> {
+        let mut region = Region::new();
+        for (kind, rect) in &self.rects {
+            match kind {
+                RectangleKind::Add => region.union_rect(*rect),
+                RectangleKind::Subtract => region.subtract_rect(*rect),
+            }
+        }
+        region
+    }
 }
 
 /// Access the data of a surface tree from bottom to top
@@ -379,6 +408,14 @@ pub fn is_sync_subsurface(surface: &WlSurface) -> bool {
     self::handlers::is_effectively_sync(surface)
 }
 
+/// Client scale of the client owning this surface, see [`CompositorClientState::set_client_scale`]
+pub(crate) fn surface_client_scale(surface: &WlSurface) -> i32 {
+    surface
+        .data::<SurfaceUserData>()
+        .map(|data| data.client_scale)
+        .unwrap_or(1)
+}
+
 /// Get the current role of this surface
 pub fn get_role(surface: &WlSurface) -> Option<&'static str> {
     PrivateSurfaceData::get_role(surface)
@@ -410,6 +447,47 @@ pub fn get_region_attributes(region: &wl_region::WlRegion) -> RegionAttributes {
     }
 }
 
+/// Result of a validation hook, see [`add_validation_hook`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommitVerdict {
+    /// The commit is valid and will be processed
+    Accept,
+    /// The commit is rejected and will be ignored
+    Reject,
+}
+
+/// Register a validation hook to be invoked on surface commit
+///
+/// It'll be invoked on surface commit, before any pre-commit hook. Validation hooks can
+/// inspect and modify the pending state of the surface and decide whether the commit is processed.
+///
+/// If any hook returns [`CommitVerdict::Reject`], the commit is ignored: The pending state
+/// is left untouched and no other hooks or [`CompositorHandler::commit`] are invoked.
+/// To disconnect a misbehaving client, the hook should post a protocol error on the
+/// appropriate resource and reject the commit.
+///
+/// D generic is the compositor state, same as used in `CompositorState::new<D>()`
+pub fn add_validation_hook<D, F>(surface: &WlSurface, hook: F)
+where
+    F: Fn(&mut D, &DisplayHandle, &WlSurface) -> CommitVerdict + Send + Sync + 'static,
+    D: 'static,
+{
+    let (user_state_type_id, user_state_type) = surface.data::<SurfaceUserData>().unwrap().user_state_type;
+    assert_eq!(
+        std::any::TypeId::of::<D>(),
+        user_state_type_id,
+        "D has to equal D used in CompositorState::new<D>(), {} != {}",
+        std::any::type_name::<D>(),
+        user_state_type,
+    );
+
+    let hook = move |state: &mut dyn Any, dh: &DisplayHandle, surface: &WlSurface| {
+        let state = state.downcast_mut::<D>().unwrap();
+        hook(state, dh, surface)
+    };
+    PrivateSurfaceData::add_validation_hook(surface, hook)
+}
+
 /// Register a pre-commit hook to be invoked on surface commit
 ///
 /// It'll be invoked on surface commit, *before* the new state is merged into the current state.
@@ -494,6 +572,9 @@ where
 /// The module will only evaluate blocker states on commit. If a blocker
 /// becomes ready later, a call to [`CompositorClientState::blocker_cleared`] is necessary
 /// to trigger a re-evaluation.
+///
+/// To make sure a blocker does not stall the surface forever, wrap it in a [`DeadlineBlocker`]
+/// and re-evaluate the blockers at [`CompositorClientState::next_blocker_deadline`].
 pub fn add_blocker(surface: &WlSurface, blocker: impl Blocker + Send + 'static) {
     PrivateSurfaceData::add_blocker(surface, blocker)
 }
@@ -511,7 +592,7 @@ pub trait CompositorHandler {
 
     /// New surface handler.
     ///
-    /// This handler can be used to setup hooks (see [`add_pre_commit_hook`]/[`add_post_commit_hook`]/[`add_destruction_hook`]),
+    /// This handler can be used to setup hooks (see [`add_validation_hook`]/[`add_pre_commit_hook`]/[`add_post_commit_hook`]/[`add_destruction_hook`]),
     /// but not much else. The surface has no role or attached data at this point and cannot be rendered.
     fn new_surface(&mut self, surface: &WlSurface) {
         let _ = surface;
@@ -542,12 +623,44 @@ pub struct CompositorState {
 }
 
 /// Per-client state of a compositor
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct CompositorClientState {
     queue: Mutex<Option<TransactionQueue>>,
+    client_scale: AtomicU32,
+}
+
+impl Default for CompositorClientState {
+    fn default() -> Self {
+        CompositorClientState {
+            queue: Mutex::new(None),
+            client_scale: AtomicU32::new(1),
+        }
+    }
 }
 
 impl CompositorClientState {
+    /// Returns the scale applied to the coordinate space of this client
+    pub fn client_scale(&self) -> u32 {
+        self.client_scale.load(Ordering::Acquire)
+    }
+
+    /// Set a scale applied to the coordinate space of this client
+    ///
+    /// The client then operates in a coordinate space that is `scale` times
+    /// as large as the logical compositor space: Buffer scales, surface damage,
+    /// buffer offsets, regions and subsurface positions of the client are divided
+    /// by the scale, while xdg-output positions and sizes as well as pointer and touch
+    /// coordinates sent to the client are multiplied by it.
+    ///
+    /// This is mostly useful for clients unable to handle scaled outputs themselves,
+    /// like XWayland, to render at the native resolution of HiDPI outputs.
+    ///
+    /// The scale should be set before the client creates any objects,
+    /// existing surfaces and outputs are not updated.
+    pub fn set_client_scale(&self, scale: u32) {
+        self.client_scale.store(scale.max(1), Ordering::Release);
+    }
+
     /// To be called, when a previously added blocker (via [`add_blocker`])
     /// got `Released` or `Cancelled` from being `Pending` previously for any
     /// surface belonging to this client.
@@ -562,6 +675,44 @@ impl CompositorClientState {
             transaction.apply(dh, state)
         }
     }
+
+    /// Returns the earliest deadline of any blocker currently delaying state changes of this client
+    ///
+    /// The compositor should call [`blocker_cleared`](CompositorClientState::blocker_cleared) once
+    /// this point in time is reached, e.g. by using a timer, so blockers resolving on their deadline
+    /// (see [`DeadlineBlocker`]) are re-evaluated.
+    pub fn next_blocker_deadline(&self) -> Option<Instant> {
+        self.queue
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|queue| queue.next_deadline())
+    }
+
+    /// Apply all state changes of this client currently delayed by blockers
+    ///
+    /// The changes are applied in the order they were committed, ignoring the state of their blockers.
+    pub fn release_blockers<D: CompositorHandler + 'static>(&self, state: &mut D, dh: &DisplayHandle) {
+        let transactions = if let Some(queue) = self.queue.lock().unwrap().as_mut() {
+            queue.take_all()
+        } else {
+            Vec::new()
+        };
+
+        for transaction in transactions {
+            transaction.apply(dh, state)
+        }
+    }
+
+    /// Discard all state changes of this client currently delayed by blockers
+    ///
+    /// This acts as if all pending blockers got `Cancelled`. The discarded states are applied
+    /// alongside the next state change of the respective surfaces, which is not blocked.
+    pub fn cancel_blockers(&self) {
+        if let Some(queue) = self.queue.lock().unwrap().as_mut() {
+            queue.take_all();
+        }
+    }
 }
 
 #[doc(hidden)]
@@ -680,5 +831,11 @@ mod tests {
         assert!(!region.contains((0, 0)));
         assert!(region.contains((5, 5)));
         assert!(region.contains((2, 2)));
+
+        let region = region.to_region();
+        assert!(!region.contains((0, 0)));
+        assert!(region.contains((5, 5)));
+        assert!(region.contains((2, 2)));
+        assert_eq!(region.area(), 79);
     }
 }
@@ -0,0 +1,134 @@
+// Typed surface roles
+//
+// Roles are identified by a `&'static str` in `SurfaceData::role`. This file provides a typed
+// layer on top of it for protocol extensions defining their own roles: the data of a role is
+// stored in the `data_map` of the surface, wrapped in a type private to this module so it can
+// not collide with other user data, and the optional lifecycle hooks of the role are registered
+// as regular pre-commit and destruction hooks when the role is first assigned.
+
+use std::sync::{Mutex, MutexGuard};
+
+use wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle};
+
+use super::{tree::PrivateSurfaceData, AlreadyHasRole, SurfaceData};
+
+/// A surface role with typed role data
+///
+/// Protocol extensions can implement this trait to define a custom surface role. A surface
+/// gets the role assigned with [`give_role_with_data`], which also stores the role data
+/// on the surface. The data can then be accessed using [`with_role_data`] or
+/// [`SurfaceData::role_data`].
+///
+/// ```no_run
+/// use smithay::wayland::compositor::{give_role_with_data, with_role_data, SurfaceRole};
+///
+/// struct CursorRole;
+///
+/// #[derive(Default)]
+/// struct CursorRoleData {
+///     hotspot: (i32, i32),
+/// }
+///
+/// impl SurfaceRole for CursorRole {
+///     const NAME: &'static str = "my_cursor";
+///     type Data = CursorRoleData;
+/// }
+///
+/// # let surface: smithay::reexports::wayland_server::protocol::wl_surface::WlSurface = unimplemented!();
+/// give_role_with_data::<CursorRole>(&surface, CursorRoleData::default())
+///     .expect("Surface already has a different role");
+/// let hotspot = with_role_data::<CursorRole, _>(&surface, |data| data.hotspot);
+/// ```
+pub trait SurfaceRole: 'static {
+    /// Identifier of the role, as returned by [`get_role`](super::get_role)
+    ///
+    /// It needs to be unique, roles of different types sharing a name conflict with each other.
+    const NAME: &'static str;
+
+    /// Data associated with a surface having this role
+    type Data: Send + 'static;
+
+    /// Invoked on commit of a surface with this role, before the pending state is applied
+    ///
+    /// This can be used to validate the pending state, see [`add_pre_commit_hook`](super::add_pre_commit_hook).
+    /// The states of the surface are locked while this is invoked, so other functions of this
+    /// module must not be called for the surface.
+    fn pre_commit(dh: &DisplayHandle, surface: &WlSurface, states: &SurfaceData, data: &mut Self::Data) {
+        let _ = (dh, surface, states, data);
+    }
+
+    /// Invoked when a surface with this role is destroyed
+    fn destroyed(states: &SurfaceData, data: &mut Self::Data) {
+        let _ = (states, data);
+    }
+}
+
+struct RoleData<R: SurfaceRole>(Mutex<R::Data>);
+
+/// Give a surface a role with the associated role data
+///
+/// Fails if the surface already has a different role. If the surface already has the
+/// same role, its role data is replaced.
+pub fn give_role_with_data<R: SurfaceRole>(surface: &WlSurface, data: R::Data) -> Result<(), AlreadyHasRole> {
+    PrivateSurfaceData::set_role(surface, R::NAME)?;
+
+    let inserted = PrivateSurfaceData::with_states(surface, |states| {
+        let mut data = Some(data);
+        let inserted = states
+            .data_map
+            .insert_if_missing_threadsafe(|| RoleData::<R>(Mutex::new(data.take().unwrap())));
+        if let Some(data) = data {
+            *states.role_data::<R>().unwrap() = data;
+        }
+        inserted
+    });
+
+    if inserted {
+        PrivateSurfaceData::add_pre_commit_hook(surface, |_, dh, surface| {
+            PrivateSurfaceData::with_states(surface, |states| {
+                if let Some(mut data) = states.role_data::<R>() {
+                    R::pre_commit(dh, surface, states, &mut data);
+                }
+            })
+        });
+        PrivateSurfaceData::add_destruction_hook(surface, |_, states| {
+            if let Some(mut data) = states.role_data::<R>() {
+                R::destroyed(states, &mut data);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Check if a surface has the given role
+pub fn has_role<R: SurfaceRole>(surface: &WlSurface) -> bool {
+    PrivateSurfaceData::get_role(surface) == Some(R::NAME)
+}
+
+/// Access the role data of a surface
+///
+/// Returns `None` if the surface does not have the given role.
+pub fn with_role_data<R, T>(surface: &WlSurface, f: impl FnOnce(&mut R::Data) -> T) -> Option<T>
+where
+    R: SurfaceRole,
+{
+    PrivateSurfaceData::with_states(surface, |states| {
+        states.role_data::<R>().map(|mut data| f(&mut data))
+    })
+}
+
+impl SurfaceData {
+    /// Access the role data of this surface
+    ///
+    /// Returns `None` if the surface does not have the given role.
+    /// See [`SurfaceRole`] for details.
+    pub fn role_data<R: SurfaceRole>(&self) -> Option<MutexGuard<'_, R::Data>> {
+        if self.role != Some(R::NAME) {
+            return None;
+        }
+        self.data_map
+            .get::<RoleData<R>>()
+            .map(|data| data.0.lock().unwrap())
+    }
+}
@@ -42,6 +42,7 @@ use std::{
     collections::HashSet,
     fmt,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource};
@@ -54,6 +55,16 @@ use super::{tree::PrivateSurfaceData, CompositorHandler};
 pub trait Blocker {
     /// Retrieve the current state of the blocker
     fn state(&self) -> BlockerState;
+
+    /// Point in time at which the blocker resolves on its own, if any
+    ///
+    /// The state of a blocker is only evaluated on commit or on calls to
+    /// [`CompositorClientState::blocker_cleared`](super::CompositorClientState::blocker_cleared),
+    /// see [`CompositorClientState::next_blocker_deadline`](super::CompositorClientState::next_blocker_deadline)
+    /// to schedule a re-evaluation.
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
 }
 
 /// States of a [`Blocker`]
@@ -67,6 +78,72 @@ pub enum BlockerState {
     Cancelled,
 }
 
+/// What a [`DeadlineBlocker`] resolves to once its deadline passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeadlineAction {
+    /// Apply the blocked changes anyway
+    Release,
+    /// Discard the blocked changes
+    Cancel,
+}
+
+/// A [`Blocker`] wrapper, that stops blocking after a deadline
+///
+/// This makes sure a blocker that never resolves, like a dmabuf fence of a stalled
+/// or malicious client, cannot freeze the surface tree forever.
+#[derive(Debug)]
+pub struct DeadlineBlocker<B> {
+    blocker: B,
+    deadline: Instant,
+    action: DeadlineAction,
+}
+
+impl<B: Blocker> DeadlineBlocker<B> {
+    /// Wrap a blocker, resolving it with `action` once `deadline` passed
+    pub fn new(blocker: B, deadline: Instant, action: DeadlineAction) -> Self {
+        DeadlineBlocker {
+            blocker,
+            deadline,
+            action,
+        }
+    }
+
+    /// Wrap a blocker, resolving it with `action` once `timeout` elapsed
+    pub fn with_timeout(blocker: B, timeout: Duration, action: DeadlineAction) -> Self {
+        Self::new(blocker, Instant::now() + timeout, action)
+    }
+
+    /// Access the wrapped blocker
+    pub fn get_ref(&self) -> &B {
+        &self.blocker
+    }
+
+    /// Unwrap the wrapped blocker
+    pub fn into_inner(self) -> B {
+        self.blocker
+    }
+}
+
+impl<B: Blocker> Blocker for DeadlineBlocker<B> {
+    fn state(&self) -> BlockerState {
+        match self.blocker.state() {
+            BlockerState::Pending if Instant::now() >= self.deadline => match self.action {
+                DeadlineAction::Release => BlockerState::Released,
+                DeadlineAction::Cancel => BlockerState::Cancelled,
+            },
+            state => state,
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(
+            self.blocker
+                .deadline()
+                .map_or(self.deadline, |deadline| deadline.min(self.deadline)),
+        )
+    }
+}
+
 #[derive(Default)]
 struct TransactionState {
     surfaces: Vec<(WlSurface, Serial)>,
@@ -209,6 +286,15 @@ impl Transaction {
             })
     }
 
+    /// Returns the earliest deadline of the pending blockers of this transaction
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.blockers
+            .iter()
+            .filter(|blocker| blocker.state() == BlockerState::Pending)
+            .filter_map(|blocker| blocker.deadline())
+            .min()
+    }
+
     pub(crate) fn apply<C: CompositorHandler + 'static>(self, dh: &DisplayHandle, state: &mut C) {
         for (surface, id) in self.surfaces {
             PrivateSurfaceData::with_states(&surface, |states| {
@@ -237,6 +323,16 @@ impl TransactionQueue {
         self.transactions.push(t);
     }
 
+    /// Returns the earliest deadline of all queued transactions
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.transactions.iter().filter_map(Transaction::deadline).min()
+    }
+
+    /// Takes all queued transactions in order, regardless of their blockers
+    pub(crate) fn take_all(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.transactions)
+    }
+
     pub(crate) fn take_ready(&mut self) -> Vec<Transaction> {
         let mut ready_transactions = Vec::new();
         // this is a very non-optimized implementation
@@ -295,3 +391,37 @@ impl TransactionQueue {
         ready_transactions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Never;
+
+    impl Blocker for Never {
+        fn state(&self) -> BlockerState {
+            BlockerState::Pending
+        }
+    }
+
+    #[test]
+    fn deadline_blocker_resolves() {
+        let blocker = DeadlineBlocker::with_timeout(Never, Duration::from_secs(3600), DeadlineAction::Cancel);
+        assert_eq!(blocker.state(), BlockerState::Pending);
+        assert!(blocker.deadline().is_some());
+
+        let past = Instant::now() - Duration::from_millis(1);
+        let blocker = DeadlineBlocker::new(Never, past, DeadlineAction::Release);
+        assert_eq!(blocker.state(), BlockerState::Released);
+        let blocker = DeadlineBlocker::new(Never, past, DeadlineAction::Cancel);
+        assert_eq!(blocker.state(), BlockerState::Cancelled);
+    }
+
+    #[test]
+    fn nested_deadline_blocker_uses_earliest_deadline() {
+        let now = Instant::now();
+        let inner = DeadlineBlocker::new(Never, now + Duration::from_secs(1), DeadlineAction::Release);
+        let outer = DeadlineBlocker::new(inner, now + Duration::from_secs(10), DeadlineAction::Cancel);
+        assert_eq!(outer.deadline(), Some(now + Duration::from_secs(1)));
+    }
+}
@@ -4,18 +4,49 @@ use super::{
     cache::MultiCache,
     handlers::{is_effectively_sync, SurfaceUserData},
     transaction::{Blocker, PendingTransaction, TransactionQueue},
-    BufferAssignment, CompositorHandler, SurfaceAttributes, SurfaceData,
+    BufferAssignment, CommitVerdict, CompositorHandler, SurfaceAttributes, SurfaceData,
 };
 use std::{
     any::Any,
     fmt,
-    sync::{atomic::Ordering, Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 use wayland_server::{backend::ObjectId, protocol::wl_surface::WlSurface, DisplayHandle, Resource};
 
 type CommitHook = dyn Fn(&mut dyn Any, &DisplayHandle, &WlSurface) + Send + Sync;
+type ValidationHook = dyn Fn(&mut dyn Any, &DisplayHandle, &WlSurface) -> CommitVerdict + Send + Sync;
 type DestructionHook = dyn Fn(&mut dyn Any, &SurfaceData) + Send;
 
+/// Generation of the subsurface trees, incremented on every change to any tree
+///
+/// Cached [`FlatTree`]s of an older generation are stale.
+static TREE_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+fn invalidate_trees() {
+    TREE_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// Flattened subsurface tree below a surface
+///
+/// Caches the structure of the tree, so traversals don't have to re-walk the
+/// children of every node. The first node is the surface itself, which is not stored
+/// to avoid a reference cycle.
+#[derive(Debug)]
+struct FlatTree {
+    generation: usize,
+    nodes: Vec<FlatNode>,
+}
+
+#[derive(Debug)]
+struct FlatNode {
+    surface: Option<WlSurface>,
+    /// Indices of the children in display order, `None` marks the position of the node itself
+    children: Vec<Option<usize>>,
+}
+
 /// Node of a subsurface tree, holding some user specified data type U
 /// at each node
 ///
@@ -35,9 +66,11 @@ pub struct PrivateSurfaceData {
     public_data: SurfaceData,
     pending_transaction: PendingTransaction,
     current_txid: Serial,
+    validation_hooks: Vec<Arc<Box<ValidationHook>>>,
     pre_commit_hooks: Vec<Arc<Box<CommitHook>>>,
     post_commit_hooks: Vec<Arc<Box<CommitHook>>>,
     destruction_hooks: Vec<Box<DestructionHook>>,
+    tree_cache: Option<Arc<FlatTree>>,
 }
 
 impl fmt::Debug for PrivateSurfaceData {
@@ -49,6 +82,7 @@ impl fmt::Debug for PrivateSurfaceData {
             .field("pending_transaction", &"...")
             .field("current_txid", &self.current_txid)
             .field("commit_hooks", &"...")
+            .field("validation_hooks.len", &self.validation_hooks.len())
             .field("pre_commit_hooks.len", &self.pre_commit_hooks.len())
             .field("post_commit_hooks.len", &self.post_commit_hooks.len())
             .field("destruction_hooks.len", &self.destruction_hooks.len())
@@ -100,9 +134,11 @@ impl PrivateSurfaceData {
             },
             pending_transaction: Default::default(),
             current_txid: Serial(0),
+            validation_hooks: Vec::new(),
             pre_commit_hooks: Vec::new(),
             post_commit_hooks: Vec::new(),
             destruction_hooks: Vec::new(),
+            tree_cache: None,
         })
     }
 
@@ -118,6 +154,8 @@ impl PrivateSurfaceData {
     pub fn cleanup<D: 'static>(state: &mut D, surface_data: &SurfaceUserData, surface_id: ObjectId) {
         let my_data_mutex = &surface_data.inner;
         let mut my_data = my_data_mutex.lock().unwrap();
+        invalidate_trees();
+        my_data.tree_cache = None;
         if let Some(old_parent) = my_data.parent.take() {
             // We had a parent, lets unregister ourselves from it
             let old_parent_mutex = &old_parent.data::<SurfaceUserData>().unwrap().inner;
@@ -187,6 +225,15 @@ impl PrivateSurfaceData {
         my_data.pending_transaction.add_blocker(blocker)
     }
 
+    pub fn add_validation_hook(
+        surface: &WlSurface,
+        hook: impl Fn(&mut dyn Any, &DisplayHandle, &WlSurface) -> CommitVerdict + Send + Sync + 'static,
+    ) {
+        let my_data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
+        let mut my_data = my_data_mutex.lock().unwrap();
+        my_data.validation_hooks.push(Arc::new(Box::new(hook)));
+    }
+
     pub fn add_pre_commit_hook(
         surface: &WlSurface,
         hook: impl Fn(&mut dyn Any, &DisplayHandle, &WlSurface) + Send + Sync + 'static,
@@ -214,6 +261,25 @@ impl PrivateSurfaceData {
         my_data.destruction_hooks.push(Box::new(hook));
     }
 
+    pub fn invoke_validation_hooks<D: 'static>(
+        state: &mut D,
+        dh: &DisplayHandle,
+        surface: &WlSurface,
+    ) -> CommitVerdict {
+        // don't hold the mutex while the hooks are invoked
+        let hooks = {
+            let my_data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
+            let my_data = my_data_mutex.lock().unwrap();
+            my_data.validation_hooks.clone()
+        };
+        for hook in hooks {
+            if hook(state, dh, surface) == CommitVerdict::Reject {
+                return CommitVerdict::Reject;
+            }
+        }
+        CommitVerdict::Accept
+    }
+
     pub fn invoke_pre_commit_hooks<D: 'static>(state: &mut D, dh: &DisplayHandle, surface: &WlSurface) {
         // don't hold the mutex while the hooks are invoked
         let hooks = {
@@ -336,6 +402,7 @@ impl PrivateSurfaceData {
             let mut parent_guard = parent_mutex.lock().unwrap();
             parent_guard.children.push(child.clone())
         }
+        invalidate_trees();
 
         Ok(())
     }
@@ -355,6 +422,7 @@ impl PrivateSurfaceData {
             let parent_mutex = &old_parent.data::<SurfaceUserData>().unwrap().inner;
             let mut parent_guard = parent_mutex.lock().unwrap();
             parent_guard.children.retain(|c| c.id() != child.id());
+            invalidate_trees();
         }
     }
 
@@ -412,6 +480,7 @@ impl PrivateSurfaceData {
             Location::After => other_index + 1,
         };
         parent_guard.children.insert(new_index, me);
+        invalidate_trees();
 
         Ok(())
     }
@@ -443,8 +512,11 @@ impl PrivateSurfaceData {
         F2: FnMut(&WlSurface, &SurfaceData, &T),
         F3: FnMut(&WlSurface, &SurfaceData, &T) -> bool,
     {
+        let tree = Self::flat_tree(surface);
         Self::map(
             surface,
+            &tree,
+            0,
             initial,
             &mut filter,
             &mut processor,
@@ -453,9 +525,55 @@ impl PrivateSurfaceData {
         );
     }
 
+    /// Retrieve the flattened tree below a surface, rebuilding it if the cache is stale
+    fn flat_tree(surface: &WlSurface) -> Arc<FlatTree> {
+        let generation = TREE_GENERATION.load(Ordering::Acquire);
+        let data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
+        if let Some(tree) = data_mutex
+            .lock()
+            .unwrap()
+            .tree_cache
+            .as_ref()
+            .filter(|tree| tree.generation == generation)
+        {
+            return tree.clone();
+        }
+
+        let mut nodes = Vec::new();
+        Self::flatten(surface, None, &mut nodes);
+        let tree = Arc::new(FlatTree { generation, nodes });
+        data_mutex.lock().unwrap().tree_cache = Some(tree.clone());
+        tree
+    }
+
+    // helper function for flat_tree
+    fn flatten(surface: &WlSurface, stored: Option<WlSurface>, nodes: &mut Vec<FlatNode>) -> usize {
+        let children = {
+            let data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
+            data_mutex.lock().unwrap().children.clone()
+        };
+        let idx = nodes.len();
+        nodes.push(FlatNode {
+            surface: stored,
+            children: Vec::with_capacity(children.len()),
+        });
+        for child in children {
+            let entry = if child.id() == surface.id() {
+                None
+            } else {
+                Some(Self::flatten(&child, Some(child.clone()), nodes))
+            };
+            nodes[idx].children.push(entry);
+        }
+        idx
+    }
+
     // helper function for map_tree
+    #[allow(clippy::too_many_arguments)]
     fn map<F1, F2, F3, T>(
-        surface: &WlSurface,
+        root: &WlSurface,
+        tree: &FlatTree,
+        idx: usize,
         initial: &T,
         filter: &mut F1,
         processor: &mut F2,
@@ -467,27 +585,28 @@ impl PrivateSurfaceData {
         F2: FnMut(&WlSurface, &SurfaceData, &T),
         F3: FnMut(&WlSurface, &SurfaceData, &T) -> bool,
     {
+        let node = &tree.nodes[idx];
+        let surface = node.surface.as_ref().unwrap_or(root);
         let data_mutex = &surface.data::<SurfaceUserData>().unwrap().inner;
-        let mut data_guard = data_mutex.lock().unwrap();
-        let data_guard = &mut *data_guard;
+        let data_guard = data_mutex.lock().unwrap();
         // call the filter on ourselves
         match filter(surface, &data_guard.public_data, initial) {
             TraversalAction::DoChildren(t) => {
                 // loop over children
-                if reverse {
-                    for c in data_guard.children.iter().rev() {
-                        if c.id() == surface.id() {
-                            processor(surface, &data_guard.public_data, initial);
-                        } else if !Self::map(c, &t, filter, processor, post_filter, true) {
-                            return false;
-                        }
-                    }
-                } else {
-                    for c in &data_guard.children {
-                        if c.id() == surface.id() {
-                            processor(surface, &data_guard.public_data, initial);
-                        } else if !Self::map(c, &t, filter, processor, post_filter, false) {
-                            return false;
+                let mut children = node.children.iter();
+                loop {
+                    let child = if reverse {
+                        children.next_back()
+                    } else {
+                        children.next()
+                    };
+                    match child {
+                        None => break,
+                        Some(None) => processor(surface, &data_guard.public_data, initial),
+                        Some(Some(child)) => {
+                            if !Self::map(root, tree, *child, &t, filter, processor, post_filter, reverse) {
+                                return false;
+                            }
                         }
                     }
                 }
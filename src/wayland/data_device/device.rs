@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, fmt, sync::Arc};
 
 use tracing::debug;
 use wayland_server::{
@@ -19,15 +19,35 @@ use crate::{
     },
 };
 
-use super::{dnd_grab, DataDeviceHandler, DataDeviceState};
+use super::{dnd_grab, DataDeviceHandler, DataDeviceState, MimeTypeFilter};
 
 /// WlSurface role of drag and drop icon
 pub const DND_ICON_ROLE: &str = "dnd_icon";
 
 #[doc(hidden)]
-#[derive(Debug)]
 pub struct DataDeviceUserData {
     pub(crate) wl_seat: WlSeat,
+    pub(crate) mime_filter: Option<Arc<MimeTypeFilter>>,
+}
+
+impl fmt::Debug for DataDeviceUserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataDeviceUserData")
+            .field("wl_seat", &self.wl_seat)
+            .field("mime_filter", &self.mime_filter.is_some())
+            .finish()
+    }
+}
+
+impl DataDeviceUserData {
+    /// Whether the given mime type may be advertised to the client
+    pub(crate) fn offers_mime_type(&self, client: &Client, mime_type: &str) -> bool {
+        offers_mime_type(self.mime_filter.as_deref(), client, mime_type)
+    }
+}
+
+pub(crate) fn offers_mime_type(filter: Option<&MimeTypeFilter>, client: &Client, mime_type: &str) -> bool {
+    filter.map(|filter| filter(client, mime_type)).unwrap_or(true)
 }
 
 impl<D> Dispatch<WlDataDevice, DataDeviceUserData, D> for DataDeviceState
@@ -71,6 +91,7 @@ where
                             // The StartDrag is in response to a pointer implicit grab, all is good
                             handler.started(source.clone(), icon.clone(), seat.clone());
                             let start_data = pointer.grab_start_data().unwrap();
+                            super::set_active_dnd_grab(&seat, serial);
                             pointer.set_grab(
                                 handler,
                                 dnd_grab::DnDGrab::new(dh, start_data, source, origin, seat, icon),
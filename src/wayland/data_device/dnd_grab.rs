@@ -10,6 +10,7 @@ use wayland_server::{
         wl_data_device_manager::DndAction,
         wl_data_offer::{self, WlDataOffer},
         wl_data_source::{self, WlDataSource},
+        wl_seat::WlSeat,
         wl_surface::WlSurface,
     },
     DisplayHandle, Resource,
@@ -17,6 +18,7 @@ use wayland_server::{
 
 use crate::{
     input::{
+        keyboard::ModifiersState,
         pointer::{
             AxisFrame, ButtonEvent, GrabStartData as PointerGrabStartData, MotionEvent, PointerGrab,
             PointerInnerHandle, RelativeMotionEvent,
@@ -27,7 +29,10 @@ use crate::{
     wayland::seat::WaylandFocus,
 };
 
-use super::{seat_data::SeatData, with_source_metadata, ClientDndGrabHandler, DataDeviceHandler};
+use super::{
+    device::offers_mime_type, seat_data::SeatData, with_source_metadata, ClientDndGrabHandler,
+    DataDeviceHandler, DataDeviceUserData, MimeTypeFilter,
+};
 
 pub(crate) struct DnDGrab<D: SeatHandler> {
     dh: DisplayHandle,
@@ -62,6 +67,23 @@ impl<D: SeatHandler> DnDGrab<D> {
             seat,
         }
     }
+
+    /// Publish the current offers on the seat, so that modifier changes can update their action
+    fn update_action_target(&self) {
+        let user_data = self.seat.user_data();
+        user_data.insert_if_missing(|| RefCell::new(None::<DndActionTarget>));
+        *user_data
+            .get::<RefCell<Option<DndActionTarget>>>()
+            .unwrap()
+            .borrow_mut() = match (&self.data_source, &self.offer_data) {
+            (Some(source), Some(offer_data)) if !self.pending_offers.is_empty() => Some(DndActionTarget {
+                offers: self.pending_offers.clone(),
+                source: source.clone(),
+                offer_data: offer_data.clone(),
+            }),
+            _ => None,
+        };
+    }
 }
 
 impl<D> PointerGrab<D> for DnDGrab<D>
@@ -102,6 +124,7 @@ where
                     if let Some(offer_data) = self.offer_data.take() {
                         offer_data.lock().unwrap().active = false;
                     }
+                    self.update_action_target();
                 }
             }
         }
@@ -123,6 +146,13 @@ where
                         dropped: false,
                         accepted: true,
                         chosen_action: DndAction::empty(),
+                        dest_actions: DndAction::empty(),
+                        preferred_action: DndAction::empty(),
+                        modifiers: self
+                            .seat
+                            .get_keyboard()
+                            .map(|keyboard| keyboard.modifier_state())
+                            .unwrap_or_default(),
                     }));
                     for device in seat_data
                         .known_devices()
@@ -130,6 +160,10 @@ where
                         .filter(|d| d.id().same_client_as(&surface.id()))
                     {
                         let handle = self.dh.backend_handle();
+                        let device_data = match device.data::<DataDeviceUserData>() {
+                            Some(data) => data,
+                            None => continue,
+                        };
 
                         // create a data offer
                         let offer = handle
@@ -140,6 +174,8 @@ where
                                 Arc::new(DndDataOffer {
                                     offer_data: offer_data.clone(),
                                     source: source.clone(),
+                                    wl_seat: device_data.wl_seat.clone(),
+                                    mime_filter: device_data.mime_filter.clone(),
                                 }),
                             )
                             .unwrap();
@@ -148,8 +184,10 @@ where
                         // advertize the offer to the client
                         device.data_offer(&offer);
                         with_source_metadata(source, |meta| {
-                            for mime_type in meta.mime_types.iter().cloned() {
-                                offer.offer(mime_type);
+                            for mime_type in meta.mime_types.iter() {
+                                if device_data.offers_mime_type(&client, mime_type) {
+                                    offer.offer(mime_type.clone());
+                                }
                             }
                             offer.source_actions(meta.dnd_action);
                         })
@@ -158,6 +196,7 @@ where
                         self.pending_offers.push(offer);
                     }
                     self.offer_data = Some(offer_data);
+                    self.update_action_target();
                 } else {
                     // only send if we are on a surface of the same client
                     if self.origin.id().same_client_as(&surface.id()) {
@@ -233,6 +272,9 @@ where
 
             ClientDndGrabHandler::dropped(data, self.seat.clone());
             self.icon = None;
+            // the action can no longer be changed
+            self.pending_offers.clear();
+            self.update_action_target();
             // in all cases abandon the drop
             // no more buttons are pressed, release the grab
             if let Some(ref surface) = self.current_focus {
@@ -254,6 +296,34 @@ where
     fn start_data(&self) -> &PointerGrabStartData<D> {
         &self.start_data
     }
+
+    fn unset(&mut self, data: &mut D) {
+        // the grab was ended by the compositor before the user dropped, cancel the drag
+        if let Some(seat_data) = self
+            .seat
+            .user_data()
+            .get::<RefCell<SeatData<D::SelectionUserData>>>()
+        {
+            if let Some(ref surface) = self.current_focus {
+                for device in seat_data.borrow().known_devices() {
+                    if device.id().same_client_as(&surface.id()) {
+                        device.leave();
+                    }
+                }
+            }
+        }
+        if let Some(ref offer_data) = self.offer_data {
+            offer_data.lock().unwrap().active = false;
+        }
+        if let Some(ref source) = self.data_source {
+            source.cancelled();
+        }
+
+        ClientDndGrabHandler::dropped(data, self.seat.clone());
+        self.icon = None;
+        self.pending_offers.clear();
+        self.update_action_target();
+    }
 }
 
 #[derive(Debug)]
@@ -262,12 +332,79 @@ struct OfferData {
     dropped: bool,
     accepted: bool,
     chosen_action: DndAction,
+    /// actions supported by the destination client
+    dest_actions: DndAction,
+    /// action preferred by the destination client
+    preferred_action: DndAction,
+    /// keyboard modifiers currently influencing the action
+    modifiers: ModifiersState,
+}
+
+/// Offers of the active client drag'n'drop of a seat, stored in its user data
+#[derive(Debug, Clone)]
+struct DndActionTarget {
+    offers: Vec<WlDataOffer>,
+    source: WlDataSource,
+    offer_data: Arc<Mutex<OfferData>>,
+}
+
+fn choose_action<D: DataDeviceHandler>(
+    handler: &mut D,
+    source: &WlDataSource,
+    data: &OfferData,
+) -> DndAction {
+    let source_actions =
+        with_source_metadata(source, |meta| meta.dnd_action).unwrap_or_else(|_| DndAction::empty());
+    let possible_actions = source_actions & data.dest_actions;
+    let chosen_action =
+        handler.action_choice_with_modifiers(possible_actions, data.preferred_action, data.modifiers);
+    // check that the user provided callback respects that one precise action should be chosen
+    debug_assert!(
+        [DndAction::None, DndAction::Move, DndAction::Copy, DndAction::Ask].contains(&chosen_action),
+        "Only one precise action should be chosen"
+    );
+    chosen_action
+}
+
+/// Re-negotiate the action of the active client drag'n'drop of a seat with new modifiers
+pub(crate) fn update_modifiers<D>(handler: &mut D, seat: &Seat<D>, modifiers: ModifiersState)
+where
+    D: DataDeviceHandler,
+    D: 'static,
+{
+    let target = match seat.user_data().get::<RefCell<Option<DndActionTarget>>>() {
+        Some(target) => target.borrow().clone(),
+        None => return,
+    };
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    let mut data = target.offer_data.lock().unwrap();
+    if data.modifiers == modifiers {
+        return;
+    }
+    data.modifiers = modifiers;
+    if !data.active || data.dropped {
+        return;
+    }
+
+    let chosen_action = choose_action(handler, &target.source, &data);
+    if chosen_action != data.chosen_action {
+        data.chosen_action = chosen_action;
+        for offer in &target.offers {
+            offer.action(chosen_action);
+        }
+        target.source.action(chosen_action);
+    }
 }
 
-#[derive(Debug)]
 struct DndDataOffer {
     offer_data: Arc<Mutex<OfferData>>,
     source: WlDataSource,
+    wl_seat: WlSeat,
+    mime_filter: Option<Arc<MimeTypeFilter>>,
 }
 
 impl<D> ObjectData<D> for DndDataOffer
@@ -300,12 +437,19 @@ where
 {
     use self::wl_data_offer::Request;
     let source = &data.source;
+    let wl_seat = &data.wl_seat;
+    let mime_filter = data.mime_filter.as_deref();
+    let client = match offer.client() {
+        Some(client) => client,
+        None => return,
+    };
     let mut data = data.offer_data.lock().unwrap();
     match request {
         Request::Accept { mime_type, .. } => {
             if let Some(mtype) = mime_type {
                 if let Err(crate::utils::UnmanagedResource) = with_source_metadata(source, |meta| {
-                    data.accepted = meta.mime_types.contains(&mtype);
+                    data.accepted =
+                        meta.mime_types.contains(&mtype) && offers_mime_type(mime_filter, &client, &mtype);
                 }) {
                     data.accepted = false;
                 }
@@ -314,11 +458,23 @@ where
             }
         }
         Request::Receive { mime_type, fd } => {
+            if !data.active || !offers_mime_type(mime_filter, &client, &mime_type) {
+                return;
+            }
+            // don't hold the lock while the compositor handles the request
+            std::mem::drop(data);
+            let seat = match Seat::<D>::from_resource(wl_seat) {
+                Some(seat) => seat,
+                None => return,
+            };
+            let (mime_type, fd) = match handler.intercept_receive(mime_type, fd, client, seat) {
+                Some(request) => request,
+                None => return,
+            };
             // check if the source and associated mime type is still valid
             let valid = with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type))
                 .unwrap_or(false)
-                && source.alive()
-                && data.active;
+                && source.alive();
             if valid {
                 source.send(mime_type, fd.as_raw_fd());
             }
@@ -371,15 +527,9 @@ where
                 return;
             }
 
-            let source_actions =
-                with_source_metadata(source, |meta| meta.dnd_action).unwrap_or_else(|_| DndAction::empty());
-            let possible_actions = source_actions & dnd_actions;
-            let chosen_action = handler.action_choice(possible_actions, preferred_action);
-            // check that the user provided callback respects that one precise action should be chosen
-            debug_assert!(
-                [DndAction::None, DndAction::Move, DndAction::Copy, DndAction::Ask].contains(&chosen_action),
-                "Only one precise action should be chosen"
-            );
+            data.dest_actions = dnd_actions;
+            data.preferred_action = preferred_action;
+            let chosen_action = choose_action(handler, source, &data);
             if chosen_action != data.chosen_action {
                 data.chosen_action = chosen_action;
                 offer.action(chosen_action);
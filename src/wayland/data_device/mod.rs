@@ -15,9 +15,20 @@
 //! the contents of the data device:
 //!
 //! - the freestanding function [`set_data_device_selection`]
-//!   allows you to set the contents of the selection for your clients
+//!   allows you to set the contents of the selection for your clients, e.g. to copy a screenshot to the
+//!   clipboard. You declare the offered mime types and an arbitrary [`DataDeviceHandler::SelectionUserData`]
+//!   describing the contents, and are asked to write them into a file descriptor through
+//!   [`DataDeviceHandler::send_selection`] whenever a client pastes. Once another selection is set,
+//!   offers of the previous one are no longer served.
+//! - [`DataDeviceState::with_mime_type_filter`] allows you to restrict which mime types of a selection or
+//!   drag'n'drop source are advertised to which client, and [`DataDeviceHandler::intercept_receive`] lets you
+//!   interpose on transfers between clients, e.g. to sanitize or convert the transferred data.
 //! - the freestanding function [`start_dnd`] allows you to initiate a drag'n'drop event from the compositor
 //!   itself and receive interactions of clients with it via an other dedicated callback.
+//!   [`start_dnd_with_icon`] additionally lets you attach an icon to it, which you can retrieve for
+//!   rendering through [`server_dnd_icon`].
+//! - the freestanding function [`cancel_dnd`] allows you to abort any ongoing drag'n'drop, e.g. when the
+//!   user presses Escape.
 //!
 //! The module defines the role `"dnd_icon"` that is assigned to surfaces used as drag'n'drop icons.
 //!
@@ -65,8 +76,10 @@
 //! ```
 
 use std::{
-    cell::{Ref, RefCell},
+    cell::{Cell, Ref, RefCell},
+    fmt,
     os::unix::io::{AsRawFd, OwnedFd},
+    sync::Arc,
 };
 
 use tracing::instrument;
@@ -82,6 +95,7 @@ use wayland_server::{
 
 use crate::{
     input::{
+        keyboard::ModifiersState,
         pointer::{Focus, GrabStartData as PointerGrabStartData},
         Seat, SeatHandler,
     },
@@ -114,6 +128,33 @@ pub trait DataDeviceHandler: Sized + ClientDndGrabHandler + ServerDndGrabHandler
         default_action_chooser(available, preferred)
     }
 
+    /// Action chooser for DnD negociation, taking the keyboard modifiers into account
+    ///
+    /// This is used for client initiated drag'n'drop, and called again when the modifiers change
+    /// during the drag (see [`update_dnd_modifiers`]).
+    ///
+    /// The default implementation picks [`DndAction::Copy`] while Ctrl is held, [`DndAction::Move`]
+    /// while Shift is held and [`DndAction::Ask`] while both are held, if the respective action is
+    /// available. Otherwise it defers to [`DataDeviceHandler::action_choice`].
+    fn action_choice_with_modifiers(
+        &mut self,
+        available: DndAction,
+        preferred: DndAction,
+        modifiers: ModifiersState,
+    ) -> DndAction {
+        let requested = match (modifiers.ctrl, modifiers.shift) {
+            (true, true) => DndAction::Ask,
+            (true, false) => DndAction::Copy,
+            (false, true) => DndAction::Move,
+            (false, false) => DndAction::empty(),
+        };
+        if !requested.is_empty() && available.contains(requested) {
+            requested
+        } else {
+            self.action_choice(available, preferred)
+        }
+    }
+
     /// A client has set the selection
     fn new_selection(&mut self, source: Option<WlDataSource>, seat: Seat<Self>) {}
 
@@ -129,6 +170,29 @@ pub trait DataDeviceHandler: Sized + ClientDndGrabHandler + ServerDndGrabHandler
         user_data: &Self::SelectionUserData,
     ) {
     }
+
+    /// A client requested to receive the contents of a selection or drag'n'drop offer of another client
+    ///
+    /// This allows to interpose on the transfer, e.g. to sanitize the contents or to convert text encodings.
+    /// Return the mime type and fd the source client should be asked to write into, or `None` to deny the
+    /// request. To transform the data, return the write end of a pipe and forward its contents into `fd`.
+    ///
+    /// The returned mime type has to be one of the mime types offered by the source, otherwise the request
+    /// is denied. The default implementation passes the request through unchanged.
+    ///
+    /// * `mime_type` - the requested mime type
+    /// * `fd` - the fd the requesting client reads from
+    /// * `client` - the client requesting the data
+    /// * `seat` - the seat the offer belongs to
+    fn intercept_receive(
+        &mut self,
+        mime_type: String,
+        fd: OwnedFd,
+        client: Client,
+        seat: Seat<Self>,
+    ) -> Option<(String, OwnedFd)> {
+        Some((mime_type, fd))
+    }
 }
 
 /// Events that are generated during client initiated drag'n'drop
@@ -145,6 +209,7 @@ pub trait ClientDndGrabHandler: SeatHandler + Sized {
     fn started(&mut self, source: Option<WlDataSource>, icon: Option<WlSurface>, seat: Seat<Self>) {}
 
     /// The drag'n'drop action was finished by the user releasing the buttons
+    /// or cancelled by the compositor (see [`cancel_dnd`])
     ///
     /// At this point, any pointer icon should be removed.
     ///
@@ -187,10 +252,22 @@ pub trait ServerDndGrabHandler: SeatHandler {
     fn finished(&mut self, seat: Seat<Self>) {}
 }
 
+/// Filter deciding if a mime type is advertised to a client
+pub type MimeTypeFilter = dyn Fn(&Client, &str) -> bool + Send + Sync;
+
 /// State of data device
-#[derive(Debug)]
 pub struct DataDeviceState {
     manager_global: GlobalId,
+    mime_filter: Option<Arc<MimeTypeFilter>>,
+}
+
+impl fmt::Debug for DataDeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataDeviceState")
+            .field("manager_global", &self.manager_global)
+            .field("mime_filter", &self.mime_filter.is_some())
+            .finish()
+    }
 }
 
 impl DataDeviceState {
@@ -202,7 +279,26 @@ impl DataDeviceState {
     {
         let manager_global = display.create_global::<D, WlDataDeviceManager, _>(3, ());
 
-        Self { manager_global }
+        Self {
+            manager_global,
+            mime_filter: None,
+        }
+    }
+
+    /// Restrict the mime types advertised to clients
+    ///
+    /// The filter is invoked for every mime type of a selection or drag'n'drop source, when an offer is
+    /// created for a client, and decides if the mime type is advertised to the client. Requests to receive
+    /// mime types not advertised to a client are denied.
+    ///
+    /// This only affects data devices created afterwards, so it should be set right after creating
+    /// the [`DataDeviceState`].
+    pub fn with_mime_type_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Client, &str) -> bool + Send + Sync + 'static,
+    {
+        self.mime_filter = Some(Arc::new(filter));
+        self
     }
 
     /// [WlDataDeviceManager] GlobalId getter
@@ -373,6 +469,21 @@ where
     seat_data.borrow_mut().set_selection::<D>(dh, Selection::Empty);
 }
 
+/// Update the keyboard modifiers of an active client initiated drag'n'drop
+///
+/// Call this whenever the modifiers of the seat's keyboard change, e.g. from your keyboard input filter.
+/// If a drag'n'drop is currently hovering a client, the action is re-negotiated through
+/// [`DataDeviceHandler::action_choice_with_modifiers`] and, if it changed, sent to both the source
+/// and the destination client.
+///
+/// Does nothing if no drag'n'drop is active on the given seat.
+pub fn update_dnd_modifiers<D>(data: &mut D, seat: &Seat<D>, modifiers: ModifiersState)
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    dnd_grab::update_modifiers(data, seat, modifiers)
+}
+
 /// Start a drag'n'drop from a resource controlled by the compositor
 ///
 /// You'll receive events generated by the interaction of clients with your
@@ -393,15 +504,126 @@ pub fn start_dnd<D>(
     seat.user_data()
         .insert_if_missing(|| RefCell::new(SeatData::<D::SelectionUserData>::new()));
     if let Some(pointer) = seat.get_pointer() {
+        set_active_dnd_grab(seat, serial);
         pointer.set_grab(
             data,
-            server_dnd_grab::ServerDnDGrab::new(dh, start_data, metadata, seat.clone()),
+            server_dnd_grab::ServerDnDGrab::new(dh, start_data, metadata, seat.clone(), None),
             serial,
             Focus::Keep,
         );
     }
 }
 
+/// Start a drag'n'drop from a resource controlled by the compositor, with an icon
+///
+/// This behaves like [`start_dnd`], but additionally associates an icon with the drag'n'drop.
+/// The icon can be of any type suitable for your renderer, e.g. a render element or a buffer,
+/// and is supposed to be drawn at the pointer location for as long as the drag'n'drop is in progress.
+///
+/// The icon can be retrieved through [`server_dnd_icon`] and is removed once the user dropped or
+/// the drag'n'drop was cancelled, i.e. right before [`ServerDndGrabHandler::dropped`] or
+/// [`ServerDndGrabHandler::cancelled`] are invoked.
+#[instrument(
+    name = "wayland_data_device",
+    level = "debug",
+    skip(dh, seat, data, icon),
+    fields(seat = seat.name())
+)]
+pub fn start_dnd_with_icon<D, I>(
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    data: &mut D,
+    serial: Serial,
+    start_data: PointerGrabStartData<D>,
+    metadata: SourceMetadata,
+    icon: I,
+) where
+    D: SeatHandler + DataDeviceHandler + 'static,
+    <D as SeatHandler>::PointerFocus: WaylandFocus,
+    I: Clone + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::<D::SelectionUserData>::new()));
+    if let Some(pointer) = seat.get_pointer() {
+        set_active_dnd_grab(seat, serial);
+        pointer.set_grab(
+            data,
+            server_dnd_grab::ServerDnDGrab::new(
+                dh,
+                start_data,
+                metadata,
+                seat.clone(),
+                Some(clear_server_dnd_icon::<D, I>),
+            ),
+            serial,
+            Focus::Keep,
+        );
+        // set after the grab, replacing a previous grab clears its icon
+        let user_data = seat.user_data();
+        user_data.insert_if_missing(|| ServerDndIcon::<I>(RefCell::new(None)));
+        *user_data.get::<ServerDndIcon<I>>().unwrap().0.borrow_mut() = Some(icon);
+    }
+}
+
+/// Icon of the compositor initiated drag'n'drop in progress on a seat
+///
+/// Returns `None` if no drag'n'drop was started through [`start_dnd_with_icon`] with an icon of
+/// type `I`, or if it already ended.
+pub fn server_dnd_icon<D, I>(seat: &Seat<D>) -> Option<I>
+where
+    D: SeatHandler + 'static,
+    I: Clone + 'static,
+{
+    seat.user_data()
+        .get::<ServerDndIcon<I>>()
+        .and_then(|icon| icon.0.borrow().clone())
+}
+
+/// Icon of the last compositor initiated drag'n'drop started on a seat
+struct ServerDndIcon<I>(RefCell<Option<I>>);
+
+fn clear_server_dnd_icon<D: SeatHandler + 'static, I: 'static>(seat: &Seat<D>) {
+    if let Some(icon) = seat.user_data().get::<ServerDndIcon<I>>() {
+        icon.0.borrow_mut().take();
+    }
+}
+
+/// Serial of the pointer grab of the last drag'n'drop started on a seat
+struct ActiveDndGrab(Cell<Option<Serial>>);
+
+fn set_active_dnd_grab<D: SeatHandler + 'static>(seat: &Seat<D>, serial: Serial) {
+    let user_data = seat.user_data();
+    user_data.insert_if_missing(|| ActiveDndGrab(Cell::new(None)));
+    user_data.get::<ActiveDndGrab>().unwrap().0.set(Some(serial));
+}
+
+/// Cancel the drag'n'drop currently in progress on this seat
+///
+/// This aborts both client and compositor initiated drag'n'drop operations, e.g. in response
+/// to the user pressing Escape or switching VTs. The destination client receives a `leave` event,
+/// the data source is cancelled and the pointer grab is released.
+/// [`ClientDndGrabHandler::dropped`] or [`ServerDndGrabHandler::cancelled`] are invoked
+/// respectively.
+///
+/// Returns `false` if no drag'n'drop was in progress on the given seat.
+#[instrument(name = "wayland_data_device", level = "debug", skip(data, seat), fields(seat = seat.name()))]
+pub fn cancel_dnd<D>(data: &mut D, seat: &Seat<D>, serial: Serial, time: u32) -> bool
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    let grab_serial = match seat.user_data().get::<ActiveDndGrab>() {
+        Some(active) => active.0.take(),
+        None => None,
+    };
+    match (grab_serial, seat.get_pointer()) {
+        (Some(grab_serial), Some(pointer)) if pointer.has_grab(grab_serial) => {
+            pointer.unset_grab(data, serial, time);
+            true
+        }
+        _ => false,
+    }
+}
+
 mod handlers {
     use std::cell::RefCell;
 
@@ -450,7 +672,7 @@ mod handlers {
         D: 'static,
     {
         fn request(
-            _state: &mut D,
+            state: &mut D,
             client: &wayland_server::Client,
             _resource: &WlDataDeviceManager,
             request: wl_data_device_manager::Request,
@@ -468,7 +690,13 @@ mod handlers {
                             seat.user_data()
                                 .insert_if_missing(|| RefCell::new(SeatData::<D::SelectionUserData>::new()));
 
-                            let data_device = data_init.init(id, DataDeviceUserData { wl_seat });
+                            let data_device = data_init.init(
+                                id,
+                                DataDeviceUserData {
+                                    wl_seat,
+                                    mime_filter: state.data_device_state().mime_filter.clone(),
+                                },
+                            );
 
                             let seat_data = seat
                                 .user_data()
@@ -35,6 +35,7 @@ pub(crate) struct ServerDnDGrab<D: SeatHandler> {
     pending_offers: Vec<wl_data_offer::WlDataOffer>,
     offer_data: Option<Arc<Mutex<ServerDndOfferData>>>,
     seat: Seat<D>,
+    clear_icon: Option<fn(&Seat<D>)>,
 }
 
 impl<D: SeatHandler> ServerDnDGrab<D> {
@@ -43,6 +44,7 @@ impl<D: SeatHandler> ServerDnDGrab<D> {
         start_data: PointerGrabStartData<D>,
         metadata: super::SourceMetadata,
         seat: Seat<D>,
+        clear_icon: Option<fn(&Seat<D>)>,
     ) -> Self {
         Self {
             dh: dh.clone(),
@@ -52,6 +54,13 @@ impl<D: SeatHandler> ServerDnDGrab<D> {
             pending_offers: Vec::with_capacity(1),
             offer_data: None,
             seat,
+            clear_icon,
+        }
+    }
+
+    fn clear_icon(&self) {
+        if let Some(clear_icon) = self.clear_icon {
+            clear_icon(&self.seat);
         }
     }
 }
@@ -122,10 +131,20 @@ where
                     .filter(|d| d.id().same_client_as(&surface.id()))
                 {
                     let handle = self.dh.backend_handle();
-                    let wl_seat = match device.data::<DataDeviceUserData>() {
-                        Some(data) => data.wl_seat.clone(),
+                    let device_data = match device.data::<DataDeviceUserData>() {
+                        Some(data) => data,
                         None => continue,
                     };
+                    let metadata = SourceMetadata {
+                        mime_types: self
+                            .metadata
+                            .mime_types
+                            .iter()
+                            .filter(|mime_type| device_data.offers_mime_type(&client, mime_type))
+                            .cloned()
+                            .collect(),
+                        dnd_action: self.metadata.dnd_action,
+                    };
                     // create a data offer
                     let offer = handle
                         .create_object::<D>(
@@ -133,9 +152,9 @@ where
                             WlDataOffer::interface(),
                             device.version(),
                             Arc::new(ServerDndData {
-                                metadata: self.metadata.clone(),
+                                metadata: metadata.clone(),
                                 ofer_data: offer_data.clone(),
-                                wl_seat,
+                                wl_seat: device_data.wl_seat.clone(),
                             }),
                         )
                         .unwrap();
@@ -143,7 +162,7 @@ where
 
                     // advertize the offer to the client
                     device.data_offer(&offer);
-                    for mime_type in self.metadata.mime_types.iter().cloned() {
+                    for mime_type in metadata.mime_types {
                         offer.offer(mime_type);
                     }
                     offer.source_actions(self.metadata.dnd_action);
@@ -207,6 +226,7 @@ where
                 }
             }
 
+            self.clear_icon();
             ServerDndGrabHandler::dropped(data, self.seat.clone());
             if !validated {
                 data.cancelled(self.seat.clone());
@@ -232,6 +252,29 @@ where
     fn start_data(&self) -> &PointerGrabStartData<D> {
         &self.start_data
     }
+
+    fn unset(&mut self, data: &mut D) {
+        // the grab was ended by the compositor before the user dropped, cancel the drag
+        if let Some(seat_data) = self
+            .seat
+            .user_data()
+            .get::<RefCell<SeatData<D::SelectionUserData>>>()
+        {
+            if let Some(ref surface) = self.current_focus {
+                for device in seat_data.borrow().known_devices() {
+                    if device.id().same_client_as(&surface.id()) {
+                        device.leave();
+                    }
+                }
+            }
+        }
+        if let Some(ref offer_data) = self.offer_data {
+            offer_data.lock().unwrap().active = false;
+        }
+
+        self.clear_icon();
+        ServerDndGrabHandler::cancelled(data, self.seat.clone());
+    }
 }
 
 #[derive(Debug)]
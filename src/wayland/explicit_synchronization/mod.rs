@@ -0,0 +1,479 @@
+//! Utilities for handling the `zwp_linux_explicit_synchronization_v1` protocol
+//!
+//! This protocol allows clients to provide an acquire fence together with a committed
+//! dmabuf buffer, which the compositor has to wait on before accessing the buffer contents,
+//! and to request a release object, that is used by the compositor to signal the client
+//! once it is done accessing the buffer (optionally again through a fence).
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create [`ExplicitSyncState`], store it in your `State` struct and
+//! use the [`delegate_explicit_sync`](crate::delegate_explicit_sync) macro to implement the required traits:
+//!
+//! ```
+//! use smithay::wayland::explicit_synchronization::ExplicitSyncState;
+//! use smithay::delegate_explicit_sync;
+//!
+//! # struct State;
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//!
+//! // Create the explicit synchronization state:
+//! let explicit_sync_state = ExplicitSyncState::new::<State>(
+//!     &display.handle(), // the display
+//! );
+//!
+//! // implement Dispatch for the explicit synchronization types
+//! delegate_explicit_sync!(State);
+//!
+//! // You're now ready to go!
+//! ```
+//!
+//! ### Use the synchronization state
+//!
+//! The [`synchronization state`](ExplicitSyncCachedState) is double-buffered and
+//! can be accessed by using the [`with_states`] function.
+//!
+//! ```no_compile
+//! with_states(surface, |states| {
+//!     let mut sync = states.cached_state.current::<ExplicitSyncCachedState>();
+//!     if let Some(fence) = sync.acquire_fence.take() {
+//!         // wait for the fence or add it as a blocker
+//!     }
+//! });
+//! ```
+//!
+//! The [`AcquireFence`] implements [`Blocker`](crate::wayland::compositor::Blocker), so it can be
+//! added with [`add_blocker`](crate::wayland::compositor::add_blocker) in a pre-commit hook to delay
+//! applying the state until the client is done rendering. Remember to call
+//! [`CompositorClientState::blocker_cleared`](crate::wayland::compositor::CompositorClientState::blocker_cleared)
+//! once the fence got signalled, e.g. by inserting the fd into your event loop.
+//!
+//! If you already hand over buffer management to smithay by using
+//! [`on_commit_buffer_handler`](crate::backend::renderer::utils::on_commit_buffer_handler),
+//! the [`BufferRelease`] object will be attached to the buffer and automatically signalled
+//! with an immediate release once smithay would have sent `wl_buffer.release`.
+
+use std::{
+    cell::RefCell,
+    os::unix::io::{AsRawFd, OwnedFd},
+};
+
+use nix::poll::{poll, PollFd, PollFlags};
+use tracing::trace;
+use wayland_protocols::wp::linux_explicit_synchronization::zv1::server::{
+    zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1,
+    zwp_linux_explicit_synchronization_v1::{self, ZwpLinuxExplicitSynchronizationV1},
+    zwp_linux_surface_synchronization_v1::{self, ZwpLinuxSurfaceSynchronizationV1},
+};
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface::WlSurface, Client, DataInit, Dispatch, DisplayHandle,
+    GlobalDispatch, New, Resource, Weak,
+};
+
+use super::{
+    compositor::{self, with_states, BufferAssignment, Cacheable, SurfaceAttributes},
+    dmabuf,
+};
+use crate::wayland::compositor::{Blocker, BlockerState};
+
+/// State of the zwp_linux_explicit_synchronization_v1 Global
+#[derive(Debug)]
+pub struct ExplicitSyncState {
+    global: GlobalId,
+}
+
+impl ExplicitSyncState {
+    /// Create new [`zwp_linux_explicit_synchronization_v1`](ZwpLinuxExplicitSynchronizationV1) global.
+    ///
+    /// It returns the explicit synchronization state, which you can drop to remove these global from
+    /// the event loop in the future.
+    pub fn new<D>(display: &DisplayHandle) -> ExplicitSyncState
+    where
+        D: GlobalDispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+            + Dispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+            + Dispatch<ZwpLinuxSurfaceSynchronizationV1, ExplicitSyncSurfaceData>
+            + Dispatch<ZwpLinuxBufferReleaseV1, ()>
+            + 'static,
+    {
+        ExplicitSyncState {
+            global: display.create_global::<D, ZwpLinuxExplicitSynchronizationV1, ()>(2, ()),
+        }
+    }
+
+    /// Returns the explicit synchronization global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+/// An acquire fence provided by a client
+///
+/// The compositor must not access the contents of the buffer committed
+/// together with this fence, before the fence is signalled.
+#[derive(Debug)]
+pub struct AcquireFence(OwnedFd);
+
+impl AcquireFence {
+    /// Checks if the fence is already signalled without blocking
+    pub fn is_signaled(&self) -> bool {
+        let mut fds = [PollFd::new(self.0.as_raw_fd(), PollFlags::POLLIN)];
+        matches!(poll(&mut fds, 0), Ok(n) if n > 0)
+    }
+
+    /// Blocks until the fence is signalled or the timeout (in milliseconds) expired
+    ///
+    /// A negative timeout will wait indefinitely. Returns `true` if the fence is signalled.
+    pub fn wait(&self, timeout: i32) -> bool {
+        let mut fds = [PollFd::new(self.0.as_raw_fd(), PollFlags::POLLIN)];
+        matches!(poll(&mut fds, timeout), Ok(n) if n > 0)
+    }
+
+    /// Returns the underlying sync file descriptor
+    pub fn fd(&self) -> &OwnedFd {
+        &self.0
+    }
+
+    /// Converts the fence into its underlying sync file descriptor
+    pub fn into_fd(self) -> OwnedFd {
+        self.0
+    }
+}
+
+impl Blocker for AcquireFence {
+    fn state(&self) -> BlockerState {
+        if self.is_signaled() {
+            BlockerState::Released
+        } else {
+            BlockerState::Pending
+        }
+    }
+}
+
+/// A buffer release object requested by a client
+///
+/// Signals the client that the compositor is done with a buffer. Either [`BufferRelease::release`]
+/// or [`BufferRelease::immediate_release`] should be called exactly once, after the compositor is done
+/// accessing the buffer committed together with this object.
+#[derive(Debug)]
+pub struct BufferRelease(ZwpLinuxBufferReleaseV1);
+
+impl BufferRelease {
+    /// Release the buffer once the provided fence is signalled
+    pub fn release(self, fence: OwnedFd) {
+        trace!(release = ?self.0, "sending fenced buffer release");
+        self.0.fenced_release(fence.as_raw_fd());
+    }
+
+    /// Release the buffer immediately
+    ///
+    /// Signals the client that the buffer may be reused without any further synchronization.
+    pub fn immediate_release(self) {
+        trace!(release = ?self.0, "sending immediate buffer release");
+        self.0.immediate_release();
+    }
+}
+
+/// Represents the double-buffered explicit synchronization
+/// state of a [`WlSurface`]
+#[derive(Debug, Default)]
+pub struct ExplicitSyncCachedState {
+    /// The acquire fence attached to the committed buffer, if any
+    pub acquire_fence: Option<AcquireFence>,
+    /// The release object attached to the committed buffer, if any
+    pub release: Option<BufferRelease>,
+}
+
+impl Cacheable for ExplicitSyncCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        ExplicitSyncCachedState {
+            acquire_fence: self.acquire_fence.take(),
+            release: self.release.take(),
+        }
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        // fences and release objects are always tied to a new buffer
+        // as checked in the pre-commit hook, so a new state replaces the old one
+        if self.acquire_fence.is_some() || self.release.is_some() {
+            if let Some(release) = into.release.take() {
+                // The previous buffer got replaced before it was ever used
+                release.immediate_release();
+            }
+            into.acquire_fence = self.acquire_fence;
+            into.release = self.release;
+        }
+    }
+}
+
+/// User data of a [`ZwpLinuxSurfaceSynchronizationV1`] object
+#[derive(Debug)]
+pub struct ExplicitSyncSurfaceData {
+    surface: Weak<WlSurface>,
+}
+
+struct ExplicitSyncMarker(Option<Weak<ZwpLinuxSurfaceSynchronizationV1>>);
+
+impl<D> GlobalDispatch<ZwpLinuxExplicitSynchronizationV1, (), D> for ExplicitSyncState
+where
+    D: GlobalDispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+        + Dispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+        + Dispatch<ZwpLinuxSurfaceSynchronizationV1, ExplicitSyncSurfaceData>
+        + Dispatch<ZwpLinuxBufferReleaseV1, ()>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpLinuxExplicitSynchronizationV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<ZwpLinuxExplicitSynchronizationV1, (), D> for ExplicitSyncState
+where
+    D: GlobalDispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+        + Dispatch<ZwpLinuxExplicitSynchronizationV1, ()>
+        + Dispatch<ZwpLinuxSurfaceSynchronizationV1, ExplicitSyncSurfaceData>
+        + Dispatch<ZwpLinuxBufferReleaseV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ZwpLinuxExplicitSynchronizationV1,
+        request: zwp_linux_explicit_synchronization_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_explicit_synchronization_v1::Request::GetSynchronization { id, surface } => {
+                let already_synchronized = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<RefCell<ExplicitSyncMarker>>()
+                        .map(|v| v.borrow().0.is_some())
+                        .unwrap_or(false)
+                });
+
+                if already_synchronized {
+                    resource.post_error(
+                        zwp_linux_explicit_synchronization_v1::Error::SynchronizationExists,
+                        "the surface already has a synchronization object associated",
+                    );
+                    return;
+                }
+
+                let synchronization = data_init.init(
+                    id,
+                    ExplicitSyncSurfaceData {
+                        surface: surface.downgrade(),
+                    },
+                );
+                let initial = with_states(&surface, |states| {
+                    let inserted = states.data_map.insert_if_missing(|| {
+                        RefCell::new(ExplicitSyncMarker(Some(synchronization.downgrade())))
+                    });
+                    if !inserted {
+                        states
+                            .data_map
+                            .get::<RefCell<ExplicitSyncMarker>>()
+                            .unwrap()
+                            .borrow_mut()
+                            .0 = Some(synchronization.downgrade());
+                    }
+                    inserted
+                });
+
+                // only add the pre-commit hook once for the surface
+                if initial {
+                    compositor::add_pre_commit_hook::<D, _>(&surface, explicit_sync_commit_hook);
+                }
+            }
+            zwp_linux_explicit_synchronization_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpLinuxSurfaceSynchronizationV1, ExplicitSyncSurfaceData, D> for ExplicitSyncState
+where
+    D: Dispatch<ZwpLinuxSurfaceSynchronizationV1, ExplicitSyncSurfaceData>
+        + Dispatch<ZwpLinuxBufferReleaseV1, ()>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ZwpLinuxSurfaceSynchronizationV1,
+        request: zwp_linux_surface_synchronization_v1::Request,
+        data: &ExplicitSyncSurfaceData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_surface_synchronization_v1::Request::SetAcquireFence { fd } => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::NoSurface,
+                        "the wl_surface was destroyed",
+                    );
+                    return;
+                };
+
+                let duplicate = with_states(&surface, |states| {
+                    let mut pending = states.cached_state.pending::<ExplicitSyncCachedState>();
+                    if pending.acquire_fence.is_some() {
+                        true
+                    } else {
+                        pending.acquire_fence = Some(AcquireFence(fd));
+                        false
+                    }
+                });
+
+                if duplicate {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::DuplicateFence,
+                        "an acquire fence was already set for this commit",
+                    );
+                }
+            }
+            zwp_linux_surface_synchronization_v1::Request::GetRelease { release } => {
+                let Ok(surface) = data.surface.upgrade() else {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::NoSurface,
+                        "the wl_surface was destroyed",
+                    );
+                    return;
+                };
+
+                let duplicate = with_states(&surface, |states| {
+                    states
+                        .cached_state
+                        .pending::<ExplicitSyncCachedState>()
+                        .release
+                        .is_some()
+                });
+
+                if duplicate {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::DuplicateRelease,
+                        "a release object was already requested for this commit",
+                    );
+                    return;
+                }
+
+                let release = data_init.init(release, ());
+                with_states(&surface, |states| {
+                    states.cached_state.pending::<ExplicitSyncCachedState>().release =
+                        Some(BufferRelease(release));
+                });
+            }
+            zwp_linux_surface_synchronization_v1::Request::Destroy => {
+                if let Ok(surface) = data.surface.upgrade() {
+                    with_states(&surface, |states| {
+                        if let Some(marker) = states.data_map.get::<RefCell<ExplicitSyncMarker>>() {
+                            marker.borrow_mut().0 = None;
+                        }
+                        // Any fence set since the last commit is discarded, release objects stay valid
+                        states
+                            .cached_state
+                            .pending::<ExplicitSyncCachedState>()
+                            .acquire_fence = None;
+                    });
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpLinuxBufferReleaseV1, (), D> for ExplicitSyncState
+where
+    D: Dispatch<ZwpLinuxBufferReleaseV1, ()>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ZwpLinuxBufferReleaseV1,
+        _request: <ZwpLinuxBufferReleaseV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // zwp_linux_buffer_release_v1 has no requests
+    }
+}
+
+fn explicit_sync_commit_hook<D: 'static>(_state: &mut D, _dh: &DisplayHandle, surface: &WlSurface) {
+    with_states(surface, |states| {
+        let Some(marker) = states.data_map.get::<RefCell<ExplicitSyncMarker>>() else {
+            return;
+        };
+        let marker = marker.borrow();
+        let Some(synchronization) = marker.0.as_ref().and_then(|s| s.upgrade().ok()) else {
+            return;
+        };
+
+        let mut sync_state = states.cached_state.pending::<ExplicitSyncCachedState>();
+        if sync_state.acquire_fence.is_none() && sync_state.release.is_none() {
+            return;
+        }
+
+        let attributes = states.cached_state.pending::<SurfaceAttributes>();
+        match attributes.buffer.as_ref() {
+            Some(BufferAssignment::NewBuffer(buffer)) => {
+                if sync_state.acquire_fence.is_some() && dmabuf::get_dmabuf(buffer).is_err() {
+                    synchronization.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::UnsupportedBuffer,
+                        "acquire fence set for a non-dmabuf buffer",
+                    );
+                }
+            }
+            _ => {
+                if let Some(release) = sync_state.release.take() {
+                    // make sure the object is not left dangling
+                    release.immediate_release();
+                }
+                sync_state.acquire_fence = None;
+                synchronization.post_error(
+                    zwp_linux_surface_synchronization_v1::Error::NoBuffer,
+                    "fence or release object set without an attached buffer",
+                );
+            }
+        }
+    });
+}
+
+/// Macro to delegate implementation of the linux explicit synchronization protocol to [`ExplicitSyncState`].
+#[macro_export]
+macro_rules! delegate_explicit_sync {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        type __ZwpLinuxExplicitSynchronizationV1 =
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1;
+        type __ZwpLinuxSurfaceSynchronizationV1 =
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1;
+        type __ZwpLinuxBufferReleaseV1 =
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1;
+
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZwpLinuxExplicitSynchronizationV1: ()
+        ] => $crate::wayland::explicit_synchronization::ExplicitSyncState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZwpLinuxExplicitSynchronizationV1: ()
+        ] => $crate::wayland::explicit_synchronization::ExplicitSyncState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZwpLinuxSurfaceSynchronizationV1: $crate::wayland::explicit_synchronization::ExplicitSyncSurfaceData
+        ] => $crate::wayland::explicit_synchronization::ExplicitSyncState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZwpLinuxBufferReleaseV1: ()
+        ] => $crate::wayland::explicit_synchronization::ExplicitSyncState);
+    };
+}
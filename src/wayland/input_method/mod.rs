@@ -96,17 +96,42 @@ pub struct InputMethodManagerState {
     global: GlobalId,
 }
 
+/// Data associated with a InputMethodManager global.
+#[allow(missing_debug_implementations)]
+pub struct InputMethodManagerGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
 impl InputMethodManagerState {
     /// Initialize a text input manager global.
     pub fn new<D>(display: &DisplayHandle) -> Self
     where
-        D: GlobalDispatch<ZwpInputMethodManagerV2, ()>,
+        D: GlobalDispatch<ZwpInputMethodManagerV2, InputMethodManagerGlobalData>,
+        D: Dispatch<ZwpInputMethodManagerV2, ()>,
+        D: Dispatch<ZwpInputMethodV2, InputMethodUserData<D>>,
+        D: SeatHandler,
+        D: 'static,
+    {
+        Self::new_with_filter::<D, _>(display, |_| true)
+    }
+
+    /// Initialize a text input manager global, only advertised to clients passing the given filter.
+    ///
+    /// Input methods are able to send text to any focused client, so you likely want to restrict
+    /// this global to trusted clients, see also [`PrivilegedGlobals`](crate::wayland::privileged::PrivilegedGlobals).
+    pub fn new_with_filter<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwpInputMethodManagerV2, InputMethodManagerGlobalData>,
         D: Dispatch<ZwpInputMethodManagerV2, ()>,
         D: Dispatch<ZwpInputMethodV2, InputMethodUserData<D>>,
         D: SeatHandler,
         D: 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
     {
-        let global = display.create_global::<D, ZwpInputMethodManagerV2, _>(MANAGER_VERSION, ());
+        let data = InputMethodManagerGlobalData {
+            filter: Box::new(filter),
+        };
+        let global = display.create_global::<D, ZwpInputMethodManagerV2, _>(MANAGER_VERSION, data);
 
         Self { global }
     }
@@ -117,9 +142,9 @@ impl InputMethodManagerState {
     }
 }
 
-impl<D> GlobalDispatch<ZwpInputMethodManagerV2, (), D> for InputMethodManagerState
+impl<D> GlobalDispatch<ZwpInputMethodManagerV2, InputMethodManagerGlobalData, D> for InputMethodManagerState
 where
-    D: GlobalDispatch<ZwpInputMethodManagerV2, ()>,
+    D: GlobalDispatch<ZwpInputMethodManagerV2, InputMethodManagerGlobalData>,
     D: Dispatch<ZwpInputMethodManagerV2, ()>,
     D: Dispatch<ZwpInputMethodV2, InputMethodUserData<D>>,
     D: SeatHandler,
@@ -130,11 +155,15 @@ where
         _: &DisplayHandle,
         _: &Client,
         resource: New<ZwpInputMethodManagerV2>,
-        _: &(),
+        _: &InputMethodManagerGlobalData,
         data_init: &mut DataInit<'_, D>,
     ) {
         data_init.init(resource, ());
     }
+
+    fn can_view(client: Client, global_data: &InputMethodManagerGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
 }
 
 impl<D> Dispatch<ZwpInputMethodManagerV2, (), D> for InputMethodManagerState
@@ -189,7 +218,7 @@ where
 macro_rules! delegate_input_method_manager {
     ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
         $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
-            $crate::reexports::wayland_protocols_misc::zwp_input_method_v2::server::zwp_input_method_manager_v2::ZwpInputMethodManagerV2: ()
+            $crate::reexports::wayland_protocols_misc::zwp_input_method_v2::server::zwp_input_method_manager_v2::ZwpInputMethodManagerV2: $crate::wayland::input_method::InputMethodManagerGlobalData
         ] => $crate::wayland::input_method::InputMethodManagerState);
 
         $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
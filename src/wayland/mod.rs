@@ -50,6 +50,7 @@ pub mod compositor;
 pub mod content_type;
 pub mod data_device;
 pub mod dmabuf;
+pub mod explicit_synchronization;
 pub mod fractional_scale;
 pub mod idle_inhibit;
 pub mod input_method;
@@ -57,12 +58,15 @@ pub mod keyboard_shortcuts_inhibit;
 pub mod output;
 pub mod presentation;
 pub mod primary_selection;
+pub mod privileged;
 pub mod relative_pointer;
 pub mod seat;
 pub mod shell;
 pub mod shm;
 pub mod socket;
 pub mod tablet_manager;
+#[cfg(any(test, feature = "test_support"))]
+pub mod testing;
 pub mod text_input;
 pub mod viewporter;
 pub mod virtual_keyboard;
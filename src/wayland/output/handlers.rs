@@ -8,6 +8,8 @@ use wayland_server::{
     Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
 };
 
+use crate::wayland::compositor::CompositorHandler;
+
 use super::{xdg::XdgOutput, Output, OutputManagerState, OutputUserData, WlOutputData};
 
 /*
@@ -136,11 +138,12 @@ impl<D> Dispatch<ZxdgOutputManagerV1, (), D> for OutputManagerState
 where
     D: Dispatch<ZxdgOutputManagerV1, ()>,
     D: Dispatch<ZxdgOutputV1, XdgOutputUserData>,
+    D: CompositorHandler,
     D: 'static,
 {
     fn request(
-        _state: &mut D,
-        _client: &Client,
+        state: &mut D,
+        client: &Client,
         _resource: &ZxdgOutputManagerV1,
         request: zxdg_output_manager_v1::Request,
         _data: &(),
@@ -155,15 +158,26 @@ where
                 let output = Output::from_resource(&wl_output).unwrap();
                 let mut inner = output.inner.0.lock().unwrap();
 
-                let xdg_output = XdgOutput::new(&inner);
-
-                if inner.xdg_output.is_none() {
-                    inner.xdg_output = Some(xdg_output.clone());
-                }
-
-                let id = data_init.init(id, XdgOutputUserData { xdg_output });
-
-                inner.xdg_output.as_ref().unwrap().add_instance(&id, &wl_output);
+                // All xdg_output instances of an output share the same state, so that
+                // later changes reach every one of them.
+                let xdg_output = match inner.xdg_output.as_ref() {
+                    Some(xdg_output) => xdg_output.clone(),
+                    None => {
+                        let xdg_output = XdgOutput::new(&inner);
+                        inner.xdg_output = Some(xdg_output.clone());
+                        xdg_output
+                    }
+                };
+
+                let id = data_init.init(
+                    id,
+                    XdgOutputUserData {
+                        xdg_output: xdg_output.clone(),
+                        client_scale: state.client_compositor_state(client).client_scale() as i32,
+                    },
+                );
+
+                xdg_output.add_instance(&id, &wl_output);
             }
             zxdg_output_manager_v1::Request::Destroy => {}
             _ => {}
@@ -175,6 +189,7 @@ where
 #[derive(Debug)]
 pub struct XdgOutputUserData {
     xdg_output: XdgOutput,
+    pub(super) client_scale: i32,
 }
 
 impl<D> Dispatch<ZxdgOutputV1, XdgOutputUserData, D> for OutputManagerState
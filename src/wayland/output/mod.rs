@@ -219,6 +219,22 @@ impl Output {
         }
     }
 
+    pub(crate) fn wl_change_description(&self) {
+        let inner = self.inner.0.lock().unwrap();
+        if let Some(xdg_output) = inner.xdg_output.as_ref() {
+            xdg_output.set_description(&inner.description);
+        }
+
+        for output in &inner.instances {
+            if output.version() >= 4 {
+                output.description(inner.description.clone());
+            }
+            if output.version() >= 2 {
+                output.done();
+            }
+        }
+    }
+
     /// Check is given [`wl_output`](WlOutput) instance is managed by this [`Output`].
     pub fn owns(&self, output: &WlOutput) -> bool {
         self.inner
@@ -11,7 +11,7 @@ use wayland_server::{protocol::wl_output::WlOutput, Resource};
 
 use crate::utils::{Logical, Physical, Point, Size, Transform};
 
-use super::{Mode, Scale};
+use super::{handlers::XdgOutputUserData, Mode, Scale};
 
 #[derive(Debug)]
 pub(crate) struct Inner {
@@ -26,6 +26,20 @@ pub(crate) struct Inner {
     pub instances: Vec<ZxdgOutputV1>,
 }
 
+impl Inner {
+    /// Logical size as advertised to clients, derived from the current mode,
+    /// scale and transform of the output.
+    fn logical_size(&self) -> Option<Size<i32, Logical>> {
+        self.physical_size.map(|size| {
+            let logical_size = size
+                .to_f64()
+                .to_logical(self.scale.fractional_scale())
+                .to_i32_round();
+            self.transform.transform_size(logical_size)
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct XdgOutput {
     pub(crate) inner: Arc<Mutex<Inner>>,
@@ -55,15 +69,13 @@ impl XdgOutput {
     pub(super) fn add_instance(&self, xdg_output: &ZxdgOutputV1, wl_output: &WlOutput) {
         let mut inner = self.inner.lock().unwrap();
 
-        xdg_output.logical_position(inner.logical_position.x, inner.logical_position.y);
+        let client_scale = client_scale(xdg_output);
+        let position = inner.logical_position.upscale(client_scale);
+        xdg_output.logical_position(position.x, position.y);
 
-        if let Some(size) = inner.physical_size {
-            let logical_size = size
-                .to_f64()
-                .to_logical(inner.scale.fractional_scale())
-                .to_i32_round();
-            let transformed_size = inner.transform.transform_size(logical_size);
-            xdg_output.logical_size(transformed_size.w, transformed_size.h);
+        if let Some(size) = inner.logical_size() {
+            let size = size.upscale(client_scale);
+            xdg_output.logical_size(size.w, size.h);
         }
 
         if xdg_output.version() >= 2 {
@@ -76,11 +88,30 @@ impl XdgOutput {
             xdg_output.done();
         }
 
-        wl_output.done();
+        if wl_output.version() >= 2 {
+            wl_output.done();
+        }
 
         inner.instances.push(xdg_output.clone());
     }
 
+    pub(super) fn set_description(&self, description: &str) {
+        let mut output = self.inner.lock().unwrap();
+        if output.description == description {
+            return;
+        }
+        output.description = description.to_owned();
+
+        for instance in output.instances.iter() {
+            // Objects of version 2 and lower must not see the description change
+            if instance.version() >= 3 {
+                instance.description(output.description.clone());
+            }
+
+            // No need for wl_output.done() here, it will be called by caller (super::Output::set_description)
+        }
+    }
+
     pub(super) fn change_current_state(
         &self,
         new_mode: Option<Mode>,
@@ -90,6 +121,10 @@ impl XdgOutput {
     ) {
         let mut output = self.inner.lock().unwrap();
 
+        // A transform change swaps the logical width and height for rotated outputs,
+        // so the size has to be re-sent just like for mode or scale changes.
+        let size_changed = new_mode.is_some() || new_scale.is_some() || new_transform.is_some();
+
         if let Some(new_mode) = new_mode {
             output.physical_size = Some(new_mode.size);
         }
@@ -103,20 +138,21 @@ impl XdgOutput {
             output.transform = new_transform.into();
         }
 
+        let logical_size = output.logical_size();
+
         for instance in output.instances.iter() {
-            if new_mode.is_some() | new_scale.is_some() {
-                if let Some(size) = output.physical_size {
-                    let logical_size = size
-                        .to_f64()
-                        .to_logical(output.scale.fractional_scale())
-                        .to_i32_round();
-                    let transformed_size = output.transform.transform_size(logical_size);
-                    instance.logical_size(transformed_size.w, transformed_size.h);
+            let client_scale = client_scale(instance);
+
+            if size_changed {
+                if let Some(size) = logical_size {
+                    let size = size.upscale(client_scale);
+                    instance.logical_size(size.w, size.h);
                 }
             }
 
             if new_location.is_some() {
-                instance.logical_position(output.logical_position.x, output.logical_position.y);
+                let position = output.logical_position.upscale(client_scale);
+                instance.logical_position(position.x, position.y);
             }
 
             // xdg_output.done() is deprecated since version 3
@@ -128,3 +164,11 @@ impl XdgOutput {
         }
     }
 }
+
+// Clients with a client scale operate in an upscaled coordinate space
+fn client_scale(xdg_output: &ZxdgOutputV1) -> i32 {
+    xdg_output
+        .data::<XdgOutputUserData>()
+        .map(|data| data.client_scale)
+        .unwrap_or(1)
+}
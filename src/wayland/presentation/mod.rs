@@ -92,6 +92,10 @@ impl PresentationState {
     ///
     /// It returns the presentation state, which you can drop to remove these global from
     /// the event loop in the future.
+    ///
+    /// `clk_id` is the clock domain of all presentation timestamps, e.g. the
+    /// [`id`](crate::utils::PresentationClock::id) of the
+    /// [`DrmDevice::presentation_clock`](crate::backend::drm::DrmDevice::presentation_clock).
     pub fn new<D>(display: &DisplayHandle, clk_id: u32) -> Self
     where
         D: GlobalDispatch<wp_presentation::WpPresentation, u32>
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, fmt, sync::Arc};
 
 use tracing::debug;
 use wayland_protocols::wp::primary_selection::zv1::server::zwp_primary_selection_device_v1::{
@@ -14,12 +14,32 @@ use crate::{
     },
 };
 
-use super::{PrimarySelectionHandler, PrimarySelectionState};
+use super::{MimeTypeFilter, PrimarySelectionHandler, PrimarySelectionState};
 
 #[doc(hidden)]
-#[derive(Debug)]
 pub struct PrimaryDeviceUserData {
     pub(crate) wl_seat: WlSeat,
+    pub(crate) mime_filter: Option<Arc<MimeTypeFilter>>,
+}
+
+impl fmt::Debug for PrimaryDeviceUserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrimaryDeviceUserData")
+            .field("wl_seat", &self.wl_seat)
+            .field("mime_filter", &self.mime_filter.is_some())
+            .finish()
+    }
+}
+
+impl PrimaryDeviceUserData {
+    /// Whether the given mime type may be advertised to the client
+    pub(crate) fn offers_mime_type(&self, client: &Client, mime_type: &str) -> bool {
+        offers_mime_type(self.mime_filter.as_deref(), client, mime_type)
+    }
+}
+
+pub(crate) fn offers_mime_type(filter: Option<&MimeTypeFilter>, client: &Client, mime_type: &str) -> bool {
+    filter.map(|filter| filter(client, mime_type)).unwrap_or(true)
 }
 
 impl<D> Dispatch<PrimaryDevice, PrimaryDeviceUserData, D> for PrimarySelectionState
@@ -17,6 +17,9 @@
 //!   allows you to set the contents of the selection for your clients
 //! - the `PrimarySelectionHandle` gives you the option to inspect new selections
 //!   by overriding [`PrimarySelectionHandler::new_selection].
+//! - [`PrimarySelectionState::with_mime_type_filter`] allows you to restrict which mime types of a
+//!   selection are advertised to which client, and [`PrimarySelectionHandler::intercept_receive`] lets you
+//!   interpose on transfers between clients.
 //!
 //! ## Initialization
 //!
@@ -61,7 +64,9 @@
 
 use std::{
     cell::{Ref, RefCell},
+    fmt,
     os::unix::io::{AsRawFd, OwnedFd},
+    sync::Arc,
 };
 
 use tracing::instrument;
@@ -107,12 +112,48 @@ pub trait PrimarySelectionHandler: Sized + SeatHandler {
         user_data: &Self::SelectionUserData,
     ) {
     }
+
+    /// A client requested to receive the contents of the primary selection of another client
+    ///
+    /// This allows to interpose on the transfer, e.g. to sanitize the contents or to convert text encodings.
+    /// Return the mime type and fd the source client should be asked to write into, or `None` to deny the
+    /// request. To transform the data, return the write end of a pipe and forward its contents into `fd`.
+    ///
+    /// The returned mime type has to be one of the mime types offered by the source, otherwise the request
+    /// is denied. The default implementation passes the request through unchanged.
+    ///
+    /// * `mime_type` - the requested mime type
+    /// * `fd` - the fd the requesting client reads from
+    /// * `client` - the client requesting the data
+    /// * `seat` - the seat the selection belongs to
+    #[allow(unused_variables)]
+    fn intercept_receive(
+        &mut self,
+        mime_type: String,
+        fd: OwnedFd,
+        client: Client,
+        seat: Seat<Self>,
+    ) -> Option<(String, OwnedFd)> {
+        Some((mime_type, fd))
+    }
 }
 
+/// Filter deciding if a mime type is advertised to a client
+pub type MimeTypeFilter = dyn Fn(&Client, &str) -> bool + Send + Sync;
+
 /// State of data device
-#[derive(Debug)]
 pub struct PrimarySelectionState {
     manager_global: GlobalId,
+    mime_filter: Option<Arc<MimeTypeFilter>>,
+}
+
+impl fmt::Debug for PrimarySelectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrimarySelectionState")
+            .field("manager_global", &self.manager_global)
+            .field("mime_filter", &self.mime_filter.is_some())
+            .finish()
+    }
 }
 
 impl PrimarySelectionState {
@@ -124,7 +165,26 @@ impl PrimarySelectionState {
     {
         let manager_global = display.create_global::<D, PrimaryDeviceManager, _>(1, ());
 
-        Self { manager_global }
+        Self {
+            manager_global,
+            mime_filter: None,
+        }
+    }
+
+    /// Restrict the mime types advertised to clients
+    ///
+    /// The filter is invoked for every mime type of a primary selection, when an offer is created
+    /// for a client, and decides if the mime type is advertised to the client. Requests to receive
+    /// mime types not advertised to a client are denied.
+    ///
+    /// This only affects devices created afterwards, so it should be set right after creating
+    /// the [`PrimarySelectionState`].
+    pub fn with_mime_type_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Client, &str) -> bool + Send + Sync + 'static,
+    {
+        self.mime_filter = Some(Arc::new(filter));
+        self
     }
 
     /// [ZwpPrimarySelectionDeviceManagerV1] GlobalId getter
@@ -319,7 +379,7 @@ mod handlers {
         D: 'static,
     {
         fn request(
-            _state: &mut D,
+            state: &mut D,
             client: &wayland_server::Client,
             _resource: &PrimaryDeviceManager,
             request: primary_device_manager::Request,
@@ -337,7 +397,13 @@ mod handlers {
                             seat.user_data()
                                 .insert_if_missing(|| RefCell::new(SeatData::<D::SelectionUserData>::new()));
 
-                            let device = data_init.init(id, PrimaryDeviceUserData { wl_seat });
+                            let device = data_init.init(
+                                id,
+                                PrimaryDeviceUserData {
+                                    wl_seat,
+                                    mime_filter: state.primary_selection_state().mime_filter.clone(),
+                                },
+                            );
 
                             let seat_data = seat
                                 .user_data()
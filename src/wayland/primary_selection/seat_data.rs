@@ -1,6 +1,6 @@
 use std::{
     os::unix::io::{AsRawFd, OwnedFd},
-    sync::Arc,
+    sync::{Arc, Weak},
 };
 
 use tracing::debug;
@@ -20,7 +20,10 @@ use crate::{
     utils::IsAlive,
 };
 
-use super::{with_source_metadata, PrimaryDeviceUserData, PrimarySelectionHandler, SourceMetadata};
+use super::{
+    device::offers_mime_type, with_source_metadata, MimeTypeFilter, PrimaryDeviceUserData,
+    PrimarySelectionHandler, SourceMetadata,
+};
 
 pub enum Selection<U: Clone + Send + Sync + 'static> {
     Empty,
@@ -31,6 +34,9 @@ pub enum Selection<U: Clone + Send + Sync + 'static> {
 pub struct SeatData<U: Clone + Send + Sync + 'static> {
     known_devices: Vec<PrimaryDevice>,
     selection: Selection<U>,
+    /// Replaced with every new selection, offers of compositor selections
+    /// only hold a weak reference to deny reads once their selection is gone.
+    selection_token: Arc<()>,
     current_focus: Option<Client>,
 }
 
@@ -39,6 +45,7 @@ impl<U: Clone + Send + Sync + 'static> Default for SeatData<U> {
         Self {
             known_devices: Vec::new(),
             selection: Selection::Empty,
+            selection_token: Arc::new(()),
             current_focus: None,
         }
     }
@@ -83,6 +90,7 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
             }
         }
         self.selection = new_selection;
+        self.selection_token = Arc::new(());
         self.send_selection::<D>(dh);
     }
 
@@ -131,13 +139,21 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
                     let source_clone = source.clone();
 
                     let handle = dh.backend_handle();
+                    let device_data = match pd.data::<PrimaryDeviceUserData>() {
+                        Some(data) => data,
+                        None => continue,
+                    };
                     // create a data offer
                     let offer = handle
                         .create_object::<D>(
                             client.id(),
                             PrimaryOffer::interface(),
                             pd.version(),
-                            Arc::new(ClientSelection { source: source_clone }),
+                            Arc::new(ClientSelection {
+                                source: source_clone,
+                                wl_seat: device_data.wl_seat.clone(),
+                                mime_filter: device_data.mime_filter.clone(),
+                            }),
                         )
                         .unwrap();
                     let offer = PrimaryOffer::from_id(dh, offer).unwrap();
@@ -145,8 +161,10 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
                     // advertize the offer to the client
                     pd.data_offer(&offer);
                     with_source_metadata(source, |meta| {
-                        for mime_type in meta.mime_types.iter().cloned() {
-                            offer.offer(mime_type);
+                        for mime_type in meta.mime_types.iter() {
+                            if device_data.offers_mime_type(client, mime_type) {
+                                offer.offer(mime_type.clone());
+                            }
                         }
                     })
                     .unwrap();
@@ -163,13 +181,20 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
                         continue;
                     }
 
-                    let offer_meta = meta.clone();
-
                     let handle = dh.backend_handle();
-                    let wl_seat = match pd.data::<PrimaryDeviceUserData>() {
-                        Some(data) => data.wl_seat.clone(),
+                    let device_data = match pd.data::<PrimaryDeviceUserData>() {
+                        Some(data) => data,
                         None => continue,
                     };
+                    let wl_seat = device_data.wl_seat.clone();
+                    let offer_meta = SourceMetadata {
+                        mime_types: meta
+                            .mime_types
+                            .iter()
+                            .filter(|mime_type| device_data.offers_mime_type(client, mime_type))
+                            .cloned()
+                            .collect(),
+                    };
                     // create a data offer
                     let offer = handle
                         .create_object::<D>(
@@ -177,9 +202,10 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
                             PrimaryOffer::interface(),
                             pd.version(),
                             Arc::new(ServerSelection {
-                                offer_meta,
+                                offer_meta: offer_meta.clone(),
                                 wl_seat,
                                 user_data: user_data.clone(),
+                                token: Arc::downgrade(&self.selection_token),
                             }),
                         )
                         .unwrap();
@@ -187,7 +213,7 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
 
                     // advertize the offer to the client
                     pd.data_offer(&offer);
-                    for mime_type in meta.mime_types.iter().cloned() {
+                    for mime_type in offer_meta.mime_types.iter().cloned() {
                         offer.offer(mime_type);
                     }
                     pd.selection(Some(&offer));
@@ -199,22 +225,30 @@ impl<U: Clone + Send + Sync + 'static> SeatData<U> {
 
 struct ClientSelection {
     source: PrimarySource,
+    wl_seat: WlSeat,
+    mime_filter: Option<Arc<MimeTypeFilter>>,
 }
 
 impl<D> ObjectData<D> for ClientSelection
 where
-    D: PrimarySelectionHandler,
+    D: PrimarySelectionHandler + 'static,
 {
     fn request(
         self: Arc<Self>,
         dh: &Handle,
-        _handler: &mut D,
+        handler: &mut D,
         _client_id: ClientId,
         msg: Message<ObjectId, OwnedFd>,
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
-        if let Ok((_resource, request)) = PrimaryOffer::parse_request(&dh, msg) {
-            handle_client_selection(request, &self.source);
+        if let Ok((resource, request)) = PrimaryOffer::parse_request(&dh, msg) {
+            let client = match dh.get_client(resource.id()) {
+                Ok(client) => client,
+                Err(_) => return None,
+            };
+            if let Some(seat) = Seat::<D>::from_resource(&self.wl_seat) {
+                handle_client_selection(handler, request, seat, client, &self);
+            }
         }
 
         None
@@ -223,19 +257,39 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-fn handle_client_selection(request: primary_offer::Request, source: &PrimarySource) {
+fn handle_client_selection<D>(
+    handler: &mut D,
+    request: primary_offer::Request,
+    seat: Seat<D>,
+    client: Client,
+    data: &ClientSelection,
+) where
+    D: PrimarySelectionHandler + 'static,
+{
     // selection data offers only care about the `receive` event
     if let primary_offer::Request::Receive { fd, mime_type } = request {
+        // check if the mime type was advertised to the client
+        if !offers_mime_type(data.mime_filter.as_deref(), &client, &mime_type) {
+            debug!("Denying a zwp_primary_selection_offer_v1.receive with filtered mime type.");
+            return;
+        }
+        let (mime_type, fd) = match handler.intercept_receive(mime_type, fd, client, seat) {
+            Some(request) => request,
+            None => {
+                debug!("Denying a zwp_primary_selection_offer_v1.receive intercepted by the compositor.");
+                return;
+            }
+        };
         // check if the source and associated mime type is still valid
         let valid =
-            with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
+            with_source_metadata(&data.source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
         // TODO:?
         // && source.as_ref().is_alive();
         if !valid {
             // deny the receive
             debug!("Denying a zwp_primary_selection_offer_v1.receive with invalid source.");
         } else {
-            source.send(mime_type, fd.as_raw_fd());
+            data.source.send(mime_type, fd.as_raw_fd());
         }
     }
 }
@@ -244,6 +298,7 @@ struct ServerSelection<U: Clone + Send + Sync + 'static> {
     offer_meta: SourceMetadata,
     wl_seat: WlSeat,
     user_data: U,
+    token: Weak<()>,
 }
 
 impl<D> ObjectData<D> for ServerSelection<D::SelectionUserData>
@@ -259,7 +314,8 @@ where
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
         if let Ok((_resource, request)) = PrimaryOffer::parse_request(&dh, msg) {
-            if self.wl_seat.is_alive() {
+            // the seat is gone or the compositor replaced its selection in the meantime
+            if !self.wl_seat.is_alive() || self.token.upgrade().is_none() {
                 return None;
             }
             if let Some(seat) = Seat::<D>::from_resource(&self.wl_seat) {
@@ -0,0 +1,115 @@
+//! Access control for privileged globals
+//!
+//! Some protocols grant clients capabilities, that should not be available to every client
+//! connected to the compositor. Examples are injecting input through
+//! `zwp_virtual_keyboard_manager_v1` or `zwp_input_method_manager_v2`, or placing
+//! desktop components using `zwlr_layer_shell_v1`.
+//!
+//! Modules providing such globals accept a client filter on creation, which decides if the global
+//! is advertised to, and can be bound by, a given client. Clients not passing the filter
+//! will never learn about the global's existence.
+//!
+//! [`PrivilegedGlobals`] allows to express a single policy for all of these globals
+//! and to derive the filter for each of them. The policy can be any callback, but a common
+//! approach is to decide on a client's permissions once, when it is inserted into the display
+//! (e.g. based on its credentials or the security context of the socket it connected to), store
+//! the result in its [`ClientData`] and make the policy consult that.
+//!
+//! ```
+//! use smithay::wayland::privileged::PrivilegedGlobals;
+//! use smithay::reexports::wayland_server::backend::{ClientData, ClientId, DisconnectReason};
+//! use smithay::reexports::wayland_protocols_misc::zwp_virtual_keyboard_v1::server::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+//!
+//! struct ClientState {
+//!     trusted: bool,
+//! }
+//!
+//! impl ClientData for ClientState {
+//!     fn initialized(&self, _client_id: ClientId) {}
+//!     fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+//! }
+//!
+//! // Only clients marked as trusted may use any of the privileged globals
+//! let privileged = PrivilegedGlobals::from_client_data(|data: &ClientState, _interface| data.trusted);
+//!
+//! // Derive the filter for a given global and pass it to the module creating the global, e.g.
+//! // `VirtualKeyboardManagerState::new::<State, _>(&display_handle, filter)`
+//! let filter = privileged.filter::<ZwpVirtualKeyboardManagerV1>();
+//! ```
+
+use std::{fmt, sync::Arc};
+
+use wayland_server::{
+    backend::{protocol::Interface, ClientData},
+    Client, Resource,
+};
+
+type Policy = dyn Fn(&Client, &'static Interface) -> bool + Send + Sync;
+
+/// Policy deciding which clients may access privileged globals
+///
+/// This type is cheap to clone, all clones share the same policy.
+#[derive(Clone)]
+pub struct PrivilegedGlobals {
+    policy: Arc<Policy>,
+}
+
+impl fmt::Debug for PrivilegedGlobals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrivilegedGlobals").finish_non_exhaustive()
+    }
+}
+
+impl PrivilegedGlobals {
+    /// Create a new policy from a callback
+    ///
+    /// The callback receives the client and the interface of the global in question
+    /// and returns whether the client is allowed to see and bind it.
+    pub fn new<F>(policy: F) -> Self
+    where
+        F: Fn(&Client, &'static Interface) -> bool + Send + Sync + 'static,
+    {
+        PrivilegedGlobals {
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Create a new policy granting access to every client
+    pub fn allow_all() -> Self {
+        Self::new(|_, _| true)
+    }
+
+    /// Create a new policy denying access to every client
+    pub fn deny_all() -> Self {
+        Self::new(|_, _| false)
+    }
+
+    /// Create a new policy based on the [`ClientData`] of a client
+    ///
+    /// Clients whose data is not of type `T` are denied access.
+    pub fn from_client_data<T, F>(policy: F) -> Self
+    where
+        T: ClientData + 'static,
+        F: Fn(&T, &'static Interface) -> bool + Send + Sync + 'static,
+    {
+        Self::new(move |client, interface| {
+            client
+                .get_data::<T>()
+                .map(|data| policy(data, interface))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Check if the given client may access globals of the given interface
+    pub fn is_allowed(&self, client: &Client, interface: &'static Interface) -> bool {
+        (self.policy)(client, interface)
+    }
+
+    /// Derive the client filter for globals of type `I`
+    ///
+    /// The returned filter can be passed to any module accepting a filter for its global.
+    pub fn filter<I: Resource>(&self) -> impl for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static {
+        let policy = self.policy.clone();
+        move |client| policy(client, I::interface())
+    }
+}
@@ -146,6 +146,7 @@ fn serialize_pressed_keys(keys: Vec<u32>) -> Vec<u8> {
 
 impl<D: SeatHandler + 'static> KeyboardTarget<D> for WlSurface {
     fn enter(&self, seat: &Seat<D>, _data: &mut D, keys: Vec<KeysymHandle<'_>>, serial: Serial) {
+        seat.record_serial(self, serial);
         for_each_focused_kbds(seat, self, |kbd| {
             kbd.enter(
                 serial.into(),
@@ -168,6 +169,7 @@ impl<D: SeatHandler + 'static> KeyboardTarget<D> for WlSurface {
         serial: Serial,
         time: u32,
     ) {
+        seat.record_serial(self, serial);
         for_each_focused_kbds(seat, self, |kbd| {
             kbd.key(serial.into(), time, key.raw_code() - 8, state.into())
         })
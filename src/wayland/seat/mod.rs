@@ -59,11 +59,14 @@
 
 pub(crate) mod keyboard;
 mod pointer;
-mod touch;
+pub mod touch;
 
 use std::{fmt, sync::Arc};
 
-use crate::input::{Inner, Seat, SeatHandler, SeatRc, SeatState};
+use crate::{
+    input::{Inner, Seat, SeatHandler, SeatRc, SeatState},
+    utils::{Serial, SerialValidator},
+};
 
 pub use self::{
     keyboard::KeyboardUserData,
@@ -165,11 +168,21 @@ impl<D: SeatHandler + 'static> SeatState<D> {
     {
         let Seat { arc } = self.new_seat(name);
 
-        let global_id = display.create_global::<D, _, _>(7, SeatGlobalData { arc: arc.clone() });
+        let global_id = display.create_global::<D, _, _>(9, SeatGlobalData { arc: arc.clone() });
         arc.inner.lock().unwrap().global = Some(global_id);
 
         Seat { arc }
     }
+
+    /// Remove a seat and its global
+    ///
+    /// Clients are notified about the removal of the global. See [`SeatState::remove_seat`].
+    pub fn remove_wl_seat(&mut self, display: &DisplayHandle, seat: &Seat<D>) {
+        if let Some(global_id) = seat.arc.inner.lock().unwrap().global.take() {
+            display.remove_global::<D>(global_id);
+        }
+        self.remove_seat(seat);
+    }
 }
 
 impl<D: SeatHandler + 'static> Seat<D> {
@@ -204,6 +217,29 @@ impl<D: SeatHandler + 'static> Seat<D> {
         self.arc.inner.lock().unwrap().global.as_ref().cloned()
     }
 
+    /// Returns the [`SerialValidator`] recording the serials of the input events sent by this seat
+    ///
+    /// This can be used to check the serials of requests like `xdg_toplevel.move`:
+    ///
+    /// ```ignore
+    /// let client = surface.client().unwrap();
+    /// if !seat.serial_validator().validate(&client.id(), serial) {
+    ///     // forged or stale serial
+    ///     return;
+    /// }
+    /// ```
+    pub fn serial_validator(&self) -> &SerialValidator {
+        self.user_data()
+            .insert_if_missing_threadsafe(SerialValidator::default);
+        self.user_data().get::<SerialValidator>().unwrap()
+    }
+
+    pub(crate) fn record_serial(&self, surface: &wl_surface::WlSurface, serial: Serial) {
+        if let Some(client) = surface.client() {
+            self.serial_validator().record(&client.id(), serial);
+        }
+    }
+
     /// Adds the touch capability to this seat
     ///
     /// You are provided a [`TouchHandle`], which allows you to send input events
@@ -230,7 +266,7 @@ impl<D: SeatHandler + 'static> Seat<D> {
     /// # let mut seat: Seat<State> = unimplemented!();
     /// let touch_handle = seat.add_touch();
     /// ```
-    pub fn add_touch(&mut self) -> TouchHandle {
+    pub fn add_touch(&mut self) -> TouchHandle<D> {
         let mut inner = self.arc.inner.lock().unwrap();
         let touch = TouchHandle::new();
         if inner.touch.is_some() {
@@ -244,7 +280,7 @@ impl<D: SeatHandler + 'static> Seat<D> {
     }
 
     /// Access the touch device of this seat, if any.
-    pub fn get_touch(&self) -> Option<TouchHandle> {
+    pub fn get_touch(&self) -> Option<TouchHandle<D>> {
         self.arc.inner.lock().unwrap().touch.clone()
     }
 
@@ -357,7 +393,7 @@ where
                 let touch = data_init.init(
                     id,
                     TouchUserData {
-                        handle: inner.touch.clone(),
+                        known_handles: inner.touch.as_ref().map(|h| h.known_handles()),
                     },
                 );
 
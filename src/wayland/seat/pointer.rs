@@ -5,8 +5,8 @@ use wayland_server::{
     backend::{ClientId, ObjectId},
     protocol::{
         wl_pointer::{
-            self, Axis as WlAxis, AxisSource as WlAxisSource, ButtonState as WlButtonState, Request,
-            WlPointer,
+            self, Axis as WlAxis, AxisRelativeDirection as WlAxisRelativeDirection,
+            AxisSource as WlAxisSource, ButtonState as WlButtonState, Request, WlPointer,
         },
         wl_surface::WlSurface,
     },
@@ -14,7 +14,7 @@ use wayland_server::{
 };
 
 use crate::{
-    backend::input::{Axis, AxisSource, ButtonState},
+    backend::input::{Axis, AxisRelativeDirection, AxisSource, ButtonState},
     input::{
         pointer::{
             AxisFrame, ButtonEvent, CursorImageAttributes, CursorImageStatus, MotionEvent, PointerHandle,
@@ -83,8 +83,11 @@ where
         if let Some(pointer) = seat.get_pointer() {
             *pointer.last_enter.lock().unwrap() = Some(serial);
         }
+        seat.record_serial(self, serial);
+        let scale = compositor::surface_client_scale(self) as f64;
+        let location = event.location.upscale(scale);
         for_each_focused_pointers(seat, self, |ptr| {
-            ptr.enter(serial.into(), self, event.location.x, event.location.y);
+            ptr.enter(serial.into(), self, location.x, location.y);
             if ptr.version() >= 5 {
                 ptr.frame();
             }
@@ -102,28 +105,34 @@ where
         }
     }
     fn motion(&self, seat: &Seat<D>, _data: &mut D, event: &MotionEvent) {
+        let scale = compositor::surface_client_scale(self) as f64;
+        let location = event.location.upscale(scale);
         for_each_focused_pointers(seat, self, |ptr| {
-            ptr.motion(event.time, event.location.x, event.location.y);
+            ptr.motion(event.time, location.x, location.y);
             if ptr.version() >= 5 {
                 ptr.frame();
             }
         })
     }
     fn relative_motion(&self, seat: &Seat<D>, _data: &mut D, event: &RelativeMotionEvent) {
+        let scale = compositor::surface_client_scale(self) as f64;
+        let delta = event.delta.upscale(scale);
+        let delta_unaccel = event.delta_unaccel.upscale(scale);
         for_each_focused_relative_pointers(seat, self, |ptr| {
             let utime_hi = (event.utime >> 32) as u32;
             let utime_lo = (event.utime & 0xffffffff) as u32;
             ptr.relative_motion(
                 utime_hi,
                 utime_lo,
-                event.delta.x,
-                event.delta.y,
-                event.delta_unaccel.x,
-                event.delta_unaccel.y,
+                delta.x,
+                delta.y,
+                delta_unaccel.x,
+                delta_unaccel.y,
             );
         })
     }
     fn button(&self, seat: &Seat<D>, _data: &mut D, event: &ButtonEvent) {
+        seat.record_serial(self, event.serial);
         for_each_focused_pointers(seat, self, |ptr| {
             ptr.button(event.serial.into(), event.time, event.button, event.state.into());
             if ptr.version() >= 5 {
@@ -133,13 +142,6 @@ where
     }
     fn axis(&self, seat: &Seat<D>, _data: &mut D, details: AxisFrame) {
         for_each_focused_pointers(seat, self, |ptr| {
-            // axis
-            if details.axis.0 != 0.0 {
-                ptr.axis(details.time, WlAxis::HorizontalScroll, details.axis.0);
-            }
-            if details.axis.1 != 0.0 {
-                ptr.axis(details.time, WlAxis::VerticalScroll, details.axis.1);
-            }
             if ptr.version() >= 5 {
                 // axis source
                 if let Some(source) = details.source {
@@ -156,23 +158,55 @@ where
                     .into();
                     ptr.axis_source(source);
                 }
-                // axis discrete
-                if let Some((x, y)) = details.discrete {
-                    if x != 0 {
-                        ptr.axis_discrete(WlAxis::HorizontalScroll, x);
+            }
+
+            let axes = [
+                (
+                    WlAxis::HorizontalScroll,
+                    details.axis.0,
+                    details.discrete.map(|(x, _)| x),
+                    details.v120.map(|(x, _)| x),
+                    details.relative_direction.0,
+                    details.stop.0,
+                ),
+                (
+                    WlAxis::VerticalScroll,
+                    details.axis.1,
+                    details.discrete.map(|(_, y)| y),
+                    details.v120.map(|(_, y)| y),
+                    details.relative_direction.1,
+                    details.stop.1,
+                ),
+            ];
+            for (axis, value, discrete, v120, relative_direction, stop) in axes {
+                if value != 0.0 {
+                    // relative direction, sent before the axis event it applies to
+                    if ptr.version() >= 9 {
+                        ptr.axis_relative_direction(axis, relative_direction.into());
                     }
-                    if y != 0 {
-                        ptr.axis_discrete(WlAxis::VerticalScroll, y);
+                    // axis_discrete was replaced by axis_value120 in version 8,
+                    // both have to be sent before the axis event
+                    if ptr.version() >= 8 {
+                        let v120 = v120.or_else(|| discrete.map(|discrete| discrete * 120));
+                        if let Some(v120) = v120.filter(|v120| *v120 != 0) {
+                            ptr.axis_value120(axis, v120);
+                        }
+                    } else if ptr.version() >= 5 {
+                        if let Some(discrete) = discrete.filter(|discrete| *discrete != 0) {
+                            ptr.axis_discrete(axis, discrete);
+                        }
                     }
+                    // axis
+                    ptr.axis(details.time, axis, value);
                 }
                 // stop
-                if details.stop.0 {
-                    ptr.axis_stop(details.time, WlAxis::HorizontalScroll);
+                if stop && ptr.version() >= 5 {
+                    ptr.axis_stop(details.time, axis);
                 }
-                if details.stop.1 {
-                    ptr.axis_stop(details.time, WlAxis::VerticalScroll);
-                }
-                // frame
+            }
+
+            // frame
+            if ptr.version() >= 5 {
                 ptr.frame();
             }
         })
@@ -339,6 +373,15 @@ impl From<AxisSource> for WlAxisSource {
     }
 }
 
+impl From<AxisRelativeDirection> for WlAxisRelativeDirection {
+    fn from(direction: AxisRelativeDirection) -> WlAxisRelativeDirection {
+        match direction {
+            AxisRelativeDirection::Identical => WlAxisRelativeDirection::Identical,
+            AxisRelativeDirection::Inverted => WlAxisRelativeDirection::Inverted,
+        }
+    }
+}
+
 impl From<ButtonState> for WlButtonState {
     fn from(state: ButtonState) -> WlButtonState {
         match state {
@@ -362,3 +405,120 @@ impl TryFrom<WlButtonState> for ButtonState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wayland_client::protocol::{wl_pointer as client_pointer, wl_seat::WlSeat as ClientSeat};
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        Client, Display,
+    };
+
+    use super::*;
+    use crate::{
+        utils::SERIAL_COUNTER,
+        wayland::{
+            compositor::{CompositorClientState, CompositorHandler, CompositorState},
+            testing::{TestClient, TestEvent},
+        },
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        seat_state: SeatState<State>,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<ClientState>().unwrap().compositor_state
+        }
+
+        fn commit(&mut self, _surface: &WlSurface) {}
+    }
+
+    impl SeatHandler for State {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_seat!(State);
+
+    #[derive(Default)]
+    struct ClientState {
+        compositor_state: CompositorClientState,
+    }
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    #[test]
+    fn scaled_client_coordinates() {
+        let mut display = Display::<State>::new().unwrap();
+        let dh = display.handle();
+        let mut seat_state = SeatState::new();
+        let mut seat = seat_state.new_wl_seat(&dh, "seat-0");
+        let pointer = seat.add_pointer();
+        let mut state = State {
+            compositor_state: CompositorState::new::<State>(&dh),
+            seat_state,
+        };
+
+        let client_state = ClientState::default();
+        client_state.compositor_state.set_client_scale(2);
+        let mut client = TestClient::new(&mut display, Arc::new(client_state)).unwrap();
+        client.roundtrip(&mut display, &mut state).unwrap();
+
+        let wl_seat = client.bind::<ClientSeat>(7).unwrap();
+        let _wl_pointer = wl_seat.get_pointer(&client.queue_handle(), ());
+        let surface = client.create_surface();
+        client.roundtrip(&mut display, &mut state).unwrap();
+        let surface = client.server_object::<WlSurface>(&dh, &surface).unwrap();
+
+        let focus = Some((surface, (100, 100).into()));
+        for location in [(110.0, 120.5), (111.0, 120.0)] {
+            let event = MotionEvent {
+                location: location.into(),
+                serial: SERIAL_COUNTER.next_serial(),
+                time: 0,
+            };
+            pointer.motion(&mut state, focus.clone(), &event);
+        }
+        client.roundtrip(&mut display, &mut state).unwrap();
+
+        let enter = client.expect_event(|event| match event {
+            TestEvent::Pointer {
+                event:
+                    client_pointer::Event::Enter {
+                        surface_x, surface_y, ..
+                    },
+                ..
+            } => Some((*surface_x, *surface_y)),
+            _ => None,
+        });
+        assert_eq!(enter, (20.0, 41.0));
+        let motion = client.expect_event(|event| match event {
+            TestEvent::Pointer {
+                event:
+                    client_pointer::Event::Motion {
+                        surface_x, surface_y, ..
+                    },
+                ..
+            } => Some((*surface_x, *surface_y)),
+            _ => None,
+        });
+        assert_eq!(motion, (22.0, 40.0));
+    }
+}
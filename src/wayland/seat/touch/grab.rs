@@ -0,0 +1,170 @@
+use std::fmt;
+
+use crate::{
+    backend::input::TouchSlot,
+    input::SeatHandler,
+    utils::{Logical, Point, Serial},
+};
+
+use super::{DownEvent, MotionEvent, TouchInnerHandle, TouchTarget, UpEvent};
+
+/// A trait to implement a touch grab
+///
+/// In some context, it is necessary to temporarily change the behavior of the touch handle. This is
+/// typically known as a touch grab. A typical example would be moving or resizing a window, or a
+/// drag'n'drop operation initiated by a touch point, during which the underlying surfaces should no
+/// longer receive classic touch events.
+///
+/// This trait is the interface to intercept regular touch events and change them as needed, its
+/// interface mimics the [`TouchHandle`](super::TouchHandle) interface.
+///
+/// Any interactions with [`TouchHandle`](super::TouchHandle)
+/// should be done using [`TouchInnerHandle`], as handle is borrowed/locked before grab methods are called,
+/// so calling methods on [`TouchHandle`](super::TouchHandle) would result in a deadlock.
+///
+/// If your logic decides that the grab should end, both [`TouchInnerHandle`]
+/// and [`TouchHandle`](super::TouchHandle) have
+/// a method to change it.
+///
+/// When your grab ends (either as you requested it or if it was forcefully cancelled by the server),
+/// the struct implementing this trait will be dropped. As such you should put clean-up logic in the destructor,
+/// rather than trying to guess when the grab will end. Clean-up logic requiring access to the compositor
+/// state can be put into [`TouchGrab::unset`] instead.
+pub trait TouchGrab<D: SeatHandler>: Send {
+    /// A new touch point appeared
+    ///
+    /// This method allows you attach additional behavior to a down event, possibly altering it.
+    /// You generally will want to invoke `TouchInnerHandle::down()` as part of your processing. If you
+    /// don't, the rest of the compositor will behave as if the down event never occurred.
+    fn down(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &DownEvent,
+    );
+    /// A touch point disappeared
+    ///
+    /// This method allows you attach additional behavior to an up event, possibly altering it.
+    /// You generally will want to invoke `TouchInnerHandle::up()` as part of your processing. If you
+    /// don't, the rest of the compositor will behave as if the up event never occurred.
+    fn up(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &UpEvent);
+    /// A touch point has changed coordinates
+    ///
+    /// This method allows you attach additional behavior to a motion event, possibly altering it.
+    /// You generally will want to invoke `TouchInnerHandle::motion()` as part of your processing. If you
+    /// don't, the rest of the compositor will behave as if the motion event never occurred.
+    ///
+    /// The `focus` is the target currently under the touch point, which is not necessarily the
+    /// target the touch point went down on.
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    );
+    /// The touch session was cancelled
+    ///
+    /// You generally will want to invoke `TouchInnerHandle::cancel()` as part of your processing.
+    fn cancel(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>);
+    /// The data about the event that started the grab.
+    fn start_data(&self) -> &GrabStartData<D>;
+    /// The grab was ended from outside of the grab
+    ///
+    /// This is invoked when the grab is unset or replaced by another grab through the
+    /// [`TouchHandle`](super::TouchHandle), but not when the grab ends itself through
+    /// the [`TouchInnerHandle`] passed to its methods.
+    #[allow(unused_variables)]
+    fn unset(&mut self, data: &mut D) {}
+}
+
+/// Data about the event that started the grab.
+pub struct GrabStartData<D: SeatHandler> {
+    /// The focused target and its location, if any, at the start of the grab.
+    ///
+    /// The location coordinates are in the global compositor space.
+    pub focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+    /// The touch point that initiated the grab.
+    pub slot: TouchSlot,
+    /// The location of the down event that initiated the grab, in the global compositor space.
+    pub location: Point<f64, Logical>,
+}
+
+impl<D: SeatHandler + 'static> fmt::Debug for GrabStartData<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GrabStartData")
+            .field("focus", &self.focus.as_ref().map(|_| "..."))
+            .field("slot", &self.slot)
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+impl<D: SeatHandler + 'static> Clone for GrabStartData<D> {
+    fn clone(&self) -> Self {
+        GrabStartData {
+            focus: self.focus.clone(),
+            slot: self.slot,
+            location: self.location,
+        }
+    }
+}
+
+pub(super) enum GrabStatus<D> {
+    None,
+    Active(Serial, Box<dyn TouchGrab<D>>),
+    Borrowed,
+}
+
+// TouchGrab is a trait, so we have to impl Debug manually
+impl<D> fmt::Debug for GrabStatus<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrabStatus::None => f.debug_tuple("GrabStatus::None").finish(),
+            GrabStatus::Active(serial, _) => f.debug_tuple("GrabStatus::Active").field(&serial).finish(),
+            GrabStatus::Borrowed => f.debug_tuple("GrabStatus::Borrowed").finish(),
+        }
+    }
+}
+
+// The default grab, the behavior when no particular grab is in progress
+pub(super) struct DefaultGrab;
+
+impl<D> TouchGrab<D> for DefaultGrab
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+{
+    fn down(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &DownEvent,
+    ) {
+        handle.down(data, focus, event);
+    }
+
+    fn up(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>, event: &UpEvent) {
+        handle.up(data, event);
+    }
+
+    fn motion(
+        &mut self,
+        data: &mut D,
+        handle: &mut TouchInnerHandle<'_, D>,
+        _focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        handle.motion(data, event);
+    }
+
+    fn cancel(&mut self, data: &mut D, handle: &mut TouchInnerHandle<'_, D>) {
+        handle.cancel(data);
+    }
+
+    fn start_data(&self) -> &GrabStartData<D> {
+        unreachable!()
+    }
+}
@@ -0,0 +1,606 @@
+//! Utilities for touch support
+//!
+//! The [`TouchHandle`] of a seat is used to forward touch events to clients. Just like pointers,
+//! the behavior of the touch handle can be temporarily altered by a [`TouchGrab`], e.g. to move
+//! or resize a window or to drive a drag'n'drop operation initiated by touch.
+//!
+//! Touch points are delivered to the same kind of targets as the pointer, i.e. the
+//! [`SeatHandler::PointerFocus`] of your compositor, which has to implement [`TouchTarget`].
+//! This allows compositor-internal elements like server-side decorations to receive touch
+//! input just like client surfaces.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use wayland_server::{
+    backend::{ClientId, ObjectId},
+    protocol::wl_touch::{self, WlTouch},
+    Dispatch, DisplayHandle, Resource,
+};
+
+use super::{SeatHandler, SeatState};
+use crate::backend::input::TouchSlot;
+use crate::input::Seat;
+use crate::utils::{IsAlive, Serial};
+use crate::utils::{Logical, Point};
+use crate::wayland::compositor;
+use crate::wayland::seat::wl_surface::WlSurface;
+
+mod grab;
+
+use grab::{DefaultGrab, GrabStatus};
+pub use grab::{GrabStartData, TouchGrab};
+
+type KnownHandles = Arc<Mutex<Vec<WlTouch>>>;
+
+/// Trait representing object that can receive touch interactions
+///
+/// The locations of the events are relative to the origin of the target.
+pub trait TouchTarget<D>
+where
+    D: SeatHandler,
+{
+    /// A new touch point went down on this target
+    fn down(&self, seat: &Seat<D>, data: &mut D, event: &DownEvent);
+    /// A touch point of this target went up
+    fn up(&self, seat: &Seat<D>, data: &mut D, event: &UpEvent);
+    /// A touch point of this target moved
+    fn motion(&self, seat: &Seat<D>, data: &mut D, event: &MotionEvent);
+    /// The touch session of this target was cancelled
+    fn cancel(&self, seat: &Seat<D>, data: &mut D);
+    /// The shape of a touch point of this target changed
+    fn shape(&self, seat: &Seat<D>, data: &mut D, slot: TouchSlot, major: f64, minor: f64);
+    /// The orientation of a touch point of this target changed
+    fn orientation(&self, seat: &Seat<D>, data: &mut D, slot: TouchSlot, orientation: f64);
+}
+
+/// An handle to a touch handler.
+///
+/// It can be cloned and all clones manipulate the same internal state.
+///
+/// This handle gives you access to an interface to send touch events to your
+/// clients.
+///
+/// When sending events using this handle, they will be intercepted by a touch
+/// grab if any is active. See the [`TouchGrab`] trait for details.
+pub struct TouchHandle<D: SeatHandler> {
+    inner: Arc<Mutex<TouchInternal<D>>>,
+    known_handles: KnownHandles,
+}
+
+impl<D: SeatHandler> fmt::Debug for TouchHandle<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchHandle")
+            .field("inner", &self.inner)
+            .field("known_handles", &self.known_handles)
+            .finish()
+    }
+}
+
+impl<D: SeatHandler> Clone for TouchHandle<D> {
+    fn clone(&self) -> Self {
+        TouchHandle {
+            inner: self.inner.clone(),
+            known_handles: self.known_handles.clone(),
+        }
+    }
+}
+
+impl<D: SeatHandler> ::std::cmp::PartialEq for TouchHandle<D> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl<D: SeatHandler + 'static> TouchHandle<D> {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TouchInternal::new())),
+            known_handles: Default::default(),
+        }
+    }
+
+    /// Register a new touch handle to this handler
+    ///
+    /// This should be done first, before anything else is done with this touch handle.
+    pub(crate) fn new_touch(&self, touch: WlTouch) {
+        self.known_handles.lock().unwrap().push(touch);
+    }
+
+    pub(crate) fn known_handles(&self) -> KnownHandles {
+        self.known_handles.clone()
+    }
+
+    fn get_seat(&self, data: &mut D) -> Seat<D> {
+        let seat_state = data.seat_state();
+        seat_state
+            .seats
+            .iter()
+            .find(|seat| seat.get_touch().map(|h| &h == self).unwrap_or(false))
+            .cloned()
+            .unwrap()
+    }
+}
+
+impl<D> TouchHandle<D>
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+{
+    /// Change the current grab on this touch handle to the provided grab
+    ///
+    /// Overwrites any current grab.
+    pub fn set_grab<G: TouchGrab<D> + 'static>(&self, data: &mut D, grab: G, serial: Serial) {
+        self.inner.lock().unwrap().set_grab(data, serial, grab);
+    }
+
+    /// Remove any current grab on this touch handle, resetting it to the default behavior
+    pub fn unset_grab(&self, data: &mut D) {
+        self.inner.lock().unwrap().unset_grab(data);
+    }
+
+    /// Check if this touch handle is currently grabbed with this serial
+    pub fn has_grab(&self, serial: Serial) -> bool {
+        let guard = self.inner.lock().unwrap();
+        match guard.grab {
+            GrabStatus::Active(s, _) => s == serial,
+            _ => false,
+        }
+    }
+
+    /// Check if this touch handle is currently being grabbed
+    pub fn is_grabbed(&self) -> bool {
+        let guard = self.inner.lock().unwrap();
+        !matches!(guard.grab, GrabStatus::None)
+    }
+
+    /// Returns the start data for the grab, if any.
+    pub fn grab_start_data(&self) -> Option<GrabStartData<D>> {
+        let guard = self.inner.lock().unwrap();
+        match &guard.grab {
+            GrabStatus::Active(_, g) => Some(g.start_data().clone()),
+            _ => None,
+        }
+    }
+
+    /// Notify about new touch points.
+    ///
+    /// You provide the target on top of which the touch point went down, and the coordinates
+    /// of its origin in the global compositor space (or `None` if the touch point is not on
+    /// top of any target).
+    ///
+    /// The touch point keeps this focus until it is lifted, subsequent motion events are
+    /// delivered to the same target.
+    pub fn down(
+        &self,
+        data: &mut D,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &DownEvent,
+    ) {
+        let seat = self.get_seat(data);
+        let mut inner = self.inner.lock().unwrap();
+        inner.with_grab(&seat, |mut handle, grab| {
+            grab.down(data, &mut handle, focus, event);
+        });
+    }
+
+    /// Notify about touch point removal.
+    pub fn up(&self, data: &mut D, event: &UpEvent) {
+        let seat = self.get_seat(data);
+        let mut inner = self.inner.lock().unwrap();
+        inner.with_grab(&seat, |mut handle, grab| {
+            grab.up(data, &mut handle, event);
+        });
+    }
+
+    /// Notify about touch motion.
+    ///
+    /// You provide the target currently under the touch point and the coordinates of its
+    /// origin in the global compositor space (or `None` if the touch point is not on top of
+    /// any target). It is only relevant to grabs, targets are always notified relative
+    /// to the target the touch point went down on.
+    pub fn motion(
+        &self,
+        data: &mut D,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        let seat = self.get_seat(data);
+        let mut inner = self.inner.lock().unwrap();
+        inner.with_grab(&seat, |mut handle, grab| {
+            grab.motion(data, &mut handle, focus, event);
+        });
+    }
+
+    /// Notify about touch shape changes.
+    pub fn shape(&self, data: &mut D, slot: TouchSlot, major: f64, minor: f64) {
+        let seat = self.get_seat(data);
+        self.inner.lock().unwrap().shape(data, &seat, slot, major, minor);
+    }
+
+    /// Notify about touch shape orientation.
+    pub fn orientation(&self, data: &mut D, slot: TouchSlot, orientation: f64) {
+        let seat = self.get_seat(data);
+        self.inner
+            .lock()
+            .unwrap()
+            .orientation(data, &seat, slot, orientation);
+    }
+
+    /// Notify about touch cancellation.
+    ///
+    /// This should be sent by the compositor when the touch stream is recognized as
+    /// a global gesture. Cancellation applies to all currently active touch slots.
+    pub fn cancel(&self, data: &mut D) {
+        let seat = self.get_seat(data);
+        let mut inner = self.inner.lock().unwrap();
+        inner.with_grab(&seat, |mut handle, grab| {
+            grab.cancel(data, &mut handle);
+        });
+    }
+}
+
+/// This inner handle is accessed from inside a touch grab logic, and directly
+/// sends event to the client
+pub struct TouchInnerHandle<'a, D: SeatHandler> {
+    inner: &'a mut TouchInternal<D>,
+    seat: &'a Seat<D>,
+}
+
+impl<'a, D: SeatHandler> fmt::Debug for TouchInnerHandle<'a, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchInnerHandle")
+            .field("inner", &self.inner)
+            .field("seat", &self.seat.arc.name)
+            .finish()
+    }
+}
+
+impl<'a, D> TouchInnerHandle<'a, D>
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+{
+    /// Change the current grab on this touch handle to the provided grab
+    ///
+    /// Overwrites any current grab.
+    pub fn set_grab<G: TouchGrab<D> + 'static>(&mut self, data: &mut D, serial: Serial, grab: G) {
+        self.inner.set_grab(data, serial, grab);
+    }
+
+    /// Remove any current grab on this touch handle, resetting it to the default behavior
+    pub fn unset_grab(&mut self, data: &mut D) {
+        self.inner.unset_grab(data);
+    }
+
+    /// The touch points currently down on top of a target
+    pub fn current_slots(&self) -> impl Iterator<Item = TouchSlot> + '_ {
+        self.inner.focus.keys().copied()
+    }
+
+    /// Access the current focus of a touch point
+    pub fn current_focus(&self, slot: TouchSlot) -> Option<&<D as SeatHandler>::PointerFocus> {
+        self.inner.focus.get(&slot).map(|(focus, _)| focus)
+    }
+
+    /// Notify about a new touch point
+    ///
+    /// See [`TouchHandle::down`].
+    pub fn down(
+        &mut self,
+        data: &mut D,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &DownEvent,
+    ) {
+        self.inner.down(data, self.seat, focus, event);
+    }
+
+    /// Notify about touch point removal
+    pub fn up(&mut self, data: &mut D, event: &UpEvent) {
+        self.inner.up(data, self.seat, event);
+    }
+
+    /// Notify about touch motion
+    ///
+    /// The event is delivered to the target the touch point went down on.
+    pub fn motion(&mut self, data: &mut D, event: &MotionEvent) {
+        self.inner.motion(data, self.seat, event);
+    }
+
+    /// Notify about touch cancellation
+    pub fn cancel(&mut self, data: &mut D) {
+        self.inner.cancel(data, self.seat);
+    }
+}
+
+struct TouchInternal<D: SeatHandler> {
+    // focused target of every touch point and the location of its origin in the global compositor space
+    focus: HashMap<TouchSlot, (<D as SeatHandler>::PointerFocus, Point<f64, Logical>)>,
+    grab: GrabStatus<D>,
+}
+
+// D does not need to implement debug, so we have to impl Debug manually
+impl<D: SeatHandler> fmt::Debug for TouchInternal<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TouchInternal")
+            .field("focus", &self.focus.keys().collect::<Vec<_>>())
+            .field("grab", &self.grab)
+            .finish()
+    }
+}
+
+impl<D: SeatHandler + 'static> TouchInternal<D> {
+    fn new() -> Self {
+        TouchInternal {
+            focus: HashMap::new(),
+            grab: GrabStatus::None,
+        }
+    }
+
+    fn set_grab<G: TouchGrab<D> + 'static>(&mut self, data: &mut D, serial: Serial, grab: G) {
+        let previous = ::std::mem::replace(&mut self.grab, GrabStatus::Active(serial, Box::new(grab)));
+        if let GrabStatus::Active(_, mut previous) = previous {
+            previous.unset(data);
+        }
+    }
+
+    fn unset_grab(&mut self, data: &mut D) {
+        if let GrabStatus::Active(_, mut grab) = ::std::mem::replace(&mut self.grab, GrabStatus::None) {
+            grab.unset(data);
+        }
+    }
+
+    fn down(
+        &mut self,
+        data: &mut D,
+        seat: &Seat<D>,
+        focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &DownEvent,
+    ) where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        let (target, target_location) = match focus {
+            Some(focus) => focus,
+            None => {
+                // The touch point is not over any target, nobody is notified about it.
+                self.focus.remove(&event.slot);
+                return;
+            }
+        };
+
+        let target_location = target_location.to_f64();
+        let mut event = *event;
+        event.location -= target_location;
+        target.down(seat, data, &event);
+        self.focus.insert(event.slot, (target, target_location));
+    }
+
+    fn up(&mut self, data: &mut D, seat: &Seat<D>, event: &UpEvent)
+    where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        // Forget about this slot and its target.
+        if let Some((target, _)) = self.focus.remove(&event.slot) {
+            target.up(seat, data, event);
+        }
+    }
+
+    fn motion(&self, data: &mut D, seat: &Seat<D>, event: &MotionEvent)
+    where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        if let Some((target, target_location)) = self.focus.get(&event.slot) {
+            let mut event = *event;
+            event.location -= *target_location;
+            target.motion(seat, data, &event);
+        }
+    }
+
+    fn shape(&self, data: &mut D, seat: &Seat<D>, slot: TouchSlot, major: f64, minor: f64)
+    where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        if let Some((target, _)) = self.focus.get(&slot) {
+            target.shape(seat, data, slot, major, minor);
+        }
+    }
+
+    fn orientation(&self, data: &mut D, seat: &Seat<D>, slot: TouchSlot, orientation: f64)
+    where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        if let Some((target, _)) = self.focus.get(&slot) {
+            target.orientation(seat, data, slot, orientation);
+        }
+    }
+
+    fn cancel(&mut self, data: &mut D, seat: &Seat<D>)
+    where
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        // Several touch points might share a target, only cancel each target once.
+        let mut targets: Vec<<D as SeatHandler>::PointerFocus> = Vec::new();
+        for (target, _) in self.focus.drain().map(|(_, focus)| focus) {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        for target in targets {
+            target.cancel(seat, data);
+        }
+    }
+
+    fn with_grab<F>(&mut self, seat: &Seat<D>, f: F)
+    where
+        F: FnOnce(TouchInnerHandle<'_, D>, &mut dyn TouchGrab<D>),
+        <D as SeatHandler>::PointerFocus: TouchTarget<D>,
+    {
+        let mut grab = ::std::mem::replace(&mut self.grab, GrabStatus::Borrowed);
+        match grab {
+            GrabStatus::Borrowed => panic!("Accessed a touch grab from within a touch grab access."),
+            GrabStatus::Active(_, ref mut handler) => {
+                // If this grab is associated with a surface that is no longer alive, discard it
+                if let Some((ref focus, _)) = handler.start_data().focus {
+                    if !focus.alive() {
+                        self.grab = GrabStatus::None;
+                        f(TouchInnerHandle { inner: self, seat }, &mut DefaultGrab);
+                        return;
+                    }
+                }
+                f(TouchInnerHandle { inner: self, seat }, &mut **handler);
+            }
+            GrabStatus::None => {
+                f(TouchInnerHandle { inner: self, seat }, &mut DefaultGrab);
+            }
+        }
+
+        if let GrabStatus::Borrowed = self.grab {
+            // the grab has not been ended nor replaced, put it back in place
+            self.grab = grab;
+        }
+    }
+}
+
+fn for_each_focused_touch<D: SeatHandler + 'static>(
+    seat: &Seat<D>,
+    surface: &WlSurface,
+    mut f: impl FnMut(WlTouch),
+) {
+    if let Some(touch) = seat.get_touch() {
+        let inner = touch.known_handles.lock().unwrap();
+        for handle in &*inner {
+            if handle.id().same_client_as(&surface.id()) {
+                f(handle.clone())
+            }
+        }
+    }
+}
+
+impl<D> TouchTarget<D> for WlSurface
+where
+    D: SeatHandler + 'static,
+{
+    fn down(&self, seat: &Seat<D>, _data: &mut D, event: &DownEvent) {
+        seat.record_serial(self, event.serial);
+        let scale = compositor::surface_client_scale(self) as f64;
+        let location = event.location.upscale(scale);
+        for_each_focused_touch(seat, self, |handle| {
+            handle.down(
+                event.serial.into(),
+                event.time,
+                self,
+                event.slot.into(),
+                location.x,
+                location.y,
+            );
+            handle.frame();
+        })
+    }
+
+    fn up(&self, seat: &Seat<D>, _data: &mut D, event: &UpEvent) {
+        seat.record_serial(self, event.serial);
+        for_each_focused_touch(seat, self, |handle| {
+            handle.up(event.serial.into(), event.time, event.slot.into());
+            handle.frame();
+        })
+    }
+
+    fn motion(&self, seat: &Seat<D>, _data: &mut D, event: &MotionEvent) {
+        let scale = compositor::surface_client_scale(self) as f64;
+        let location = event.location.upscale(scale);
+        for_each_focused_touch(seat, self, |handle| {
+            handle.motion(event.time, event.slot.into(), location.x, location.y);
+            handle.frame();
+        })
+    }
+
+    fn cancel(&self, seat: &Seat<D>, _data: &mut D) {
+        for_each_focused_touch(seat, self, |handle| handle.cancel())
+    }
+
+    fn shape(&self, seat: &Seat<D>, _data: &mut D, slot: TouchSlot, major: f64, minor: f64) {
+        for_each_focused_touch(seat, self, |handle| {
+            if handle.version() >= 6 {
+                handle.shape(slot.into(), major, minor);
+                handle.frame();
+            }
+        })
+    }
+
+    fn orientation(&self, seat: &Seat<D>, _data: &mut D, slot: TouchSlot, orientation: f64) {
+        for_each_focused_touch(seat, self, |handle| {
+            if handle.version() >= 6 {
+                handle.orientation(slot.into(), orientation);
+                handle.frame();
+            }
+        })
+    }
+}
+
+/// Touch down event
+#[derive(Debug, Clone, Copy)]
+pub struct DownEvent {
+    /// Touch point that went down
+    pub slot: TouchSlot,
+    /// Location of the touch point in compositor space
+    pub location: Point<f64, Logical>,
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+}
+
+/// Touch up event
+#[derive(Debug, Clone, Copy)]
+pub struct UpEvent {
+    /// Touch point that went up
+    pub slot: TouchSlot,
+    /// Serial of the event
+    pub serial: Serial,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+}
+
+/// Touch motion event
+#[derive(Debug, Clone, Copy)]
+pub struct MotionEvent {
+    /// Touch point that moved
+    pub slot: TouchSlot,
+    /// Location of the touch point in compositor space
+    pub location: Point<f64, Logical>,
+    /// Timestamp of the event, with millisecond granularity
+    pub time: u32,
+}
+
+/// User data for touch
+#[derive(Debug)]
+pub struct TouchUserData {
+    pub(crate) known_handles: Option<KnownHandles>,
+}
+
+impl<D> Dispatch<WlTouch, TouchUserData, D> for SeatState<D>
+where
+    D: Dispatch<WlTouch, TouchUserData>,
+    D: SeatHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        _resource: &WlTouch,
+        _request: wl_touch::Request,
+        _data: &TouchUserData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+    }
+
+    fn destroyed(_state: &mut D, _client_id: ClientId, object_id: ObjectId, data: &TouchUserData) {
+        if let Some(ref known_handles) = data.known_handles {
+            known_handles.lock().unwrap().retain(|k| k.id() != object_id)
+        }
+    }
+}
@@ -15,7 +15,7 @@ use crate::wayland::{compositor, shell::wlr_layer::Layer};
 
 use super::{
     Anchor, KeyboardInteractivity, LayerSurfaceAttributes, LayerSurfaceCachedState, LayerSurfaceData,
-    Margins, WlrLayerShellHandler, WlrLayerShellState,
+    Margins, WlrLayerShellGlobalData, WlrLayerShellHandler, WlrLayerShellState,
 };
 
 use super::LAYER_SURFACE_ROLE;
@@ -24,9 +24,9 @@ use super::LAYER_SURFACE_ROLE;
  * layer_shell
  */
 
-impl<D> GlobalDispatch<ZwlrLayerShellV1, (), D> for WlrLayerShellState
+impl<D> GlobalDispatch<ZwlrLayerShellV1, WlrLayerShellGlobalData, D> for WlrLayerShellState
 where
-    D: GlobalDispatch<ZwlrLayerShellV1, ()>,
+    D: GlobalDispatch<ZwlrLayerShellV1, WlrLayerShellGlobalData>,
     D: Dispatch<ZwlrLayerShellV1, ()>,
     D: Dispatch<ZwlrLayerSurfaceV1, WlrLayerSurfaceUserData>,
     D: WlrLayerShellHandler,
@@ -37,11 +37,15 @@ where
         _handle: &DisplayHandle,
         _client: &Client,
         resource: wayland_server::New<ZwlrLayerShellV1>,
-        _global_data: &(),
+        _global_data: &WlrLayerShellGlobalData,
         data_init: &mut DataInit<'_, D>,
     ) {
         data_init.init(resource, ());
     }
+
+    fn can_view(client: Client, global_data: &WlrLayerShellGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
 }
 
 impl<D> Dispatch<ZwlrLayerShellV1, (), D> for WlrLayerShellState
@@ -274,16 +278,32 @@ where
                     .data::<crate::wayland::shell::xdg::XdgShellSurfaceUserData>()
                     .unwrap();
 
-                compositor::with_states(&data.wl_surface, move |states| {
-                    states
+                let valid = compositor::with_states(&data.wl_surface, move |states| {
+                    let mut attributes = states
                         .data_map
                         .get::<XdgPopupSurfaceData>()
                         .unwrap()
                         .lock()
-                        .unwrap()
-                        .parent = Some(parent_surface);
+                        .unwrap();
+
+                    // The popup has to be created without a parent and
+                    // this request has to be sent before the initial commit
+                    if attributes.parent.is_some() || attributes.committed {
+                        return false;
+                    }
+
+                    attributes.parent = Some(parent_surface);
+                    true
                 });
 
+                if !valid {
+                    layer_surface.post_error(
+                        zwlr_layer_surface_v1::Error::InvalidSurfaceState,
+                        "popup already has a parent or was already committed",
+                    );
+                    return;
+                }
+
                 WlrLayerShellHandler::new_popup(
                     state,
                     make_surface_handle(layer_surface),
@@ -54,7 +54,7 @@ use wayland_protocols_wlr::layer_shell::v1::server::{
 use wayland_server::{
     backend::GlobalId,
     protocol::{wl_output::WlOutput, wl_surface},
-    DisplayHandle, GlobalDispatch, Resource,
+    Client, DisplayHandle, GlobalDispatch, Resource,
 };
 
 use crate::{
@@ -197,6 +197,38 @@ pub struct LayerSurfaceCachedState {
     pub layer: Layer,
 }
 
+impl LayerSurfaceCachedState {
+    /// Returns the edge the exclusive zone of this surface applies to, if any
+    ///
+    /// The edge is deduced from the anchor points as described by the protocol:
+    /// A surface anchored to a single edge, or to an edge and both perpendicular edges,
+    /// has its exclusive zone applied to that edge. A surface that is not anchored,
+    /// anchored to a corner, to two parallel edges or to all edges has no exclusive edge,
+    /// in which case a positive exclusive zone is treated like [`ExclusiveZone::Neutral`].
+    pub fn exclusive_edge(&self) -> Option<Anchor> {
+        let horizontal = self.anchor & (Anchor::LEFT | Anchor::RIGHT);
+        let vertical = self.anchor & (Anchor::TOP | Anchor::BOTTOM);
+
+        match (horizontal.bits().count_ones(), vertical.bits().count_ones()) {
+            // a single edge, or a single edge and both perpendicular edges
+            (1, 0) | (1, 2) => Some(horizontal),
+            (0, 1) | (2, 1) => Some(vertical),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the exclusive zone of this surface, if it has any
+    ///
+    /// This takes into account if the exclusive zone is actually meaningful
+    /// with the current anchors (see [`exclusive_edge`](LayerSurfaceCachedState::exclusive_edge)).
+    pub fn exclusive_zone_size(&self) -> Option<u32> {
+        match self.exclusive_zone {
+            ExclusiveZone::Exclusive(amount) if self.exclusive_edge().is_some() => Some(amount),
+            _ => None,
+        }
+    }
+}
+
 impl Cacheable for LayerSurfaceCachedState {
     fn commit(&mut self, _dh: &DisplayHandle) -> Self {
         *self
@@ -216,14 +248,35 @@ pub struct WlrLayerShellState {
     shell_global: GlobalId,
 }
 
+/// Data associated with a layer shell global
+#[allow(missing_debug_implementations)]
+pub struct WlrLayerShellGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
 impl WlrLayerShellState {
     /// Create a new `wlr_layer_shell` globals
     pub fn new<D>(display: &DisplayHandle) -> WlrLayerShellState
     where
-        D: GlobalDispatch<ZwlrLayerShellV1, ()>,
+        D: GlobalDispatch<ZwlrLayerShellV1, WlrLayerShellGlobalData>,
         D: 'static,
     {
-        let shell_global = display.create_global::<D, ZwlrLayerShellV1, _>(4, ());
+        Self::new_with_filter::<D, _>(display, |_| true)
+    }
+
+    /// Create a new `wlr_layer_shell` globals, only advertised to clients passing the given filter
+    ///
+    /// See also [`PrivilegedGlobals`](crate::wayland::privileged::PrivilegedGlobals).
+    pub fn new_with_filter<D, F>(display: &DisplayHandle, filter: F) -> WlrLayerShellState
+    where
+        D: GlobalDispatch<ZwlrLayerShellV1, WlrLayerShellGlobalData>,
+        D: 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let data = WlrLayerShellGlobalData {
+            filter: Box::new(filter),
+        };
+        let shell_global = display.create_global::<D, ZwlrLayerShellV1, _>(4, data);
 
         WlrLayerShellState {
             known_layers: Default::default(),
@@ -506,7 +559,7 @@ macro_rules! delegate_layer_shell {
         ] => $crate::wayland::shell::wlr_layer::WlrLayerShellState);
 
         $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
-            __ZwlrLayerShellV1: ()
+            __ZwlrLayerShellV1: $crate::wayland::shell::wlr_layer::WlrLayerShellGlobalData
         ] => $crate::wayland::shell::wlr_layer::WlrLayerShellState);
     };
 }
@@ -137,6 +137,16 @@ where
                     },
                 );
 
+                compositor::with_states(surface, |states| {
+                    states
+                        .data_map
+                        .get::<XdgToplevelSurfaceData>()
+                        .unwrap()
+                        .lock()
+                        .unwrap()
+                        .toplevel_handle = Some(toplevel.clone());
+                });
+
                 state
                     .xdg_shell_state()
                     .known_toplevels
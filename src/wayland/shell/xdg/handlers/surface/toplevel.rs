@@ -87,11 +87,19 @@ where
                 }
             }
             xdg_toplevel::Request::SetMaxSize { width, height } => {
+                if width < 0 || height < 0 {
+                    toplevel.post_error(xdg_toplevel::Error::InvalidSize, "max size must not be negative");
+                    return;
+                }
                 with_toplevel_pending_state(data, |toplevel_data| {
                     toplevel_data.max_size = (width, height).into();
                 });
             }
             xdg_toplevel::Request::SetMinSize { width, height } => {
+                if width < 0 || height < 0 {
+                    toplevel.post_error(xdg_toplevel::Error::InvalidSize, "min size must not be negative");
+                    return;
+                }
                 with_toplevel_pending_state(data, |toplevel_data| {
                     toplevel_data.min_size = (width, height).into();
                 });
@@ -176,6 +176,12 @@ macro_rules! xdg_role {
                 pub last_acked: Option<$state>,
                 /// Holds the current state after a successful commit.
                 pub current: $state,
+                /// The serial of the configure the current state was
+                /// acknowledged with, updated on every commit.
+                pub current_serial: Option<Serial>,
+                /// The serial of the configure the current state before
+                /// the last commit was acknowledged with.
+                previous_serial: Option<Serial>,
 
                 $(
                     $(#[$attributes_field_meta])*
@@ -252,6 +258,45 @@ macro_rules! xdg_role {
             pub fn has_pending_changes(&self) -> bool {
                 self.server_pending.as_ref().map(|s| s != self.current_server_state()).unwrap_or(false)
             }
+
+            /// Configures sent to the client, that have not been
+            /// acknowledged yet, from oldest to newest.
+            pub fn pending_configures(&self) -> &[$configure_name] {
+                &self.pending_configures
+            }
+
+            /// Returns how far the configure with the given serial progressed.
+            ///
+            /// A configure is implicitly acknowledged and committed
+            /// with any later configure, so serials older than the last
+            /// acknowledged or committed one are reported accordingly.
+            pub fn configure_status(&self, serial: Serial) -> ConfigureStatus {
+                if self.current_serial.map(|current| serial <= current).unwrap_or(false) {
+                    ConfigureStatus::Committed
+                } else if self.configure_serial.map(|acked| serial <= acked).unwrap_or(false) {
+                    ConfigureStatus::Acked
+                } else if self.pending_configures.iter().any(|configure| configure.serial == serial) {
+                    ConfigureStatus::Pending
+                } else {
+                    ConfigureStatus::Unknown
+                }
+            }
+
+            /// Check if the last commit applied the configure with the given serial.
+            ///
+            /// This is the case if the configure, or a later one, was
+            /// acknowledged and the state was not committed before.
+            pub fn committed_in_last_commit(&self, serial: Serial) -> bool {
+                let committed = self.current_serial.map(|current| serial <= current).unwrap_or(false);
+                let previously_committed =
+                    self.previous_serial.map(|previous| serial <= previous).unwrap_or(false);
+                committed && !previously_committed
+            }
+
+            fn track_commit(&mut self) {
+                self.previous_serial = self.current_serial;
+                self.current_serial = self.configure_serial;
+            }
         }
 
         impl Default for $attributes_name {
@@ -265,6 +310,8 @@ macro_rules! xdg_role {
                     server_pending: None,
                     last_acked: None,
                     current: Default::default(),
+                    current_serial: None,
+                    previous_serial: None,
 
                     $(
                         $attributes_field_name: Default::default(),
@@ -331,7 +378,9 @@ xdg_role!(
         /// Maximum size requested for this surface
         ///
         /// A value of 0 on an axis means this axis is not constrained
-        pub max_size: Size<i32, Logical>
+        pub max_size: Size<i32, Logical>,
+
+        toplevel_handle: Option<xdg_toplevel::XdgToplevel>
     }
 );
 
@@ -562,8 +611,8 @@ impl PositionerState {
     /// The position is calculated according to the rules defined
     /// in the `xdg_shell` protocol.
     /// The `constraint_adjustment` will not be considered by this
-    /// implementation and the position and size should be re-calculated
-    /// in the compositor if the compositor implements `constraint_adjustment`
+    /// implementation, see [`get_unconstrained_geometry`](PositionerState::get_unconstrained_geometry)
+    /// to apply it.
     pub fn get_geometry(&self) -> Rectangle<i32, Logical> {
         // From the `xdg_shell` prococol specification:
         //
@@ -621,6 +670,159 @@ impl PositionerState {
 
         geometry
     }
+
+    /// Get the geometry for a popup as defined by this positioner, after applying
+    /// the `constraint_adjustment` to fit it into the given target rectangle.
+    ///
+    /// The `target` is given in the same coordinate space as the returned geometry,
+    /// i.e. relative to the parent surface `window_geometry`. It usually corresponds
+    /// to the work area of the output the parent is displayed on.
+    ///
+    /// The adjustments are applied as defined by the `xdg_shell` protocol: on each axis
+    /// the popup is flipped, then slid, then resized, as long as it remains constrained
+    /// and the respective adjustment is allowed.
+    pub fn get_unconstrained_geometry(&self, target: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        use xdg_positioner::ConstraintAdjustment;
+
+        let mut geometry = self.get_geometry();
+
+        // x axis
+        if constrained_x(geometry, target) {
+            if self.constraint_adjustment.contains(ConstraintAdjustment::FlipX) {
+                let flipped = PositionerState {
+                    anchor_edges: flip_anchor_x(self.anchor_edges),
+                    gravity: flip_gravity_x(self.gravity),
+                    ..*self
+                }
+                .get_geometry();
+                // a flip which is still constrained is discarded
+                if !constrained_x(flipped, target) {
+                    geometry.loc.x = flipped.loc.x;
+                }
+            }
+            if constrained_x(geometry, target)
+                && self.constraint_adjustment.contains(ConstraintAdjustment::SlideX)
+            {
+                let overflow = geometry.loc.x + geometry.size.w - (target.loc.x + target.size.w);
+                if overflow > 0 {
+                    geometry.loc.x -= overflow;
+                }
+                // the left edge takes precedence if the popup is larger than the target
+                if geometry.loc.x < target.loc.x {
+                    geometry.loc.x = target.loc.x;
+                }
+            }
+            if constrained_x(geometry, target)
+                && self.constraint_adjustment.contains(ConstraintAdjustment::ResizeX)
+            {
+                let left = geometry.loc.x.max(target.loc.x);
+                let right = (geometry.loc.x + geometry.size.w).min(target.loc.x + target.size.w);
+                if right > left {
+                    geometry.loc.x = left;
+                    geometry.size.w = right - left;
+                }
+            }
+        }
+
+        // y axis
+        if constrained_y(geometry, target) {
+            if self.constraint_adjustment.contains(ConstraintAdjustment::FlipY) {
+                let flipped = PositionerState {
+                    anchor_edges: flip_anchor_y(self.anchor_edges),
+                    gravity: flip_gravity_y(self.gravity),
+                    ..*self
+                }
+                .get_geometry();
+                if !constrained_y(flipped, target) {
+                    geometry.loc.y = flipped.loc.y;
+                }
+            }
+            if constrained_y(geometry, target)
+                && self.constraint_adjustment.contains(ConstraintAdjustment::SlideY)
+            {
+                let overflow = geometry.loc.y + geometry.size.h - (target.loc.y + target.size.h);
+                if overflow > 0 {
+                    geometry.loc.y -= overflow;
+                }
+                // the top edge takes precedence if the popup is larger than the target
+                if geometry.loc.y < target.loc.y {
+                    geometry.loc.y = target.loc.y;
+                }
+            }
+            if constrained_y(geometry, target)
+                && self.constraint_adjustment.contains(ConstraintAdjustment::ResizeY)
+            {
+                let top = geometry.loc.y.max(target.loc.y);
+                let bottom = (geometry.loc.y + geometry.size.h).min(target.loc.y + target.size.h);
+                if bottom > top {
+                    geometry.loc.y = top;
+                    geometry.size.h = bottom - top;
+                }
+            }
+        }
+
+        geometry
+    }
+}
+
+fn constrained_x(geometry: Rectangle<i32, Logical>, target: Rectangle<i32, Logical>) -> bool {
+    geometry.loc.x < target.loc.x || geometry.loc.x + geometry.size.w > target.loc.x + target.size.w
+}
+
+fn constrained_y(geometry: Rectangle<i32, Logical>, target: Rectangle<i32, Logical>) -> bool {
+    geometry.loc.y < target.loc.y || geometry.loc.y + geometry.size.h > target.loc.y + target.size.h
+}
+
+fn flip_anchor_x(anchor: xdg_positioner::Anchor) -> xdg_positioner::Anchor {
+    use xdg_positioner::Anchor;
+    match anchor {
+        Anchor::Left => Anchor::Right,
+        Anchor::Right => Anchor::Left,
+        Anchor::TopLeft => Anchor::TopRight,
+        Anchor::TopRight => Anchor::TopLeft,
+        Anchor::BottomLeft => Anchor::BottomRight,
+        Anchor::BottomRight => Anchor::BottomLeft,
+        anchor => anchor,
+    }
+}
+
+fn flip_anchor_y(anchor: xdg_positioner::Anchor) -> xdg_positioner::Anchor {
+    use xdg_positioner::Anchor;
+    match anchor {
+        Anchor::Top => Anchor::Bottom,
+        Anchor::Bottom => Anchor::Top,
+        Anchor::TopLeft => Anchor::BottomLeft,
+        Anchor::BottomLeft => Anchor::TopLeft,
+        Anchor::TopRight => Anchor::BottomRight,
+        Anchor::BottomRight => Anchor::TopRight,
+        anchor => anchor,
+    }
+}
+
+fn flip_gravity_x(gravity: xdg_positioner::Gravity) -> xdg_positioner::Gravity {
+    use xdg_positioner::Gravity;
+    match gravity {
+        Gravity::Left => Gravity::Right,
+        Gravity::Right => Gravity::Left,
+        Gravity::TopLeft => Gravity::TopRight,
+        Gravity::TopRight => Gravity::TopLeft,
+        Gravity::BottomLeft => Gravity::BottomRight,
+        Gravity::BottomRight => Gravity::BottomLeft,
+        gravity => gravity,
+    }
+}
+
+fn flip_gravity_y(gravity: xdg_positioner::Gravity) -> xdg_positioner::Gravity {
+    use xdg_positioner::Gravity;
+    match gravity {
+        Gravity::Top => Gravity::Bottom,
+        Gravity::Bottom => Gravity::Top,
+        Gravity::TopLeft => Gravity::BottomLeft,
+        Gravity::BottomLeft => Gravity::TopLeft,
+        Gravity::TopRight => Gravity::BottomRight,
+        Gravity::BottomRight => Gravity::TopRight,
+        gravity => gravity,
+    }
 }
 
 /// State of a regular toplevel surface
@@ -1286,7 +1488,7 @@ impl ToplevelSurface {
         _dh: &DisplayHandle,
         surface: &wl_surface::WlSurface,
     ) {
-        compositor::with_states(surface, |states| {
+        let invalid_size = compositor::with_states(surface, |states| {
             let mut guard = states
                 .data_map
                 .get::<XdgToplevelSurfaceData>()
@@ -1296,7 +1498,25 @@ impl ToplevelSurface {
             if let Some(state) = guard.last_acked.clone() {
                 guard.current = state;
             }
+            guard.track_commit();
+
+            // A max size smaller than the min size is illegal, but can
+            // only be checked on commit as both are double-buffered
+            let cached = states.cached_state.pending::<SurfaceCachedState>();
+            let (min_size, max_size) = (cached.min_size, cached.max_size);
+            if (max_size.w > 0 && min_size.w > max_size.w) || (max_size.h > 0 && min_size.h > max_size.h) {
+                guard.toplevel_handle.clone()
+            } else {
+                None
+            }
         });
+
+        if let Some(toplevel) = invalid_size {
+            toplevel.post_error(
+                xdg_toplevel::Error::InvalidSize,
+                "max size is smaller than min size",
+            );
+        }
     }
 
     /// Make sure this surface was configured
@@ -1403,6 +1623,61 @@ impl ToplevelSurface {
         })
     }
 
+    /// Returns how far the configure with the given serial progressed
+    ///
+    /// See [`ConfigureStatus`] for details.
+    pub fn configure_status(&self, serial: Serial) -> ConfigureStatus {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_status(serial)
+        })
+    }
+
+    /// Check if the last commit of this toplevel applied the configure with the given serial
+    ///
+    /// This can be called from the commit handler of the compositor to react once the client
+    /// adopted a specific configure, e.g. to start an animation after a maximize configure was
+    /// committed. Returns `true` only for the first commit after the configure, or a later one,
+    /// was acknowledged.
+    pub fn committed_in_last_commit(&self, serial: Serial) -> bool {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .committed_in_last_commit(serial)
+        })
+    }
+
+    /// Constrain a size to the min and max size of this toplevel
+    ///
+    /// The min and max size of the last commit of the client are used, axes
+    /// without constraint are left untouched. This should be applied to sizes
+    /// set with [`with_pending_state`](ToplevelSurface::with_pending_state),
+    /// unless the compositor deliberately overrides the constraints, e.g. for
+    /// fullscreen surfaces.
+    pub fn constrain_size(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let (min_size, max_size) = compositor::with_states(&self.wl_surface, |states| {
+            let cached = states.cached_state.current::<SurfaceCachedState>();
+            (cached.min_size, cached.max_size)
+        });
+        let constrain = |value: i32, min: i32, max: i32| {
+            let value = if max > 0 { value.min(max) } else { value };
+            value.max(min)
+        };
+        Size::from((
+            constrain(size.w, min_size.w, max_size.w),
+            constrain(size.h, min_size.h, max_size.h),
+        ))
+    }
+
     /// Returns the parent of this toplevel surface.
     pub fn parent(&self) -> Option<wl_surface::WlSurface> {
         handlers::get_parent(&self.shell_surface)
@@ -1637,6 +1912,7 @@ impl PopupSurface {
                 .lock()
                 .unwrap();
             attributes.committed = true;
+            attributes.track_commit();
             if attributes.initial_configure_sent {
                 if let Some(state) = attributes.last_acked {
                     if state != attributes.current {
@@ -1740,6 +2016,54 @@ impl PopupSurface {
             !attributes.initial_configure_sent || attributes.has_pending_changes()
         })
     }
+
+    /// Returns how far the configure with the given serial progressed
+    ///
+    /// See [`ConfigureStatus`] for details.
+    pub fn configure_status(&self, serial: Serial) -> ConfigureStatus {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgPopupSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .configure_status(serial)
+        })
+    }
+
+    /// Check if the last commit of this popup applied the configure with the given serial
+    ///
+    /// This can be called from the commit handler of the compositor to react once the client
+    /// adopted a specific configure, e.g. to start an animation after a maximize configure was
+    /// committed. Returns `true` only for the first commit after the configure, or a later one,
+    /// was acknowledged.
+    pub fn committed_in_last_commit(&self, serial: Serial) -> bool {
+        compositor::with_states(&self.wl_surface, |states| {
+            states
+                .data_map
+                .get::<XdgPopupSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .committed_in_last_commit(serial)
+        })
+    }
+}
+
+/// Progress of a configure sent to a xdg surface
+///
+/// See [`ToplevelSurface::configure_status`] and [`PopupSurface::configure_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigureStatus {
+    /// The configure was sent, but not acknowledged by the client yet
+    Pending,
+    /// The configure was acknowledged, but the state was not committed yet
+    Acked,
+    /// The configure was acknowledged and the state was committed
+    Committed,
+    /// No configure with this serial is known for the surface
+    Unknown,
 }
 
 /// Defines the possible configure variants
@@ -1792,3 +2116,160 @@ macro_rules! delegate_xdg_shell {
         ] => $crate::wayland::shell::xdg::XdgShellState);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        Client, Display,
+    };
+
+    use super::*;
+    use crate::wayland::{
+        compositor::{CompositorClientState, CompositorHandler, CompositorState},
+        testing::TestClient,
+    };
+
+    struct State {
+        compositor_state: CompositorState,
+        xdg_shell_state: XdgShellState,
+        toplevels: Vec<ToplevelSurface>,
+    }
+
+    impl CompositorHandler for State {
+        fn compositor_state(&mut self) -> &mut CompositorState {
+            &mut self.compositor_state
+        }
+
+        fn client_compositor_state<'a>(&self, client: &'a Client) -> &'a CompositorClientState {
+            &client.get_data::<ClientState>().unwrap().compositor_state
+        }
+
+        fn commit(&mut self, _surface: &wl_surface::WlSurface) {}
+    }
+
+    impl XdgShellHandler for State {
+        fn xdg_shell_state(&mut self) -> &mut XdgShellState {
+            &mut self.xdg_shell_state
+        }
+
+        fn new_toplevel(&mut self, surface: ToplevelSurface) {
+            self.toplevels.push(surface);
+        }
+
+        fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {}
+
+        fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {}
+    }
+
+    crate::delegate_compositor!(State);
+    crate::delegate_xdg_shell!(State);
+
+    #[derive(Default)]
+    struct ClientState {
+        compositor_state: CompositorClientState,
+    }
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    fn setup() -> (Display<State>, State, TestClient) {
+        let mut display = Display::<State>::new().unwrap();
+        let dh = display.handle();
+        let mut state = State {
+            compositor_state: CompositorState::new::<State>(&dh),
+            xdg_shell_state: XdgShellState::new::<State>(&dh),
+            toplevels: Vec::new(),
+        };
+        let mut client = TestClient::new(&mut display, Arc::new(ClientState::default())).unwrap();
+        client.roundtrip(&mut display, &mut state).unwrap();
+        (display, state, client)
+    }
+
+    #[test]
+    fn configure_status() {
+        let (mut display, mut state, mut client) = setup();
+        let (surface, xdg_surface, _toplevel) = client.create_toplevel();
+        client.roundtrip(&mut display, &mut state).unwrap();
+
+        let toplevel = state.toplevels[0].clone();
+        toplevel.with_pending_state(|pending| pending.states.set(xdg_toplevel::State::Maximized));
+        let serial = toplevel.send_configure();
+        assert_eq!(toplevel.configure_status(serial), ConfigureStatus::Pending);
+        client.roundtrip(&mut display, &mut state).unwrap();
+
+        assert_eq!(client.ack_last_configure(&xdg_surface), Some(u32::from(serial)));
+        client.roundtrip(&mut display, &mut state).unwrap();
+        assert_eq!(toplevel.configure_status(serial), ConfigureStatus::Acked);
+        assert!(!toplevel.committed_in_last_commit(serial));
+
+        surface.commit();
+        client.roundtrip(&mut display, &mut state).unwrap();
+        assert_eq!(toplevel.configure_status(serial), ConfigureStatus::Committed);
+        assert!(toplevel.committed_in_last_commit(serial));
+        assert!(toplevel
+            .current_state()
+            .states
+            .contains(xdg_toplevel::State::Maximized));
+
+        surface.commit();
+        client.roundtrip(&mut display, &mut state).unwrap();
+        assert!(!toplevel.committed_in_last_commit(serial));
+        assert!(client.protocol_error().is_none());
+    }
+
+    #[test]
+    fn max_size_smaller_than_min_size() {
+        let (mut display, mut state, mut client) = setup();
+        let (surface, _xdg_surface, toplevel) = client.create_toplevel();
+        toplevel.set_min_size(200, 200);
+        toplevel.set_max_size(100, 100);
+        surface.commit();
+
+        assert!(client.roundtrip(&mut display, &mut state).is_err());
+        let error = client.protocol_error().unwrap();
+        assert_eq!(error.code, xdg_toplevel::Error::InvalidSize as u32);
+    }
+
+    fn positioner() -> PositionerState {
+        PositionerState {
+            rect_size: (100, 50).into(),
+            anchor_rect: Rectangle::from_loc_and_size((150, 0), (20, 20)),
+            anchor_edges: xdg_positioner::Anchor::BottomRight,
+            gravity: xdg_positioner::Gravity::BottomRight,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unconstrained_flip() {
+        let target = Rectangle::from_loc_and_size((0, -100), (200, 200));
+        let mut positioner = positioner();
+        assert_eq!(
+            positioner.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((170, 20), (100, 50))
+        );
+
+        positioner.constraint_adjustment = xdg_positioner::ConstraintAdjustment::FlipX;
+        assert_eq!(
+            positioner.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((50, 20), (100, 50))
+        );
+    }
+
+    #[test]
+    fn unconstrained_slide_and_resize() {
+        let target = Rectangle::from_loc_and_size((0, 0), (200, 60));
+        let mut positioner = positioner();
+        positioner.constraint_adjustment =
+            xdg_positioner::ConstraintAdjustment::SlideX | xdg_positioner::ConstraintAdjustment::ResizeY;
+        assert_eq!(
+            positioner.get_unconstrained_geometry(target),
+            Rectangle::from_loc_and_size((100, 20), (100, 40))
+        );
+    }
+}
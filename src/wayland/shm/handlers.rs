@@ -34,9 +34,12 @@ where
         let shm = data_init.init(resource, ());
 
         // send the formats
-        for &f in &state.shm_state().formats {
+        let shm_state = state.shm_state();
+        for &f in &shm_state.formats {
             shm.format(f);
         }
+        // remember the instance to advertise formats added later on
+        shm_state.instances.lock().unwrap().push(shm);
     }
 }
 
@@ -83,6 +86,20 @@ where
             },
         );
     }
+
+    fn destroyed(
+        state: &mut D,
+        _client_id: wayland_server::backend::ClientId,
+        object_id: wayland_server::backend::ObjectId,
+        _data: &(),
+    ) {
+        state
+            .shm_state()
+            .instances
+            .lock()
+            .unwrap()
+            .retain(|shm| shm.id() != object_id);
+    }
 }
 
 /*
@@ -91,11 +91,18 @@
 //! **Note**
 //!
 //! This handler makes itself safe regarding the client providing a wrong size for the memory pool
-//! by using a SIGBUS handler.
+//! or truncating the file backing it by using a SIGBUS handler. If an access faults, the memory of
+//! the pool is replaced by zeroed memory, the access returns [`BufferAccessError::BadMap`] and all
+//! further accesses to buffers of that pool fail the same way. Buffers of different pools may be
+//! accessed in a nested manner, e.g. to copy between them, but accessing the same pool recursively
+//! panics.
 //!
 //! If you are already using an handler for this signal, you probably don't want to use this handler.
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
 
 use wayland_server::{
     backend::GlobalId,
@@ -121,6 +128,7 @@ use super::buffer::BufferHandler;
 pub struct ShmState {
     formats: HashSet<wl_shm::Format>,
     shm: GlobalId,
+    instances: Mutex<Vec<WlShm>>,
 }
 
 impl ShmState {
@@ -132,6 +140,10 @@ impl ShmState {
     /// The global is directly created on the provided [`Display`](wayland_server::Display),
     /// and this function returns the a delegate type. The id provided by [`ShmState::global`] may be used to
     /// remove this global in the future.
+    ///
+    /// The formats supported by a renderer are usually not known at this point, in that case the
+    /// list can be populated later using [`ShmState::update_formats`], e.g. with the formats returned by
+    /// [`ImportMemWl::shm_formats`](crate::backend::renderer::ImportMemWl::shm_formats).
     pub fn new<D>(display: &DisplayHandle, formats: impl IntoIterator<Item = wl_shm::Format>) -> ShmState
     where
         D: GlobalDispatch<WlShm, ()>
@@ -149,7 +161,11 @@ impl ShmState {
 
         let shm = display.create_global::<D, WlShm, _>(1, ());
 
-        ShmState { formats, shm }
+        ShmState {
+            formats,
+            shm,
+            instances: Mutex::new(Vec::new()),
+        }
     }
 
     /// Returns the id of the [`WlShm`] global.
@@ -159,17 +175,35 @@ impl ShmState {
 
     /// Updates the list of formats advertised by the global.
     ///
-    /// This will only affect new binds to the wl_shm global.
+    /// This is meant to be called whenever the renderer responsible for importing shm buffers
+    /// changes, e.g. when the primary gpu of a multi-gpu setup is switched, with the formats
+    /// returned by [`ImportMemWl::shm_formats`](crate::backend::renderer::ImportMemWl::shm_formats).
     ///
-    /// Removing formats will cause old clients trying to create
-    /// a buffer of a now unsupported format to be killed.
+    /// Newly added formats are also advertised to clients that already bound the wl_shm global.
+    /// The protocol offers no way to withdraw a format, so removing formats will cause old clients
+    /// trying to create a buffer of a now unsupported format to be killed.
     ///
     /// This function will never remove the mandatory formats `ARGB8888` and `XRGB8888`.
     pub fn update_formats(&mut self, formats: impl IntoIterator<Item = wl_shm::Format>) {
-        self.formats = formats.into_iter().collect::<HashSet<_>>();
+        let mut formats = formats.into_iter().collect::<HashSet<_>>();
         // Mandatory formats
-        self.formats.insert(wl_shm::Format::Argb8888);
-        self.formats.insert(wl_shm::Format::Xrgb8888);
+        formats.insert(wl_shm::Format::Argb8888);
+        formats.insert(wl_shm::Format::Xrgb8888);
+
+        let instances = self.instances.get_mut().unwrap();
+        instances.retain(|shm| shm.is_alive());
+        for &format in formats.difference(&self.formats) {
+            for shm in instances.iter() {
+                shm.format(format);
+            }
+        }
+
+        self.formats = formats;
+    }
+
+    /// Returns the formats currently advertised by the global.
+    pub fn formats(&self) -> impl Iterator<Item = wl_shm::Format> + '_ {
+        self.formats.iter().copied()
     }
 }
 
@@ -189,7 +223,7 @@ pub enum BufferAccessError {
     /// An error occurred while accessing the memory map
     ///
     /// This can happen if the client advertized a wrong size
-    /// for the memory map.
+    /// for the memory map or truncated the file backing it.
     ///
     /// If this error occurs, the client has been killed as a result.
     #[error("invalid client buffer")]
@@ -571,3 +605,81 @@ macro_rules! delegate_shm {
         ] => $crate::wayland::shm::ShmState);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wayland_client::{protocol::wl_shm as client_shm, WEnum};
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::wl_buffer::WlBuffer,
+        Display,
+    };
+
+    use super::*;
+    use crate::wayland::testing::{TestClient, TestEvent};
+
+    struct State {
+        shm_state: ShmState,
+    }
+
+    impl BufferHandler for State {
+        fn buffer_destroyed(&mut self, _buffer: &WlBuffer) {}
+    }
+
+    impl ShmHandler for State {
+        fn shm_state(&self) -> &ShmState {
+            &self.shm_state
+        }
+    }
+
+    crate::delegate_shm!(State);
+
+    struct ClientState;
+
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    #[test]
+    fn shm_buffer_roundtrip() {
+        let mut display = Display::<State>::new().unwrap();
+        let mut state = State {
+            shm_state: ShmState::new::<State>(&display.handle(), vec![]),
+        };
+        let mut client = TestClient::new(&mut display, Arc::new(ClientState)).unwrap();
+        client.roundtrip(&mut display, &mut state).unwrap();
+
+        let buffer = client.create_shm_buffer((4, 2), 0xff336699).unwrap();
+        client.roundtrip(&mut display, &mut state).unwrap();
+        assert!(client.protocol_error().is_none());
+
+        let formats = client
+            .take_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                TestEvent::Shm {
+                    event: client_shm::Event::Format { format },
+                    ..
+                } => Some(format),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(formats.contains(&WEnum::Value(client_shm::Format::Argb8888)));
+        assert!(formats.contains(&WEnum::Value(client_shm::Format::Xrgb8888)));
+
+        let buffer = client
+            .server_object::<WlBuffer>(&display.handle(), &buffer)
+            .unwrap();
+        let pixel = with_buffer_contents(&buffer, |ptr, len, data| {
+            assert_eq!((data.width, data.height, data.stride), (4, 2, 16));
+            assert!(len >= (data.offset + data.stride * data.height) as usize);
+            let pixels = unsafe { std::slice::from_raw_parts(ptr.offset(data.offset as isize), 4) };
+            u32::from_le_bytes(pixels.try_into().unwrap())
+        })
+        .unwrap();
+        assert_eq!(pixel, 0xff336699);
+    }
+}
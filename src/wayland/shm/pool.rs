@@ -5,7 +5,10 @@ use std::{
     num::NonZeroUsize,
     os::unix::io::{AsRawFd, OwnedFd, RawFd},
     ptr,
-    sync::{Once, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once, RwLock,
+    },
 };
 
 use nix::{
@@ -17,7 +20,22 @@ use nix::{
 };
 use tracing::{debug, instrument, trace};
 
-thread_local!(static SIGBUS_GUARD: Cell<(*const MemMap, bool)> = Cell::new((ptr::null_mut(), false)));
+/// Maximum number of different pools accessed at the same time by a thread
+const MAX_NESTED_ACCESS: usize = 8;
+
+/// Pools currently accessed by a thread, checked by the SIGBUS handler
+///
+/// This is a plain array in a `Cell`, so the signal handler never observes a borrowed state.
+#[derive(Clone, Copy)]
+struct AccessStack {
+    entries: [(*const Pool, *const MemMap); MAX_NESTED_ACCESS],
+    len: usize,
+}
+
+thread_local!(static SIGBUS_GUARD: Cell<AccessStack> = Cell::new(AccessStack {
+    entries: [(ptr::null(), ptr::null()); MAX_NESTED_ACCESS],
+    len: 0,
+}));
 
 /// SAFETY:
 /// This will be only set in the `SIGBUS_INIT` closure, hence only once!
@@ -77,74 +95,86 @@ impl Pool {
 
     #[instrument(level = "trace", skip_all, name = "wayland_shm")]
     pub fn with_data<T, F: FnOnce(*const u8, usize) -> T>(&self, f: F) -> Result<T, ()> {
-        // Place the sigbus handler
-        SIGBUS_INIT.call_once(|| unsafe {
-            place_sigbus_handler();
-        });
-
+        AccessEntry::check_recursion(self);
         let pool_guard = self.map.read().unwrap();
-
         trace!(fd = ?self.fd, "Buffer access on shm pool");
-
-        // Prepare the access
-        SIGBUS_GUARD.with(|guard| {
-            let (p, _) = guard.get();
-            if !p.is_null() {
-                // Recursive call of this method is not supported
-                panic!("Recursive access to a SHM pool content is not supported.");
-            }
-            guard.set((&*pool_guard as *const MemMap, false))
-        });
-
-        let t = f(pool_guard.ptr as *const _, pool_guard.size);
-
-        // Cleanup Post-access
-        SIGBUS_GUARD.with(|guard| {
-            let (_, triggered) = guard.get();
-            guard.set((ptr::null_mut(), false));
-            if triggered {
-                debug!(fd = ?self.fd, "SIGBUS caught on access on shm pool");
-                Err(())
-            } else {
-                Ok(t)
-            }
-        })
+        self.guarded_access(&pool_guard, || f(pool_guard.ptr as *const _, pool_guard.size))
     }
 
     #[instrument(level = "trace", skip_all, name = "wayland_shm")]
     pub fn with_data_mut<T, F: FnOnce(*mut u8, usize) -> T>(&self, f: F) -> Result<T, ()> {
+        AccessEntry::check_recursion(self);
+        let pool_guard = self.map.write().unwrap();
+        trace!(fd = ?self.fd, "Mutable buffer access on shm pool");
+        self.guarded_access(&pool_guard, || f(pool_guard.ptr, pool_guard.size))
+    }
+
+    /// Run `f` with the SIGBUS handler protecting accesses to `map`
+    ///
+    /// If the client truncated the file backing the pool, the access faults, the handler replaces
+    /// the mapping with anonymous memory and the pool is poisoned. Accesses to a poisoned pool fail
+    /// without calling `f`.
+    fn guarded_access<T>(&self, map: &MemMap, f: impl FnOnce() -> T) -> Result<T, ()> {
         // Place the sigbus handler
         SIGBUS_INIT.call_once(|| unsafe {
             place_sigbus_handler();
         });
 
-        let pool_guard = self.map.write().unwrap();
+        if map.is_poisoned() {
+            debug!(fd = ?self.fd, "Access to poisoned shm pool");
+            return Err(());
+        }
 
-        trace!(fd = ?self.fd, "Mutable buffer access on shm pool");
+        // Prepare the access, the entry is removed again when `_access` is dropped,
+        // even if `f` panics
+        let _access = AccessEntry::push(self, map);
+        let t = f();
 
-        // Prepare the access
+        if map.is_poisoned() {
+            debug!(fd = ?self.fd, "SIGBUS caught on access on shm pool");
+            Err(())
+        } else {
+            Ok(t)
+        }
+    }
+}
+
+struct AccessEntry;
+
+impl AccessEntry {
+    /// Panics if the pool is already accessed by this thread, checked before locking the pool
+    fn check_recursion(pool: &Pool) {
         SIGBUS_GUARD.with(|guard| {
-            let (p, _) = guard.get();
-            if !p.is_null() {
+            let stack = guard.get();
+            if stack.entries[..stack.len].iter().any(|(p, _)| ptr::eq(*p, pool)) {
                 // Recursive call of this method is not supported
                 panic!("Recursive access to a SHM pool content is not supported.");
             }
-            guard.set((&*pool_guard as *const MemMap, false))
         });
+    }
 
-        let t = f(pool_guard.ptr, pool_guard.size);
-
-        // Cleanup Post-access
+    fn push(pool: &Pool, map: &MemMap) -> AccessEntry {
         SIGBUS_GUARD.with(|guard| {
-            let (_, triggered) = guard.get();
-            guard.set((ptr::null_mut(), false));
-            if triggered {
-                debug!(fd = ?self.fd, "SIGBUS caught on access on shm pool");
-                Err(())
-            } else {
-                Ok(t)
+            let mut stack = guard.get();
+            if stack.len == MAX_NESTED_ACCESS {
+                panic!("Too many nested accesses to SHM pools.");
             }
-        })
+            stack.entries[stack.len] = (pool as *const Pool, map as *const MemMap);
+            stack.len += 1;
+            guard.set(stack);
+        });
+        AccessEntry
+    }
+}
+
+impl Drop for AccessEntry {
+    fn drop(&mut self) {
+        SIGBUS_GUARD.with(|guard| {
+            let mut stack = guard.get();
+            stack.len -= 1;
+            stack.entries[stack.len] = (ptr::null(), ptr::null());
+            guard.set(stack);
+        });
     }
 }
 
@@ -153,6 +183,7 @@ struct MemMap {
     ptr: *mut u8,
     fd: RawFd,
     size: usize,
+    poisoned: AtomicBool,
 }
 
 impl MemMap {
@@ -161,6 +192,7 @@ impl MemMap {
             ptr: unsafe { map(fd, size) }?,
             fd,
             size: size.into(),
+            poisoned: AtomicBool::new(false),
         })
     }
 
@@ -196,7 +228,16 @@ impl MemMap {
         ptr >= self.ptr && ptr < unsafe { self.ptr.add(self.size) }
     }
 
-    fn nullify(&self) -> Result<(), ()> {
+    fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::SeqCst)
+    }
+
+    /// Replace the mapping with anonymous memory after a SIGBUS and mark it as poisoned
+    ///
+    /// Once poisoned the map stays poisoned, even if it is remapped, as the client
+    /// already misbehaved.
+    fn poison(&self) -> Result<(), ()> {
+        self.poisoned.store(true, Ordering::SeqCst);
         unsafe { nullify_map(self.ptr, self.size) }
     }
 }
@@ -237,7 +278,8 @@ unsafe fn nullify_map(ptr: *mut u8, size: usize) -> Result<(), ()> {
         mman::mmap(
             Some(addr),
             size,
-            mman::ProtFlags::PROT_READ,
+            // writable, so mutable accesses continuing after the fault do not raise SIGSEGV
+            mman::ProtFlags::PROT_READ | mman::ProtFlags::PROT_WRITE,
             mman::MapFlags::MAP_ANONYMOUS | mman::MapFlags::MAP_PRIVATE | mman::MapFlags::MAP_FIXED,
             -1,
             0,
@@ -272,19 +314,21 @@ unsafe fn reraise_sigbus() {
 extern "C" fn sigbus_handler(_signum: libc::c_int, info: *mut libc::siginfo_t, _context: *mut libc::c_void) {
     let faulty_ptr = unsafe { siginfo_si_addr(info) } as *mut u8;
     SIGBUS_GUARD.with(|guard| {
-        let (memmap, _) = guard.get();
-        match unsafe { memmap.as_ref() }.map(|m| (m, m.contains(faulty_ptr))) {
-            Some((m, true)) => {
+        let stack = guard.get();
+        let faulty_map = stack.entries[..stack.len]
+            .iter()
+            .filter_map(|(_, memmap)| unsafe { memmap.as_ref() })
+            .find(|memmap| memmap.contains(faulty_ptr));
+        match faulty_map {
+            Some(memmap) => {
                 // we are in a faulty memory pool !
-                // remember that it was faulty
-                guard.set((memmap, true));
-                // nullify the pool
-                if m.nullify().is_err() {
+                // remember that it was faulty and nullify the pool
+                if memmap.poison().is_err() {
                     // something terrible occurred !
                     unsafe { reraise_sigbus() }
                 }
             }
-            _ => {
+            None => {
                 // something else occurred, let's die honorably
                 unsafe { reraise_sigbus() }
             }
@@ -314,3 +358,49 @@ unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
 unsafe fn siginfo_si_addr(info: *mut libc::siginfo_t) -> *mut libc::c_void {
     unsafe { (*info).si_addr }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, num::NonZeroUsize, os::unix::io::OwnedFd};
+
+    use super::Pool;
+
+    fn pool(size: usize) -> (File, Pool) {
+        let file = tempfile::tempfile().unwrap();
+        file.set_len(size as u64).unwrap();
+        let fd = OwnedFd::from(file.try_clone().unwrap());
+        let pool = Pool::new(fd, NonZeroUsize::new(size).unwrap()).unwrap();
+        (file, pool)
+    }
+
+    #[test]
+    fn truncated_pool_is_poisoned() {
+        let (file, pool) = pool(65536);
+        let written = pool.with_data_mut(|ptr, len| unsafe { ptr.write_bytes(0xff, len) });
+        assert_eq!(written, Ok(()));
+
+        file.set_len(0).unwrap();
+        assert!(pool.with_data(|ptr, _| unsafe { ptr.read_volatile() }).is_err());
+        // later accesses fail without touching the memory
+        assert!(pool
+            .with_data_mut(|_, _| panic!("poisoned pool accessed"))
+            .is_err());
+    }
+
+    #[test]
+    fn nested_access() {
+        let (_src_file, src) = pool(4096);
+        let (_dst_file, dst) = pool(4096);
+        let copied = dst.with_data_mut(|dst, len| {
+            src.with_data(|src, _| unsafe { std::ptr::copy_nonoverlapping(src, dst, len) })
+        });
+        assert_eq!(copied, Ok(Ok(())));
+    }
+
+    #[test]
+    #[should_panic(expected = "Recursive access")]
+    fn recursive_access() {
+        let (_file, pool) = pool(4096);
+        let _ = pool.with_data(|_, _| pool.with_data(|_, _| ()));
+    }
+}
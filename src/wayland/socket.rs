@@ -43,8 +43,37 @@
 //! #     ) {}
 //! # }
 //! ```
+//!
+//! # Socket activation
+//!
+//! A compositor started as a socket-activated service (e.g. by systemd) does not create its listening
+//! socket itself, but receives it already bound via the `LISTEN_FDS` protocol. Use
+//! [`ListeningSocketSource::from_listen_fds`] to pick up these sockets, and fall back to creating a
+//! socket if the compositor was not activated:
+//!
+//! ```no_run
+//! use smithay::wayland::socket::ListeningSocketSource;
+//!
+//! let listening_socket = match ListeningSocketSource::from_listen_fds().unwrap().pop() {
+//!     Some(socket) => socket,
+//!     None => ListeningSocketSource::new_auto().unwrap(),
+//! };
+//! std::env::set_var("WAYLAND_DISPLAY", listening_socket.socket_name());
+//! ```
 
-use std::{ffi::OsStr, io, os::unix::net::UnixStream};
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs::DirBuilder,
+    io,
+    mem::ManuallyDrop,
+    os::unix::{
+        fs::DirBuilderExt,
+        io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, RawFd},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+};
 
 use calloop::{
     generic::Generic, EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory,
@@ -52,12 +81,59 @@ use calloop::{
 use tracing::{debug, info};
 use wayland_server::{BindError, ListeningSocket};
 
+/// First file descriptor passed by the service manager, see `sd_listen_fds(3)`
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+#[derive(Debug)]
+enum Socket {
+    /// Socket created and owned by us, removed again on drop
+    Bound(ListeningSocket),
+    /// Pre-opened socket passed to us on startup, owned by the service manager
+    Activated { listener: UnixListener, name: OsString },
+}
+
+impl Socket {
+    fn accept(&self) -> io::Result<Option<UnixStream>> {
+        match self {
+            Socket::Bound(socket) => socket.accept(),
+            Socket::Activated { listener, .. } => match listener.accept() {
+                Ok((stream, _)) => Ok(Some(stream)),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    fn socket_name(&self) -> Option<&OsStr> {
+        match self {
+            Socket::Bound(socket) => socket.socket_name(),
+            Socket::Activated { name, .. } => Some(name),
+        }
+    }
+}
+
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Socket::Bound(socket) => socket.as_raw_fd(),
+            Socket::Activated { listener, .. } => listener.as_raw_fd(),
+        }
+    }
+}
+
+impl AsFd for Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the fd is owned by `self` and thus valid for its lifetime
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
 /// A Wayland listening socket event source.
 ///
 /// This implements [`EventSource`] and may be inserted into an event loop.
 #[derive(Debug)]
 pub struct ListeningSocketSource {
-    socket: Generic<ListeningSocket>,
+    socket: Generic<Socket>,
 }
 
 impl ListeningSocketSource {
@@ -71,9 +147,7 @@ impl ListeningSocketSource {
 
         info!(name = ?socket.socket_name(), "Created new socket");
 
-        Ok(ListeningSocketSource {
-            socket: Generic::new(socket, Interest::READ, Mode::Level),
-        })
+        Ok(ListeningSocketSource::from_socket(Socket::Bound(socket)))
     }
 
     /// Creates a new listening socket with the specified name.
@@ -81,9 +155,114 @@ impl ListeningSocketSource {
         let socket = ListeningSocket::bind(name)?;
         info!(name = ?socket.socket_name(), "Created new socket");
 
-        Ok(ListeningSocketSource {
+        Ok(ListeningSocketSource::from_socket(Socket::Bound(socket)))
+    }
+
+    /// Creates a new listening socket with the specified name inside a subdirectory of `XDG_RUNTIME_DIR`.
+    ///
+    /// The subdirectory is created with permissions restricted to the current user, if it does not exist
+    /// yet. The resulting [`socket_name`](ListeningSocketSource::socket_name) is relative to
+    /// `XDG_RUNTIME_DIR` (e.g. `subdir/name`), so it can be directly used as `WAYLAND_DISPLAY`.
+    pub fn with_name_in_subdir(
+        subdir: impl AsRef<Path>,
+        name: &str,
+    ) -> Result<ListeningSocketSource, BindError> {
+        let subdir = subdir.as_ref();
+        if subdir.is_absolute() {
+            return Err(BindError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "subdirectory has to be relative to XDG_RUNTIME_DIR",
+            )));
+        }
+
+        let runtime_dir: PathBuf = env::var_os("XDG_RUNTIME_DIR")
+            .ok_or(BindError::RuntimeDirNotSet)?
+            .into();
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(runtime_dir.join(subdir))
+            .map_err(BindError::Io)?;
+
+        let socket = ListeningSocket::bind(subdir.join(name))?;
+        info!(name = ?socket.socket_name(), "Created new socket");
+
+        Ok(ListeningSocketSource::from_socket(Socket::Bound(socket)))
+    }
+
+    /// Creates a listening socket source from an already bound and listening unix socket.
+    ///
+    /// This is useful, if the socket was created by another process and inherited by the compositor.
+    /// The name reported by [`socket_name`](ListeningSocketSource::socket_name) is derived from the
+    /// socket's path, relative to `XDG_RUNTIME_DIR` if it is located in there.
+    ///
+    /// The socket file is not removed, once the returned source is dropped.
+    pub fn from_listener(listener: UnixListener) -> io::Result<ListeningSocketSource> {
+        let name = prepare_inherited(&listener)?;
+        Ok(ListeningSocketSource::from_inherited(listener, name))
+    }
+
+    /// Creates listening socket sources from the sockets passed by a service manager using
+    /// the `LISTEN_FDS` protocol (see `sd_listen_fds(3)`).
+    ///
+    /// Returns an empty list, if the process was not socket-activated. The `LISTEN_PID`, `LISTEN_FDS`
+    /// and `LISTEN_FDNAMES` environment variables are removed, so they are not inherited by child
+    /// processes. Consequently only the first call to this function may return any sockets.
+    ///
+    /// If any of the passed sockets is invalid, an error is returned and none of the sockets are closed.
+    pub fn from_listen_fds() -> io::Result<Vec<ListeningSocketSource>> {
+        let pid = env::var("LISTEN_PID").ok();
+        let fds = env::var("LISTEN_FDS").ok();
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+
+        // The variables are meant for a specific process, they might have been leaked to us by our parent
+        if pid.and_then(|pid| pid.parse::<u32>().ok()) != Some(std::process::id()) {
+            return Ok(Vec::new());
+        }
+        // bound the count, so a bogus value neither overflows the fd range nor probes countless fds
+        let count = match fds.and_then(|fds| fds.parse::<u16>().ok()) {
+            Some(count) if count > 0 => RawFd::from(count),
+            _ => return Ok(Vec::new()),
+        };
+        let end = SD_LISTEN_FDS_START
+            .checked_add(count)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "LISTEN_FDS is out of range"))?;
+
+        // Validate all sockets before taking ownership of any of them,
+        // so an invalid socket does not cause the valid ones to be closed.
+        let names = (SD_LISTEN_FDS_START..end)
+            .map(|fd| {
+                // Do not leak the sockets to our clients
+                nix::fcntl::fcntl(fd, nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::FD_CLOEXEC))?;
+                // SAFETY: The fd was passed to this process by the service manager, it is only borrowed
+                // here and not closed, as the listener is never dropped.
+                let listener = ManuallyDrop::new(unsafe { UnixListener::from_raw_fd(fd) });
+                prepare_inherited(&listener)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok((SD_LISTEN_FDS_START..)
+            .zip(names)
+            .map(|(fd, name)| {
+                // SAFETY: The service manager passed these fds to this process exclusively, and we removed
+                // the environment variables above, so nobody else will take ownership of them.
+                let listener = unsafe { UnixListener::from_raw_fd(fd) };
+                ListeningSocketSource::from_inherited(listener, name)
+            })
+            .collect())
+    }
+
+    fn from_socket(socket: Socket) -> ListeningSocketSource {
+        ListeningSocketSource {
             socket: Generic::new(socket, Interest::READ, Mode::Level),
-        })
+        }
+    }
+
+    fn from_inherited(listener: UnixListener, name: OsString) -> ListeningSocketSource {
+        info!(?name, "Using inherited socket");
+        ListeningSocketSource::from_socket(Socket::Activated { listener, name })
     }
 
     /// Returns the name of the listening socket.
@@ -92,6 +271,22 @@ impl ListeningSocketSource {
     }
 }
 
+/// Makes an inherited socket non-blocking and returns its name, relative to `XDG_RUNTIME_DIR` if possible
+fn prepare_inherited(listener: &UnixListener) -> io::Result<OsString> {
+    let path = listener
+        .local_addr()?
+        .as_pathname()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "socket is not bound to a path"))?;
+    let name = env::var_os("XDG_RUNTIME_DIR")
+        .and_then(|runtime_dir| path.strip_prefix(runtime_dir).ok().map(Path::to_path_buf))
+        .unwrap_or(path)
+        .into_os_string();
+
+    listener.set_nonblocking(true)?;
+    Ok(name)
+}
+
 impl EventSource for ListeningSocketSource {
     /// A stream to the new client.
     ///
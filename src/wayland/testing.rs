@@ -0,0 +1,579 @@
+//! In-process wayland clients for tests
+//!
+//! This module allows to test protocol implementations with real clients, without spawning
+//! processes or creating sockets on the filesystem. A [`TestClient`] is a
+//! [`wayland_client`] connection to the [`Display`] under test, connected through a socketpair.
+//!
+//! Client and server run on the same thread, so the client never blocks waiting for the server.
+//! Instead [`TestClient::roundtrip`] alternates between dispatching the server and the client,
+//! until the server processed all requests sent so far and the client received all events
+//! resulting from them.
+//!
+//! All events received by the client are recorded as [`TestEvent`]s, which can be inspected
+//! with [`TestClient::events`], consumed with [`TestClient::take_events`] or asserted on with
+//! [`TestClient::expect_event`]. Pings of the `xdg_wm_base` are answered automatically.
+//!
+//! ```ignore
+//! let mut display = Display::<State>::new()?;
+//! let mut state = State::new(&display.handle());
+//! let mut client = TestClient::new(&mut display, Arc::new(ClientState::default()))?;
+//! client.roundtrip(&mut display, &mut state)?;
+//!
+//! let (surface, xdg_surface, _toplevel) = client.create_toplevel();
+//! client.roundtrip(&mut display, &mut state)?;
+//! let serial = client.expect_event(|event| match event {
+//!     TestEvent::XdgSurface {
+//!         event: xdg_surface::Event::Configure { serial },
+//!         ..
+//!     } => Some(*serial),
+//!     _ => None,
+//! });
+//! xdg_surface.ack_configure(serial);
+//!
+//! let buffer = client.create_shm_buffer((64, 64), 0xff00ff00)?;
+//! surface.attach(Some(&buffer), 0, 0);
+//! surface.commit();
+//! client.roundtrip(&mut display, &mut state)?;
+//! assert!(client.protocol_error().is_none());
+//! ```
+//!
+//! This module is used by the tests of smithay itself and is available to compositors
+//! through the `test_support` feature.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Write},
+    os::unix::{io::AsRawFd, net::UnixStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use wayland_client::{
+    backend::{protocol::ProtocolError, WaylandError},
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_callback::{self, WlCallback},
+        wl_compositor::WlCompositor,
+        wl_keyboard::{self, WlKeyboard},
+        wl_output::{self, WlOutput},
+        wl_pointer::{self, WlPointer},
+        wl_region::WlRegion,
+        wl_registry::{self, WlRegistry},
+        wl_seat::{self, WlSeat},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::WlShmPool,
+        wl_subcompositor::WlSubcompositor,
+        wl_subsurface::WlSubsurface,
+        wl_surface::{self, WlSurface},
+        wl_touch::{self, WlTouch},
+    },
+    Connection, Dispatch, DispatchError, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::xdg::shell::client::{
+    xdg_popup::{self, XdgPopup},
+    xdg_positioner::XdgPositioner,
+    xdg_surface::{self, XdgSurface},
+    xdg_toplevel::{self, XdgToplevel},
+    xdg_wm_base::{self, XdgWmBase},
+};
+use wayland_server::{backend::ClientData, Client, Display, DisplayHandle, Resource};
+
+// upper bound of dispatch iterations of a roundtrip, before giving up
+const MAX_ROUNDTRIP_ITERATIONS: usize = 100;
+
+/// Errors of a [`TestClient`]
+#[derive(Debug, thiserror::Error)]
+pub enum TestClientError {
+    /// Dispatching or flushing the server failed
+    #[error("Server error: {0}")]
+    Server(#[from] io::Error),
+    /// The connection of the client failed, e.g. because the server posted a protocol error
+    #[error("Connection error: {0}")]
+    Connection(#[from] WaylandError),
+    /// Dispatching the events of the client failed
+    #[error("Dispatch error: {0}")]
+    Dispatch(#[from] DispatchError),
+    /// The server did not answer the roundtrip
+    #[error("The roundtrip did not complete")]
+    Timeout,
+}
+
+/// A global advertised to a [`TestClient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestGlobal {
+    /// Numeric name of the global
+    pub name: u32,
+    /// Interface of the global
+    pub interface: String,
+    /// Version of the global
+    pub version: u32,
+}
+
+/// An event received by a [`TestClient`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TestEvent {
+    /// Event of a `wl_callback`, e.g. a frame callback
+    Callback {
+        /// The callback
+        callback: WlCallback,
+        /// The received event
+        event: wl_callback::Event,
+    },
+    /// Event of a `wl_surface`
+    Surface {
+        /// The surface
+        surface: WlSurface,
+        /// The received event
+        event: wl_surface::Event,
+    },
+    /// Event of a `wl_buffer`
+    Buffer {
+        /// The buffer
+        buffer: WlBuffer,
+        /// The received event
+        event: wl_buffer::Event,
+    },
+    /// Event of a `wl_shm`
+    Shm {
+        /// The shm global
+        shm: WlShm,
+        /// The received event
+        event: wl_shm::Event,
+    },
+    /// Event of a `wl_seat`
+    Seat {
+        /// The seat
+        seat: WlSeat,
+        /// The received event
+        event: wl_seat::Event,
+    },
+    /// Event of a `wl_pointer`
+    Pointer {
+        /// The pointer
+        pointer: WlPointer,
+        /// The received event
+        event: wl_pointer::Event,
+    },
+    /// Event of a `wl_keyboard`
+    Keyboard {
+        /// The keyboard
+        keyboard: WlKeyboard,
+        /// The received event
+        event: wl_keyboard::Event,
+    },
+    /// Event of a `wl_touch`
+    Touch {
+        /// The touch device
+        touch: WlTouch,
+        /// The received event
+        event: wl_touch::Event,
+    },
+    /// Event of a `wl_output`
+    Output {
+        /// The output
+        output: WlOutput,
+        /// The received event
+        event: wl_output::Event,
+    },
+    /// Event of a `xdg_surface`
+    XdgSurface {
+        /// The xdg surface
+        surface: XdgSurface,
+        /// The received event
+        event: xdg_surface::Event,
+    },
+    /// Event of a `xdg_toplevel`
+    XdgToplevel {
+        /// The toplevel
+        toplevel: XdgToplevel,
+        /// The received event
+        event: xdg_toplevel::Event,
+    },
+    /// Event of a `xdg_popup`
+    XdgPopup {
+        /// The popup
+        popup: XdgPopup,
+        /// The received event
+        event: xdg_popup::Event,
+    },
+}
+
+/// State of a [`TestClient`], recording the received events
+///
+/// Only needed to create objects through the [`QueueHandle`] of the client,
+/// see [`TestClient::queue_handle`].
+#[derive(Debug, Default)]
+pub struct TestClientState {
+    globals: Vec<TestGlobal>,
+    events: Vec<TestEvent>,
+}
+
+/// An in-process wayland client connected to a [`Display`]
+///
+/// See the [module documentation](self) for details.
+pub struct TestClient {
+    connection: Connection,
+    queue: EventQueue<TestClientState>,
+    state: TestClientState,
+    registry: WlRegistry,
+    client: Client,
+    compositor: Option<WlCompositor>,
+    shm: Option<WlShm>,
+    wm_base: Option<XdgWmBase>,
+    // memory of shm pools, kept open until the requests creating the pools were sent
+    pending_files: Vec<File>,
+}
+
+impl fmt::Debug for TestClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestClient")
+            .field("client", &self.client.id())
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TestClient {
+    /// Connect a new client to the display
+    ///
+    /// The `data` is associated with the client on the server side, like for clients
+    /// inserted through [`DisplayHandle::insert_client`].
+    pub fn new<D: 'static>(display: &mut Display<D>, data: Arc<dyn ClientData>) -> io::Result<TestClient> {
+        let (client_stream, server_stream) = UnixStream::pair()?;
+        let client = display.handle().insert_client(server_stream, data)?;
+        let connection = Connection::from_socket(client_stream)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        let queue = connection.new_event_queue();
+        let registry = connection.display().get_registry(&queue.handle(), ());
+
+        Ok(TestClient {
+            connection,
+            queue,
+            state: TestClientState::default(),
+            registry,
+            client,
+            compositor: None,
+            shm: None,
+            wm_base: None,
+            pending_files: Vec::new(),
+        })
+    }
+
+    /// The server side [`Client`] of this client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The client side [`Connection`]
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Handle of the event queue of the client, to create objects not covered by the helpers
+    pub fn queue_handle(&self) -> QueueHandle<TestClientState> {
+        self.queue.handle()
+    }
+
+    /// Send all pending requests and process them with the server, until the client received
+    /// all resulting events
+    ///
+    /// This fails if the server posted a protocol error to the client, which can be retrieved
+    /// with [`protocol_error`](TestClient::protocol_error).
+    pub fn roundtrip<D>(&mut self, display: &mut Display<D>, state: &mut D) -> Result<(), TestClientError> {
+        let done = Arc::new(AtomicBool::new(false));
+        self.connection.display().sync(&self.queue.handle(), done.clone());
+
+        for _ in 0..MAX_ROUNDTRIP_ITERATIONS {
+            self.connection.flush()?;
+            self.pending_files.clear();
+
+            display.dispatch_clients(state)?;
+            display.flush_clients()?;
+
+            match self.queue.prepare_read()?.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(err)) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err.into()),
+            }
+            self.queue.dispatch_pending(&mut self.state)?;
+
+            if done.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+        }
+
+        Err(TestClientError::Timeout)
+    }
+
+    /// The protocol error posted by the server, if any
+    pub fn protocol_error(&self) -> Option<ProtocolError> {
+        self.connection.protocol_error()
+    }
+
+    /// Globals currently advertised to the client
+    pub fn globals(&self) -> &[TestGlobal] {
+        &self.state.globals
+    }
+
+    /// Events received by the client so far
+    pub fn events(&self) -> &[TestEvent] {
+        &self.state.events
+    }
+
+    /// Take all events received by the client so far
+    pub fn take_events(&mut self) -> Vec<TestEvent> {
+        std::mem::take(&mut self.state.events)
+    }
+
+    /// Remove the first received event matching `f` and return the extracted value
+    ///
+    /// ## Panics
+    ///
+    /// Panics if no received event matches.
+    pub fn expect_event<T>(&mut self, mut f: impl FnMut(&TestEvent) -> Option<T>) -> T {
+        let Some((idx, value)) = self
+            .state
+            .events
+            .iter()
+            .enumerate()
+            .find_map(|(idx, event)| f(event).map(|value| (idx, value)))
+        else {
+            panic!("No matching event received, got: {:#?}", self.state.events);
+        };
+        self.state.events.remove(idx);
+        value
+    }
+
+    /// Bind the global with the interface of `I`
+    ///
+    /// The global is bound with the lower of the given and the advertised version.
+    /// Returns `None` if no such global was advertised.
+    pub fn bind<I>(&mut self, version: u32) -> Option<I>
+    where
+        I: Proxy + 'static,
+        TestClientState: Dispatch<I, ()>,
+    {
+        let interface = I::interface().name;
+        let global = self
+            .state
+            .globals
+            .iter()
+            .find(|global| global.interface == interface)?;
+        Some(
+            self.registry
+                .bind(global.name, version.min(global.version), &self.queue.handle(), ()),
+        )
+    }
+
+    /// Returns the server side object of a client side proxy
+    pub fn server_object<I: Resource + 'static>(&self, dh: &DisplayHandle, proxy: &impl Proxy) -> Option<I> {
+        self.client
+            .object_from_protocol_id(dh, proxy.id().protocol_id())
+            .ok()
+    }
+
+    fn compositor(&mut self) -> WlCompositor {
+        if self.compositor.is_none() {
+            self.compositor = Some(self.bind(5).expect("wl_compositor is not advertised"));
+        }
+        self.compositor.clone().unwrap()
+    }
+
+    fn shm(&mut self) -> WlShm {
+        if self.shm.is_none() {
+            self.shm = Some(self.bind(1).expect("wl_shm is not advertised"));
+        }
+        self.shm.clone().unwrap()
+    }
+
+    fn wm_base(&mut self) -> XdgWmBase {
+        if self.wm_base.is_none() {
+            self.wm_base = Some(self.bind(5).expect("xdg_wm_base is not advertised"));
+        }
+        self.wm_base.clone().unwrap()
+    }
+
+    /// Create a new surface
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `wl_compositor` is not advertised, the globals are received with the first roundtrip.
+    pub fn create_surface(&mut self) -> WlSurface {
+        self.compositor().create_surface(&self.queue.handle(), ())
+    }
+
+    /// Create a new `Argb8888` shm buffer of the given size, filled with a single `0xAARRGGBB` pixel value
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `wl_shm` is not advertised.
+    pub fn create_shm_buffer(&mut self, size: (i32, i32), pixel: u32) -> io::Result<WlBuffer> {
+        let (width, height) = size;
+        let stride = width * 4;
+        let len = (stride * height).max(1);
+
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&pixel.to_le_bytes().repeat((width * height) as usize))?;
+        file.set_len(len as u64)?;
+
+        let qh = self.queue.handle();
+        let pool = self.shm().create_pool(file.as_raw_fd(), len, &qh, ());
+        let buffer = pool.create_buffer(0, width, height, stride, wl_shm::Format::Argb8888, &qh, ());
+        pool.destroy();
+        self.pending_files.push(file);
+
+        Ok(buffer)
+    }
+
+    /// Create a new surface with the `xdg_toplevel` role
+    ///
+    /// The surface is committed once, so the server sends the initial configure.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `wl_compositor` or `xdg_wm_base` are not advertised.
+    pub fn create_toplevel(&mut self) -> (WlSurface, XdgSurface, XdgToplevel) {
+        let qh = self.queue.handle();
+        let surface = self.create_surface();
+        let xdg_surface = self.wm_base().get_xdg_surface(&surface, &qh, ());
+        let toplevel = xdg_surface.get_toplevel(&qh, ());
+        surface.commit();
+        (surface, xdg_surface, toplevel)
+    }
+
+    /// Acknowledge the last configure received for the given xdg surface
+    ///
+    /// Returns the acknowledged serial, if any configure was received.
+    pub fn ack_last_configure(&mut self, xdg_surface: &XdgSurface) -> Option<u32> {
+        let serial = self.state.events.iter().rev().find_map(|event| match event {
+            TestEvent::XdgSurface {
+                surface,
+                event: xdg_surface::Event::Configure { serial },
+            } if surface == xdg_surface => Some(*serial),
+            _ => None,
+        })?;
+        xdg_surface.ack_configure(serial);
+        Some(serial)
+    }
+}
+
+impl Dispatch<WlRegistry, ()> for TestClientState {
+    fn event(
+        state: &mut Self,
+        _registry: &WlRegistry,
+        event: wl_registry::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } => state.globals.push(TestGlobal {
+                name,
+                interface,
+                version,
+            }),
+            wl_registry::Event::GlobalRemove { name } => state.globals.retain(|global| global.name != name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlCallback, Arc<AtomicBool>> for TestClientState {
+    fn event(
+        _state: &mut Self,
+        _callback: &WlCallback,
+        _event: wl_callback::Event,
+        done: &Arc<AtomicBool>,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        done.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for TestClientState {
+    fn event(
+        _state: &mut Self,
+        wm_base: &XdgWmBase,
+        event: xdg_wm_base::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+macro_rules! record_events {
+    ($($interface:ty => $variant:ident { $field:ident }),* $(,)?) => {
+        $(
+            impl Dispatch<$interface, ()> for TestClientState {
+                fn event(
+                    state: &mut Self,
+                    proxy: &$interface,
+                    event: <$interface as Proxy>::Event,
+                    _data: &(),
+                    _conn: &Connection,
+                    _qh: &QueueHandle<Self>,
+                ) {
+                    state.events.push(TestEvent::$variant {
+                        $field: proxy.clone(),
+                        event,
+                    });
+                }
+            }
+        )*
+    };
+}
+
+record_events!(
+    WlCallback => Callback { callback },
+    WlSurface => Surface { surface },
+    WlBuffer => Buffer { buffer },
+    WlShm => Shm { shm },
+    WlSeat => Seat { seat },
+    WlPointer => Pointer { pointer },
+    WlKeyboard => Keyboard { keyboard },
+    WlTouch => Touch { touch },
+    WlOutput => Output { output },
+    XdgSurface => XdgSurface { surface },
+    XdgToplevel => XdgToplevel { toplevel },
+    XdgPopup => XdgPopup { popup },
+);
+
+macro_rules! ignore_events {
+    ($($interface:ty),* $(,)?) => {
+        $(
+            impl Dispatch<$interface, ()> for TestClientState {
+                fn event(
+                    _state: &mut Self,
+                    _proxy: &$interface,
+                    _event: <$interface as Proxy>::Event,
+                    _data: &(),
+                    _conn: &Connection,
+                    _qh: &QueueHandle<Self>,
+                ) {
+                }
+            }
+        )*
+    };
+}
+
+ignore_events!(
+    WlCompositor,
+    WlRegion,
+    WlShmPool,
+    WlSubcompositor,
+    WlSubsurface,
+    XdgPositioner,
+);
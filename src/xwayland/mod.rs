@@ -10,7 +10,13 @@
 //! function properly. You'll need to treat XWayland (and all its X11 apps) as one
 //! special client, and play the role of an X11 Window Manager.
 //!
-//! Smithay does not provide any helper for doing that yet, but it is planned.
+//! The [`xwm`] module provides an implementation of such a window manager.
+//!
+//! Multiple XWayland instances can run at the same time, e.g. one per sandbox.
+//! Every [`XWayland`] handle manages its own server on a separate display, and
+//! every instance needs its own [`X11Wm`], which has its own windows and selections.
+//! [`XwmHandler`] callbacks identify the responsible WM by its [`XwmId`](xwm::XwmId),
+//! [`X11Wm::id_for_client`] maps the wayland client of an instance to it.
 mod x11_sockets;
 mod xserver;
 pub mod xwm;
@@ -121,6 +121,7 @@ impl XWayland {
             instance: None,
             sender,
             dh: dh.clone(),
+            client_scale: 1,
         }));
         (XWayland { inner }, XWaylandSource { channel })
     }
@@ -174,6 +175,50 @@ impl XWayland {
         )
     }
 
+    /// Set the scale XWayland is started with
+    ///
+    /// XWayland will operate in a coordinate space `scale` times as large as
+    /// the logical compositor space, see [`CompositorClientState::set_client_scale`].
+    /// This allows X11 clients to render at the native resolution of HiDPI outputs,
+    /// if they are configured to scale their contents accordingly (e.g. via `Xft.dpi`
+    /// or `GDK_SCALE`), instead of being upscaled by the compositor.
+    ///
+    /// Takes effect the next time XWayland is started.
+    #[cfg(feature = "wayland_frontend")]
+    pub fn set_client_scale(&self, scale: u32) {
+        self.inner.lock().unwrap().client_scale = scale.max(1);
+    }
+
+    /// Returns the scale XWayland is started with
+    #[cfg(feature = "wayland_frontend")]
+    pub fn client_scale(&self) -> u32 {
+        self.inner.lock().unwrap().client_scale
+    }
+
+    /// Returns the display number of the running XWayland instance
+    ///
+    /// Returns `None` if XWayland is not started.
+    pub fn display(&self) -> Option<u32> {
+        self.inner
+            .lock()
+            .unwrap()
+            .instance
+            .as_ref()
+            .map(|instance| instance.display_lock.display())
+    }
+
+    /// Returns the wayland client of the running XWayland instance
+    ///
+    /// Returns `None` if XWayland is not started.
+    pub fn client(&self) -> Option<Client> {
+        self.inner
+            .lock()
+            .unwrap()
+            .instance
+            .as_ref()
+            .map(|instance| instance.wayland_client.clone())
+    }
+
     /// Shutdown XWayland
     ///
     /// Does nothing if it was not already running, otherwise kills it and you will
@@ -204,6 +249,7 @@ struct Inner {
     sender: SyncSender<XWaylandEvent>,
     instance: Option<XWaylandInstance>,
     dh: DisplayHandle,
+    client_scale: u32,
 }
 
 /// Inner `ClientData`-type of an xwayland client
@@ -294,12 +340,16 @@ where
 
     let data_map = UserDataMap::new();
     user_data(&data_map);
+    #[cfg(feature = "wayland_frontend")]
+    let compositor_state = CompositorClientState::default();
+    #[cfg(feature = "wayland_frontend")]
+    compositor_state.set_client_scale(guard.client_scale);
     let client = dh.insert_client(
         wl_me,
         Arc::new(XWaylandClientData {
             inner: inner.clone(),
             #[cfg(feature = "wayland_frontend")]
-            compositor_state: CompositorClientState::default(),
+            compositor_state,
             data_map,
         }),
     )?;
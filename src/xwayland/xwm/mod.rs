@@ -71,7 +71,7 @@ use calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction, Registra
 use nix::fcntl::OFlag;
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
     fmt,
     os::unix::{
         io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
@@ -151,6 +151,7 @@ mod atoms {
             _NET_WM_WINDOW_TYPE_UTILITY,
             _NET_WM_STATE_MODAL,
             _MOTIF_WM_HINTS,
+            _XWAYLAND_RANDR_EMU_MONITOR_RECTS,
 
             // server -> client
             WM_S0,
@@ -167,6 +168,7 @@ mod atoms {
             _NET_WM_STATE_HIDDEN,
             _NET_WM_STATE_FULLSCREEN,
             _NET_WM_STATE_FOCUSED,
+            _NET_WM_STATE_DEMANDS_ATTENTION,
             _NET_SUPPORTING_WM_CHECK,
 
             // selection
@@ -272,6 +274,8 @@ pub trait XwmHandler {
     /// Window asks to be positioned or sized differently.
     ///
     /// Requests can be granted by calling [`X11Surface::configure`] with updated values.
+    /// Like all geometry reported by the window manager, the values are given in the
+    /// logical compositor space, accounting for the client scale of XWayland.
     #[allow(clippy::too_many_arguments)]
     fn configure_request(
         &mut self,
@@ -305,6 +309,9 @@ pub trait XwmHandler {
         let _ = (xwm, window);
     }
     /// Window requests to be fullscreened.
+    ///
+    /// This is also requested for windows covering a monitor with a mode emulated by XWayland,
+    /// see [`X11Surface::emulated_fullscreen_rect`].
     fn fullscreen_request(&mut self, xwm: XwmId, window: X11Surface) {
         let _ = (xwm, window);
     }
@@ -320,6 +327,12 @@ pub trait XwmHandler {
     fn unminimize_request(&mut self, xwm: XwmId, window: X11Surface) {
         let _ = (xwm, window);
     }
+    /// Window started or stopped demanding attention.
+    ///
+    /// See [`X11Surface::is_urgent`].
+    fn urgency_changed(&mut self, xwm: XwmId, window: X11Surface) {
+        let _ = (xwm, window);
+    }
 
     /// Window requests to be resized.
     ///
@@ -365,6 +378,7 @@ pub struct X11Wm {
     atoms: Atoms,
 
     wl_client: Client,
+    client_scale: i32,
     unpaired_surfaces: HashMap<u32, X11Window>,
     sequences_to_ignore: BinaryHeap<Reverse<u16>>,
 
@@ -755,6 +769,7 @@ impl X11Wm {
                 atoms._NET_WM_STATE_FULLSCREEN,
                 atoms._NET_WM_STATE_MODAL,
                 atoms._NET_WM_STATE_FOCUSED,
+                atoms._NET_WM_STATE_DEMANDS_ATTENTION,
                 atoms._NET_ACTIVE_WINDOW,
                 atoms._NET_WM_MOVERESIZE,
                 atoms._NET_CLIENT_LIST,
@@ -813,11 +828,9 @@ impl X11Wm {
             xwm: id,
             handle: handle.clone(),
         };
-        client
-            .get_data::<XWaylandClientData>()
-            .unwrap()
-            .user_data()
-            .insert_if_missing(move || injector);
+        let client_data = client.get_data::<XWaylandClientData>().unwrap();
+        client_data.user_data().insert_if_missing(move || injector);
+        client_data.user_data().insert_if_missing(move || id);
 
         let _xfixes_data = conn
             .query_extension(x11rb::protocol::xfixes::X11_EXTENSION_NAME.as_bytes())?
@@ -831,6 +844,12 @@ impl X11Wm {
         let clipboard = XWmSelection::new(&conn, &screen, &atoms, atoms.CLIPBOARD)?;
         let primary = XWmSelection::new(&conn, &screen, &atoms, atoms.PRIMARY)?;
 
+        // X11 coordinates are in the client coordinate space of Xwayland
+        let client_scale = client
+            .get_data::<XWaylandClientData>()
+            .map(|data| data.compositor_state.client_scale() as i32)
+            .unwrap_or(1);
+
         drop(_guard);
         let wm = Self {
             id,
@@ -840,6 +859,7 @@ impl X11Wm {
             atoms,
             wm_window: win,
             wl_client: client,
+            client_scale,
             _xfixes_data,
             clipboard,
             primary,
@@ -865,6 +885,20 @@ impl X11Wm {
         self.id
     }
 
+    /// Returns the wayland client of the Xwayland instance this WM is managing
+    pub fn wayland_client(&self) -> &Client {
+        &self.wl_client
+    }
+
+    /// Returns the id of the WM managing the given Xwayland client, if any
+    ///
+    /// Useful to tell apart multiple Xwayland instances, e.g. in wayland protocol handlers.
+    pub fn id_for_client(client: &Client) -> Option<XwmId> {
+        client
+            .get_data::<XWaylandClientData>()
+            .and_then(|data| data.user_data().get::<XwmId>().copied())
+    }
+
     /// Raises a window in the internal X11 state
     ///
     /// Needs to be called to match raising of windows inside the compositor to keep the stacking order
@@ -1325,7 +1359,7 @@ fn handle_event<D: XwmHandler + 'static>(
 
             let geo = conn.get_geometry(n.window)?.reply()?;
 
-            let surface = X11Surface::new(
+            let mut surface = X11Surface::new(
                 xwm_id,
                 n.window,
                 n.override_redirect,
@@ -1334,8 +1368,10 @@ fn handle_event<D: XwmHandler + 'static>(
                 Rectangle::from_loc_and_size(
                     (geo.x as i32, geo.y as i32),
                     (geo.width as i32, geo.height as i32),
-                ),
+                )
+                .downscale(xwm.client_scale),
             );
+            surface.client_scale = xwm.client_scale;
             surface.update_properties(None)?;
             xwm.windows.push(surface.clone());
 
@@ -1430,28 +1466,58 @@ fn handle_event<D: XwmHandler + 'static>(
         }
         Event::ConfigureRequest(r) => {
             if let Some(surface) = xwm.windows.iter().find(|x| x.window_id() == r.window).cloned() {
+                let scale = xwm.client_scale;
                 drop(_guard);
+
+                // Games resize their window to cover the monitor after an emulated mode change,
+                // which is a request to be fullscreen on that monitor
+                if !surface.is_fullscreen() && !surface.emulated_monitor_rects().is_empty() {
+                    let mut geometry = surface.geometry().upscale(scale);
+                    if u16::from(r.value_mask) & u16::from(ConfigWindow::X) != 0 {
+                        geometry.loc.x = i32::from(r.x);
+                    }
+                    if u16::from(r.value_mask) & u16::from(ConfigWindow::Y) != 0 {
+                        geometry.loc.y = i32::from(r.y);
+                    }
+                    if u16::from(r.value_mask) & u16::from(ConfigWindow::WIDTH) != 0 {
+                        geometry.size.w = i32::from(r.width);
+                    }
+                    if u16::from(r.value_mask) & u16::from(ConfigWindow::HEIGHT) != 0 {
+                        geometry.size.h = i32::from(r.height);
+                    }
+                    let requested = geometry.downscale(scale);
+                    if surface.emulated_monitor_rects().contains(&requested) {
+                        state.fullscreen_request(xwm_id, surface.clone());
+                        surface.configure(None).map_err(|err| match err {
+                            X11SurfaceError::Connection(err) => err,
+                            X11SurfaceError::UnsupportedForOverrideRedirect => unreachable!(),
+                        })?;
+                        conn.flush()?;
+                        return Ok(());
+                    }
+                }
+
                 // Pass the request to downstream to decide
                 state.configure_request(
                     xwm_id,
                     surface.clone(),
                     if u16::from(r.value_mask) & u16::from(ConfigWindow::X) != 0 {
-                        Some(i32::from(r.x))
+                        Some(i32::from(r.x) / scale)
                     } else {
                         None
                     },
                     if u16::from(r.value_mask) & u16::from(ConfigWindow::Y) != 0 {
-                        Some(i32::from(r.y))
+                        Some(i32::from(r.y) / scale)
                     } else {
                         None
                     },
                     if u16::from(r.value_mask) & u16::from(ConfigWindow::WIDTH) != 0 {
-                        Some(u32::from(r.width))
+                        Some(u32::from(r.width) / scale as u32)
                     } else {
                         None
                     },
                     if u16::from(r.value_mask) & u16::from(ConfigWindow::HEIGHT) != 0 {
-                        Some(u32::from(r.height))
+                        Some(u32::from(r.height) / scale as u32)
                     } else {
                         None
                     },
@@ -1492,11 +1558,14 @@ fn handle_event<D: XwmHandler + 'static>(
                 .find(|x| x.mapped_window_id() == Some(n.window))
                 .cloned()
             {
+                let geometry =
+                    Rectangle::from_loc_and_size((n.x as i32, n.y as i32), (n.width as i32, n.height as i32))
+                        .downscale(xwm.client_scale);
                 drop(_guard);
                 state.configure_notify(
                     xwm_id,
                     surface,
-                    Rectangle::from_loc_and_size((n.x as i32, n.y as i32), (n.width as i32, n.height as i32)),
+                    geometry,
                     if n.above_sibling == x11rb::NONE {
                         None
                     } else {
@@ -1508,7 +1577,8 @@ fn handle_event<D: XwmHandler + 'static>(
                     let geometry = Rectangle::from_loc_and_size(
                         (n.x as i32, n.y as i32),
                         (n.width as i32, n.height as i32),
-                    );
+                    )
+                    .downscale(xwm.client_scale);
                     surface.state.lock().unwrap().geometry = geometry;
                     drop(_guard);
                     state.configure_notify(
@@ -1549,11 +1619,12 @@ fn handle_event<D: XwmHandler + 'static>(
                     )?;
                     {
                         let mut state = surface.state.lock().unwrap();
+                        let location = state.geometry.loc.upscale(xwm.client_scale);
                         conn.reparent_window(
                             n.window,
                             xwm.screen.root,
-                            state.geometry.loc.x as i16,
-                            state.geometry.loc.y as i16,
+                            location.x as i16,
+                            location.y as i16,
                         )?;
                         if let Some(frame) = state.mapped_onto.take() {
                             conn.destroy_window(frame)?;
@@ -1672,7 +1743,9 @@ fn handle_event<D: XwmHandler + 'static>(
                                             val if val == xwm.atoms.UTF8_STRING => {
                                                 Some(Ok(String::from("text/plain;charset=utf-8")))
                                             }
-                                            val if val == xwm.atoms.TEXT => {
+                                            val if val == xwm.atoms.TEXT
+                                                || val == u32::from(AtomEnum::STRING) =>
+                                            {
                                                 Some(Ok(String::from("text/plain")))
                                             }
                                             val if val == xwm.atoms.TARGETS || val == xwm.atoms.TIMESTAMP => {
@@ -1705,6 +1778,10 @@ fn handle_event<D: XwmHandler + 'static>(
                                         }
                                     })
                                     .collect::<Result<Vec<String>, _>>()?;
+                                // TEXT and STRING both map to text/plain
+                                let mut mime_types = mime_types;
+                                let mut seen = HashSet::new();
+                                mime_types.retain(|mime| seen.insert(mime.clone()));
 
                                 let selection = selection.type_;
                                 drop(_guard);
@@ -1803,22 +1880,28 @@ fn handle_event<D: XwmHandler + 'static>(
                             return Ok(());
                         }
 
+                        // X11 clients expect all text targets to be available, if any text is offered
+                        let text_targets = text_mime_type(&selection.mime_types)
+                            .map(|_| [xwm.atoms.UTF8_STRING, xwm.atoms.TEXT, AtomEnum::STRING.into()]);
                         let targets = [xwm.atoms.TARGETS, xwm.atoms.TIMESTAMP]
                             .iter()
                             .copied()
-                            .chain(selection.mime_types.iter().filter_map(|mime| {
-                                Some(match &**mime {
-                                    "text/plain" => xwm.atoms.TEXT,
-                                    "text/plain;charset=utf-8" => xwm.atoms.UTF8_STRING,
-                                    mime => {
-                                        conn.intern_atom(false, mime.as_bytes())
-                                            .ok()?
-                                            .reply_unchecked()
-                                            .ok()??
-                                            .atom
-                                    }
-                                })
-                            }))
+                            .chain(text_targets.into_iter().flatten())
+                            .chain(
+                                selection
+                                    .mime_types
+                                    .iter()
+                                    .filter(|mime| !TEXT_MIME_TYPES.contains(&mime.as_str()))
+                                    .filter_map(|mime| {
+                                        Some(
+                                            conn.intern_atom(false, mime.as_bytes())
+                                                .ok()?
+                                                .reply_unchecked()
+                                                .ok()??
+                                                .atom,
+                                        )
+                                    }),
+                            )
                             .collect::<Vec<u32>>();
                         trace!(requstor = n.requestor, ?targets, "Sending TARGETS");
                         conn.change_property32(
@@ -1850,8 +1933,17 @@ fn handle_event<D: XwmHandler + 'static>(
                     }
                     target => {
                         let mime_type = match target {
-                            x if x == xwm.atoms.TEXT => "text/plain".to_string(),
-                            x if x == xwm.atoms.UTF8_STRING => "text/plain;charset=utf-8".to_string(),
+                            x if x == xwm.atoms.TEXT
+                                || x == xwm.atoms.UTF8_STRING
+                                || x == u32::from(AtomEnum::STRING) =>
+                            {
+                                let Some(mime) = text_mime_type(&selection.mime_types) else {
+                                    warn!("Text requested by X client not offered");
+                                    send_selection_notify_resp(&conn, &n, false)?;
+                                    return Ok(());
+                                };
+                                mime.to_string()
+                            }
                             x => {
                                 let Some(mime) = conn.get_atom_name(x)?.reply_unchecked()?.and_then(|reply| String::from_utf8(reply.name).ok()) else {
                                     debug!("Unable to determine mime type from atom: {}", x);
@@ -1972,8 +2064,33 @@ fn handle_event<D: XwmHandler + 'static>(
             }
         }
         Event::PropertyNotify(n) => {
-            if let Some(surface) = xwm.windows.iter().find(|x| x.window_id() == n.window) {
+            if let Some(surface) = xwm.windows.iter().find(|x| x.window_id() == n.window).cloned() {
+                let was_urgent = surface.is_urgent();
+                let was_emulated = surface.emulated_fullscreen_rect().is_some();
                 surface.update_properties(Some(n.atom))?;
+                if n.atom == xwm.atoms._XWAYLAND_RANDR_EMU_MONITOR_RECTS {
+                    // the client changed the emulated mode while covering the monitor,
+                    // or restored the real mode.
+                    let emulated = surface.emulated_fullscreen_rect().is_some();
+                    if emulated && !surface.is_fullscreen() {
+                        drop(_guard);
+                        state.fullscreen_request(xwm_id, surface);
+                        conn.flush()?;
+                        return Ok(());
+                    } else if was_emulated && !emulated && surface.is_fullscreen() {
+                        drop(_guard);
+                        state.unfullscreen_request(xwm_id, surface);
+                        conn.flush()?;
+                        return Ok(());
+                    }
+                }
+                if surface.is_urgent() != was_urgent {
+                    // client windows are never part of a selection transfer
+                    drop(_guard);
+                    state.urgency_changed(xwm_id, surface);
+                    conn.flush()?;
+                    return Ok(());
+                }
             }
 
             if n.state == Property::NEW_VALUE && n.atom == xwm.atoms._WL_SELECTION {
@@ -2200,6 +2317,19 @@ fn handle_event<D: XwmHandler + 'static>(
                                     _ => {}
                                 }
                             }
+                            actions if actions.contains(&xwm.atoms._NET_WM_STATE_DEMANDS_ATTENTION) => {
+                                let demands_attention = match data[0] {
+                                    0 => false,
+                                    1 => true,
+                                    2 => !surface.is_urgent(),
+                                    _ => return Ok(()),
+                                };
+                                let was_urgent = surface.is_urgent();
+                                surface.set_demands_attention(demands_attention)?;
+                                if surface.is_urgent() != was_urgent {
+                                    state.urgency_changed(xwm_id, surface);
+                                }
+                            }
                             _ => {}
                         }
                     }
@@ -2361,6 +2491,23 @@ fn write_selection_callback(
     }
 }
 
+// Mime types used for plain text, in order of preference
+const TEXT_MIME_TYPES: [&str; 5] = [
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "TEXT",
+    "STRING",
+];
+
+/// Returns the preferred text mime type out of a list of offered mime types
+fn text_mime_type(mime_types: &[String]) -> Option<&'static str> {
+    TEXT_MIME_TYPES
+        .iter()
+        .copied()
+        .find(|text| mime_types.iter().any(|mime| mime == text))
+}
+
 fn send_selection_notify_resp(
     conn: &RustConnection,
     req: &SelectionRequestEvent,
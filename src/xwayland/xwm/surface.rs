@@ -36,6 +36,7 @@ pub struct X11Surface {
     override_redirect: bool,
     conn: Weak<RustConnection>,
     atoms: super::Atoms,
+    pub(super) client_scale: i32,
     pub(crate) state: Arc<Mutex<SharedSurfaceState>>,
     user_data: Arc<UserDataMap>,
 }
@@ -61,6 +62,7 @@ pub(crate) struct SharedSurfaceState {
     net_state: HashSet<Atom>,
     motif_hints: Vec<u32>,
     window_type: Vec<Atom>,
+    randr_emu_monitor_rects: Vec<Rectangle<i32, Logical>>,
 }
 
 pub(super) type Protocols = Vec<WMProtocol>;
@@ -139,6 +141,7 @@ impl X11Surface {
             override_redirect,
             conn,
             atoms,
+            client_scale: 1,
             state: Arc::new(Mutex::new(SharedSurfaceState {
                 alive: true,
                 wl_surface: None,
@@ -154,6 +157,7 @@ impl X11Surface {
                 net_state: HashSet::new(),
                 motif_hints: vec![0; 5],
                 window_type: Vec::new(),
+                randr_emu_monitor_rects: Vec::new(),
             })),
             user_data: Arc::new(UserDataMap::new()),
         }
@@ -240,16 +244,17 @@ impl X11Surface {
         if let Some(conn) = self.conn.upgrade() {
             let mut state = self.state.lock().unwrap();
             let rect = rect.unwrap_or(state.geometry);
+            let x11_rect = rect.upscale(self.client_scale);
             let aux = ConfigureWindowAux::default()
-                .x(rect.loc.x)
-                .y(rect.loc.y)
-                .width(rect.size.w as u32)
-                .height(rect.size.h as u32)
+                .x(x11_rect.loc.x)
+                .y(x11_rect.loc.y)
+                .width(x11_rect.size.w as u32)
+                .height(x11_rect.size.h as u32)
                 .border_width(0);
             if let Some(frame) = state.mapped_onto {
                 let win_aux = ConfigureWindowAux::default()
-                    .width(rect.size.w as u32)
-                    .height(rect.size.h as u32)
+                    .width(x11_rect.size.w as u32)
+                    .height(x11_rect.size.h as u32)
                     .border_width(0);
                 conn.configure_window(frame, &aux)?;
                 conn.configure_window(self.window, &win_aux)?;
@@ -318,7 +323,7 @@ impl X11Surface {
             .normal_hints
             .as_ref()
             .and_then(|hints| hints.min_size)
-            .map(Size::from)
+            .map(|size| Size::from(size).downscale(self.client_scale))
     }
 
     /// Returns the suggested minimum size of the underlying X11 window
@@ -328,7 +333,7 @@ impl X11Surface {
             .normal_hints
             .as_ref()
             .and_then(|hints| hints.max_size)
-            .map(Size::from)
+            .map(|size| Size::from(size).downscale(self.client_scale))
     }
 
     /// Returns the suggested base size of the underlying X11 window
@@ -338,7 +343,7 @@ impl X11Surface {
             .normal_hints
             .as_ref()
             .and_then(|hints| hints.base_size)
-            .map(Size::from);
+            .map(|size| Size::from(size).downscale(self.client_scale));
         std::mem::drop(state);
         res.or_else(|| self.min_size())
     }
@@ -377,6 +382,47 @@ impl X11Surface {
             .contains(&self.atoms._NET_WM_STATE_FOCUSED)
     }
 
+    /// Returns if the window is demanding attention
+    ///
+    /// This is the case if either the urgency hint of the window is set (ICCCM)
+    /// or the window is in the demands-attention state (EWMH).
+    pub fn is_urgent(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.hints.as_ref().map(|hints| hints.urgent).unwrap_or(false)
+            || state
+                .net_state
+                .contains(&self.atoms._NET_WM_STATE_DEMANDS_ATTENTION)
+    }
+
+    /// Returns the ICCCM window manager hints of the underlying X11 window
+    pub fn hints(&self) -> Option<WmHints> {
+        self.state.lock().unwrap().hints
+    }
+
+    /// Returns the monitor geometries XWayland emulates for the client of this window
+    ///
+    /// XWayland emulates RandR mode changes, e.g. done by fullscreen games, by
+    /// presenting monitors with the requested mode to the client only. The window
+    /// contents are then scaled to the real output by XWayland via `wp_viewporter`,
+    /// without the actual mode of the output being changed.
+    pub fn emulated_monitor_rects(&self) -> Vec<Rectangle<i32, Logical>> {
+        self.state.lock().unwrap().randr_emu_monitor_rects.clone()
+    }
+
+    /// Returns the emulated monitor geometry the window covers, if any
+    ///
+    /// Windows covering an emulated monitor are meant to be fullscreen on
+    /// the output the monitor is emulated for.
+    /// See [`X11Surface::emulated_monitor_rects`].
+    pub fn emulated_fullscreen_rect(&self) -> Option<Rectangle<i32, Logical>> {
+        let state = self.state.lock().unwrap();
+        state
+            .randr_emu_monitor_rects
+            .iter()
+            .find(|rect| **rect == state.geometry)
+            .copied()
+    }
+
     /// Returns true if the window is client-side decorated
     pub fn is_decorated(&self) -> bool {
         let state = self.state.lock().unwrap();
@@ -446,6 +492,19 @@ impl X11Surface {
         Ok(())
     }
 
+    /// Sets the demands-attention state of the window.
+    ///
+    /// Compositors usually clear this state once the window gets activated.
+    /// The urgency hint of the window is controlled by the client and not affected.
+    pub fn set_demands_attention(&self, demands_attention: bool) -> Result<(), ConnectionError> {
+        if demands_attention {
+            self.change_net_state(&[self.atoms._NET_WM_STATE_DEMANDS_ATTENTION], &[])?;
+        } else {
+            self.change_net_state(&[], &[self.atoms._NET_WM_STATE_DEMANDS_ATTENTION])?;
+        }
+        Ok(())
+    }
+
     /// Returns the reported window type of the underlying X11 window if set.
     ///
     /// Windows without a window type set should be considered to be of type `Normal` for
@@ -529,6 +588,9 @@ impl X11Surface {
             Some(atom) if atom == AtomEnum::WM_TRANSIENT_FOR.into() => self.update_transient_for(),
             Some(atom) if atom == self.atoms._NET_WM_WINDOW_TYPE => self.update_net_window_type(),
             Some(atom) if atom == self.atoms._MOTIF_WM_HINTS => self.update_motif_hints(),
+            Some(atom) if atom == self.atoms._XWAYLAND_RANDR_EMU_MONITOR_RECTS => {
+                self.update_randr_emu_monitor_rects()
+            }
             Some(_) => Ok(()), // unknown
             None => {
                 self.update_title()?;
@@ -540,6 +602,7 @@ impl X11Surface {
                 // NET_WM_STATE is managed by the WM, we don't need to update it unless explicitly asked to
                 self.update_net_window_type()?;
                 self.update_motif_hints()?;
+                self.update_randr_emu_monitor_rects()?;
                 Ok(())
             }
         }
@@ -701,6 +764,42 @@ impl X11Surface {
         Ok(())
     }
 
+    fn update_randr_emu_monitor_rects(&self) -> Result<(), ConnectionError> {
+        let conn = self.conn.upgrade().ok_or(ConnectionError::UnknownError)?;
+        let values = match conn
+            .get_property(
+                false,
+                self.window,
+                self.atoms._XWAYLAND_RANDR_EMU_MONITOR_RECTS,
+                AtomEnum::CARDINAL,
+                0,
+                1024,
+            )?
+            .reply_unchecked()
+        {
+            Ok(values) => values,
+            Err(ConnectionError::ParseError(_)) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        // the property consists of x, y, width and height of each emulated monitor
+        let values = values
+            .and_then(|values| Some(values.value32()?.collect::<Vec<_>>()))
+            .unwrap_or_default();
+        let mut state = self.state.lock().unwrap();
+        state.randr_emu_monitor_rects = values
+            .chunks_exact(4)
+            .map(|rect| {
+                Rectangle::from_loc_and_size(
+                    (rect[0] as i32, rect[1] as i32),
+                    (rect[2] as i32, rect[3] as i32),
+                )
+                .downscale(self.client_scale)
+            })
+            .collect();
+        Ok(())
+    }
+
     fn read_window_property_string(&self, atom: impl Into<Atom>) -> Result<Option<String>, ConnectionError> {
         let conn = self.conn.upgrade().ok_or(ConnectionError::UnknownError)?;
         let reply = match conn